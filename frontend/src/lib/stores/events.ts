@@ -40,6 +40,8 @@ function matchesSearchQuery(event: SimulatorEvent, query: string): boolean {
 			return event.vin.toLowerCase().includes(query);
 		case 'BatchCompleted':
 			return event.total.toString().includes(query);
+		case 'BatchReset':
+			return event.pset_id.toString().includes(query);
 		case 'TighteningCompleted':
 			return (
 				event.result.tightening_status.toString().includes(query) ||
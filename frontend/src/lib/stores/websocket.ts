@@ -234,6 +234,14 @@ const eventHandlers: EventHandlerMap = {
 	},
 	BatchCompleted: (event) => {
 		addEvent(event);
+	},
+	BatchReset: (event) => {
+		autoTighteningProgress.update((progress) => ({
+			...progress,
+			counter: 0,
+			target_size: event.batch_size
+		}));
+		addEvent(event);
 	}
 };
 
@@ -267,6 +275,9 @@ function dispatchEvent(event: SimulatorEvent): void {
 		case 'BatchCompleted':
 			eventHandlers.BatchCompleted(event);
 			break;
+		case 'BatchReset':
+			eventHandlers.BatchReset(event);
+			break;
 		default:
 			// Exhaustiveness check - TypeScript will error if a case is missing
 			const _exhaustive: never = event;
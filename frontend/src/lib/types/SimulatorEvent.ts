@@ -5,7 +5,16 @@ export type SimulatorEvent =
 	| { type: 'TighteningCompleted'; result: TighteningResult }
 	| { type: 'PsetChanged'; pset_id: number; pset_name: string }
 	| { type: 'ToolStateChanged'; enabled: boolean }
-	| { type: 'BatchCompleted'; total: number }
+	| { type: 'BatchCompleted'; total: number; tool_locked: boolean }
+	| { type: 'BatchReset'; pset_id: number; batch_size: number }
+	| {
+			type: 'JobSelected' | 'JobRestarted';
+			job_id: number;
+			job_name: string;
+			pset_id: number;
+			batch_size: number;
+			job_batch_size: number;
+	  }
 	| { type: 'VehicleIdChanged'; vin: string }
 	| { type: 'MultiSpindleStatusCompleted'; status: MultiSpindleStatus }
 	| { type: 'MultiSpindleResultCompleted'; result: MultiSpindleResult }
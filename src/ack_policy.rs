@@ -0,0 +1,528 @@
+//! Retransmission of unacknowledged subscription data
+//!
+//! Controllers differ in what they do when a client does not acknowledge
+//! pushed data (MID 0062 for MID 0061, MID 0072 for MID 0071, ...): some
+//! resend it a few times, some give up and drop it, some close the
+//! connection, and some hold everything back until the client finally acks.
+//! An [`AckPolicy`] per subscription family selects that behavior, from
+//! `[acks.<kind>]` in the config or `PUT /ack-policies/{kind}` at runtime.
+//!
+//! A family with a policy is sent stop-and-wait: each client has at most one
+//! unacknowledged push of it outstanding and later pushes wait behind it.
+//! Families without a policy are fire-and-forget, and NoAck subscriptions
+//! are never tracked.
+
+use crate::protocol::Mid;
+use crate::subscriptions::SubscriptionKind;
+use crate::throttle::Admission;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_max_queued() -> usize {
+    1000
+}
+
+/// What happens once a push was resent `max_retries` times without an ack
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AckExhaustion {
+    /// Give up on the push and send the next one
+    #[default]
+    Drop,
+    /// Close the connection
+    Disconnect,
+    /// Stop resending but keep waiting; later pushes stay queued until the ack
+    Buffer,
+}
+
+/// Acknowledgment timeout and retransmission for one subscription family
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AckPolicy {
+    /// Time to wait for the ack before resending (default: 2000)
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Resends before `on_exhaustion` applies (default: 3)
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    #[serde(default)]
+    pub on_exhaustion: AckExhaustion,
+    /// Pushes waiting behind the unacknowledged one before the oldest is dropped (default: 1000)
+    #[serde(default = "default_max_queued")]
+    pub max_queued: usize,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AckPolicyError {
+    #[error("timeout_ms must be at least 1")]
+    InvalidTimeout,
+
+    #[error("max_queued must be at least 1")]
+    InvalidQueue,
+}
+
+impl AckPolicy {
+    pub fn validate(&self) -> Result<(), AckPolicyError> {
+        if self.timeout_ms == 0 {
+            return Err(AckPolicyError::InvalidTimeout);
+        }
+        if self.max_queued == 0 {
+            return Err(AckPolicyError::InvalidQueue);
+        }
+        Ok(())
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// Ack policies shared by all connections
+#[derive(Debug, Default)]
+pub struct AckPolicies {
+    policies: RwLock<BTreeMap<SubscriptionKind, AckPolicy>>,
+}
+
+impl AckPolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set or replace the policy of a subscription family
+    pub fn set(&self, kind: SubscriptionKind, policy: AckPolicy) -> Result<(), AckPolicyError> {
+        policy.validate()?;
+        self.policies.write().unwrap().insert(kind, policy);
+        Ok(())
+    }
+
+    /// Remove the policy of a subscription family; false if there was none
+    pub fn remove(&self, kind: SubscriptionKind) -> bool {
+        self.policies.write().unwrap().remove(&kind).is_some()
+    }
+
+    pub fn get(&self, kind: SubscriptionKind) -> Option<AckPolicy> {
+        self.policies.read().unwrap().get(&kind).cloned()
+    }
+
+    pub fn list(&self) -> BTreeMap<SubscriptionKind, AckPolicy> {
+        self.policies.read().unwrap().clone()
+    }
+}
+
+/// Something the connection has to do when an ack deadline passes
+#[derive(Debug, PartialEq)]
+pub enum AckAction<T> {
+    /// Send a push that waited behind an unacknowledged one
+    Send(T),
+    /// Send the unacknowledged push again
+    Resend(T),
+    /// Retries ran out and the push was given up
+    Dropped(T),
+    /// Retries ran out and the policy closes the connection
+    Disconnect(SubscriptionKind),
+}
+
+/// Retransmission counters of one connection, shown by `GET /connections`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AckStats {
+    /// Pushes sent but not acknowledged yet, plus the ones waiting behind them
+    pub awaiting: usize,
+    pub retransmitted: u64,
+    /// Pushes given up after their retries ran out
+    pub given_up: u64,
+}
+
+#[derive(Debug)]
+struct Outstanding<T> {
+    item: T,
+    ack: Mid,
+    retries: u32,
+    /// `None` once a buffering policy stopped resending
+    deadline: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Family<T> {
+    outstanding: Option<Outstanding<T>>,
+    queued: VecDeque<(T, Mid)>,
+}
+
+impl<T> Default for Family<T> {
+    fn default() -> Self {
+        Self {
+            outstanding: None,
+            queued: VecDeque::new(),
+        }
+    }
+}
+
+/// Unacknowledged and waiting pushes of one connection
+#[derive(Debug)]
+pub struct ClientAcks<T> {
+    families: BTreeMap<SubscriptionKind, Family<T>>,
+    retransmitted: u64,
+    given_up: u64,
+}
+
+impl<T> Default for ClientAcks<T> {
+    fn default() -> Self {
+        Self {
+            families: BTreeMap::new(),
+            retransmitted: 0,
+            given_up: 0,
+        }
+    }
+}
+
+impl<T: Clone> ClientAcks<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer a push of `kind` acknowledged with `ack` (`None` for NoAck pushes)
+    ///
+    /// It is sent now, or waits while another push of the family is unacknowledged.
+    pub fn admit(
+        &mut self,
+        policies: &AckPolicies,
+        kind: SubscriptionKind,
+        ack: Option<Mid>,
+        item: T,
+        now: Instant,
+    ) -> Admission<T> {
+        let Some(ack) = ack else {
+            return Admission {
+                send: Some(item),
+                dropped: Vec::new(),
+            };
+        };
+        let policy = policies.get(kind);
+        let family = self.families.entry(kind).or_default();
+        let busy = family.outstanding.is_some() || !family.queued.is_empty();
+        let Some(policy) = policy else {
+            if busy {
+                // Policy just removed: the next poll sends everything in order
+                family.queued.push_back((item, ack));
+                return Admission {
+                    send: None,
+                    dropped: Vec::new(),
+                };
+            }
+            return Admission {
+                send: Some(item),
+                dropped: Vec::new(),
+            };
+        };
+        if !busy {
+            family.outstanding = Some(Outstanding {
+                item: item.clone(),
+                ack,
+                retries: 0,
+                deadline: Some(now + policy.timeout()),
+            });
+            return Admission {
+                send: Some(item),
+                dropped: Vec::new(),
+            };
+        }
+
+        let mut dropped = Vec::new();
+        while family.queued.len() >= policy.max_queued {
+            dropped.extend(family.queued.pop_front().map(|(item, _)| item));
+        }
+        family.queued.push_back((item, ack));
+        Admission {
+            send: None,
+            dropped,
+        }
+    }
+
+    /// The client acknowledged with `ack`; false if no push waited for it
+    ///
+    /// The next waiting push of the family is due at the next [`poll`](Self::poll).
+    pub fn acknowledge(&mut self, ack: Mid) -> bool {
+        let family = self.families.values_mut().find(|family| {
+            family
+                .outstanding
+                .as_ref()
+                .is_some_and(|outstanding| outstanding.ack == ack)
+        });
+        match family {
+            Some(family) => {
+                family.outstanding = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make the oldest waiting push the outstanding one
+    fn promote(family: &mut Family<T>, policy: Option<&AckPolicy>, now: Instant) -> Option<T> {
+        let (item, ack) = family.queued.pop_front()?;
+        if let Some(policy) = policy {
+            family.outstanding = Some(Outstanding {
+                item: item.clone(),
+                ack,
+                retries: 0,
+                deadline: Some(now + policy.timeout()),
+            });
+        }
+        Some(item)
+    }
+
+    /// Resends, give-ups and waiting pushes due at `now`
+    pub fn poll(&mut self, policies: &AckPolicies, now: Instant) -> Vec<AckAction<T>> {
+        let mut actions = Vec::new();
+        for (kind, family) in &mut self.families {
+            let Some(policy) = policies.get(*kind) else {
+                // Policy removed: stop tracking and send everything that waits
+                family.outstanding = None;
+                actions.extend(
+                    family
+                        .queued
+                        .drain(..)
+                        .map(|(item, _)| AckAction::Send(item)),
+                );
+                continue;
+            };
+            if let Some(outstanding) = &mut family.outstanding {
+                if outstanding.deadline.is_none_or(|deadline| deadline > now) {
+                    continue;
+                }
+                if outstanding.retries < policy.max_retries {
+                    outstanding.retries += 1;
+                    outstanding.deadline = Some(now + policy.timeout());
+                    self.retransmitted += 1;
+                    actions.push(AckAction::Resend(outstanding.item.clone()));
+                    continue;
+                }
+                match policy.on_exhaustion {
+                    AckExhaustion::Drop => {
+                        self.given_up += 1;
+                        let outstanding = family.outstanding.take().unwrap();
+                        actions.push(AckAction::Dropped(outstanding.item));
+                    }
+                    AckExhaustion::Disconnect => {
+                        outstanding.deadline = None;
+                        actions.push(AckAction::Disconnect(*kind));
+                        continue;
+                    }
+                    AckExhaustion::Buffer => {
+                        outstanding.deadline = None;
+                        continue;
+                    }
+                }
+            }
+            if family.outstanding.is_none() {
+                actions.extend(Self::promote(family, Some(&policy), now).map(AckAction::Send));
+            }
+        }
+        self.families
+            .retain(|_, family| family.outstanding.is_some() || !family.queued.is_empty());
+        actions
+    }
+
+    /// When [`poll`](Self::poll) has something to do next, if anything is tracked
+    pub fn next_deadline(&self, policies: &AckPolicies) -> Option<Instant> {
+        self.families
+            .iter()
+            .filter(|(_, family)| family.outstanding.is_some() || !family.queued.is_empty())
+            .filter_map(|(kind, family)| {
+                if policies.get(*kind).is_none() {
+                    // Policy removed: release right away
+                    return Some(Instant::now());
+                }
+                match &family.outstanding {
+                    Some(outstanding) => outstanding.deadline,
+                    None if !family.queued.is_empty() => Some(Instant::now()),
+                    None => None,
+                }
+            })
+            .min()
+    }
+
+    pub fn stats(&self) -> AckStats {
+        AckStats {
+            awaiting: self
+                .families
+                .values()
+                .map(|family| family.outstanding.is_some() as usize + family.queued.len())
+                .sum(),
+            retransmitted: self.retransmitted,
+            given_up: self.given_up,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIND: SubscriptionKind = SubscriptionKind::TighteningResult;
+    const ACK: Mid = Mid::TIGHTENING_RESULT_ACK;
+
+    fn policies(max_retries: u32, on_exhaustion: AckExhaustion) -> AckPolicies {
+        let policies = AckPolicies::new();
+        policies
+            .set(
+                KIND,
+                AckPolicy {
+                    timeout_ms: 100,
+                    max_retries,
+                    on_exhaustion,
+                    max_queued: 2,
+                },
+            )
+            .unwrap();
+        policies
+    }
+
+    #[test]
+    fn test_stop_and_wait_until_acknowledged() {
+        let policies = policies(1, AckExhaustion::Drop);
+        let start = Instant::now();
+        let mut acks = ClientAcks::new();
+
+        assert_eq!(
+            acks.admit(&policies, KIND, Some(ACK), 1, start).send,
+            Some(1)
+        );
+        assert_eq!(acks.admit(&policies, KIND, Some(ACK), 2, start).send, None);
+        // NoAck pushes and families without a policy are not held back
+        assert_eq!(acks.admit(&policies, KIND, None, 3, start).send, Some(3));
+        let vin = acks.admit(
+            &policies,
+            SubscriptionKind::VehicleId,
+            Some(Mid::VEHICLE_ID_ACK),
+            4,
+            start,
+        );
+        assert_eq!(vin.send, Some(4));
+        assert_eq!(acks.stats().awaiting, 2);
+
+        // A different ack does not release the family
+        assert!(!acks.acknowledge(Mid::ALARM_ACK));
+        assert!(acks.acknowledge(ACK));
+        assert_eq!(acks.poll(&policies, start), vec![AckAction::Send(2)]);
+        assert!(acks.acknowledge(ACK));
+        assert!(!acks.acknowledge(ACK));
+        assert!(acks.poll(&policies, start).is_empty());
+        assert_eq!(acks.stats().awaiting, 0);
+        assert_eq!(acks.next_deadline(&policies), None);
+    }
+
+    #[test]
+    fn test_pset_selection_is_resent_until_mid_0016() {
+        let kind = SubscriptionKind::PsetSelection;
+        let policy: AckPolicy = serde_json::from_str("{}").unwrap();
+        let timeout = Duration::from_millis(policy.timeout_ms);
+        let policies = AckPolicies::new();
+        policies.set(kind, policy).unwrap();
+        let start = Instant::now();
+        let mut acks = ClientAcks::new();
+
+        let ack = Mid::PSET_SELECTED.info().and_then(|info| info.ack);
+        assert_eq!(ack, Some(Mid::PSET_SELECTED_ACK));
+        assert_eq!(acks.admit(&policies, kind, ack, 15, start).send, Some(15));
+        assert_eq!(
+            acks.poll(&policies, start + timeout),
+            vec![AckAction::Resend(15)]
+        );
+        assert!(acks.acknowledge(Mid::PSET_SELECTED_ACK));
+        assert!(acks.poll(&policies, start + timeout * 2).is_empty());
+    }
+
+    #[test]
+    fn test_retries_then_drop_sends_next() {
+        let policies = policies(2, AckExhaustion::Drop);
+        let start = Instant::now();
+        let mut acks = ClientAcks::new();
+        acks.admit(&policies, KIND, Some(ACK), 1, start);
+        acks.admit(&policies, KIND, Some(ACK), 2, start);
+
+        let timeout = Duration::from_millis(100);
+        assert_eq!(acks.next_deadline(&policies), Some(start + timeout));
+        assert!(acks.poll(&policies, start).is_empty());
+        assert_eq!(
+            acks.poll(&policies, start + timeout),
+            vec![AckAction::Resend(1)]
+        );
+        assert_eq!(
+            acks.poll(&policies, start + timeout * 2),
+            vec![AckAction::Resend(1)]
+        );
+        assert_eq!(
+            acks.poll(&policies, start + timeout * 3),
+            vec![AckAction::Dropped(1), AckAction::Send(2)]
+        );
+        let stats = acks.stats();
+        assert_eq!(
+            (stats.awaiting, stats.retransmitted, stats.given_up),
+            (1, 2, 1)
+        );
+    }
+
+    #[test]
+    fn test_disconnect_and_buffer_on_exhaustion() {
+        let start = Instant::now();
+        let timeout = Duration::from_millis(100);
+
+        let policies = policies(0, AckExhaustion::Disconnect);
+        let mut acks = ClientAcks::new();
+        acks.admit(&policies, KIND, Some(ACK), 1, start);
+        assert_eq!(
+            acks.poll(&policies, start + timeout),
+            vec![AckAction::Disconnect(KIND)]
+        );
+
+        let policies = self::policies(0, AckExhaustion::Buffer);
+        let mut acks = ClientAcks::new();
+        acks.admit(&policies, KIND, Some(ACK), 1, start);
+        acks.admit(&policies, KIND, Some(ACK), 2, start);
+        acks.admit(&policies, KIND, Some(ACK), 3, start);
+        // The queue holds two; the oldest waiting push makes room
+        assert_eq!(
+            acks.admit(&policies, KIND, Some(ACK), 4, start).dropped,
+            vec![2]
+        );
+        assert!(acks.poll(&policies, start + timeout).is_empty());
+        assert_eq!(acks.next_deadline(&policies), None);
+        assert!(acks.acknowledge(ACK));
+        assert_eq!(
+            acks.poll(&policies, start + timeout * 5),
+            vec![AckAction::Send(3)]
+        );
+
+        // Removing the policy sends everything that waits
+        assert!(policies.remove(KIND));
+        assert_eq!(acks.poll(&policies, start), vec![AckAction::Send(4)]);
+        assert_eq!(acks.stats().awaiting, 0);
+    }
+
+    #[test]
+    fn test_invalid_policies_are_rejected() {
+        let policies = AckPolicies::new();
+        let policy = AckPolicy {
+            timeout_ms: 0,
+            max_retries: 1,
+            on_exhaustion: AckExhaustion::Drop,
+            max_queued: 1,
+        };
+        assert_eq!(
+            policies.set(KIND, policy),
+            Err(AckPolicyError::InvalidTimeout)
+        );
+        assert!(policies.list().is_empty());
+    }
+}
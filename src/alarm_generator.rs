@@ -0,0 +1,106 @@
+//! Random alarm noise
+//!
+//! Long soak tests against a real line see the odd overheated tool or low
+//! battery. While auto-tightening runs, the generator raises alarms from the
+//! `[alarms]` catalogue at a configurable average rate and clears each one
+//! again after `auto_clear_secs`, so client alarm handling is exercised
+//! alongside the results stream.
+
+use crate::alarms::AlarmTemplate;
+use crate::config::AlarmsConfig;
+use crate::observable_state::ObservableState;
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::sync::Weak;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often the generator rolls the dice
+const TICK: Duration = Duration::from_secs(1);
+
+/// Pick a catalogue entry with probability proportional to its weight
+///
+/// Entries with a non-positive weight are never picked.
+pub fn pick<'a, R: Rng>(catalogue: &'a [AlarmTemplate], rng: &mut R) -> Option<&'a AlarmTemplate> {
+    let total: f64 = catalogue.iter().map(|a| a.weight.max(0.0)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut roll = rng.random_range(0.0..total);
+    for alarm in catalogue.iter().filter(|a| a.weight > 0.0) {
+        if roll < alarm.weight {
+            return Some(alarm);
+        }
+        roll -= alarm.weight;
+    }
+    catalogue.iter().rev().find(|a| a.weight > 0.0)
+}
+
+/// Raise random alarms while `active` is set
+///
+/// Does nothing when `random_per_minute` is 0 or the catalogue is empty.
+pub fn spawn_alarm_noise(state: ObservableState, config: AlarmsConfig, active: Weak<AtomicBool>) {
+    if config.random_per_minute <= 0.0 || config.catalogue.is_empty() {
+        return;
+    }
+    let chance_per_tick = (config.random_per_minute * TICK.as_secs_f64() / 60.0).min(1.0);
+
+    tokio::spawn(async move {
+        let mut rng = StdRng::from_os_rng();
+        let is_active = || active.upgrade().is_some_and(|a| a.load(Ordering::Relaxed));
+        while is_active() {
+            tokio::time::sleep(TICK).await;
+            if !is_active() || rng.random::<f64>() >= chance_per_tick {
+                continue;
+            }
+            let Some(alarm) = pick(&config.catalogue, &mut rng) else {
+                break;
+            };
+
+            println!("Random alarm {}: {}", alarm.error_code, alarm.description);
+            let id = state.raise_alarm(&alarm.error_code, &alarm.description, alarm.severity);
+            if config.auto_clear_secs > 0 {
+                let state = state.clone();
+                let after = Duration::from_secs(config.auto_clear_secs);
+                tokio::spawn(async move {
+                    tokio::time::sleep(after).await;
+                    // Already cleared by hand is fine
+                    let _ = state.clear_alarm(id);
+                });
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarms::{AlarmSeverity, default_catalogue};
+
+    #[test]
+    fn test_pick_follows_weights() {
+        let catalogue = vec![
+            AlarmTemplate::new("E001", "Common", AlarmSeverity::Info, 9.0),
+            AlarmTemplate::new("E002", "Rare", AlarmSeverity::Info, 1.0),
+            AlarmTemplate::new("E003", "Never", AlarmSeverity::Info, 0.0),
+        ];
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut common = 0;
+        for _ in 0..1000 {
+            let alarm = pick(&catalogue, &mut rng).unwrap();
+            assert_ne!(alarm.error_code, "E003");
+            if alarm.error_code == "E001" {
+                common += 1;
+            }
+        }
+        assert!(
+            (850..=950).contains(&common),
+            "picked common {} times",
+            common
+        );
+
+        assert!(pick(&catalogue[2..], &mut rng).is_none());
+        assert!(default_catalogue().iter().all(|a| a.error_code.len() == 4));
+    }
+}
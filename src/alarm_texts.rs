@@ -0,0 +1,153 @@
+//! Localized alarm texts
+//!
+//! HMIs display the alarm text the controller sends instead of keeping their
+//! own translations. The table maps an error code to its text per language
+//! (two-letter ISO 639-1 code); the built-in entries cover the default alarm
+//! catalogue and the retry-limit alarm, and `[alarms.texts]` adds or replaces
+//! entries. The language of the controller is `[alarms] language`, can be
+//! changed with `PATCH /state` or the simulator extension MID 9130, and is
+//! used for the alarm text of MID 0071 revision 2. `GET /alarms?lang=` and
+//! `GET /alarms/texts` let an HMI fetch texts in any language of the table.
+//! Codes without an entry for a language fall back to the alarm description.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Language used when none is configured
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Text per language (ISO 639-1 code → text)
+pub type Translations = BTreeMap<String, String>;
+
+/// Built-in texts: (error code, language, text)
+const BUILT_IN: &[(&str, &str, &str)] = &[
+    ("E101", "en", "Tool motor overheated"),
+    ("E101", "de", "Werkzeugmotor überhitzt"),
+    ("E101", "fr", "Moteur de l'outil en surchauffe"),
+    ("E101", "sv", "Verktygsmotorn överhettad"),
+    ("E102", "en", "Tool battery low"),
+    ("E102", "de", "Werkzeugakku schwach"),
+    ("E102", "fr", "Batterie de l'outil faible"),
+    ("E102", "sv", "Låg batterinivå i verktyget"),
+    ("E103", "en", "Torque transducer error"),
+    ("E103", "de", "Fehler Drehmomentaufnehmer"),
+    ("E103", "fr", "Erreur du capteur de couple"),
+    ("E103", "sv", "Fel på momentgivaren"),
+    ("E104", "en", "Parameter set locked"),
+    ("E104", "de", "Parametersatz gesperrt"),
+    ("E104", "fr", "Jeu de paramètres verrouillé"),
+    ("E104", "sv", "Parameteruppsättningen är låst"),
+    ("E105", "en", "Tool calibration due"),
+    ("E105", "de", "Werkzeugkalibrierung fällig"),
+    ("E105", "fr", "Étalonnage de l'outil requis"),
+    ("E105", "sv", "Verktyget behöver kalibreras"),
+    ("E501", "en", "Retry limit reached"),
+    ("E501", "de", "Wiederholungsgrenze erreicht"),
+    ("E501", "fr", "Limite de reprises atteinte"),
+    ("E501", "sv", "Gränsen för omförsök nådd"),
+    ("E502", "en", "Tool service due"),
+    ("E502", "de", "Werkzeugwartung fällig"),
+    ("E502", "fr", "Entretien de l'outil requis"),
+    ("E502", "sv", "Verktyget behöver service"),
+];
+
+/// Whether `language` is a two-letter lowercase language code
+pub fn is_valid_language(language: &str) -> bool {
+    language.len() == 2 && language.bytes().all(|b| b.is_ascii_lowercase())
+}
+
+/// Alarm texts by error code and language
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlarmTexts {
+    texts: BTreeMap<String, Translations>,
+}
+
+impl AlarmTexts {
+    /// Built-in texts with `overrides` (error code → translations) applied
+    pub fn with_overrides(overrides: &BTreeMap<String, Translations>) -> Self {
+        let mut texts = Self::default();
+        for (error_code, translations) in overrides {
+            let entry = texts.texts.entry(error_code.clone()).or_default();
+            for (language, text) in translations {
+                entry.insert(language.clone(), text.clone());
+            }
+        }
+        texts
+    }
+
+    /// Text of `error_code` in `language`, if the table has one
+    pub fn text(&self, error_code: &str, language: &str) -> Option<&str> {
+        self.texts
+            .get(error_code)
+            .and_then(|translations| translations.get(language))
+            .map(String::as_str)
+    }
+
+    /// Text of `error_code` in `language`, or `description` without one
+    pub fn localize(&self, error_code: &str, language: &str, description: &str) -> String {
+        self.text(error_code, language)
+            .unwrap_or(description)
+            .to_string()
+    }
+
+    /// Languages with at least one text, sorted
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self
+            .texts
+            .values()
+            .flat_map(|translations| translations.keys().cloned())
+            .collect();
+        languages.sort();
+        languages.dedup();
+        languages
+    }
+
+    /// All texts by error code
+    pub fn entries(&self) -> &BTreeMap<String, Translations> {
+        &self.texts
+    }
+}
+
+impl Default for AlarmTexts {
+    fn default() -> Self {
+        let mut texts: BTreeMap<String, Translations> = BTreeMap::new();
+        for (error_code, language, text) in BUILT_IN {
+            texts
+                .entry(error_code.to_string())
+                .or_default()
+                .insert(language.to_string(), text.to_string());
+        }
+        Self { texts }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_and_fallback() {
+        let overrides = BTreeMap::from([
+            (
+                "E101".to_string(),
+                Translations::from([("de".to_string(), "Motor zu heiß".to_string())]),
+            ),
+            (
+                "E900".to_string(),
+                Translations::from([("it".to_string(), "Porta aperta".to_string())]),
+            ),
+        ]);
+        let texts = AlarmTexts::with_overrides(&overrides);
+
+        assert_eq!(texts.text("E101", "de"), Some("Motor zu heiß"));
+        assert_eq!(texts.text("E101", "sv"), Some("Verktygsmotorn överhettad"));
+        assert_eq!(texts.localize("E900", "it", "Door open"), "Porta aperta");
+        assert_eq!(texts.localize("E900", "en", "Door open"), "Door open");
+        assert_eq!(texts.languages(), ["de", "en", "fr", "it", "sv"]);
+
+        assert!(is_valid_language("de"));
+        for language in ["", "DE", "deu", "d1"] {
+            assert!(!is_valid_language(language), "{language:?}");
+        }
+    }
+}
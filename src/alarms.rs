@@ -0,0 +1,401 @@
+//! Controller alarm list
+//!
+//! Alarms raised by the simulator go through the same lifecycle as on a real
+//! controller: they are raised `active`, get `acknowledged` either on the
+//! controller (`POST /alarms/{id}/ack`, announced to clients with MID 0074)
+//! or by a client over the protocol (MID 0075), and are finally `cleared`.
+//! All transitions happen under the device state lock, so when an HTTP and a
+//! protocol acknowledgment race for the same alarm exactly one of them wins.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use thiserror::Error;
+
+/// Number of alarms kept before the oldest cleared ones are discarded
+pub const ALARM_LIST_CAPACITY: usize = 500;
+
+/// How serious an alarm is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmSeverity {
+    Info,
+    Warning,
+    #[default]
+    Error,
+    Critical,
+}
+
+impl AlarmSeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlarmSeverity::Info => "info",
+            AlarmSeverity::Warning => "warning",
+            AlarmSeverity::Error => "error",
+            AlarmSeverity::Critical => "critical",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "info" => Some(AlarmSeverity::Info),
+            "warning" => Some(AlarmSeverity::Warning),
+            "error" => Some(AlarmSeverity::Error),
+            "critical" => Some(AlarmSeverity::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// Lifecycle state of an alarm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmState {
+    Active,
+    Acknowledged,
+    Cleared,
+}
+
+impl AlarmState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlarmState::Active => "active",
+            AlarmState::Acknowledged => "acknowledged",
+            AlarmState::Cleared => "cleared",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "active" => Some(AlarmState::Active),
+            "acknowledged" => Some(AlarmState::Acknowledged),
+            "cleared" => Some(AlarmState::Cleared),
+            _ => None,
+        }
+    }
+}
+
+/// Where an acknowledgment came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AckSource {
+    /// Acknowledged on the controller (HTTP API / web UI)
+    Http,
+    /// Acknowledged by an integrator over Open Protocol (MID 0075)
+    Protocol,
+}
+
+/// A single alarm and its lifecycle timestamps
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alarm {
+    /// Sequence number, increasing for the lifetime of the simulator
+    pub id: u64,
+    /// Four-character Open Protocol error code (e.g. "E501")
+    pub error_code: String,
+    pub description: String,
+    /// Localized alarm text (see `alarm_texts`); the description without one
+    #[serde(default)]
+    pub text: String,
+    pub severity: AlarmSeverity,
+    pub state: AlarmState,
+    /// Local time, Open Protocol format (YYYY-MM-DD:HH:MM:SS)
+    pub raised_at: String,
+    pub acknowledged_at: Option<String>,
+    pub acknowledged_by: Option<AckSource>,
+    /// Whether a client confirmed the MID 0074 sent for a controller-side ack
+    pub ack_confirmed: bool,
+    pub cleared_at: Option<String>,
+}
+
+/// An alarm the random alarm generator can raise
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmTemplate {
+    /// Four-character Open Protocol error code
+    pub error_code: String,
+    pub description: String,
+    #[serde(default)]
+    pub severity: AlarmSeverity,
+    /// Relative probability of this alarm being picked (default: 1.0)
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl AlarmTemplate {
+    pub fn new(error_code: &str, description: &str, severity: AlarmSeverity, weight: f64) -> Self {
+        Self {
+            error_code: error_code.to_string(),
+            description: description.to_string(),
+            severity,
+            weight,
+        }
+    }
+}
+
+/// Built-in catalogue of typical controller alarms
+pub fn default_catalogue() -> Vec<AlarmTemplate> {
+    vec![
+        AlarmTemplate::new("E101", "Tool motor overheated", AlarmSeverity::Warning, 3.0),
+        AlarmTemplate::new("E102", "Tool battery low", AlarmSeverity::Warning, 4.0),
+        AlarmTemplate::new(
+            "E103",
+            "Torque transducer error",
+            AlarmSeverity::Critical,
+            1.0,
+        ),
+        AlarmTemplate::new("E104", "Parameter set locked", AlarmSeverity::Info, 2.0),
+        AlarmTemplate::new("E105", "Tool calibration due", AlarmSeverity::Info, 1.0),
+    ]
+}
+
+/// Why an alarm transition was refused
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AlarmError {
+    #[error("Alarm {0} not found")]
+    NotFound(u64),
+
+    #[error("Alarm {id} is already {}", state.as_str())]
+    InvalidTransition { id: u64, state: AlarmState },
+}
+
+/// What a MID 0075 from a client did to the alarm list
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolAck {
+    /// Confirmed the MID 0074 of an alarm acknowledged on the controller
+    Confirmed(Alarm),
+    /// Acknowledged the oldest active alarm
+    Acknowledged(Alarm),
+    /// Nothing was waiting for an acknowledgment
+    Nothing,
+}
+
+/// Bounded list of controller alarms
+#[derive(Debug, Clone)]
+pub struct AlarmList {
+    alarms: VecDeque<Alarm>,
+    capacity: usize,
+    next_id: u64,
+}
+
+fn now() -> String {
+    chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string()
+}
+
+impl AlarmList {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            alarms: VecDeque::new(),
+            capacity: capacity.max(1),
+            next_id: 1,
+        }
+    }
+
+    /// Raise a new active alarm and return a copy of it
+    pub fn raise(
+        &mut self,
+        error_code: impl Into<String>,
+        description: impl Into<String>,
+        severity: AlarmSeverity,
+    ) -> Alarm {
+        let description = description.into();
+        self.raise_with_text(error_code, description.clone(), description, severity)
+    }
+
+    /// Raise a new active alarm with a localized text
+    pub fn raise_with_text(
+        &mut self,
+        error_code: impl Into<String>,
+        description: impl Into<String>,
+        text: impl Into<String>,
+        severity: AlarmSeverity,
+    ) -> Alarm {
+        let alarm = Alarm {
+            id: self.next_id,
+            error_code: error_code.into(),
+            description: description.into(),
+            text: text.into(),
+            severity,
+            state: AlarmState::Active,
+            raised_at: now(),
+            acknowledged_at: None,
+            acknowledged_by: None,
+            ack_confirmed: false,
+            cleared_at: None,
+        };
+        self.next_id += 1;
+
+        if self.alarms.len() >= self.capacity {
+            // Prefer dropping history over alarms that still need attention
+            let oldest = self
+                .alarms
+                .iter()
+                .position(|a| a.state == AlarmState::Cleared)
+                .unwrap_or(0);
+            self.alarms.remove(oldest);
+        }
+        self.alarms.push_back(alarm.clone());
+        alarm
+    }
+
+    /// Acknowledge an active alarm
+    pub fn acknowledge(&mut self, id: u64, source: AckSource) -> Result<Alarm, AlarmError> {
+        let alarm = self.find_mut(id)?;
+        if alarm.state != AlarmState::Active {
+            return Err(AlarmError::InvalidTransition {
+                id,
+                state: alarm.state,
+            });
+        }
+        alarm.state = AlarmState::Acknowledged;
+        alarm.acknowledged_at = Some(now());
+        alarm.acknowledged_by = Some(source);
+        // A protocol ack needs no further confirmation from the client
+        alarm.ack_confirmed = source == AckSource::Protocol;
+        Ok(alarm.clone())
+    }
+
+    /// Apply a MID 0075 from a client
+    ///
+    /// A pending MID 0074 (controller-side ack not yet confirmed) is confirmed
+    /// first; otherwise the oldest active alarm is acknowledged.
+    pub fn acknowledge_by_protocol(&mut self) -> ProtocolAck {
+        if let Some(alarm) = self
+            .alarms
+            .iter_mut()
+            .find(|a| a.state == AlarmState::Acknowledged && !a.ack_confirmed)
+        {
+            alarm.ack_confirmed = true;
+            return ProtocolAck::Confirmed(alarm.clone());
+        }
+        match self.alarms.iter().find(|a| a.state == AlarmState::Active) {
+            Some(alarm) => {
+                let id = alarm.id;
+                ProtocolAck::Acknowledged(
+                    self.acknowledge(id, AckSource::Protocol)
+                        .expect("alarm was active"),
+                )
+            }
+            None => ProtocolAck::Nothing,
+        }
+    }
+
+    /// Clear an active or acknowledged alarm
+    pub fn clear(&mut self, id: u64) -> Result<Alarm, AlarmError> {
+        let alarm = self.find_mut(id)?;
+        if alarm.state == AlarmState::Cleared {
+            return Err(AlarmError::InvalidTransition {
+                id,
+                state: alarm.state,
+            });
+        }
+        alarm.state = AlarmState::Cleared;
+        alarm.cleared_at = Some(now());
+        Ok(alarm.clone())
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Alarm> {
+        self.alarms.iter().find(|a| a.id == id)
+    }
+
+    /// All retained alarms, oldest first
+    pub fn alarms(&self) -> Vec<Alarm> {
+        self.alarms.iter().cloned().collect()
+    }
+
+    /// Number of alarms kept (open and cleared)
+    pub fn len(&self) -> usize {
+        self.alarms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alarms.is_empty()
+    }
+
+    /// Number of alarms that are not cleared yet
+    pub fn open_count(&self) -> usize {
+        self.alarms
+            .iter()
+            .filter(|a| a.state != AlarmState::Cleared)
+            .count()
+    }
+
+    fn find_mut(&mut self, id: u64) -> Result<&mut Alarm, AlarmError> {
+        self.alarms
+            .iter_mut()
+            .find(|a| a.id == id)
+            .ok_or(AlarmError::NotFound(id))
+    }
+}
+
+impl Default for AlarmList {
+    fn default() -> Self {
+        Self::new(ALARM_LIST_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle_and_invalid_transitions() {
+        let mut list = AlarmList::default();
+        let alarm = list.raise("E501", "Retry limit", AlarmSeverity::Critical);
+        assert_eq!(alarm.id, 1);
+        assert_eq!(alarm.state, AlarmState::Active);
+
+        let acked = list.acknowledge(1, AckSource::Http).unwrap();
+        assert_eq!(acked.state, AlarmState::Acknowledged);
+        assert_eq!(acked.acknowledged_by, Some(AckSource::Http));
+        assert_eq!(
+            list.acknowledge(1, AckSource::Protocol),
+            Err(AlarmError::InvalidTransition {
+                id: 1,
+                state: AlarmState::Acknowledged
+            })
+        );
+
+        assert_eq!(list.clear(1).unwrap().state, AlarmState::Cleared);
+        assert!(list.clear(1).is_err());
+        assert_eq!(
+            list.acknowledge(9, AckSource::Http),
+            Err(AlarmError::NotFound(9))
+        );
+        assert_eq!(list.open_count(), 0);
+    }
+
+    #[test]
+    fn test_protocol_ack_confirms_pending_0074_before_acknowledging() {
+        let mut list = AlarmList::default();
+        list.raise("E100", "First", AlarmSeverity::Warning);
+        list.raise("E200", "Second", AlarmSeverity::Error);
+        list.acknowledge(2, AckSource::Http).unwrap();
+
+        match list.acknowledge_by_protocol() {
+            ProtocolAck::Confirmed(alarm) => assert_eq!(alarm.id, 2),
+            other => panic!("expected confirmation, got {:?}", other),
+        }
+        match list.acknowledge_by_protocol() {
+            ProtocolAck::Acknowledged(alarm) => {
+                assert_eq!(alarm.id, 1);
+                assert_eq!(alarm.acknowledged_by, Some(AckSource::Protocol));
+            }
+            other => panic!("expected acknowledgment, got {:?}", other),
+        }
+        assert_eq!(list.acknowledge_by_protocol(), ProtocolAck::Nothing);
+    }
+
+    #[test]
+    fn test_capacity_drops_cleared_alarms_first() {
+        let mut list = AlarmList::new(2);
+        list.raise("E001", "Open", AlarmSeverity::Info);
+        list.raise("E002", "Done", AlarmSeverity::Info);
+        list.clear(2).unwrap();
+        list.raise("E003", "New", AlarmSeverity::Info);
+
+        let ids: Vec<u64> = list.alarms().iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+}
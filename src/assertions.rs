@@ -0,0 +1,444 @@
+//! Protocol expectations checked by the simulator
+//!
+//! A test registers an assertion such as "the client at 10.0.0.5 acknowledges
+//! every MID 0061 within 2 s" or "no frame fails to parse for 60 s" through
+//! `POST /assertions`. The connection tasks report what they send and receive,
+//! each assertion keeps the observations it needs as evidence, and
+//! `GET /assertions/{id}` reports whether it is still pending, passed or
+//! failed. An assertion fails as soon as a violation is seen and passes once
+//! its observation window ends (and every push in it was acknowledged).
+
+use crate::protocol::Mid;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use thiserror::Error;
+
+/// Assertions kept; the oldest finished ones are evicted first
+pub const MAX_ASSERTIONS: usize = 1000;
+
+/// Observations kept as evidence per assertion
+pub const MAX_EVIDENCE: usize = 1000;
+
+/// What the simulator expects from its clients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Expectation {
+    /// Every push of `mid` is acknowledged within `within_ms`
+    AckWithin { mid: u16, within_ms: u64 },
+    /// No frame fails to parse
+    NoParseErrors,
+}
+
+/// An expectation, the clients it applies to and how long it is observed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssertionSpec {
+    #[serde(flatten)]
+    pub expectation: Expectation,
+    /// Only clients connecting from this address (default: every client)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<IpAddr>,
+    /// Observation window, starting when the assertion is registered
+    pub duration_ms: u64,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AssertionError {
+    #[error("duration_ms must be at least 1")]
+    InvalidDuration,
+
+    #[error("within_ms must be at least 1")]
+    InvalidDeadline,
+
+    #[error("MID {0} is not acknowledged by clients")]
+    NotAcknowledged(Mid),
+}
+
+impl AssertionSpec {
+    pub fn validate(&self) -> Result<(), AssertionError> {
+        if self.duration_ms == 0 {
+            return Err(AssertionError::InvalidDuration);
+        }
+        if let Expectation::AckWithin { mid, within_ms } = self.expectation {
+            if within_ms == 0 {
+                return Err(AssertionError::InvalidDeadline);
+            }
+            if ack_mid(Mid(mid)).is_none() {
+                return Err(AssertionError::NotAcknowledged(Mid(mid)));
+            }
+        }
+        Ok(())
+    }
+
+    fn applies_to(&self, addr: SocketAddr) -> bool {
+        self.client.is_none_or(|ip| ip == addr.ip())
+    }
+}
+
+/// MID a client acknowledges a push of `mid` with
+fn ack_mid(mid: Mid) -> Option<Mid> {
+    mid.info().and_then(|info| info.ack)
+}
+
+/// Where an assertion stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssertionStatus {
+    Pending,
+    Passed,
+    Failed,
+}
+
+/// A push waiting for (or given) its acknowledgment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PushEvidence {
+    pub client: SocketAddr,
+    /// Milliseconds after the assertion started
+    pub sent_at_ms: u64,
+    pub acked_after_ms: Option<u64>,
+}
+
+/// A frame that could not be parsed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseErrorEvidence {
+    pub client: SocketAddr,
+    /// Milliseconds after the assertion started
+    pub at_ms: u64,
+    pub error: String,
+}
+
+/// Observations relevant to one assertion
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Evidence {
+    Pushes(Vec<PushEvidence>),
+    ParseErrors(Vec<ParseErrorEvidence>),
+}
+
+/// Status of an assertion with the evidence it was decided on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssertionReport {
+    pub id: u64,
+    #[serde(flatten)]
+    pub spec: AssertionSpec,
+    pub status: AssertionStatus,
+    /// The first violation, if failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Milliseconds since the assertion was registered
+    pub elapsed_ms: u64,
+    pub evidence: Evidence,
+}
+
+#[derive(Debug)]
+struct Assertion {
+    spec: AssertionSpec,
+    started_at: Instant,
+    pushes: Vec<PushEvidence>,
+    parse_errors: Vec<ParseErrorEvidence>,
+}
+
+impl Assertion {
+    fn ms_since_start(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started_at).as_millis() as u64
+    }
+
+    fn observing(&self, now: Instant) -> bool {
+        self.ms_since_start(now) < self.spec.duration_ms
+    }
+
+    fn on_sent(&mut self, addr: SocketAddr, mid: Mid, now: Instant) {
+        let Expectation::AckWithin { mid: expected, .. } = self.spec.expectation else {
+            return;
+        };
+        if mid.value() != expected || !self.observing(now) || self.pushes.len() >= MAX_EVIDENCE {
+            return;
+        }
+        self.pushes.push(PushEvidence {
+            client: addr,
+            sent_at_ms: self.ms_since_start(now),
+            acked_after_ms: None,
+        });
+    }
+
+    fn on_received(&mut self, addr: SocketAddr, mid: Mid, now: Instant) {
+        let Expectation::AckWithin { mid: expected, .. } = self.spec.expectation else {
+            return;
+        };
+        if ack_mid(Mid(expected)) != Some(mid) {
+            return;
+        }
+        // Acknowledgments answer the oldest outstanding push of the connection
+        let at_ms = self.ms_since_start(now);
+        if let Some(push) = self
+            .pushes
+            .iter_mut()
+            .find(|push| push.client == addr && push.acked_after_ms.is_none())
+        {
+            push.acked_after_ms = Some(at_ms.saturating_sub(push.sent_at_ms));
+        }
+    }
+
+    fn on_parse_error(&mut self, addr: SocketAddr, error: &str, now: Instant) {
+        if self.spec.expectation != Expectation::NoParseErrors
+            || !self.observing(now)
+            || self.parse_errors.len() >= MAX_EVIDENCE
+        {
+            return;
+        }
+        self.parse_errors.push(ParseErrorEvidence {
+            client: addr,
+            at_ms: self.ms_since_start(now),
+            error: error.to_string(),
+        });
+    }
+
+    fn verdict(&self, now: Instant) -> (AssertionStatus, Option<String>) {
+        let elapsed_ms = self.ms_since_start(now);
+        match self.spec.expectation {
+            Expectation::NoParseErrors => {
+                if let Some(error) = self.parse_errors.first() {
+                    let reason = format!(
+                        "parse error from {} after {} ms: {}",
+                        error.client, error.at_ms, error.error
+                    );
+                    return (AssertionStatus::Failed, Some(reason));
+                }
+                if elapsed_ms >= self.spec.duration_ms {
+                    (AssertionStatus::Passed, None)
+                } else {
+                    (AssertionStatus::Pending, None)
+                }
+            }
+            Expectation::AckWithin { mid, within_ms } => {
+                for push in &self.pushes {
+                    let waited = push
+                        .acked_after_ms
+                        .unwrap_or(elapsed_ms.saturating_sub(push.sent_at_ms));
+                    if waited > within_ms {
+                        let reason = match push.acked_after_ms {
+                            Some(after) => format!(
+                                "MID {} to {} acknowledged after {} ms (limit {} ms)",
+                                Mid(mid),
+                                push.client,
+                                after,
+                                within_ms
+                            ),
+                            None => format!(
+                                "MID {} to {} not acknowledged within {} ms",
+                                Mid(mid),
+                                push.client,
+                                within_ms
+                            ),
+                        };
+                        return (AssertionStatus::Failed, Some(reason));
+                    }
+                }
+                let all_acked = self.pushes.iter().all(|push| push.acked_after_ms.is_some());
+                if elapsed_ms >= self.spec.duration_ms && all_acked {
+                    (AssertionStatus::Passed, None)
+                } else {
+                    (AssertionStatus::Pending, None)
+                }
+            }
+        }
+    }
+
+    fn report(&self, id: u64, now: Instant) -> AssertionReport {
+        let (status, reason) = self.verdict(now);
+        let evidence = match self.spec.expectation {
+            Expectation::AckWithin { .. } => Evidence::Pushes(self.pushes.clone()),
+            Expectation::NoParseErrors => Evidence::ParseErrors(self.parse_errors.clone()),
+        };
+        AssertionReport {
+            id,
+            spec: self.spec.clone(),
+            status,
+            reason,
+            elapsed_ms: self.ms_since_start(now),
+            evidence,
+        }
+    }
+
+    /// Whether the verdict is final
+    fn is_settled(&self, now: Instant) -> bool {
+        self.verdict(now).0 != AssertionStatus::Pending
+    }
+}
+
+/// Registered assertions, fed by every connection
+#[derive(Debug, Default)]
+pub struct Assertions {
+    entries: Mutex<BTreeMap<u64, Assertion>>,
+    next_id: AtomicU64,
+}
+
+impl Assertions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start observing an expectation; returns its ID
+    pub fn register(&self, spec: AssertionSpec, now: Instant) -> Result<u64, AssertionError> {
+        spec.validate()?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ASSERTIONS {
+            let evict = entries
+                .iter()
+                .find(|(_, assertion)| assertion.is_settled(now))
+                .or_else(|| entries.iter().next())
+                .map(|(id, _)| *id);
+            if let Some(evict) = evict {
+                entries.remove(&evict);
+            }
+        }
+        entries.insert(
+            id,
+            Assertion {
+                spec,
+                started_at: now,
+                pushes: Vec::new(),
+                parse_errors: Vec::new(),
+            },
+        );
+        Ok(id)
+    }
+
+    pub fn get(&self, id: u64, now: Instant) -> Option<AssertionReport> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(&id).map(|assertion| assertion.report(id, now))
+    }
+
+    /// Reports of every assertion, oldest first
+    pub fn list(&self, now: Instant) -> Vec<AssertionReport> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .map(|(id, assertion)| assertion.report(*id, now))
+            .collect()
+    }
+
+    /// Forget an assertion; false if there was none
+    pub fn remove(&self, id: u64) -> bool {
+        self.entries.lock().unwrap().remove(&id).is_some()
+    }
+
+    fn observe(&self, addr: SocketAddr, mut f: impl FnMut(&mut Assertion)) {
+        let mut entries = self.entries.lock().unwrap();
+        for assertion in entries.values_mut() {
+            if assertion.spec.applies_to(addr) {
+                f(assertion);
+            }
+        }
+    }
+
+    /// A message was sent to the client at `addr`
+    pub fn record_sent(&self, addr: SocketAddr, mid: Mid, now: Instant) {
+        self.observe(addr, |assertion| assertion.on_sent(addr, mid, now));
+    }
+
+    /// A message was received from the client at `addr`
+    pub fn record_received(&self, addr: SocketAddr, mid: Mid, now: Instant) {
+        self.observe(addr, |assertion| assertion.on_received(addr, mid, now));
+    }
+
+    /// A frame from the client at `addr` could not be parsed
+    pub fn record_parse_error(&self, addr: SocketAddr, error: &str, now: Instant) {
+        self.observe(addr, |assertion| assertion.on_parse_error(addr, error, now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn millis(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn ack_within(client: Option<IpAddr>) -> AssertionSpec {
+        AssertionSpec {
+            expectation: Expectation::AckWithin {
+                mid: 61,
+                within_ms: 2000,
+            },
+            client,
+            duration_ms: 10_000,
+        }
+    }
+
+    #[test]
+    fn test_ack_within_passes_fails_and_filters_clients() {
+        let assertions = Assertions::new();
+        let start = Instant::now();
+        let station: SocketAddr = "10.0.0.5:50000".parse().unwrap();
+        let other: SocketAddr = "10.0.0.6:50000".parse().unwrap();
+        let id = assertions
+            .register(ack_within(Some(station.ip())), start)
+            .unwrap();
+
+        assertions.record_sent(station, Mid(61), start + millis(100));
+        assertions.record_sent(other, Mid(61), start + millis(100));
+        assertions.record_received(station, Mid(62), start + millis(600));
+        let report = assertions.get(id, start + millis(1000)).unwrap();
+        assert_eq!(report.status, AssertionStatus::Pending);
+        assert_eq!(
+            report.evidence,
+            Evidence::Pushes(vec![PushEvidence {
+                client: station,
+                sent_at_ms: 100,
+                acked_after_ms: Some(500),
+            }])
+        );
+        assert_eq!(
+            assertions.get(id, start + millis(10_000)).unwrap().status,
+            AssertionStatus::Passed
+        );
+
+        // A push left unacknowledged fails once its deadline passes
+        assertions.record_sent(station, Mid(61), start + millis(9_000));
+        let report = assertions.get(id, start + millis(10_500)).unwrap();
+        assert_eq!(report.status, AssertionStatus::Pending);
+        let report = assertions.get(id, start + millis(11_001)).unwrap();
+        assert_eq!(report.status, AssertionStatus::Failed);
+        assert!(report.reason.unwrap().contains("not acknowledged"));
+    }
+
+    #[test]
+    fn test_parse_errors_fail_and_specs_are_validated() {
+        let assertions = Assertions::new();
+        let start = Instant::now();
+        let spec = AssertionSpec {
+            expectation: Expectation::NoParseErrors,
+            client: None,
+            duration_ms: 60_000,
+        };
+        let id = assertions.register(spec, start).unwrap();
+        let client: SocketAddr = "127.0.0.1:40000".parse().unwrap();
+        assertions.record_parse_error(client, "bad header", start + millis(5));
+        let report = assertions.get(id, start + millis(10)).unwrap();
+        assert_eq!(report.status, AssertionStatus::Failed);
+        assert!(report.reason.unwrap().contains("bad header"));
+
+        let mut not_acked = ack_within(None);
+        not_acked.expectation = Expectation::AckWithin {
+            mid: 1,
+            within_ms: 100,
+        };
+        assert_eq!(
+            assertions.register(not_acked, start),
+            Err(AssertionError::NotAcknowledged(Mid(1)))
+        );
+        let mut no_window = ack_within(None);
+        no_window.duration_ms = 0;
+        assert_eq!(
+            assertions.register(no_window, start),
+            Err(AssertionError::InvalidDuration)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Controller audit log
+//!
+//! Records controller-side events (PSET changes, tool locks, configuration
+//! updates, connections, ...) the way a real controller keeps an event log,
+//! so traceability software that pulls controller logs can be tested. The log
+//! is exposed via `GET /audit` and the event log upload MIDs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Number of entries kept before the oldest are discarded
+pub const AUDIT_LOG_CAPACITY: usize = 1000;
+
+/// Kind of controller event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCategory {
+    Pset,
+    Tool,
+    Batch,
+    Identification,
+    Configuration,
+    Connection,
+    Alarm,
+    Operator,
+}
+
+impl AuditCategory {
+    /// Two-digit code used in the event log upload MID
+    pub fn code(self) -> u8 {
+        match self {
+            AuditCategory::Pset => 1,
+            AuditCategory::Tool => 2,
+            AuditCategory::Batch => 3,
+            AuditCategory::Identification => 4,
+            AuditCategory::Configuration => 5,
+            AuditCategory::Connection => 6,
+            AuditCategory::Alarm => 7,
+            AuditCategory::Operator => 8,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pset" => Some(AuditCategory::Pset),
+            "tool" => Some(AuditCategory::Tool),
+            "batch" => Some(AuditCategory::Batch),
+            "identification" => Some(AuditCategory::Identification),
+            "configuration" => Some(AuditCategory::Configuration),
+            "connection" => Some(AuditCategory::Connection),
+            "alarm" => Some(AuditCategory::Alarm),
+            "operator" => Some(AuditCategory::Operator),
+            _ => None,
+        }
+    }
+}
+
+/// A single audit log entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Sequence number, increasing for the lifetime of the simulator
+    pub id: u64,
+    /// Local time, Open Protocol format (YYYY-MM-DD:HH:MM:SS)
+    pub timestamp: String,
+    pub category: AuditCategory,
+    pub description: String,
+}
+
+/// Bounded in-memory audit log
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    entries: VecDeque<AuditEntry>,
+    capacity: usize,
+    next_id: u64,
+}
+
+impl AuditLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+            next_id: 1,
+        }
+    }
+
+    /// Append an entry stamped with the current local time
+    pub fn record(&mut self, category: AuditCategory, description: impl Into<String>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(AuditEntry {
+            id,
+            timestamp: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+            category,
+            description: description.into(),
+        });
+        id
+    }
+
+    /// Entries with an ID greater than `after_id`, oldest first
+    pub fn since(&self, after_id: u64) -> Vec<AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.id > after_id)
+            .cloned()
+            .collect()
+    }
+
+    /// All retained entries, oldest first
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.since(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new(AUDIT_LOG_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_assigns_increasing_ids() {
+        let mut log = AuditLog::default();
+        assert_eq!(log.record(AuditCategory::Pset, "Pset 2 selected"), 1);
+        assert_eq!(log.record(AuditCategory::Tool, "Tool disabled"), 2);
+
+        let since = log.since(1);
+        assert_eq!(since.len(), 1);
+        assert_eq!(since[0].category, AuditCategory::Tool);
+        assert_eq!(since[0].timestamp.len(), 19);
+    }
+
+    #[test]
+    fn test_log_is_bounded_but_ids_keep_counting() {
+        let mut log = AuditLog::new(2);
+        for i in 0..5 {
+            log.record(AuditCategory::Connection, format!("Client {}", i));
+        }
+        let ids: Vec<u64> = log.entries().iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![4, 5]);
+    }
+}
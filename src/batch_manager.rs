@@ -35,6 +35,10 @@ pub struct BatchManager {
     completed: bool,
     /// Whether any tightening in this batch has been NOK
     has_nok: bool,
+    /// Start over automatically after completion instead of waiting for MID 0019
+    auto_reset: bool,
+    /// Consecutive NOK tightenings at the current position
+    consecutive_nok: u32,
 }
 
 impl BatchManager {
@@ -45,6 +49,41 @@ impl BatchManager {
             target_size,
             completed: false,
             has_nok: false,
+            auto_reset: false,
+            consecutive_nok: 0,
+        }
+    }
+
+    /// Create a batch manager that restarts automatically once completed
+    pub fn with_auto_reset(target_size: u32, auto_reset: bool) -> Self {
+        Self {
+            auto_reset,
+            ..Self::new(target_size)
+        }
+    }
+
+    /// Whether the batch restarts automatically after completion
+    pub fn auto_reset(&self) -> bool {
+        self.auto_reset
+    }
+
+    /// Change the auto-reset behavior of this batch
+    pub fn set_auto_reset(&mut self, auto_reset: bool) {
+        self.auto_reset = auto_reset;
+    }
+
+    /// Whether the batch is done and must wait for a new MID 0019
+    pub fn is_waiting_for_config(&self) -> bool {
+        self.completed && !self.auto_reset
+    }
+
+    /// Number of OK tightenings still needed
+    /// A completed auto-reset batch reports a full batch of remaining work.
+    pub fn remaining(&self) -> u32 {
+        if self.completed && self.auto_reset {
+            self.target_size
+        } else {
+            self.target_size.saturating_sub(self.counter)
         }
     }
 
@@ -54,12 +93,19 @@ impl BatchManager {
     /// Note: Counter only increments on OK tightenings. NOK tightenings
     /// don't advance position - this allows integrator to retry same position.
     /// The tightening_id field is set to 0 here and should be overridden by the caller.
+    /// With auto-reset enabled, the first tightening after completion starts a new batch.
     pub fn add_tightening(&mut self, result_ok: bool) -> TighteningInfo {
+        if self.completed && self.auto_reset {
+            self.reset();
+        }
+
         // Only increment counter on OK (NOK allows retry at same position)
         if result_ok {
             self.counter += 1;
+            self.consecutive_nok = 0;
         } else {
             self.has_nok = true;
+            self.consecutive_nok += 1;
         }
 
         // Check if batch is complete (based on OK count)
@@ -90,6 +136,7 @@ impl BatchManager {
         self.counter = 0;
         self.completed = false;
         self.has_nok = false;
+        self.consecutive_nok = 0;
     }
 
     /// Set a new target batch size
@@ -136,11 +183,22 @@ impl BatchManager {
         self.target_size
     }
 
+    /// Number of consecutive NOK tightenings at the current position
+    pub fn consecutive_nok(&self) -> u32 {
+        self.consecutive_nok
+    }
+
+    /// Clear the consecutive NOK count (e.g. after the retry limit was handled)
+    pub fn clear_consecutive_nok(&mut self) {
+        self.consecutive_nok = 0;
+    }
+
     /// Increment the batch counter without a tightening result.
     /// Used by MID 0020 to skip a bolt position (e.g., after max retries).
     /// Returns the new counter value.
     pub fn increment(&mut self) -> u32 {
         self.counter += 1;
+        self.consecutive_nok = 0;
         if self.counter >= self.target_size {
             self.completed = true;
         }
@@ -336,4 +394,45 @@ mod tests {
         assert_eq!(final_info.batch_status, BatchStatus::CompletedNok);
         assert!(manager.is_complete());
     }
+
+    #[test]
+    fn test_auto_reset_starts_new_batch() {
+        let mut manager = BatchManager::with_auto_reset(2, true);
+
+        manager.add_tightening(true);
+        let info = manager.add_tightening(true);
+        assert_eq!(info.batch_status, BatchStatus::CompletedOk);
+        assert!(manager.is_complete());
+        assert!(!manager.is_waiting_for_config());
+        assert_eq!(manager.remaining(), 2);
+
+        let info = manager.add_tightening(true);
+        assert_eq!(info.counter, 1);
+        assert_eq!(info.batch_status, BatchStatus::NotFinished);
+        assert!(!manager.is_complete());
+    }
+
+    #[test]
+    fn test_without_auto_reset_waits_for_config() {
+        let mut manager = BatchManager::new(1);
+        manager.add_tightening(true);
+        assert!(manager.is_waiting_for_config());
+        assert_eq!(manager.remaining(), 0);
+    }
+
+    #[test]
+    fn test_consecutive_nok_tracking() {
+        let mut manager = BatchManager::new(3);
+
+        manager.add_tightening(false);
+        manager.add_tightening(false);
+        assert_eq!(manager.consecutive_nok(), 2);
+
+        manager.add_tightening(true);
+        assert_eq!(manager.consecutive_nok(), 0);
+
+        manager.add_tightening(false);
+        manager.increment();
+        assert_eq!(manager.consecutive_nok(), 0);
+    }
 }
@@ -0,0 +1,207 @@
+//! Load-dependent broadcast latency
+//!
+//! A real controller pushes subscription data a little later when it is busy:
+//! more connected clients and a higher tightening rate mean more work before a
+//! result reaches the wire. The model in `[broadcast_latency]` turns the
+//! current load into a processing delay that each connection waits before
+//! pushing, so dashboards showing end-to-end latency see plausible values
+//! that rise and fall with the load instead of a constant.
+
+use crate::config::BroadcastLatencyConfig;
+use crate::context::AppContext;
+use crate::events::SimulatorEvent;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::AbortHandle;
+
+/// Window the tightening rate is measured over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Load the latency is derived from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Load {
+    /// Connected Open Protocol clients
+    pub clients: usize,
+    /// Tightenings completed in the last minute
+    pub tightenings_per_minute: usize,
+}
+
+/// Broadcast latency model and the tightening rate it depends on
+#[derive(Debug)]
+pub struct BroadcastLatency {
+    config: BroadcastLatencyConfig,
+    tightenings: Mutex<VecDeque<Instant>>,
+}
+
+impl BroadcastLatency {
+    pub fn new(config: BroadcastLatencyConfig) -> Self {
+        Self {
+            config,
+            tightenings: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Whether the model can produce a delay at all
+    pub fn is_enabled(&self) -> bool {
+        self.config.max_ms > 0
+            && (self.config.base_ms > 0
+                || self.config.per_client_ms > 0.0
+                || self.config.per_tightening_ms > 0.0)
+    }
+
+    /// Count a completed tightening towards the rate
+    pub fn record_tightening(&self) {
+        self.record_tightening_at(Instant::now());
+    }
+
+    fn record_tightening_at(&self, at: Instant) {
+        let mut tightenings = self.tightenings.lock().unwrap();
+        tightenings.push_back(at);
+        Self::expire(&mut tightenings, at);
+    }
+
+    /// Tightenings completed within the last minute before `now`
+    fn tightenings_per_minute_at(&self, now: Instant) -> usize {
+        let mut tightenings = self.tightenings.lock().unwrap();
+        Self::expire(&mut tightenings, now);
+        tightenings.len()
+    }
+
+    fn expire(tightenings: &mut VecDeque<Instant>, now: Instant) {
+        while tightenings
+            .front()
+            .is_some_and(|&t| now.saturating_duration_since(t) > RATE_WINDOW)
+        {
+            tightenings.pop_front();
+        }
+    }
+
+    /// Current load with `clients` connected
+    pub fn load(&self, clients: usize) -> Load {
+        Load {
+            clients,
+            tightenings_per_minute: self.tightenings_per_minute_at(Instant::now()),
+        }
+    }
+
+    /// Modelled delay without jitter, in milliseconds
+    pub fn nominal_ms(&self, load: Load) -> f64 {
+        let config = &self.config;
+        let ms = config.base_ms as f64
+            + config.per_client_ms * load.clients as f64
+            + config.per_tightening_ms * load.tightenings_per_minute as f64;
+        ms.clamp(0.0, config.max_ms as f64)
+    }
+
+    /// Delay to wait before pushing under `load`, including jitter
+    pub fn delay<R: Rng>(&self, load: Load, rng: &mut R) -> Duration {
+        let nominal = self.nominal_ms(load);
+        let spread = (self.config.jitter_percent / 100.0).clamp(0.0, 1.0);
+        let factor = if spread > 0.0 {
+            rng.random_range(1.0 - spread..=1.0 + spread)
+        } else {
+            1.0
+        };
+        let ms = (nominal * factor).clamp(0.0, self.config.max_ms as f64);
+        Duration::from_micros((ms * 1000.0) as u64)
+    }
+}
+
+/// Track the tightening rate from the event channel
+pub fn spawn_load_monitor(ctx: &AppContext) -> Option<AbortHandle> {
+    if !ctx.broadcast_latency.is_enabled() {
+        return None;
+    }
+    let latency = std::sync::Arc::clone(&ctx.broadcast_latency);
+    let mut events = ctx.events.subscribe();
+
+    let task = ctx.resources.track("load_monitor");
+    let handle = tokio::spawn(async move {
+        let _task = task;
+        loop {
+            match events.recv().await {
+                Ok(SimulatorEvent::TighteningCompleted { .. }) => latency.record_tightening(),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+    Some(handle.abort_handle())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn model(jitter_percent: f64) -> BroadcastLatency {
+        BroadcastLatency::new(BroadcastLatencyConfig {
+            base_ms: 10,
+            per_client_ms: 2.0,
+            per_tightening_ms: 0.5,
+            jitter_percent,
+            max_ms: 100,
+        })
+    }
+
+    #[test]
+    fn test_delay_grows_with_load_and_is_capped() {
+        let latency = model(0.0);
+        let idle = Load {
+            clients: 0,
+            tightenings_per_minute: 0,
+        };
+        let busy = Load {
+            clients: 5,
+            tightenings_per_minute: 30,
+        };
+        assert_eq!(latency.nominal_ms(idle), 10.0);
+        assert_eq!(latency.nominal_ms(busy), 35.0);
+        assert_eq!(
+            latency.delay(busy, &mut StdRng::seed_from_u64(1)),
+            Duration::from_millis(35)
+        );
+        let overloaded = Load {
+            clients: 100,
+            tightenings_per_minute: 0,
+        };
+        assert_eq!(latency.nominal_ms(overloaded), 100.0);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_spread() {
+        let latency = model(20.0);
+        let load = Load {
+            clients: 5,
+            tightenings_per_minute: 30,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let ms = latency.delay(load, &mut rng).as_secs_f64() * 1000.0;
+            assert!((28.0..=42.0).contains(&ms), "{ms}");
+        }
+    }
+
+    #[test]
+    fn test_rate_counts_the_last_minute() {
+        let latency = model(0.0);
+        let start = Instant::now();
+        latency.record_tightening_at(start);
+        latency.record_tightening_at(start + Duration::from_secs(30));
+        assert_eq!(
+            latency.tightenings_per_minute_at(start + Duration::from_secs(45)),
+            2
+        );
+        assert_eq!(
+            latency.tightenings_per_minute_at(start + Duration::from_secs(75)),
+            1
+        );
+        assert!(latency.is_enabled());
+        assert!(!BroadcastLatency::new(BroadcastLatencyConfig::default()).is_enabled());
+    }
+}
@@ -0,0 +1,85 @@
+//! Copies of the raw frames of one connection
+//!
+//! A [`FrameTap`] is attached to the codec of an Open Protocol connection and
+//! publishes every frame it decodes or encodes, without the NUL terminator.
+//! Nothing is copied while no one listens. `GET /ws/protocol` streams the
+//! frames of a connection to browser-based protocol analyzers.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio_util::bytes::Bytes;
+
+/// Frames a listener may fall behind before it skips some
+pub const FRAME_TAP_CAPACITY: usize = 256;
+
+/// Which way a frame travelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameDirection {
+    /// Sent by the client
+    Inbound,
+    /// Sent by the simulator
+    Outbound,
+}
+
+/// One frame seen on the connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TappedFrame {
+    pub direction: FrameDirection,
+    pub bytes: Bytes,
+}
+
+/// Publisher of the frames of one connection
+#[derive(Debug, Clone)]
+pub struct FrameTap {
+    tx: broadcast::Sender<TappedFrame>,
+}
+
+impl Default for FrameTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameTap {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(FRAME_TAP_CAPACITY);
+        Self { tx }
+    }
+
+    /// Publish a frame if anyone listens
+    pub fn record(&self, direction: FrameDirection, frame: &[u8]) {
+        if self.tx.receiver_count() > 0 {
+            let _ = self.tx.send(TappedFrame {
+                direction,
+                bytes: Bytes::copy_from_slice(frame),
+            });
+        }
+    }
+
+    /// Receive the frames from now on; the stream ends when the connection closes
+    pub fn subscribe(&self) -> broadcast::Receiver<TappedFrame> {
+        self.tx.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_reach_listeners_only() {
+        let tap = FrameTap::new();
+        tap.record(FrameDirection::Inbound, b"00200001001         ");
+
+        let mut frames = tap.subscribe();
+        tap.record(FrameDirection::Outbound, b"00209999001         ");
+        let frame = frames.try_recv().unwrap();
+        assert_eq!(frame.direction, FrameDirection::Outbound);
+        assert_eq!(&frame.bytes[..], b"00209999001         ");
+        assert!(
+            frames.try_recv().is_err(),
+            "frames before subscribing are not kept"
+        );
+    }
+}
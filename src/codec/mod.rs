@@ -1 +1,2 @@
+pub mod frame_tap;
 pub mod null_delimited_codec;
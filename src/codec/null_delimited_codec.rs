@@ -1,7 +1,21 @@
+use super::frame_tap::{FrameDirection, FrameTap};
+use thiserror::Error;
 use tokio_util::bytes;
 use tokio_util::bytes::BufMut;
 
-pub struct NullDelimitedCodec;
+/// A peer sent more than the maximum frame length without a terminator
+///
+/// Carried inside the `std::io::Error` returned by the decoder.
+#[derive(Debug, Error)]
+#[error("frame exceeds {max_length} bytes without a terminator")]
+pub struct FrameTooLong {
+    pub max_length: usize,
+}
+
+pub struct NullDelimitedCodec {
+    max_length: Option<usize>,
+    tap: Option<FrameTap>,
+}
 
 impl Default for NullDelimitedCodec {
     fn default() -> Self {
@@ -11,7 +25,24 @@ impl Default for NullDelimitedCodec {
 
 impl NullDelimitedCodec {
     pub fn new() -> Self {
-        NullDelimitedCodec {}
+        NullDelimitedCodec {
+            max_length: None,
+            tap: None,
+        }
+    }
+
+    /// Codec refusing frames longer than `max_length` bytes
+    pub fn with_max_length(max_length: usize) -> Self {
+        NullDelimitedCodec {
+            max_length: Some(max_length),
+            tap: None,
+        }
+    }
+
+    /// Publish every decoded and encoded frame to `tap`
+    pub fn with_tap(mut self, tap: FrameTap) -> Self {
+        self.tap = Some(tap);
+        self
     }
 }
 
@@ -20,9 +51,21 @@ impl tokio_util::codec::Decoder for NullDelimitedCodec {
     type Error = std::io::Error;
 
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if let Some(pos) = src.iter().position(|b| *b == 0) {
+        let terminator = src.iter().position(|b| *b == 0);
+        let length = terminator.unwrap_or(src.len());
+        if let Some(max_length) = self.max_length.filter(|max| length > *max) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                FrameTooLong { max_length },
+            ));
+        }
+
+        if let Some(pos) = terminator {
             let mut line = src.split_to(pos + 1);
             line.truncate(pos); // Remove the null byte
+            if let Some(tap) = &self.tap {
+                tap.record(FrameDirection::Inbound, &line);
+            }
             Ok(Some(line))
         } else {
             Ok(None)
@@ -38,6 +81,9 @@ impl tokio_util::codec::Encoder<bytes::BytesMut> for NullDelimitedCodec {
         item: bytes::BytesMut,
         dst: &mut bytes::BytesMut,
     ) -> Result<(), Self::Error> {
+        if let Some(tap) = &self.tap {
+            tap.record(FrameDirection::Outbound, &item);
+        }
         dst.extend_from_slice(&item);
         dst.put_u8(0); // Append null byte
         Ok(())
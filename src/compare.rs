@@ -0,0 +1,348 @@
+//! Diff mode: compare simulator responses with a real controller capture
+//!
+//! `open-protocol-device-simulator compare capture.jsonl` reads a session
+//! recorded in gateway mode (`[proxy] record_path`), replays every request
+//! the client sent through the simulator's handler registry in order and
+//! compares each simulated response with the reply the controller gave.
+//! Pushed data MIDs (results, alarms, ...) in the capture are skipped: only
+//! direct replies are paired with requests, per client and in order.
+//!
+//! Responses are compared by header (MID, revision) and by numbered data
+//! field. Field boundaries are not known without the MID layout, so a field
+//! ends where both frames carry the next parameter number at the same
+//! offset; once the layouts drift apart the rest is compared as one field.
+
+use crate::handler::HandlerRegistry;
+use crate::handler::data::ErrorResponse;
+use crate::protocol::{Mid, parser, serializer};
+use crate::proxy::{Direction, Exchange, ExchangeOutcome};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+use thiserror::Error;
+
+/// Failure while reading a capture
+#[derive(Debug, Error)]
+pub enum CompareError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid capture line {line}: {source}")]
+    Json {
+        line: usize,
+        source: serde_json::Error,
+    },
+}
+
+/// A field that differs between the controller and the simulator
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldDiff {
+    /// `mid`, `revision`, a two-digit parameter number or `data`
+    pub field: String,
+    pub reference: String,
+    pub simulator: String,
+}
+
+/// One request whose simulated response differs from the controller's reply
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseDiff {
+    /// Sequence number of the request in the capture
+    pub seq: u64,
+    pub request_mid: u16,
+    pub fields: Vec<FieldDiff>,
+}
+
+/// Per-request-MID totals
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MidSummary {
+    pub replayed: usize,
+    pub matching: usize,
+    pub differing: usize,
+}
+
+/// Outcome of a comparison run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompareReport {
+    /// Requests replayed against the simulator and paired with a reply
+    pub replayed: usize,
+    /// Requests the controller never answered in the capture
+    pub unanswered: usize,
+    pub per_mid: BTreeMap<u16, MidSummary>,
+    pub diffs: Vec<ResponseDiff>,
+}
+
+impl CompareReport {
+    pub fn is_faithful(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+impl fmt::Display for CompareReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Replayed {} requests ({} unanswered in the capture)",
+            self.replayed, self.unanswered
+        )?;
+        for (mid, summary) in &self.per_mid {
+            writeln!(
+                f,
+                "  MID {:04} ({}): {} replayed, {} matching, {} differing",
+                mid,
+                Mid(*mid).name(),
+                summary.replayed,
+                summary.matching,
+                summary.differing
+            )?;
+        }
+        for diff in &self.diffs {
+            writeln!(f, "Request #{} (MID {:04}):", diff.seq, diff.request_mid)?;
+            for field in &diff.fields {
+                writeln!(
+                    f,
+                    "  {:<8} controller {:?} simulator {:?}",
+                    field.field, field.reference, field.simulator
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a gateway-mode recording (one JSON exchange per line)
+pub fn load_capture(path: &Path) -> Result<Vec<Exchange>, CompareError> {
+    let text = std::fs::read_to_string(path)?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|source| CompareError::Json {
+                line: i + 1,
+                source,
+            })
+        })
+        .collect()
+}
+
+/// Replay the client side of `capture` through `registry` and diff the replies
+///
+/// `ignore` lists fields left out of the comparison as (response MID, field)
+/// pairs, e.g. `(61, "19")` for the MID 0061 timestamp.
+pub fn compare(
+    capture: &[Exchange],
+    registry: &HandlerRegistry,
+    ignore: &[(u16, String)],
+) -> CompareReport {
+    let mut report = CompareReport::default();
+    let mut pending: HashMap<SocketAddr, VecDeque<(u64, u16, Vec<u8>)>> = HashMap::new();
+
+    let mut exchanges: Vec<&Exchange> = capture.iter().collect();
+    exchanges.sort_by_key(|e| e.seq);
+
+    for exchange in exchanges {
+        let frame = exchange.frame.as_bytes();
+        match exchange.direction {
+            Direction::ClientToController => {
+                // Only frames the controller actually received were answered
+                if exchange.outcome != ExchangeOutcome::Forwarded {
+                    continue;
+                }
+                let Ok(message) = parser::parse_message(frame) else {
+                    continue;
+                };
+                let response = match registry.handle_message(&message) {
+                    Ok(response) => response,
+                    Err(_) => ErrorResponse::generic(message.mid).into_response(message.revision),
+                };
+                pending.entry(exchange.client).or_default().push_back((
+                    exchange.seq,
+                    message.mid,
+                    serializer::serialize_response(&response),
+                ));
+            }
+            Direction::ControllerToClient => {
+                let Ok(reply) = parser::parse_message(frame) else {
+                    continue;
+                };
+                if is_pushed(reply.mid) {
+                    continue;
+                }
+                let Some((seq, request_mid, simulated)) = pending
+                    .get_mut(&exchange.client)
+                    .and_then(VecDeque::pop_front)
+                else {
+                    continue;
+                };
+
+                let fields: Vec<FieldDiff> = diff_frames(frame, &simulated)
+                    .into_iter()
+                    .filter(|d| !ignore.iter().any(|(m, f)| *m == reply.mid && *f == d.field))
+                    .collect();
+                report.replayed += 1;
+                let summary = report.per_mid.entry(request_mid).or_default();
+                summary.replayed += 1;
+                if fields.is_empty() {
+                    summary.matching += 1;
+                } else {
+                    summary.differing += 1;
+                    report.diffs.push(ResponseDiff {
+                        seq,
+                        request_mid,
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+
+    report.unanswered = pending.values().map(VecDeque::len).sum();
+    report
+}
+
+/// Data MIDs the controller pushes on its own (they carry an acknowledge MID)
+fn is_pushed(mid: u16) -> bool {
+    Mid(mid).info().is_some_and(|info| info.ack.is_some())
+}
+
+/// Field-level differences between two frames
+pub fn diff_frames(reference: &[u8], simulated: &[u8]) -> Vec<FieldDiff> {
+    let header = |frame: &[u8], range: std::ops::Range<usize>| {
+        frame
+            .get(range)
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+            .unwrap_or_default()
+    };
+    let mut diffs = Vec::new();
+    for (field, range) in [("mid", 4..8), ("revision", 8..11)] {
+        let (a, b) = (header(reference, range.clone()), header(simulated, range));
+        if a != b {
+            diffs.push(FieldDiff {
+                field: field.to_string(),
+                reference: a,
+                simulator: b,
+            });
+        }
+    }
+
+    let data =
+        |frame: &[u8]| String::from_utf8_lossy(frame.get(20..).unwrap_or_default()).into_owned();
+    let (a, b) = (data(reference), data(simulated));
+    for (field, a, b) in data_fields(&a, &b) {
+        if a != b {
+            diffs.push(FieldDiff {
+                field,
+                reference: a.to_string(),
+                simulator: b.to_string(),
+            });
+        }
+    }
+    diffs
+}
+
+/// Split two data sections into numbered fields walked in lockstep
+fn data_fields<'a>(a: &'a str, b: &'a str) -> Vec<(String, &'a str, &'a str)> {
+    let numbered = |s: &str| s.starts_with("01") && s.is_ascii();
+    if !(numbered(a) && numbered(b)) {
+        return vec![("data".to_string(), a, b)];
+    }
+
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut id = 1;
+    loop {
+        let next = format!("{:02}", id + 1);
+        let end = (start + 3..a.len().min(b.len()).saturating_sub(1))
+            .find(|&i| a[i..i + 2] == next && b[i..i + 2] == next);
+        match end {
+            Some(end) => {
+                fields.push((format!("{:02}", id), &a[start + 2..end], &b[start + 2..end]));
+                start = end;
+                id += 1;
+            }
+            None => {
+                fields.push((format!("{:02}", id), &a[start + 2..], &b[start + 2..]));
+                return fields;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler;
+    use crate::observable_state::ObservableState;
+    use crate::state::DeviceState;
+    use crate::{job, pset};
+
+    fn exchange(seq: u64, direction: Direction, frame: &str) -> Exchange {
+        Exchange {
+            seq,
+            timestamp: String::new(),
+            client: "127.0.0.1:5000".parse().unwrap(),
+            direction,
+            mid: None,
+            outcome: ExchangeOutcome::Forwarded,
+            delay_ms: 0,
+            frame: frame.to_string(),
+            forwarded_frame: None,
+        }
+    }
+
+    #[test]
+    fn test_data_fields_follow_parameter_numbers() {
+        let fields = data_fields("0100010203030405", "0100020203030406");
+        assert_eq!(
+            fields,
+            vec![
+                ("01".to_string(), "0001", "0002"),
+                ("02".to_string(), "03", "03"),
+                ("03".to_string(), "0405", "0406"),
+            ]
+        );
+        assert_eq!(data_fields("ABC", "ABD")[0].0, "data");
+    }
+
+    #[test]
+    fn test_compare_reports_field_differences_and_skips_pushes() {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let registry = handler::create_default_registry(
+            ObservableState::new(DeviceState::new_shared(), tx),
+            pset::create_default_repository(),
+            job::create_default_job_repository(),
+        );
+
+        let start = String::from_utf8(serializer::serialize_frame(1, 1, b"")).unwrap();
+        let reply = String::from_utf8(serializer::serialize_frame(
+            2,
+            1,
+            b"010001020103Real controller          04ACM",
+        ))
+        .unwrap();
+        let push = String::from_utf8(serializer::serialize_frame(52, 1, b"VIN")).unwrap();
+        let keep_alive = String::from_utf8(serializer::serialize_frame(9999, 1, b"")).unwrap();
+        let capture = vec![
+            exchange(1, Direction::ClientToController, &start),
+            exchange(2, Direction::ControllerToClient, &push),
+            exchange(3, Direction::ControllerToClient, &reply),
+            exchange(4, Direction::ClientToController, &keep_alive),
+            exchange(5, Direction::ControllerToClient, &keep_alive),
+            exchange(6, Direction::ClientToController, &keep_alive),
+        ];
+
+        let report = compare(&capture, &registry, &[(2, "04".to_string())]);
+        assert_eq!(report.replayed, 2);
+        assert_eq!(report.unanswered, 1);
+        assert_eq!(report.per_mid[&1].differing, 1);
+        assert_eq!(report.per_mid[&9999].matching, 1);
+        assert_eq!(report.diffs.len(), 1);
+        let fields: Vec<&str> = report.diffs[0]
+            .fields
+            .iter()
+            .map(|d| d.field.as_str())
+            .collect();
+        assert_eq!(fields, vec!["03"]);
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! This module defines CLI arguments using clap with environment variable support.
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 /// Open Protocol Device Simulator
@@ -48,9 +48,104 @@ pub struct CliArgs {
     #[arg(long, env = "SIMULATOR_SUPPLIER_CODE")]
     pub supplier_code: Option<String>,
 
+    /// Forward connections to this controller ("host:port") instead of simulating one
+    #[arg(long, env = "SIMULATOR_PROXY_UPSTREAM")]
+    pub proxy_upstream: Option<String>,
+
     /// Print the loaded configuration and exit
     #[arg(long)]
     pub print_config: bool,
+
+    /// Detach from the terminal and run in the background (Unix)
+    #[arg(long, env = "SIMULATOR_DAEMON")]
+    pub daemon: bool,
+
+    /// Write the process ID to this file while running
+    #[arg(long, env = "SIMULATOR_PID_FILE")]
+    pub pid_file: Option<PathBuf>,
+
+    /// Append all output to this file instead of the terminal
+    #[arg(long, env = "SIMULATOR_LOG_FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Run under the Windows service control manager
+    #[arg(long)]
+    pub service: bool,
+
+    /// Run a tool instead of the simulator
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Tools run instead of the simulator
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Replay a gateway-mode capture against the simulator and report
+    /// field-level differences in the responses per MID
+    Compare {
+        /// Capture recorded with `[proxy] record_path` (JSON lines)
+        capture: PathBuf,
+
+        /// Leave a response field out, as MID:FIELD (e.g. 61:19); repeatable
+        #[arg(long, value_parser = parse_field_ref)]
+        ignore: Vec<(u16, String)>,
+
+        /// Also write the report as JSON to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+
+    /// Wait for a client on the TCP port and check its protocol behavior
+    /// (acks, keep-alives, reconnect and resubscription)
+    Conformance {
+        /// Also write the report as JSON to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// Also write the report as JUnit XML to this file
+        #[arg(long)]
+        junit: Option<PathBuf>,
+
+        /// Seconds allowed for connecting, starting communication and subscribing
+        #[arg(long, default_value_t = 30)]
+        step_timeout_secs: u64,
+
+        /// Seconds allowed for acknowledging a pushed result
+        #[arg(long, default_value_t = 5)]
+        ack_timeout_secs: u64,
+
+        /// Longest silence (seconds) allowed from an idle client
+        #[arg(long, default_value_t = 15)]
+        keep_alive_secs: u64,
+    },
+
+    /// Start the simulator on ephemeral ports, run a client smoke sequence
+    /// against it and exit non-zero on failure
+    SelfTest {
+        /// Also write the report as JSON to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// Seconds allowed for each step
+        #[arg(long, default_value_t = 5)]
+        step_timeout_secs: u64,
+    },
+}
+
+/// Parse `MID:FIELD` into the MID and a two-digit field number (or `mid`,
+/// `revision`, `data`)
+fn parse_field_ref(value: &str) -> Result<(u16, String), String> {
+    let (mid, field) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected MID:FIELD, got '{}'", value))?;
+    let mid = mid
+        .parse::<u16>()
+        .map_err(|_| format!("invalid MID '{}'", mid))?;
+    let field = match field.parse::<u8>() {
+        Ok(n) => format!("{:02}", n),
+        Err(_) => field.to_string(),
+    };
+    Ok((mid, field))
 }
 
 impl CliArgs {
@@ -71,6 +166,26 @@ mod tests {
         assert!(args.config.is_none());
         assert!(args.tcp_port.is_none());
         assert!(!args.print_config);
+        assert!(!args.daemon);
+        assert!(args.pid_file.is_none());
+    }
+
+    #[test]
+    fn test_cli_daemon_flags() {
+        let args = CliArgs::try_parse_from([
+            "test",
+            "--daemon",
+            "--pid-file",
+            "/run/simulator.pid",
+            "--log-file",
+            "simulator.log",
+        ])
+        .unwrap();
+
+        assert!(args.daemon);
+        assert_eq!(args.pid_file, Some(PathBuf::from("/run/simulator.pid")));
+        assert_eq!(args.log_file, Some(PathBuf::from("simulator.log")));
+        assert!(!args.service);
     }
 
     #[test]
@@ -91,6 +206,38 @@ mod tests {
         assert_eq!(args.cell_id, Some(5));
     }
 
+    #[test]
+    fn test_cli_compare_command() {
+        let args =
+            CliArgs::try_parse_from(["test", "compare", "capture.jsonl", "--ignore", "61:9"])
+                .unwrap();
+        match args.command {
+            Some(Command::Compare {
+                capture, ignore, ..
+            }) => {
+                assert_eq!(capture, PathBuf::from("capture.jsonl"));
+                assert_eq!(ignore, vec![(61, "09".to_string())]);
+            }
+            other => panic!("expected the compare command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cli_self_test_command() {
+        let args =
+            CliArgs::try_parse_from(["test", "self-test", "--step-timeout-secs", "10"]).unwrap();
+        match args.command {
+            Some(Command::SelfTest {
+                step_timeout_secs,
+                report,
+            }) => {
+                assert_eq!(step_timeout_secs, 10);
+                assert!(report.is_none());
+            }
+            other => panic!("expected the self-test command, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_cli_print_config() {
         let args = CliArgs::try_parse_from(["test", "--print-config"]).unwrap();
@@ -9,8 +9,13 @@
 mod cli;
 mod settings;
 
-pub use cli::CliArgs;
-pub use settings::{DatabaseConfig, DefaultsConfig, DeviceConfig, ServerConfig, Settings};
+pub use cli::{CliArgs, Command};
+pub use settings::{
+    AlarmsConfig, BroadcastLatencyConfig, DatabaseConfig, DefaultsConfig, DeviceConfig,
+    DisconnectsConfig, ExportConfig, HandlersConfig, IntegrationsConfig, JournalConfig,
+    KeepAliveConfig, LoggingConfig, MaintenanceConfig, ProxyConfig, ResultsConfig, ServerConfig,
+    Settings, StatsConfig, TelemetryConfig,
+};
 
 use config::{Config, File, FileFormat};
 use std::path::Path;
@@ -43,8 +48,15 @@ pub enum ConfigError {
 ///
 /// Returns `ConfigError` if a specified configuration file cannot be read or parsed.
 pub fn load_config() -> Result<Settings, ConfigError> {
-    let cli = CliArgs::parse_args();
+    load_config_from(&CliArgs::parse_args())
+}
 
+/// Load configuration for already parsed command-line arguments.
+///
+/// # Errors
+///
+/// Returns `ConfigError` if a specified configuration file cannot be read or parsed.
+pub fn load_config_from(cli: &CliArgs) -> Result<Settings, ConfigError> {
     // Start with defaults
     let mut settings = Settings::default();
 
@@ -70,7 +82,7 @@ pub fn load_config() -> Result<Settings, ConfigError> {
     }
 
     // Apply CLI overrides (highest priority)
-    apply_cli_overrides(&mut settings, &cli);
+    apply_cli_overrides(&mut settings, cli);
 
     // Handle --print-config
     if cli.print_config {
@@ -127,6 +139,11 @@ fn apply_cli_overrides(settings: &mut Settings, cli: &CliArgs) {
     if let Some(ref code) = cli.supplier_code {
         settings.device.supplier_code = code.clone();
     }
+
+    // Gateway mode
+    if let Some(ref upstream) = cli.proxy_upstream {
+        settings.proxy.upstream = Some(upstream.clone());
+    }
 }
 
 /// Print configuration in a readable format.
@@ -142,6 +159,30 @@ fn print_config(settings: &Settings) {
         "  event_channel_capacity = {}",
         settings.server.event_channel_capacity
     );
+    println!(
+        "  cors_allowed_origins = {:?}",
+        settings.server.cors_allowed_origins
+    );
+    println!("  http_base_path = \"{}\"", settings.server.http_base_path);
+    println!("  max_connections = {}", settings.server.max_connections);
+    println!("  max_frame_bytes = {}", settings.server.max_frame_bytes);
+    println!(
+        "  observer_tcp_port = {}",
+        settings.server.observer_tcp_port
+    );
+    println!(
+        "  flush_interval_ms = {}",
+        settings.server.flush_interval_ms
+    );
+    println!("  max_batch_frames = {}", settings.server.max_batch_frames);
+    println!(
+        "  handshake_timeout_ms = {}",
+        settings.server.handshake_timeout_ms
+    );
+    println!(
+        "  handshake_timeout_error = {}",
+        settings.server.handshake_timeout_error
+    );
     println!();
     println!("[device]");
     println!("  cell_id = {}", settings.device.cell_id);
@@ -151,6 +192,50 @@ fn print_config(settings: &Settings) {
         settings.device.controller_name
     );
     println!("  supplier_code = \"{}\"", settings.device.supplier_code);
+    println!(
+        "  software_version = \"{}\"",
+        settings.device.software_version
+    );
+    println!(
+        "  tool_software_version = \"{}\"",
+        settings.device.tool_software_version
+    );
+    println!(
+        "  text_encoding = \"{}\"",
+        settings.device.text_encoding.as_str()
+    );
+    println!(
+        "  numeric_padding = \"{}\"",
+        settings.device.numeric_padding.as_str()
+    );
+    println!(
+        "  push_on_connect = {}",
+        serde_json::to_string(&settings.device.push_on_connect).unwrap_or_default()
+    );
+    println!("  push_revision = {}", settings.device.push_revision);
+    println!("  stamp_cycle_id = {}", settings.device.stamp_cycle_id);
+    println!("  greeting = {:?}", settings.device.greeting);
+    println!(
+        "  max_size_payloads = {}",
+        settings.device.max_size_payloads
+    );
+    println!(
+        "  identification_scope = {:?}",
+        settings.device.identification_scope
+    );
+    let torque_format = &settings.device.torque_format;
+    println!(
+        "  torque_format = {{ resolution = \"{}\", decimal_separator = \"{}\", mids = {:?} }}",
+        torque_format.resolution.as_str(),
+        torque_format.decimal_separator.as_str(),
+        Vec::<u16>::from(torque_format.mids)
+    );
+    let timestamp_format = &settings.device.timestamp_format;
+    println!(
+        "  timestamp_format = {{ separator = \"{}\", zero_padded = {} }}",
+        timestamp_format.separator.as_str(),
+        timestamp_format.zero_padded
+    );
     println!();
     println!("[database]");
     println!("  path = \"{}\"", settings.database.path.display());
@@ -165,6 +250,188 @@ fn print_config(settings: &Settings) {
         settings.defaults.auto_tightening_duration_ms
     );
     println!("  failure_rate = {}", settings.defaults.failure_rate);
+    println!(
+        "  auto_reset_batch = {}",
+        settings.defaults.auto_reset_batch
+    );
+    println!(
+        "  lock_at_batch_done = {}",
+        settings.defaults.lock_at_batch_done
+    );
+    println!(
+        "  require_operator_login = {}",
+        settings.defaults.require_operator_login
+    );
+    println!();
+    println!("[handlers]");
+    println!("  log_messages = {}", settings.handlers.log_messages);
+    println!("  strict = {}", settings.handlers.strict);
+    println!(
+        "  response_delay_ms = {}",
+        settings.handlers.response_delay_ms
+    );
+    println!("  locked_down = {}", settings.handlers.locked_down);
+    println!("  allowed_mids = {:?}", settings.handlers.allowed_mids);
+    println!(
+        "  unknown_mid = \"{}\"",
+        settings.handlers.unknown_mid.as_str()
+    );
+    println!();
+    println!("[results]");
+    println!("  history_capacity = {}", settings.results.history_capacity);
+    println!("  curve_points = {}", settings.results.curve_points);
+    println!(
+        "  signing_key = {}",
+        if settings.results.signing_key.is_some() {
+            "(set)"
+        } else {
+            "(none)"
+        }
+    );
+    println!();
+    println!("[export]");
+    println!("  parquet_dir = {:?}", settings.export.parquet_dir);
+    println!(
+        "  parquet_interval_secs = {}",
+        settings.export.parquet_interval_secs
+    );
+    println!(
+        "  parquet_include_curves = {}",
+        settings.export.parquet_include_curves
+    );
+    println!();
+    println!("[alarms]");
+    println!(
+        "  random_per_minute = {}",
+        settings.alarms.random_per_minute
+    );
+    println!("  auto_clear_secs = {}", settings.alarms.auto_clear_secs);
+    println!("  catalogue = {} alarms", settings.alarms.catalogue.len());
+    println!("  language = {:?}", settings.alarms.language);
+    println!("  texts = {} error codes", settings.alarms.texts.len());
+    println!();
+    println!("[telemetry]");
+    println!("  interval_ms = {}", settings.telemetry.interval_ms);
+    println!();
+    println!("[stats]");
+    println!("  lifetime_path = {:?}", settings.stats.lifetime_path);
+    println!(
+        "  save_interval_secs = {}",
+        settings.stats.save_interval_secs
+    );
+    println!(
+        "  cycle_time_summary_secs = {}",
+        settings.stats.cycle_time_summary_secs
+    );
+    println!();
+    println!("[proxy]");
+    println!("  upstream = {:?}", settings.proxy.upstream);
+    println!(
+        "  connect_timeout_ms = {}",
+        settings.proxy.connect_timeout_ms
+    );
+    println!("  record_capacity = {}", settings.proxy.record_capacity);
+    println!("  record_path = {:?}", settings.proxy.record_path);
+    println!();
+    println!("[broadcast_latency]");
+    println!("  base_ms = {}", settings.broadcast_latency.base_ms);
+    println!(
+        "  per_client_ms = {}",
+        settings.broadcast_latency.per_client_ms
+    );
+    println!(
+        "  per_tightening_ms = {}",
+        settings.broadcast_latency.per_tightening_ms
+    );
+    println!(
+        "  jitter_percent = {}",
+        settings.broadcast_latency.jitter_percent
+    );
+    println!("  max_ms = {}", settings.broadcast_latency.max_ms);
+    println!();
+    println!("[integrations]");
+    println!("  webhook_url = {:?}", settings.integrations.webhook_url);
+    println!("  batch_size = {}", settings.integrations.batch_size);
+    println!(
+        "  poll_interval_ms = {}",
+        settings.integrations.poll_interval_ms
+    );
+    println!("  retry_base_ms = {}", settings.integrations.retry_base_ms);
+    println!("  retry_max_ms = {}", settings.integrations.retry_max_ms);
+    println!("  max_attempts = {}", settings.integrations.max_attempts);
+    println!("  timeout_ms = {}", settings.integrations.timeout_ms);
+    println!();
+    println!("[keep_alive]");
+    println!("  reply = {}", settings.keep_alive.reply);
+    println!("  reply_delay_ms = {}", settings.keep_alive.reply_delay_ms);
+    println!(
+        "  probe_interval_ms = {}",
+        settings.keep_alive.probe_interval_ms
+    );
+    println!();
+    println!("[disconnects]");
+    println!(
+        "  mean_interval_secs = {}",
+        settings.disconnects.mean_interval_secs
+    );
+    println!(
+        "  modes = {:?}",
+        settings
+            .disconnects
+            .modes
+            .iter()
+            .map(|mode| mode.as_str())
+            .collect::<Vec<_>>()
+    );
+    println!();
+    println!("[maintenance]");
+    println!("  notice_ms = {}", settings.maintenance.notice_ms);
+    println!(
+        "  refuse_new_sessions = {}",
+        settings.maintenance.refuse_new_sessions
+    );
+    println!(
+        "  service_interval = {}",
+        settings.maintenance.service_interval
+    );
+    println!(
+        "  service_lockout = {}",
+        settings.maintenance.service_lockout
+    );
+    println!();
+    println!("[interlock]");
+    println!("  jobs = {:?}", settings.interlock.jobs);
+    println!();
+    println!("[logging.journal]");
+    println!("  path = {:?}", settings.logging.journal.path);
+    println!("  frames = {}", settings.logging.journal.frames);
+    println!("  max_bytes = {}", settings.logging.journal.max_bytes);
+    println!("  max_age_secs = {}", settings.logging.journal.max_age_secs);
+    println!("  max_files = {}", settings.logging.journal.max_files);
+    for (kind, policy) in &settings.acks {
+        println!();
+        println!(
+            "[acks.{}]",
+            serde_json::to_value(kind)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default()
+        );
+        println!("  timeout_ms = {}", policy.timeout_ms);
+        println!("  max_retries = {}", policy.max_retries);
+        println!("  on_exhaustion = {:?}", policy.on_exhaustion);
+        println!("  max_queued = {}", policy.max_queued);
+    }
+    for rule in &settings.schedules {
+        println!();
+        println!("[[schedules]]");
+        println!("  name = {:?}", rule.name);
+        println!("  cron = {:?}", rule.cron);
+        println!(
+            "  action = {}",
+            serde_json::to_string(&rule.action).unwrap_or_default()
+        );
+    }
 }
 
 #[cfg(test)]
@@ -219,7 +486,13 @@ mod tests {
             channel_id: None,
             controller_name: Some("TestController".to_string()),
             supplier_code: None,
+            proxy_upstream: None,
             print_config: false,
+            daemon: false,
+            pid_file: None,
+            log_file: None,
+            service: false,
+            command: None,
         };
 
         apply_cli_overrides(&mut settings, &cli);
@@ -244,20 +517,111 @@ tcp_port = 9000
 http_port = 9001
 bind_address = "192.168.1.1"
 event_channel_capacity = 200
+max_connections = 8
+max_frame_bytes = 4096
+observer_tcp_port = 9002
+flush_interval_ms = 2
+max_batch_frames = 16
+handshake_timeout_ms = 3000
+handshake_timeout_error = true
 
 [device]
 cell_id = 42
 channel_id = 7
 controller_name = "TestSimulator"
 supplier_code = "TST"
+text_encoding = "iso-8859-1"
+numeric_padding = "space"
+push_on_connect = ["tightening_result", "vehicle_id"]
+stamp_cycle_id = true
+max_size_payloads = true
+identification_scope = "per_connection"
+
+[device.greeting]
+mid = 9998
+data = "Welcome to {controller_name}"
+
+[device.torque_format]
+resolution = "milli"
+decimal_separator = "comma"
+mids = [61]
+
+[device.timestamp_format]
+separator = "space"
+zero_padded = false
 
 [database]
 path = "/tmp/test.db"
 
+[integrations]
+webhook_url = "http://localhost:9000/results"
+max_attempts = 5
+
+[broadcast_latency]
+base_ms = 5
+per_client_ms = 1.5
+jitter_percent = 20.0
+
+[keep_alive]
+reply_delay_ms = 250
+probe_interval_ms = 10000
+
+[disconnects]
+mean_interval_secs = 120.0
+modes = ["abort", "half_close"]
+
+[maintenance]
+notice_ms = 30000
+refuse_new_sessions = true
+service_interval = 50000
+service_lockout = true
+
+[interlock]
+jobs = [3, 7]
+
+[logging.journal]
+path = "/tmp/journal/events.ndjson"
+frames = true
+max_files = 3
+
+[acks.tightening_result]
+timeout_ms = 500
+max_retries = 2
+on_exhaustion = "disconnect"
+
+[stats]
+cycle_time_summary_secs = 300
+
+[alarms]
+language = "de"
+
+[alarms.texts.E900]
+en = "Safety door open"
+de = "Schutztür offen"
+
+[handlers]
+unknown_mid = "disconnect"
+
+[results]
+signing_key = "line-7-secret"
+
 [defaults]
 auto_tightening_interval_ms = 5000
 auto_tightening_duration_ms = 2000
 failure_rate = 0.25
+lock_at_batch_done = true
+
+[[schedules]]
+name = "shift change outage"
+cron = "0 6,14,22 * * 1-5"
+action = "outage"
+duration_secs = 120
+
+[[schedules]]
+name = "hourly alarm"
+cron = "0 * * * *"
+action = "raise_alarm"
+error_code = "E101"
 "#,
         );
 
@@ -267,7 +631,59 @@ failure_rate = 0.25
         assert_eq!(settings.server.http_port, 9001);
         assert_eq!(settings.server.bind_address, "192.168.1.1");
         assert_eq!(settings.server.event_channel_capacity, 200);
+        assert_eq!(settings.server.max_connections, 8);
+        assert_eq!(settings.server.max_frame_bytes, 4096);
+        assert_eq!(settings.server.observer_tcp_port, 9002);
+        assert_eq!(settings.server.flush_interval_ms, 2);
+        assert_eq!(settings.server.max_batch_frames, 16);
+        assert_eq!(settings.server.handshake_timeout_ms, 3000);
+        assert!(settings.server.handshake_timeout_error);
         assert_eq!(settings.device.cell_id, 42);
+        assert_eq!(
+            settings.device.text_encoding,
+            crate::protocol::TextEncoding::Latin1
+        );
+        assert_eq!(
+            settings.device.numeric_padding,
+            crate::protocol::NumericPadding::Space
+        );
+        assert_eq!(
+            settings.device.push_on_connect,
+            vec![
+                crate::protocol::mid::SubscriptionKind::TighteningResult,
+                crate::protocol::mid::SubscriptionKind::VehicleId
+            ]
+        );
+        assert_eq!(settings.device.push_revision, 1);
+        assert!(settings.device.stamp_cycle_id);
+        assert!(settings.device.max_size_payloads);
+        assert_eq!(
+            settings.device.identification_scope,
+            crate::identification::IdentificationScope::PerConnection
+        );
+        assert!(settings.device.wire_format().max_size_text);
+        let torque = settings.device.wire_format().torque;
+        assert_eq!(torque.resolution, crate::protocol::TorqueResolution::Milli);
+        assert_eq!(
+            torque.decimal_separator,
+            crate::protocol::DecimalSeparator::Comma
+        );
+        assert_eq!(Vec::<u16>::from(torque.mids), vec![61]);
+        assert_eq!(
+            settings.device.wire_format().timestamp,
+            crate::protocol::TimestampFormat {
+                separator: crate::protocol::DateTimeSeparator::Space,
+                zero_padded: false,
+            }
+        );
+        assert_eq!(
+            settings.device.greeting,
+            Some(crate::greeting::GreetingFrame::Mid {
+                mid: 9998,
+                revision: 1,
+                data: "Welcome to {controller_name}".to_string(),
+            })
+        );
         assert_eq!(settings.device.channel_id, 7);
         assert_eq!(settings.device.controller_name, "TestSimulator");
         assert_eq!(settings.device.supplier_code, "TST");
@@ -275,6 +691,76 @@ failure_rate = 0.25
         assert_eq!(settings.defaults.auto_tightening_interval_ms, 5000);
         assert_eq!(settings.defaults.auto_tightening_duration_ms, 2000);
         assert!((settings.defaults.failure_rate - 0.25).abs() < f64::EPSILON);
+        assert!(settings.defaults.lock_at_batch_done);
+        assert_eq!(settings.broadcast_latency.base_ms, 5);
+        assert!((settings.broadcast_latency.per_client_ms - 1.5).abs() < f64::EPSILON);
+        assert_eq!(settings.broadcast_latency.per_tightening_ms, 0.0);
+        assert_eq!(settings.broadcast_latency.max_ms, 2000);
+        assert_eq!(
+            settings.integrations.webhook_url.as_deref(),
+            Some("http://localhost:9000/results")
+        );
+        assert_eq!(settings.integrations.max_attempts, 5);
+        assert_eq!(settings.integrations.batch_size, 10);
+        assert_eq!(settings.alarms.language, "de");
+        assert_eq!(settings.alarms.texts["E900"]["de"], "Schutztür offen");
+        assert_eq!(settings.alarms.auto_clear_secs, 30);
+        assert!(settings.keep_alive.reply);
+        assert_eq!(settings.keep_alive.reply_delay_ms, 250);
+        assert_eq!(settings.keep_alive.probe_interval_ms, 10000);
+        assert_eq!(settings.disconnects.mean_interval_secs, 120.0);
+        assert_eq!(
+            settings.disconnects.modes,
+            vec![
+                crate::session_registry::CloseMode::Abort,
+                crate::session_registry::CloseMode::HalfClose
+            ]
+        );
+        assert_eq!(settings.maintenance.notice_ms, 30000);
+        assert!(settings.maintenance.refuse_new_sessions);
+        assert_eq!(settings.maintenance.service_interval, 50000);
+        assert!(settings.maintenance.service_lockout);
+        assert_eq!(settings.interlock.jobs, vec![3, 7]);
+        let journal = &settings.logging.journal;
+        assert_eq!(
+            journal.path,
+            Some(PathBuf::from("/tmp/journal/events.ndjson"))
+        );
+        assert!(journal.frames);
+        assert_eq!(journal.max_bytes, 10 * 1024 * 1024);
+        assert_eq!(journal.max_files, 3);
+        let policy = &settings.acks[&crate::protocol::mid::SubscriptionKind::TighteningResult];
+        assert_eq!(policy.timeout_ms, 500);
+        assert_eq!(policy.max_retries, 2);
+        assert_eq!(
+            policy.on_exhaustion,
+            crate::ack_policy::AckExhaustion::Disconnect
+        );
+        assert_eq!(policy.max_queued, 1000);
+        assert_eq!(settings.stats.cycle_time_summary_secs, 300);
+        assert_eq!(
+            settings.results.signing_key.as_deref(),
+            Some("line-7-secret")
+        );
+        assert_eq!(
+            settings.handlers.unknown_mid,
+            crate::handler::UnknownMidPolicy::Disconnect
+        );
+        assert!(settings.handlers.log_messages);
+        assert_eq!(settings.schedules.len(), 2);
+        assert_eq!(settings.schedules[0].cron, "0 6,14,22 * * 1-5");
+        assert_eq!(
+            settings.schedules[0].action,
+            crate::schedules::ScheduleAction::Outage { duration_secs: 120 }
+        );
+        assert_eq!(
+            settings.schedules[1].action,
+            crate::schedules::ScheduleAction::RaiseAlarm {
+                error_code: "E101".to_string(),
+                description: "Scheduled alarm".to_string(),
+                severity: crate::alarms::AlarmSeverity::Error,
+            }
+        );
     }
 
     #[test]
@@ -2,7 +2,18 @@
 //!
 //! This module defines the settings hierarchy used throughout the application.
 
+use crate::ack_policy::AckPolicy;
+use crate::alarm_texts::{DEFAULT_LANGUAGE, Translations};
+use crate::alarms::{AlarmTemplate, default_catalogue};
+use crate::greeting::GreetingFrame;
+use crate::handler::UnknownMidPolicy;
+use crate::identification::IdentificationScope;
+use crate::protocol::mid::SubscriptionKind;
+use crate::protocol::{NumericPadding, TextEncoding, TimestampFormat, TorqueFormat, WireFormat};
+use crate::schedules::ScheduleRule;
+use crate::session_registry::CloseMode;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Root configuration structure containing all settings.
@@ -23,6 +34,70 @@ pub struct Settings {
     /// Default values for various operations
     #[serde(default)]
     pub defaults: DefaultsConfig,
+
+    /// Handler middleware configuration
+    #[serde(default)]
+    pub handlers: HandlersConfig,
+
+    /// Tightening results history configuration
+    #[serde(default)]
+    pub results: ResultsConfig,
+
+    /// Scheduled export configuration
+    #[serde(default)]
+    pub export: ExportConfig,
+
+    /// Random alarm generator configuration
+    #[serde(default)]
+    pub alarms: AlarmsConfig,
+
+    /// Simulated tool telemetry configuration
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+
+    /// Lifetime statistics configuration
+    #[serde(default)]
+    pub stats: StatsConfig,
+
+    /// Gateway (proxy) mode configuration
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+
+    /// Load-dependent broadcast latency model
+    #[serde(default)]
+    pub broadcast_latency: BroadcastLatencyConfig,
+
+    /// Result delivery to external integrations
+    #[serde(default)]
+    pub integrations: IntegrationsConfig,
+
+    /// Keep-alive (MID 9999) echo and probing behavior
+    #[serde(default)]
+    pub keep_alive: KeepAliveConfig,
+
+    /// Scheduled random disconnects of clients
+    #[serde(default)]
+    pub disconnects: DisconnectsConfig,
+
+    /// Advance notice of planned restarts and outages
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+
+    /// Jobs whose positions wait for an external release
+    #[serde(default)]
+    pub interlock: InterlockConfig,
+
+    /// Journal files of events and frames
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
+    /// Retransmission of unacknowledged pushes per subscription family (`[acks.<kind>]`)
+    #[serde(default)]
+    pub acks: BTreeMap<SubscriptionKind, AckPolicy>,
+
+    /// Recurring chaos actions (`[[schedules]]`)
+    #[serde(default)]
+    pub schedules: Vec<ScheduleRule>,
 }
 
 /// Server configuration for TCP and HTTP listeners.
@@ -43,6 +118,47 @@ pub struct ServerConfig {
     /// Capacity of the event broadcast channel (default: 100)
     #[serde(default = "default_event_channel_capacity")]
     pub event_channel_capacity: usize,
+
+    /// Origins allowed by the CORS policy; empty allows any origin (default: [])
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// Path prefix for all HTTP routes, e.g. "/sim1" behind a reverse proxy (default: "")
+    #[serde(default)]
+    pub http_base_path: String,
+
+    /// Open Protocol clients served at once; further ones are refused, 0 is unlimited (default: 0)
+    #[serde(default)]
+    pub max_connections: usize,
+
+    /// Largest incoming frame in bytes; a client exceeding it is disconnected (default: 65536)
+    #[serde(default = "default_max_frame_bytes")]
+    pub max_frame_bytes: usize,
+
+    /// Second TCP port whose clients are read-only observers, 0 disables it (default: 0)
+    #[serde(default)]
+    pub observer_tcp_port: u16,
+
+    /// Milliseconds broadcasts may wait to be written together, 0 writes each at once (default: 0)
+    #[serde(default)]
+    pub flush_interval_ms: u64,
+
+    /// Broadcasts written together at most; more are written before the interval ends (default: 64)
+    #[serde(default = "default_max_batch_frames")]
+    pub max_batch_frames: usize,
+
+    /// Milliseconds a client has from connecting to completing MID 0001; clients
+    /// that miss it are disconnected, 0 waits forever (default: 0)
+    ///
+    /// Not applied while `[device] push_on_connect` is set: those clients get
+    /// data without a handshake, so none is awaited.
+    #[serde(default)]
+    pub handshake_timeout_ms: u64,
+
+    /// Send MID 0004 (failed MID 0001) before dropping a client that missed the
+    /// handshake deadline (default: false)
+    #[serde(default)]
+    pub handshake_timeout_error: bool,
 }
 
 impl Default for ServerConfig {
@@ -52,10 +168,40 @@ impl Default for ServerConfig {
             http_port: default_http_port(),
             bind_address: default_bind_address(),
             event_channel_capacity: default_event_channel_capacity(),
+            cors_allowed_origins: Vec::new(),
+            http_base_path: String::new(),
+            max_connections: 0,
+            max_frame_bytes: default_max_frame_bytes(),
+            observer_tcp_port: 0,
+            flush_interval_ms: 0,
+            max_batch_frames: default_max_batch_frames(),
+            handshake_timeout_ms: 0,
+            handshake_timeout_error: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// `http_base_path` normalized to "/prefix" (no trailing slash), or None
+    /// when routes are served from the root
+    pub fn base_path(&self) -> Option<String> {
+        let trimmed = self.http_base_path.trim().trim_matches('/');
+        if trimmed.is_empty() {
+            None
+        } else {
+            Some(format!("/{}", trimmed))
         }
     }
 }
 
+fn default_max_frame_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_max_batch_frames() -> usize {
+    64
+}
+
 fn default_tcp_port() -> u16 {
     8080
 }
@@ -90,6 +236,73 @@ pub struct DeviceConfig {
     /// Supplier code reported in Open Protocol messages (default: "SIM")
     #[serde(default = "default_supplier_code")]
     pub supplier_code: String,
+
+    /// Controller software version reported in MID 0002 rev 3 and MID 0041 rev 2
+    #[serde(default = "default_software_version")]
+    pub software_version: String,
+
+    /// Tool software version reported in MID 0002 rev 3
+    #[serde(default = "default_software_version")]
+    pub tool_software_version: String,
+
+    /// Encoding of text fields on the wire: "utf-8" or "iso-8859-1" (default: "utf-8")
+    #[serde(default)]
+    pub text_encoding: TextEncoding,
+
+    /// Fill of numeric fields: "zero" or "space" (default: "zero")
+    #[serde(default)]
+    pub numeric_padding: NumericPadding,
+
+    /// Streams pushed from the moment a client connects, without MID 0001 or
+    /// a subscribe MID, as some gateway devices do (default: [])
+    #[serde(default)]
+    pub push_on_connect: Vec<SubscriptionKind>,
+
+    /// Revision of the data pushed on `push_on_connect` streams (default: 1)
+    #[serde(default = "default_push_revision")]
+    pub push_revision: u16,
+
+    /// Append the cycle ID as parameter 99 to MID 0052, 0061, 0091 and 0101
+    /// so clients can tell which messages belong to one cycle (default: false)
+    #[serde(default)]
+    pub stamp_cycle_id: bool,
+
+    /// Frame sent right after a client connects, before MID 0001 (default: none)
+    #[serde(default)]
+    pub greeting: Option<GreetingFrame>,
+
+    /// Stress test mode: fill names, VINs and alarm texts to their full field
+    /// width and report the most spindles MID 0101 can carry (default: false)
+    #[serde(default)]
+    pub max_size_payloads: bool,
+
+    /// Whether the selected PSET and VIN are shared by all clients ("global")
+    /// or sandboxed per connection ("per_connection") (default: "global")
+    #[serde(default)]
+    pub identification_scope: IdentificationScope,
+
+    /// Torque resolution and decimal mark of locale-specific controllers,
+    /// optionally limited to some MIDs (default: Nm × 100, no mark, as specified)
+    #[serde(default)]
+    pub torque_format: TorqueFormat,
+
+    /// Date/time separator and zero padding of timestamps in every MID, as
+    /// firmware versions differ (default: "YYYY-MM-DD:HH:MM:SS", as specified)
+    #[serde(default)]
+    pub timestamp_format: TimestampFormat,
+}
+
+impl DeviceConfig {
+    /// Wire conventions of this controller personality
+    pub fn wire_format(&self) -> WireFormat {
+        WireFormat {
+            encoding: self.text_encoding,
+            numeric_padding: self.numeric_padding,
+            max_size_text: self.max_size_payloads,
+            torque: self.torque_format,
+            timestamp: self.timestamp_format,
+        }
+    }
 }
 
 impl Default for DeviceConfig {
@@ -99,6 +312,18 @@ impl Default for DeviceConfig {
             channel_id: default_channel_id(),
             controller_name: default_controller_name(),
             supplier_code: default_supplier_code(),
+            software_version: default_software_version(),
+            tool_software_version: default_software_version(),
+            text_encoding: TextEncoding::default(),
+            numeric_padding: NumericPadding::default(),
+            push_on_connect: Vec::new(),
+            push_revision: default_push_revision(),
+            stamp_cycle_id: false,
+            greeting: None,
+            max_size_payloads: false,
+            identification_scope: IdentificationScope::default(),
+            torque_format: TorqueFormat::default(),
+            timestamp_format: TimestampFormat::default(),
         }
     }
 }
@@ -119,6 +344,14 @@ fn default_supplier_code() -> String {
     "SIM".to_string()
 }
 
+fn default_software_version() -> String {
+    format!("SIM {}", env!("CARGO_PKG_VERSION"))
+}
+
+fn default_push_revision() -> u16 {
+    1
+}
+
 /// Database configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
@@ -153,6 +386,18 @@ pub struct DefaultsConfig {
     /// Default failure rate for auto-tightening (0.0-1.0, default: 0.1)
     #[serde(default = "default_failure_rate")]
     pub failure_rate: f64,
+
+    /// Restart a completed batch automatically instead of waiting for MID 0019 (default: false)
+    #[serde(default)]
+    pub auto_reset_batch: bool,
+
+    /// Disable the tool when a batch completes, reported by MID 0022 (default: false)
+    #[serde(default)]
+    pub lock_at_batch_done: bool,
+
+    /// Refuse tightenings while no operator is logged in (default: false)
+    #[serde(default)]
+    pub require_operator_login: bool,
 }
 
 impl Default for DefaultsConfig {
@@ -161,6 +406,9 @@ impl Default for DefaultsConfig {
             auto_tightening_interval_ms: default_auto_tightening_interval(),
             auto_tightening_duration_ms: default_auto_tightening_duration(),
             failure_rate: default_failure_rate(),
+            auto_reset_batch: false,
+            lock_at_batch_done: false,
+            require_operator_login: false,
         }
     }
 }
@@ -177,6 +425,518 @@ fn default_failure_rate() -> f64 {
     0.1
 }
 
+/// Middleware layers applied around MID handler dispatch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandlersConfig {
+    /// Log every dispatched MID and its outcome (default: true)
+    #[serde(default = "default_log_messages")]
+    pub log_messages: bool,
+
+    /// NAK frames with revision 0, length mismatches or non-ASCII data (default: false)
+    #[serde(default)]
+    pub strict: bool,
+
+    /// Fixed delay added before every response in milliseconds (default: 0)
+    #[serde(default)]
+    pub response_delay_ms: u64,
+
+    /// Only accept the MIDs in `allowed_mids`; others get MID 0004 (default: false)
+    #[serde(default)]
+    pub locked_down: bool,
+
+    /// MIDs accepted while locked down (default: 1, 3, 9999)
+    #[serde(default = "default_allowed_mids")]
+    pub allowed_mids: Vec<u16>,
+
+    /// Reply to MIDs without a handler: "error" (MID 0004), "ignore" or
+    /// "disconnect" (default: "error")
+    #[serde(default)]
+    pub unknown_mid: UnknownMidPolicy,
+}
+
+impl Default for HandlersConfig {
+    fn default() -> Self {
+        Self {
+            log_messages: default_log_messages(),
+            strict: false,
+            response_delay_ms: 0,
+            locked_down: false,
+            allowed_mids: default_allowed_mids(),
+            unknown_mid: UnknownMidPolicy::default(),
+        }
+    }
+}
+
+fn default_log_messages() -> bool {
+    true
+}
+
+fn default_allowed_mids() -> Vec<u16> {
+    vec![1, 3, 9999]
+}
+
+/// Tightening results history configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultsConfig {
+    /// Maximum number of results kept in the history (default: 10000)
+    #[serde(default = "default_history_capacity")]
+    pub history_capacity: usize,
+    /// Samples kept per torque/angle curve; 0 disables curve recording (default: 200)
+    #[serde(default = "default_curve_points")]
+    pub curve_points: usize,
+    /// HMAC-SHA256 key; when set, every stored result carries a `signature`
+    /// (default: none)
+    #[serde(default)]
+    pub signing_key: Option<String>,
+}
+
+impl Default for ResultsConfig {
+    fn default() -> Self {
+        Self {
+            history_capacity: default_history_capacity(),
+            curve_points: default_curve_points(),
+            signing_key: None,
+        }
+    }
+}
+
+fn default_history_capacity() -> usize {
+    10_000
+}
+
+fn default_curve_points() -> usize {
+    200
+}
+
+/// Scheduled Parquet export configuration (used with the `parquet` feature).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportConfig {
+    /// Directory the scheduled export writes into (default: "exports")
+    #[serde(default = "default_parquet_dir")]
+    pub parquet_dir: PathBuf,
+
+    /// Seconds between scheduled exports; 0 disables the schedule (default: 0)
+    #[serde(default)]
+    pub parquet_interval_secs: u64,
+
+    /// Also write a curves file with every scheduled export (default: true)
+    #[serde(default = "default_true")]
+    pub parquet_include_curves: bool,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            parquet_dir: default_parquet_dir(),
+            parquet_interval_secs: 0,
+            parquet_include_curves: true,
+        }
+    }
+}
+
+/// Random alarm noise raised during auto-tightening runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlarmsConfig {
+    /// Average number of random alarms per minute; 0 disables the generator (default: 0)
+    #[serde(default)]
+    pub random_per_minute: f64,
+
+    /// Seconds after which a random alarm clears itself; 0 keeps it until cleared (default: 30)
+    #[serde(default = "default_auto_clear_secs")]
+    pub auto_clear_secs: u64,
+
+    /// Alarms the generator picks from, weighted by `weight`
+    #[serde(default = "default_catalogue")]
+    pub catalogue: Vec<AlarmTemplate>,
+
+    /// Language of alarm texts sent to clients, two-letter code (default: "en")
+    #[serde(default = "default_alarm_language")]
+    pub language: String,
+
+    /// Alarm texts added to or replacing the built-in ones, by error code and language
+    #[serde(default)]
+    pub texts: BTreeMap<String, Translations>,
+}
+
+impl Default for AlarmsConfig {
+    fn default() -> Self {
+        Self {
+            random_per_minute: 0.0,
+            auto_clear_secs: default_auto_clear_secs(),
+            catalogue: default_catalogue(),
+            language: default_alarm_language(),
+            texts: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_auto_clear_secs() -> u64 {
+    30
+}
+
+fn default_alarm_language() -> String {
+    DEFAULT_LANGUAGE.to_string()
+}
+
+/// Simulated tool telemetry (motor temperature, current, supply voltage).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// Milliseconds between telemetry updates and WebSocket `Telemetry`
+    /// events; 0 freezes the values between tightenings (default: 1000)
+    #[serde(default = "default_telemetry_interval_ms")]
+    pub interval_ms: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: default_telemetry_interval_ms(),
+        }
+    }
+}
+
+fn default_telemetry_interval_ms() -> u64 {
+    1000
+}
+
+/// Lifetime tool statistics persistence and cycle-time summaries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsConfig {
+    /// JSON file the lifetime counters are kept in (default: "lifetime_stats.json")
+    #[serde(default = "default_lifetime_path")]
+    pub lifetime_path: PathBuf,
+
+    /// Seconds between saves of changed counters; 0 disables saving (default: 5)
+    #[serde(default = "default_save_interval_secs")]
+    pub save_interval_secs: u64,
+
+    /// Seconds between `CycleTimeSummary` events; 0 disables them (default: 60)
+    #[serde(default = "default_cycle_time_summary_secs")]
+    pub cycle_time_summary_secs: u64,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            lifetime_path: default_lifetime_path(),
+            save_interval_secs: default_save_interval_secs(),
+            cycle_time_summary_secs: default_cycle_time_summary_secs(),
+        }
+    }
+}
+
+fn default_lifetime_path() -> PathBuf {
+    PathBuf::from("lifetime_stats.json")
+}
+
+fn default_save_interval_secs() -> u64 {
+    5
+}
+
+fn default_cycle_time_summary_secs() -> u64 {
+    60
+}
+
+/// Gateway mode: forward client connections to a real controller.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// Controller address ("host:port") to forward to; unset runs the simulator
+    #[serde(default)]
+    pub upstream: Option<String>,
+
+    /// Milliseconds to wait for the controller to accept a connection (default: 5000)
+    #[serde(default = "default_proxy_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+
+    /// Number of recorded frames kept in memory for GET /proxy/exchanges (default: 1000)
+    #[serde(default = "default_proxy_record_capacity")]
+    pub record_capacity: usize,
+
+    /// Also append every recorded frame to this file as JSON lines
+    #[serde(default)]
+    pub record_path: Option<PathBuf>,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            upstream: None,
+            connect_timeout_ms: default_proxy_connect_timeout_ms(),
+            record_capacity: default_proxy_record_capacity(),
+            record_path: None,
+        }
+    }
+}
+
+fn default_proxy_connect_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_proxy_record_capacity() -> usize {
+    1000
+}
+
+/// Processing delay before subscription data is pushed, growing with load.
+///
+/// The delay is `base_ms + per_client_ms * clients + per_tightening_ms *
+/// tightenings per minute`, varied by up to `jitter_percent` either way and
+/// capped at `max_ms`. All zero (the default) pushes immediately.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastLatencyConfig {
+    /// Delay under no load in milliseconds (default: 0)
+    #[serde(default)]
+    pub base_ms: u64,
+
+    /// Added delay per connected client in milliseconds (default: 0)
+    #[serde(default)]
+    pub per_client_ms: f64,
+
+    /// Added delay per tightening in the last minute in milliseconds (default: 0)
+    #[serde(default)]
+    pub per_tightening_ms: f64,
+
+    /// Random variation around the modelled delay in percent (default: 0)
+    #[serde(default)]
+    pub jitter_percent: f64,
+
+    /// Upper bound of the delay in milliseconds (default: 2000)
+    #[serde(default = "default_broadcast_latency_max_ms")]
+    pub max_ms: u64,
+}
+
+impl Default for BroadcastLatencyConfig {
+    fn default() -> Self {
+        Self {
+            base_ms: 0,
+            per_client_ms: 0.0,
+            per_tightening_ms: 0.0,
+            jitter_percent: 0.0,
+            max_ms: default_broadcast_latency_max_ms(),
+        }
+    }
+}
+
+fn default_broadcast_latency_max_ms() -> u64 {
+    2000
+}
+
+/// Delivery of tightening results to external integrations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrationsConfig {
+    /// Webhook receiving batches of results ("http://host:port/path"); unset disables it
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+
+    /// Maximum outbox entries per webhook request (default: 10)
+    #[serde(default = "default_webhook_batch_size")]
+    pub batch_size: usize,
+
+    /// Milliseconds between checks for due outbox entries (default: 500)
+    #[serde(default = "default_webhook_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+
+    /// Milliseconds before the first retry, doubled for each further one (default: 1000)
+    #[serde(default = "default_webhook_retry_base_ms")]
+    pub retry_base_ms: u64,
+
+    /// Longest wait between retries in milliseconds (default: 60000)
+    #[serde(default = "default_webhook_retry_max_ms")]
+    pub retry_max_ms: u64,
+
+    /// Attempts before an entry is marked failed; 0 retries forever (default: 0)
+    #[serde(default)]
+    pub max_attempts: u32,
+
+    /// Milliseconds to wait for the webhook to answer (default: 5000)
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for IntegrationsConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            batch_size: default_webhook_batch_size(),
+            poll_interval_ms: default_webhook_poll_interval_ms(),
+            retry_base_ms: default_webhook_retry_base_ms(),
+            retry_max_ms: default_webhook_retry_max_ms(),
+            max_attempts: 0,
+            timeout_ms: default_webhook_timeout_ms(),
+        }
+    }
+}
+
+fn default_webhook_batch_size() -> usize {
+    10
+}
+
+fn default_webhook_poll_interval_ms() -> u64 {
+    500
+}
+
+fn default_webhook_retry_base_ms() -> u64 {
+    1000
+}
+
+fn default_webhook_retry_max_ms() -> u64 {
+    60_000
+}
+
+fn default_webhook_timeout_ms() -> u64 {
+    5000
+}
+
+/// Keep-alive (MID 9999) behavior of the controller.
+///
+/// By default client keep-alives are echoed at once and the controller never
+/// probes. Integrations that expect a slow echo, no echo at all, or
+/// controller-initiated keep-alives can be tested by changing these.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeepAliveConfig {
+    /// Echo client keep-alives; false ignores them (default: true)
+    #[serde(default = "default_true")]
+    pub reply: bool,
+
+    /// Milliseconds to wait before the echo (default: 0)
+    #[serde(default)]
+    pub reply_delay_ms: u64,
+
+    /// Milliseconds between keep-alives sent by the controller; 0 disables probing (default: 0)
+    #[serde(default)]
+    pub probe_interval_ms: u64,
+}
+
+impl Default for KeepAliveConfig {
+    fn default() -> Self {
+        Self {
+            reply: true,
+            reply_delay_ms: 0,
+            probe_interval_ms: 0,
+        }
+    }
+}
+
+/// Random controller-initiated disconnects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisconnectsConfig {
+    /// Average seconds between disconnects of a random client; 0 disables them (default: 0)
+    #[serde(default)]
+    pub mean_interval_secs: f64,
+
+    /// Close modes picked from at random (default: all of graceful, abort, half_close)
+    #[serde(default = "default_close_modes")]
+    pub modes: Vec<CloseMode>,
+}
+
+impl Default for DisconnectsConfig {
+    fn default() -> Self {
+        Self {
+            mean_interval_secs: 0.0,
+            modes: default_close_modes(),
+        }
+    }
+}
+
+fn default_close_modes() -> Vec<CloseMode> {
+    vec![CloseMode::Graceful, CloseMode::Abort, CloseMode::HalfClose]
+}
+
+/// Advance notice of planned restarts and outages, and the tool service interval.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceConfig {
+    /// Milliseconds between the `MaintenanceImminent` event and a firmware
+    /// upgrade or scheduled outage; 0 starts them without notice (default: 0)
+    #[serde(default)]
+    pub notice_ms: u64,
+
+    /// Refuse new Open Protocol sessions during the notice period (default: false)
+    #[serde(default)]
+    pub refuse_new_sessions: bool,
+
+    /// Tightenings after which the tool is due for service and a service
+    /// alarm is raised; 0 disables the check (default: 0)
+    #[serde(default)]
+    pub service_interval: u64,
+
+    /// Lock the tool once service is due, until `POST /tool/service-reset`
+    /// (default: false)
+    #[serde(default)]
+    pub service_lockout: bool,
+}
+
+/// Station interlocks between job positions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InterlockConfig {
+    /// Jobs that disable the tool after every OK tightening until the next
+    /// position is released (default: none)
+    #[serde(default)]
+    pub jobs: Vec<u32>,
+}
+
+/// Logging to files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Journal of every event (`[logging.journal]`)
+    #[serde(default)]
+    pub journal: JournalConfig,
+}
+
+/// Journal appending every simulator event to rotating NDJSON files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalConfig {
+    /// Journal file; rotated files are kept next to it. Unset disables the journal
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Also journal the raw frames of every Open Protocol connection (default: false)
+    #[serde(default)]
+    pub frames: bool,
+
+    /// Rotate once the file reaches this many bytes; 0 disables (default: 10 MiB)
+    #[serde(default = "default_journal_max_bytes")]
+    pub max_bytes: u64,
+
+    /// Rotate once the file is this many seconds old; 0 disables (default: 86400)
+    #[serde(default = "default_journal_max_age_secs")]
+    pub max_age_secs: u64,
+
+    /// Rotated files kept; older ones are deleted, 0 keeps all (default: 10)
+    #[serde(default = "default_journal_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            frames: false,
+            max_bytes: default_journal_max_bytes(),
+            max_age_secs: default_journal_max_age_secs(),
+            max_files: default_journal_max_files(),
+        }
+    }
+}
+
+fn default_journal_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_journal_max_age_secs() -> u64 {
+    86400
+}
+
+fn default_journal_max_files() -> usize {
+    10
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_parquet_dir() -> PathBuf {
+    PathBuf::from("exports")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -191,5 +951,23 @@ mod tests {
         assert_eq!(settings.device.controller_name, "OpenProtocolSimulator");
         assert_eq!(settings.database.path, PathBuf::from("simulator.db"));
         assert_eq!(settings.defaults.auto_tightening_interval_ms, 3000);
+        assert!(settings.handlers.log_messages);
+        assert!(!settings.handlers.locked_down);
+        assert!(settings.server.cors_allowed_origins.is_empty());
+        assert_eq!(settings.server.base_path(), None);
+    }
+
+    #[test]
+    fn test_base_path_normalization() {
+        let mut server = ServerConfig::default();
+        for (raw, expected) in [
+            ("/", None),
+            ("sim1", Some("/sim1")),
+            ("/sim1/", Some("/sim1")),
+            (" /lab/sim2 ", Some("/lab/sim2")),
+        ] {
+            server.http_base_path = raw.to_string();
+            assert_eq!(server.base_path().as_deref(), expected, "{:?}", raw);
+        }
     }
 }
@@ -0,0 +1,683 @@
+//! Conformance suite for Open Protocol clients
+//!
+//! `open-protocol-device-simulator conformance` waits for the client under
+//! test on the TCP port and drives it through a fixed script: communication
+//! start, result subscription, a pushed result that must be acknowledged, an
+//! idle period in which keep-alives must arrive in time, a corrupt frame the
+//! client must survive, and a dropped link after which the client must
+//! reconnect, restart communication and subscribe again. Requests are
+//! answered by the normal handler registry, so the client sees the same
+//! replies as from the simulator.
+//!
+//! Each expected behavior becomes a check in a [`ConformanceReport`], which
+//! renders as text, JSON or JUnit XML for CI systems. Once a check the rest
+//! of the script depends on fails, the remaining checks are skipped.
+
+use crate::codec::null_delimited_codec::NullDelimitedCodec;
+use crate::events::SimulatorEvent;
+use crate::handler::HandlerRegistry;
+use crate::handler::data::{ErrorResponse, TighteningResult};
+use crate::protocol::{HeaderOptions, Message, Mid, parser, serializer};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::Framed;
+
+/// Checks in script order: (name, expected client behavior)
+pub const CHECKS: [(&str, &str); 10] = [
+    ("connects", "Client opens a TCP connection"),
+    (
+        "communication_start",
+        "First message on a new connection is MID 0001",
+    ),
+    (
+        "result_subscription",
+        "Client subscribes to tightening results (MID 0060)",
+    ),
+    (
+        "result_acknowledged",
+        "Pushed MID 0061 is acknowledged with MID 0062 (and not when subscribed with No Ack)",
+    ),
+    (
+        "keep_alive_timely",
+        "Idle client sends MID 9999 before the keep-alive interval runs out",
+    ),
+    (
+        "survives_corrupt_frame",
+        "Client keeps the link after receiving a corrupt frame",
+    ),
+    (
+        "reconnects_after_link_drop",
+        "Client reconnects after the controller closes the link",
+    ),
+    (
+        "communication_restarted",
+        "First message on the new connection is MID 0001",
+    ),
+    (
+        "resubscribes_after_reconnect",
+        "Client subscribes to tightening results again after reconnecting",
+    ),
+    (
+        "result_acknowledged_after_reconnect",
+        "Results pushed on the new link are acknowledged (unless subscribed with No Ack)",
+    ),
+];
+
+/// Timing of the script
+#[derive(Debug, Clone)]
+pub struct ConformanceConfig {
+    /// Time allowed for connecting, starting communication and subscribing
+    pub step_timeout: Duration,
+    /// Time allowed for acknowledging a pushed result
+    pub ack_timeout: Duration,
+    /// Longest silence allowed from an idle client (Open Protocol: 15 s)
+    pub keep_alive_interval: Duration,
+}
+
+impl Default for ConformanceConfig {
+    fn default() -> Self {
+        Self {
+            step_timeout: Duration::from_secs(30),
+            ack_timeout: Duration::from_secs(5),
+            keep_alive_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+/// Outcome of a conformance run
+#[derive(Debug, Clone, Serialize)]
+pub struct ConformanceReport {
+    /// Address of the client under test
+    pub client: Option<SocketAddr>,
+    /// Local time the run started (RFC 3339)
+    pub started_at: String,
+    pub checks: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    fn new() -> Self {
+        Self {
+            client: None,
+            started_at: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            checks: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, name: &'static str, started: Instant, passed: bool, detail: String) {
+        let description = CHECKS
+            .iter()
+            .find(|(check, _)| *check == name)
+            .map_or("", |(_, description)| description);
+        self.checks.push(CheckResult {
+            name,
+            description,
+            status: if passed {
+                CheckStatus::Passed
+            } else {
+                CheckStatus::Failed
+            },
+            detail,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+
+    /// Mark every check that did not run as skipped
+    fn skip_remaining(&mut self) {
+        for (name, description) in CHECKS {
+            if !self.checks.iter().any(|c| c.name == name) {
+                self.checks.push(CheckResult {
+                    name,
+                    description,
+                    status: CheckStatus::Skipped,
+                    detail: "Not run: an earlier check failed".to_string(),
+                    duration_ms: 0,
+                });
+            }
+        }
+    }
+
+    /// True when every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status == CheckStatus::Passed)
+    }
+
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks.iter().filter(|c| c.status == status).count()
+    }
+
+    /// Render the report as a JUnit XML test suite
+    pub fn to_junit(&self) -> String {
+        let total_secs: f64 = self
+            .checks
+            .iter()
+            .map(|c| c.duration_ms as f64 / 1000.0)
+            .sum();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuite name=\"open-protocol-conformance\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\" timestamp=\"{}\">\n",
+            self.checks.len(),
+            self.count(CheckStatus::Failed),
+            self.count(CheckStatus::Skipped),
+            total_secs,
+            xml_escape(&self.started_at)
+        ));
+        for check in &self.checks {
+            let open = format!(
+                "  <testcase classname=\"conformance\" name=\"{}\" time=\"{:.3}\"",
+                check.name,
+                check.duration_ms as f64 / 1000.0
+            );
+            match check.status {
+                CheckStatus::Passed => xml.push_str(&format!("{}/>\n", open)),
+                CheckStatus::Failed => xml.push_str(&format!(
+                    "{}>\n    <failure message=\"{}\">{}</failure>\n  </testcase>\n",
+                    open,
+                    xml_escape(check.description),
+                    xml_escape(&check.detail)
+                )),
+                CheckStatus::Skipped => xml.push_str(&format!(
+                    "{}>\n    <skipped message=\"{}\"/>\n  </testcase>\n",
+                    open,
+                    xml_escape(&check.detail)
+                )),
+            }
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.client {
+            Some(client) => writeln!(f, "Conformance report for {}", client)?,
+            None => writeln!(f, "Conformance report (no client connected)")?,
+        }
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Passed => "PASS",
+                CheckStatus::Failed => "FAIL",
+                CheckStatus::Skipped => "SKIP",
+            };
+            writeln!(f, "  [{}] {:<38} {}", status, check.name, check.detail)?;
+        }
+        writeln!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.count(CheckStatus::Passed),
+            self.count(CheckStatus::Failed),
+            self.count(CheckStatus::Skipped)
+        )
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// What waiting for a client message produced
+enum Wait {
+    Got(Message),
+    Timeout,
+    Closed,
+}
+
+/// Connection to the client under test, answering its requests
+struct Link<'a> {
+    framed: Framed<TcpStream, NullDelimitedCodec>,
+    registry: &'a HandlerRegistry,
+}
+
+impl<'a> Link<'a> {
+    fn new(stream: TcpStream, registry: &'a HandlerRegistry) -> Self {
+        Self {
+            framed: Framed::new(stream, NullDelimitedCodec::new()),
+            registry,
+        }
+    }
+
+    /// Answer client messages until one matches `want` or `timeout` runs out
+    async fn wait_for(&mut self, timeout: Duration, want: impl Fn(&Message) -> bool) -> Wait {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let frame = match tokio::time::timeout_at(deadline, self.framed.next()).await {
+                Err(_) => return Wait::Timeout,
+                Ok(Some(Ok(frame))) => frame,
+                Ok(_) => return Wait::Closed,
+            };
+            let Ok(message) = parser::parse_message(&frame) else {
+                continue;
+            };
+            if self.answer(&message).await.is_err() {
+                return Wait::Closed;
+            }
+            if want(&message) {
+                return Wait::Got(message);
+            }
+        }
+    }
+
+    async fn answer(&mut self, message: &Message) -> std::io::Result<()> {
+        let response = self.registry.handle_message(message).unwrap_or_else(|_| {
+            ErrorResponse::generic(message.mid).into_response(message.revision)
+        });
+        self.send(serializer::serialize_response(&response)).await
+    }
+
+    async fn send(&mut self, bytes: Vec<u8>) -> std::io::Result<()> {
+        self.framed.send(bytes.as_slice().into()).await
+    }
+
+    /// Push a tightening result matching the client's subscription
+    async fn push_result(&mut self, subscribe: &Message) -> std::io::Result<()> {
+        let event = SimulatorEvent::TighteningCompleted {
+            result: TighteningResult::example(),
+            delivery: None,
+        };
+        let (revision, data) = event
+            .subscription_payload(subscribe.revision.max(1))
+            .expect("tightening results have a payload");
+        let header = HeaderOptions {
+            no_ack: subscribe.header.no_ack,
+            ..HeaderOptions::default()
+        };
+        self.send(serializer::serialize_frame_with(
+            Mid::TIGHTENING_RESULT.value(),
+            revision,
+            &header,
+            &data,
+        ))
+        .await
+    }
+}
+
+fn describe(wait: &Wait, what: &str, timeout: Duration) -> String {
+    match wait {
+        Wait::Got(message) => format!("Got MID {:04} instead of {}", message.mid, what),
+        Wait::Timeout => format!("No {} within {} ms", what, timeout.as_millis()),
+        Wait::Closed => format!("Connection closed while waiting for {}", what),
+    }
+}
+
+/// Wait for a connection on `listener` and run the script against it
+pub async fn run(
+    listener: &TcpListener,
+    registry: &HandlerRegistry,
+    config: &ConformanceConfig,
+) -> ConformanceReport {
+    let mut report = ConformanceReport::new();
+    script(listener, registry, config, &mut report).await;
+    report.skip_remaining();
+    report
+}
+
+async fn accept<'a>(
+    listener: &TcpListener,
+    registry: &'a HandlerRegistry,
+    timeout: Duration,
+) -> Option<(Link<'a>, SocketAddr)> {
+    let (stream, addr) = tokio::time::timeout(timeout, listener.accept())
+        .await
+        .ok()?
+        .ok()?;
+    Some((Link::new(stream, registry), addr))
+}
+
+/// Communication start, result subscription and one acknowledged result;
+/// returns false once a check fails
+async fn start_session(
+    link: &mut Link<'_>,
+    config: &ConformanceConfig,
+    report: &mut ConformanceReport,
+    checks: [&'static str; 3],
+) -> bool {
+    let [start_check, subscription_check, ack_check] = checks;
+
+    let started = Instant::now();
+    let wait = link.wait_for(config.step_timeout, |_| true).await;
+    let ok = matches!(&wait, Wait::Got(m) if m.mid == Mid::COMMUNICATION_START.value());
+    let detail = if ok {
+        "MID 0001 received".to_string()
+    } else {
+        describe(&wait, "MID 0001", config.step_timeout)
+    };
+    report.record(start_check, started, ok, detail);
+    if !ok {
+        return false;
+    }
+
+    let started = Instant::now();
+    let wait = link
+        .wait_for(config.step_timeout, |m| {
+            m.mid == Mid::TIGHTENING_RESULT_SUBSCRIBE.value()
+        })
+        .await;
+    let Wait::Got(subscribe) = wait else {
+        report.record(
+            subscription_check,
+            started,
+            false,
+            describe(&wait, "MID 0060", config.step_timeout),
+        );
+        return false;
+    };
+    report.record(
+        subscription_check,
+        started,
+        true,
+        format!("MID 0060 revision {}", subscribe.revision),
+    );
+
+    let started = Instant::now();
+    if link.push_result(&subscribe).await.is_err() {
+        report.record(
+            ack_check,
+            started,
+            false,
+            "Connection closed before MID 0061 was sent".to_string(),
+        );
+        return false;
+    }
+    let wait = link
+        .wait_for(config.ack_timeout, |m| {
+            m.mid == Mid::TIGHTENING_RESULT_ACK.value()
+        })
+        .await;
+    // With No Ack the push is fire-and-forget: an acknowledgment is a fault
+    let (ok, detail) = match (&wait, subscribe.header.no_ack) {
+        (Wait::Got(_), false) => (
+            true,
+            format!("MID 0062 after {} ms", started.elapsed().as_millis()),
+        ),
+        (_, false) => (false, describe(&wait, "MID 0062", config.ack_timeout)),
+        (Wait::Timeout, true) => (true, "No MID 0062 for the No Ack push".to_string()),
+        (Wait::Got(_), true) => (false, "MID 0062 sent for a No Ack subscription".to_string()),
+        (Wait::Closed, true) => (false, "Connection closed after the No Ack push".to_string()),
+    };
+    report.record(ack_check, started, ok, detail);
+    ok
+}
+
+async fn script(
+    listener: &TcpListener,
+    registry: &HandlerRegistry,
+    config: &ConformanceConfig,
+    report: &mut ConformanceReport,
+) {
+    let started = Instant::now();
+    let Some((mut link, addr)) = accept(listener, registry, config.step_timeout).await else {
+        report.record(
+            "connects",
+            started,
+            false,
+            format!(
+                "No client connected within {} ms",
+                config.step_timeout.as_millis()
+            ),
+        );
+        return;
+    };
+    report.client = Some(addr);
+    report.record("connects", started, true, format!("{} connected", addr));
+
+    if !start_session(
+        &mut link,
+        config,
+        report,
+        [
+            "communication_start",
+            "result_subscription",
+            "result_acknowledged",
+        ],
+    )
+    .await
+    {
+        return;
+    }
+
+    // Idle period: the controller sends nothing, keep-alives must keep coming
+    let started = Instant::now();
+    let window = config.keep_alive_interval * 3 / 2;
+    let mut keep_alives = 0;
+    let mut max_gap = Duration::ZERO;
+    let mut last = Instant::now();
+    let closed = loop {
+        let remaining = window.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            break false;
+        }
+        match link.wait_for(remaining, |_| true).await {
+            Wait::Got(message) => {
+                max_gap = max_gap.max(last.elapsed());
+                last = Instant::now();
+                if message.mid == Mid::KEEP_ALIVE.value() {
+                    keep_alives += 1;
+                }
+            }
+            Wait::Timeout => break false,
+            Wait::Closed => break true,
+        }
+    };
+    max_gap = max_gap.max(last.elapsed());
+    let ok = !closed && keep_alives > 0 && max_gap <= config.keep_alive_interval;
+    let detail = if closed {
+        "Connection closed during the idle period".to_string()
+    } else {
+        format!(
+            "{} keep-alives, longest silence {} ms (limit {} ms)",
+            keep_alives,
+            max_gap.as_millis(),
+            config.keep_alive_interval.as_millis()
+        )
+    };
+    report.record("keep_alive_timely", started, ok, detail);
+    if closed {
+        return;
+    }
+
+    // Error injection: a frame with a broken header
+    let started = Instant::now();
+    let corrupt = b"99XX0061001         garbage".to_vec();
+    let wait = if link.send(corrupt).await.is_err() {
+        Wait::Closed
+    } else {
+        link.wait_for(config.keep_alive_interval, |_| true).await
+    };
+    let ok = matches!(wait, Wait::Got(_));
+    let detail = if ok {
+        "Client kept talking after the corrupt frame".to_string()
+    } else {
+        describe(
+            &wait,
+            "a message after the corrupt frame",
+            config.keep_alive_interval,
+        )
+    };
+    report.record("survives_corrupt_frame", started, ok, detail);
+
+    // Link drop: the client must come back on its own
+    drop(link);
+    let started = Instant::now();
+    let Some((mut link, addr)) = accept(listener, registry, config.step_timeout).await else {
+        report.record(
+            "reconnects_after_link_drop",
+            started,
+            false,
+            format!("No reconnect within {} ms", config.step_timeout.as_millis()),
+        );
+        return;
+    };
+    report.record(
+        "reconnects_after_link_drop",
+        started,
+        true,
+        format!(
+            "{} reconnected after {} ms",
+            addr,
+            started.elapsed().as_millis()
+        ),
+    );
+
+    start_session(
+        &mut link,
+        config,
+        report,
+        [
+            "communication_restarted",
+            "resubscribes_after_reconnect",
+            "result_acknowledged_after_reconnect",
+        ],
+    )
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler;
+    use crate::observable_state::ObservableState;
+    use crate::state::DeviceState;
+    use crate::{job, pset};
+
+    fn frame(mid: u16) -> tokio_util::bytes::BytesMut {
+        serializer::serialize_frame(mid, 1, b"").as_slice().into()
+    }
+
+    /// Client that behaves the way the suite expects, optionally subscribing with No Ack
+    async fn conformant_client(addr: SocketAddr, keep_alive: Duration, no_ack: bool) {
+        while let Ok(stream) = TcpStream::connect(addr).await {
+            let mut framed = Framed::new(stream, NullDelimitedCodec::new());
+            if framed.send(frame(1)).await.is_err() {
+                return;
+            }
+            let mut tick = tokio::time::interval(keep_alive);
+            loop {
+                tokio::select! {
+                    received = framed.next() => {
+                        let Some(Ok(received)) = received else { break };
+                        let reply = match parser::parse_message(&received) {
+                            Ok(m) if m.mid == 2 => {
+                                let header = HeaderOptions { no_ack, ..HeaderOptions::default() };
+                                Some(serializer::serialize_frame_with(60, 1, &header, b""))
+                            }
+                            Ok(m) if m.mid == 61 && !m.header.no_ack => {
+                                Some(serializer::serialize_frame(62, 1, b""))
+                            }
+                            _ => None,
+                        };
+                        if let Some(reply) = reply {
+                            let _ = framed.send(reply.as_slice().into()).await;
+                        }
+                    }
+                    _ = tick.tick() => {
+                        let _ = framed.send(frame(9999)).await;
+                    }
+                }
+            }
+        }
+    }
+
+    fn registry() -> HandlerRegistry {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        handler::create_default_registry(
+            ObservableState::new(DeviceState::new_shared(), tx),
+            pset::create_default_repository(),
+            job::create_default_job_repository(),
+        )
+    }
+
+    fn config() -> ConformanceConfig {
+        ConformanceConfig {
+            step_timeout: Duration::from_secs(2),
+            ack_timeout: Duration::from_millis(500),
+            keep_alive_interval: Duration::from_millis(200),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conformant_client_passes_every_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(conformant_client(addr, Duration::from_millis(50), false));
+
+        let report = run(&listener, &registry(), &config()).await;
+        client.abort();
+
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checks.len(), CHECKS.len());
+        let names: Vec<&str> = report.checks.iter().map(|c| c.name).collect();
+        let expected: Vec<&str> = CHECKS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, expected);
+    }
+
+    #[tokio::test]
+    async fn test_no_ack_client_passes_without_acknowledging() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(conformant_client(addr, Duration::from_millis(50), true));
+
+        let report = run(&listener, &registry(), &config()).await;
+        client.abort();
+
+        assert!(report.passed(), "{}", report);
+        let ack = report
+            .checks
+            .iter()
+            .find(|c| c.name == "result_acknowledged")
+            .unwrap();
+        assert!(ack.detail.contains("No Ack"), "{}", ack.detail);
+    }
+
+    #[tokio::test]
+    async fn test_silent_client_fails_and_skips_the_rest() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Connects but never says anything
+        let _client = TcpStream::connect(addr).await.unwrap();
+
+        let config = ConformanceConfig {
+            step_timeout: Duration::from_millis(300),
+            ..config()
+        };
+        let report = run(&listener, &registry(), &config).await;
+        assert!(!report.passed());
+        assert_eq!(report.checks[0].status, CheckStatus::Passed);
+        assert_eq!(report.checks[1].status, CheckStatus::Failed);
+        assert!(
+            report.checks[2..]
+                .iter()
+                .all(|c| c.status == CheckStatus::Skipped)
+        );
+
+        let junit = report.to_junit();
+        assert!(junit.contains("tests=\"10\" failures=\"1\" skipped=\"8\""));
+        assert!(
+            junit.contains("<failure message=\"First message on a new connection is MID 0001\">")
+        );
+    }
+}
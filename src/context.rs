@@ -0,0 +1,305 @@
+//! Shared application context
+//!
+//! Everything the TCP server, HTTP router, handler registry and automation
+//! tasks share is created once at startup and handed around as an
+//! `AppContext`, so tests wire the simulator up exactly like production.
+
+use crate::ack_policy::AckPolicies;
+use crate::alarm_texts::{self, AlarmTexts};
+use crate::assertions::Assertions;
+use crate::broadcast_latency::BroadcastLatency;
+use crate::config::Settings;
+use crate::counters::{self, SharedCounterStore};
+use crate::events::{EventBroadcaster, SimulatorEvent};
+use crate::failure_simulator::{InjectionLog, SharedInjectionLog};
+use crate::handler::middleware::{HandlerMetrics, ObserverLayer, layers_from_config};
+use crate::handler::{self, HandlerRegistry};
+use crate::job::{self, SharedJobRepository};
+use crate::lifetime::LifetimeStats;
+use crate::observable_state::ObservableState;
+use crate::outbox::{self, SharedOutbox};
+use crate::proxy::{ExchangeLog, SharedExchangeLog};
+use crate::pset::{self, SharedPsetRepository};
+use crate::resources::ResourceMonitor;
+use crate::results::{self, SharedResultRepository};
+use crate::schedules::Schedules;
+use crate::session_registry::SharedSessionRegistry;
+use crate::state::DeviceState;
+use crate::throttle::BroadcastThrottles;
+use crate::user_data::{self, SharedUserDataStore};
+use std::sync::Arc;
+
+/// Shared services of a running simulator (cheap to clone)
+#[derive(Clone)]
+pub struct AppContext {
+    pub settings: Arc<Settings>,
+    pub observable_state: ObservableState,
+    pub pset_repository: SharedPsetRepository,
+    pub job_repository: SharedJobRepository,
+    pub result_repository: SharedResultRepository,
+    pub events: EventBroadcaster,
+    pub handler_metrics: Arc<HandlerMetrics>,
+    pub sessions: SharedSessionRegistry,
+    pub exchange_log: SharedExchangeLog,
+    pub injection_log: SharedInjectionLog,
+    pub broadcast_latency: Arc<BroadcastLatency>,
+    pub outbox: SharedOutbox,
+    pub resources: Arc<ResourceMonitor>,
+    pub schedules: Arc<Schedules>,
+    pub throttles: Arc<BroadcastThrottles>,
+    pub assertions: Arc<Assertions>,
+    pub ack_policies: Arc<AckPolicies>,
+    pub counters: SharedCounterStore,
+    pub user_data: SharedUserDataStore,
+}
+
+impl AppContext {
+    /// Create the device state, event channel and repositories from configuration
+    pub fn new(settings: Settings) -> Self {
+        let device_state = DeviceState::new_shared_from_config(&settings.device);
+        {
+            let mut state = device_state.write().unwrap();
+            state
+                .tightening_tracker
+                .set_auto_reset_batch(settings.defaults.auto_reset_batch);
+            state
+                .tightening_tracker
+                .set_lock_at_batch_done(settings.defaults.lock_at_batch_done);
+            state.alarm_texts = Arc::new(AlarmTexts::with_overrides(&settings.alarms.texts));
+            if alarm_texts::is_valid_language(&settings.alarms.language) {
+                state.alarm_language = settings.alarms.language.clone();
+            } else {
+                eprintln!(
+                    "Invalid alarm language {:?}, using {:?}",
+                    settings.alarms.language, state.alarm_language
+                );
+            }
+        }
+        match LifetimeStats::load(&settings.stats.lifetime_path) {
+            Ok(stats) => device_state.write().unwrap().lifetime_stats = stats,
+            Err(e) => eprintln!(
+                "Failed to load lifetime statistics from {:?}: {}. Starting from zero.",
+                settings.stats.lifetime_path, e
+            ),
+        }
+
+        let (events, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(
+            settings.server.event_channel_capacity,
+        );
+        let observable_state = ObservableState::new(device_state, events);
+
+        let ctx = Self::with_state(observable_state, settings);
+        match ctx.counters.read().unwrap().load() {
+            Ok(saved) => ctx.observable_state.write().restore_counters(saved),
+            Err(e) => eprintln!("Failed to load counters: {}. Starting from zero.", e),
+        }
+        match ctx.user_data.read().unwrap().load() {
+            Ok(slots) => ctx.observable_state.write().user_data.restore(slots),
+            Err(e) => eprintln!("Failed to load user data: {}. Starting empty.", e),
+        }
+        ctx
+    }
+
+    /// Build a context around an existing device state (used by tests to
+    /// inspect or pre-configure the state and its event channel)
+    pub fn with_state(observable_state: ObservableState, settings: Settings) -> Self {
+        let pset_repository = open_pset_repository(&settings);
+        let job_repository = open_job_repository(&settings);
+        let result_repository =
+            results::create_result_repository(settings.results.history_capacity);
+        let exchange_log = Arc::new(ExchangeLog::new(settings.proxy.record_capacity));
+        let outbox = open_outbox(&settings);
+        let counters = open_counter_store(&settings);
+        let user_data = open_user_data_store(&settings);
+        let broadcast_latency = Arc::new(BroadcastLatency::new(settings.broadcast_latency.clone()));
+        let ack_policies = Arc::new(ack_policies(&settings));
+        let schedules = Arc::new(Schedules::from_rules(
+            &settings.schedules,
+            chrono::Local::now(),
+        ));
+
+        Self {
+            events: observable_state.broadcaster().clone(),
+            settings: Arc::new(settings),
+            observable_state,
+            pset_repository,
+            job_repository,
+            result_repository,
+            handler_metrics: Arc::new(HandlerMetrics::new()),
+            sessions: Default::default(),
+            exchange_log,
+            injection_log: Arc::new(InjectionLog::default()),
+            broadcast_latency,
+            outbox,
+            resources: Default::default(),
+            schedules,
+            throttles: Default::default(),
+            assertions: Default::default(),
+            ack_policies,
+            counters,
+            user_data,
+        }
+    }
+
+    /// Handler registry with all standard handlers and the configured middleware
+    pub fn handler_registry(&self) -> HandlerRegistry {
+        let mut registry = handler::create_default_registry(
+            self.observable_state.clone(),
+            Arc::clone(&self.pset_repository),
+            Arc::clone(&self.job_repository),
+        );
+        for layer in layers_from_config(&self.settings.handlers, Arc::clone(&self.handler_metrics))
+        {
+            registry.add_layer(layer);
+        }
+        registry
+    }
+
+    /// Handler registry for read-only observer connections
+    pub fn observer_registry(&self) -> HandlerRegistry {
+        let mut registry = self.handler_registry();
+        registry.add_layer(Box::new(ObserverLayer));
+        registry
+    }
+}
+
+/// Ack policies from `[acks]`, skipping invalid ones
+fn ack_policies(settings: &Settings) -> AckPolicies {
+    let policies = AckPolicies::new();
+    for (kind, policy) in &settings.acks {
+        if let Err(e) = policies.set(*kind, policy.clone()) {
+            eprintln!("Ignoring ack policy for {:?}: {}", kind, e);
+        }
+    }
+    policies
+}
+
+/// Open the SQLite PSET repository, falling back to in-memory storage
+#[cfg(feature = "sqlite")]
+fn open_pset_repository(settings: &Settings) -> SharedPsetRepository {
+    let db_path = settings.database.path.to_str().unwrap_or_else(|| {
+        eprintln!(
+            "Warning: Database path {:?} is not valid UTF-8, falling back to 'simulator.db'",
+            settings.database.path
+        );
+        "simulator.db"
+    });
+    pset::create_sqlite_repository(db_path).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to create SQLite repository: {}. Falling back to in-memory.",
+            e
+        );
+        pset::create_default_repository()
+    })
+}
+
+/// Open the SQLite job repository, falling back to in-memory storage
+#[cfg(feature = "sqlite")]
+fn open_job_repository(settings: &Settings) -> SharedJobRepository {
+    let Some(db_path) = settings.database.path.to_str() else {
+        eprintln!(
+            "Database path {:?} is not valid UTF-8, jobs are not persisted",
+            settings.database.path
+        );
+        return job::create_default_job_repository();
+    };
+    job::create_sqlite_job_repository(db_path).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to open SQLite job repository: {}. Falling back to in-memory.",
+            e
+        );
+        job::create_default_job_repository()
+    })
+}
+
+/// Open the durable outbox when a webhook is configured
+///
+/// Without a webhook nothing is queued, so the database is left alone.
+#[cfg(feature = "sqlite")]
+fn open_outbox(settings: &Settings) -> SharedOutbox {
+    if settings.integrations.webhook_url.is_none() {
+        return outbox::create_in_memory_outbox();
+    }
+    let Some(db_path) = settings.database.path.to_str() else {
+        eprintln!(
+            "Database path {:?} is not valid UTF-8, keeping the outbox in memory",
+            settings.database.path
+        );
+        return outbox::create_in_memory_outbox();
+    };
+    outbox::create_sqlite_outbox(db_path).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to open SQLite outbox: {}. Falling back to in-memory.",
+            e
+        );
+        outbox::create_in_memory_outbox()
+    })
+}
+
+/// Open the SQLite counter store, falling back to in-memory storage
+#[cfg(feature = "sqlite")]
+fn open_counter_store(settings: &Settings) -> SharedCounterStore {
+    let Some(db_path) = settings.database.path.to_str() else {
+        eprintln!(
+            "Database path {:?} is not valid UTF-8, counters are not persisted",
+            settings.database.path
+        );
+        return counters::create_in_memory_counter_store();
+    };
+    counters::create_sqlite_counter_store(db_path).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to open SQLite counter store: {}. Falling back to in-memory.",
+            e
+        );
+        counters::create_in_memory_counter_store()
+    })
+}
+
+/// Open the SQLite user data store, falling back to in-memory storage
+#[cfg(feature = "sqlite")]
+fn open_user_data_store(settings: &Settings) -> SharedUserDataStore {
+    let Some(db_path) = settings.database.path.to_str() else {
+        eprintln!(
+            "Database path {:?} is not valid UTF-8, user data is not persisted",
+            settings.database.path
+        );
+        return user_data::create_in_memory_user_data_store();
+    };
+    user_data::create_sqlite_user_data_store(db_path).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to open SQLite user data store: {}. Falling back to in-memory.",
+            e
+        );
+        user_data::create_in_memory_user_data_store()
+    })
+}
+
+/// Without the `sqlite` feature user data is not persisted
+#[cfg(not(feature = "sqlite"))]
+fn open_user_data_store(_settings: &Settings) -> SharedUserDataStore {
+    user_data::create_in_memory_user_data_store()
+}
+
+/// Without the `sqlite` feature counters are not persisted
+#[cfg(not(feature = "sqlite"))]
+fn open_counter_store(_settings: &Settings) -> SharedCounterStore {
+    counters::create_in_memory_counter_store()
+}
+
+/// Without the `sqlite` feature the outbox only lives in memory
+#[cfg(not(feature = "sqlite"))]
+fn open_outbox(_settings: &Settings) -> SharedOutbox {
+    outbox::create_in_memory_outbox()
+}
+
+/// Without the `sqlite` feature jobs only live in memory
+#[cfg(not(feature = "sqlite"))]
+fn open_job_repository(_settings: &Settings) -> SharedJobRepository {
+    job::create_default_job_repository()
+}
+
+/// Without the `sqlite` feature PSETs only live in memory
+#[cfg(not(feature = "sqlite"))]
+fn open_pset_repository(_settings: &Settings) -> SharedPsetRepository {
+    pset::create_default_repository()
+}
@@ -0,0 +1,145 @@
+//! Sequence counters that survive restarts
+//!
+//! Tightening IDs and job sequence numbers identify results downstream, so a
+//! restart must not hand them out again. The last issued values are kept in
+//! the SQLite database (`sqlite` feature), restored when the simulator
+//! starts and saved shortly after they change. `GET /counters` reports them
+//! and `PUT /counters` sets them for test setup.
+
+use crate::context::AppContext;
+use crate::persistence::{self, Persister};
+use crate::state::DeviceState;
+use crate::vin_history::MAX_JOB_SEQUENCE_NUMBER;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteCounterStore, create_sqlite_counter_store};
+
+/// How often changed counters are saved
+pub const SAVE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Last issued sequence values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counters {
+    /// Last tightening ID handed out (0 = none yet)
+    pub tightening_id: u32,
+    /// Sequence number of the current job instance (0 = none yet)
+    pub job_sequence_number: u32,
+}
+
+impl Counters {
+    /// Check the values before they are applied
+    pub fn validate(&self) -> Result<(), String> {
+        if self.job_sequence_number > MAX_JOB_SEQUENCE_NUMBER {
+            return Err(format!(
+                "job_sequence_number must be at most {}",
+                MAX_JOB_SEQUENCE_NUMBER
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Storage of the counters
+pub trait CounterStore: Send + Sync {
+    /// Saved counters; zeros when nothing was saved yet
+    fn load(&self) -> Result<Counters, String>;
+
+    fn save(&mut self, counters: Counters) -> Result<(), String>;
+}
+
+/// In-memory counter store (lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryCounterStore {
+    counters: Counters,
+}
+
+impl CounterStore for InMemoryCounterStore {
+    fn load(&self) -> Result<Counters, String> {
+        Ok(self.counters)
+    }
+
+    fn save(&mut self, counters: Counters) -> Result<(), String> {
+        self.counters = counters;
+        Ok(())
+    }
+}
+
+/// Thread-safe wrapper for CounterStore
+pub type SharedCounterStore = Arc<RwLock<Box<dyn CounterStore>>>;
+
+pub fn create_in_memory_counter_store() -> SharedCounterStore {
+    Arc::new(RwLock::new(Box::new(InMemoryCounterStore::default())))
+}
+
+/// Save the counters every [`SAVE_INTERVAL`] while they change
+pub fn spawn_persistence(ctx: &AppContext) -> AbortHandle {
+    persistence::spawn_persistence(ctx, CounterPersister::new(ctx), SAVE_INTERVAL)
+}
+
+/// Save the counters once more on shutdown
+///
+/// IDs issued since the last tick would otherwise be handed out again after
+/// a restart.
+pub fn save_on_shutdown(ctx: &AppContext) {
+    persistence::save_changes(&mut CounterPersister::new(ctx));
+}
+
+/// Saves the counters when they differ from the stored ones
+struct CounterPersister {
+    state: Arc<RwLock<DeviceState>>,
+    store: SharedCounterStore,
+    saved: Option<Counters>,
+}
+
+impl CounterPersister {
+    fn new(ctx: &AppContext) -> Self {
+        Self {
+            state: Arc::clone(ctx.observable_state.state()),
+            store: Arc::clone(&ctx.counters),
+            saved: ctx.counters.read().unwrap().load().ok(),
+        }
+    }
+}
+
+impl Persister for CounterPersister {
+    const NAME: &'static str = "counters";
+
+    fn save_changes(&mut self) -> Result<(), String> {
+        let counters = self.state.read().unwrap().counters();
+        if self.saved == Some(counters) {
+            return Ok(());
+        }
+        self.store.write().unwrap().save(counters)?;
+        self.saved = Some(counters);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_sequence_number_range() {
+        let counters = Counters {
+            tightening_id: u32::MAX,
+            job_sequence_number: MAX_JOB_SEQUENCE_NUMBER,
+        };
+        assert!(counters.validate().is_ok());
+        assert!(
+            Counters {
+                job_sequence_number: MAX_JOB_SEQUENCE_NUMBER + 1,
+                ..counters
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
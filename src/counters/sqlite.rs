@@ -0,0 +1,91 @@
+//! SQLite persistence for the counters (`sqlite` feature)
+
+use super::{CounterStore, Counters, SharedCounterStore};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params};
+use std::sync::{Arc, RwLock};
+
+/// SQLite-backed counter store, one row per counter
+pub struct SqliteCounterStore {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteCounterStore {
+    /// Open the counters table in the given database file
+    pub fn new(db_path: &str) -> Result<Self, String> {
+        let manager = SqliteConnectionManager::file(db_path);
+        let pool = Pool::new(manager).map_err(|e| format!("Failed to create pool: {}", e))?;
+
+        let store = Self { pool };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn init_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS counters (
+                name TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| format!("Failed to create table: {}", e))?;
+        Ok(())
+    }
+
+    fn connection(&self) -> Result<r2d2::PooledConnection<SqliteConnectionManager>, String> {
+        self.pool
+            .get()
+            .map_err(|e| format!("Failed to get connection: {}", e))
+    }
+
+    fn get(conn: &rusqlite::Connection, name: &str) -> Result<u32, String> {
+        conn.query_row(
+            "SELECT value FROM counters WHERE name = ?1",
+            params![name],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+        .map(|value| value.unwrap_or(0) as u32)
+        .map_err(|e| format!("Failed to read counter {}: {}", name, e))
+    }
+}
+
+impl CounterStore for SqliteCounterStore {
+    fn load(&self) -> Result<Counters, String> {
+        let conn = self.connection()?;
+        Ok(Counters {
+            tightening_id: Self::get(&conn, "tightening_id")?,
+            job_sequence_number: Self::get(&conn, "job_sequence_number")?,
+        })
+    }
+
+    fn save(&mut self, counters: Counters) -> Result<(), String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        for (name, value) in [
+            ("tightening_id", counters.tightening_id),
+            ("job_sequence_number", counters.job_sequence_number),
+        ] {
+            tx.execute(
+                "INSERT INTO counters (name, value) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET value = excluded.value",
+                params![name, value as i64],
+            )
+            .map_err(|e| format!("Failed to save counter {}: {}", name, e))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to save counters: {}", e))
+    }
+}
+
+/// Open the SQLite counter store in `db_path`
+pub fn create_sqlite_counter_store(db_path: &str) -> Result<SharedCounterStore, String> {
+    Ok(Arc::new(RwLock::new(Box::new(SqliteCounterStore::new(
+        db_path,
+    )?))))
+}
@@ -0,0 +1,137 @@
+//! Torque/angle traces of simulated tightenings
+//!
+//! A real controller samples torque and angle during the rundown; the
+//! simulator synthesizes an equivalent trace from the final values so client
+//! dashboards can plot something realistic. Curves are stored next to the
+//! results history and served by `GET /results/{id}/curve`.
+
+use crate::handler::data::TighteningResult;
+use serde::{Deserialize, Serialize};
+
+/// Fraction of the final angle at which the joint snugs and torque starts rising
+const SNUG_FRACTION: f64 = 0.6;
+
+/// Rundown (prevailing) torque before snug, as a fraction of the final torque
+const RUNDOWN_TORQUE_FRACTION: f64 = 0.08;
+
+/// One sample of a tightening trace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurvePoint {
+    /// Time since the start of the tightening in milliseconds
+    pub time_ms: f64,
+    /// Angle in degrees
+    pub angle: f64,
+    /// Torque in Nm
+    pub torque: f64,
+}
+
+/// Torque/angle trace of one tightening
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TighteningCurve {
+    pub tightening_id: u32,
+    pub points: Vec<CurvePoint>,
+}
+
+impl TighteningCurve {
+    /// Synthesize a trace ending at the result's final torque and angle
+    ///
+    /// The angle advances linearly over `duration_ms`; torque stays at a low
+    /// rundown level until snug and then rises linearly to the final torque.
+    /// `points` is clamped to at least two samples.
+    pub fn generate(result: &TighteningResult, duration_ms: u64, points: usize) -> Self {
+        let points = points.max(2);
+        let rundown_torque = result.torque * RUNDOWN_TORQUE_FRACTION;
+
+        let samples = (0..points)
+            .map(|i| {
+                let progress = i as f64 / (points - 1) as f64;
+                let torque = if progress < SNUG_FRACTION {
+                    rundown_torque * progress / SNUG_FRACTION
+                } else {
+                    // Counted back from the final torque so the last sample hits it exactly
+                    let remaining = (1.0 - progress) / (1.0 - SNUG_FRACTION);
+                    result.torque - (result.torque - rundown_torque) * remaining
+                };
+                CurvePoint {
+                    time_ms: duration_ms as f64 * progress,
+                    angle: result.angle * progress,
+                    torque,
+                }
+            })
+            .collect();
+
+        Self {
+            tightening_id: result.tightening_id.unwrap_or(0),
+            points: samples,
+        }
+    }
+
+    /// Copy of the curve reduced to at most `max_points` evenly spaced
+    /// samples (first and last sample are always kept)
+    pub fn downsample(&self, max_points: usize) -> Self {
+        let len = self.points.len();
+        if max_points >= len || len <= 2 {
+            return self.clone();
+        }
+        let max_points = max_points.max(2);
+        let points = (0..max_points)
+            .map(|i| self.points[i * (len - 1) / (max_points - 1)].clone())
+            .collect();
+        Self {
+            tightening_id: self.tightening_id,
+            points,
+        }
+    }
+
+    /// Render the samples as CSV
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("time_ms,angle,torque\n");
+        for point in &self.points {
+            out.push_str(&format!(
+                "{:.1},{:.2},{:.3}\n",
+                point.time_ms, point.angle, point.torque
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curve_ends_at_final_values() {
+        let result = TighteningResult::example();
+        let curve = TighteningCurve::generate(&result, 500, 101);
+
+        assert_eq!(curve.points.len(), 101);
+        assert_eq!(curve.tightening_id, 12345);
+        let first = &curve.points[0];
+        let last = curve.points.last().unwrap();
+        assert_eq!((first.time_ms, first.angle, first.torque), (0.0, 0.0, 0.0));
+        assert_eq!(last.time_ms, 500.0);
+        assert_eq!((last.angle, last.torque), (result.angle, result.torque));
+        assert!(
+            curve
+                .points
+                .windows(2)
+                .all(|pair| pair[1].torque >= pair[0].torque)
+        );
+    }
+
+    #[test]
+    fn test_downsample_keeps_endpoints() {
+        let curve = TighteningCurve::generate(&TighteningResult::example(), 500, 101);
+        let small = curve.downsample(5);
+
+        assert_eq!(small.points.len(), 5);
+        assert_eq!(small.points[0], curve.points[0]);
+        assert_eq!(small.points[4], curve.points[100]);
+        assert_eq!(curve.downsample(1000), curve);
+
+        let csv = small.to_csv();
+        assert_eq!(csv.lines().count(), 6);
+        assert!(csv.starts_with("time_ms,angle,torque\n0.0,0.00,0.000\n"));
+    }
+}
@@ -0,0 +1,207 @@
+//! Station takt and line-balancing statistics
+//!
+//! Cycle times are summarized from the results history, overall and per PSET
+//! and job, so OEE and line-balancing dashboards can be checked against known
+//! numbers. `GET /stats/cycle-time` computes the report on demand and, with
+//! `[stats] cycle_time_summary_secs`, a `CycleTimeSummary` event carries it
+//! periodically. Results without a cycle time (generated by the firehose or
+//! a dataset) are left out.
+//!
+//! Result timestamps have one-second resolution, so takt and idle ratio are
+//! only meaningful over runs of more than a few cycles.
+
+use crate::context::AppContext;
+use crate::events::SimulatorEvent;
+use crate::generator::RESULT_TIMESTAMP_FORMAT;
+use crate::handler::data::TighteningResult;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::task::AbortHandle;
+
+/// Cycle-time figures of a group of results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleTimeStats {
+    pub count: usize,
+    pub mean_ms: f64,
+    /// 95th percentile (nearest rank)
+    pub p95_ms: u64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    /// Mean time between consecutive results (None below two results)
+    pub takt_ms: Option<f64>,
+    /// Share of the time from the first cycle's start to the last cycle's
+    /// end not spent tightening (None below two results)
+    pub idle_ratio: Option<f64>,
+}
+
+/// Cycle-time statistics of the results history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleTimeReport {
+    /// None when no timed results are stored
+    pub overall: Option<CycleTimeStats>,
+    pub by_pset: BTreeMap<u32, CycleTimeStats>,
+    pub by_job: BTreeMap<u32, CycleTimeStats>,
+}
+
+/// A timed result: completion time and cycle time
+#[derive(Debug, Clone, Copy)]
+struct Cycle {
+    completed_at: NaiveDateTime,
+    duration_ms: u64,
+}
+
+impl CycleTimeStats {
+    /// Figures of `cycles`, which are in completion order; None if empty
+    fn from_cycles(cycles: &[Cycle]) -> Option<Self> {
+        let first = cycles.first()?;
+        let last = cycles.last()?;
+        let mut durations: Vec<u64> = cycles.iter().map(|cycle| cycle.duration_ms).collect();
+        durations.sort_unstable();
+        let count = durations.len();
+        let busy_ms: u64 = durations.iter().sum();
+        // Nearest rank: the smallest value with at least 95 % of the values at or below it
+        let p95_rank = (count * 95).div_ceil(100).max(1);
+
+        let (takt_ms, idle_ratio) = if count < 2 {
+            (None, None)
+        } else {
+            let span_ms = (last.completed_at - first.completed_at)
+                .num_milliseconds()
+                .max(0) as u64;
+            let elapsed_ms = span_ms + first.duration_ms;
+            let idle = 1.0 - busy_ms as f64 / elapsed_ms as f64;
+            (
+                Some(span_ms as f64 / (count - 1) as f64),
+                Some(idle.clamp(0.0, 1.0)),
+            )
+        };
+
+        Some(Self {
+            count,
+            mean_ms: busy_ms as f64 / count as f64,
+            p95_ms: durations[p95_rank - 1],
+            min_ms: durations[0],
+            max_ms: durations[count - 1],
+            takt_ms,
+            idle_ratio,
+        })
+    }
+}
+
+impl CycleTimeReport {
+    /// Summarize timed results, given oldest first
+    pub fn from_results(results: &[TighteningResult]) -> Self {
+        let mut all = Vec::new();
+        let mut by_pset: BTreeMap<u32, Vec<Cycle>> = BTreeMap::new();
+        let mut by_job: BTreeMap<u32, Vec<Cycle>> = BTreeMap::new();
+        for result in results.iter().filter(|result| result.cycle_time_ms > 0) {
+            let Ok(completed_at) =
+                NaiveDateTime::parse_from_str(&result.timestamp, RESULT_TIMESTAMP_FORMAT)
+            else {
+                continue;
+            };
+            let cycle = Cycle {
+                completed_at,
+                duration_ms: result.cycle_time_ms,
+            };
+            all.push(cycle);
+            by_pset.entry(result.pset_id).or_default().push(cycle);
+            by_job.entry(result.job_id).or_default().push(cycle);
+        }
+
+        let summarize = |groups: BTreeMap<u32, Vec<Cycle>>| {
+            groups
+                .into_iter()
+                .filter_map(|(id, cycles)| CycleTimeStats::from_cycles(&cycles).map(|s| (id, s)))
+                .collect()
+        };
+        Self {
+            overall: CycleTimeStats::from_cycles(&all),
+            by_pset: summarize(by_pset),
+            by_job: summarize(by_job),
+        }
+    }
+}
+
+/// Broadcast a `CycleTimeSummary` every `[stats] cycle_time_summary_secs`
+///
+/// Nothing is sent while the history holds no new results.
+pub fn spawn_cycle_time_summary(ctx: &AppContext) -> Option<AbortHandle> {
+    let interval_secs = ctx.settings.stats.cycle_time_summary_secs;
+    if interval_secs == 0 {
+        return None;
+    }
+    let results = ctx.result_repository.clone();
+    let observable_state = ctx.observable_state.clone();
+
+    let task = ctx.resources.track("cycle_time_summary");
+    let handle = tokio::spawn(async move {
+        let _task = task;
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        let mut last_summarized = None;
+        loop {
+            interval.tick().await;
+            let history = results.read().unwrap().get_all();
+            let newest = history.last().and_then(|result| result.tightening_id);
+            if newest.is_none() || newest == last_summarized {
+                continue;
+            }
+            last_summarized = newest;
+            let summary = CycleTimeReport::from_results(&history);
+            if summary.overall.is_some() {
+                observable_state.broadcast(SimulatorEvent::CycleTimeSummary { summary });
+            }
+        }
+    });
+    Some(handle.abort_handle())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(pset_id: u32, timestamp: &str, cycle_time_ms: u64) -> TighteningResult {
+        TighteningResult {
+            pset_id,
+            timestamp: timestamp.to_string(),
+            cycle_time_ms,
+            ..TighteningResult::example()
+        }
+    }
+
+    #[test]
+    fn test_takt_percentile_and_idle_ratio() {
+        let results = vec![
+            result(1, "2025-01-15:10:00:00", 1000),
+            result(2, "2025-01-15:10:00:02", 3000),
+            result(1, "2025-01-15:10:00:04", 1000),
+            result(1, "2025-01-15:10:00:06", 2000),
+            // Generated results are not timed
+            result(1, "2025-01-15:10:00:07", 0),
+        ];
+        let report = CycleTimeReport::from_results(&results);
+
+        let overall = report.overall.unwrap();
+        assert_eq!(overall.count, 4);
+        assert_eq!(overall.mean_ms, 1750.0);
+        assert_eq!(
+            (overall.min_ms, overall.p95_ms, overall.max_ms),
+            (1000, 3000, 3000)
+        );
+        assert_eq!(overall.takt_ms, Some(2000.0));
+        // 7 s from the first start to the last end, 7 s busy
+        assert_eq!(overall.idle_ratio, Some(0.0));
+
+        let pset1 = &report.by_pset[&1];
+        assert_eq!(pset1.count, 3);
+        assert_eq!(pset1.takt_ms, Some(3000.0));
+        // 7 s elapsed, 4 s busy
+        assert!((pset1.idle_ratio.unwrap() - 3.0 / 7.0).abs() < 1e-9);
+        assert_eq!(report.by_pset[&2].idle_ratio, None);
+        assert_eq!(report.by_job[&1].count, 4);
+
+        assert_eq!(CycleTimeReport::from_results(&[]).overall, None);
+    }
+}
@@ -0,0 +1,86 @@
+//! Frames a connection holds back for a while
+//!
+//! The load-latency model (`[broadcast_latency]`) and `[keep_alive]
+//! reply_delay_ms` make a connection send some frames later than it could.
+//! Waiting inline would stall everything else the connection does (client
+//! requests, close requests, ack deadlines), so the frames wait here with a
+//! deadline and the connection sends them from their own timer.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Items released in the order they were held, once their delay is over
+#[derive(Debug)]
+pub struct DelayQueue<T> {
+    queue: VecDeque<(Instant, T)>,
+}
+
+impl<T> Default for DelayQueue<T> {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> DelayQueue<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold `item` for `delay`; it never overtakes an item held earlier
+    pub fn hold(&mut self, item: T, delay: Duration, now: Instant) {
+        let due = self
+            .queue
+            .back()
+            .map_or(now + delay, |(last, _)| (now + delay).max(*last));
+        self.queue.push_back((due, item));
+    }
+
+    /// Items whose delay is over, in order
+    pub fn release(&mut self, now: Instant) -> Vec<T> {
+        let mut released = Vec::new();
+        while self.queue.front().is_some_and(|(due, _)| *due <= now) {
+            released.extend(self.queue.pop_front().map(|(_, item)| item));
+        }
+        released
+    }
+
+    /// When [`release`](Self::release) has something to send next, if anything waits
+    pub fn deadline(&self) -> Option<Instant> {
+        self.queue.front().map(|(due, _)| *due)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_items_leave_in_order_when_due() {
+        let start = Instant::now();
+        let mut queue = DelayQueue::new();
+        queue.hold("slow", Duration::from_millis(50), start);
+        queue.hold("fast", Duration::from_millis(10), start);
+        queue.hold(
+            "late",
+            Duration::from_millis(20),
+            start + Duration::from_millis(60),
+        );
+
+        // The fast item waits behind the slow one
+        assert_eq!(queue.deadline(), Some(start + Duration::from_millis(50)));
+        assert!(queue.release(start + Duration::from_millis(20)).is_empty());
+        assert_eq!(
+            queue.release(start + Duration::from_millis(50)),
+            ["slow", "fast"]
+        );
+        assert_eq!(queue.deadline(), Some(start + Duration::from_millis(80)));
+        assert_eq!(queue.release(start + Duration::from_millis(80)), ["late"]);
+        assert!(queue.is_empty());
+    }
+}
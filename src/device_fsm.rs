@@ -1,4 +1,6 @@
-use serde::Serialize;
+use crate::pset::{Pset, PsetStage, TighteningStrategy};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
 /// Idle state - device is waiting for a tightening operation
@@ -50,6 +52,10 @@ pub struct TighteningParams {
     pub angle_max: f64,
     /// Realistic duration of tightening cycle in milliseconds
     pub duration_ms: u64,
+    /// Which of torque/angle decide the overall OK/NOK
+    pub strategy: TighteningStrategy,
+    /// Intermediate steps of a multistage PSET, run before the final step
+    pub stages: Vec<PsetStage>,
 }
 
 impl TighteningParams {
@@ -63,6 +69,23 @@ impl TighteningParams {
             angle_min: 30.0,
             angle_max: 50.0,
             duration_ms: 1500, // 1.5 seconds
+            strategy: TighteningStrategy::default(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Parameters of a PSET: the targets are the middle of its limits
+    pub fn for_pset(pset: &Pset, duration_ms: u64) -> Self {
+        Self {
+            target_torque: (pset.torque_min + pset.torque_max) / 2.0,
+            torque_min: pset.torque_min,
+            torque_max: pset.torque_max,
+            target_angle: (pset.angle_min + pset.angle_max) / 2.0,
+            angle_min: pset.angle_min,
+            angle_max: pset.angle_max,
+            duration_ms,
+            strategy: pset.strategy,
+            stages: pset.stages.clone(),
         }
     }
 }
@@ -82,6 +105,208 @@ pub struct TighteningOutcome {
     pub torque_ok: bool,
     /// Angle within limits
     pub angle_ok: bool,
+    /// Per-stage results of a multistage tightening (the last entry is the
+    /// final step); empty for single-stage tightenings
+    pub stages: Vec<StageResult>,
+}
+
+impl TighteningOutcome {
+    /// Evaluate a tightening that lands at `0.95 + torque_variation` times the
+    /// target torque and `0.95 + angle_variation` times the target angle
+    ///
+    /// The variations are in `[0.0, 0.1)`, so values stay within +/- 5% of
+    /// the target. Intermediate stages get the same variation around the
+    /// middle of their own limits.
+    pub fn model(
+        params: &TighteningParams,
+        torque_variation: f64,
+        angle_variation: f64,
+        duration: Duration,
+    ) -> Self {
+        let actual_torque = params.target_torque * (0.95 + torque_variation);
+        let actual_angle = params.target_angle * (0.95 + angle_variation);
+
+        // Check if within acceptable limits
+        let torque_ok = actual_torque >= params.torque_min && actual_torque <= params.torque_max;
+        let angle_ok = actual_angle >= params.angle_min && actual_angle <= params.angle_max;
+        let final_ok = params.strategy.evaluate(torque_ok, angle_ok);
+
+        let mut stages: Vec<StageResult> = params
+            .stages
+            .iter()
+            .zip(1..)
+            .map(|(stage, number)| {
+                let torque =
+                    (stage.torque_min + stage.torque_max) / 2.0 * (0.95 + torque_variation);
+                let angle = (stage.angle_min + stage.angle_max) / 2.0 * (0.95 + angle_variation);
+                let torque_ok = torque >= stage.torque_min && torque <= stage.torque_max;
+                let angle_ok = angle >= stage.angle_min && angle <= stage.angle_max;
+                StageResult {
+                    stage: number,
+                    torque,
+                    angle,
+                    torque_ok,
+                    angle_ok,
+                    ok: params.strategy.evaluate(torque_ok, angle_ok),
+                }
+            })
+            .collect();
+        if !stages.is_empty() {
+            stages.push(StageResult {
+                stage: stages.len() as u32 + 1,
+                torque: actual_torque,
+                angle: actual_angle,
+                torque_ok,
+                angle_ok,
+                ok: final_ok,
+            });
+        }
+
+        TighteningOutcome {
+            actual_torque,
+            actual_angle,
+            duration,
+            ok: stages.iter().all(|stage| stage.ok) && final_ok,
+            torque_ok,
+            angle_ok,
+            stages,
+        }
+    }
+
+    /// Turn the outcome into a NOK that fails for `reason`
+    ///
+    /// Used when a NOK is forced (failure rate, manual override): the value
+    /// the reason names is pushed 5% beyond its limit so the reported torque
+    /// and angle agree with the status flags.
+    pub fn force_nok(&mut self, params: &TighteningParams, reason: NokReason) {
+        match reason {
+            NokReason::TorqueLow => {
+                self.actual_torque = below(params.torque_min);
+                self.torque_ok = false;
+            }
+            NokReason::TorqueHigh => {
+                self.actual_torque = above(params.torque_max);
+                self.torque_ok = false;
+            }
+            NokReason::AngleLow => {
+                self.actual_angle = below(params.angle_min);
+                self.angle_ok = false;
+            }
+            NokReason::AngleHigh => {
+                self.actual_angle = above(params.angle_max);
+                self.angle_ok = false;
+            }
+        }
+        self.ok = false;
+        self.update_final_stage();
+    }
+
+    /// Turn the outcome into an OK, clamping torque and angle (and those of
+    /// any intermediate stages) into their limits
+    pub fn force_ok(&mut self, params: &TighteningParams) {
+        self.actual_torque = self
+            .actual_torque
+            .clamp(params.torque_min, params.torque_max.max(params.torque_min));
+        self.actual_angle = self
+            .actual_angle
+            .clamp(params.angle_min, params.angle_max.max(params.angle_min));
+        self.torque_ok = true;
+        self.angle_ok = true;
+        self.ok = true;
+        for (stage, limits) in self.stages.iter_mut().zip(&params.stages) {
+            stage.torque = stage
+                .torque
+                .clamp(limits.torque_min, limits.torque_max.max(limits.torque_min));
+            stage.angle = stage
+                .angle
+                .clamp(limits.angle_min, limits.angle_max.max(limits.angle_min));
+            stage.torque_ok = true;
+            stage.angle_ok = true;
+            stage.ok = true;
+        }
+        self.update_final_stage();
+    }
+
+    /// Mirror the final values into the last stage entry
+    fn update_final_stage(&mut self) {
+        if let Some(last) = self.stages.last_mut() {
+            last.torque = self.actual_torque;
+            last.angle = self.actual_angle;
+            last.torque_ok = self.torque_ok;
+            last.angle_ok = self.angle_ok;
+            last.ok = self.ok;
+        }
+    }
+}
+
+/// A value 5% below a lower limit (at least 0.1 below it)
+fn below(limit: f64) -> f64 {
+    limit - (limit.abs() * 0.05).max(0.1)
+}
+
+/// A value 5% above an upper limit (at least 0.1 above it)
+fn above(limit: f64) -> f64 {
+    limit + (limit.abs() * 0.05).max(0.1)
+}
+
+/// Why a forced NOK failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NokReason {
+    TorqueLow,
+    TorqueHigh,
+    AngleLow,
+    AngleHigh,
+}
+
+impl NokReason {
+    /// Reasons that fail a tightening under the PSET's strategy
+    ///
+    /// A torque-controlled tightening only fails on torque, an
+    /// angle-controlled one only on angle. Low reasons are left out when the
+    /// lower limit is not positive, since the value would go negative.
+    pub fn candidates(params: &TighteningParams) -> Vec<NokReason> {
+        let torque = !matches!(
+            params.strategy,
+            TighteningStrategy::AngleControl | TighteningStrategy::Reverse
+        );
+        let angle = params.strategy != TighteningStrategy::TorqueControl;
+        let mut reasons = Vec::new();
+        if torque {
+            if params.torque_min > 0.0 {
+                reasons.push(NokReason::TorqueLow);
+            }
+            reasons.push(NokReason::TorqueHigh);
+        }
+        if angle {
+            if params.angle_min > 0.0 {
+                reasons.push(NokReason::AngleLow);
+            }
+            reasons.push(NokReason::AngleHigh);
+        }
+        reasons
+    }
+
+    /// Pick one of the candidate reasons at random
+    pub fn choose(params: &TighteningParams, rng: &mut impl Rng) -> NokReason {
+        let reasons = Self::candidates(params);
+        reasons[rng.random_range(0..reasons.len())]
+    }
+}
+
+/// Result of one stage of a multistage tightening
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageResult {
+    /// Stage number, starting at 1
+    pub stage: u32,
+    /// Torque at the end of the stage in Nm
+    pub torque: f64,
+    /// Angle turned during the stage in degrees
+    pub angle: f64,
+    pub torque_ok: bool,
+    pub angle_ok: bool,
+    /// Stage OK/NOK under the PSET's strategy
+    pub ok: bool,
 }
 
 /// Error codes for tightening operations
@@ -179,23 +404,9 @@ impl DeviceFSM<Tightening> {
         let variation2 = ((seed * 7) % 1000) as f64 / 1000.0 * 0.1;
 
         // Simulate realistic outcome with +/- 5% variation around target
-        let actual_torque = params.target_torque * (0.95 + variation1);
-        let actual_angle = params.target_angle * (0.95 + variation2);
-
-        // Check if within acceptable limits
-        let torque_ok = actual_torque >= params.torque_min && actual_torque <= params.torque_max;
-        let angle_ok = actual_angle >= params.angle_min && actual_angle <= params.angle_max;
-
         DeviceFSM {
             state: Evaluating {
-                result: TighteningOutcome {
-                    actual_torque,
-                    actual_angle,
-                    duration,
-                    ok: torque_ok && angle_ok,
-                    torque_ok,
-                    angle_ok,
-                },
+                result: TighteningOutcome::model(params, variation1, variation2, duration),
             },
         }
     }
@@ -229,6 +440,11 @@ impl DeviceFSM<Evaluating> {
         &self.state.result
     }
 
+    /// Get the tightening result for overriding its status
+    pub fn result_mut(&mut self) -> &mut TighteningOutcome {
+        &mut self.state.result
+    }
+
     /// Consume the result and return to Idle state
     /// Transitions: Evaluating → Idle
     ///
@@ -429,6 +645,8 @@ mod tests {
             angle_min: 39.99,
             angle_max: 40.01,
             duration_ms: 1000,
+            strategy: TighteningStrategy::TorqueAndAngle,
+            stages: Vec::new(),
         };
 
         let fsm = fsm.start_tightening(params);
@@ -444,6 +662,128 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_strategy_decides_which_values_count() {
+        // Angle window can never be met; torque window always is
+        let params = TighteningParams {
+            angle_min: 1000.0,
+            angle_max: 1001.0,
+            torque_min: 0.0,
+            torque_max: 100.0,
+            ..TighteningParams::default_test()
+        };
+
+        let torque_control = TighteningParams {
+            strategy: TighteningStrategy::TorqueControl,
+            ..params.clone()
+        };
+        let fsm = DeviceFSM::new().start_tightening(torque_control).complete();
+        assert!(!fsm.result().angle_ok);
+        assert!(fsm.result().ok);
+
+        let fsm = DeviceFSM::new().start_tightening(params).complete();
+        assert!(!fsm.result().ok);
+    }
+
+    #[test]
+    fn test_multistage_reports_every_stage() {
+        let params = TighteningParams {
+            // Inverted angle window: the first stage can never be OK
+            stages: vec![PsetStage {
+                torque_min: 0.0,
+                torque_max: 100.0,
+                angle_min: 50.0,
+                angle_max: 40.0,
+            }],
+            angle_min: 0.0,
+            angle_max: 100.0,
+            torque_min: 0.0,
+            torque_max: 100.0,
+            ..TighteningParams::default_test()
+        };
+
+        let fsm = DeviceFSM::new().start_tightening(params).complete();
+        let result = fsm.result();
+        let numbers: Vec<_> = result.stages.iter().map(|stage| stage.stage).collect();
+        assert_eq!(numbers, [1, 2]);
+        // The first stage misses its angle window, so the whole tightening is NOK
+        assert!(!result.stages[0].ok);
+        assert!(result.stages[1].ok);
+        assert!(!result.ok);
+    }
+
+    #[test]
+    fn test_forced_nok_values_match_reason() {
+        let params = TighteningParams::default_test();
+        for reason in NokReason::candidates(&params) {
+            let mut fsm = DeviceFSM::new().start_tightening(params.clone()).complete();
+            fsm.result_mut().force_nok(&params, reason);
+            let result = fsm.result();
+
+            let torque_in = result.actual_torque >= params.torque_min
+                && result.actual_torque <= params.torque_max;
+            let angle_in =
+                result.actual_angle >= params.angle_min && result.actual_angle <= params.angle_max;
+            assert_eq!(result.torque_ok, torque_in, "{reason:?}");
+            assert_eq!(result.angle_ok, angle_in, "{reason:?}");
+            assert!(!result.ok);
+            match reason {
+                NokReason::TorqueLow => assert!(result.actual_torque < params.torque_min),
+                NokReason::TorqueHigh => assert!(result.actual_torque > params.torque_max),
+                NokReason::AngleLow => assert!(result.actual_angle < params.angle_min),
+                NokReason::AngleHigh => assert!(result.actual_angle > params.angle_max),
+            }
+        }
+    }
+
+    #[test]
+    fn test_nok_reasons_follow_strategy() {
+        let torque_control = TighteningParams {
+            strategy: TighteningStrategy::TorqueControl,
+            ..TighteningParams::default_test()
+        };
+        assert_eq!(
+            NokReason::candidates(&torque_control),
+            [NokReason::TorqueLow, NokReason::TorqueHigh]
+        );
+
+        // A zero lower limit cannot be undershot without going negative
+        let angle_control = TighteningParams {
+            strategy: TighteningStrategy::AngleControl,
+            angle_min: 0.0,
+            ..TighteningParams::default_test()
+        };
+        assert_eq!(
+            NokReason::candidates(&angle_control),
+            [NokReason::AngleHigh]
+        );
+    }
+
+    #[test]
+    fn test_forced_nok_and_ok_update_final_stage() {
+        let params = TighteningParams {
+            stages: vec![PsetStage {
+                torque_min: 0.0,
+                torque_max: 100.0,
+                angle_min: 50.0,
+                angle_max: 40.0,
+            }],
+            ..TighteningParams::default_test()
+        };
+        let mut fsm = DeviceFSM::new().start_tightening(params.clone()).complete();
+
+        fsm.result_mut().force_nok(&params, NokReason::TorqueHigh);
+        let last = fsm.result().stages.last().unwrap().clone();
+        assert_eq!(last.torque, fsm.result().actual_torque);
+        assert!(!last.torque_ok && !last.ok);
+
+        fsm.result_mut().force_ok(&params);
+        let result = fsm.result();
+        assert!(result.ok && result.torque_ok && result.angle_ok);
+        assert!(result.actual_torque <= params.torque_max);
+        assert!(result.stages.iter().all(|stage| stage.ok));
+    }
+
     #[test]
     fn test_fsm_state_snapshot_idle() {
         let snapshot = DeviceFSMState::idle();
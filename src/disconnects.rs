@@ -0,0 +1,158 @@
+//! Controller-initiated disconnects
+//!
+//! Real controllers drop clients in more than one way: an orderly close
+//! after the last message, a reset when the controller restarts its network
+//! stack, or a half-closed socket that still accepts data. Client reconnect
+//! logic has to cope with all of them. `POST /connections/{id}/close` asks a
+//! connection to end with a [`CloseMode`]; with `[disconnects]
+//! mean_interval_secs` set, random clients are dropped on a schedule as well.
+
+use crate::config::DisconnectsConfig;
+use crate::context::AppContext;
+use crate::session_registry::{CloseMode, SessionSnapshot};
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::task::AbortHandle;
+
+/// Longest wait for the client to close a half-closed connection
+pub const HALF_CLOSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// End `stream` the way `mode` says
+///
+/// Pending data must have been flushed already for a graceful close. A
+/// half-close returns once the client closed its side (or after
+/// [`HALF_CLOSE_TIMEOUT`]); data it still sends is discarded.
+pub async fn terminate(mut stream: TcpStream, mode: CloseMode) -> std::io::Result<()> {
+    match mode {
+        CloseMode::Graceful => stream.shutdown().await,
+        CloseMode::Abort => {
+            // A zero linger makes the close send RST; nothing blocks on drop
+            #[allow(deprecated)]
+            stream.set_linger(Some(Duration::ZERO))
+        }
+        CloseMode::HalfClose => {
+            stream.shutdown().await?;
+            let drain = async {
+                let mut buf = [0u8; 4096];
+                while let Ok(read) = stream.read(&mut buf).await {
+                    if read == 0 {
+                        break;
+                    }
+                }
+            };
+            let _ = tokio::time::timeout(HALF_CLOSE_TIMEOUT, drain).await;
+            Ok(())
+        }
+    }
+}
+
+/// Time until the next random disconnect (exponentially distributed)
+pub fn next_delay<R: Rng>(mean_interval_secs: f64, rng: &mut R) -> Duration {
+    let uniform: f64 = rng.random();
+    Duration::from_secs_f64(-mean_interval_secs * (1.0 - uniform).ln())
+}
+
+/// Pick the client to drop and how
+pub fn pick<'a, R: Rng>(
+    sessions: &'a [SessionSnapshot],
+    modes: &[CloseMode],
+    rng: &mut R,
+) -> Option<(&'a SessionSnapshot, CloseMode)> {
+    if sessions.is_empty() || modes.is_empty() {
+        return None;
+    }
+    let session = &sessions[rng.random_range(0..sessions.len())];
+    let mode = modes[rng.random_range(0..modes.len())];
+    Some((session, mode))
+}
+
+/// Drop random clients per `[disconnects]`
+///
+/// Does nothing when `mean_interval_secs` is 0 or no mode is configured.
+pub fn spawn_random_disconnects(ctx: &AppContext) -> Option<AbortHandle> {
+    let DisconnectsConfig {
+        mean_interval_secs,
+        modes,
+    } = ctx.settings.disconnects.clone();
+    if mean_interval_secs <= 0.0 || modes.is_empty() {
+        return None;
+    }
+
+    let sessions = ctx.sessions.clone();
+    let task = ctx.resources.track("random_disconnects");
+    let handle = tokio::spawn(async move {
+        let _task = task;
+        let mut rng = StdRng::from_os_rng();
+        loop {
+            tokio::time::sleep(next_delay(mean_interval_secs, &mut rng)).await;
+            let snapshot = sessions.snapshot();
+            if let Some((session, mode)) = pick(&snapshot, &modes, &mut rng) {
+                println!(
+                    "Random disconnect of client {} ({})",
+                    session.addr,
+                    mode.as_str()
+                );
+                sessions.request_close(session.id, mode);
+            }
+        }
+    });
+    Some(handle.abort_handle())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Connected (server side, client side) pair
+    async fn pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn test_close_modes_as_seen_by_the_client() {
+        let mut buf = [0u8; 16];
+
+        let (server, mut client) = pair().await;
+        terminate(server, CloseMode::Graceful).await.unwrap();
+        assert_eq!(client.read(&mut buf).await.unwrap(), 0, "FIN");
+
+        let (server, mut client) = pair().await;
+        terminate(server, CloseMode::Abort).await.unwrap();
+        let err = client.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+
+        let (server, mut client) = pair().await;
+        let closing = tokio::spawn(terminate(server, CloseMode::HalfClose));
+        assert_eq!(client.read(&mut buf).await.unwrap(), 0, "FIN");
+        // The controller still reads until the client closes its side
+        client.write_all(b"00209999001         \0").await.unwrap();
+        assert!(!closing.is_finished());
+        client.shutdown().await.unwrap();
+        closing.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!(pick(&[], &[CloseMode::Abort], &mut rng).is_none());
+
+        let registry = crate::session_registry::SessionRegistry::new();
+        registry.register("127.0.0.1:5000".parse().unwrap());
+        let sessions = registry.snapshot();
+        assert!(pick(&sessions, &[], &mut rng).is_none());
+        let (session, mode) = pick(&sessions, &[CloseMode::HalfClose], &mut rng).unwrap();
+        assert_eq!(session.id, sessions[0].id);
+        assert_eq!(mode, CloseMode::HalfClose);
+        assert!(next_delay(10.0, &mut rng) < Duration::from_secs(1000));
+    }
+}
@@ -1,27 +1,92 @@
-use crate::handler::data::TighteningResult;
-use crate::multi_spindle::{MultiSpindleResult, MultiSpindleStatus};
+use crate::alarms::{AckSource, AlarmSeverity};
+use crate::cycle_time::CycleTimeReport;
+use crate::handler::data::{
+    AlarmAcknowledgedBroadcast, AlarmBroadcast, JobInfo, LockAtBatchDoneUpload,
+    MultiSpindleResultBroadcast, MultiSpindleStatusBroadcast, PsetSelected, TighteningResult,
+    UserDataUpload, VehicleIdBroadcast,
+};
+use crate::interlock::{InterlockWait, ReleaseSource};
+use crate::multi_spindle::{MultiSpindleResult, MultiSpindleStatus, SpindlePhase};
+use crate::protocol::{Mid, ResponseData, WireFormat};
+use crate::session_registry::{DeliveryId, DisconnectReason, SessionId};
+use crate::state::{ControllerIdentity, MaintenanceNotice};
+use crate::subscriptions::SubscriptionKind;
+use crate::telemetry::Telemetry;
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use tokio::sync::broadcast;
 
 /// Events that can be broadcast to all connected clients
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[allow(dead_code)]
+#[allow(clippy::large_enum_variant)] // Results are cloned per subscriber anyway
 pub enum SimulatorEvent {
     /// A tightening operation was completed
-    TighteningCompleted { result: TighteningResult },
+    TighteningCompleted {
+        result: TighteningResult,
+        /// Set when the emitter waits for delivery receipts
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delivery: Option<DeliveryId>,
+    },
+
+    /// A stored result was voided (`POST /results/{id}/void`); carries the
+    /// corrected result, which is resent on MID 0061 under its tightening ID
+    ResultVoided { result: TighteningResult },
 
     /// A parameter set was selected
-    PsetChanged { pset_id: u32, pset_name: String },
+    PsetChanged {
+        pset_id: u32,
+        pset_name: String,
+        /// Date of the last change in the PSET settings
+        #[serde(default)]
+        last_change: String,
+    },
+
+    /// A job was selected (MID 0038); its first PSET is selected with the
+    /// step's batch size. Pushed to MID 0034 subscribers as MID 0035
+    JobSelected {
+        job_id: u32,
+        job_name: String,
+        pset_id: u32,
+        batch_size: u32,
+        /// Tightenings needed to finish the job
+        #[serde(default)]
+        job_batch_size: u32,
+    },
+
+    /// The selected job was restarted (MID 0039): the batch counters of all
+    /// its steps are reset and its first PSET is selected again. Pushed to
+    /// MID 0034 subscribers as MID 0035
+    JobRestarted {
+        job_id: u32,
+        job_name: String,
+        pset_id: u32,
+        batch_size: u32,
+        job_batch_size: u32,
+    },
 
     /// Tool state changed (enabled/disabled)
     ToolStateChanged { enabled: bool },
 
-    /// Batch was completed
-    BatchCompleted { total: u32 },
+    /// Batch was completed; pushed to MID 0021 subscribers as MID 0022
+    BatchCompleted {
+        total: u32,
+        /// The tool was disabled (`[defaults] lock_at_batch_done`)
+        #[serde(default)]
+        tool_locked: bool,
+    },
+
+    /// Batch counter was reset to 0 by MID 0020; the batch size is unchanged
+    BatchReset { pset_id: u32, batch_size: u32 },
 
     /// Vehicle ID was changed
-    VehicleIdChanged { vin: String },
+    VehicleIdChanged {
+        vin: String,
+        /// Cycle the VIN belongs to (claimed by the next tightening)
+        #[serde(default)]
+        cycle_id: u64,
+    },
 
     /// Multi-spindle status update completed
     MultiSpindleStatusCompleted { status: MultiSpindleStatus },
@@ -29,12 +94,325 @@ pub enum SimulatorEvent {
     /// Multi-spindle tightening result completed
     MultiSpindleResultCompleted { result: MultiSpindleResult },
 
+    /// Torque sample of one spindle during an auto multi-spindle cycle
+    SpindleProgress {
+        sync_id: u32,
+        spindle_id: u8,
+        phase: SpindlePhase,
+        /// Torque in Nm
+        torque: f64,
+        /// Spindle status, once completed
+        ok: Option<bool>,
+    },
+
     /// Auto-tightening progress update
     AutoTighteningProgress {
         counter: u32,
         target_size: u32,
         running: bool,
     },
+
+    /// Controller alarm raised (MID 0071)
+    AlarmRaised {
+        id: u64,
+        error_code: String,
+        description: String,
+        /// Alarm text in the controller language
+        text: String,
+        severity: AlarmSeverity,
+        tool_ready: bool,
+    },
+
+    /// Alarm acknowledged on the controller or by a client (MID 0074)
+    AlarmAcknowledged {
+        id: u64,
+        error_code: String,
+        source: AckSource,
+    },
+
+    /// Alarm cleared
+    AlarmCleared { id: u64, error_code: String },
+
+    /// Operator logged in (Some) or out (None)
+    OperatorChanged { operator_id: Option<String> },
+
+    /// Periodic telemetry sample
+    Telemetry { telemetry: Telemetry },
+
+    /// Controller is rebooting for a firmware upgrade; TCP clients are dropped
+    ControllerRebooting {
+        software_version: String,
+        reboot_ms: u64,
+    },
+
+    /// Controller is back up after a firmware upgrade
+    ControllerRebooted { software_version: String },
+
+    /// A firmware upgrade or outage starts after the notice period; clients
+    /// see the connection drop as planned rather than as a failure
+    MaintenanceImminent { notice: MaintenanceNotice },
+
+    /// Cell, channel, controller name or supplier code changed; MID 0002 and
+    /// results from now on carry the new identity
+    IdentityChanged { identity: ControllerIdentity },
+
+    /// A state field without an event of its own was changed by `PATCH /state`
+    StateChanged {
+        field: String,
+        value: serde_json::Value,
+    },
+
+    /// An Open Protocol client connected
+    ClientConnected {
+        session_id: SessionId,
+        addr: SocketAddr,
+    },
+
+    /// A client started communication (MID 0001 answered with MID 0002)
+    ClientAuthenticated {
+        session_id: SessionId,
+        addr: SocketAddr,
+    },
+
+    /// A client subscribed to a data MID family
+    ClientSubscribed {
+        session_id: SessionId,
+        kind: SubscriptionKind,
+    },
+
+    /// An Open Protocol client connection ended
+    ClientDisconnected {
+        session_id: SessionId,
+        addr: SocketAddr,
+        reason: DisconnectReason,
+    },
+
+    /// Periodic cycle-time statistics of the results history
+    CycleTimeSummary { summary: CycleTimeReport },
+
+    /// An interlocked job position completed; the tool stays disabled until
+    /// the next position is released
+    InterlockWaiting { wait: InterlockWait },
+
+    /// The next position of an interlocked job was released
+    InterlockReleased {
+        wait: InterlockWait,
+        source: ReleaseSource,
+    },
+
+    /// A user data slot was written or cleared (empty `data`)
+    UserDataChanged { slot: u16, data: String },
+
+    /// A client selected a PSET (MID 0018) over the one another connected
+    /// client selected (global identification scope)
+    PsetConflict {
+        session_id: SessionId,
+        pset_id: u32,
+        overruled_session_id: SessionId,
+        overruled_pset_id: u32,
+    },
+}
+
+/// Parameter 99 carrying `cycle_id` (ten digits, wrapping), as appended to
+/// data MIDs with `[device] stamp_cycle_id`
+pub fn cycle_id_field(cycle_id: u64) -> Vec<u8> {
+    format!("99{:010}", cycle_id % 10_000_000_000).into_bytes()
+}
+
+impl SimulatorEvent {
+    /// Delivery this event is tracked under, if the emitter asked for receipts
+    pub fn delivery_id(&self) -> Option<DeliveryId> {
+        match self {
+            SimulatorEvent::TighteningCompleted { delivery, .. } => *delivery,
+            _ => None,
+        }
+    }
+
+    /// Cycle this event belongs to (see `DeviceState::claim_cycle`)
+    pub fn cycle_id(&self) -> Option<u64> {
+        match self {
+            SimulatorEvent::TighteningCompleted { result, .. }
+            | SimulatorEvent::ResultVoided { result } => Some(result.cycle_id),
+            SimulatorEvent::VehicleIdChanged { cycle_id, .. } => Some(*cycle_id),
+            SimulatorEvent::MultiSpindleStatusCompleted { status } => Some(status.cycle_id),
+            SimulatorEvent::MultiSpindleResultCompleted { result } => Some(result.cycle_id),
+            _ => None,
+        }
+        .filter(|&cycle_id| cycle_id != 0)
+    }
+
+    /// Subscription stream this event is pushed on
+    ///
+    /// The data MID is the one the MID table lists for the returned kind;
+    /// events without an Open Protocol counterpart return `None`.
+    pub fn subscription_kind(&self) -> Option<SubscriptionKind> {
+        match self {
+            SimulatorEvent::TighteningCompleted { .. } | SimulatorEvent::ResultVoided { .. } => {
+                Some(SubscriptionKind::TighteningResult)
+            }
+            SimulatorEvent::PsetChanged { .. } => Some(SubscriptionKind::PsetSelection),
+            SimulatorEvent::VehicleIdChanged { .. } => Some(SubscriptionKind::VehicleId),
+            SimulatorEvent::MultiSpindleStatusCompleted { .. } => {
+                Some(SubscriptionKind::MultiSpindleStatus)
+            }
+            SimulatorEvent::MultiSpindleResultCompleted { .. } => {
+                Some(SubscriptionKind::MultiSpindleResult)
+            }
+            SimulatorEvent::AlarmRaised { .. } | SimulatorEvent::AlarmAcknowledged { .. } => {
+                Some(SubscriptionKind::Alarm)
+            }
+            SimulatorEvent::UserDataChanged { .. } => Some(SubscriptionKind::UserData),
+            SimulatorEvent::BatchCompleted { .. } => Some(SubscriptionKind::LockAtBatchDone),
+            SimulatorEvent::JobSelected { .. } | SimulatorEvent::JobRestarted { .. } => {
+                Some(SubscriptionKind::JobInfo)
+            }
+            SimulatorEvent::ToolStateChanged { .. }
+            | SimulatorEvent::AlarmCleared { .. }
+            | SimulatorEvent::BatchReset { .. }
+            | SimulatorEvent::AutoTighteningProgress { .. }
+            | SimulatorEvent::SpindleProgress { .. }
+            | SimulatorEvent::OperatorChanged { .. }
+            | SimulatorEvent::Telemetry { .. }
+            | SimulatorEvent::ControllerRebooting { .. }
+            | SimulatorEvent::ControllerRebooted { .. }
+            | SimulatorEvent::MaintenanceImminent { .. }
+            | SimulatorEvent::IdentityChanged { .. }
+            | SimulatorEvent::StateChanged { .. }
+            | SimulatorEvent::ClientConnected { .. }
+            | SimulatorEvent::ClientAuthenticated { .. }
+            | SimulatorEvent::ClientSubscribed { .. }
+            | SimulatorEvent::ClientDisconnected { .. }
+            | SimulatorEvent::CycleTimeSummary { .. }
+            | SimulatorEvent::InterlockWaiting { .. }
+            | SimulatorEvent::InterlockReleased { .. }
+            | SimulatorEvent::PsetConflict { .. } => None,
+        }
+    }
+
+    /// MID this event is pushed as
+    ///
+    /// Usually the data MID of its subscription family; alarm
+    /// acknowledgments travel on the alarm subscription as MID 0074.
+    pub fn data_mid(&self) -> Option<Mid> {
+        match self {
+            SimulatorEvent::AlarmAcknowledged { .. } => Some(Mid::ALARM_ACKNOWLEDGED),
+            _ => self.subscription_kind().map(SubscriptionKind::data_mid),
+        }
+    }
+
+    /// Serialized data MID payload for a subscriber that asked for `revision`
+    ///
+    /// Returns the revision actually produced (the highest supported one not
+    /// above the requested revision) together with the data. MID 0061 and
+    /// MID 0071 have more than one revision so far. A payload whose values do not fit
+    /// their fields is logged and not pushed.
+    pub fn subscription_payload(&self, revision: u16) -> Option<(u16, Vec<u8>)> {
+        let (revision, data) = match self {
+            SimulatorEvent::TighteningCompleted { result, .. }
+            | SimulatorEvent::ResultVoided { result } => {
+                let revision = TighteningResult::supported_revision(revision);
+                let format = WireFormat::current().for_mid(Mid::TIGHTENING_RESULT);
+                (
+                    revision,
+                    format.sync_scope(|| result.serialize_revision(revision)),
+                )
+            }
+
+            SimulatorEvent::PsetChanged {
+                pset_id,
+                last_change,
+                ..
+            } => (
+                1,
+                PsetSelected::new(*pset_id, last_change.clone()).serialize(),
+            ),
+            SimulatorEvent::VehicleIdChanged { vin, .. } => {
+                (1, VehicleIdBroadcast::new(vin.clone()).serialize())
+            }
+            SimulatorEvent::MultiSpindleStatusCompleted { status } => (
+                1,
+                MultiSpindleStatusBroadcast::new(status.clone()).serialize(),
+            ),
+            SimulatorEvent::MultiSpindleResultCompleted { result } => {
+                let broadcast = MultiSpindleResultBroadcast::new(
+                    result.clone(),
+                    String::new(), // VIN (not available in session context)
+                    1,             // job_id
+                    1,             // pset_id
+                    0,             // batch_size
+                    0,             // batch_counter
+                    2,             // batch_status
+                );
+                let format = WireFormat::current().for_mid(Mid::MULTI_SPINDLE_RESULT);
+                (1, format.sync_scope(|| broadcast.serialize()))
+            }
+            SimulatorEvent::AlarmRaised {
+                error_code,
+                text,
+                tool_ready,
+                ..
+            } => {
+                let revision = revision.clamp(1, AlarmBroadcast::MAX_REVISION);
+                let broadcast =
+                    AlarmBroadcast::new(error_code.clone(), true, *tool_ready, text.clone());
+                (revision, broadcast.serialize_revision(revision))
+            }
+            SimulatorEvent::AlarmAcknowledged { error_code, .. } => (
+                1,
+                AlarmAcknowledgedBroadcast::new(error_code.clone()).serialize(),
+            ),
+            SimulatorEvent::UserDataChanged { slot, data } => {
+                (1, UserDataUpload::new(*slot, data.clone()).serialize())
+            }
+            SimulatorEvent::BatchCompleted { tool_locked, .. } => {
+                (1, LockAtBatchDoneUpload::new(*tool_locked).serialize())
+            }
+            SimulatorEvent::JobSelected {
+                job_id,
+                job_batch_size,
+                ..
+            }
+            | SimulatorEvent::JobRestarted {
+                job_id,
+                job_batch_size,
+                ..
+            } => {
+                let revision = revision.clamp(1, JobInfo::MAX_REVISION);
+                let info = JobInfo::started(*job_id, *job_batch_size);
+                (revision, info.serialize_revision(revision))
+            }
+            SimulatorEvent::ToolStateChanged { .. }
+            | SimulatorEvent::AlarmCleared { .. }
+            | SimulatorEvent::BatchReset { .. }
+            | SimulatorEvent::AutoTighteningProgress { .. }
+            | SimulatorEvent::SpindleProgress { .. }
+            | SimulatorEvent::OperatorChanged { .. }
+            | SimulatorEvent::Telemetry { .. }
+            | SimulatorEvent::ControllerRebooting { .. }
+            | SimulatorEvent::ControllerRebooted { .. }
+            | SimulatorEvent::MaintenanceImminent { .. }
+            | SimulatorEvent::IdentityChanged { .. }
+            | SimulatorEvent::StateChanged { .. }
+            | SimulatorEvent::ClientConnected { .. }
+            | SimulatorEvent::ClientAuthenticated { .. }
+            | SimulatorEvent::ClientSubscribed { .. }
+            | SimulatorEvent::ClientDisconnected { .. }
+            | SimulatorEvent::CycleTimeSummary { .. }
+            | SimulatorEvent::InterlockWaiting { .. }
+            | SimulatorEvent::InterlockReleased { .. }
+            | SimulatorEvent::PsetConflict { .. } => return None,
+        };
+        match data {
+            Ok(data) => Some((revision, data)),
+            Err(e) => {
+                eprintln!(
+                    "Cannot serialize {:?} payload: {e}",
+                    self.subscription_kind()
+                );
+                None
+            }
+        }
+    }
 }
 
 /// Type alias for the event broadcaster (sender side)
@@ -43,3 +421,83 @@ pub type EventBroadcaster = broadcast::Sender<SimulatorEvent>;
 /// Type alias for event receivers (subscriber side)
 #[allow(dead_code)]
 pub type EventReceiver = broadcast::Receiver<SimulatorEvent>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tightening_payload_follows_subscribed_revision() {
+        let event = SimulatorEvent::TighteningCompleted {
+            result: TighteningResult::example(),
+            delivery: None,
+        };
+        let (revision, data) = event.subscription_payload(5).unwrap();
+        assert_eq!(revision, TighteningResult::MAX_REVISION);
+        assert_eq!(
+            data,
+            TighteningResult::example()
+                .serialize_revision(revision)
+                .unwrap()
+        );
+        assert_eq!(event.subscription_payload(1).unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_subscription_payload_routes_by_kind() {
+        let event = SimulatorEvent::PsetChanged {
+            pset_id: 3,
+            pset_name: "Pset 3".to_string(),
+            last_change: "2024-01-15:09:00:00".to_string(),
+        };
+        let kind = event.subscription_kind().unwrap();
+        assert_eq!(kind, SubscriptionKind::PsetSelection);
+        assert_eq!(kind.data_mid(), Mid::PSET_SELECTED);
+        assert_eq!(
+            event.subscription_payload(3),
+            Some((1, b"01003022024-01-15:09:00:00".to_vec()))
+        );
+
+        let event = SimulatorEvent::AlarmAcknowledged {
+            id: 1,
+            error_code: "E501".to_string(),
+            source: AckSource::Http,
+        };
+        assert_eq!(event.subscription_kind(), Some(SubscriptionKind::Alarm));
+        assert_eq!(event.data_mid(), Some(Mid::ALARM_ACKNOWLEDGED));
+        assert_eq!(event.subscription_payload(1), Some((1, b"01E501".to_vec())));
+
+        let event = SimulatorEvent::JobRestarted {
+            job_id: 2,
+            job_name: "Heavy Assembly".to_string(),
+            pset_id: 3,
+            batch_size: 6,
+            job_batch_size: 8,
+        };
+        assert_eq!(event.data_mid(), Some(Mid::JOB_INFO));
+        let (revision, data) = event.subscription_payload(3).unwrap();
+        assert_eq!(revision, 2);
+        assert!(data.starts_with(b"010002020030040008050000"));
+
+        let event = SimulatorEvent::ToolStateChanged { enabled: true };
+        assert!(event.subscription_kind().is_none());
+        assert!(event.data_mid().is_none());
+        assert!(event.subscription_payload(1).is_none());
+    }
+
+    #[test]
+    fn test_cycle_id() {
+        let event = SimulatorEvent::VehicleIdChanged {
+            vin: "VIN1".to_string(),
+            cycle_id: 12,
+        };
+        assert_eq!(event.cycle_id(), Some(12));
+        let event = SimulatorEvent::TighteningCompleted {
+            result: TighteningResult::example(),
+            delivery: None,
+        };
+        assert_eq!(event.cycle_id(), None, "not claimed");
+        assert_eq!(cycle_id_field(12), b"990000000012");
+        assert_eq!(cycle_id_field(10_000_000_001), b"990000000001");
+    }
+}
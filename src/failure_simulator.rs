@@ -1,9 +1,16 @@
+use chrono::{DateTime, Local, SecondsFormat};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Injections kept for `GET /debug/injection-log`
+pub const INJECTION_LOG_CAPACITY: usize = 1000;
 
 /// Configuration for communication failure injection
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FailureConfig {
     /// Master enable/disable for all failure injection
     pub enabled: bool,
@@ -29,6 +36,68 @@ pub struct FailureConfig {
     /// Force disconnect rate (0.0-1.0)
     /// Probability that the connection will be forcefully dropped
     pub force_disconnect_rate: f64,
+
+    /// Link bandwidth of each connection (None = unlimited)
+    #[serde(default)]
+    pub bandwidth: Option<BandwidthLimit>,
+}
+
+/// Token-bucket bandwidth of a simulated slow link
+///
+/// Every connection gets its own bucket, so each client sees the limit on
+/// its own link. Frames are never dropped; they wait until the link has
+/// transmitted the bytes before them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BandwidthLimit {
+    /// Sustained throughput per connection
+    pub bytes_per_sec: u64,
+    /// Bytes a connection may send back to back before the rate applies (default: 0)
+    #[serde(default)]
+    pub burst_bytes: u64,
+}
+
+/// Bandwidth bucket of one connection
+///
+/// The bucket may go into debt: a frame larger than the available tokens is
+/// sent after the time the link needs to transmit the missing bytes.
+#[derive(Debug, Clone)]
+pub struct LinkShaper {
+    limit: Option<BandwidthLimit>,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl LinkShaper {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            limit: None,
+            tokens: 0.0,
+            refilled_at: now,
+        }
+    }
+
+    /// How long a frame of `len` bytes waits for the link; the bucket
+    /// restarts full when the limit changes
+    pub fn delay(&mut self, limit: BandwidthLimit, len: usize, now: Instant) -> Duration {
+        let rate = limit.bytes_per_sec.max(1) as f64;
+        let burst = limit.burst_bytes as f64;
+        if self.limit != Some(limit) {
+            self.limit = Some(limit);
+            self.tokens = burst;
+        } else {
+            let elapsed = now
+                .saturating_duration_since(self.refilled_at)
+                .as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(burst);
+        }
+        self.refilled_at = now;
+        self.tokens -= len as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
 }
 
 impl Default for FailureConfig {
@@ -41,6 +110,7 @@ impl Default for FailureConfig {
             delay_max_ms: 0,
             corruption_rate: 0.0,
             force_disconnect_rate: 0.0,
+            bandwidth: None,
         }
     }
 }
@@ -79,6 +149,7 @@ impl FailureConfig {
             delay_max_ms: max_delay,
             corruption_rate: corruption,
             force_disconnect_rate: disconnect,
+            bandwidth: None,
         }
     }
 
@@ -97,6 +168,215 @@ impl FailureConfig {
             && self.force_disconnect_rate >= 0.0
             && self.force_disconnect_rate <= 1.0
             && self.delay_min_ms <= self.delay_max_ms
+            && self.bandwidth.is_none_or(|limit| limit.bytes_per_sec > 0)
+    }
+
+    /// Bandwidth limit in effect, if injection is enabled
+    pub fn active_bandwidth(&self) -> Option<BandwidthLimit> {
+        self.bandwidth.filter(|_| self.enabled)
+    }
+}
+
+/// What failure injection does with one outgoing frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum Injection {
+    /// Send `bytes` after `delay`; `corrupted` tells whether they were altered
+    Send {
+        bytes: Vec<u8>,
+        delay: Duration,
+        corrupted: bool,
+    },
+    /// Drop the frame (packet loss)
+    Drop,
+    /// Close the connection instead of sending
+    Disconnect,
+}
+
+impl Injection {
+    /// What the injection does to the frame, `None` if it is sent untouched
+    pub fn action(&self) -> Option<InjectionAction> {
+        match self {
+            Injection::Disconnect => Some(InjectionAction::Disconnected),
+            Injection::Drop => Some(InjectionAction::Dropped),
+            Injection::Send {
+                corrupted: true, ..
+            } => Some(InjectionAction::Corrupted),
+            Injection::Send { delay, .. } if !delay.is_zero() => Some(InjectionAction::Delayed),
+            Injection::Send { .. } => None,
+        }
+    }
+
+    /// Injected delay (zero unless the frame is sent)
+    pub fn delay(&self) -> Duration {
+        match self {
+            Injection::Send { delay, .. } => *delay,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Action taken on a frame, as logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InjectionAction {
+    /// Sent late, otherwise unchanged
+    Delayed,
+    /// Sent altered (possibly also late)
+    Corrupted,
+    Dropped,
+    /// The connection was closed instead of sending
+    Disconnected,
+}
+
+/// One frame failure injection interfered with
+#[derive(Debug, Clone, Serialize)]
+pub struct InjectionRecord {
+    /// Sequence number, increasing for the lifetime of the simulator
+    pub seq: u64,
+    /// Client the frame was meant for
+    pub connection: SocketAddr,
+    /// MID from the frame header, when it parses
+    pub mid: Option<u16>,
+    /// What was being sent, e.g. "MID 61 broadcast"
+    pub context: String,
+    pub action: InjectionAction,
+    pub delay_ms: u64,
+    /// When the injection was decided (RFC 3339 with milliseconds)
+    pub decided_at: String,
+    /// Decision time plus the injected delay; absent for drops and disconnects
+    pub planned_send_at: Option<String>,
+    /// When the frame was written to the socket; absent if it never was
+    pub actual_send_at: Option<String>,
+    /// Actual minus planned send time
+    pub lateness_ms: Option<i64>,
+}
+
+/// An injection in progress, logged once the frame has been sent or discarded
+#[derive(Debug)]
+pub struct InjectionTrace {
+    connection: SocketAddr,
+    mid: Option<u16>,
+    context: String,
+    action: InjectionAction,
+    delay: Duration,
+    decided_at: DateTime<Local>,
+}
+
+impl InjectionTrace {
+    /// Start tracing `injection` of a frame; `None` if it leaves the frame alone
+    pub fn new(
+        connection: SocketAddr,
+        frame: &[u8],
+        context: &str,
+        injection: &Injection,
+    ) -> Option<Self> {
+        Some(Self {
+            connection,
+            mid: frame_mid(frame),
+            context: context.to_string(),
+            action: injection.action()?,
+            delay: injection.delay(),
+            decided_at: Local::now(),
+        })
+    }
+}
+
+/// MID of a frame from its header
+fn frame_mid(frame: &[u8]) -> Option<u16> {
+    std::str::from_utf8(frame.get(4..8)?).ok()?.parse().ok()
+}
+
+fn timestamp(time: DateTime<Local>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+#[derive(Debug)]
+struct InjectionLogInner {
+    entries: VecDeque<InjectionRecord>,
+    capacity: usize,
+    next_seq: u64,
+}
+
+/// Bounded log of the frames failure injection delayed, corrupted or discarded
+#[derive(Debug)]
+pub struct InjectionLog {
+    inner: Mutex<InjectionLogInner>,
+}
+
+/// Injection log shared by the connections and the HTTP layer
+pub type SharedInjectionLog = Arc<InjectionLog>;
+
+impl InjectionLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(InjectionLogInner {
+                entries: VecDeque::new(),
+                capacity: capacity.max(1),
+                next_seq: 1,
+            }),
+        }
+    }
+
+    /// Log a traced injection; `sent` tells whether the frame went out (now)
+    ///
+    /// Frames injection left alone have no trace and are not logged.
+    pub fn finish(&self, trace: Option<InjectionTrace>, sent: bool) {
+        let Some(trace) = trace else {
+            return;
+        };
+        let planned = (trace.action == InjectionAction::Delayed
+            || trace.action == InjectionAction::Corrupted)
+            .then(|| trace.decided_at + trace.delay);
+        let actual = sent.then(Local::now);
+        let lateness_ms = planned
+            .zip(actual)
+            .map(|(planned, actual)| (actual - planned).num_milliseconds());
+
+        let mut inner = self.inner.lock().unwrap();
+        let record = InjectionRecord {
+            seq: inner.next_seq,
+            connection: trace.connection,
+            mid: trace.mid,
+            context: trace.context,
+            action: trace.action,
+            delay_ms: trace.delay.as_millis() as u64,
+            decided_at: timestamp(trace.decided_at),
+            planned_send_at: planned.map(timestamp),
+            actual_send_at: actual.map(timestamp),
+            lateness_ms,
+        };
+        inner.next_seq += 1;
+        if inner.entries.len() >= inner.capacity {
+            inner.entries.pop_front();
+        }
+        inner.entries.push_back(record);
+    }
+
+    /// Number of logged injections
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Logged injections with a sequence number greater than `after_seq`, oldest first
+    pub fn since(&self, after_seq: u64) -> Vec<InjectionRecord> {
+        self.inner
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|e| e.seq > after_seq)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for InjectionLog {
+    fn default() -> Self {
+        Self::new(INJECTION_LOG_CAPACITY)
     }
 }
 
@@ -217,6 +497,31 @@ impl FailureSimulator {
         corrupted
     }
 
+    /// Make all decisions for one outgoing frame
+    ///
+    /// A forced disconnect wins over packet loss, which wins over delay and
+    /// corruption. With injection disabled the frame is sent unchanged.
+    pub fn decide(&mut self, bytes: Vec<u8>) -> Injection {
+        if self.should_disconnect() {
+            return Injection::Disconnect;
+        }
+        if self.should_drop_packet() {
+            return Injection::Drop;
+        }
+        let delay = self.get_delay();
+        let corrupted = self.should_corrupt_message();
+        let bytes = if corrupted {
+            self.corrupt_message(&bytes)
+        } else {
+            bytes
+        };
+        Injection::Send {
+            bytes,
+            delay,
+            corrupted,
+        }
+    }
+
     /// Get the current configuration
     pub fn config(&self) -> &FailureConfig {
         &self.config
@@ -227,6 +532,91 @@ impl FailureSimulator {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_decide_passes_frames_through_when_disabled() {
+        let mut simulator = FailureSimulator::new(FailureConfig::default());
+        assert_eq!(
+            simulator.decide(b"00200001001         ".to_vec()),
+            Injection::Send {
+                bytes: b"00200001001         ".to_vec(),
+                delay: Duration::ZERO,
+                corrupted: false,
+            }
+        );
+
+        let mut simulator = FailureSimulator::new(FailureConfig {
+            enabled: true,
+            packet_loss_rate: 1.0,
+            ..FailureConfig::default()
+        });
+        assert_eq!(simulator.decide(b"0020".to_vec()), Injection::Drop);
+    }
+
+    #[test]
+    fn test_injection_log_records_only_interfering_injections() {
+        let log = InjectionLog::new(2);
+        let client: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let frame = b"00200061001         ";
+
+        let untouched = Injection::Send {
+            bytes: frame.to_vec(),
+            delay: Duration::ZERO,
+            corrupted: false,
+        };
+        log.finish(
+            InjectionTrace::new(client, frame, "MID 61", &untouched),
+            true,
+        );
+        assert!(log.since(0).is_empty());
+
+        let delayed = Injection::Send {
+            bytes: frame.to_vec(),
+            delay: Duration::from_millis(20),
+            corrupted: false,
+        };
+        let trace = InjectionTrace::new(client, frame, "MID 61 broadcast", &delayed);
+        std::thread::sleep(Duration::from_millis(20));
+        log.finish(trace, true);
+        log.finish(
+            InjectionTrace::new(client, frame, "MID 61", &Injection::Drop),
+            false,
+        );
+        log.finish(
+            InjectionTrace::new(client, b"garbage", "MID 2", &Injection::Disconnect),
+            false,
+        );
+
+        let records = log.since(0);
+        assert_eq!(records.len(), 2, "capacity is 2");
+        assert_eq!(records[0].seq, 2);
+        assert_eq!(records[0].action, InjectionAction::Dropped);
+        assert_eq!(records[0].mid, Some(61));
+        assert!(records[0].planned_send_at.is_none() && records[0].actual_send_at.is_none());
+        assert_eq!(records[1].action, InjectionAction::Disconnected);
+        assert_eq!(records[1].mid, None);
+        assert_eq!(log.since(2).len(), 1);
+    }
+
+    #[test]
+    fn test_injection_trace_compares_planned_and_actual_send() {
+        let log = InjectionLog::default();
+        let client: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let delayed = Injection::Send {
+            bytes: Vec::new(),
+            delay: Duration::from_millis(10),
+            corrupted: false,
+        };
+        let trace = InjectionTrace::new(client, b"00200061001", "MID 61", &delayed);
+        std::thread::sleep(Duration::from_millis(30));
+        log.finish(trace, true);
+
+        let record = &log.since(0)[0];
+        assert_eq!(record.action, InjectionAction::Delayed);
+        assert_eq!(record.delay_ms, 10);
+        assert!(record.planned_send_at.is_some() && record.actual_send_at.is_some());
+        assert!(record.lateness_ms.unwrap() >= 15);
+    }
+
     #[test]
     fn test_default_config_is_disabled() {
         let config = FailureConfig::default();
@@ -278,6 +668,39 @@ mod tests {
         config.delay_min_ms = 1000;
         config.delay_max_ms = 500;
         assert!(!config.is_valid());
+
+        config.delay_min_ms = 0;
+        config.bandwidth = Some(BandwidthLimit {
+            bytes_per_sec: 0,
+            burst_bytes: 0,
+        });
+        assert!(!config.is_valid());
+    }
+
+    #[test]
+    fn test_link_shaper_spends_burst_then_paces_at_rate() {
+        let limit = BandwidthLimit {
+            bytes_per_sec: 1000,
+            burst_bytes: 500,
+        };
+        let start = Instant::now();
+        let mut shaper = LinkShaper::new(start);
+
+        assert_eq!(shaper.delay(limit, 400, start), Duration::ZERO);
+        // 100 bytes left in the burst; the other 200 take 200 ms
+        assert_eq!(shaper.delay(limit, 300, start), Duration::from_millis(200));
+        // Frames queue behind the debt
+        assert_eq!(shaper.delay(limit, 100, start), Duration::from_millis(300));
+        // After the link caught up and idled, the burst is available again
+        let later = start + Duration::from_secs(2);
+        assert_eq!(shaper.delay(limit, 500, later), Duration::ZERO);
+
+        // A new limit starts with a full bucket
+        let faster = BandwidthLimit {
+            bytes_per_sec: 2000,
+            burst_bytes: 0,
+        };
+        assert_eq!(shaper.delay(faster, 200, later), Duration::from_millis(100));
     }
 
     #[test]
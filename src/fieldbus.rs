@@ -0,0 +1,167 @@
+//! Virtual fieldbus I/O image
+//!
+//! Many stations drive the controller from a PLC over a fieldbus (start,
+//! reset and PSET select bits) while line software monitors the same
+//! controller over Open Protocol. The simulator keeps a small I/O image so
+//! both views can be tested together: inputs are written through
+//! `PUT /io/fieldbus`, outputs are derived from the device state and the
+//! tightening lifecycle, so they always agree with what Open Protocol
+//! clients see. A `release` edge releases a job position held by an
+//! interlock (see `interlock`).
+
+use serde::{Deserialize, Serialize};
+
+/// Signals written by the PLC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldbusInputs {
+    /// Rising edge starts a tightening
+    pub start: bool,
+    /// Rising edge clears the OK/NOK outputs
+    pub reset: bool,
+    /// Binary-coded PSET select bits; a change to a non-zero value selects that PSET
+    pub pset: u8,
+    /// Rising edge releases the next position of an interlocked job
+    #[serde(default)]
+    pub release: bool,
+}
+
+/// Partial update of the inputs; omitted signals keep their level
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldbusInputsPatch {
+    pub start: Option<bool>,
+    pub reset: Option<bool>,
+    pub pset: Option<u8>,
+    pub release: Option<bool>,
+}
+
+/// Signals read by the PLC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FieldbusOutputs {
+    /// Tool enabled and controller up
+    pub ready: bool,
+    pub tool_enabled: bool,
+    /// Last tightening OK, latched until the next start or reset
+    pub tightening_ok: bool,
+    /// Last tightening NOK, latched until the next start or reset
+    pub tightening_nok: bool,
+    pub batch_complete: bool,
+    /// Job position waiting for its interlock release
+    pub interlock_waiting: bool,
+    /// Open (unacknowledged or uncleared) alarms present
+    pub alarm: bool,
+    /// Selected PSET as binary-coded bits (0 = none or above 255)
+    pub pset: u8,
+}
+
+/// Edges detected when new inputs are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputEdges {
+    pub start: bool,
+    pub reset: bool,
+    /// PSET newly requested by the select bits
+    pub pset: Option<u8>,
+    pub release: bool,
+}
+
+/// Input levels and the latched tightening outcome
+#[derive(Debug, Clone, Default)]
+pub struct FieldbusImage {
+    inputs: FieldbusInputs,
+    result: Option<bool>,
+}
+
+impl FieldbusImage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inputs(&self) -> FieldbusInputs {
+        self.inputs
+    }
+
+    /// Inputs after applying `patch`, without storing them
+    pub fn patched(&self, patch: FieldbusInputsPatch) -> FieldbusInputs {
+        FieldbusInputs {
+            start: patch.start.unwrap_or(self.inputs.start),
+            reset: patch.reset.unwrap_or(self.inputs.reset),
+            pset: patch.pset.unwrap_or(self.inputs.pset),
+            release: patch.release.unwrap_or(self.inputs.release),
+        }
+    }
+
+    /// Store new input levels and report the edges; a start or reset edge clears the result
+    pub fn write_inputs(&mut self, inputs: FieldbusInputs) -> InputEdges {
+        let edges = InputEdges {
+            start: inputs.start && !self.inputs.start,
+            reset: inputs.reset && !self.inputs.reset,
+            pset: (inputs.pset != 0 && inputs.pset != self.inputs.pset).then_some(inputs.pset),
+            release: inputs.release && !self.inputs.release,
+        };
+        if edges.start || edges.reset {
+            self.result = None;
+        }
+        self.inputs = inputs;
+        edges
+    }
+
+    /// Latch the outcome of a completed tightening
+    pub fn record_result(&mut self, ok: bool) {
+        self.result = Some(ok);
+    }
+
+    /// Latched outcome of the last tightening, if not cleared
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+}
+
+/// PSET ID as select bits; IDs that do not fit in the byte read as 0
+pub fn pset_bits(pset_id: Option<u32>) -> u8 {
+    pset_id.and_then(|id| u8::try_from(id).ok()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edges_and_latched_result() {
+        let mut image = FieldbusImage::new();
+        image.record_result(false);
+
+        let inputs = image.patched(FieldbusInputsPatch {
+            pset: Some(3),
+            ..Default::default()
+        });
+        let edges = image.write_inputs(inputs);
+        assert_eq!(edges.pset, Some(3));
+        assert!(!edges.start);
+        assert_eq!(image.result(), Some(false), "PSET bits keep the result");
+
+        let inputs = image.patched(FieldbusInputsPatch {
+            start: Some(true),
+            ..Default::default()
+        });
+        let edges = image.write_inputs(inputs);
+        assert!(edges.start);
+        assert_eq!(edges.pset, None, "unchanged bits select nothing");
+        assert_eq!(image.result(), None);
+
+        // Holding start high is not another edge
+        image.record_result(true);
+        assert!(!image.write_inputs(image.inputs()).start);
+        assert_eq!(image.result(), Some(true));
+
+        let inputs = image.patched(FieldbusInputsPatch {
+            release: Some(true),
+            ..Default::default()
+        });
+        assert!(image.write_inputs(inputs).release);
+        assert!(!image.write_inputs(image.inputs()).release);
+
+        assert_eq!(pset_bits(Some(7)), 7);
+        assert_eq!(pset_bits(Some(300)), 0);
+        assert_eq!(pset_bits(None), 0);
+    }
+}
@@ -0,0 +1,494 @@
+//! Synthetic tightening result generation
+//!
+//! Produces MID 0061 results without running the device FSM (no sleeps, no
+//! batch tracking). Used by the firehose mode to load test subscribers and the
+//! simulator's own fan-out at thousands of results per second, and to backfill
+//! the results history with a historical dataset.
+
+use crate::device_fsm::TighteningParams;
+use crate::handler::data::TighteningResult;
+use chrono::{NaiveDate, NaiveDateTime};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// How torque/angle values are spread inside the PSET limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueDistribution {
+    /// Bell curve centred on the target (sigma = 1/6 of the limit window)
+    #[default]
+    Normal,
+    /// Evenly spread between min and max
+    Uniform,
+}
+
+/// Generates synthetic results from a template
+///
+/// The template carries the controller identity, PSET, VIN and limits; every
+/// generated result only differs in measured values, status, ID and timestamp.
+pub struct ResultGenerator {
+    template: TighteningResult,
+    params: TighteningParams,
+    nok_rate: f64,
+    distribution: ValueDistribution,
+    rng: StdRng,
+}
+
+impl ResultGenerator {
+    pub fn new(
+        template: TighteningResult,
+        params: TighteningParams,
+        nok_rate: f64,
+        distribution: ValueDistribution,
+    ) -> Self {
+        Self {
+            template,
+            params,
+            nok_rate: nok_rate.clamp(0.0, 1.0),
+            distribution,
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// Generate one result with the given tightening ID and timestamp
+    pub fn generate(&mut self, tightening_id: u32, timestamp: String) -> TighteningResult {
+        let p = &self.params;
+        let mut torque = sample(
+            &mut self.rng,
+            self.distribution,
+            p.torque_min,
+            p.target_torque,
+            p.torque_max,
+        );
+        let mut angle = sample(
+            &mut self.rng,
+            self.distribution,
+            p.angle_min,
+            p.target_angle,
+            p.angle_max,
+        );
+
+        let mut torque_ok = true;
+        let mut angle_ok = true;
+        if self.rng.random::<f64>() < self.nok_rate {
+            // Push one of the values 5-15% outside its window
+            let overshoot = 1.0 + self.rng.random_range(0.05..0.15);
+            if self.rng.random_bool(0.5) {
+                torque = if self.rng.random_bool(0.5) {
+                    p.torque_max * overshoot
+                } else {
+                    p.torque_min / overshoot
+                };
+                torque_ok = false;
+            } else {
+                angle = if self.rng.random_bool(0.5) {
+                    p.angle_max * overshoot
+                } else {
+                    p.angle_min / overshoot
+                };
+                angle_ok = false;
+            }
+        }
+
+        TighteningResult {
+            torque,
+            angle,
+            torque_status: torque_ok,
+            angle_status: angle_ok,
+            tightening_status: torque_ok && angle_ok,
+            tightening_id: Some(tightening_id),
+            timestamp,
+            // Generated results are not timed cycles
+            cycle_time_ms: 0,
+            ..self.template.clone()
+        }
+    }
+}
+
+/// Timestamp format used in tightening results
+pub const RESULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d:%H:%M:%S";
+
+/// Sample a value inside `[min, max]` according to the distribution
+fn sample(
+    rng: &mut StdRng,
+    distribution: ValueDistribution,
+    min: f64,
+    target: f64,
+    max: f64,
+) -> f64 {
+    if max <= min {
+        return min;
+    }
+    match distribution {
+        ValueDistribution::Uniform => rng.random_range(min..=max),
+        ValueDistribution::Normal => {
+            // Box-Muller transform
+            let u1: f64 = 1.0 - rng.random::<f64>(); // (0, 1]
+            let u2: f64 = rng.random();
+            let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+            (target + z * (max - min) / 6.0).clamp(min, max)
+        }
+    }
+}
+
+// ============================================================================
+// Firehose
+// ============================================================================
+
+/// Parameters of a firehose run (body of POST /firehose/start)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirehoseConfig {
+    /// Results per second
+    #[serde(default = "default_rate_per_sec")]
+    pub rate_per_sec: u32,
+
+    /// Stop after this many results (runs until stopped if omitted)
+    #[serde(default)]
+    pub count: Option<u64>,
+
+    /// Stop after this many milliseconds (runs until stopped if omitted)
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+
+    /// Probability of a NOK result (0.0-1.0)
+    #[serde(default = "default_nok_rate")]
+    pub nok_rate: f64,
+
+    /// Spread of torque/angle values inside the PSET limits
+    #[serde(default)]
+    pub distribution: ValueDistribution,
+}
+
+impl Default for FirehoseConfig {
+    fn default() -> Self {
+        Self {
+            rate_per_sec: default_rate_per_sec(),
+            count: None,
+            duration_ms: None,
+            nok_rate: default_nok_rate(),
+            distribution: ValueDistribution::default(),
+        }
+    }
+}
+
+fn default_rate_per_sec() -> u32 {
+    1000
+}
+
+fn default_nok_rate() -> f64 {
+    0.1
+}
+
+impl FirehoseConfig {
+    /// Validate configuration values are within acceptable ranges
+    pub fn is_valid(&self) -> bool {
+        self.rate_per_sec > 0 && (0.0..=1.0).contains(&self.nok_rate)
+    }
+
+    /// Number of results due after `elapsed_secs`, capped by `count`
+    pub fn due(&self, elapsed_secs: f64) -> u64 {
+        let due = (self.rate_per_sec as f64 * elapsed_secs) as u64;
+        self.count.map_or(due, |count| due.min(count))
+    }
+}
+
+/// Run state of the firehose, shared between the HTTP handlers and the task
+#[derive(Debug, Default)]
+pub struct FirehoseStatus {
+    active: AtomicBool,
+    generated: AtomicU64,
+}
+
+impl FirehoseStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark the firehose as running; returns false if it already was
+    pub fn try_start(&self) -> bool {
+        if self.active.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+        self.generated.store(0, Ordering::Relaxed);
+        true
+    }
+
+    /// Stop the firehose; returns whether it was running
+    pub fn stop(&self) -> bool {
+        self.active.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn add_generated(&self, count: u64) {
+        self.generated.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Results generated by the current (or last) run
+    pub fn generated(&self) -> u64 {
+        self.generated.load(Ordering::Relaxed)
+    }
+}
+
+// ============================================================================
+// Historical backfill
+// ============================================================================
+
+/// Upper bound for a single backfill request
+pub const MAX_BACKFILL_COUNT: u32 = 1_000_000;
+
+/// Parameters of a historical dataset (body of POST /results/generate)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillConfig {
+    /// Number of results to generate
+    pub count: u32,
+
+    /// Start of the time range (defaults to 30 days before `to`)
+    #[serde(default)]
+    pub from: Option<String>,
+
+    /// End of the time range (defaults to now)
+    #[serde(default)]
+    pub to: Option<String>,
+
+    /// Relative weight per PSET ID, e.g. `{"1": 3, "2": 1}` (defaults to the selected PSET)
+    #[serde(default)]
+    pub pset_mix: BTreeMap<u32, f64>,
+
+    /// Probability of a NOK result (0.0-1.0)
+    #[serde(default = "default_nok_rate")]
+    pub nok_rate: f64,
+
+    /// Spread of torque/angle values inside the PSET limits
+    #[serde(default)]
+    pub distribution: ValueDistribution,
+}
+
+impl BackfillConfig {
+    /// Validate count, NOK rate and weights; returns a message for the client
+    pub fn validate(&self) -> Result<(), String> {
+        if self.count == 0 || self.count > MAX_BACKFILL_COUNT {
+            return Err(format!(
+                "count must be between 1 and {}",
+                MAX_BACKFILL_COUNT
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.nok_rate) {
+            return Err("nok_rate must be between 0.0 and 1.0".to_string());
+        }
+        if self.pset_mix.values().any(|w| !w.is_finite() || *w < 0.0)
+            || (!self.pset_mix.is_empty() && self.pset_mix.values().sum::<f64>() <= 0.0)
+        {
+            return Err("pset_mix weights must be non-negative and not all zero".to_string());
+        }
+        Ok(())
+    }
+
+    /// Resolve `from`/`to` against the current time
+    pub fn time_range(&self, now: NaiveDateTime) -> Result<(NaiveDateTime, NaiveDateTime), String> {
+        let to = match &self.to {
+            Some(to) => parse_timestamp(to)?,
+            None => now,
+        };
+        let from = match &self.from {
+            Some(from) => parse_timestamp(from)?,
+            None => to - chrono::Duration::days(30),
+        };
+        if from > to {
+            return Err("'from' must not be after 'to'".to_string());
+        }
+        Ok((from, to))
+    }
+}
+
+/// Parse a timestamp given as `YYYY-MM-DD`, `YYYY-MM-DDTHH:MM:SS` or the
+/// Open Protocol `YYYY-MM-DD:HH:MM:SS`
+pub fn parse_timestamp(value: &str) -> Result<NaiveDateTime, String> {
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        RESULT_TIMESTAMP_FORMAT,
+        "%Y-%m-%d %H:%M:%S",
+    ] {
+        if let Ok(ts) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(ts);
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
+        .map_err(|_| format!("Invalid timestamp '{}'", value))
+}
+
+/// Generate a historical dataset, oldest first
+///
+/// `generators` holds one generator per PSET of the mix (weights are looked
+/// up in `config.pset_mix`; an empty mix uses the generators evenly). IDs are
+/// assigned consecutively from `first_id` in chronological order.
+pub fn generate_history(
+    config: &BackfillConfig,
+    generators: &mut BTreeMap<u32, ResultGenerator>,
+    range: (NaiveDateTime, NaiveDateTime),
+    first_id: u32,
+) -> Vec<TighteningResult> {
+    let mut rng = StdRng::from_os_rng();
+    let (from, to) = range;
+    let span_secs = (to - from).num_seconds().max(0);
+
+    let mut timestamps: Vec<NaiveDateTime> = (0..config.count)
+        .map(|_| from + chrono::Duration::seconds(rng.random_range(0..=span_secs)))
+        .collect();
+    timestamps.sort();
+
+    let weighted: Vec<(u32, f64)> = generators
+        .keys()
+        .map(|id| (*id, config.pset_mix.get(id).copied().unwrap_or(1.0)))
+        .collect();
+    let total_weight: f64 = weighted.iter().map(|(_, w)| w).sum();
+
+    timestamps
+        .into_iter()
+        .zip(first_id..)
+        .filter_map(|(ts, id)| {
+            let mut pick = rng.random::<f64>() * total_weight;
+            let pset_id = weighted
+                .iter()
+                .find(|(_, w)| {
+                    pick -= w;
+                    pick < 0.0
+                })
+                .or(weighted.last())
+                .map(|(id, _)| *id)?;
+            let generator = generators.get_mut(&pset_id)?;
+            Some(generator.generate(id, ts.format(RESULT_TIMESTAMP_FORMAT).to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(nok_rate: f64, distribution: ValueDistribution) -> ResultGenerator {
+        ResultGenerator::new(
+            TighteningResult::example(),
+            TighteningParams::default_test(),
+            nok_rate,
+            distribution,
+        )
+    }
+
+    #[test]
+    fn test_ok_results_stay_within_limits() {
+        for distribution in [ValueDistribution::Normal, ValueDistribution::Uniform] {
+            let mut generator = generator(0.0, distribution);
+            let params = TighteningParams::default_test();
+            for id in 1..=500 {
+                let result = generator.generate(id, "2024-01-01:00:00:00".to_string());
+                assert!(result.tightening_status);
+                assert!(result.torque >= params.torque_min && result.torque <= params.torque_max);
+                assert!(result.angle >= params.angle_min && result.angle <= params.angle_max);
+                assert_eq!(result.tightening_id, Some(id));
+            }
+        }
+    }
+
+    #[test]
+    fn test_nok_results_are_out_of_limits() {
+        let mut generator = generator(1.0, ValueDistribution::Normal);
+        let params = TighteningParams::default_test();
+        for id in 1..=100 {
+            let result = generator.generate(id, String::new());
+            assert!(!result.tightening_status);
+            let torque_out = result.torque < params.torque_min || result.torque > params.torque_max;
+            let angle_out = result.angle < params.angle_min || result.angle > params.angle_max;
+            assert_eq!(torque_out, !result.torque_status);
+            assert_eq!(angle_out, !result.angle_status);
+        }
+    }
+
+    #[test]
+    fn test_firehose_due_respects_rate_and_count() {
+        let config = FirehoseConfig {
+            rate_per_sec: 5000,
+            count: Some(7000),
+            ..Default::default()
+        };
+        assert_eq!(config.due(0.5), 2500);
+        assert_eq!(config.due(2.0), 7000);
+        assert!(config.is_valid());
+        assert!(
+            !FirehoseConfig {
+                rate_per_sec: 0,
+                ..Default::default()
+            }
+            .is_valid()
+        );
+    }
+
+    #[test]
+    fn test_history_is_chronological_and_follows_mix() {
+        let config = BackfillConfig {
+            count: 1000,
+            from: Some("2024-01-01".to_string()),
+            to: Some("2024-03-31T23:59:59".to_string()),
+            pset_mix: BTreeMap::from([(1, 1.0), (2, 0.0)]),
+            nok_rate: 0.0,
+            distribution: ValueDistribution::Uniform,
+        };
+        config.validate().unwrap();
+        let range = config
+            .time_range(chrono::Local::now().naive_local())
+            .unwrap();
+
+        let mut generators = BTreeMap::from([
+            (1, generator(0.0, ValueDistribution::Uniform)),
+            (2, generator(0.0, ValueDistribution::Uniform)),
+        ]);
+        generators.get_mut(&2).unwrap().template.pset_id = 2;
+        generators.get_mut(&1).unwrap().template.pset_id = 1;
+
+        let history = generate_history(&config, &mut generators, range, 10);
+        assert_eq!(history.len(), 1000);
+        assert_eq!(history[0].tightening_id, Some(10));
+        assert!(history.iter().all(|r| r.pset_id == 1));
+        assert!(history.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        assert!(history[0].timestamp.as_str() >= "2024-01-01:00:00:00");
+        assert!(history[999].timestamp.as_str() <= "2024-03-31:23:59:59");
+    }
+
+    #[test]
+    fn test_backfill_config_validation() {
+        let config: BackfillConfig = serde_json::from_str(r#"{"count": 10}"#).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config = BackfillConfig {
+            from: Some("2024-02-01".to_string()),
+            to: Some("2024-01-01".to_string()),
+            ..config
+        };
+        assert!(
+            config
+                .time_range(chrono::Local::now().naive_local())
+                .is_err()
+        );
+        assert!(parse_timestamp("yesterday").is_err());
+
+        let config: BackfillConfig = serde_json::from_str(r#"{"count": 0}"#).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_firehose_status_single_run() {
+        let status = FirehoseStatus::new();
+        assert!(status.try_start());
+        assert!(!status.try_start());
+        status.add_generated(3);
+        assert_eq!(status.generated(), 3);
+        assert!(status.stop());
+        assert!(!status.is_active());
+    }
+}
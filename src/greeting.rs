@@ -0,0 +1,94 @@
+//! Unsolicited frame sent right after a client connects
+//!
+//! Some controllers greet a new connection before the client has sent MID
+//! 0001: a banner line, or a message of their own. Clients have to tolerate
+//! that traffic, so a personality can configure `[device.greeting]` to send
+//! either raw bytes or a templated MID frame as soon as the connection is
+//! accepted.
+
+use crate::protocol::{Mid, Response};
+use crate::state::DeviceState;
+use serde::{Deserialize, Serialize};
+
+fn default_revision() -> u16 {
+    1
+}
+
+/// What is sent on connect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GreetingFrame {
+    /// Bytes sent verbatim, without a NUL terminator unless included
+    Raw { raw: String },
+    /// A framed Open Protocol message with a templated data field
+    Mid {
+        mid: u16,
+        #[serde(default = "default_revision")]
+        revision: u16,
+        /// `{controller_name}`, `{supplier_code}`, `{software_version}`,
+        /// `{cell_id}`, `{channel_id}` and `{time}` are filled in
+        #[serde(default)]
+        data: String,
+    },
+}
+
+/// The frame to send, rendered for the current device state
+#[derive(Debug, Clone)]
+pub enum Greeting {
+    Raw(Vec<u8>),
+    Message(Response),
+}
+
+impl GreetingFrame {
+    pub fn render(&self, state: &DeviceState) -> Greeting {
+        match self {
+            GreetingFrame::Raw { raw } => Greeting::Raw(raw.clone().into_bytes()),
+            GreetingFrame::Mid {
+                mid,
+                revision,
+                data,
+            } => {
+                let data = data
+                    .replace("{controller_name}", &state.controller_name)
+                    .replace("{supplier_code}", &state.supplier_code)
+                    .replace("{software_version}", &state.software_version)
+                    .replace("{cell_id}", &format!("{:04}", state.cell_id))
+                    .replace("{channel_id}", &format!("{:02}", state.channel_id))
+                    .replace(
+                        "{time}",
+                        &chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+                    );
+                Greeting::Message(Response::new(Mid(*mid), *revision, data.into_bytes()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_and_templated_greetings() {
+        let raw: GreetingFrame = serde_json::from_str(r#"{"raw": "HELLO\r\n"}"#).unwrap();
+        match raw.render(&DeviceState::new()) {
+            Greeting::Raw(bytes) => assert_eq!(bytes, b"HELLO\r\n"),
+            other => panic!("expected raw bytes, got {:?}", other),
+        }
+
+        let templated: GreetingFrame = serde_json::from_str(
+            r#"{"mid": 9998, "data": "{cell_id}{channel_id}{controller_name}"}"#,
+        )
+        .unwrap();
+        let mut state = DeviceState::new();
+        state.controller_name = "Station7".to_string();
+        match templated.render(&state) {
+            Greeting::Message(response) => {
+                assert_eq!(response.mid, 9998);
+                assert_eq!(response.revision, 1);
+                assert_eq!(response.data, b"000101Station7");
+            }
+            other => panic!("expected a MID frame, got {:?}", other),
+        }
+    }
+}
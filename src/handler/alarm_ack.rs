@@ -0,0 +1,14 @@
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0072 - Alarm acknowledge
+/// Client acknowledges receipt of an alarm (MID 0071)
+pub struct AlarmAckHandler;
+
+impl MidHandler for AlarmAckHandler {
+    fn handle(&self, _message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0072: Alarm acknowledged by client");
+
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
+    }
+}
@@ -0,0 +1,63 @@
+//! MID 0075 - Alarm acknowledged on controller acknowledge handler
+//!
+//! Clients answer a MID 0074 with MID 0075. The simulator also treats an
+//! unsolicited MID 0075 as the client acknowledging the oldest active alarm,
+//! so the acknowledgment lifecycle can be driven from the protocol side.
+
+use crate::alarms::ProtocolAck;
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0075 - Alarm acknowledged on controller acknowledge
+pub struct AlarmAcknowledgedAckHandler {
+    state: ObservableState,
+}
+
+impl AlarmAcknowledgedAckHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+impl MidHandler for AlarmAcknowledgedAckHandler {
+    fn handle(&self, _message: &Message) -> Result<Response, HandlerError> {
+        match self.state.acknowledge_alarm_by_protocol() {
+            ProtocolAck::Confirmed(alarm) => {
+                println!(
+                    "MID 0075: Client confirmed acknowledgment of alarm {}",
+                    alarm.id
+                )
+            }
+            ProtocolAck::Acknowledged(alarm) => {
+                println!("MID 0075: Client acknowledged alarm {}", alarm.id)
+            }
+            ProtocolAck::Nothing => println!("MID 0075: No alarm awaiting acknowledgment"),
+        }
+
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarms::{AckSource, AlarmSeverity, AlarmState};
+    use crate::state::DeviceState;
+
+    #[test]
+    fn test_mid_0075_acknowledges_active_alarm() {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(DeviceState::new_shared(), tx);
+        let id = state.raise_alarm("E301", "Tool overheated", AlarmSeverity::Warning);
+        let handler = AlarmAcknowledgedAckHandler::new(state.clone());
+        let message = Message::new(75, 1, Vec::new());
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, Mid::COMMAND_ACCEPTED.value());
+
+        let alarm = state.read().alarms.get(id).cloned().unwrap();
+        assert_eq!(alarm.state, AlarmState::Acknowledged);
+        assert_eq!(alarm.acknowledged_by, Some(AckSource::Protocol));
+    }
+}
@@ -0,0 +1,101 @@
+//! MID 9130 - Select alarm text language (simulator extension)
+//!
+//! Open Protocol has no MID for the language of controller texts, so the
+//! simulator accepts one outside the ranges used by the specification. The
+//! request data is a two-letter language code (e.g. `de`); alarm texts of
+//! MID 0071 revision 2 are sent in that language from then on.
+
+use crate::alarm_texts;
+use crate::handler::data::{CommandAccepted, ErrorCode, ErrorResponse};
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+use crate::state::StatePatch;
+
+/// MID 9130 - Select alarm text language
+pub struct AlarmLanguageHandler {
+    state: ObservableState,
+}
+
+impl AlarmLanguageHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+impl MidHandler for AlarmLanguageHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let language = String::from_utf8_lossy(&message.data).trim().to_string();
+
+        if !alarm_texts::is_valid_language(&language) {
+            println!("MID 9130: Alarm language rejected - invalid language code");
+            return Ok(Response::from_data(
+                Mid::COMMAND_ERROR,
+                message.revision,
+                ErrorResponse::new(Mid::ALARM_LANGUAGE_SELECT, ErrorCode::InvalidData),
+            )?);
+        }
+
+        println!("MID 9130: Alarm language - {}", language);
+        let patch = StatePatch {
+            alarm_language: Some(language),
+            ..StatePatch::default()
+        };
+        self.state
+            .apply_patch(patch)
+            .map_err(HandlerError::Processing)?;
+
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            CommandAccepted::with_mid(Mid::ALARM_LANGUAGE_SELECT),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DeviceState;
+    use std::sync::{Arc, RwLock};
+
+    fn handler() -> (AlarmLanguageHandler, ObservableState) {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(Arc::new(RwLock::new(DeviceState::new())), tx);
+        (AlarmLanguageHandler::new(state.clone()), state)
+    }
+
+    fn message(data: &[u8]) -> Message {
+        Message {
+            length: 20 + data.len() as u32,
+            mid: 9130,
+            revision: 1,
+            data: data.to_vec(),
+            header: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_select_language() {
+        let (handler, state) = handler();
+        let response = handler.handle(&message(b"sv")).unwrap();
+
+        assert_eq!(response.mid, 5);
+        assert_eq!(response.data, b"9130");
+        assert_eq!(state.read().alarm_language, "sv");
+        assert_eq!(
+            state.read().alarm_text("E102", "Tool battery low"),
+            "Låg batterinivå i verktyget"
+        );
+    }
+
+    #[test]
+    fn test_invalid_language_is_rejected() {
+        let (handler, state) = handler();
+        let response = handler.handle(&message(b"german")).unwrap();
+
+        assert_eq!(response.mid, 4);
+        assert_eq!(response.data, b"913004");
+        assert_eq!(state.read().alarm_language, "en");
+    }
+}
@@ -0,0 +1,41 @@
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0070 - Alarm subscribe
+/// Client requests subscription to controller alarms (MID 0071)
+pub struct AlarmSubscribeHandler;
+
+impl MidHandler for AlarmSubscribeHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0070: Alarm subscription request");
+
+        let ack_data = CommandAccepted::with_mid(Mid::ALARM_SUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alarm_subscribe() {
+        let handler = AlarmSubscribeHandler;
+        let message = Message {
+            length: 20,
+            mid: 70,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert_eq!(response.data, b"0070");
+    }
+}
@@ -0,0 +1,41 @@
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0073 - Alarm unsubscribe
+/// Client cancels its subscription to controller alarms
+pub struct AlarmUnsubscribeHandler;
+
+impl MidHandler for AlarmUnsubscribeHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0073: Alarm unsubscribe request");
+
+        let ack_data = CommandAccepted::with_mid(Mid::ALARM_UNSUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alarm_unsubscribe() {
+        let handler = AlarmUnsubscribeHandler;
+        let message = Message {
+            length: 20,
+            mid: 73,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert_eq!(response.data, b"0073");
+    }
+}
@@ -6,7 +6,7 @@
 use crate::handler::data::command_accepted::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
 use crate::observable_state::ObservableState;
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0128 - Job batch increment
 /// Increments the batch counter to skip a bolt position
@@ -38,10 +38,14 @@ impl MidHandler for BatchIncrementHandler {
         self.state
             .broadcast_auto_progress(new_counter, target_size, true);
 
-        let ack_data = CommandAccepted::with_mid(128);
+        let ack_data = CommandAccepted::with_mid(Mid::JOB_BATCH_INCREMENT);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
 
@@ -76,6 +80,7 @@ mod tests {
             mid: 128,
             revision: 1,
             data: vec![],
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
@@ -2,21 +2,22 @@
 //!
 //! Resets the batch counter of the running parameter set at runtime.
 //! The batch size remains unchanged, only the counter is reset to 0.
+//! The reset is broadcast as `BatchReset`, so the dashboard follows it.
 
+use crate::events::SimulatorEvent;
 use crate::handler::data::command_accepted::CommandAccepted;
 use crate::handler::data::error_response::ErrorResponse;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
-use crate::state::DeviceState;
-use std::sync::{Arc, RwLock};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0020 - Reset parameter set batch counter
 pub struct BatchResetHandler {
-    state: Arc<RwLock<DeviceState>>,
+    state: ObservableState,
 }
 
 impl BatchResetHandler {
-    pub fn new(state: Arc<RwLock<DeviceState>>) -> Self {
+    pub fn new(state: ObservableState) -> Self {
         Self { state }
     }
 }
@@ -33,9 +34,10 @@ impl MidHandler for BatchResetHandler {
             0
         };
 
-        let was_batch_mode = {
-            let mut state = self.state.write().unwrap();
-            state.reset_batch()
+        let (was_batch_mode, batch_size) = {
+            let mut state = self.state.write();
+            state.touch();
+            (state.reset_batch(), state.tightening_tracker.batch_size())
         };
 
         if was_batch_mode {
@@ -43,8 +45,16 @@ impl MidHandler for BatchResetHandler {
                 "MID 0020: Reset batch counter for pset {} - counter reset to 0",
                 pset_id
             );
-            let ack_data = CommandAccepted::with_mid(20);
-            Ok(Response::from_data(5, message.revision, ack_data))
+            self.state.broadcast(SimulatorEvent::BatchReset {
+                pset_id,
+                batch_size,
+            });
+            let ack_data = CommandAccepted::with_mid(Mid::RESET_PSET_BATCH_COUNTER);
+            Ok(Response::from_data(
+                Mid::COMMAND_ACCEPTED,
+                message.revision,
+                ack_data,
+            )?)
         } else {
             // Not in batch mode - return error
             println!(
@@ -52,7 +62,11 @@ impl MidHandler for BatchResetHandler {
                 pset_id
             );
             let error_data = ErrorResponse::invalid_data(20);
-            Ok(Response::from_data(4, message.revision, error_data))
+            Ok(Response::from_data(
+                Mid::COMMAND_ERROR,
+                message.revision,
+                error_data,
+            )?)
         }
     }
 }
@@ -61,21 +75,29 @@ impl MidHandler for BatchResetHandler {
 mod tests {
     use super::*;
     use crate::protocol::Message;
+    use crate::state::DeviceState;
+    use tokio::sync::broadcast;
+
+    fn create_test_observable() -> (ObservableState, broadcast::Receiver<SimulatorEvent>) {
+        let state = DeviceState::new_shared();
+        let (tx, rx) = broadcast::channel(16);
+        (ObservableState::new(state, tx), rx)
+    }
 
     #[test]
     fn test_batch_reset_in_batch_mode() {
-        let state = DeviceState::new_shared();
+        let (observable, mut events) = create_test_observable();
 
         // Enable batch mode and add some tightenings
         {
-            let mut s = state.write().unwrap();
+            let mut s = observable.write();
             s.set_batch_size(5);
             s.tightening_tracker.add_tightening(true);
             s.tightening_tracker.add_tightening(true);
             assert_eq!(s.tightening_tracker.counter(), 2);
         }
 
-        let handler = BatchResetHandler::new(Arc::clone(&state));
+        let handler = BatchResetHandler::new(observable.clone());
 
         // Create a MID 0020 message with pset ID "001"
         let message = Message {
@@ -83,31 +105,43 @@ mod tests {
             mid: 20,
             revision: 1,
             data: b"001".to_vec(),
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
         assert_eq!(response.mid, 5); // Command accepted
 
         // Verify counter was reset
-        let s = state.read().unwrap();
-        assert_eq!(s.tightening_tracker.counter(), 0);
+        assert_eq!(observable.read().tightening_tracker.counter(), 0);
+        match events.try_recv().unwrap() {
+            SimulatorEvent::BatchReset {
+                pset_id,
+                batch_size,
+            } => {
+                assert_eq!(pset_id, 1);
+                assert_eq!(batch_size, 5);
+            }
+            other => panic!("expected BatchReset, got {:?}", other),
+        }
     }
 
     #[test]
     fn test_batch_reset_not_in_batch_mode() {
-        let state = DeviceState::new_shared();
+        let (observable, mut events) = create_test_observable();
 
         // Don't enable batch mode (stay in single mode)
-        let handler = BatchResetHandler::new(Arc::clone(&state));
+        let handler = BatchResetHandler::new(observable);
 
         let message = Message {
             length: 23,
             mid: 20,
             revision: 1,
             data: b"001".to_vec(),
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
         assert_eq!(response.mid, 4); // Command error
+        assert!(events.try_recv().is_err());
     }
 }
@@ -1,11 +1,12 @@
 //! MID 0019 - Batch size handler
 //!
-//! Sets the batch size for the parameter set. When received, the device
-//! transitions to batch mode and tracks tightening progress through the batch.
+//! Sets the batch size for the parameter set given in the message. Batch sizes
+//! are stored per pset: if the pset is active the device transitions to batch
+//! mode immediately, otherwise the batch starts when that pset is selected.
 
 use crate::handler::data::command_accepted::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 use crate::state::DeviceState;
 use std::sync::{Arc, RwLock};
 
@@ -23,35 +24,80 @@ impl BatchSizeHandler {
 
 impl MidHandler for BatchSizeHandler {
     fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
-        // Extract batch size from message data if present
-        let batch_str = if !message.data.is_empty() {
-            let pset_id = &message.data[0..=2];
-            let batch_size = &message.data[3..];
+        let mut state = self.state.write().unwrap();
+        state.touch();
+        let active_pset = state.tightening_tracker.active_pset();
+
+        // Extract pset ID (3 digits) and batch size (remaining digits) if present
+        let (pset_id, batch_size) = if message.data.len() > 3 {
+            let pset_id = String::from_utf8_lossy(&message.data[0..=2]);
+            let batch_size = String::from_utf8_lossy(&message.data[3..]);
             (
-                String::from_utf8_lossy(pset_id).to_string(),
-                String::from_utf8_lossy(batch_size).to_string(),
+                pset_id.trim().parse::<u32>().unwrap_or(active_pset),
+                batch_size.trim().parse::<u32>().unwrap_or(1),
             )
         } else {
-            ("1".to_string(), "1".to_string())
+            (active_pset, 1)
         };
 
-        // Parse batch size
-        let batch_size = batch_str.1.trim().parse::<u32>().unwrap_or(1);
-        let pset_id = batch_str.0.trim().parse::<u32>().unwrap_or(1);
         println!(
             "MID 0019: Set batch size - PSet: {} -  Size: {}",
             pset_id, batch_size
         );
 
-        // Update device state
-        {
-            let mut state = self.state.write().unwrap();
-            state.set_batch_size(batch_size);
-        }
+        state.set_pset_batch_size(pset_id, batch_size);
+        drop(state);
 
-        let ack_data = CommandAccepted::with_mid(19);
+        let ack_data = CommandAccepted::with_mid(Mid::SET_PSET_BATCH_SIZE);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch_message(data: &[u8]) -> Message {
+        Message {
+            length: 20 + data.len() as u32,
+            mid: 19,
+            revision: 1,
+            data: data.to_vec(),
+            header: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_batch_size_for_active_pset() {
+        let state = DeviceState::new_shared();
+        let handler = BatchSizeHandler::new(Arc::clone(&state));
+
+        let response = handler.handle(&batch_message(b"00105")).unwrap();
+        assert_eq!(response.mid, 5);
+
+        let s = state.read().unwrap();
+        assert!(s.tightening_tracker.is_batch_mode());
+        assert_eq!(s.tightening_tracker.batch_size(), 5);
+    }
+
+    #[test]
+    fn test_batch_size_for_other_pset_is_stored() {
+        let state = DeviceState::new_shared();
+        let handler = BatchSizeHandler::new(Arc::clone(&state));
+
+        handler.handle(&batch_message(b"00312")).unwrap();
+
+        let mut s = state.write().unwrap();
+        assert!(!s.tightening_tracker.is_batch_mode());
+        assert_eq!(s.tightening_tracker.batch_size_for_pset(3), 12);
+
+        s.set_pset(3, None);
+        assert_eq!(s.tightening_tracker.batch_size(), 12);
     }
 }
@@ -5,7 +5,7 @@
 
 use crate::handler::data::CommunicationStartAck;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 use crate::state::DeviceState;
 use std::sync::{Arc, RwLock};
 
@@ -34,9 +34,20 @@ impl MidHandler for CommunicationStartHandler {
                 state.controller_name.clone(),
                 Some(state.supplier_code.clone()),
             )
+            .versions(
+                state.software_version.clone(),
+                state.tool_software_version.clone(),
+            )
         };
 
         // Respond with MID 0002 (Communication start acknowledge)
-        Ok(Response::from_data(2, message.revision, ack_data))
+        let revision = message
+            .revision
+            .clamp(1, CommunicationStartAck::MAX_REVISION);
+        Ok(Response::new(
+            Mid::COMMUNICATION_START_ACK,
+            revision,
+            ack_data.serialize_revision(revision)?,
+        ))
     }
 }
@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0003 - Communication stop request
 /// Responds with MID 0005 (Command accepted)
@@ -23,9 +23,13 @@ impl MidHandler for CommunicationStopHandler {
         println!("MID 0003: Communication stop request");
 
         // Read device state to populate response
-        let ack_data = CommandAccepted::with_mid(3);
+        let ack_data = CommandAccepted::with_mid(Mid::COMMUNICATION_STOP);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
@@ -0,0 +1,104 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+
+/// MID 0071 - Alarm (broadcast to subscribers)
+///
+/// Revision 1 fields:
+/// - 01: Error code (4 characters)
+/// - 02: Controller ready status (1 = ready)
+/// - 03: Tool ready status (1 = ready)
+/// - 04: Time (YYYY-MM-DD:HH:MM:SS)
+///
+/// Revision 2 adds:
+/// - 05: Alarm text (50 characters, in the controller language)
+#[derive(Debug, Clone)]
+pub struct AlarmBroadcast {
+    pub error_code: String,
+    pub controller_ready: bool,
+    pub tool_ready: bool,
+    pub timestamp: String,
+    pub text: String,
+}
+
+impl AlarmBroadcast {
+    /// Highest MID 0071 revision the simulator produces
+    pub const MAX_REVISION: u16 = 2;
+
+    /// Create an alarm stamped with the current local time
+    pub fn new(error_code: String, controller_ready: bool, tool_ready: bool, text: String) -> Self {
+        Self {
+            error_code,
+            controller_ready,
+            tool_ready,
+            timestamp: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+            text,
+        }
+    }
+
+    /// Serialize the requested revision (clamped to 1..=MAX_REVISION)
+    pub fn serialize_revision(&self, revision: u16) -> Result<Vec<u8>, ProtocolError> {
+        let mut builder = FieldBuilder::new()
+            .add_str(Some(1), &self.error_code, 4)
+            .add_int(Some(2), self.controller_ready as i32, 1)
+            .add_int(Some(3), self.tool_ready as i32, 1)
+            .add_timestamp(Some(4), &self.timestamp);
+        if revision >= 2 {
+            builder = builder.add_text(Some(5), &self.text, 50);
+        }
+        builder.build()
+    }
+}
+
+impl ResponseData for AlarmBroadcast {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        self.serialize_revision(1)
+    }
+}
+
+/// MID 0074 - Alarm acknowledged on controller (sent to alarm subscribers)
+///
+/// Revision 1 fields:
+/// - 01: Error code (4 characters)
+#[derive(Debug, Clone)]
+pub struct AlarmAcknowledgedBroadcast {
+    pub error_code: String,
+}
+
+impl AlarmAcknowledgedBroadcast {
+    pub fn new(error_code: String) -> Self {
+        Self { error_code }
+    }
+}
+
+impl ResponseData for AlarmAcknowledgedBroadcast {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        FieldBuilder::new()
+            .add_str(Some(1), &self.error_code, 4)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alarm_serialization() {
+        let alarm = AlarmBroadcast {
+            error_code: "E501".to_string(),
+            controller_ready: true,
+            tool_ready: false,
+            timestamp: "2024-01-15:10:30:45".to_string(),
+            text: "Wiederholungsgrenze erreicht".to_string(),
+        };
+        let data = alarm.serialize().unwrap();
+
+        assert_eq!(&data[..], b"01E501021030042024-01-15:10:30:45");
+
+        let rev2 = alarm.serialize_revision(2).unwrap();
+        assert_eq!(rev2.len(), data.len() + 2 + 50);
+        assert!(rev2.starts_with(&data));
+        assert!(rev2[data.len()..].starts_with(b"05Wiederholungsgrenze erreicht "));
+    }
+}
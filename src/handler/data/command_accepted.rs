@@ -1,3 +1,5 @@
+use crate::protocol::Mid;
+use crate::protocol::ProtocolError;
 use crate::protocol::field::FieldBuilder;
 use crate::protocol::response_data::ResponseData;
 
@@ -6,7 +8,7 @@ pub struct CommandAccepted {
 }
 
 impl ResponseData for CommandAccepted {
-    fn serialize(&self) -> Vec<u8> {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
         let builder = FieldBuilder::new().add_int(None, self.accepted_mid as i32, 4);
         builder.build()
     }
@@ -18,8 +20,10 @@ impl CommandAccepted {
         Self { accepted_mid: 0 }
     }
 
-    pub fn with_mid(mid: u32) -> Self {
-        Self { accepted_mid: mid }
+    pub fn with_mid(mid: impl Into<Mid>) -> Self {
+        Self {
+            accepted_mid: u32::from(mid.into().value()),
+        }
     }
 }
 
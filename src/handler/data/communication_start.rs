@@ -1,6 +1,10 @@
+use crate::protocol::ProtocolError;
 use crate::protocol::field::FieldBuilder;
 use crate::protocol::response_data::ResponseData;
 
+/// Open Protocol version reported in MID 0002 revision 3
+pub const OPEN_PROTOCOL_VERSION: &str = "2.8.0";
+
 /// MID 0002 - Communication start acknowledge
 ///
 /// Response sent after receiving MID 0001 to acknowledge connection
@@ -17,9 +21,21 @@ pub struct CommunicationStartAck {
 
     /// Supplier Code (Parameter 04) - Optional
     pub supplier_code: Option<String>,
+
+    /// Open Protocol version (Parameter 05, revision 3)
+    pub open_protocol_version: String,
+
+    /// Controller software version (Parameter 06, revision 3)
+    pub software_version: String,
+
+    /// Tool software version (Parameter 07, revision 3)
+    pub tool_software_version: String,
 }
 
 impl CommunicationStartAck {
+    /// Highest revision the simulator produces
+    pub const MAX_REVISION: u16 = 3;
+
     /// Create a new communication start acknowledge with default values
     pub fn new() -> Self {
         Self {
@@ -27,6 +43,9 @@ impl CommunicationStartAck {
             channel_id: 1,
             controller_name: "Simulator".to_string(),
             supplier_code: Some("SIM".to_string()),
+            open_protocol_version: OPEN_PROTOCOL_VERSION.to_string(),
+            software_version: String::new(),
+            tool_software_version: String::new(),
         }
     }
 
@@ -42,31 +61,54 @@ impl CommunicationStartAck {
             channel_id,
             controller_name,
             supplier_code,
+            ..Self::new()
         }
     }
-}
 
-impl Default for CommunicationStartAck {
-    fn default() -> Self {
-        Self::new()
+    /// Set the software versions reported in revision 3
+    pub fn versions(mut self, software_version: String, tool_software_version: String) -> Self {
+        self.software_version = software_version;
+        self.tool_software_version = tool_software_version;
+        self
     }
-}
 
-impl ResponseData for CommunicationStartAck {
-    fn serialize(&self) -> Vec<u8> {
+    /// Serialize the requested revision (clamped to 1..=MAX_REVISION)
+    ///
+    /// Revisions 1 and 2 share a layout (the supplier code is sent whenever
+    /// it is set); revision 3 appends the protocol and software versions.
+    pub fn serialize_revision(&self, revision: u16) -> Result<Vec<u8>, ProtocolError> {
         let mut builder = FieldBuilder::new()
             .add_int(Some(1), self.cell_id as i32, 4)
             .add_int(Some(2), self.channel_id as i32, 2)
-            .add_str(Some(3), &self.controller_name, 25);
+            .add_text(Some(3), &self.controller_name, 25);
 
         if let Some(ref supplier) = self.supplier_code {
             builder = builder.add_str(Some(4), supplier, 3);
         }
 
+        if revision >= 3 {
+            builder = builder
+                .add_str(Some(5), &self.open_protocol_version, 19)
+                .add_str(Some(6), &self.software_version, 19)
+                .add_str(Some(7), &self.tool_software_version, 19);
+        }
+
         builder.build()
     }
 }
 
+impl Default for CommunicationStartAck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseData for CommunicationStartAck {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        self.serialize_revision(1)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,7 +116,7 @@ mod tests {
     #[test]
     fn test_communication_start_ack_serialization() {
         let ack = CommunicationStartAck::new();
-        let data = ack.serialize();
+        let data = ack.serialize().unwrap();
 
         // Should contain parameters 01, 02, 03, and 04
         assert!(!data.is_empty());
@@ -88,8 +130,29 @@ mod tests {
             "TestController".to_string(),
             Some("TST".to_string()),
         );
-        let data = ack.serialize();
+        let data = ack.serialize().unwrap();
 
         assert!(!data.is_empty());
     }
+
+    #[test]
+    fn test_revision_3_reports_versions() {
+        let ack = CommunicationStartAck::with_values(1, 1, "Ctrl".to_string(), None)
+            .versions("FW 2.1".to_string(), "TOOL 1.4".to_string());
+
+        let rev1 = ack.serialize_revision(1).unwrap();
+        let rev3 = ack
+            .serialize_revision(CommunicationStartAck::MAX_REVISION)
+            .unwrap();
+        assert_eq!(&rev1[..], ack.serialize().unwrap().as_slice());
+        assert!(rev3.starts_with(&rev1));
+        let tail = String::from_utf8(rev3[rev1.len()..].to_vec()).unwrap();
+        assert_eq!(
+            tail,
+            format!(
+                "05{:<19}06{:<19}07{:<19}",
+                OPEN_PROTOCOL_VERSION, "FW 2.1", "TOOL 1.4"
+            )
+        );
+    }
 }
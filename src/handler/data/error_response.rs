@@ -1,5 +1,6 @@
-use crate::protocol::field::FieldBuilder;
+use crate::protocol::field::{FieldBuilder, OverflowPolicy};
 use crate::protocol::response_data::ResponseData;
+use crate::protocol::{Mid, ProtocolError, Response};
 
 /// MID 0004 - Error/NAK Response
 ///
@@ -35,14 +36,18 @@ pub enum ErrorCode {
     SubscriptionAlreadyExists = 8,
     /// Subscription does not exist
     SubscriptionDoesNotExist = 9,
-    /// Generic error
+    /// Controller is in observer mode (simulator: read-only connection)
+    ObserverMode = 10,
+    /// Communication start (MID 0001) not completed in time
+    HandshakeTimeout = 98,
+    /// Generic error; also sent for MIDs the controller does not support
     GenericError = 99,
 }
 
 impl ErrorResponse {
-    pub fn new(failed_mid: u16, error_code: ErrorCode) -> Self {
+    pub fn new(failed_mid: impl Into<Mid>, error_code: ErrorCode) -> Self {
         Self {
-            failed_mid,
+            failed_mid: failed_mid.into().value(),
             error_code,
         }
     }
@@ -66,16 +71,25 @@ impl ErrorResponse {
     }
 
     /// Generic error
-    pub fn generic(failed_mid: u16) -> Self {
+    pub fn generic(failed_mid: impl Into<Mid>) -> Self {
         Self::new(failed_mid, ErrorCode::GenericError)
     }
+
+    /// MID 0004 response with the given revision
+    ///
+    /// Unlike other data MIDs this cannot fail, so errors can always be reported.
+    pub fn into_response(self, revision: u16) -> Response {
+        Response::from_data(Mid::COMMAND_ERROR, revision, self)
+            .expect("MID 0004 fields wrap instead of overflowing")
+    }
 }
 
 impl ResponseData for ErrorResponse {
-    fn serialize(&self) -> Vec<u8> {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
         // Format: Failed MID (4 digits) + Error Code (2 digits)
         let builder = FieldBuilder::new()
-            .add_int(None, self.failed_mid as i32, 4)
+            .with_overflow(OverflowPolicy::Wrap)
+            .add_int(None, self.failed_mid, 4)
             .add_int(None, self.error_code as i32, 2);
 
         builder.build()
@@ -94,8 +108,8 @@ mod tests {
 
     #[test]
     fn test_error_response_serialization() {
-        let error = ErrorResponse::new(18, ErrorCode::ParameterSetNotFound);
-        let data = error.serialize();
+        let error = ErrorResponse::new(Mid::SELECT_PSET, ErrorCode::ParameterSetNotFound);
+        let data = error.serialize().unwrap();
 
         // Should contain MID (4 chars) + error code (2 chars) = 6 bytes
         assert_eq!(data.len(), 6);
@@ -105,7 +119,14 @@ mod tests {
     #[test]
     fn test_revision_unsupported() {
         let error = ErrorResponse::revision_unsupported(1);
-        let data = error.serialize();
+        let data = error.serialize().unwrap();
         assert_eq!(&data[..], b"000101");
     }
+
+    #[test]
+    fn test_into_response_never_fails() {
+        let response = ErrorResponse::generic(12345).into_response(1);
+        assert_eq!(response.mid, Mid::COMMAND_ERROR.value());
+        assert_eq!(response.data, b"234599");
+    }
 }
@@ -0,0 +1,66 @@
+use crate::audit::AuditEntry;
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+
+/// Maximum number of entries in a single upload reply
+pub const EVENT_LOG_MAX_ENTRIES: usize = 50;
+
+/// MID 9101 - Event log upload reply (simulator extension)
+///
+/// Revision 1 fields:
+/// - 01: Number of entries (3 digits)
+/// - Per entry, without parameter IDs:
+///   - Entry ID (10 digits)
+///   - Time (YYYY-MM-DD:HH:MM:SS)
+///   - Category code (2 digits)
+///   - Description (40 characters, space padded)
+#[derive(Debug, Clone)]
+pub struct EventLogUpload {
+    pub entries: Vec<AuditEntry>,
+}
+
+impl ResponseData for EventLogUpload {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        let entries = &self.entries[..self.entries.len().min(EVENT_LOG_MAX_ENTRIES)];
+        let mut builder = FieldBuilder::new().add_int(Some(1), entries.len() as i32, 3);
+
+        for entry in entries {
+            // Fixed-width fields are byte based; keep descriptions ASCII
+            let description: String = entry
+                .description
+                .chars()
+                .map(|c| if c.is_ascii() { c } else { '?' })
+                .collect();
+            builder = builder
+                .add_str(None, format!("{:010}", entry.id % 10_000_000_000), 10)
+                .add_timestamp(None, &entry.timestamp)
+                .add_int(None, entry.category.code() as i32, 2)
+                .add_str(None, description, 40);
+        }
+
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditCategory;
+
+    #[test]
+    fn test_event_log_serialization() {
+        let upload = EventLogUpload {
+            entries: vec![AuditEntry {
+                id: 42,
+                timestamp: "2024-01-15:10:30:45".to_string(),
+                category: AuditCategory::Tool,
+                description: "Tool disabled".to_string(),
+            }],
+        };
+        let data = upload.serialize().unwrap();
+
+        assert_eq!(data.len(), 5 + 10 + 19 + 2 + 40);
+        assert!(data.starts_with(b"0100100000000422024-01-15:10:30:4502Tool disabled"));
+    }
+}
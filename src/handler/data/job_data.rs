@@ -0,0 +1,113 @@
+use crate::job::Job;
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+
+/// Channel reported for every job step (the simulator has one channel)
+pub const JOB_STEP_CHANNEL_ID: u32 = 1;
+
+/// MID 0033 - Job data upload reply
+///
+/// Revision 1 fields:
+/// - 01: Job ID (2 digits)
+/// - 02: Job name (25 characters)
+/// - 03: Forced order (1 digit, 1 = forced: steps run in the listed order)
+/// - 04: Max time for first tightening (4 digits, 0 = no limit)
+/// - 05: Max time to complete job (5 digits, 0 = no limit)
+/// - 06: Job batch mode (1 digit, 0 = only OK tightenings are counted)
+/// - 07: Lock at job done (1 digit)
+/// - 08: Use line control (1 digit)
+/// - 09: Repeat job (1 digit)
+/// - 10: Tool loosening (1 digit, 0 = enabled)
+/// - 11: Reserved (1 digit)
+/// - 12: Number of parameter sets (2 digits)
+/// - 13: Job list, one `CC:PPP:A:BB;` entry per step (channel, PSET ID,
+///   auto select, batch size)
+///
+/// Revision 2 widens the job ID to 4 digits, as in MID 0031.
+#[derive(Debug, Clone)]
+pub struct JobData {
+    pub job: Job,
+}
+
+impl JobData {
+    /// Highest revision the simulator produces
+    pub const MAX_REVISION: u16 = 2;
+
+    /// Serialize the requested revision (clamped to 1..=MAX_REVISION)
+    pub fn serialize_revision(&self, revision: u16) -> Result<Vec<u8>, ProtocolError> {
+        let job_id_width = if revision >= 2 { 4 } else { 2 };
+        let mut job_list = String::new();
+        for step in &self.job.steps {
+            job_list.push_str(&format!(
+                "{:02}:{:03}:0:{:02};",
+                JOB_STEP_CHANNEL_ID, step.pset_id, step.batch_size
+            ));
+        }
+
+        FieldBuilder::new()
+            .add_int(Some(1), self.job.id, job_id_width)
+            .add_text(Some(2), &self.job.name, 25)
+            .add_int(Some(3), 1, 1)
+            .add_int(Some(4), 0, 4)
+            .add_int(Some(5), 0, 5)
+            .add_int(Some(6), 0, 1)
+            .add_int(Some(7), 0, 1)
+            .add_int(Some(8), 0, 1)
+            .add_int(Some(9), 0, 1)
+            .add_int(Some(10), 0, 1)
+            .add_int(Some(11), 0, 1)
+            .add_int(Some(12), self.job.steps.len() as i64, 2)
+            .add_str(Some(13), &job_list, job_list.len())
+            .build()
+    }
+}
+
+impl ResponseData for JobData {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        self.serialize_revision(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::JobStep;
+
+    #[test]
+    fn test_job_data_revisions() {
+        let data = JobData {
+            job: Job {
+                id: 12,
+                name: "Door Module".to_string(),
+                steps: vec![
+                    JobStep {
+                        pset_id: 1,
+                        batch_size: 4,
+                    },
+                    JobStep {
+                        pset_id: 14,
+                        batch_size: 12,
+                    },
+                ],
+                description: None,
+            },
+        };
+
+        let rev1 = "0112\
+                      02Door Module              \
+                      031\
+                      040000\
+                      0500000\
+                      060070080090100110\
+                      1202\
+                      1301:001:0:04;01:014:0:12;";
+        assert_eq!(
+            String::from_utf8(data.serialize_revision(1).unwrap()).unwrap(),
+            rev1
+        );
+        let rev2 = String::from_utf8(data.serialize_revision(2).unwrap()).unwrap();
+        assert!(rev2.starts_with("010012"));
+        assert_eq!(rev2.len(), rev1.len() + 2);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+
+/// Highest job ID revision 1 can carry (2-digit field)
+pub const MAX_REVISION_1_JOB_ID: u32 = 99;
+
+/// MID 0031 - Job ID upload reply
+///
+/// Revision 1:
+/// - Number of jobs (2 digits)
+/// - Each job ID (2 digits)
+///
+/// Revision 2 widens both fields to 4 digits.
+#[derive(Debug, Clone, Default)]
+pub struct JobIdUpload {
+    pub job_ids: Vec<u32>,
+}
+
+impl JobIdUpload {
+    /// Highest revision the simulator produces
+    pub const MAX_REVISION: u16 = 2;
+
+    /// Serialize the requested revision (clamped to 1..=MAX_REVISION)
+    ///
+    /// Revision 1 leaves out IDs above 99, which its field cannot hold.
+    pub fn serialize_revision(&self, revision: u16) -> Result<Vec<u8>, ProtocolError> {
+        let width = if revision >= 2 { 4 } else { 2 };
+        let ids: Vec<u32> = self
+            .job_ids
+            .iter()
+            .copied()
+            .filter(|id| revision >= 2 || *id <= MAX_REVISION_1_JOB_ID)
+            .collect();
+        let mut builder = FieldBuilder::new().add_int(None, ids.len() as i64, width);
+        for id in ids {
+            builder = builder.add_int(None, id, width);
+        }
+        builder.build()
+    }
+}
+
+impl ResponseData for JobIdUpload {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        self.serialize_revision(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_id_upload_revisions() {
+        let upload = JobIdUpload {
+            job_ids: vec![1, 12, 150],
+        };
+
+        assert_eq!(&upload.serialize_revision(1).unwrap()[..], b"020112");
+        assert_eq!(
+            &upload.serialize_revision(2).unwrap()[..],
+            b"0003000100120150"
+        );
+        assert_eq!(
+            &JobIdUpload::default().serialize_revision(1).unwrap()[..],
+            b"00"
+        );
+    }
+}
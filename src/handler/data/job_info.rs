@@ -0,0 +1,81 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+
+/// MID 0035 - Job info
+///
+/// Sent to MID 0034 subscribers when a job is selected (MID 0038) or
+/// restarted (MID 0039)
+///
+/// Revision 1 fields:
+/// - 01: Job ID (2 digits)
+/// - 02: Job status (1 digit, 0 = not completed, 1 = OK, 2 = NOK)
+/// - 03: Job batch mode (1 digit, 0 = only OK tightenings are counted)
+/// - 04: Job batch size (4 digits, tightenings needed to finish the job)
+/// - 05: Job batch counter (4 digits)
+/// - 06: Timestamp (19 characters)
+///
+/// Revision 2 widens the job ID to 4 digits, as in MID 0031.
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    pub job_id: u32,
+    pub job_status: u8,
+    pub batch_size: u32,
+    pub batch_counter: u32,
+    pub timestamp: String,
+}
+
+impl JobInfo {
+    /// Highest revision the simulator produces
+    pub const MAX_REVISION: u16 = 2;
+
+    /// A job that was just (re)started: not completed, nothing counted yet
+    pub fn started(job_id: u32, batch_size: u32) -> Self {
+        Self {
+            job_id,
+            job_status: 0,
+            batch_size,
+            batch_counter: 0,
+            timestamp: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+        }
+    }
+
+    /// Serialize the requested revision (clamped to 1..=MAX_REVISION)
+    pub fn serialize_revision(&self, revision: u16) -> Result<Vec<u8>, ProtocolError> {
+        let job_id_width = if revision >= 2 { 4 } else { 2 };
+        FieldBuilder::new()
+            .add_int(Some(1), self.job_id, job_id_width)
+            .add_int(Some(2), self.job_status, 1)
+            .add_int(Some(3), 0, 1)
+            .add_int(Some(4), self.batch_size, 4)
+            .add_int(Some(5), self.batch_counter, 4)
+            .add_timestamp(Some(6), &self.timestamp)
+            .build()
+    }
+}
+
+impl ResponseData for JobInfo {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        self.serialize_revision(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_info_revisions() {
+        let info = JobInfo {
+            timestamp: "2024-01-15:10:30:45".to_string(),
+            ..JobInfo::started(2, 8)
+        };
+
+        assert_eq!(
+            String::from_utf8(info.serialize_revision(1).unwrap()).unwrap(),
+            "0102020030040008050000062024-01-15:10:30:45"
+        );
+        let rev2 = String::from_utf8(info.serialize_revision(2).unwrap()).unwrap();
+        assert!(rev2.starts_with("010002020"), "{}", rev2);
+    }
+}
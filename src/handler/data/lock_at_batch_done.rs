@@ -0,0 +1,39 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+
+/// MID 0022 - Lock at batch done upload
+///
+/// Sent to subscribers when a batch is completed
+///
+/// Fields (no parameter IDs):
+/// - Relay status (1 digit): 1 if the tool was locked at batch done, 0 if not
+#[derive(Debug, Clone)]
+pub struct LockAtBatchDoneUpload {
+    pub locked: bool,
+}
+
+impl LockAtBatchDoneUpload {
+    pub fn new(locked: bool) -> Self {
+        Self { locked }
+    }
+}
+
+impl ResponseData for LockAtBatchDoneUpload {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        FieldBuilder::new()
+            .add_int(None, self.locked as i32, 1)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_at_batch_done_upload() {
+        assert_eq!(LockAtBatchDoneUpload::new(true).serialize().unwrap(), b"1");
+        assert_eq!(LockAtBatchDoneUpload::new(false).serialize().unwrap(), b"0");
+    }
+}
@@ -3,23 +3,43 @@
 //! Each module defines typed data structures for specific MID responses,
 //! implementing the ResponseData trait for automatic serialization.
 
+pub mod alarm;
 pub mod command_accepted;
 pub mod communication_start;
 pub mod error_response;
+pub mod event_log;
+pub mod job_data;
+pub mod job_id_upload;
+pub mod job_info;
+pub mod lock_at_batch_done;
 pub mod multi_spindle_result_broadcast;
 pub mod multi_spindle_status_broadcast;
+pub mod pset_id_upload;
 pub mod pset_selected;
+pub mod telemetry;
 pub mod tightening_result;
+pub mod tool_data;
+pub mod user_data_upload;
 pub mod vehicle_id_broadcast;
 
+pub use alarm::{AlarmAcknowledgedBroadcast, AlarmBroadcast};
 pub use command_accepted::CommandAccepted;
 pub use communication_start::CommunicationStartAck;
 #[allow(unused_imports)]
 pub use error_response::ErrorCode;
 pub use error_response::ErrorResponse;
+pub use event_log::EventLogUpload;
+pub use job_data::JobData;
+pub use job_id_upload::JobIdUpload;
+pub use job_info::JobInfo;
+pub use lock_at_batch_done::LockAtBatchDoneUpload;
 pub use multi_spindle_result_broadcast::MultiSpindleResultBroadcast;
 pub use multi_spindle_status_broadcast::MultiSpindleStatusBroadcast;
+pub use pset_id_upload::PsetIdUpload;
 #[allow(unused_imports)]
 pub use pset_selected::PsetSelected;
+pub use telemetry::TelemetryUpload;
 pub use tightening_result::TighteningResult;
+pub use tool_data::ToolDataUpload;
+pub use user_data_upload::UserDataUpload;
 pub use vehicle_id_broadcast::VehicleIdBroadcast;
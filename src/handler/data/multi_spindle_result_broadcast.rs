@@ -1,6 +1,8 @@
 use crate::multi_spindle::MultiSpindleResult;
+use crate::protocol::ProtocolError;
 use crate::protocol::field::FieldBuilder;
 use crate::protocol::response_data::ResponseData;
+use crate::protocol::units::{AngleDeciDeg, TorqueCnm};
 
 /// MID 0101 - Multi-spindle result broadcast
 /// Sent to subscribed clients after each sync tightening operation
@@ -13,12 +15,12 @@ pub struct MultiSpindleResultBroadcast {
     pub batch_size: u32,
     pub batch_counter: u32,
     pub batch_status: u8, // 0=NOK, 1=OK, 2=not used
-    pub torque_min: i32,
-    pub torque_max: i32,
-    pub torque_target: i32,
-    pub angle_min: i32,
-    pub angle_max: i32,
-    pub angle_target: i32,
+    pub torque_min: TorqueCnm,
+    pub torque_max: TorqueCnm,
+    pub torque_target: TorqueCnm,
+    pub angle_min: AngleDeciDeg,
+    pub angle_max: AngleDeciDeg,
+    pub angle_target: AngleDeciDeg,
     pub last_change_timestamp: String,
 }
 
@@ -41,20 +43,20 @@ impl MultiSpindleResultBroadcast {
             batch_counter,
             batch_status,
             // Default torque limits (50.00 Nm target, ±5.00 Nm range)
-            torque_min: 4500,    // 45.00 Nm
-            torque_max: 5500,    // 55.00 Nm
-            torque_target: 5000, // 50.00 Nm
+            torque_min: TorqueCnm::from_nm(45.0),
+            torque_max: TorqueCnm::from_nm(55.0),
+            torque_target: TorqueCnm::from_nm(50.0),
             // Default angle limits (180° target, ±10° range)
-            angle_min: 170,
-            angle_max: 190,
-            angle_target: 180,
+            angle_min: AngleDeciDeg::from_degrees(170.0),
+            angle_max: AngleDeciDeg::from_degrees(190.0),
+            angle_target: AngleDeciDeg::from_degrees(180.0),
             last_change_timestamp: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
         }
     }
 }
 
 impl ResponseData for MultiSpindleResultBroadcast {
-    fn serialize(&self) -> Vec<u8> {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
         // MID 0101 Revision 1, 2, 3 format
         let mut builder = FieldBuilder::new();
 
@@ -62,12 +64,7 @@ impl ResponseData for MultiSpindleResultBroadcast {
         builder = builder.add_int(Some(1), self.result.spindle_count as i32, 2);
 
         // Parameter 02: VIN Number (25 bytes)
-        let vin = if self.vin_number.len() >= 25 {
-            self.vin_number[..25].to_string()
-        } else {
-            format!("{:<25}", self.vin_number)
-        };
-        builder = builder.add_str(Some(2), &vin, 25);
+        builder = builder.add_text(Some(2), &self.vin_number, 25);
 
         // Parameter 03: Job ID (2 bytes)
         builder = builder.add_int(Some(3), self.job_id as i32, 2);
@@ -85,28 +82,28 @@ impl ResponseData for MultiSpindleResultBroadcast {
         builder = builder.add_int(Some(7), self.batch_status as i32, 1);
 
         // Parameter 08: Torque Min limit (6 bytes, Nm * 100)
-        builder = builder.add_int(Some(8), self.torque_min, 6);
+        builder = builder.add_torque(Some(8), self.torque_min, 6);
 
         // Parameter 09: Torque Max limit (6 bytes, Nm * 100)
-        builder = builder.add_int(Some(9), self.torque_max, 6);
+        builder = builder.add_torque(Some(9), self.torque_max, 6);
 
         // Parameter 10: Torque final target (6 bytes, Nm * 100)
-        builder = builder.add_int(Some(10), self.torque_target, 6);
+        builder = builder.add_torque(Some(10), self.torque_target, 6);
 
         // Parameter 11: Angle Min (5 bytes, degrees)
-        builder = builder.add_int(Some(11), self.angle_min, 5);
+        builder = builder.add_angle(Some(11), self.angle_min, 5);
 
         // Parameter 12: Angle Max (5 bytes, degrees)
-        builder = builder.add_int(Some(12), self.angle_max, 5);
+        builder = builder.add_angle(Some(12), self.angle_max, 5);
 
         // Parameter 13: Final Angle Target (5 bytes, degrees)
-        builder = builder.add_int(Some(13), self.angle_target, 5);
+        builder = builder.add_angle(Some(13), self.angle_target, 5);
 
         // Parameter 14: Date/time of last change (19 bytes)
-        builder = builder.add_str(Some(14), &self.last_change_timestamp, 19);
+        builder = builder.add_timestamp(Some(14), &self.last_change_timestamp);
 
         // Parameter 15: Time stamp (19 bytes)
-        builder = builder.add_str(Some(15), &self.result.timestamp, 19);
+        builder = builder.add_timestamp(Some(15), &self.result.timestamp);
 
         // Parameter 16: Sync tightening ID (5 bytes)
         builder = builder.add_int(Some(16), self.result.result_id as i32, 5);
@@ -130,14 +127,14 @@ impl ResponseData for MultiSpindleResultBroadcast {
             // Byte 6: Individual torque status (0=Low, 1=OK, 2=High)
             builder = builder.add_int(None, spindle.torque_status as i32, 1);
 
-            // Bytes 7-12: Torque result (Nm * 100, already stored as integer)
-            builder = builder.add_int(None, spindle.torque, 6);
+            // Bytes 7-12: Torque result (Nm * 100)
+            builder = builder.add_torque(None, spindle.torque, 6);
 
             // Byte 13: Individual angle status (0=NOK, 1=OK)
             builder = builder.add_int(None, spindle.angle_status as i32, 1);
 
             // Bytes 14-18: Angle value (degrees)
-            builder = builder.add_int(None, spindle.angle, 5);
+            builder = builder.add_angle(None, spindle.angle, 5);
         }
 
         builder = builder.add_int(Some(18), 0, 0); // Parameter marker for spindle status section
@@ -156,19 +153,19 @@ mod tests {
         let spindle1 = SpindleResult {
             spindle_id: 1,
             channel_id: 1,
-            torque: 5000,     // 50.00 Nm
-            angle: 1800,      // 180.0 degrees
-            torque_status: 1, // OK
-            angle_status: 0,  // OK
+            torque: TorqueCnm(5000),   // 50.00 Nm
+            angle: AngleDeciDeg(1800), // 180.0 degrees
+            torque_status: 1,          // OK
+            angle_status: 0,           // OK
         };
 
         let spindle2 = SpindleResult {
             spindle_id: 2,
             channel_id: 2,
-            torque: 5100,     // 51.00 Nm
-            angle: 1850,      // 185.0 degrees
-            torque_status: 1, // OK
-            angle_status: 0,  // OK
+            torque: TorqueCnm(5100),   // 51.00 Nm
+            angle: AngleDeciDeg(1850), // 185.0 degrees
+            torque_status: 1,          // OK
+            angle_status: 0,           // OK
         };
 
         let spindles = vec![spindle1, spindle2];
@@ -184,7 +181,7 @@ mod tests {
             2,  // batch_status (not used)
         );
 
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
         let data_str = String::from_utf8_lossy(&data);
 
         // Verify parameter markers and key fields
@@ -199,6 +196,9 @@ mod tests {
 
         // Parameter 17: Overall status should be "1" (OK, since both spindles OK)
         assert!(data_str.contains("171"));
+
+        // Spindle 1: torque 50.00 Nm, angle sent in whole degrees
+        assert!(data_str.contains("005000000180"));
     }
 
     #[test]
@@ -206,8 +206,8 @@ mod tests {
         let spindle1 = SpindleResult {
             spindle_id: 1,
             channel_id: 1,
-            torque: 5000,
-            angle: 1800,
+            torque: TorqueCnm(5000),
+            angle: AngleDeciDeg(1800),
             torque_status: 1,
             angle_status: 0,
         };
@@ -215,8 +215,8 @@ mod tests {
         let spindle2 = SpindleResult {
             spindle_id: 2,
             channel_id: 2,
-            torque: 4000, // Too low
-            angle: 1850,
+            torque: TorqueCnm(4000), // Too low
+            angle: AngleDeciDeg(1850),
             torque_status: 0, // NOK (low)
             angle_status: 0,
         };
@@ -226,7 +226,7 @@ mod tests {
 
         let broadcast = MultiSpindleResultBroadcast::new(result, "VIN".to_string(), 1, 10, 0, 0, 2);
 
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
         let data_str = String::from_utf8_lossy(&data);
 
         // Overall status should be "0" (NOK, since spindle 2 failed)
@@ -1,4 +1,5 @@
 use crate::multi_spindle::MultiSpindleStatus;
+use crate::protocol::ProtocolError;
 use crate::protocol::field::FieldBuilder;
 use crate::protocol::response_data::ResponseData;
 
@@ -25,13 +26,14 @@ impl MultiSpindleStatusBroadcast {
             status: status_code,
             spindle_count,
             timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            cycle_id: 0,
         };
         Self { status }
     }
 }
 
 impl ResponseData for MultiSpindleStatusBroadcast {
-    fn serialize(&self) -> Vec<u8> {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
         // MID 0091 Revision 1 format:
         // - Sync tightening ID (4 digits)
         // - Status (1 digit): 0=Waiting, 1=Running, 2=Completed
@@ -42,7 +44,7 @@ impl ResponseData for MultiSpindleStatusBroadcast {
             .add_int(None, self.status.sync_id as i32, 4)
             .add_int(None, self.status.status as i32, 1)
             .add_int(None, self.status.spindle_count as i32, 2)
-            .add_str(None, &self.status.timestamp, 19)
+            .add_timestamp(None, &self.status.timestamp)
             .build()
     }
 }
@@ -58,10 +60,11 @@ mod tests {
             status: 1, // Running
             spindle_count: 4,
             timestamp: "2024-01-15 14:30:45".to_string(),
+            cycle_id: 0,
         };
 
         let broadcast = MultiSpindleStatusBroadcast::new(status);
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
 
         // Verify structure: sync_id(4) + status(1) + count(2) + timestamp(19)
         let data_str = String::from_utf8_lossy(&data);
@@ -75,14 +78,14 @@ mod tests {
         // spindle_count should be "04"
         assert_eq!(&data_str[5..7], "04");
 
-        // timestamp should be the full timestamp
-        assert_eq!(&data_str[7..26], "2024-01-15 14:30:45");
+        // timestamp in the configured layout (the specified one by default)
+        assert_eq!(&data_str[7..26], "2024-01-15:14:30:45");
     }
 
     #[test]
     fn test_multi_spindle_status_broadcast_waiting() {
         let broadcast = MultiSpindleStatusBroadcast::from_sync_id(100, 2, 0);
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
         let data_str = String::from_utf8_lossy(&data);
 
         assert_eq!(&data_str[0..4], "0100"); // sync_id
@@ -93,7 +96,7 @@ mod tests {
     #[test]
     fn test_multi_spindle_status_broadcast_completed() {
         let broadcast = MultiSpindleStatusBroadcast::from_sync_id(999, 16, 2);
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
         let data_str = String::from_utf8_lossy(&data);
 
         assert_eq!(&data_str[0..4], "0999"); // sync_id
@@ -104,7 +107,7 @@ mod tests {
     #[test]
     fn test_multi_spindle_status_broadcast_length() {
         let broadcast = MultiSpindleStatusBroadcast::from_sync_id(1, 2, 1);
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
 
         // Total: 4 + 1 + 2 + 19 = 26 bytes
         assert_eq!(data.len(), 26);
@@ -0,0 +1,68 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+
+/// Highest PSET ID that fits the 3-digit field
+pub const MAX_PSET_ID: u32 = 999;
+
+/// MID 0011 - Parameter set ID upload reply
+///
+/// Revision 1 (fields without parameter IDs):
+/// - Number of parameter sets (3 digits)
+/// - Each parameter set ID (3 digits)
+///
+/// Revision 2 follows each ID with the date of the last change in its
+/// settings (YYYY-MM-DD:HH:MM:SS, see `pset_history`).
+#[derive(Debug, Clone, Default)]
+pub struct PsetIdUpload {
+    /// (PSET ID, last change date)
+    pub psets: Vec<(u32, String)>,
+}
+
+impl PsetIdUpload {
+    /// Highest revision the simulator produces
+    pub const MAX_REVISION: u16 = 2;
+
+    /// Serialize the requested revision (clamped to 1..=MAX_REVISION)
+    pub fn serialize_revision(&self, revision: u16) -> Result<Vec<u8>, ProtocolError> {
+        let mut builder = FieldBuilder::new().add_int(None, self.psets.len() as i64, 3);
+        for (id, last_change) in &self.psets {
+            builder = builder.add_int(None, *id, 3);
+            if revision >= 2 {
+                builder = builder.add_timestamp(None, last_change);
+            }
+        }
+        builder.build()
+    }
+}
+
+impl ResponseData for PsetIdUpload {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        self.serialize_revision(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pset_id_upload_revisions() {
+        let upload = PsetIdUpload {
+            psets: vec![
+                (1, "2024-01-01:00:00:00".to_string()),
+                (12, "2025-03-04:05:06:07".to_string()),
+            ],
+        };
+
+        assert_eq!(&upload.serialize_revision(1).unwrap()[..], b"002001012");
+        assert_eq!(
+            &upload.serialize_revision(2).unwrap()[..],
+            b"0020012024-01-01:00:00:000122025-03-04:05:06:07"
+        );
+        assert_eq!(
+            &PsetIdUpload::default().serialize_revision(2).unwrap()[..],
+            b"000"
+        );
+    }
+}
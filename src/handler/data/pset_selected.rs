@@ -1,34 +1,37 @@
+use crate::protocol::ProtocolError;
 use crate::protocol::field::FieldBuilder;
 use crate::protocol::response_data::ResponseData;
 
 /// MID 0015 - Parameter Set Selected
 ///
 /// Notification sent when a parameter set is selected
+///
+/// Fields:
+/// - 01: Parameter set ID (3 digits)
+/// - 02: Date of last change in parameter set settings (YYYY-MM-DD:HH:MM:SS)
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct PsetSelected {
     /// Parameter Set ID that was selected
     pub pset_id: u32,
+    /// Date of the last change in the PSET settings (see `pset_history`)
+    pub last_change: String,
 }
 
 impl PsetSelected {
-    #[allow(dead_code)]
-    pub fn new(pset_id: u32) -> Self {
-        Self { pset_id }
+    pub fn new(pset_id: u32, last_change: String) -> Self {
+        Self {
+            pset_id,
+            last_change,
+        }
     }
 }
 
 impl ResponseData for PsetSelected {
-    fn serialize(&self) -> Vec<u8> {
-        // Format: Pset ID (3 digits padded with zeros)
-        let builder = FieldBuilder::new().add_int(None, self.pset_id as i32, 3);
-        builder.build()
-    }
-}
-
-impl Default for PsetSelected {
-    fn default() -> Self {
-        Self::new(1)
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        FieldBuilder::new()
+            .add_int(Some(1), self.pset_id as i32, 3)
+            .add_timestamp(Some(2), &self.last_change)
+            .build()
     }
 }
 
@@ -38,15 +41,15 @@ mod tests {
 
     #[test]
     fn test_pset_selected_serialization() {
-        let pset = PsetSelected::new(5);
-        let data = pset.serialize();
-        assert_eq!(&data[..], b"005");
+        let pset = PsetSelected::new(5, "2024-01-15:09:00:00".to_string());
+        let data = pset.serialize().unwrap();
+        assert_eq!(&data[..], b"01005022024-01-15:09:00:00");
     }
 
     #[test]
     fn test_pset_selected_large_id() {
-        let pset = PsetSelected::new(123);
-        let data = pset.serialize();
-        assert_eq!(&data[..], b"123");
+        let pset = PsetSelected::new(123, "2024-01-15:09:00:00".to_string());
+        let data = pset.serialize().unwrap();
+        assert_eq!(&data[..5], b"01123");
     }
 }
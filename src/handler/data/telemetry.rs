@@ -0,0 +1,62 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+use crate::telemetry::Telemetry;
+
+/// MID 9121 - Telemetry upload reply (simulator extension)
+///
+/// Revision 1 fields:
+/// - 01: Motor temperature in 0.1 °C (5 digits)
+/// - 02: Current draw in 0.01 A (5 digits)
+/// - 03: Supply voltage in 0.01 V (5 digits)
+/// - 04: Time (YYYY-MM-DD:HH:MM:SS)
+#[derive(Debug, Clone)]
+pub struct TelemetryUpload {
+    pub telemetry: Telemetry,
+    pub timestamp: String,
+}
+
+impl TelemetryUpload {
+    /// Create an upload stamped with the current local time
+    pub fn new(telemetry: Telemetry) -> Self {
+        Self {
+            telemetry,
+            timestamp: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+        }
+    }
+}
+
+impl ResponseData for TelemetryUpload {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        let scaled = |value: f64, scale: f64| (value * scale).round().clamp(0.0, 99_999.0) as i32;
+        let t = &self.telemetry;
+        FieldBuilder::new()
+            .add_int(Some(1), scaled(t.motor_temperature_c, 10.0), 5)
+            .add_int(Some(2), scaled(t.current_a, 100.0), 5)
+            .add_int(Some(3), scaled(t.supply_voltage_v, 100.0), 5)
+            .add_timestamp(Some(4), &self.timestamp)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telemetry_serialization() {
+        let upload = TelemetryUpload {
+            telemetry: Telemetry {
+                motor_temperature_c: 41.27,
+                current_a: 12.5,
+                supply_voltage_v: 23.38,
+            },
+            timestamp: "2024-01-15:10:30:45".to_string(),
+        };
+
+        assert_eq!(
+            &upload.serialize().unwrap()[..],
+            b"010041302012500302338042024-01-15:10:30:45"
+        );
+    }
+}
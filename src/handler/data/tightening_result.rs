@@ -1,6 +1,14 @@
-use crate::protocol::field::FieldBuilder;
+use crate::batch_manager::{BatchStatus, TighteningInfo};
+use crate::device_fsm::{StageResult, TighteningParams};
+use crate::protocol::ProtocolError;
+use crate::protocol::field::{Field, FieldBuilder};
 use crate::protocol::response_data::ResponseData;
+use crate::protocol::units::{AngleDeciDeg, TorqueCnm};
+use crate::pset::{Pset, TighteningStrategy};
+use crate::results::{ResultVoid, TighteningMetadata};
+use crate::state::DeviceState;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 /// MID 0061 - Last tightening result data
 ///
@@ -75,9 +83,89 @@ pub struct TighteningResult {
 
     /// Tightening ID (Parameter 23)
     pub tightening_id: Option<u32>,
+
+    /// Job sequence number (revision 2+ only, Parameter 42)
+    #[serde(default)]
+    pub job_sequence_number: u32,
+
+    /// Strategy of the PSET used (revision 2+ only, Parameter 07)
+    #[serde(default)]
+    pub strategy: TighteningStrategy,
+
+    /// Per-stage results of a multistage tightening (revision 998, Parameter 49)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<StageResult>,
+
+    /// Correlation ID of the cycle (see `DeviceState::claim_cycle`) - only
+    /// sent in MID 0061 with `[device] stamp_cycle_id`
+    #[serde(default)]
+    pub cycle_id: u64,
+
+    /// Duration of the tightening cycle in milliseconds (0 = not timed, e.g.
+    /// generated results) - not sent in MID 0061
+    #[serde(default)]
+    pub cycle_time_ms: u64,
+
+    /// HMAC-SHA256 integrity hash with `[results] signing_key` (see
+    /// `results::sign`) - not sent in MID 0061
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+
+    /// Contextual metadata (operator, station, ...) - not sent in MID 0061
+    #[serde(default, skip_serializing_if = "TighteningMetadata::is_empty")]
+    pub metadata: TighteningMetadata,
+
+    /// Set once the result was voided on the controller - not sent in MID 0061
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voided: Option<ResultVoid>,
 }
 
 impl TighteningResult {
+    /// Start building a result; see [`TighteningResultBuilder`] for the defaults
+    pub fn builder() -> TighteningResultBuilder {
+        TighteningResultBuilder::new()
+    }
+
+    /// Check that the fields are consistent with each other
+    ///
+    /// The batch counter must not exceed the batch size (when batch mode is
+    /// used), and a torque/angle reported as OK must lie inside its limits.
+    pub fn validate(&self) -> Result<(), TighteningResultError> {
+        if self.torque_min > self.torque_max {
+            return Err(TighteningResultError::InvalidTorqueLimits {
+                min: self.torque_min,
+                max: self.torque_max,
+            });
+        }
+        if self.angle_min > self.angle_max {
+            return Err(TighteningResultError::InvalidAngleLimits {
+                min: self.angle_min,
+                max: self.angle_max,
+            });
+        }
+        if self.batch_size > 0 && self.batch_counter > self.batch_size {
+            return Err(TighteningResultError::BatchCounterExceedsSize {
+                counter: self.batch_counter,
+                size: self.batch_size,
+            });
+        }
+        if self.torque_status && !(self.torque_min..=self.torque_max).contains(&self.torque) {
+            return Err(TighteningResultError::TorqueOutOfLimits {
+                torque: self.torque,
+                min: self.torque_min,
+                max: self.torque_max,
+            });
+        }
+        if self.angle_status && !(self.angle_min..=self.angle_max).contains(&self.angle) {
+            return Err(TighteningResultError::AngleOutOfLimits {
+                angle: self.angle,
+                min: self.angle_min,
+                max: self.angle_max,
+            });
+        }
+        Ok(())
+    }
+
     /// Create a new tightening result with example values
     #[allow(dead_code)]
     pub fn example() -> Self {
@@ -105,12 +193,20 @@ impl TighteningResult {
             last_pset_change: Some("2025-01-15:09:00:00".to_string()),
             batch_status: Some(true),
             tightening_id: Some(12345),
+            job_sequence_number: 0,
+            strategy: TighteningStrategy::default(),
+            stages: Vec::new(),
+            cycle_id: 0,
+            cycle_time_ms: 0,
+            signature: None,
+            metadata: TighteningMetadata::default(),
+            voided: None,
         }
     }
 }
 
 impl ResponseData for TighteningResult {
-    fn serialize(&self) -> Vec<u8> {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
         // Always send VIN (param 04) - use empty string (25 spaces) if None
         let vin = self.vin_number.as_deref().unwrap_or("");
 
@@ -130,8 +226,8 @@ impl ResponseData for TighteningResult {
         FieldBuilder::new()
             .add_int(Some(1), self.cell_id as i32, 4)
             .add_int(Some(2), self.channel_id as i32, 2)
-            .add_str(Some(3), &self.controller_name, 25)
-            .add_str(Some(4), vin, 25)
+            .add_text(Some(3), &self.controller_name, 25)
+            .add_text(Some(4), vin, 25)
             .add_int(Some(5), self.job_id as i32, 2)
             .add_int(Some(6), self.pset_id as i32, 3)
             .add_int(Some(7), self.batch_size as i32, 4)
@@ -139,22 +235,365 @@ impl ResponseData for TighteningResult {
             .add_int(Some(9), if self.tightening_status { 1 } else { 0 }, 1)
             .add_int(Some(10), if self.torque_status { 1 } else { 0 }, 1)
             .add_int(Some(11), if self.angle_status { 1 } else { 0 }, 1)
-            .add_int(Some(12), (self.torque_min * 100.0) as i32, 6)
-            .add_int(Some(13), (self.torque_max * 100.0) as i32, 6)
-            .add_int(Some(14), (self.torque_target * 100.0) as i32, 6)
-            .add_int(Some(15), (self.torque * 100.0) as i32, 6)
-            .add_int(Some(16), self.angle_min as i32, 5)
-            .add_int(Some(17), self.angle_max as i32, 5)
-            .add_int(Some(18), self.angle_target as i32, 5)
-            .add_int(Some(19), self.angle as i32, 5)
-            .add_str(Some(20), &self.timestamp, 19)
-            .add_str(Some(21), pset_change, 19)
+            .add_torque(Some(12), TorqueCnm::from_nm(self.torque_min), 6)
+            .add_torque(Some(13), TorqueCnm::from_nm(self.torque_max), 6)
+            .add_torque(Some(14), TorqueCnm::from_nm(self.torque_target), 6)
+            .add_torque(Some(15), TorqueCnm::from_nm(self.torque), 6)
+            .add_angle(Some(16), AngleDeciDeg::from_degrees(self.angle_min), 5)
+            .add_angle(Some(17), AngleDeciDeg::from_degrees(self.angle_max), 5)
+            .add_angle(Some(18), AngleDeciDeg::from_degrees(self.angle_target), 5)
+            .add_angle(Some(19), AngleDeciDeg::from_degrees(self.angle), 5)
+            .add_timestamp(Some(20), &self.timestamp)
+            .add_timestamp(Some(21), pset_change)
             .add_int(Some(22), batch_status_val, 1)
-            .add_int(Some(23), tightening_id as i32, 10)
+            .add_int(Some(23), tightening_id, 10)
             .build()
     }
 }
 
+impl TighteningResult {
+    /// Highest regular MID 0061 revision the simulator can produce
+    pub const MAX_REVISION: u16 = 2;
+
+    /// MID 0061 revision that carries per-stage results
+    pub const STAGE_RESULT_REVISION: u16 = 998;
+
+    /// Revision produced for a subscriber that asked for `requested`
+    ///
+    /// The highest supported revision not above the request, with anything
+    /// from [`Self::STAGE_RESULT_REVISION`] up mapping to that revision.
+    pub fn supported_revision(requested: u16) -> u16 {
+        if requested >= Self::STAGE_RESULT_REVISION {
+            Self::STAGE_RESULT_REVISION
+        } else {
+            requested.clamp(1, Self::MAX_REVISION)
+        }
+    }
+
+    /// Serialize for the given MID 0061 revision
+    ///
+    /// Revision 1 is the classic 23-parameter layout; revision 2 uses the
+    /// 46-parameter layout that adds the tightening strategy, and revision
+    /// 998 appends the stage results of a multistage tightening to it.
+    /// Unsupported revisions fall back as in [`Self::supported_revision`].
+    pub fn serialize_revision(&self, revision: u16) -> Result<Vec<u8>, ProtocolError> {
+        match Self::supported_revision(revision) {
+            1 => ResponseData::serialize(self),
+            Self::STAGE_RESULT_REVISION => {
+                let stage_results: String = self
+                    .stages
+                    .iter()
+                    .map(|stage| {
+                        format!(
+                            "{}{:05}",
+                            Field::from_torque(None, TorqueCnm::from_nm(stage.torque), 6).value,
+                            AngleDeciDeg::from_degrees(stage.angle).wire_value()
+                        )
+                    })
+                    .collect();
+                self.rev2_fields()
+                    .add_int(Some(47), self.stages.len() as i32, 2) // stages in multistage
+                    .add_int(Some(48), self.stages.len() as i32, 2) // stage results
+                    .add_field(Field::new(Some(49), stage_results))
+                    .build()
+            }
+            _ => self.rev2_fields().build(),
+        }
+    }
+
+    fn rev2_fields(&self) -> FieldBuilder {
+        let vin = self.vin_number.as_deref().unwrap_or("");
+        let pset_change = self.last_pset_change.as_deref().unwrap_or("");
+        let batch_status_val = match self.batch_status {
+            Some(true) => 1,
+            Some(false) => 0,
+            None => 2,
+        };
+
+        // Revision 2 reports low (0) / OK (1) / high (2) per value
+        let limit_status = |value: f64, min: f64, max: f64| {
+            if value < min {
+                0
+            } else if value > max {
+                2
+            } else {
+                1
+            }
+        };
+        let torque_status = limit_status(self.torque, self.torque_min, self.torque_max);
+        let angle_status = limit_status(self.angle, self.angle_min, self.angle_max);
+
+        let mut builder = FieldBuilder::new()
+            .add_int(Some(1), self.cell_id as i32, 4)
+            .add_int(Some(2), self.channel_id as i32, 2)
+            .add_text(Some(3), &self.controller_name, 25)
+            .add_text(Some(4), vin, 25)
+            .add_int(Some(5), self.job_id as i32, 4)
+            .add_int(Some(6), self.pset_id as i32, 3)
+            .add_int(Some(7), self.strategy.code() as i32, 2)
+            .add_int(Some(8), 0, 5) // strategy options
+            .add_int(Some(9), self.batch_size as i32, 4)
+            .add_int(Some(10), self.batch_counter as i32, 4)
+            .add_int(Some(11), if self.tightening_status { 1 } else { 0 }, 1)
+            .add_int(Some(12), batch_status_val, 1)
+            .add_int(Some(13), torque_status, 1)
+            .add_int(Some(14), angle_status, 1);
+
+        // Rundown angle, current monitoring, self-tap, prevail torque
+        // monitoring and current monitoring statuses: not simulated, all OK
+        for id in 15..=19 {
+            builder = builder.add_int(Some(id), 1, 1);
+        }
+        builder = builder
+            .add_int(Some(20), 0, 10) // tightening error status
+            .add_torque(Some(21), TorqueCnm::from_nm(self.torque_min), 6)
+            .add_torque(Some(22), TorqueCnm::from_nm(self.torque_max), 6)
+            .add_torque(Some(23), TorqueCnm::from_nm(self.torque_target), 6)
+            .add_torque(Some(24), TorqueCnm::from_nm(self.torque), 6)
+            .add_angle(Some(25), AngleDeciDeg::from_degrees(self.angle_min), 5)
+            .add_angle(Some(26), AngleDeciDeg::from_degrees(self.angle_max), 5)
+            .add_angle(Some(27), AngleDeciDeg::from_degrees(self.angle_target), 5)
+            .add_angle(Some(28), AngleDeciDeg::from_degrees(self.angle), 5);
+
+        // Monitoring values that are not simulated are reported as zero
+        for id in 29..=31 {
+            builder = builder.add_int(Some(id), 0, 5); // rundown angle
+        }
+        for id in 32..=34 {
+            builder = builder.add_int(Some(id), 0, 3); // current monitoring
+        }
+        for id in 35..=40 {
+            builder = builder.add_int(Some(id), 0, 6); // self-tap, prevail torque
+        }
+
+        builder
+            .add_int(Some(41), self.tightening_id.unwrap_or(0), 10)
+            .add_int(Some(42), self.job_sequence_number as i32, 5)
+            .add_int(Some(43), 0, 5) // sync tightening ID
+            .add_str(Some(44), "", 14) // tool serial number
+            .add_timestamp(Some(45), &self.timestamp)
+            .add_timestamp(Some(46), pset_change)
+    }
+}
+
+/// Inconsistency detected by [`TighteningResult::validate`]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TighteningResultError {
+    #[error("Batch counter {counter} exceeds batch size {size}")]
+    BatchCounterExceedsSize { counter: u32, size: u32 },
+
+    #[error("Torque {torque} reported OK but outside limits {min}-{max}")]
+    TorqueOutOfLimits { torque: f64, min: f64, max: f64 },
+
+    #[error("Angle {angle} reported OK but outside limits {min}-{max}")]
+    AngleOutOfLimits { angle: f64, min: f64, max: f64 },
+
+    #[error("Torque min {min} is greater than torque max {max}")]
+    InvalidTorqueLimits { min: f64, max: f64 },
+
+    #[error("Angle min {min} is greater than angle max {max}")]
+    InvalidAngleLimits { min: f64, max: f64 },
+}
+
+/// Builder for [`TighteningResult`]
+///
+/// Starts from a single-mode OK result with the default test limits, stamped
+/// with the current local time. Torque and angle default to their targets
+/// when not set explicitly.
+#[derive(Debug, Clone)]
+pub struct TighteningResultBuilder {
+    result: TighteningResult,
+    torque: Option<f64>,
+    angle: Option<f64>,
+}
+
+impl TighteningResultBuilder {
+    pub fn new() -> Self {
+        let params = TighteningParams::default_test();
+        Self {
+            result: TighteningResult {
+                cell_id: 1,
+                channel_id: 1,
+                controller_name: "OpenProtocolSimulator".to_string(),
+                vin_number: None,
+                job_id: 1,
+                pset_id: 1,
+                batch_size: 0,
+                batch_counter: 0,
+                tightening_status: true,
+                torque_status: true,
+                angle_status: true,
+                torque_min: params.torque_min,
+                torque_max: params.torque_max,
+                torque_target: params.target_torque,
+                torque: params.target_torque,
+                angle_min: params.angle_min,
+                angle_max: params.angle_max,
+                angle_target: params.target_angle,
+                angle: params.target_angle,
+                timestamp: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+                last_pset_change: None,
+                batch_status: None,
+                tightening_id: None,
+                job_sequence_number: 0,
+                strategy: params.strategy,
+                stages: Vec::new(),
+                cycle_id: 0,
+                cycle_time_ms: 0,
+                signature: None,
+                metadata: TighteningMetadata::default(),
+                voided: None,
+            },
+            torque: None,
+            angle: None,
+        }
+    }
+
+    /// Controller identity, VIN, job and its sequence number, selected PSET with
+    /// its last change date and batch size from the device
+    pub fn device(mut self, state: &DeviceState) -> Self {
+        self.result.cell_id = state.cell_id;
+        self.result.channel_id = state.channel_id;
+        self.result.controller_name = state.controller_name.clone();
+        self.result.vin_number = state.vehicle_id.clone();
+        self.result.job_id = state.current_job_id.unwrap_or(1);
+        self.result.job_sequence_number = state.job_sequence_number;
+        self.result.pset_id = state.current_pset_id.unwrap_or(1);
+        self.result.last_pset_change = Some(state.last_pset_change(self.result.pset_id));
+        self.result.batch_size = state.tightening_tracker.batch_size();
+        self
+    }
+
+    /// Batch position, batch status and tightening ID from the tracker
+    pub fn tightening(mut self, info: &TighteningInfo) -> Self {
+        self.result.batch_counter = info.counter;
+        self.result.tightening_id = Some(info.tightening_id);
+        self.result.batch_status = match info.batch_status {
+            BatchStatus::CompletedOk => Some(true),
+            BatchStatus::CompletedNok => Some(false),
+            BatchStatus::NotFinished | BatchStatus::NotUsed => None,
+        };
+        self
+    }
+
+    /// Torque/angle limits and targets, strategy and cycle time
+    pub fn params(mut self, params: &TighteningParams) -> Self {
+        self.result.torque_min = params.torque_min;
+        self.result.torque_max = params.torque_max;
+        self.result.torque_target = params.target_torque;
+        self.result.angle_min = params.angle_min;
+        self.result.angle_max = params.angle_max;
+        self.result.angle_target = params.target_angle;
+        self.result.strategy = params.strategy;
+        self.result.cycle_time_ms = params.duration_ms;
+        self
+    }
+
+    /// PSET ID, strategy and limits; targets are the middle of each range
+    pub fn pset(mut self, pset: &Pset) -> Self {
+        self.result.pset_id = pset.id;
+        self.result.torque_min = pset.torque_min;
+        self.result.torque_max = pset.torque_max;
+        self.result.torque_target = (pset.torque_min + pset.torque_max) / 2.0;
+        self.result.angle_min = pset.angle_min;
+        self.result.angle_max = pset.angle_max;
+        self.result.angle_target = (pset.angle_min + pset.angle_max) / 2.0;
+        self.result.strategy = pset.strategy;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: TighteningStrategy) -> Self {
+        self.result.strategy = strategy;
+        self
+    }
+
+    /// Per-stage results from the FSM outcome
+    pub fn stages(mut self, stages: Vec<StageResult>) -> Self {
+        self.result.stages = stages;
+        self
+    }
+
+    pub fn pset_id(mut self, pset_id: u32) -> Self {
+        self.result.pset_id = pset_id;
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.result.batch_size = batch_size;
+        self
+    }
+
+    pub fn batch_counter(mut self, batch_counter: u32) -> Self {
+        self.result.batch_counter = batch_counter;
+        self
+    }
+
+    pub fn tightening_id(mut self, tightening_id: u32) -> Self {
+        self.result.tightening_id = Some(tightening_id);
+        self
+    }
+
+    pub fn vin(mut self, vin: impl Into<String>) -> Self {
+        self.result.vin_number = Some(vin.into());
+        self
+    }
+
+    /// Actual torque (Nm)
+    pub fn torque(mut self, torque: f64) -> Self {
+        self.torque = Some(torque);
+        self
+    }
+
+    /// Actual angle (degrees)
+    pub fn angle(mut self, angle: f64) -> Self {
+        self.angle = Some(angle);
+        self
+    }
+
+    /// Overall, torque and angle status (OK = true)
+    pub fn status(mut self, tightening_ok: bool, torque_ok: bool, angle_ok: bool) -> Self {
+        self.result.tightening_status = tightening_ok;
+        self.result.torque_status = torque_ok;
+        self.result.angle_status = angle_ok;
+        self
+    }
+
+    /// Timestamp in Open Protocol format (YYYY-MM-DD:HH:MM:SS)
+    pub fn timestamp(mut self, timestamp: impl Into<String>) -> Self {
+        self.result.timestamp = timestamp.into();
+        self
+    }
+
+    pub fn metadata(mut self, metadata: TighteningMetadata) -> Self {
+        self.result.metadata = metadata;
+        self
+    }
+
+    /// Build the result without checking consistency
+    ///
+    /// The simulator uses this for forced and injected results, which may be
+    /// deliberately inconsistent.
+    pub fn build(self) -> TighteningResult {
+        let mut result = self.result;
+        result.torque = self.torque.unwrap_or(result.torque_target);
+        result.angle = self.angle.unwrap_or(result.angle_target);
+        result
+    }
+
+    /// Build the result, rejecting inconsistent field combinations
+    pub fn try_build(self) -> Result<TighteningResult, TighteningResultError> {
+        let result = self.build();
+        result.validate()?;
+        Ok(result)
+    }
+}
+
+impl Default for TighteningResultBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,10 +601,136 @@ mod tests {
     #[test]
     fn test_tightening_result_serialization() {
         let result = TighteningResult::example();
-        let data = ResponseData::serialize(&result);
+        let data = ResponseData::serialize(&result).unwrap();
 
         // Should contain multiple parameters
         assert!(!data.is_empty());
         assert!(data.len() > 100); // Complex structure should be large
     }
+
+    #[test]
+    fn test_fractional_values_are_rounded() {
+        let result = TighteningResult {
+            torque: 12.35,
+            angle: 39.6,
+            ..TighteningResult::example()
+        };
+        let data = String::from_utf8(ResponseData::serialize(&result).unwrap()).unwrap();
+
+        assert!(data.contains("15001235"));
+        assert!(data.contains("1900040"));
+    }
+
+    #[test]
+    fn test_revision_2_reports_strategy() {
+        let result = TighteningResult {
+            strategy: TighteningStrategy::TorqueControl,
+            torque: 16.0,
+            ..TighteningResult::example()
+        };
+        let rev1 = result.serialize_revision(1).unwrap();
+        assert_eq!(rev1, ResponseData::serialize(&result).unwrap());
+
+        let data = String::from_utf8(result.serialize_revision(2).unwrap()).unwrap();
+        assert!(data.contains("0701"));
+        assert!(data.contains("0800000"));
+        // Statuses 11-14: overall, batch, torque high (2), angle OK
+        assert!(data.contains("111121132141"));
+        assert!(data.contains("452025-01-15:10:30:45"));
+        assert!(data.ends_with("462025-01-15:09:00:00"));
+        assert_eq!(
+            result.serialize_revision(9).unwrap(),
+            result.serialize_revision(2).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stage_result_revision() {
+        let stage = |stage, torque, angle| StageResult {
+            stage,
+            torque,
+            angle,
+            torque_ok: true,
+            angle_ok: true,
+            ok: true,
+        };
+        let result = TighteningResult {
+            stages: vec![stage(1, 5.0, 90.0), stage(2, 12.3, 39.5)],
+            ..TighteningResult::example()
+        };
+
+        let data = String::from_utf8(result.serialize_revision(998).unwrap()).unwrap();
+        assert!(
+            data.starts_with(&String::from_utf8(result.serialize_revision(2).unwrap()).unwrap())
+        );
+        assert!(data.ends_with("47024802490005000009000123000040"));
+        assert_eq!(TighteningResult::supported_revision(999), 998);
+        assert_eq!(TighteningResult::supported_revision(500), 2);
+    }
+
+    #[test]
+    fn test_builder_defaults_are_consistent() {
+        let result = TighteningResult::builder().try_build().unwrap();
+
+        assert_eq!(result.torque, result.torque_target);
+        assert_eq!(result.angle, result.angle_target);
+        assert_eq!(result.timestamp.len(), 19);
+        assert!(result.tightening_status);
+    }
+
+    #[test]
+    fn test_builder_uses_device_and_tracker() {
+        let mut state = DeviceState::new();
+        state.set_batch_size(3);
+        state.set_vehicle_id("VIN123".to_string());
+        let info = TighteningInfo {
+            counter: 3,
+            tightening_id: 42,
+            batch_status: BatchStatus::CompletedOk,
+        };
+
+        let result = TighteningResult::builder()
+            .device(&state)
+            .tightening(&info)
+            .try_build()
+            .unwrap();
+
+        assert_eq!(result.controller_name, state.controller_name);
+        assert_eq!(result.vin_number.as_deref(), Some("VIN123"));
+        assert_eq!(result.batch_size, 3);
+        assert_eq!(result.batch_status, Some(true));
+        assert_eq!(result.tightening_id, Some(42));
+        assert_eq!(result.job_sequence_number, 1);
+
+        let data = String::from_utf8(result.serialize_revision(2).unwrap()).unwrap();
+        assert!(data.contains("41000000004242000014300000"), "{data}");
+    }
+
+    #[test]
+    fn test_validation_rejects_inconsistent_results() {
+        let overrun = TighteningResult::builder()
+            .batch_size(2)
+            .batch_counter(3)
+            .try_build();
+        assert!(matches!(
+            overrun,
+            Err(TighteningResultError::BatchCounterExceedsSize {
+                counter: 3,
+                size: 2
+            })
+        ));
+
+        let torque_ok_outside = TighteningResult::builder().torque(20.0).try_build();
+        assert!(matches!(
+            torque_ok_outside,
+            Err(TighteningResultError::TorqueOutOfLimits { .. })
+        ));
+
+        // Out-of-limit torque is fine when reported as NOK
+        let nok = TighteningResult::builder()
+            .torque(20.0)
+            .status(false, false, true)
+            .try_build();
+        assert!(nok.is_ok());
+    }
 }
@@ -0,0 +1,113 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::field::FieldBuilder;
+use crate::protocol::response_data::ResponseData;
+
+/// Serial number reported for the simulated tool
+pub const TOOL_SERIAL_NUMBER: &str = "SIMTOOL0000001";
+
+/// Serial number reported for the simulated controller
+pub const CONTROLLER_SERIAL_NUMBER: &str = "SIM0000001";
+
+/// Calibration and service date reported for the simulated tool
+pub const TOOL_CALIBRATION_DATE: &str = "2024-01-01:00:00:00";
+
+/// MID 0041 - Tool data upload reply
+///
+/// Revision 1 fields:
+/// - 01: Tool serial number (14 characters)
+/// - 02: Tool number of tightenings (10 digits)
+/// - 03: Last calibration date (YYYY-MM-DD:HH:MM:SS)
+/// - 04: Controller serial number (10 characters)
+///
+/// Revision 2 adds:
+/// - 05: Calibration value (6 digits, cNm)
+/// - 06: Last service date (YYYY-MM-DD:HH:MM:SS)
+/// - 07: Tightenings since service (10 digits)
+/// - 08: Tool type (2 digits, 01 = S-tool)
+/// - 09: Motor size (2 digits)
+/// - 10: Open end data (3 digits)
+/// - 11: Controller software version (19 characters)
+#[derive(Debug, Clone)]
+pub struct ToolDataUpload {
+    pub tool_serial_number: String,
+    pub tightenings: u64,
+    pub last_calibration_date: String,
+    pub controller_serial_number: String,
+    pub calibration_value_cnm: u32,
+    pub last_service_date: String,
+    pub tightenings_since_service: u64,
+    pub software_version: String,
+}
+
+impl ToolDataUpload {
+    /// Highest revision the simulator produces
+    pub const MAX_REVISION: u16 = 2;
+
+    /// Tool data of the simulated tool with the given lifetime tightening count
+    pub fn new(tightenings: u64) -> Self {
+        Self {
+            tool_serial_number: TOOL_SERIAL_NUMBER.to_string(),
+            tightenings,
+            last_calibration_date: TOOL_CALIBRATION_DATE.to_string(),
+            controller_serial_number: CONTROLLER_SERIAL_NUMBER.to_string(),
+            calibration_value_cnm: 0,
+            last_service_date: TOOL_CALIBRATION_DATE.to_string(),
+            tightenings_since_service: tightenings,
+            software_version: format!("SIM {}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// Serialize the requested revision (clamped to 1..=MAX_REVISION)
+    pub fn serialize_revision(&self, revision: u16) -> Result<Vec<u8>, ProtocolError> {
+        // Counters are 10 digits wide; wrap like a controller counter would
+        let counter = |value: u64| format!("{:010}", value % 10_000_000_000);
+        let mut builder = FieldBuilder::new()
+            .add_str(Some(1), &self.tool_serial_number, 14)
+            .add_str(Some(2), counter(self.tightenings), 10)
+            .add_timestamp(Some(3), &self.last_calibration_date)
+            .add_str(Some(4), &self.controller_serial_number, 10);
+
+        if revision >= 2 {
+            builder = builder
+                .add_int(Some(5), self.calibration_value_cnm as i32, 6)
+                .add_timestamp(Some(6), &self.last_service_date)
+                .add_str(Some(7), counter(self.tightenings_since_service), 10)
+                .add_int(Some(8), 1, 2)
+                .add_int(Some(9), 0, 2)
+                .add_int(Some(10), 0, 3)
+                .add_str(Some(11), &self.software_version, 19);
+        }
+
+        builder.build()
+    }
+}
+
+impl ResponseData for ToolDataUpload {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        self.serialize_revision(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_data_revisions() {
+        let upload = ToolDataUpload::new(1234);
+
+        let rev1 = upload.serialize_revision(1).unwrap();
+        assert_eq!(
+            &rev1[..],
+            b"01SIMTOOL0000001020000001234032024-01-01:00:00:0004SIM0000001"
+        );
+
+        let rev2 = upload.serialize_revision(2).unwrap();
+        assert!(rev2.starts_with(&rev1));
+        assert_eq!(
+            rev2.len(),
+            rev1.len() + 7 * 2 + 6 + 19 + 10 + 2 + 2 + 3 + 19
+        );
+        assert_eq!(&rev2[rev1.len()..rev1.len() + 8], b"05000000");
+    }
+}
@@ -0,0 +1,41 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::field::{Field, FieldBuilder};
+use crate::protocol::response_data::ResponseData;
+
+/// MID 0242 - User data upload (pushed to subscribers)
+///
+/// Carries one user data slot: parameter 01 is the slot (the offset it was
+/// written at, three digits), parameter 02 the stored text (1-200 characters,
+/// not padded).
+#[derive(Debug, Clone)]
+pub struct UserDataUpload {
+    pub slot: u16,
+    pub data: String,
+}
+
+impl UserDataUpload {
+    pub fn new(slot: u16, data: String) -> Self {
+        Self { slot, data }
+    }
+}
+
+impl ResponseData for UserDataUpload {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        FieldBuilder::new()
+            .add_int(Some(1), self.slot, 3)
+            .add_field(Field::new(Some(2), self.data.clone()))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_data_upload_layout() {
+        let upload = UserDataUpload::new(7, "LINE A STATION 3".to_string());
+        let data = upload.serialize().unwrap();
+        assert_eq!(&data[..], b"0100702LINE A STATION 3");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::protocol::ProtocolError;
 use crate::protocol::field::FieldBuilder;
 use crate::protocol::response_data::ResponseData;
 
@@ -19,16 +20,12 @@ impl VehicleIdBroadcast {
 }
 
 impl ResponseData for VehicleIdBroadcast {
-    fn serialize(&self) -> Vec<u8> {
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
         // Revision 1: VIN number only (no parameter ID)
-        // 25 bytes, left-padded with spaces if shorter, truncated if longer
-        let vin = if self.vin_number.len() >= 25 {
-            self.vin_number[..25].to_string()
-        } else {
-            format!("{:<25}", self.vin_number)
-        };
-
-        FieldBuilder::new().add_str(None, &vin, 25).build()
+        // 25 characters, padded with spaces if shorter, truncated if longer
+        FieldBuilder::new()
+            .add_text(None, &self.vin_number, 25)
+            .build()
     }
 }
 
@@ -39,7 +36,7 @@ mod tests {
     #[test]
     fn test_vehicle_id_broadcast_exact_length() {
         let broadcast = VehicleIdBroadcast::new("SSC044207                ".to_string());
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
         assert_eq!(data.len(), 25);
         assert_eq!(&data[..], b"SSC044207                ");
     }
@@ -47,7 +44,7 @@ mod tests {
     #[test]
     fn test_vehicle_id_broadcast_short_vin() {
         let broadcast = VehicleIdBroadcast::new("TEST123".to_string());
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
         assert_eq!(data.len(), 25);
         assert_eq!(&data[..], b"TEST123                  ");
     }
@@ -57,7 +54,7 @@ mod tests {
         let broadcast = VehicleIdBroadcast::new(
             "THIS_IS_A_VERY_LONG_VIN_NUMBER_THAT_EXCEEDS_25_CHARS".to_string(),
         );
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
         assert_eq!(data.len(), 25);
         assert_eq!(&data[..], b"THIS_IS_A_VERY_LONG_VIN_N");
     }
@@ -65,7 +62,7 @@ mod tests {
     #[test]
     fn test_vehicle_id_broadcast_empty() {
         let broadcast = VehicleIdBroadcast::new(String::new());
-        let data = broadcast.serialize();
+        let data = broadcast.serialize().unwrap();
         assert_eq!(data.len(), 25);
         assert_eq!(&data[..], b"                         ");
     }
@@ -0,0 +1,78 @@
+//! MID 9100 - Event log upload request (simulator extension)
+//!
+//! Open Protocol has no standard MID for pulling the controller event log,
+//! so the simulator exposes its audit log through a request/reply pair outside
+//! the ranges used by the specification. The optional request data is the ID
+//! of the last entry already received (10 digits); the reply (MID 9101)
+//! carries the following entries, oldest first, up to 50 per request.
+
+use crate::handler::data::EventLogUpload;
+use crate::handler::data::event_log::EVENT_LOG_MAX_ENTRIES;
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 9100 - Event log upload request
+pub struct EventLogUploadHandler {
+    state: ObservableState,
+}
+
+impl EventLogUploadHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+impl MidHandler for EventLogUploadHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let after_id = String::from_utf8_lossy(&message.data)
+            .trim()
+            .parse::<u64>()
+            .unwrap_or(0);
+
+        let mut entries = self.state.read().audit_log.since(after_id);
+        entries.truncate(EVENT_LOG_MAX_ENTRIES);
+
+        println!(
+            "MID 9100: Event log upload after entry {} - {} entries",
+            after_id,
+            entries.len()
+        );
+
+        Ok(Response::from_data(
+            Mid::EVENT_LOG_UPLOAD,
+            message.revision,
+            EventLogUpload { entries },
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditCategory;
+    use crate::state::DeviceState;
+    use std::sync::{Arc, RwLock};
+
+    #[test]
+    fn test_upload_returns_entries_after_id() {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(Arc::new(RwLock::new(DeviceState::new())), tx);
+        state.audit(AuditCategory::Connection, "Client connected");
+        state.disable_tool();
+        let handler = EventLogUploadHandler::new(state);
+
+        let message = Message {
+            length: 30,
+            mid: 9100,
+            revision: 1,
+            data: b"0000000001".to_vec(),
+            header: Default::default(),
+        };
+        let response = handler.handle(&message).unwrap();
+
+        assert_eq!(response.mid, 9101);
+        assert!(response.data.starts_with(b"01001"));
+        assert_eq!(&response.data[5..15], b"0000000002");
+    }
+}
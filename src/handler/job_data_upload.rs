@@ -0,0 +1,97 @@
+//! MID 0032 - Job data upload request handler
+//!
+//! The request carries the job ID (2 digits in revision 1, 4 in revision 2).
+//! Replies with MID 0033 describing the job's PSETs, batch sizes and order,
+//! or MID 0004 if the ID is missing or no such job exists.
+
+use crate::handler::data::{ErrorCode, ErrorResponse, JobData};
+use crate::handler::{HandlerError, MidHandler};
+use crate::job::SharedJobRepository;
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0032 - Job data upload request
+pub struct JobDataUploadHandler {
+    jobs: SharedJobRepository,
+}
+
+impl JobDataUploadHandler {
+    pub fn new(jobs: SharedJobRepository) -> Self {
+        Self { jobs }
+    }
+}
+
+impl MidHandler for JobDataUploadHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let revision = message.revision.clamp(1, JobData::MAX_REVISION);
+        let Ok(job_id) = String::from_utf8_lossy(&message.data).trim().parse::<u32>() else {
+            println!("MID 0032: Job data upload rejected - invalid job ID");
+            return Ok(Response::from_data(
+                Mid::COMMAND_ERROR,
+                message.revision,
+                ErrorResponse::new(Mid::JOB_DATA_UPLOAD_REQUEST, ErrorCode::InvalidData),
+            )?);
+        };
+
+        let Some(job) = self.jobs.read().unwrap().get_by_id(job_id) else {
+            println!(
+                "MID 0032: Job data upload rejected - job {} not found",
+                job_id
+            );
+            return Ok(Response::from_data(
+                Mid::COMMAND_ERROR,
+                message.revision,
+                ErrorResponse::new(Mid::JOB_DATA_UPLOAD_REQUEST, ErrorCode::JobNotFound),
+            )?);
+        };
+        println!(
+            "MID 0032: Job data upload (rev {}) - job {} \"{}\", {} steps",
+            revision,
+            job.id,
+            job.name,
+            job.steps.len()
+        );
+
+        let data = JobData { job }.serialize_revision(revision)?;
+        Ok(Response::new(Mid::JOB_DATA_UPLOAD, revision, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::create_default_job_repository;
+
+    #[test]
+    fn test_uploads_job_data() {
+        let handler = JobDataUploadHandler::new(create_default_job_repository());
+
+        let response = handler
+            .handle(&Message::new(32, 1, b"02".to_vec()))
+            .unwrap();
+        assert_eq!(response.mid, 33);
+        assert_eq!(response.revision, 1);
+        let data = String::from_utf8(response.data).unwrap();
+        assert!(data.starts_with("0102"), "{}", data);
+        assert!(data.ends_with("1301:003:0:06;01:005:0:02;"), "{}", data);
+
+        let response = handler
+            .handle(&Message::new(32, 2, b"0001".to_vec()))
+            .unwrap();
+        assert_eq!(response.revision, 2);
+        assert!(response.data.starts_with(b"010001"));
+    }
+
+    #[test]
+    fn test_unknown_or_invalid_job_is_rejected() {
+        let handler = JobDataUploadHandler::new(create_default_job_repository());
+
+        let response = handler
+            .handle(&Message::new(32, 1, b"42".to_vec()))
+            .unwrap();
+        assert_eq!(response.mid, 4);
+        assert_eq!(response.data, b"003206");
+
+        let response = handler.handle(&Message::new(32, 1, Vec::new())).unwrap();
+        assert_eq!(response.data, b"003204");
+    }
+}
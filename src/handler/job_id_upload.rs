@@ -0,0 +1,66 @@
+//! MID 0030 - Job ID upload request handler
+//!
+//! Replies with MID 0031 listing the IDs of the jobs in the repository, in
+//! the revision the client asked for.
+
+use crate::handler::data::JobIdUpload;
+use crate::handler::{HandlerError, MidHandler};
+use crate::job::SharedJobRepository;
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0030 - Job ID upload request
+pub struct JobIdUploadHandler {
+    jobs: SharedJobRepository,
+}
+
+impl JobIdUploadHandler {
+    pub fn new(jobs: SharedJobRepository) -> Self {
+        Self { jobs }
+    }
+}
+
+impl MidHandler for JobIdUploadHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let revision = message.revision.clamp(1, JobIdUpload::MAX_REVISION);
+        let mut job_ids: Vec<u32> = self
+            .jobs
+            .read()
+            .unwrap()
+            .get_all()
+            .iter()
+            .map(|job| job.id)
+            .collect();
+        job_ids.sort_unstable();
+        let upload = JobIdUpload { job_ids };
+        println!(
+            "MID 0030: Job ID upload (rev {}) - {} jobs",
+            revision,
+            upload.job_ids.len()
+        );
+
+        let data = upload.serialize_revision(revision)?;
+        Ok(Response::new(Mid::JOB_ID_UPLOAD, revision, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::create_default_job_repository;
+
+    #[test]
+    fn test_lists_repository_jobs() {
+        let jobs = create_default_job_repository();
+        let handler = JobIdUploadHandler::new(jobs.clone());
+
+        let response = handler.handle(&Message::new(30, 1, Vec::new())).unwrap();
+        assert_eq!(response.mid, 31);
+        assert_eq!(response.revision, 1);
+        assert_eq!(&response.data[..], b"020102");
+
+        jobs.write().unwrap().delete(1).unwrap();
+        let response = handler.handle(&Message::new(30, 3, Vec::new())).unwrap();
+        assert_eq!(response.revision, 2);
+        assert_eq!(&response.data[..], b"00010002");
+    }
+}
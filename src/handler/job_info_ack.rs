@@ -0,0 +1,35 @@
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0036 - Job info acknowledge
+/// Client acknowledges receipt of a job info upload (MID 0035)
+pub struct JobInfoAckHandler;
+
+impl MidHandler for JobInfoAckHandler {
+    fn handle(&self, _message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0036: Job info acknowledged by client");
+
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_info_ack() {
+        let handler = JobInfoAckHandler;
+        let message = Message {
+            length: 20,
+            mid: 36,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert!(response.data.is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0034 - Job info subscribe
+/// Client requests a MID 0035 upload whenever a job is selected (MID 0038)
+/// or restarted (MID 0039)
+pub struct JobInfoSubscribeHandler;
+
+impl MidHandler for JobInfoSubscribeHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0034: Job info subscribe request");
+
+        let ack_data = CommandAccepted::with_mid(Mid::JOB_INFO_SUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_info_subscribe() {
+        let handler = JobInfoSubscribeHandler;
+        let message = Message {
+            length: 20,
+            mid: 34,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert_eq!(response.data, b"0034");
+    }
+}
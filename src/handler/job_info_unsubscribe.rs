@@ -0,0 +1,41 @@
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0037 - Job info unsubscribe
+/// Client cancels its subscription to job info uploads
+pub struct JobInfoUnsubscribeHandler;
+
+impl MidHandler for JobInfoUnsubscribeHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0037: Job info unsubscribe request");
+
+        let ack_data = CommandAccepted::with_mid(Mid::JOB_INFO_UNSUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_info_unsubscribe() {
+        let handler = JobInfoUnsubscribeHandler;
+        let message = Message {
+            length: 20,
+            mid: 37,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert_eq!(response.data, b"0037");
+    }
+}
@@ -0,0 +1,168 @@
+//! MID 0039 - Job restart handler
+//!
+//! The request carries the job ID (2 digits in revision 1, 4 in revision 2)
+//! and must name the selected job; an empty ID restarts the selected job.
+//! The batch counters of all steps are reset and the first PSET is selected
+//! again. Replies with MID 0005, or MID 0004 if the ID is invalid or not the
+//! selected job, or the job no longer exists.
+
+use crate::handler::data::command_accepted::CommandAccepted;
+use crate::handler::data::{ErrorCode, ErrorResponse};
+use crate::handler::job_select::first_pset_name;
+use crate::handler::{HandlerError, MidHandler};
+use crate::job::SharedJobRepository;
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+use crate::pset::SharedPsetRepository;
+
+/// MID 0039 - Job restart
+pub struct JobRestartHandler {
+    state: ObservableState,
+    jobs: SharedJobRepository,
+    psets: SharedPsetRepository,
+}
+
+impl JobRestartHandler {
+    pub fn new(
+        state: ObservableState,
+        jobs: SharedJobRepository,
+        psets: SharedPsetRepository,
+    ) -> Self {
+        Self { state, jobs, psets }
+    }
+
+    fn reject(message: &Message, code: ErrorCode) -> Result<Response, HandlerError> {
+        Ok(Response::from_data(
+            Mid::COMMAND_ERROR,
+            message.revision,
+            ErrorResponse::new(Mid::RESTART_JOB, code),
+        )?)
+    }
+}
+
+impl MidHandler for JobRestartHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let selected = self.state.read().current_job_id;
+        let requested = String::from_utf8_lossy(&message.data).trim().to_string();
+        let job_id = if requested.is_empty() {
+            selected
+        } else {
+            requested.parse::<u32>().ok()
+        };
+        let Some(job_id) = job_id.filter(|id| Some(*id) == selected) else {
+            println!(
+                "MID 0039: Job restart rejected - job \"{}\" is not the selected job",
+                requested
+            );
+            return Self::reject(message, ErrorCode::InvalidData);
+        };
+
+        let Some(job) = self.jobs.read().unwrap().get_by_id(job_id) else {
+            println!("MID 0039: Job restart rejected - job {} not found", job_id);
+            return Self::reject(message, ErrorCode::JobNotFound);
+        };
+        println!("MID 0039: Job restart - job {} \"{}\"", job.id, job.name);
+
+        self.state
+            .restart_job(&job, first_pset_name(&job, &self.psets));
+
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            CommandAccepted::with_mid(Mid::RESTART_JOB),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SimulatorEvent;
+    use crate::job::create_default_job_repository;
+    use crate::pset::create_default_repository;
+    use crate::state::DeviceState;
+
+    fn handler() -> (
+        JobRestartHandler,
+        ObservableState,
+        tokio::sync::broadcast::Receiver<SimulatorEvent>,
+    ) {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(DeviceState::new_shared(), tx);
+        let handler = JobRestartHandler::new(
+            state.clone(),
+            create_default_job_repository(),
+            create_default_repository(),
+        );
+        (handler, state, rx)
+    }
+
+    #[test]
+    fn test_restart_resets_all_step_batches() {
+        let (handler, state, mut events) = handler();
+        {
+            // Job 1 runs PSET 1 (4 tightenings) then PSET 2 (2 tightenings)
+            let mut state = state.write();
+            state.set_pset(2, None);
+            state.set_pset_batch_size(2, 2);
+            state.tightening_tracker.add_tightening(true);
+            state.set_pset_batch_size(1, 4);
+            state.set_pset(1, None);
+            state.tightening_tracker.add_tightening(true);
+        }
+        let sequence = state.read().job_sequence_number;
+
+        let response = handler
+            .handle(&Message::new(39, 1, b"01".to_vec()))
+            .unwrap();
+        assert_eq!(response.mid, 5);
+        assert_eq!(response.data, b"0039");
+
+        {
+            let state = state.read();
+            assert_eq!(state.current_pset_id, Some(1));
+            assert_eq!(state.tightening_tracker.counter(), 0);
+            assert_eq!(state.tightening_tracker.batch_size(), 4);
+            assert_eq!(state.job_sequence_number, sequence + 1);
+        }
+        state.write().set_pset(2, None);
+        assert_eq!(state.read().tightening_tracker.counter(), 0);
+
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            SimulatorEvent::PsetChanged { pset_id: 1, .. }
+        ));
+        match events.try_recv().unwrap() {
+            SimulatorEvent::JobRestarted {
+                job_id,
+                job_batch_size,
+                ..
+            } => assert_eq!((job_id, job_batch_size), (1, 6)),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_only_the_selected_job_restarts() {
+        let (handler, state, _) = handler();
+
+        let response = handler
+            .handle(&Message::new(39, 1, b"02".to_vec()))
+            .unwrap();
+        assert_eq!(response.mid, 4);
+        assert_eq!(response.data, b"003904");
+
+        let response = handler.handle(&Message::new(39, 1, Vec::new())).unwrap();
+        assert_eq!(response.mid, 5);
+
+        state.write().current_job_id = Some(42);
+        let response = handler
+            .handle(&Message::new(39, 2, b"0042".to_vec()))
+            .unwrap();
+        assert_eq!(response.data, b"003906");
+
+        state.write().current_job_id = None;
+        let response = handler.handle(&Message::new(39, 1, Vec::new())).unwrap();
+        assert_eq!(response.data, b"003904");
+    }
+}
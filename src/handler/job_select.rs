@@ -0,0 +1,144 @@
+//! MID 0038 - Select job handler
+//!
+//! The request carries the job ID (2 digits in revision 1, 4 in revision 2).
+//! The job becomes the active one and its first PSET is selected with that
+//! step's batch size. Replies with MID 0005, or MID 0004 if the ID is missing
+//! or no such job exists.
+
+use crate::handler::data::command_accepted::CommandAccepted;
+use crate::handler::data::{ErrorCode, ErrorResponse};
+use crate::handler::{HandlerError, MidHandler};
+use crate::job::{Job, SharedJobRepository};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+use crate::pset::SharedPsetRepository;
+
+/// MID 0038 - Select job
+pub struct JobSelectHandler {
+    state: ObservableState,
+    jobs: SharedJobRepository,
+    psets: SharedPsetRepository,
+}
+
+impl JobSelectHandler {
+    pub fn new(
+        state: ObservableState,
+        jobs: SharedJobRepository,
+        psets: SharedPsetRepository,
+    ) -> Self {
+        Self { state, jobs, psets }
+    }
+}
+
+/// Name of the first PSET of `job` (`Pset_<id>` when it is not stored)
+pub fn first_pset_name(job: &Job, psets: &SharedPsetRepository) -> Option<String> {
+    job.steps.first().map(|step| {
+        psets
+            .read()
+            .unwrap()
+            .get_by_id(step.pset_id)
+            .map(|pset| pset.name)
+            .unwrap_or_else(|| format!("Pset_{}", step.pset_id))
+    })
+}
+
+impl MidHandler for JobSelectHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let Ok(job_id) = String::from_utf8_lossy(&message.data).trim().parse::<u32>() else {
+            println!("MID 0038: Select job rejected - invalid job ID");
+            return Ok(Response::from_data(
+                Mid::COMMAND_ERROR,
+                message.revision,
+                ErrorResponse::new(Mid::SELECT_JOB, ErrorCode::InvalidData),
+            )?);
+        };
+
+        let Some(job) = self.jobs.read().unwrap().get_by_id(job_id) else {
+            println!("MID 0038: Select job rejected - job {} not found", job_id);
+            return Ok(Response::from_data(
+                Mid::COMMAND_ERROR,
+                message.revision,
+                ErrorResponse::new(Mid::SELECT_JOB, ErrorCode::JobNotFound),
+            )?);
+        };
+        println!("MID 0038: Select job - job {} \"{}\"", job.id, job.name);
+
+        self.state
+            .select_job(&job, first_pset_name(&job, &self.psets));
+
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            CommandAccepted::with_mid(Mid::SELECT_JOB),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SimulatorEvent;
+    use crate::job::create_default_job_repository;
+    use crate::pset::create_default_repository;
+    use crate::state::DeviceState;
+
+    fn handler() -> (
+        JobSelectHandler,
+        ObservableState,
+        tokio::sync::broadcast::Receiver<SimulatorEvent>,
+    ) {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(DeviceState::new_shared(), tx);
+        let handler = JobSelectHandler::new(
+            state.clone(),
+            create_default_job_repository(),
+            create_default_repository(),
+        );
+        (handler, state, rx)
+    }
+
+    #[test]
+    fn test_selects_job_and_first_pset() {
+        let (handler, state, mut events) = handler();
+
+        let response = handler
+            .handle(&Message::new(38, 1, b"02".to_vec()))
+            .unwrap();
+        assert_eq!(response.mid, 5);
+        assert_eq!(response.data, b"0038");
+
+        let state = state.read();
+        assert_eq!(state.current_job_id, Some(2));
+        assert_eq!(state.current_pset_id, Some(3));
+        assert_eq!(state.tightening_tracker.batch_size(), 6);
+
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            SimulatorEvent::PsetChanged { pset_id: 3, .. }
+        ));
+        match events.try_recv().unwrap() {
+            SimulatorEvent::JobSelected {
+                job_id,
+                pset_id,
+                batch_size,
+                ..
+            } => assert_eq!((job_id, pset_id, batch_size), (2, 3, 6)),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_or_invalid_job_is_rejected() {
+        let (handler, state, _) = handler();
+
+        let response = handler
+            .handle(&Message::new(38, 2, b"0042".to_vec()))
+            .unwrap();
+        assert_eq!(response.mid, 4);
+        assert_eq!(response.data, b"003806");
+
+        let response = handler.handle(&Message::new(38, 1, Vec::new())).unwrap();
+        assert_eq!(response.data, b"003804");
+        assert_eq!(state.read().current_job_id, Some(1));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 9999 - Keep alive / Heartbeat
 /// Responds with MID 9999 (Keep alive acknowledge)
@@ -10,6 +10,6 @@ impl MidHandler for KeepAliveHandler {
         println!("MID 9999: Keep alive ping");
 
         // Respond with MID 9999 (Keep alive acknowledge)
-        Ok(Response::new(9999, message.revision, Vec::new()))
+        Ok(Response::new(Mid::KEEP_ALIVE, message.revision, Vec::new()))
     }
 }
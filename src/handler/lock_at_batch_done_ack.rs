@@ -0,0 +1,14 @@
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0023 - Lock at batch done upload acknowledge
+/// Client acknowledges receipt of a lock at batch done upload (MID 0022)
+pub struct LockAtBatchDoneAckHandler;
+
+impl MidHandler for LockAtBatchDoneAckHandler {
+    fn handle(&self, _message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0023: Lock at batch done upload acknowledged by client");
+
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
+    }
+}
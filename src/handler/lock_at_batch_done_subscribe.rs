@@ -0,0 +1,42 @@
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0021 - Lock at batch done subscribe
+/// Client requests a MID 0022 upload whenever a batch is completed, telling
+/// whether the tool was locked (`[defaults] lock_at_batch_done`)
+pub struct LockAtBatchDoneSubscribeHandler;
+
+impl MidHandler for LockAtBatchDoneSubscribeHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0021: Lock at batch done subscribe request");
+
+        let ack_data = CommandAccepted::with_mid(Mid::LOCK_AT_BATCH_DONE_SUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_at_batch_done_subscribe() {
+        let handler = LockAtBatchDoneSubscribeHandler;
+        let message = Message {
+            length: 20,
+            mid: 21,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert_eq!(response.data, b"0021");
+    }
+}
@@ -0,0 +1,41 @@
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0024 - Lock at batch done unsubscribe
+/// Client cancels its subscription to lock at batch done uploads
+pub struct LockAtBatchDoneUnsubscribeHandler;
+
+impl MidHandler for LockAtBatchDoneUnsubscribeHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0024: Lock at batch done unsubscribe request");
+
+        let ack_data = CommandAccepted::with_mid(Mid::LOCK_AT_BATCH_DONE_UNSUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_at_batch_done_unsubscribe() {
+        let handler = LockAtBatchDoneUnsubscribeHandler;
+        let message = Message {
+            length: 20,
+            mid: 24,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert_eq!(response.data, b"0024");
+    }
+}
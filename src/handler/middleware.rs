@@ -0,0 +1,563 @@
+//! Middleware layers wrapped around handler dispatch
+//!
+//! Cross-cutting concerns (logging, metrics, validation, artificial delay,
+//! authorization) are implemented as composable layers instead of being
+//! hardwired into the connection loop or individual handlers. Layers run in
+//! the order they were added to the registry; each one decides whether to call
+//! the rest of the chain via [`Next::run`].
+
+use super::data::{ErrorCode, ErrorResponse};
+use super::{HandlerError, MidHandler};
+use crate::config::HandlersConfig;
+use crate::protocol::{Message, Mid, Response};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Header size of an Open Protocol frame (length + MID + revision + spare)
+const HEADER_SIZE: u32 = 20;
+
+/// A layer that wraps handler dispatch
+pub trait Middleware: Send + Sync {
+    /// Process a message, optionally delegating to the rest of the chain
+    fn handle(&self, message: &Message, next: Next<'_>) -> Result<Response, HandlerError>;
+
+    /// How long the connection waits before sending a response
+    ///
+    /// Handlers are synchronous, so a layer cannot sleep itself without
+    /// blocking a runtime worker; the connection awaits the delay instead.
+    fn response_delay(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// The remainder of the middleware chain, ending in the MID handler
+pub struct Next<'a> {
+    layers: &'a [Box<dyn Middleware>],
+    handler: Option<&'a dyn MidHandler>,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        layers: &'a [Box<dyn Middleware>],
+        handler: Option<&'a dyn MidHandler>,
+    ) -> Self {
+        Self { layers, handler }
+    }
+
+    /// Run the remaining layers and finally the handler itself
+    pub fn run(self, message: &Message) -> Result<Response, HandlerError> {
+        match self.layers.split_first() {
+            Some((layer, rest)) => layer.handle(message, Next::new(rest, self.handler)),
+            None => match self.handler {
+                Some(handler) => handler.handle(message),
+                None => Err(HandlerError::UnknownMid(message.mid)),
+            },
+        }
+    }
+}
+
+// ============================================================================
+// Logging
+// ============================================================================
+
+/// Logs every dispatched MID and the outcome
+pub struct LoggingLayer;
+
+impl Middleware for LoggingLayer {
+    fn handle(&self, message: &Message, next: Next<'_>) -> Result<Response, HandlerError> {
+        let mid = Mid(message.mid);
+        println!(
+            "Parsed MID {mid} ({}), revision {}",
+            mid.name(),
+            message.revision
+        );
+        let result = next.run(message);
+        match &result {
+            Ok(response) => {
+                let reply = Mid(response.mid);
+                println!("MID {mid} handled -> MID {reply} ({})", reply.name());
+            }
+            Err(e) => println!("MID {mid} ({}) failed: {e}", mid.name()),
+        }
+        result
+    }
+}
+
+// ============================================================================
+// Metrics
+// ============================================================================
+
+/// Dispatch latencies kept per MID for the percentiles
+pub const LATENCY_SAMPLES: usize = 1024;
+
+/// The most recent failure of a handler
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LastError {
+    /// Key it is counted under in `errors_by_type`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub detail: String,
+    /// RFC 3339 with milliseconds
+    pub at: String,
+}
+
+/// Per-MID dispatch counters
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MidMetrics {
+    /// Human-readable MID name from the MID table
+    pub name: &'static str,
+    /// Number of messages dispatched
+    pub count: u64,
+    /// Number of dispatches that returned a handler error
+    pub errors: u64,
+    /// Number of dispatches answered with MID 0004
+    pub naks: u64,
+    /// Accumulated time spent in the rest of the chain (microseconds)
+    pub total_micros: u64,
+    /// Handler errors by kind and MID 0004 answers by error code (`nak_05`)
+    pub errors_by_type: BTreeMap<String, u64>,
+    pub last_error: Option<LastError>,
+    /// Latest dispatch times (microseconds), oldest first
+    #[serde(skip)]
+    samples: VecDeque<u64>,
+}
+
+impl MidMetrics {
+    fn record_failure(&mut self, kind: String, detail: String) {
+        *self.errors_by_type.entry(kind.clone()).or_default() += 1;
+        self.last_error = Some(LastError {
+            kind,
+            detail,
+            at: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+        });
+    }
+}
+
+/// Latency and error summary of one handler
+#[derive(Debug, Clone, Serialize)]
+pub struct HandlerStats {
+    pub mid: u16,
+    pub name: &'static str,
+    pub count: u64,
+    pub errors: u64,
+    pub naks: u64,
+    pub mean_micros: u64,
+    /// Percentiles over the last [`LATENCY_SAMPLES`] dispatches (nearest rank)
+    pub p50_micros: u64,
+    pub p99_micros: u64,
+    pub max_micros: u64,
+    pub errors_by_type: BTreeMap<String, u64>,
+    pub last_error: Option<LastError>,
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied().unwrap_or(0)
+}
+
+/// Shared metrics storage filled in by [`MetricsLayer`]
+#[derive(Debug, Default)]
+pub struct HandlerMetrics {
+    per_mid: Mutex<BTreeMap<u16, MidMetrics>>,
+}
+
+impl HandlerMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a single dispatch and its outcome
+    pub fn record(&self, mid: u16, elapsed: Duration, outcome: &Result<Response, HandlerError>) {
+        let micros = elapsed.as_micros() as u64;
+        let mut per_mid = self.per_mid.lock().unwrap();
+        let entry = per_mid.entry(mid).or_insert_with(|| MidMetrics {
+            name: Mid(mid).name(),
+            ..MidMetrics::default()
+        });
+        entry.count += 1;
+        entry.total_micros += micros;
+        if entry.samples.len() >= LATENCY_SAMPLES {
+            entry.samples.pop_front();
+        }
+        entry.samples.push_back(micros);
+        match outcome {
+            Err(e) => {
+                entry.errors += 1;
+                entry.record_failure(e.kind().to_string(), e.to_string());
+            }
+            Ok(response) if response.mid == Mid::COMMAND_ERROR.value() => {
+                entry.naks += 1;
+                // MID 0004 data: failed MID (4 digits) + error code (2 digits)
+                let code = String::from_utf8_lossy(response.data.get(4..6).unwrap_or_default());
+                entry.record_failure(
+                    format!("nak_{code}"),
+                    format!("MID {} answered with MID 0004 error {code}", Mid(mid)),
+                );
+            }
+            Ok(_) => {}
+        }
+    }
+
+    /// Snapshot of the counters, keyed by MID
+    pub fn snapshot(&self) -> BTreeMap<u16, MidMetrics> {
+        self.per_mid.lock().unwrap().clone()
+    }
+
+    /// Latency percentiles and errors of every dispatched MID, by MID
+    pub fn stats(&self) -> Vec<HandlerStats> {
+        let per_mid = self.per_mid.lock().unwrap();
+        per_mid
+            .iter()
+            .map(|(&mid, metrics)| {
+                let mut sorted: Vec<u64> = metrics.samples.iter().copied().collect();
+                sorted.sort_unstable();
+                HandlerStats {
+                    mid,
+                    name: metrics.name,
+                    count: metrics.count,
+                    errors: metrics.errors,
+                    naks: metrics.naks,
+                    mean_micros: metrics.total_micros / metrics.count.max(1),
+                    p50_micros: percentile(&sorted, 50),
+                    p99_micros: percentile(&sorted, 99),
+                    max_micros: sorted.last().copied().unwrap_or(0),
+                    errors_by_type: metrics.errors_by_type.clone(),
+                    last_error: metrics.last_error.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Counts dispatches, errors and time spent per MID
+pub struct MetricsLayer {
+    metrics: Arc<HandlerMetrics>,
+}
+
+impl MetricsLayer {
+    pub fn new(metrics: Arc<HandlerMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl Middleware for MetricsLayer {
+    fn handle(&self, message: &Message, next: Next<'_>) -> Result<Response, HandlerError> {
+        let started = Instant::now();
+        let result = next.run(message);
+        self.metrics.record(message.mid, started.elapsed(), &result);
+        result
+    }
+}
+
+// ============================================================================
+// Strictness
+// ============================================================================
+
+/// Rejects frames that a strict controller would NAK
+///
+/// Checks that the revision is non-zero, the header length matches the
+/// payload and the payload is printable ASCII.
+pub struct StrictnessLayer;
+
+impl Middleware for StrictnessLayer {
+    fn handle(&self, message: &Message, next: Next<'_>) -> Result<Response, HandlerError> {
+        if message.revision == 0 {
+            return Ok(error_response(message, ErrorCode::MidRevisionUnsupported));
+        }
+
+        let length_ok = message.length == HEADER_SIZE + message.data.len() as u32;
+        let ascii_ok = message.data.iter().all(|b| (0x20..0x7f).contains(b));
+        if !length_ok || !ascii_ok {
+            return Ok(error_response(message, ErrorCode::InvalidData));
+        }
+
+        next.run(message)
+    }
+}
+
+// ============================================================================
+// Artificial delay
+// ============================================================================
+
+/// Delays every response by a fixed amount to emulate a slow controller
+///
+/// The connection awaits the delay (see [`Middleware::response_delay`]), so
+/// other connections are not held up.
+pub struct DelayLayer {
+    delay: Duration,
+}
+
+impl DelayLayer {
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl Middleware for DelayLayer {
+    fn handle(&self, message: &Message, next: Next<'_>) -> Result<Response, HandlerError> {
+        next.run(message)
+    }
+
+    fn response_delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+// ============================================================================
+// Authorization (locked-down mode)
+// ============================================================================
+
+/// Only lets an allow-list of MIDs through; everything else gets MID 0004
+pub struct AuthorizationLayer {
+    allowed_mids: HashSet<u16>,
+}
+
+impl AuthorizationLayer {
+    pub fn new(allowed_mids: impl IntoIterator<Item = u16>) -> Self {
+        Self {
+            allowed_mids: allowed_mids.into_iter().collect(),
+        }
+    }
+}
+
+impl Middleware for AuthorizationLayer {
+    fn handle(&self, message: &Message, next: Next<'_>) -> Result<Response, HandlerError> {
+        if !self.allowed_mids.contains(&message.mid) {
+            println!("MID {} rejected: controller is locked down", message.mid);
+            return Ok(error_response(message, ErrorCode::ControllerNotReady));
+        }
+        next.run(message)
+    }
+}
+
+// ============================================================================
+// Observer (read-only connections)
+// ============================================================================
+
+/// Refuses state-changing MIDs with MID 0004 "controller is in observer mode"
+///
+/// Subscriptions, acknowledgments and upload requests pass through, so an
+/// observer sees the same broadcasts as any other client.
+pub struct ObserverLayer;
+
+impl Middleware for ObserverLayer {
+    fn handle(&self, message: &Message, next: Next<'_>) -> Result<Response, HandlerError> {
+        if Mid(message.mid).changes_state() {
+            println!(
+                "MID {} rejected: controller is in observer mode",
+                message.mid
+            );
+            return Ok(error_response(message, ErrorCode::ObserverMode));
+        }
+        next.run(message)
+    }
+}
+
+fn error_response(message: &Message, code: ErrorCode) -> Response {
+    ErrorResponse::new(message.mid, code).into_response(message.revision.max(1))
+}
+
+/// Build the layer stack described by the `[handlers]` configuration
+///
+/// Order (outermost first): logging, metrics, authorization, strictness, delay.
+pub fn layers_from_config(
+    config: &HandlersConfig,
+    metrics: Arc<HandlerMetrics>,
+) -> Vec<Box<dyn Middleware>> {
+    let mut layers: Vec<Box<dyn Middleware>> = Vec::new();
+
+    if config.log_messages {
+        layers.push(Box::new(LoggingLayer));
+    }
+    layers.push(Box::new(MetricsLayer::new(metrics)));
+    if config.locked_down {
+        layers.push(Box::new(AuthorizationLayer::new(
+            config.allowed_mids.iter().copied(),
+        )));
+    }
+    if config.strict {
+        layers.push(Box::new(StrictnessLayer));
+    }
+    if config.response_delay_ms > 0 {
+        layers.push(Box::new(DelayLayer::new(Duration::from_millis(
+            config.response_delay_ms,
+        ))));
+    }
+
+    layers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::HandlerRegistry;
+    use crate::handler::keep_alive::KeepAliveHandler;
+
+    fn message(mid: u16, revision: u16, data: &[u8]) -> Message {
+        Message {
+            length: HEADER_SIZE + data.len() as u32,
+            mid,
+            revision,
+            data: data.to_vec(),
+            header: Default::default(),
+        }
+    }
+
+    fn registry_with(layer: Box<dyn Middleware>) -> HandlerRegistry {
+        let mut registry = HandlerRegistry::new();
+        registry.register(Mid::KEEP_ALIVE, Box::new(KeepAliveHandler));
+        registry.add_layer(layer);
+        registry
+    }
+
+    struct Tagging(u16);
+
+    impl Middleware for Tagging {
+        fn handle(&self, message: &Message, next: Next<'_>) -> Result<Response, HandlerError> {
+            let mut response = next.run(message)?;
+            response
+                .data
+                .extend_from_slice(self.0.to_string().as_bytes());
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn test_layers_run_in_registration_order() {
+        let mut registry = registry_with(Box::new(Tagging(1)));
+        registry.add_layer(Box::new(Tagging(2)));
+
+        let response = registry.handle_message(&message(9999, 1, b"")).unwrap();
+        // Inner layer appends first, outer layer last
+        assert_eq!(response.data, b"21");
+    }
+
+    #[test]
+    fn test_metrics_layer_counts_success_and_unknown_mid() {
+        let metrics = Arc::new(HandlerMetrics::new());
+        let registry = registry_with(Box::new(MetricsLayer::new(Arc::clone(&metrics))));
+
+        registry.handle_message(&message(9999, 1, b"")).unwrap();
+        assert!(registry.handle_message(&message(42, 1, b"")).is_err());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot[&9999].count, 1);
+        assert_eq!(snapshot[&9999].errors, 0);
+        assert_eq!(snapshot[&42].errors, 1);
+    }
+
+    #[test]
+    fn test_metrics_percentiles_and_error_breakdown() {
+        let metrics = HandlerMetrics::new();
+        for micros in 1..=100 {
+            metrics.record(
+                9999,
+                Duration::from_micros(micros),
+                &Ok(Response::new(Mid(9999), 1, vec![])),
+            );
+        }
+        metrics.record(
+            18,
+            Duration::from_micros(5),
+            &Ok(Response::new(Mid::COMMAND_ERROR, 1, b"001810".to_vec())),
+        );
+        metrics.record(
+            18,
+            Duration::from_micros(5),
+            &Err(HandlerError::UnknownMid(18)),
+        );
+
+        let stats = metrics.stats();
+        let keep_alive = stats.iter().find(|s| s.mid == 9999).unwrap();
+        assert_eq!(keep_alive.count, 100);
+        assert_eq!(
+            (
+                keep_alive.p50_micros,
+                keep_alive.p99_micros,
+                keep_alive.max_micros
+            ),
+            (50, 99, 100)
+        );
+        assert_eq!(keep_alive.last_error, None);
+
+        let pset_select = stats.iter().find(|s| s.mid == 18).unwrap();
+        assert_eq!((pset_select.errors, pset_select.naks), (1, 1));
+        assert_eq!(pset_select.errors_by_type["nak_10"], 1);
+        assert_eq!(pset_select.errors_by_type["unknown_mid"], 1);
+        assert_eq!(pset_select.last_error.as_ref().unwrap().kind, "unknown_mid");
+    }
+
+    #[test]
+    fn test_authorization_layer_rejects_unlisted_mid() {
+        let registry = registry_with(Box::new(AuthorizationLayer::new([1])));
+
+        let response = registry.handle_message(&message(9999, 1, b"")).unwrap();
+        assert_eq!(response.mid, 4);
+        assert_eq!(response.data, b"999902");
+    }
+
+    #[test]
+    fn test_observer_layer_rejects_state_changes_only() {
+        let mut registry = registry_with(Box::new(ObserverLayer));
+        registry.register(
+            Mid::PSET_SELECTED_SUBSCRIBE,
+            Box::new(crate::handler::pset_subscription::PsetSubscriptionHandler),
+        );
+
+        let response = registry.handle_message(&message(18, 1, b"001")).unwrap();
+        assert_eq!(response.mid, 4);
+        assert_eq!(response.data, b"001810");
+
+        let response = registry.handle_message(&message(14, 1, b"")).unwrap();
+        assert_eq!(response.mid, 5);
+        let response = registry.handle_message(&message(9999, 1, b"")).unwrap();
+        assert_eq!(response.mid, 9999);
+    }
+
+    #[test]
+    fn test_strictness_layer_rejects_bad_frames() {
+        let registry = registry_with(Box::new(StrictnessLayer));
+
+        let response = registry.handle_message(&message(9999, 0, b"")).unwrap();
+        assert_eq!(response.data, b"999901");
+
+        let mut mismatched = message(9999, 1, b"abc");
+        mismatched.length = 20;
+        let response = registry.handle_message(&mismatched).unwrap();
+        assert_eq!(response.data, b"999904");
+
+        let response = registry.handle_message(&message(9999, 1, b"")).unwrap();
+        assert_eq!(response.mid, 9999);
+    }
+
+    #[test]
+    fn test_delay_is_left_to_the_connection() {
+        let delay = Duration::from_secs(5);
+        let registry = registry_with(Box::new(DelayLayer::new(delay)));
+
+        let started = Instant::now();
+        let response = registry.handle_message(&message(9999, 1, b"")).unwrap();
+        assert_eq!(response.mid, 9999);
+        assert!(started.elapsed() < delay);
+        assert_eq!(registry.response_delay(), delay);
+        assert_eq!(HandlerRegistry::new().response_delay(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_layers_from_config_respects_flags() {
+        let metrics = Arc::new(HandlerMetrics::new());
+        let config = HandlersConfig::default();
+        assert_eq!(layers_from_config(&config, Arc::clone(&metrics)).len(), 2);
+
+        let config = HandlersConfig {
+            strict: true,
+            locked_down: true,
+            response_delay_ms: 5,
+            ..Default::default()
+        };
+        assert_eq!(layers_from_config(&config, metrics).len(), 5);
+    }
+}
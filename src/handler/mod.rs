@@ -1,31 +1,63 @@
+pub mod alarm_ack;
+pub mod alarm_acknowledged_ack;
+pub mod alarm_language;
+pub mod alarm_subscribe;
+pub mod alarm_unsubscribe;
 pub mod batch_increment;
 pub mod batch_reset;
 pub mod batch_size;
 pub mod communication_start;
 pub mod communication_stop;
 pub mod data;
+pub mod event_log_upload;
+pub mod job_data_upload;
+pub mod job_id_upload;
+pub mod job_info_ack;
+pub mod job_info_subscribe;
+pub mod job_info_unsubscribe;
+pub mod job_restart;
+pub mod job_select;
 pub mod keep_alive;
+pub mod lock_at_batch_done_ack;
+pub mod lock_at_batch_done_subscribe;
+pub mod lock_at_batch_done_unsubscribe;
+pub mod middleware;
 pub mod multi_spindle_result_ack;
 pub mod multi_spindle_result_subscribe;
 pub mod multi_spindle_result_unsubscribe;
 pub mod multi_spindle_status_ack;
 pub mod multi_spindle_status_subscribe;
 pub mod multi_spindle_status_unsubscribe;
+pub mod operator_login;
+pub mod operator_logout;
+pub mod pset_id_upload;
 pub mod pset_select;
+pub mod pset_selected_ack;
 pub mod pset_subscription;
 pub mod pset_unsubscribe;
+pub mod telemetry_upload;
 pub mod tightening_result_ack;
 pub mod tightening_result_subscription;
 pub mod tightening_result_unsubscribe;
+pub mod tool_data_upload;
 pub mod tool_disable;
 pub mod tool_enable;
+pub mod user_data_ack;
+pub mod user_data_download;
+pub mod user_data_download_offset;
+pub mod user_data_subscribe;
+pub mod user_data_unsubscribe;
 pub mod vehicle_id_ack;
 pub mod vehicle_id_download;
 pub mod vehicle_id_subscription;
 pub mod vehicle_id_unsubscribe;
 
+use crate::job::SharedJobRepository;
 use crate::observable_state::ObservableState;
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, ProtocolError, Response};
+use crate::pset::SharedPsetRepository;
+use middleware::{Middleware, Next};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
@@ -38,6 +70,46 @@ pub enum HandlerError {
     #[error("Handler error: {0}")]
     #[allow(dead_code)]
     Processing(String),
+
+    #[error("Response serialization failed: {0}")]
+    Serialization(#[from] ProtocolError),
+}
+
+impl HandlerError {
+    /// Short name of the error kind, as counted by the metrics layer
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HandlerError::UnknownMid(_) => "unknown_mid",
+            HandlerError::Processing(_) => "processing",
+            HandlerError::Serialization(_) => "serialization",
+        }
+    }
+}
+
+/// What the controller does with a MID it has no handler for
+///
+/// Real controllers differ here, and clients often assume one of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownMidPolicy {
+    /// Reply with MID 0004, error 99 (MID not supported)
+    #[default]
+    Error,
+    /// Drop the frame without a reply
+    Ignore,
+    /// Close the connection
+    Disconnect,
+}
+
+impl UnknownMidPolicy {
+    /// Configuration name of the policy
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Ignore => "ignore",
+            Self::Disconnect => "disconnect",
+        }
+    }
 }
 
 /// Trait for handling specific MID messages
@@ -47,30 +119,71 @@ pub trait MidHandler: Send + Sync {
 }
 
 /// Registry that routes MIDs to their handlers
+///
+/// Every dispatch passes through the registered middleware layers before
+/// reaching the handler (see [`middleware`]).
 pub struct HandlerRegistry {
     handlers: HashMap<u16, Box<dyn MidHandler>>,
+    layers: Vec<Box<dyn Middleware>>,
 }
 
 impl HandlerRegistry {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            layers: Vec::new(),
         }
     }
 
+    /// Time to wait before sending a response, summed over the layers
+    pub fn response_delay(&self) -> std::time::Duration {
+        self.layers.iter().map(|layer| layer.response_delay()).sum()
+    }
+
+    /// Add a middleware layer; layers run in the order they are added
+    pub fn add_layer(&mut self, layer: Box<dyn Middleware>) {
+        self.layers.push(layer);
+    }
+
     /// Register a handler for a specific MID
-    pub fn register(&mut self, mid: u16, handler: Box<dyn MidHandler>) {
-        self.handlers.insert(mid, handler);
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid` is not described in [`MID_TABLE`](crate::protocol::mid::MID_TABLE),
+    /// so every handled MID has a name for logs and metrics.
+    pub fn register(&mut self, mid: Mid, handler: Box<dyn MidHandler>) {
+        assert!(
+            mid.info().is_some(),
+            "MID {} has no entry in the MID table",
+            mid
+        );
+        self.handlers.insert(mid.value(), handler);
+    }
+
+    /// MIDs with a registered handler, in ascending order
+    pub fn mids(&self) -> Vec<Mid> {
+        let mut mids: Vec<Mid> = self.handlers.keys().map(|mid| Mid(*mid)).collect();
+        mids.sort();
+        mids
     }
 
     /// Process a message using the appropriate handler
     pub fn handle_message(&self, message: &Message) -> Result<Response, HandlerError> {
-        let handler = self
-            .handlers
-            .get(&message.mid)
-            .ok_or(HandlerError::UnknownMid(message.mid))?;
+        let handler = self.handlers.get(&message.mid).map(|h| h.as_ref());
 
-        handler.handle(message)
+        Next::new(&self.layers, handler).run(message)
+    }
+
+    /// Process a message with `handler` in place of the registered one
+    ///
+    /// The message still passes every layer, for handlers that belong to a
+    /// single connection rather than to the controller.
+    pub fn handle_message_with(
+        &self,
+        message: &Message,
+        handler: &dyn MidHandler,
+    ) -> Result<Response, HandlerError> {
+        Next::new(&self.layers, Some(handler)).run(message)
     }
 }
 
@@ -81,107 +194,257 @@ impl Default for HandlerRegistry {
 }
 
 /// Create a registry with all standard handlers registered
-pub fn create_default_registry(observable_state: ObservableState) -> HandlerRegistry {
+pub fn create_default_registry(
+    observable_state: ObservableState,
+    pset_repository: SharedPsetRepository,
+    job_repository: SharedJobRepository,
+) -> HandlerRegistry {
     let mut registry = HandlerRegistry::new();
     let state = observable_state.state();
 
     // Register all MID handlers (sorted by MID number)
     registry.register(
-        1,
+        Mid::COMMUNICATION_START,
         Box::new(communication_start::CommunicationStartHandler::new(
             Arc::clone(state),
         )),
     );
     registry.register(
-        3,
+        Mid::COMMUNICATION_STOP,
         Box::new(communication_stop::CommunicationStopHandler::new()),
     );
-    registry.register(14, Box::new(pset_subscription::PsetSubscriptionHandler));
-    registry.register(17, Box::new(pset_unsubscribe::PsetUnsubscribeHandler));
     registry.register(
-        18,
+        Mid::PSET_ID_UPLOAD_REQUEST,
+        Box::new(pset_id_upload::PsetIdUploadHandler::new(
+            observable_state.clone(),
+            pset_repository.clone(),
+        )),
+    );
+    registry.register(
+        Mid::PSET_SELECTED_SUBSCRIBE,
+        Box::new(pset_subscription::PsetSubscriptionHandler),
+    );
+    registry.register(
+        Mid::PSET_SELECTED_ACK,
+        Box::new(pset_selected_ack::PsetSelectedAckHandler),
+    );
+    registry.register(
+        Mid::PSET_SELECTED_UNSUBSCRIBE,
+        Box::new(pset_unsubscribe::PsetUnsubscribeHandler),
+    );
+    registry.register(
+        Mid::SELECT_PSET,
         Box::new(pset_select::PsetSelectHandler::new(
             observable_state.clone(),
         )),
     );
     registry.register(
-        19,
+        Mid::SET_PSET_BATCH_SIZE,
         Box::new(batch_size::BatchSizeHandler::new(Arc::clone(state))),
     );
     registry.register(
-        20,
-        Box::new(batch_reset::BatchResetHandler::new(Arc::clone(state))),
+        Mid::RESET_PSET_BATCH_COUNTER,
+        Box::new(batch_reset::BatchResetHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(
+        Mid::LOCK_AT_BATCH_DONE_SUBSCRIBE,
+        Box::new(lock_at_batch_done_subscribe::LockAtBatchDoneSubscribeHandler),
+    );
+    registry.register(
+        Mid::LOCK_AT_BATCH_DONE_ACK,
+        Box::new(lock_at_batch_done_ack::LockAtBatchDoneAckHandler),
+    );
+    registry.register(
+        Mid::LOCK_AT_BATCH_DONE_UNSUBSCRIBE,
+        Box::new(lock_at_batch_done_unsubscribe::LockAtBatchDoneUnsubscribeHandler),
+    );
+    registry.register(
+        Mid::JOB_ID_UPLOAD_REQUEST,
+        Box::new(job_id_upload::JobIdUploadHandler::new(
+            job_repository.clone(),
+        )),
+    );
+    registry.register(
+        Mid::JOB_DATA_UPLOAD_REQUEST,
+        Box::new(job_data_upload::JobDataUploadHandler::new(
+            job_repository.clone(),
+        )),
+    );
+    registry.register(
+        Mid::JOB_INFO_SUBSCRIBE,
+        Box::new(job_info_subscribe::JobInfoSubscribeHandler),
+    );
+    registry.register(Mid::JOB_INFO_ACK, Box::new(job_info_ack::JobInfoAckHandler));
+    registry.register(
+        Mid::JOB_INFO_UNSUBSCRIBE,
+        Box::new(job_info_unsubscribe::JobInfoUnsubscribeHandler),
+    );
+    registry.register(
+        Mid::SELECT_JOB,
+        Box::new(job_select::JobSelectHandler::new(
+            observable_state.clone(),
+            job_repository.clone(),
+            pset_repository.clone(),
+        )),
     );
     registry.register(
-        128,
+        Mid::RESTART_JOB,
+        Box::new(job_restart::JobRestartHandler::new(
+            observable_state.clone(),
+            job_repository,
+            pset_repository,
+        )),
+    );
+    registry.register(
+        Mid::JOB_BATCH_INCREMENT,
         Box::new(batch_increment::BatchIncrementHandler::new(
             observable_state.clone(),
         )),
     );
     registry.register(
-        42,
+        Mid::TOOL_DATA_UPLOAD_REQUEST,
+        Box::new(tool_data_upload::ToolDataUploadHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(
+        Mid::DISABLE_TOOL,
         Box::new(tool_disable::ToolDisableHandler::new(
             observable_state.clone(),
         )),
     );
     registry.register(
-        43,
+        Mid::ENABLE_TOOL,
         Box::new(tool_enable::ToolEnableHandler::new(
             observable_state.clone(),
         )),
     );
     registry.register(
-        50,
+        Mid::VEHICLE_ID_DOWNLOAD,
         Box::new(vehicle_id_download::VehicleIdDownloadHandler::new(
             observable_state.clone(),
         )),
     );
     registry.register(
-        51,
+        Mid::VEHICLE_ID_SUBSCRIBE,
         Box::new(vehicle_id_subscription::VehicleIdSubscriptionHandler),
     );
-    registry.register(53, Box::new(vehicle_id_ack::VehicleIdAckHandler));
     registry.register(
-        54,
+        Mid::VEHICLE_ID_ACK,
+        Box::new(vehicle_id_ack::VehicleIdAckHandler),
+    );
+    registry.register(
+        Mid::VEHICLE_ID_UNSUBSCRIBE,
         Box::new(vehicle_id_unsubscribe::VehicleIdUnsubscribeHandler),
     );
     registry.register(
-        90,
+        Mid::ALARM_SUBSCRIBE,
+        Box::new(alarm_subscribe::AlarmSubscribeHandler),
+    );
+    registry.register(Mid::ALARM_ACK, Box::new(alarm_ack::AlarmAckHandler));
+    registry.register(
+        Mid::ALARM_UNSUBSCRIBE,
+        Box::new(alarm_unsubscribe::AlarmUnsubscribeHandler),
+    );
+    registry.register(
+        Mid::ALARM_ACKNOWLEDGED_ACK,
+        Box::new(alarm_acknowledged_ack::AlarmAcknowledgedAckHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(
+        Mid::MULTI_SPINDLE_STATUS_SUBSCRIBE,
         Box::new(multi_spindle_status_subscribe::MultiSpindleStatusSubscribeHandler),
     );
     registry.register(
-        92,
+        Mid::MULTI_SPINDLE_STATUS_UNSUBSCRIBE,
         Box::new(multi_spindle_status_unsubscribe::MultiSpindleStatusUnsubscribeHandler),
     );
     registry.register(
-        93,
+        Mid::MULTI_SPINDLE_STATUS_ACK,
         Box::new(multi_spindle_status_ack::MultiSpindleStatusAckHandler),
     );
     registry.register(
-        100,
+        Mid::MULTI_SPINDLE_RESULT_SUBSCRIBE,
         Box::new(multi_spindle_result_subscribe::MultiSpindleResultSubscribeHandler),
     );
     registry.register(
-        102,
+        Mid::MULTI_SPINDLE_RESULT_ACK,
         Box::new(multi_spindle_result_ack::MultiSpindleResultAckHandler),
     );
     registry.register(
-        103,
+        Mid::MULTI_SPINDLE_RESULT_UNSUBSCRIBE,
         Box::new(multi_spindle_result_unsubscribe::MultiSpindleResultUnsubscribeHandler),
     );
     registry.register(
-        60,
+        Mid::TIGHTENING_RESULT_SUBSCRIBE,
         Box::new(tightening_result_subscription::TighteningResultSubscriptionHandler),
     );
     registry.register(
-        62,
+        Mid::TIGHTENING_RESULT_ACK,
         Box::new(tightening_result_ack::TighteningResultAckHandler),
     );
     registry.register(
-        63,
+        Mid::TIGHTENING_RESULT_UNSUBSCRIBE,
         Box::new(tightening_result_unsubscribe::TighteningResultUnsubscribeHandler),
     );
-    registry.register(9999, Box::new(keep_alive::KeepAliveHandler));
+    registry.register(
+        Mid::USER_DATA_DOWNLOAD,
+        Box::new(user_data_download::UserDataDownloadHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(
+        Mid::USER_DATA_SUBSCRIBE,
+        Box::new(user_data_subscribe::UserDataSubscribeHandler),
+    );
+    registry.register(
+        Mid::USER_DATA_ACK,
+        Box::new(user_data_ack::UserDataAckHandler),
+    );
+    registry.register(
+        Mid::USER_DATA_UNSUBSCRIBE,
+        Box::new(user_data_unsubscribe::UserDataUnsubscribeHandler),
+    );
+    registry.register(
+        Mid::USER_DATA_DOWNLOAD_WITH_OFFSET,
+        Box::new(
+            user_data_download_offset::UserDataDownloadOffsetHandler::new(observable_state.clone()),
+        ),
+    );
+    registry.register(
+        Mid::EVENT_LOG_UPLOAD_REQUEST,
+        Box::new(event_log_upload::EventLogUploadHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(
+        Mid::OPERATOR_LOGIN,
+        Box::new(operator_login::OperatorLoginHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(
+        Mid::OPERATOR_LOGOUT,
+        Box::new(operator_logout::OperatorLogoutHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(
+        Mid::TELEMETRY_UPLOAD_REQUEST,
+        Box::new(telemetry_upload::TelemetryUploadHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(
+        Mid::ALARM_LANGUAGE_SELECT,
+        Box::new(alarm_language::AlarmLanguageHandler::new(
+            observable_state.clone(),
+        )),
+    );
+    registry.register(Mid::KEEP_ALIVE, Box::new(keep_alive::KeepAliveHandler));
 
     registry
 }
@@ -1,5 +1,5 @@
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0102 - Multi-spindle result acknowledge
 /// Client acknowledges receipt of multi-spindle result broadcast (MID 0101)
@@ -11,7 +11,7 @@ impl MidHandler for MultiSpindleResultAckHandler {
         println!("MID 0102: Multi-spindle result acknowledged by client");
 
         // No response data required for acknowledgments
-        Ok(Response::new(5, 1, Vec::new()))
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
     }
 }
 
@@ -27,6 +27,7 @@ mod tests {
             mid: 102,
             revision: 1,
             data: vec![],
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
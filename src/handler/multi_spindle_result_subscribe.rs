@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0100 - Multi-spindle result subscribe
 /// Client requests subscription to multi-spindle tightening results
@@ -12,8 +12,12 @@ impl MidHandler for MultiSpindleResultSubscribeHandler {
         println!("MID 0100: Multi-spindle result subscription request");
 
         // Acknowledge subscription
-        let ack_data = CommandAccepted::with_mid(100);
-        Ok(Response::from_data(5, message.revision, ack_data))
+        let ack_data = CommandAccepted::with_mid(Mid::MULTI_SPINDLE_RESULT_SUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
 
@@ -29,6 +33,7 @@ mod tests {
             mid: 100,
             revision: 1,
             data: vec![],
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0103 - Multi-spindle result unsubscribe
 /// Client requests to stop receiving multi-spindle result updates
@@ -11,8 +11,12 @@ impl MidHandler for MultiSpindleResultUnsubscribeHandler {
         println!("MID 0103: Multi-spindle result unsubscribe request");
 
         // Acknowledge unsubscription
-        let ack_data = CommandAccepted::with_mid(103);
-        Ok(Response::from_data(5, message.revision, ack_data))
+        let ack_data = CommandAccepted::with_mid(Mid::MULTI_SPINDLE_RESULT_UNSUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
 
@@ -28,6 +32,7 @@ mod tests {
             mid: 103,
             revision: 1,
             data: vec![],
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
@@ -1,5 +1,5 @@
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0093 - Multi-spindle status acknowledge
 /// Client acknowledges receipt of multi-spindle status broadcast (MID 0091)
@@ -10,7 +10,7 @@ impl MidHandler for MultiSpindleStatusAckHandler {
         println!("MID 0093: Multi-spindle status acknowledged by client");
 
         // No response data required for acknowledgments
-        Ok(Response::new(5, 1, Vec::new()))
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
     }
 }
 
@@ -26,6 +26,7 @@ mod tests {
             mid: 93,
             revision: 1,
             data: vec![],
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
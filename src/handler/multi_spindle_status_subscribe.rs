@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0090 - Multi-spindle status subscribe
 /// Client requests subscription to multi-spindle status updates
@@ -11,8 +11,12 @@ impl MidHandler for MultiSpindleStatusSubscribeHandler {
         println!("MID 0090: Multi-spindle status subscription request");
 
         // Acknowledge subscription
-        let ack_data = CommandAccepted::with_mid(90);
-        Ok(Response::from_data(5, message.revision, ack_data))
+        let ack_data = CommandAccepted::with_mid(Mid::MULTI_SPINDLE_STATUS_SUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
 
@@ -28,6 +32,7 @@ mod tests {
             mid: 90,
             revision: 1,
             data: vec![],
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
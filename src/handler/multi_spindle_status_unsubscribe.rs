@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0092 - Multi-spindle status unsubscribe
 /// Client requests to stop receiving multi-spindle status updates
@@ -11,8 +11,12 @@ impl MidHandler for MultiSpindleStatusUnsubscribeHandler {
         println!("MID 0092: Multi-spindle status unsubscribe request");
 
         // Acknowledge unsubscription
-        let ack_data = CommandAccepted::with_mid(92);
-        Ok(Response::from_data(5, message.revision, ack_data))
+        let ack_data = CommandAccepted::with_mid(Mid::MULTI_SPINDLE_STATUS_UNSUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
 
@@ -28,6 +32,7 @@ mod tests {
             mid: 92,
             revision: 1,
             data: vec![],
+            header: Default::default(),
         };
 
         let response = handler.handle(&message).unwrap();
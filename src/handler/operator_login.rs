@@ -0,0 +1,96 @@
+//! MID 9110 - Operator login (simulator extension)
+//!
+//! Open Protocol has no standard MID for operator login, so the simulator
+//! accepts one outside the ranges used by the specification. The request data
+//! is the operator ID (up to 20 ASCII characters). When
+//! `require_operator_login` is enabled, tightenings are refused until an
+//! operator is logged in.
+
+use crate::handler::data::{CommandAccepted, ErrorCode, ErrorResponse};
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+
+/// Maximum operator ID length carried by MID 9110
+pub const OPERATOR_ID_MAX_LEN: usize = 20;
+
+/// MID 9110 - Operator login
+pub struct OperatorLoginHandler {
+    state: ObservableState,
+}
+
+impl OperatorLoginHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+impl MidHandler for OperatorLoginHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let operator_id = String::from_utf8_lossy(&message.data).trim().to_string();
+
+        if operator_id.is_empty() || operator_id.len() > OPERATOR_ID_MAX_LEN {
+            println!("MID 9110: Operator login rejected - invalid operator ID");
+            return Ok(Response::from_data(
+                Mid::COMMAND_ERROR,
+                message.revision,
+                ErrorResponse::new(Mid::OPERATOR_LOGIN, ErrorCode::InvalidData),
+            )?);
+        }
+
+        println!("MID 9110: Operator login - {}", operator_id);
+        self.state.login_operator(operator_id, None);
+
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            CommandAccepted::with_mid(Mid::OPERATOR_LOGIN),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DeviceState;
+    use std::sync::{Arc, RwLock};
+
+    fn handler() -> (OperatorLoginHandler, ObservableState) {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(Arc::new(RwLock::new(DeviceState::new())), tx);
+        (OperatorLoginHandler::new(state.clone()), state)
+    }
+
+    fn message(data: &[u8]) -> Message {
+        Message {
+            length: 20 + data.len() as u32,
+            mid: 9110,
+            revision: 1,
+            data: data.to_vec(),
+            header: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_login_sets_operator() {
+        let (handler, state) = handler();
+        let response = handler.handle(&message(b"OP-1234")).unwrap();
+
+        assert_eq!(response.mid, 5);
+        assert_eq!(response.data, b"9110");
+        assert_eq!(
+            state.read().operator.as_ref().unwrap().operator_id,
+            "OP-1234"
+        );
+    }
+
+    #[test]
+    fn test_empty_operator_id_is_rejected() {
+        let (handler, state) = handler();
+        let response = handler.handle(&message(b"   ")).unwrap();
+
+        assert_eq!(response.mid, 4);
+        assert_eq!(response.data, b"911004");
+        assert!(state.read().operator.is_none());
+    }
+}
@@ -0,0 +1,32 @@
+//! MID 9111 - Operator logout (simulator extension)
+//!
+//! Counterpart of MID 9110. Logging out when nobody is logged in is accepted.
+
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 9111 - Operator logout
+pub struct OperatorLogoutHandler {
+    state: ObservableState,
+}
+
+impl OperatorLogoutHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+impl MidHandler for OperatorLogoutHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 9111: Operator logout");
+        self.state.logout_operator();
+
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            CommandAccepted::with_mid(Mid::OPERATOR_LOGOUT),
+        )?)
+    }
+}
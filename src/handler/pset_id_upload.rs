@@ -0,0 +1,95 @@
+//! MID 0010 - Parameter set ID upload request handler
+//!
+//! Replies with MID 0011 listing the IDs of the PSETs in the repository, in
+//! the revision the client asked for. Revision 2 adds the last-change date
+//! MID 0015 reports for each.
+
+use crate::handler::data::PsetIdUpload;
+use crate::handler::data::pset_id_upload::MAX_PSET_ID;
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+use crate::pset::SharedPsetRepository;
+
+/// MID 0010 - Parameter set ID upload request
+pub struct PsetIdUploadHandler {
+    state: ObservableState,
+    psets: SharedPsetRepository,
+}
+
+impl PsetIdUploadHandler {
+    pub fn new(state: ObservableState, psets: SharedPsetRepository) -> Self {
+        Self { state, psets }
+    }
+}
+
+impl MidHandler for PsetIdUploadHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let revision = message.revision.clamp(1, PsetIdUpload::MAX_REVISION);
+        let mut ids: Vec<u32> = self
+            .psets
+            .read()
+            .unwrap()
+            .get_all()
+            .iter()
+            .map(|pset| pset.id)
+            .filter(|id| *id <= MAX_PSET_ID)
+            .collect();
+        ids.sort_unstable();
+        let upload = {
+            let state = self.state.read();
+            PsetIdUpload {
+                psets: ids
+                    .into_iter()
+                    .map(|id| (id, state.pset_history.last_change(id).to_string()))
+                    .collect(),
+            }
+        };
+        println!(
+            "MID 0010: Parameter set ID upload (rev {}) - {} PSETs",
+            revision,
+            upload.psets.len()
+        );
+
+        let data = upload.serialize_revision(revision)?;
+        Ok(Response::new(Mid::PSET_ID_UPLOAD, revision, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pset::create_default_repository;
+    use crate::pset_history::{PsetChangeKind, PsetChangeSource};
+    use crate::state::DeviceState;
+
+    #[test]
+    fn test_lists_repository_psets() {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(DeviceState::new_shared(), tx);
+        let psets = create_default_repository();
+        psets.write().unwrap().delete(3).unwrap();
+        let handler = PsetIdUploadHandler::new(state.clone(), psets);
+
+        let response = handler.handle(&Message::new(10, 1, Vec::new())).unwrap();
+        assert_eq!(response.mid, 11);
+        assert_eq!(response.revision, 1);
+        assert_eq!(&response.data[..], b"004001002004005");
+
+        let response = handler.handle(&Message::new(10, 3, Vec::new())).unwrap();
+        assert_eq!(response.revision, 2);
+        assert_eq!(response.data.len(), 3 + 4 * (3 + 19));
+
+        // The date MID 0015 reports follows the PSET's last settings change
+        let updated =
+            state
+                .write()
+                .pset_history
+                .record(2, PsetChangeKind::Updated, PsetChangeSource::Http);
+        let response = handler.handle(&Message::new(10, 2, Vec::new())).unwrap();
+        assert_eq!(
+            &response.data[3 + 22 + 3..3 + 22 + 22],
+            updated.timestamp.as_bytes()
+        );
+    }
+}
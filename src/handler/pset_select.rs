@@ -5,7 +5,8 @@
 
 use crate::handler::{HandlerError, MidHandler};
 use crate::observable_state::ObservableState;
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
+use crate::pset_history::PsetChangeSource;
 
 /// MID 0018 - Parameter set selection
 /// Selects a specific parameter set (pset) for tightening operations
@@ -19,25 +20,37 @@ impl PsetSelectHandler {
     }
 }
 
+/// PSET ID requested by a MID 0018 (PSET 1 when missing or invalid)
+pub fn requested_pset(message: &Message) -> u32 {
+    // Extract pset ID from message data if present
+    let pset_str = if !message.data.is_empty() {
+        String::from_utf8_lossy(&message.data).to_string()
+    } else {
+        "1".to_string()
+    };
+
+    // Parse pset ID
+    pset_str.trim().parse::<u32>().unwrap_or(1)
+}
+
 impl MidHandler for PsetSelectHandler {
     fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
-        // Extract pset ID from message data if present
-        let pset_str = if !message.data.is_empty() {
-            String::from_utf8_lossy(&message.data).to_string()
-        } else {
-            "1".to_string()
-        };
-
-        // Parse pset ID
-        let pset_id = pset_str.trim().parse::<u32>().unwrap_or(1);
+        let pset_id = requested_pset(message);
 
         println!("MID 0018: Parameter set select - Pset ID: {}", pset_id);
 
         // Update device state and broadcast event
-        self.state
-            .set_pset(pset_id, Some(format!("Pset_{}", pset_id)));
+        self.state.set_pset(
+            pset_id,
+            Some(format!("Pset_{}", pset_id)),
+            PsetChangeSource::Mid0018,
+        );
 
         // Respond with MID 0016 (Command accepted)
-        Ok(Response::new(16, message.revision, Vec::new()))
+        Ok(Response::new(
+            Mid::PSET_SELECTED_ACK,
+            message.revision,
+            Vec::new(),
+        ))
     }
 }
@@ -0,0 +1,15 @@
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0016 - Parameter set selected acknowledge
+/// Client acknowledges receipt of a PSET selection (MID 0015); under an
+/// `[acks.pset_selection]` policy the MID 0015 is resent until this arrives
+pub struct PsetSelectedAckHandler;
+
+impl MidHandler for PsetSelectedAckHandler {
+    fn handle(&self, _message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0016: Parameter set selection acknowledged by client");
+
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
+    }
+}
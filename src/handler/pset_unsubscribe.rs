@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0017 - Unsubscribe from pset selection
 /// Responds with MID 0005 (Command accepted)
@@ -13,9 +13,13 @@ impl MidHandler for PsetUnsubscribeHandler {
     fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
         println!("MID 0017: Pset selection unsubscribe request");
 
-        let ack_data = CommandAccepted::with_mid(17);
+        let ack_data = CommandAccepted::with_mid(Mid::PSET_SELECTED_UNSUBSCRIBE);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
@@ -0,0 +1,38 @@
+//! MID 9120 - Telemetry upload request (simulator extension)
+//!
+//! Open Protocol has no MID for motor temperature, current or supply voltage,
+//! so the simulator serves its telemetry through a request/reply pair next to
+//! the event log upload. The request carries no data; the reply (MID 9121)
+//! holds the current values.
+
+use crate::handler::data::TelemetryUpload;
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 9120 - Telemetry upload request
+pub struct TelemetryUploadHandler {
+    state: ObservableState,
+}
+
+impl TelemetryUploadHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+impl MidHandler for TelemetryUploadHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let telemetry = self.state.read().telemetry;
+        println!(
+            "MID 9120: Telemetry upload - {:.1} °C, {:.2} A, {:.2} V",
+            telemetry.motor_temperature_c, telemetry.current_a, telemetry.supply_voltage_v
+        );
+
+        Ok(Response::from_data(
+            Mid::TELEMETRY_UPLOAD,
+            message.revision,
+            TelemetryUpload::new(telemetry),
+        )?)
+    }
+}
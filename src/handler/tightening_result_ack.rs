@@ -1,5 +1,5 @@
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0062 - Last tightening result data acknowledge
 /// Client sends this to acknowledge receipt of MID 0061
@@ -15,6 +15,6 @@ impl MidHandler for TighteningResultAckHandler {
         // In a real implementation, you might want to track ACKs or handle this differently
 
         // Return a simple response with no data (just the header will be sent)
-        Ok(Response::new(5, 1, Vec::new()))
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
     }
 }
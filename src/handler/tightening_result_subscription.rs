@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0060 - Last tightening result data subscribe
 /// Responds with MID 0005 (Command accepted)
@@ -13,9 +13,13 @@ impl MidHandler for TighteningResultSubscriptionHandler {
     fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
         println!("MID 0060: Last tightening result subscription request");
 
-        let ack_data = CommandAccepted::with_mid(60);
+        let ack_data = CommandAccepted::with_mid(Mid::TIGHTENING_RESULT_SUBSCRIBE);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0063 - Last tightening result data unsubscribe
 /// Responds with MID 0005 (Command accepted)
@@ -13,9 +13,13 @@ impl MidHandler for TighteningResultUnsubscribeHandler {
     fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
         println!("MID 0063: Last tightening result unsubscribe request");
 
-        let ack_data = CommandAccepted::with_mid(63);
+        let ack_data = CommandAccepted::with_mid(Mid::TIGHTENING_RESULT_UNSUBSCRIBE);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
@@ -0,0 +1,67 @@
+//! MID 0040 - Tool data upload request handler
+//!
+//! Replies with MID 0041 carrying the tool and controller serial numbers and
+//! the lifetime tightening counter, in the revision the client asked for.
+
+use crate::handler::data::ToolDataUpload;
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0040 - Tool data upload request
+pub struct ToolDataUploadHandler {
+    state: ObservableState,
+}
+
+impl ToolDataUploadHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+impl MidHandler for ToolDataUploadHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let (tightenings, since_service, last_service_at, software_version) = {
+            let state = self.state.read();
+            (
+                state.lifetime_stats.total_tightenings,
+                state.lifetime_stats.tightenings_since_service,
+                state.lifetime_stats.last_service_at.clone(),
+                state.software_version.clone(),
+            )
+        };
+        let revision = message.revision.clamp(1, ToolDataUpload::MAX_REVISION);
+        println!(
+            "MID 0040: Tool data upload (rev {}) - {} tightenings",
+            revision, tightenings
+        );
+
+        let mut data = ToolDataUpload::new(tightenings);
+        data.software_version = software_version;
+        data.tightenings_since_service = since_service;
+        if let Some(date) = last_service_at {
+            data.last_service_date = date;
+        }
+        let data = data.serialize_revision(revision)?;
+        Ok(Response::new(Mid::TOOL_DATA_UPLOAD, revision, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DeviceState;
+
+    #[test]
+    fn test_reports_lifetime_tightenings() {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(DeviceState::new_shared(), tx);
+        state.write().record_tool_cycle(true, 40.0, 180.0, 500);
+        let handler = ToolDataUploadHandler::new(state);
+
+        let response = handler.handle(&Message::new(40, 5, Vec::new())).unwrap();
+        assert_eq!(response.mid, 41);
+        assert_eq!(response.revision, ToolDataUpload::MAX_REVISION);
+        assert_eq!(&response.data[16..28], b"020000000001");
+    }
+}
@@ -6,7 +6,7 @@
 use crate::handler::data::command_accepted::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
 use crate::observable_state::ObservableState;
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0042 - Tool disable
 /// Disables the tool to prevent tightening operations
@@ -27,9 +27,13 @@ impl MidHandler for ToolDisableHandler {
         // Update device state and broadcast event
         self.state.disable_tool();
 
-        let ack_data = CommandAccepted::with_mid(42);
+        let ack_data = CommandAccepted::with_mid(Mid::DISABLE_TOOL);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
@@ -2,11 +2,14 @@
 //!
 //! Enables the tool for tightening operations. When enabled, the device
 //! can perform tightenings either via auto-mode or manual triggering.
+//! A tool locked for service or held by an interlock is refused with MID 0004
+//! (controller not ready).
 
 use crate::handler::data::command_accepted::CommandAccepted;
+use crate::handler::data::{ErrorCode, ErrorResponse};
 use crate::handler::{HandlerError, MidHandler};
 use crate::observable_state::ObservableState;
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0043 - Tool enable
 /// Enables the tool for tightening operations
@@ -25,11 +28,21 @@ impl MidHandler for ToolEnableHandler {
         println!("MID 0043: Tool enable request");
 
         // Update device state and broadcast event
-        self.state.enable_tool();
+        if !self.state.enable_tool() {
+            println!("MID 0043: Tool is locked (service due or interlock waiting)");
+            return Ok(
+                ErrorResponse::new(Mid::ENABLE_TOOL, ErrorCode::ControllerNotReady)
+                    .into_response(message.revision),
+            );
+        }
 
-        let ack_data = CommandAccepted::with_mid(43);
+        let ack_data = CommandAccepted::with_mid(Mid::ENABLE_TOOL);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
@@ -0,0 +1,15 @@
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0243 - User data upload acknowledge
+/// Client acknowledges receipt of a user data upload (MID 0242)
+pub struct UserDataAckHandler;
+
+impl MidHandler for UserDataAckHandler {
+    fn handle(&self, _message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0243: User data upload acknowledged by client");
+
+        // No response data required for acknowledgments
+        Ok(Response::new(Mid::COMMAND_ACCEPTED, 1, Vec::new()))
+    }
+}
@@ -0,0 +1,73 @@
+//! MID 0240 - User data download handler
+//!
+//! The integrator stores user data (1-200 ASCII characters) on the
+//! controller. It lands in user data slot 0 (see [`crate::user_data`]);
+//! MID 0245 writes other slots. Empty or oversized data is refused with
+//! MID 0004 (invalid data).
+
+use crate::handler::data::{CommandAccepted, ErrorCode, ErrorResponse};
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0240 - User data download
+pub struct UserDataDownloadHandler {
+    state: ObservableState,
+}
+
+impl UserDataDownloadHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+impl MidHandler for UserDataDownloadHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let data = String::from_utf8_lossy(&message.data)
+            .trim_end()
+            .to_string();
+
+        let stored = if data.is_empty() {
+            Err("user data is empty".to_string())
+        } else {
+            self.state.set_user_data(0, data)
+        };
+        if let Err(e) = stored {
+            println!("MID 0240: User data download rejected - {}", e);
+            return Ok(
+                ErrorResponse::new(Mid::USER_DATA_DOWNLOAD, ErrorCode::InvalidData)
+                    .into_response(message.revision),
+            );
+        }
+
+        println!("MID 0240: User data stored in slot 0");
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            CommandAccepted::with_mid(Mid::USER_DATA_DOWNLOAD),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DeviceState;
+
+    #[test]
+    fn test_user_data_download_stores_slot_zero() {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(DeviceState::new_shared(), tx);
+        let handler = UserDataDownloadHandler::new(state.clone());
+
+        let response = handler
+            .handle(&Message::new(240, 1, b"STATION 12  ".to_vec()))
+            .unwrap();
+        assert_eq!(response.mid, Mid::COMMAND_ACCEPTED.value());
+        assert_eq!(state.read().user_data.get(0), Some("STATION 12"));
+
+        let response = handler.handle(&Message::new(240, 1, Vec::new())).unwrap();
+        assert_eq!(response.mid, Mid::COMMAND_ERROR.value());
+        assert_eq!(response.data, b"024004");
+    }
+}
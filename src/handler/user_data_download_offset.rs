@@ -0,0 +1,96 @@
+//! MID 0245 - User data download with offset handler
+//!
+//! Like MID 0240, but parameter 01 (three digits) gives the offset and
+//! parameter 02 the user data. The simulator keeps every offset as its own
+//! user data slot (see [`crate::user_data`]), so integrations can store
+//! several independent blobs. Empty data clears the slot.
+
+use crate::handler::data::{CommandAccepted, ErrorCode, ErrorResponse};
+use crate::handler::{HandlerError, MidHandler};
+use crate::observable_state::ObservableState;
+use crate::protocol::{FieldParser, Message, Mid, Response};
+
+/// MID 0245 - User data download with offset
+pub struct UserDataDownloadOffsetHandler {
+    state: ObservableState,
+}
+
+impl UserDataDownloadOffsetHandler {
+    pub fn new(state: ObservableState) -> Self {
+        Self { state }
+    }
+}
+
+/// Offset and user data of a MID 0245 request
+fn parse(data: &[u8]) -> Result<(u16, String), String> {
+    let mut parser = FieldParser::new(data);
+    let offset = parser
+        .read_int(Some(1), 3)
+        .map_err(|e| format!("invalid offset: {}", e))?;
+    let data = if parser.is_empty() {
+        String::new()
+    } else {
+        let width = parser.remaining().saturating_sub(2);
+        parser
+            .read_str(Some(2), width)
+            .map_err(|e| format!("invalid user data: {}", e))?
+    };
+    let offset = u16::try_from(offset).map_err(|_| "invalid offset".to_string())?;
+    Ok((offset, data))
+}
+
+impl MidHandler for UserDataDownloadOffsetHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let stored = parse(&message.data).and_then(|(slot, data)| {
+            self.state.set_user_data(slot, data)?;
+            Ok(slot)
+        });
+        match stored {
+            Ok(slot) => {
+                println!("MID 0245: User data stored in slot {}", slot);
+                Ok(Response::from_data(
+                    Mid::COMMAND_ACCEPTED,
+                    message.revision,
+                    CommandAccepted::with_mid(Mid::USER_DATA_DOWNLOAD_WITH_OFFSET),
+                )?)
+            }
+            Err(e) => {
+                println!("MID 0245: User data download rejected - {}", e);
+                Ok(
+                    ErrorResponse::new(Mid::USER_DATA_DOWNLOAD_WITH_OFFSET, ErrorCode::InvalidData)
+                        .into_response(message.revision),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DeviceState;
+
+    #[test]
+    fn test_user_data_download_with_offset() {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(DeviceState::new_shared(), tx);
+        let handler = UserDataDownloadOffsetHandler::new(state.clone());
+
+        let response = handler
+            .handle(&Message::new(245, 1, b"0101202FIXTURE 4".to_vec()))
+            .unwrap();
+        assert_eq!(response.mid, Mid::COMMAND_ACCEPTED.value());
+        assert_eq!(state.read().user_data.get(12), Some("FIXTURE 4"));
+
+        // Offset only clears the slot
+        handler
+            .handle(&Message::new(245, 1, b"01012".to_vec()))
+            .unwrap();
+        assert_eq!(state.read().user_data.get(12), None);
+
+        let response = handler
+            .handle(&Message::new(245, 1, b"01ABC02X".to_vec()))
+            .unwrap();
+        assert_eq!(response.data, b"024504");
+    }
+}
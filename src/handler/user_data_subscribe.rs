@@ -0,0 +1,42 @@
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0241 - User data upload subscribe
+/// Client requests subscription to user data uploads (MID 0242); every
+/// stored slot is uploaded right after the acknowledgment
+pub struct UserDataSubscribeHandler;
+
+impl MidHandler for UserDataSubscribeHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0241: User data upload subscribe request");
+
+        let ack_data = CommandAccepted::with_mid(Mid::USER_DATA_SUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_data_subscribe() {
+        let handler = UserDataSubscribeHandler;
+        let message = Message {
+            length: 20,
+            mid: 241,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert_eq!(response.data, b"0241");
+    }
+}
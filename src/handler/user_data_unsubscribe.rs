@@ -0,0 +1,41 @@
+use crate::handler::data::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler};
+use crate::protocol::{Message, Mid, Response};
+
+/// MID 0244 - User data upload unsubscribe
+/// Client cancels its subscription to user data uploads
+pub struct UserDataUnsubscribeHandler;
+
+impl MidHandler for UserDataUnsubscribeHandler {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        println!("MID 0244: User data upload unsubscribe request");
+
+        let ack_data = CommandAccepted::with_mid(Mid::USER_DATA_UNSUBSCRIBE);
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_data_unsubscribe() {
+        let handler = UserDataUnsubscribeHandler;
+        let message = Message {
+            length: 20,
+            mid: 244,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = handler.handle(&message).unwrap();
+        assert_eq!(response.mid, 5); // Command accepted
+        assert_eq!(response.data, b"0244");
+    }
+}
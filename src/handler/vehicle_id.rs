@@ -30,12 +30,13 @@ impl MidHandler for VehicleIdHandler {
         // Update device state
         {
             let mut state = self.state.write().unwrap();
+            state.touch();
             state.set_vehicle_id(vin);
         }
 
         let ack_data = CommandAccepted::with_mid(52);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(5, message.revision, ack_data)?)
     }
 }
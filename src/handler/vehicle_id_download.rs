@@ -1,7 +1,7 @@
 use crate::handler::data::command_accepted::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
 use crate::observable_state::ObservableState;
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0050 - Vehicle ID Number download
 /// Receives vehicle identification from integrator and responds with acknowledgment
@@ -15,23 +15,32 @@ impl VehicleIdDownloadHandler {
     }
 }
 
+/// VIN carried by a MID 0050 (`NO_VIN` when empty)
+pub fn requested_vin(message: &Message) -> String {
+    // Extract VIN from message data if present
+    if !message.data.is_empty() {
+        String::from_utf8_lossy(&message.data).trim().to_string()
+    } else {
+        "NO_VIN".to_string()
+    }
+}
+
 impl MidHandler for VehicleIdDownloadHandler {
     fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
-        // Extract VIN from message data if present
-        let vin = if !message.data.is_empty() {
-            String::from_utf8_lossy(&message.data).trim().to_string()
-        } else {
-            "NO_VIN".to_string()
-        };
+        let vin = requested_vin(message);
 
         println!("MID 0050: Vehicle ID download - VIN: {}", vin);
 
         // Update device state and broadcast event
         self.state.set_vehicle_id(vin);
 
-        let ack_data = CommandAccepted::with_mid(50);
+        let ack_data = CommandAccepted::with_mid(Mid::VEHICLE_ID_DOWNLOAD);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
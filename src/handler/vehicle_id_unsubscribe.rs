@@ -1,6 +1,6 @@
 use crate::handler::data::CommandAccepted;
 use crate::handler::{HandlerError, MidHandler};
-use crate::protocol::{Message, Response};
+use crate::protocol::{Message, Mid, Response};
 
 /// MID 0054 - Vehicle ID Number unsubscribe
 /// Responds with MID 0005 (Command accepted)
@@ -13,9 +13,13 @@ impl MidHandler for VehicleIdUnsubscribeHandler {
     fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
         println!("MID 0054: Vehicle ID unsubscribe request");
 
-        let ack_data = CommandAccepted::with_mid(54);
+        let ack_data = CommandAccepted::with_mid(Mid::VEHICLE_ID_UNSUBSCRIBE);
 
         // Respond with MID 0005 (Command accepted)
-        Ok(Response::from_data(5, message.revision, ack_data))
+        Ok(Response::from_data(
+            Mid::COMMAND_ACCEPTED,
+            message.revision,
+            ack_data,
+        )?)
     }
 }
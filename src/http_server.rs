@@ -1,39 +1,77 @@
+use crate::ack_policy::AckPolicy;
+use crate::alarm_generator;
+use crate::alarm_texts;
+use crate::alarms::{AckSource, AlarmError, AlarmSeverity, AlarmState};
+use crate::assertions::AssertionSpec;
+use crate::audit::AuditCategory;
+use crate::codec::frame_tap::{FrameDirection, TappedFrame};
 use crate::config::Settings;
-use crate::device_fsm::{DeviceFSM, DeviceFSMState, TighteningParams};
+use crate::context::AppContext;
+use crate::counters::Counters;
+use crate::curve::TighteningCurve;
+use crate::cycle_time::CycleTimeReport;
+use crate::device_fsm::{DeviceFSM, DeviceFSMState, NokReason, TighteningParams};
 use crate::events::SimulatorEvent;
-use crate::failure_simulator::FailureConfig;
+use crate::failure_simulator::{BandwidthLimit, FailureConfig};
+use crate::fieldbus::FieldbusInputsPatch;
+use crate::generator::{
+    BackfillConfig, FirehoseConfig, FirehoseStatus, ResultGenerator, generate_history,
+};
 use crate::handler::data::TighteningResult;
-use crate::multi_spindle::{MultiSpindleStatus, generate_multi_spindle_results};
+use crate::interlock::ReleaseSource;
+use crate::job;
+use crate::multi_spindle::{
+    MAX_SPINDLES_PER_MESSAGE, MultiSpindleConfig, MultiSpindleStatus, SPINDLE_PROGRESS_SAMPLES,
+    SpindlePhase, generate_multi_spindle_results, spindle_torque_sample,
+};
 use crate::observable_state::ObservableState;
+#[cfg(feature = "parquet")]
+use crate::parquet_export;
 use crate::pset::{self, SharedPsetRepository};
-use crate::state::DeviceState;
+use crate::pset_history::{PsetChangeKind, PsetChangeSource};
+use crate::pset_preview;
+use crate::resources::{ResourceReport, TaskGuard};
+use crate::results::{self, TighteningMetadata};
+use crate::session_registry::{CloseMode, DeliveryId, DeliveryReceipt, SessionId};
+use crate::state::{
+    FirmwareUpgrade, IdentityPatch, MaintenanceKind, MaintenanceNotice, StatePatch,
+};
+use crate::subscriptions::SubscriptionKind;
+use crate::tenants::{ControllerDefinition, TenantError, TenantRegistry};
+use crate::throttle::ThrottleRule;
+use crate::web_ui;
 use axum::{
     Router,
     extract::{
-        Path, State as AxumState, WebSocketUpgrade,
+        Path, Query, State as AxumState, WebSocketUpgrade,
         ws::{Message, WebSocket},
     },
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Json},
-    routing::{get, post},
+    routing::{any, delete, get, post, put},
 };
 use futures_util::{SinkExt, StreamExt};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::Duration;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
 /// Shared state for HTTP server
 #[derive(Clone)]
 pub struct ServerState {
-    pub observable_state: ObservableState,
+    pub ctx: AppContext,
     pub auto_tightening_active: Arc<AtomicBool>,
-    pub pset_repository: SharedPsetRepository,
-    pub settings: Settings,
+    pub firehose: Arc<FirehoseStatus>,
 }
 
 /// Get TighteningParams from selected PSET, or default if no PSET selected
+/// Tightening parameters of the selected PSET
+///
+/// The duration is sampled from the PSET's cycle time model, or is
+/// `duration_ms` for PSETs without one.
 fn get_tightening_params(
     pset_id: Option<u32>,
     pset_repo: &SharedPsetRepository,
@@ -42,17 +80,10 @@ fn get_tightening_params(
     if let Some(id) = pset_id {
         let repo = pset_repo.read().unwrap();
         if let Some(pset) = repo.get_by_id(id) {
-            let target_torque = (pset.torque_min + pset.torque_max) / 2.0;
-            let target_angle = (pset.angle_min + pset.angle_max) / 2.0;
-            return TighteningParams {
-                target_torque,
-                torque_min: pset.torque_min,
-                torque_max: pset.torque_max,
-                target_angle,
-                angle_min: pset.angle_min,
-                angle_max: pset.angle_max,
-                duration_ms,
-            };
+            let duration_ms = pset.cycle_time.map_or(duration_ms, |cycle_time| {
+                cycle_time.sample(&mut rand::rng())
+            });
+            return TighteningParams::for_pset(&pset, duration_ms);
         }
     }
 
@@ -60,144 +91,498 @@ fn get_tightening_params(
     TighteningParams::default_test()
 }
 
-/// Helper function to build a TighteningResult from device state and tightening info
-#[allow(clippy::too_many_arguments)]
-fn build_tightening_result(
-    state: &DeviceState,
-    info: &crate::batch_manager::TighteningInfo,
-    torque: f64,
-    angle: f64,
-    tightening_ok: bool,
-    torque_ok: bool,
-    angle_ok: bool,
-    params: &TighteningParams,
-) -> TighteningResult {
-    let batch_status = match info.batch_status {
-        crate::batch_manager::BatchStatus::NotFinished => None,
-        crate::batch_manager::BatchStatus::CompletedOk => Some(true),
-        crate::batch_manager::BatchStatus::CompletedNok => Some(false),
-        crate::batch_manager::BatchStatus::NotUsed => None,
-    };
+/// Raise the service alarm (and lock the tool) once `[maintenance] service_interval` is reached
+fn enforce_tool_service(ctx: &AppContext) {
+    let maintenance = &ctx.settings.maintenance;
+    ctx.observable_state
+        .enforce_service_interval(maintenance.service_interval, maintenance.service_lockout);
+}
+
+/// Spend a multi-spindle cycle's duration reporting per-spindle torque samples
+///
+/// Every spindle ramps towards its nominal torque in [`SPINDLE_PROGRESS_SAMPLES`]
+/// steps; the final sample comes with the result.
+async fn run_down_spindles(
+    observable_state: &ObservableState,
+    config: &MultiSpindleConfig,
+    duration: Duration,
+) {
+    let interval = duration / SPINDLE_PROGRESS_SAMPLES;
+    for step in 1..SPINDLE_PROGRESS_SAMPLES {
+        tokio::time::sleep(interval).await;
+        for spindle_id in 1..=config.spindle_count {
+            observable_state.broadcast(SimulatorEvent::SpindleProgress {
+                sync_id: config.sync_id,
+                spindle_id,
+                phase: SpindlePhase::Running,
+                torque: spindle_torque_sample(spindle_id, step, SPINDLE_PROGRESS_SAMPLES).as_nm(),
+                ok: None,
+            });
+        }
+    }
+    tokio::time::sleep(duration.saturating_sub(interval * (SPINDLE_PROGRESS_SAMPLES - 1))).await;
+}
+
+/// Hold the next position of an `[interlock] jobs` job after an OK tightening
+fn enforce_interlock(ctx: &AppContext, ok: bool) {
+    ctx.observable_state
+        .hold_for_interlock(&ctx.settings.interlock.jobs, ok);
+}
+
+/// Apply the selected PSET's NOK retry limit after a tightening was recorded
+fn enforce_pset_retry_limit(observable_state: &ObservableState, pset_repo: &SharedPsetRepository) {
+    let pset_id = observable_state.read().current_pset_id;
+    let policy = pset_id
+        .and_then(|id| pset_repo.read().unwrap().get_by_id(id))
+        .and_then(|pset| pset.max_retries.map(|max| (max, pset.retry_action)));
+
+    if let Some((max_retries, action)) = policy {
+        observable_state.enforce_retry_limit(max_retries, action);
+    }
+}
 
-    TighteningResult {
-        cell_id: state.cell_id,
-        channel_id: state.channel_id,
-        controller_name: state.controller_name.clone(),
-        vin_number: state.vehicle_id.clone(),
-        job_id: state.current_job_id.unwrap_or(1),
-        pset_id: state.current_pset_id.unwrap_or(1),
-        batch_size: state.tightening_tracker.batch_size(),
-        batch_counter: info.counter,
-        tightening_status: tightening_ok,
-        torque_status: torque_ok,
-        angle_status: angle_ok,
-        torque_min: params.torque_min,
-        torque_max: params.torque_max,
-        torque_target: params.target_torque,
-        torque,
-        angle_min: params.angle_min,
-        angle_max: params.angle_max,
-        angle_target: params.target_angle,
-        angle,
-        timestamp: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
-        last_pset_change: None,
-        batch_status,
-        tightening_id: Some(info.tightening_id),
+/// Store a tightening in the results history and broadcast it to all clients
+///
+/// The logged-in operator is attached to the metadata unless the caller set one,
+/// and the result is signed with `[results] signing_key` if configured.
+/// Pass a delivery ID to collect delivery receipts for the broadcast.
+fn record_tightening(
+    ctx: &AppContext,
+    mut result: TighteningResult,
+    curve: Option<TighteningCurve>,
+    delivery: Option<DeliveryId>,
+) {
+    let observable_state = &ctx.observable_state;
+    if result.metadata.operator_id.is_none() {
+        result.metadata.operator_id = observable_state
+            .read()
+            .operator
+            .as_ref()
+            .map(|operator| operator.operator_id.clone());
     }
+    {
+        let mut state = observable_state.write();
+        if result.cycle_id == 0 {
+            result.cycle_id = state.claim_cycle();
+        }
+        state.fieldbus.record_result(result.tightening_status);
+    }
+    if let Some(key) = &ctx.settings.results.signing_key {
+        results::sign(&mut result, key);
+    }
+    {
+        let mut repo = ctx.result_repository.write().unwrap();
+        repo.add(result.clone());
+        if let Some(curve) = curve {
+            repo.add_curve(curve);
+        }
+    }
+    observable_state.broadcast(SimulatorEvent::TighteningCompleted { result, delivery });
+}
+
+/// Torque/angle curve of an FSM tightening, unless curve recording is disabled
+fn tightening_curve(
+    settings: &Settings,
+    result: &TighteningResult,
+    duration_ms: u64,
+) -> Option<TighteningCurve> {
+    let points = settings.results.curve_points;
+    (points > 0).then(|| TighteningCurve::generate(result, duration_ms, points))
+}
+
+/// Whether tightenings are refused because login is required and nobody is logged in
+fn operator_login_missing(observable_state: &ObservableState, settings: &Settings) -> bool {
+    settings.defaults.require_operator_login && observable_state.read().operator.is_none()
 }
 
 /// Create the HTTP router with all endpoints configured
-pub fn create_router(observable_state: ObservableState, settings: Settings) -> Router {
-    let db_path = settings.database.path.to_str().unwrap_or_else(|| {
-        eprintln!(
-            "Warning: Database path {:?} is not valid UTF-8, falling back to 'simulator.db'",
-            settings.database.path
-        );
-        "simulator.db"
-    });
-    let pset_repository = crate::pset::create_sqlite_repository(db_path).unwrap_or_else(|e| {
-        eprintln!(
-            "Failed to create SQLite repository: {}. Falling back to in-memory.",
-            e
-        );
-        crate::pset::create_default_repository()
-    });
+///
+/// Tenants created through this router are only reachable over HTTP; use
+/// [`create_router_with_tenants`] to serve their Open Protocol ports too.
+pub fn create_router(ctx: AppContext) -> Router {
+    let tenants = Arc::new(TenantRegistry::new(ctx.settings.as_ref().clone()));
+    create_router_with_tenants(ctx, tenants)
+}
+
+/// Create the HTTP router, serving the APIs of `tenants` under `/tenants/{name}`
+pub fn create_router_with_tenants(ctx: AppContext, tenants: Arc<TenantRegistry>) -> Router {
+    let cors = cors_layer(&ctx.settings.server.cors_allowed_origins);
+    let base_path = ctx.settings.server.base_path();
+    let ui_base = base_path.clone().unwrap_or_default();
+
+    let tenant_state = TenantState {
+        registry: tenants,
+        routers: Default::default(),
+        ui_base: ui_base.clone(),
+    };
+    let router = api_router(ctx, ui_base).merge(
+        Router::new()
+            .route("/tenants", get(list_tenants).post(create_tenant))
+            .route("/tenants/{name}", get(get_tenant))
+            .route("/tenants/{name}/", any(tenant_index))
+            .route("/tenants/{name}/{*rest}", any(tenant_api))
+            .route("/controllers/bulk", post(create_controllers))
+            .route("/controllers/{id}", delete(delete_controller))
+            .with_state(tenant_state),
+    );
+
+    match base_path {
+        Some(base_path) => Router::new().nest(&base_path, router).layer(cors),
+        None => router.layer(cors),
+    }
+}
 
+/// Routes of one simulated controller, with the web UI served below `ui_base`
+fn api_router(ctx: AppContext, ui_base: String) -> Router {
     let server_state = ServerState {
-        observable_state,
+        ctx,
         auto_tightening_active: Arc::new(AtomicBool::new(false)),
-        pset_repository,
-        settings,
+        firehose: Arc::new(FirehoseStatus::new()),
     };
 
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
     Router::new()
-        .route("/state", get(get_state))
+        .route("/", get(move || web_ui::index(ui_base)))
+        .route("/ui/{*path}", get(web_ui::asset))
+        .route("/state", get(get_state).patch(patch_state))
+        .route("/identity", get(get_identity).patch(patch_identity))
         .route("/simulate/tightening", post(simulate_tightening))
+        .route(
+            "/simulate/firmware-upgrade",
+            post(simulate_firmware_upgrade),
+        )
         .route("/auto-tightening/start", post(start_auto_tightening))
         .route("/auto-tightening/stop", post(stop_auto_tightening))
         .route("/auto-tightening/status", get(get_auto_tightening_status))
+        .route("/firehose/start", post(start_firehose))
+        .route("/firehose/stop", post(stop_firehose))
+        .route("/firehose/status", get(get_firehose_status))
         .route("/config/multi-spindle", post(configure_multi_spindle))
         .route(
             "/config/failure",
             get(get_failure_config).post(update_failure_config),
         )
         .route("/psets", get(get_psets).post(create_pset))
+        .route("/psets/import", post(import_psets))
         .route(
             "/psets/{id}",
             get(get_pset_by_id).put(update_pset).delete(delete_pset),
         )
         .route("/psets/{id}/select", post(select_pset))
+        .route("/psets/{id}/history", get(get_pset_history))
+        .route("/psets/{id}/simulate", get(simulate_pset))
+        .route("/jobs", get(get_jobs).post(create_job))
+        .route(
+            "/jobs/{id}",
+            get(get_job_by_id).put(update_job).delete(delete_job),
+        )
+        .route("/results", get(get_results))
+        .route("/results/{id}", get(get_result_by_id))
+        .route("/results/{id}/curve", get(get_result_curve))
+        .route("/results/{id}/void", post(void_result))
+        .route("/results/export", get(export_results))
+        .route("/results/generate", post(generate_results))
+        .route("/audit", get(get_audit_log))
+        .route("/vehicle-ids/history", get(get_vin_history))
+        .route("/counters", get(get_counters).put(set_counters))
+        .route("/tool/service-reset", post(reset_tool_service))
+        .route("/stats/lifetime", get(get_lifetime_stats))
+        .route("/stats/cycle-time", get(get_cycle_time_stats))
+        .route("/stats/handlers", get(get_handler_stats))
+        .route("/alarms", get(get_alarms).post(raise_alarm))
+        .route("/alarms/texts", get(get_alarm_texts))
+        .route("/alarms/{id}/ack", post(acknowledge_alarm))
+        .route("/alarms/{id}/clear", post(clear_alarm))
+        .route("/operator", get(get_operator))
+        .route("/operator/login", post(login_operator))
+        .route("/operator/logout", post(logout_operator))
+        .route("/connections", get(get_connections))
+        .route("/connections/{id}/close", post(close_connection))
+        .route("/connections/{id}/observer", put(set_connection_observer))
+        .route("/schedules", get(get_schedules))
+        .route("/schedules/{id}", delete(cancel_schedule))
+        .route("/io/fieldbus", get(get_fieldbus).put(set_fieldbus_inputs))
+        .route("/interlock", get(get_interlock))
+        .route("/interlock/release", post(release_interlock))
+        .route("/user-data", get(get_user_data))
+        .route(
+            "/user-data/{slot}",
+            put(set_user_data).delete(clear_user_data),
+        )
+        .route("/throttles", get(get_throttles))
+        .route(
+            "/throttles/{kind}",
+            put(set_throttle).delete(remove_throttle),
+        )
+        .route("/ack-policies", get(get_ack_policies))
+        .route(
+            "/ack-policies/{kind}",
+            put(set_ack_policy).delete(remove_ack_policy),
+        )
+        .route("/assertions", get(list_assertions).post(create_assertion))
+        .route(
+            "/assertions/{id}",
+            get(get_assertion).delete(remove_assertion),
+        )
+        .route("/proxy/exchanges", get(get_proxy_exchanges))
+        .route("/debug/injection-log", get(get_injection_log))
+        .route("/debug/resources", get(get_resources))
+        .route("/integrations/outbox", get(get_outbox))
+        .route("/events/next", get(next_event))
         .route("/ws/events", get(websocket_handler))
-        .layer(cors)
+        .route("/ws/protocol", get(protocol_tap_handler))
         .with_state(server_state)
 }
 
+/// Build the CORS policy: any origin when the list is empty, otherwise only
+/// the configured origins (invalid entries are skipped with a warning)
+fn cors_layer(allowed_origins: &[String]) -> CorsLayer {
+    let cors = CorsLayer::new().allow_methods(Any).allow_headers(Any);
+    if allowed_origins.is_empty() {
+        return cors.allow_origin(Any);
+    }
+
+    let origins: Vec<header::HeaderValue> = allowed_origins
+        .iter()
+        .filter_map(|origin| match origin.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                eprintln!("Warning: ignoring invalid CORS origin {:?}", origin);
+                None
+            }
+        })
+        .collect();
+    cors.allow_origin(AllowOrigin::list(origins))
+}
+
 /// Start the HTTP server for state inspection and simulation control
-pub async fn start_http_server(observable_state: ObservableState, settings: Settings) {
+pub async fn start_http_server(ctx: AppContext, tenants: Arc<TenantRegistry>) {
     let bind_addr = format!(
         "{}:{}",
-        settings.server.bind_address, settings.server.http_port
+        ctx.settings.server.bind_address, ctx.settings.server.http_port
     );
-    let app = create_router(observable_state, settings);
+    let base_path = ctx.settings.server.base_path().unwrap_or_default();
+    let app = create_router_with_tenants(ctx, tenants);
 
     let listener = tokio::net::TcpListener::bind(&bind_addr)
         .await
         .unwrap_or_else(|_| panic!("Failed to bind HTTP server to {}", bind_addr));
 
-    println!("HTTP state server listening on http://{}", bind_addr);
-    println!("Endpoints:");
+    println!(
+        "HTTP state server listening on http://{}{}",
+        bind_addr, base_path
+    );
+    println!("Endpoints (relative to the listening URL):");
+    println!("  GET    /                          - Embedded web UI");
     println!("  GET    /state                     - View device state");
+    println!("  PATCH  /state                     - Change identity, tool, VIN or job fields");
+    println!("  GET    /identity                  - Controller identity (MID 0002/0061)");
+    println!("  PATCH  /identity                  - Change cell, channel, name or supplier");
     println!("  POST   /simulate/tightening       - Simulate a single tightening operation");
+    println!("  POST   /simulate/firmware-upgrade - Reboot with a new firmware version");
     println!(
         "  POST   /auto-tightening/start     - Start automated tightening simulation (continuous)"
     );
     println!("  POST   /auto-tightening/stop      - Stop automated tightening simulation");
     println!("  GET    /auto-tightening/status    - Get auto-tightening status");
+    println!("  POST   /firehose/start            - Start high-rate synthetic result generation");
+    println!("  POST   /firehose/stop             - Stop the firehose");
+    println!("  GET    /firehose/status           - Get firehose status");
     println!("  POST   /config/multi-spindle      - Configure multi-spindle mode");
     println!("  GET    /config/failure            - Get failure injection configuration");
     println!("  POST   /config/failure            - Update failure injection configuration");
     println!("  GET    /psets                     - Get all PSETs");
     println!("  POST   /psets                     - Create a new PSET");
+    println!(
+        "  POST   /psets/import              - Import PSETs under their own IDs (remapping collisions)"
+    );
     println!("  GET    /psets/{{id}}                - Get a specific PSET by ID");
     println!("  PUT    /psets/{{id}}                - Update a PSET");
     println!("  DELETE /psets/{{id}}                - Delete a PSET");
     println!("  POST   /psets/{{id}}/select         - Select a PSET as active");
+    println!("  GET    /psets/{{id}}/history        - Creations, updates and selections of a PSET");
+    println!(
+        "  GET    /psets/{{id}}/simulate       - Dry-run a PSET: torque/angle distribution and NOK rate"
+    );
+    println!("  GET    /jobs                      - Get all jobs");
+    println!("  POST   /jobs                      - Create a job (PSET sequence with batch sizes)");
+    println!("  GET    /jobs/{{id}}                 - Get a specific job by ID");
+    println!("  PUT    /jobs/{{id}}                 - Update a job");
+    println!("  DELETE /jobs/{{id}}                 - Delete a job");
+    println!("  GET    /results                   - List recent tightening results");
+    println!("  GET    /results/:id               - Get a result with its stage breakdown");
+    println!("  GET    /results/:id/curve         - Torque/angle curve of a result (json or csv)");
+    println!("  POST   /results/:id/void          - Void a result and resend the correction");
+    println!("  GET    /results/export            - Export results history (json, csv or parquet)");
+    println!("  POST   /results/generate          - Backfill a historical results dataset");
+    println!("  GET    /audit                     - Controller audit log");
+    println!("  GET    /vehicle-ids/history       - VINs and jobs by job sequence number");
+    println!("  GET    /counters                  - Next tightening ID and job sequence number");
+    println!("  PUT    /counters                  - Set the persisted sequence counters");
+    println!(
+        "  POST   /tool/service-reset        - Record a tool service and lift the service lock"
+    );
+    println!("  GET    /stats/lifetime            - Lifetime tightening/runtime/energy counters");
+    println!("  GET    /stats/cycle-time          - Cycle time, takt and idle ratio per PSET/job");
+    println!(
+        "  GET    /stats/handlers            - Per-MID handler latency percentiles and errors"
+    );
+    println!("  GET    /alarms                    - List alarms (filter by state/severity)");
+    println!("  POST   /alarms                    - Raise an alarm");
+    println!("  GET    /alarms/texts              - Localized alarm texts and current language");
+    println!("  POST   /alarms/{{id}}/ack           - Acknowledge an alarm on the controller");
+    println!("  POST   /alarms/{{id}}/clear         - Clear an alarm");
+    println!("  GET    /operator                  - Get logged-in operator");
+    println!("  POST   /operator/login            - Log an operator in");
+    println!("  POST   /operator/logout           - Log the operator out");
+    println!("  GET    /connections               - Connected TCP clients and their subscriptions");
+    println!(
+        "  POST   /connections/{{id}}/close    - Disconnect a client (graceful, abort, half_close)"
+    );
+    println!(
+        "  PUT    /connections/{{id}}/observer - Switch a client to or from read-only observer mode"
+    );
+    println!("  GET    /schedules                 - Chaos schedule rules and their next run");
+    println!("  DELETE /schedules/{{id}}            - Cancel a chaos schedule rule");
+    println!("  GET    /io/fieldbus               - Virtual PLC I/O image (inputs and outputs)");
+    println!("  PUT    /io/fieldbus               - Write PLC inputs (start, reset, PSET bits)");
+    println!("  GET    /interlock                 - Interlocked jobs and the position waiting");
+    println!(
+        "  POST   /interlock/release         - Release the next position of an interlocked job"
+    );
+    println!("  GET    /user-data                 - User data slots stored on the controller");
+    println!(
+        "  PUT    /user-data/{{slot}}          - Write a user data slot (MID 0242 to subscribers)"
+    );
+    println!("  DELETE /user-data/{{slot}}          - Clear a user data slot");
+    println!("  GET    /throttles                 - Broadcast rate limits per subscription");
+    println!("  PUT    /throttles/{{kind}}          - Limit pushes per second per client");
+    println!("  DELETE /throttles/{{kind}}          - Remove a broadcast rate limit");
+    println!("  GET    /ack-policies              - Retransmission of unacked pushes");
+    println!("  PUT    /ack-policies/{{kind}}       - Resend timeout, retries and give-up action");
+    println!("  DELETE /ack-policies/{{kind}}       - Make a subscription fire-and-forget again");
+    println!("  GET    /assertions                - Registered protocol assertions");
+    println!("  POST   /assertions                - Expect e.g. acks within a deadline");
+    println!("  GET    /assertions/{{id}}           - Pass/fail of an assertion with evidence");
+    println!("  DELETE /assertions/{{id}}           - Forget an assertion");
+    println!("  GET    /proxy/exchanges           - Frames recorded in gateway mode");
+    println!(
+        "  GET    /debug/injection-log       - Frames delayed or discarded by failure injection"
+    );
+    println!("  GET    /debug/resources           - Task, buffer and queue accounting");
+    println!("  GET    /events/next               - Wait for the next (matching) event");
     println!("  GET    /ws/events                 - WebSocket event stream");
+    println!("  GET    /ws/protocol               - Raw frames of a TCP connection (binary)");
+    println!("  GET    /tenants                   - List simulation namespaces");
+    println!("  POST   /tenants                   - Create a namespace with its own controller");
+    println!("  *      /tenants/{{name}}/...        - Any endpoint above, for one namespace");
+    println!(
+        "  POST   /controllers/bulk          - Provision many controllers (namespaces) at once"
+    );
+    println!("  DELETE /controllers/{{id}}          - Remove a controller and close its port");
 
     axum::serve(listener, app)
         .await
         .expect("HTTP server failed");
 }
 
+#[derive(Deserialize)]
+struct StateQuery {
+    /// Only return the state if its version is newer than this
+    if_version_gt: Option<u64>,
+}
+
 /// Handler for GET /state endpoint
-async fn get_state(AxumState(server_state): AxumState<ServerState>) -> Json<DeviceState> {
-    let state = server_state.observable_state.read();
-    Json(state.clone())
+///
+/// Every state mutation bumps `version`, which is also sent as the ETag.
+/// Pollers that already have the current version (`?if_version_gt=N` or
+/// `If-None-Match`) get an empty `304 Not Modified` instead of the full state.
+async fn get_state(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<StateQuery>,
+    headers: HeaderMap,
+) -> axum::response::Response {
+    let state = server_state.ctx.observable_state.read();
+    let etag = format!("\"{}\"", state.version);
+    let unchanged = query
+        .if_version_gt
+        .is_some_and(|version| state.version <= version)
+        || headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|tags| {
+                tags.split(',')
+                    .map(|tag| tag.trim().trim_start_matches("W/"))
+                    .any(|tag| tag == etag || tag == "*")
+            });
+    if unchanged {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    ([(header::ETAG, etag)], Json(&*state)).into_response()
+}
+
+/// Handler for PATCH /state endpoint
+///
+/// Accepts a JSON merge patch of the writable fields (see [`StatePatch`]);
+/// anything else, including unknown or read-only fields, is rejected.
+async fn patch_state(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let result = serde_json::from_value::<StatePatch>(body)
+        .map_err(|e| e.to_string())
+        .and_then(|patch| server_state.ctx.observable_state.apply_patch(patch));
+    match result {
+        Ok(changed) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "changed": changed,
+                "version": server_state.ctx.observable_state.read().version
+            })),
+        ),
+        Err(error) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": error
+            })),
+        ),
+    }
+}
+
+/// Handler for GET /identity endpoint
+async fn get_identity(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    Json(server_state.ctx.observable_state.read().identity())
+}
+
+/// Handler for PATCH /identity endpoint
+///
+/// Like `PATCH /state` restricted to the identity fields.
+async fn patch_identity(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let observable_state = &server_state.ctx.observable_state;
+    let result = serde_json::from_value::<IdentityPatch>(body)
+        .map_err(|e| e.to_string())
+        .and_then(|patch| observable_state.apply_patch(patch.into()));
+    match result {
+        Ok(changed) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "changed": changed,
+                "identity": observable_state.read().identity()
+            })),
+        ),
+        Err(error) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": error
+            })),
+        ),
+    }
 }
 
 #[derive(Deserialize)]
@@ -208,14 +593,23 @@ struct TighteningRequest {
     angle: Option<f64>,
     /// Optional OK/NOK override (None = FSM decides, Some(true) = Force OK, Some(false) = Force NOK)
     ok: Option<bool>,
+    /// Optional metadata (operator, station, custom key/values) attached to the result
+    #[serde(default)]
+    metadata: TighteningMetadata,
 }
 
+/// How long a simulate request waits for connections to report delivery
+const DELIVERY_RECEIPT_TIMEOUT: Duration = Duration::from_secs(2);
+
 #[derive(Serialize)]
 struct TighteningResponse {
     success: bool,
     message: String,
     batch_counter: u32,
+    /// Clients subscribed to tightening results when the result was broadcast
     subscribers: usize,
+    /// Per-client delivery status of the broadcast
+    deliveries: Vec<DeliveryReceipt>,
 }
 
 /// Handler for POST /simulate/tightening endpoint
@@ -225,7 +619,7 @@ async fn simulate_tightening(
     Json(payload): Json<TighteningRequest>,
 ) -> impl IntoResponse {
     let tool_enabled = {
-        let state = server_state.observable_state.read();
+        let state = server_state.ctx.observable_state.read();
         state.tool_enabled
     };
 
@@ -237,6 +631,23 @@ async fn simulate_tightening(
                 message: "Cannot simulate tightening: tool is disabled".to_string(),
                 batch_counter: 0,
                 subscribers: 0,
+                deliveries: Vec::new(),
+            }),
+        );
+    }
+
+    if operator_login_missing(
+        &server_state.ctx.observable_state,
+        &server_state.ctx.settings,
+    ) {
+        return (
+            StatusCode::CONFLICT,
+            Json(TighteningResponse {
+                success: false,
+                message: "Cannot simulate tightening: no operator logged in".to_string(),
+                batch_counter: 0,
+                subscribers: 0,
+                deliveries: Vec::new(),
             }),
         );
     }
@@ -249,6 +660,22 @@ async fn simulate_tightening(
                 "Manual tightening override: Torque={:.1} Nm, Angle={:.1}°",
                 torque, angle
             );
+            // Keep the selected PSET's strategy so MID 0061 reports it
+            let strategy = server_state
+                .ctx
+                .observable_state
+                .read()
+                .current_pset_id
+                .and_then(|id| {
+                    server_state
+                        .ctx
+                        .pset_repository
+                        .read()
+                        .unwrap()
+                        .get_by_id(id)
+                })
+                .map(|pset| pset.strategy)
+                .unwrap_or_default();
             TighteningParams {
                 target_torque: torque,
                 torque_min: torque,
@@ -257,14 +684,16 @@ async fn simulate_tightening(
                 angle_min: angle,
                 angle_max: angle,
                 duration_ms: 500,
+                strategy,
+                stages: Vec::new(),
             }
         }
         _ => {
             // Use PSET values
-            let state = server_state.observable_state.read();
+            let state = server_state.ctx.observable_state.read();
             get_tightening_params(
                 state.current_pset_id,
-                &server_state.pset_repository,
+                &server_state.ctx.pset_repository,
                 500, // duration_ms for simulation
             )
         }
@@ -284,20 +713,25 @@ async fn simulate_tightening(
     let fsm = DeviceFSM::new();
     let fsm = fsm.start_tightening(params.clone());
     tokio::time::sleep(Duration::from_millis(10)).await; // Brief simulation
-    let fsm = fsm.complete();
-    let fsm_outcome = fsm.result();
+    let mut fsm = fsm.complete();
 
-    // Apply manual OK/NOK override if provided, otherwise use FSM result
-    let final_ok = if let Some(force_ok) = payload.ok {
+    // Apply manual OK/NOK override if provided, moving torque/angle so they
+    // agree with the forced status; otherwise use FSM result
+    if let Some(force_ok) = payload.ok {
         println!(
             "Forcing result: {} (FSM determined: {})",
             if force_ok { "OK" } else { "NOK" },
-            if fsm_outcome.ok { "OK" } else { "NOK" }
+            if fsm.result().ok { "OK" } else { "NOK" }
         );
-        force_ok
-    } else {
-        fsm_outcome.ok
-    };
+        if force_ok {
+            fsm.result_mut().force_ok(&params);
+        } else if fsm.result().ok {
+            let reason = NokReason::choose(&params, &mut rand::rng());
+            fsm.result_mut().force_nok(&params, reason);
+        }
+    }
+    let fsm_outcome = fsm.result();
+    let final_ok = fsm_outcome.ok;
 
     println!(
         "Result: Torque={:.2} Nm ({}), Angle={:.1}° ({}), Overall: {}",
@@ -309,75 +743,78 @@ async fn simulate_tightening(
     );
 
     let (result, batch_counter, batch_completed) = {
-        let mut state = server_state.observable_state.write();
+        let mut state = server_state.ctx.observable_state.write();
 
         // Add tightening to tracker
         let info = state.tightening_tracker.add_tightening(final_ok);
 
         // Build tightening result from device state
-        let result = build_tightening_result(
-            &state,
-            &info,
-            fsm_outcome.actual_torque,
-            fsm_outcome.actual_angle,
-            final_ok,
-            fsm_outcome.torque_ok,
-            fsm_outcome.angle_ok,
-            &params,
+        let result = TighteningResult::builder()
+            .device(&state)
+            .tightening(&info)
+            .params(&params)
+            .torque(fsm_outcome.actual_torque)
+            .angle(fsm_outcome.actual_angle)
+            .status(final_ok, fsm_outcome.torque_ok, fsm_outcome.angle_ok)
+            .stages(fsm_outcome.stages.clone())
+            .metadata(payload.metadata)
+            .build();
+        state.record_tool_cycle(
+            result.tightening_status,
+            result.torque,
+            result.angle,
+            params.duration_ms,
         );
 
         let batch_completed = state.tightening_tracker.is_complete();
 
-        // Note: Batch is NOT auto-reset here - integrator must send new batch config (MID 0019)
+        // Note: Batch is NOT reset here - it restarts on the next tightening when
+        // auto_reset_batch is enabled, otherwise the integrator must send MID 0019
 
         (result, info.counter, batch_completed)
     };
 
-    // Broadcast the tightening event to all TCP clients
-    let event = SimulatorEvent::TighteningCompleted { result };
-    server_state.observable_state.broadcast(event);
+    // Record in history and broadcast the tightening event to all TCP clients
+    let sessions = &server_state.ctx.sessions;
+    let ticket = sessions.begin_delivery(SubscriptionKind::TighteningResult);
+    let curve = tightening_curve(&server_state.ctx.settings, &result, params.duration_ms);
+    record_tightening(&server_state.ctx, result, curve, Some(ticket.id));
 
     // If batch completed, emit batch completion event
     if batch_completed {
-        let batch_event = SimulatorEvent::BatchCompleted {
-            total: batch_counter,
-        };
-        server_state.observable_state.broadcast(batch_event);
+        server_state
+            .ctx
+            .observable_state
+            .complete_batch(batch_counter);
         println!("Batch completed with {} tightenings", batch_counter);
     }
 
-    let subscribers = 0; // WebSocket subscribers (not tracked in current API)
-    let tightening_result: Result<(), String> = Ok(());
+    enforce_pset_retry_limit(
+        &server_state.ctx.observable_state,
+        &server_state.ctx.pset_repository,
+    );
+    enforce_tool_service(&server_state.ctx);
+    enforce_interlock(&server_state.ctx, final_ok);
 
-    match tightening_result {
-        Ok(_) => {
-            println!("Tightening event broadcast to {} subscribers", subscribers);
-            (
-                StatusCode::OK,
-                Json(TighteningResponse {
-                    success: true,
-                    message: format!(
-                        "Tightening result broadcast to {} TCP client(s)",
-                        subscribers
-                    ),
-                    batch_counter,
-                    subscribers,
-                }),
-            )
-        }
-        Err(e) => {
-            eprintln!("Failed to broadcast event: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(TighteningResponse {
-                    success: false,
-                    message: "Failed to broadcast tightening event".to_string(),
-                    batch_counter,
-                    subscribers: 0,
-                }),
-            )
-        }
-    }
+    let deliveries = sessions
+        .wait_for_delivery(ticket, DELIVERY_RECEIPT_TIMEOUT)
+        .await;
+    let subscribers = deliveries.len();
+    println!("Tightening event broadcast to {} subscribers", subscribers);
+
+    (
+        StatusCode::OK,
+        Json(TighteningResponse {
+            success: true,
+            message: format!(
+                "Tightening result broadcast to {} TCP client(s)",
+                subscribers
+            ),
+            batch_counter,
+            subscribers,
+            deliveries,
+        }),
+    )
 }
 
 // ============================================================================
@@ -392,6 +829,11 @@ struct AutoTighteningRequest {
     duration_ms: Option<u64>,
     /// Probability of failure (0.0 = never fail, 1.0 = always fail, uses config default if not specified)
     failure_rate: Option<f64>,
+    /// Metadata attached to every result produced by this run
+    #[serde(default)]
+    metadata: TighteningMetadata,
+    /// Random alarms per minute during this run (uses `[alarms]` config if not specified)
+    random_alarms_per_minute: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -421,36 +863,79 @@ async fn start_auto_tightening(
         );
     }
 
+    if operator_login_missing(
+        &server_state.ctx.observable_state,
+        &server_state.ctx.settings,
+    ) {
+        return (
+            StatusCode::CONFLICT,
+            Json(AutoTighteningResponse {
+                success: false,
+                message: "Cannot start auto-tightening: no operator logged in".to_string(),
+                duration_ms: 0,
+                interval_ms: 0,
+            }),
+        );
+    }
+
     // Use request values or fall back to configuration defaults
-    let defaults = &server_state.settings.defaults;
+    let defaults = &server_state.ctx.settings.defaults;
     let interval_ms = payload
         .interval_ms
         .unwrap_or(defaults.auto_tightening_interval_ms);
-    let duration_ms = payload
-        .duration_ms
-        .unwrap_or(defaults.auto_tightening_duration_ms);
+    // An explicit duration overrides the PSETs' cycle time models
+    let duration_override = payload.duration_ms;
+    let duration_ms = duration_override.unwrap_or(defaults.auto_tightening_duration_ms);
     let failure_rate = payload
         .failure_rate
         .unwrap_or(defaults.failure_rate)
         .clamp(0.0, 1.0);
 
-    // Clone observable state for background task
-    let observable_state = server_state.observable_state.clone();
-    let auto_active = Arc::clone(&server_state.auto_tightening_active);
-    let pset_repository = Arc::clone(&server_state.pset_repository);
+    // Clone shared context for background task
+    let ctx = server_state.ctx.clone();
+    let metadata = payload.metadata;
 
     // Set active flag
-    auto_active.store(true, Ordering::Relaxed);
+    server_state
+        .auto_tightening_active
+        .store(true, Ordering::Relaxed);
+    // The task only holds a weak reference, so dropping the HTTP state (an
+    // embedding application or a test tearing down its router) stops it
+    let auto_active = Arc::downgrade(&server_state.auto_tightening_active);
+    let is_active = move || {
+        auto_active
+            .upgrade()
+            .is_some_and(|active| active.load(Ordering::Relaxed))
+    };
+
+    let mut alarms_config = ctx.settings.alarms.clone();
+    if let Some(rate) = payload.random_alarms_per_minute {
+        alarms_config.random_per_minute = rate;
+    }
+    alarm_generator::spawn_alarm_noise(
+        ctx.observable_state.clone(),
+        alarms_config,
+        Arc::downgrade(&server_state.auto_tightening_active),
+    );
 
     // Spawn background task
+    let task = ctx.resources.track("auto_tightening");
+    let auto_active = Arc::downgrade(&server_state.auto_tightening_active);
     tokio::spawn(async move {
+        let _task = task;
         println!("Starting automated tightening (continuous mode)");
 
         let mut cycle = 0u64;
-        while auto_active.load(Ordering::Relaxed) {
+        while is_active() {
+            // An interlocked job waits for the release of its next position
+            if ctx.observable_state.read().interlock.is_some() {
+                tokio::time::sleep(Duration::from_millis(interval_ms)).await;
+                continue;
+            }
+
             // Check if tool is enabled
             let tool_enabled = {
-                let s = observable_state.read();
+                let s = ctx.observable_state.read();
                 s.tool_enabled
             };
 
@@ -459,11 +944,16 @@ async fn start_auto_tightening(
                 break;
             }
 
+            if operator_login_missing(&ctx.observable_state, &ctx.settings) {
+                println!("Auto-tightening stopped: operator logged out");
+                break;
+            }
+
             // Check if we should wait for new configuration
             // In batch mode: waits when batch is complete
             // In single mode: never waits (integrator controls via tool enable/disable)
             let (should_wait, remaining) = {
-                let s = observable_state.read();
+                let s = ctx.observable_state.read();
                 (
                     s.tightening_tracker.should_wait_for_config(),
                     s.tightening_tracker.remaining_work(),
@@ -487,14 +977,17 @@ async fn start_auto_tightening(
             // ================================================================
 
             // Get params from selected PSET
-            let params = {
-                let s = observable_state.read();
-                get_tightening_params(s.current_pset_id, &pset_repository, duration_ms)
+            let mut params = {
+                let s = ctx.observable_state.read();
+                get_tightening_params(s.current_pset_id, &ctx.pset_repository, duration_ms)
             };
+            if let Some(duration_ms) = duration_override {
+                params.duration_ms = duration_ms;
+            }
 
             // Update state to reflect tightening in progress
             {
-                let mut s = observable_state.write();
+                let mut s = ctx.observable_state.write();
                 let fsm = DeviceFSM::new().start_tightening(params.clone());
                 s.device_fsm_state = DeviceFSMState::tightening(&fsm);
             }
@@ -509,11 +1002,35 @@ async fn start_auto_tightening(
                 println!("Cycle {}: Tightening started (single mode)", cycle);
             }
 
+            // Check if multi-spindle mode is enabled
+            let (multi_spindle_enabled, mut multi_spindle_config) = {
+                let s = ctx.observable_state.read();
+                (
+                    s.multi_spindle_config.enabled,
+                    s.multi_spindle_config.clone(),
+                )
+            };
+            if ctx.settings.device.max_size_payloads {
+                // Stress clients with the largest MID 0091/0101 the format allows
+                multi_spindle_config.spindle_count = MAX_SPINDLES_PER_MESSAGE;
+            }
+            // Samples of 99 spindles at once would overrun the event channel
+            let report_spindles = multi_spindle_enabled && !ctx.settings.device.max_size_payloads;
+
             // ================================================================
             // Phase 2: Simulate tightening duration
             // ================================================================
 
-            tokio::time::sleep(Duration::from_millis(duration_ms)).await;
+            if report_spindles {
+                run_down_spindles(
+                    &ctx.observable_state,
+                    &multi_spindle_config,
+                    Duration::from_millis(params.duration_ms),
+                )
+                .await;
+            } else {
+                tokio::time::sleep(Duration::from_millis(params.duration_ms)).await;
+            }
 
             // ================================================================
             // Phase 3: TIGHTENING → EVALUATING
@@ -521,21 +1038,22 @@ async fn start_auto_tightening(
 
             // Complete the tightening and get result
             let fsm = DeviceFSM::new().start_tightening(params.clone());
-            let fsm = fsm.complete();
-            let outcome = fsm.result();
+            let mut fsm = fsm.complete();
 
-            // Apply failure rate (override natural variation)
+            // Apply failure rate (override natural variation); a forced NOK
+            // pushes torque or angle outside its limits to match
             let seed = chrono::Local::now().timestamp_micros() as u64;
             let random_value = (seed % 100) as f64 / 100.0;
-            let final_ok = if random_value < failure_rate {
-                false // Force NOK based on failure rate
-            } else {
-                outcome.ok // Use natural OK/NOK from FSM
-            };
+            if random_value < failure_rate && fsm.result().ok {
+                let reason = NokReason::choose(&params, &mut rand::rng());
+                fsm.result_mut().force_nok(&params, reason);
+            }
+            let outcome = fsm.result();
+            let final_ok = outcome.ok;
 
             // Update state to evaluating
             {
-                let mut s = observable_state.write();
+                let mut s = ctx.observable_state.write();
                 s.device_fsm_state = DeviceFSMState::evaluating(&fsm);
             }
 
@@ -551,26 +1069,19 @@ async fn start_auto_tightening(
             // Phase 4: Add to batch and broadcast
             // ================================================================
 
-            // Check if multi-spindle mode is enabled
-            let (multi_spindle_enabled, multi_spindle_config) = {
-                let s = observable_state.read();
-                (
-                    s.multi_spindle_config.enabled,
-                    s.multi_spindle_config.clone(),
-                )
-            };
-
             if multi_spindle_enabled {
                 // ============================================================
                 // MULTI-SPINDLE PATH
                 // ============================================================
 
-                // Get result_id and pset_id before generating results
-                let (result_id, pset_id) = {
-                    let s = observable_state.read();
+                // Get result_id and pset_id before generating results; the
+                // status, result and status messages share one cycle ID
+                let (result_id, pset_id, cycle_id) = {
+                    let mut s = ctx.observable_state.write();
                     (
                         s.tightening_tracker.tightening_sequence() + 1, // Next sequence number
                         s.current_pset_id.unwrap_or(1),
+                        s.claim_cycle(),
                     )
                 };
 
@@ -580,109 +1091,160 @@ async fn start_auto_tightening(
                 );
 
                 // Broadcast "Running" status (MID 0091)
-                let running_status = MultiSpindleStatus::running(
-                    multi_spindle_config.sync_id,
-                    multi_spindle_config.spindle_count,
-                );
-                observable_state.broadcast(SimulatorEvent::MultiSpindleStatusCompleted {
-                    status: running_status,
-                });
+                let running_status = MultiSpindleStatus {
+                    cycle_id,
+                    ..MultiSpindleStatus::running(
+                        multi_spindle_config.sync_id,
+                        multi_spindle_config.spindle_count,
+                    )
+                };
+                ctx.observable_state
+                    .broadcast(SimulatorEvent::MultiSpindleStatusCompleted {
+                        status: running_status,
+                    });
 
                 // Generate multi-spindle results
-                let multi_result =
+                let mut multi_result =
                     generate_multi_spindle_results(&multi_spindle_config, result_id, pset_id);
+                multi_result.cycle_id = cycle_id;
 
-                // Log per-spindle results
+                // Log per-spindle results and report their final samples
                 for spindle in &multi_result.spindle_results {
                     println!(
                         "  Spindle {}: {} (torque: {:.2} Nm, angle: {:.1}°)",
                         spindle.spindle_id,
                         if spindle.is_ok() { "OK" } else { "NOK" },
-                        spindle.torque as f64 / 100.0,
-                        spindle.angle as f64 / 10.0
+                        spindle.torque.as_nm(),
+                        spindle.angle.as_degrees()
                     );
+                    if report_spindles {
+                        ctx.observable_state
+                            .broadcast(SimulatorEvent::SpindleProgress {
+                                sync_id: multi_result.sync_id,
+                                spindle_id: spindle.spindle_id,
+                                phase: SpindlePhase::Completed,
+                                torque: spindle.torque.as_nm(),
+                                ok: Some(spindle.is_ok()),
+                            });
+                    }
                 }
 
                 // Determine overall status for tracker
                 let overall_ok = multi_result.is_ok();
+                let spindle_cycles: Vec<(bool, f64, f64)> = multi_result
+                    .spindle_results
+                    .iter()
+                    .map(|spindle| {
+                        (
+                            spindle.is_ok(),
+                            spindle.torque.as_nm(),
+                            spindle.angle.as_degrees(),
+                        )
+                    })
+                    .collect();
 
                 // Broadcast multi-spindle result (MID 0101)
-                observable_state.broadcast(SimulatorEvent::MultiSpindleResultCompleted {
-                    result: multi_result,
-                });
+                ctx.observable_state
+                    .broadcast(SimulatorEvent::MultiSpindleResultCompleted {
+                        result: multi_result,
+                    });
 
                 // Broadcast "Completed" status (MID 0091)
-                let completed_status = MultiSpindleStatus::completed(
-                    multi_spindle_config.sync_id,
-                    multi_spindle_config.spindle_count,
-                );
-                observable_state.broadcast(SimulatorEvent::MultiSpindleStatusCompleted {
-                    status: completed_status,
-                });
+                let completed_status = MultiSpindleStatus {
+                    cycle_id,
+                    ..MultiSpindleStatus::completed(
+                        multi_spindle_config.sync_id,
+                        multi_spindle_config.spindle_count,
+                    )
+                };
+                ctx.observable_state
+                    .broadcast(SimulatorEvent::MultiSpindleStatusCompleted {
+                        status: completed_status,
+                    });
 
                 // Update tracker with overall status
                 let (batch_counter, batch_completed, target_size) = {
-                    let mut s = observable_state.write();
+                    let mut s = ctx.observable_state.write();
                     let info = s.tightening_tracker.add_tightening(overall_ok);
+                    for &(ok, torque, angle) in &spindle_cycles {
+                        s.record_tool_cycle(ok, torque, angle, params.duration_ms);
+                    }
                     let batch_completed = s.tightening_tracker.is_complete();
                     let target = s.tightening_tracker.batch_size();
                     (info.counter, batch_completed, target)
                 };
 
                 // Broadcast auto-tightening progress
-                let is_running = auto_active.load(Ordering::Relaxed);
-                observable_state.broadcast_auto_progress(batch_counter, target_size, is_running);
+                let is_running = is_active();
+                ctx.observable_state.broadcast_auto_progress(
+                    batch_counter,
+                    target_size,
+                    is_running,
+                );
 
                 if batch_completed {
-                    let batch_event = SimulatorEvent::BatchCompleted {
-                        total: batch_counter,
-                    };
-                    observable_state.broadcast(batch_event);
+                    ctx.observable_state.complete_batch(batch_counter);
                     println!("Batch completed with {} tightenings", batch_counter);
                 }
+
+                enforce_pset_retry_limit(&ctx.observable_state, &ctx.pset_repository);
+                enforce_tool_service(&ctx);
+                enforce_interlock(&ctx, overall_ok);
             } else {
                 // ============================================================
                 // SINGLE-SPINDLE PATH
                 // ============================================================
 
                 let (result, batch_counter, batch_completed, target_size) = {
-                    let mut s = observable_state.write();
+                    let mut s = ctx.observable_state.write();
                     let info = s.tightening_tracker.add_tightening(final_ok);
 
-                    let result = build_tightening_result(
-                        &s,
-                        &info,
-                        outcome.actual_torque,
-                        outcome.actual_angle,
-                        final_ok,
-                        outcome.torque_ok,
-                        outcome.angle_ok,
-                        &params,
+                    let result = TighteningResult::builder()
+                        .device(&s)
+                        .tightening(&info)
+                        .params(&params)
+                        .torque(outcome.actual_torque)
+                        .angle(outcome.actual_angle)
+                        .status(final_ok, outcome.torque_ok, outcome.angle_ok)
+                        .stages(outcome.stages.clone())
+                        .metadata(metadata.clone())
+                        .build();
+                    s.record_tool_cycle(
+                        result.tightening_status,
+                        result.torque,
+                        result.angle,
+                        params.duration_ms,
                     );
 
                     let batch_completed = s.tightening_tracker.is_complete();
                     let target = s.tightening_tracker.batch_size();
 
-                    // Note: Batch is NOT auto-reset here - integrator must send new batch config (MID 0019)
+                    // Note: Batch is NOT reset here - it restarts on the next tightening when
+                    // auto_reset_batch is enabled, otherwise the integrator must send MID 0019
 
                     (result, info.counter, batch_completed, target)
                 };
 
-                // Broadcast to subscribed TCP clients
-                let event = SimulatorEvent::TighteningCompleted { result };
-                observable_state.broadcast(event);
+                // Record in history and broadcast to subscribed TCP clients
+                let curve = tightening_curve(&ctx.settings, &result, params.duration_ms);
+                record_tightening(&ctx, result, curve, None);
 
                 // Broadcast auto-tightening progress
-                let is_running = auto_active.load(Ordering::Relaxed);
-                observable_state.broadcast_auto_progress(batch_counter, target_size, is_running);
+                let is_running = is_active();
+                ctx.observable_state.broadcast_auto_progress(
+                    batch_counter,
+                    target_size,
+                    is_running,
+                );
 
                 if batch_completed {
-                    let batch_event = SimulatorEvent::BatchCompleted {
-                        total: batch_counter,
-                    };
-                    observable_state.broadcast(batch_event);
+                    ctx.observable_state.complete_batch(batch_counter);
                     println!("Batch completed with {} tightenings", batch_counter);
                 }
+
+                enforce_pset_retry_limit(&ctx.observable_state, &ctx.pset_repository);
+                enforce_tool_service(&ctx);
+                enforce_interlock(&ctx, final_ok);
             }
 
             // ================================================================
@@ -690,7 +1252,7 @@ async fn start_auto_tightening(
             // ================================================================
 
             {
-                let mut s = observable_state.write();
+                let mut s = ctx.observable_state.write();
                 s.device_fsm_state = DeviceFSMState::idle();
             }
 
@@ -699,17 +1261,20 @@ async fn start_auto_tightening(
         }
 
         // Reset active flag when loop exits
-        auto_active.store(false, Ordering::Relaxed);
+        if let Some(active) = auto_active.upgrade() {
+            active.store(false, Ordering::Relaxed);
+        }
 
         // Broadcast stopped status so frontend updates
         let (counter, target_size) = {
-            let state = observable_state.read();
+            let state = ctx.observable_state.read();
             (
                 state.tightening_tracker.counter(),
                 state.tightening_tracker.batch_size(),
             )
         };
-        observable_state.broadcast_auto_progress(counter, target_size, false);
+        ctx.observable_state
+            .broadcast_auto_progress(counter, target_size, false);
 
         println!("Automated tightening stopped");
     });
@@ -737,13 +1302,14 @@ async fn stop_auto_tightening(
     if was_running {
         // Broadcast the stopped status
         let (counter, target_size) = {
-            let state = server_state.observable_state.read();
+            let state = server_state.ctx.observable_state.read();
             let counter = state.tightening_tracker.counter();
             let target = state.tightening_tracker.batch_size();
             (counter, target)
         };
 
         server_state
+            .ctx
             .observable_state
             .broadcast_auto_progress(counter, target_size, false);
 
@@ -780,7 +1346,7 @@ async fn get_auto_tightening_status(
     AxumState(server_state): AxumState<ServerState>,
 ) -> Json<AutoTighteningStatus> {
     let running = server_state.auto_tightening_active.load(Ordering::Relaxed);
-    let state = server_state.observable_state.read();
+    let state = server_state.ctx.observable_state.read();
     let counter = state.tightening_tracker.counter();
     let target = state.tightening_tracker.batch_size();
 
@@ -793,51 +1359,191 @@ async fn get_auto_tightening_status(
 }
 
 // ============================================================================
-// Multi-Spindle Configuration
+// Firehose (high-rate synthetic results)
 // ============================================================================
 
-#[derive(Deserialize)]
-struct MultiSpindleConfigRequest {
-    /// Enable or disable multi-spindle mode
-    enabled: bool,
-    /// Number of spindles (2-16, only used if enabled=true)
-    #[serde(default = "default_spindle_count")]
-    spindle_count: u8,
-    /// Sync tightening ID (only used if enabled=true)
-    #[serde(default = "default_sync_id")]
-    sync_id: u32,
-}
-
-fn default_spindle_count() -> u8 {
-    2
-}
-fn default_sync_id() -> u32 {
-    1
-}
-
-#[derive(Serialize)]
-struct MultiSpindleConfigResponse {
-    success: bool,
-    message: String,
-    enabled: bool,
-    spindle_count: u8,
-    sync_id: u32,
-}
+/// Interval at which the firehose task catches up with its target rate
+const FIREHOSE_TICK: Duration = Duration::from_millis(10);
 
-/// Handler for POST /config/multi-spindle endpoint
-/// Configures multi-spindle mode (enable/disable)
-async fn configure_multi_spindle(
+/// Handler for POST /firehose/start endpoint
+/// Emits synthetic results at a fixed rate straight into the results history
+/// and event broadcast, bypassing the device FSM and batch tracking
+async fn start_firehose(
     AxumState(server_state): AxumState<ServerState>,
-    Json(payload): Json<MultiSpindleConfigRequest>,
+    Json(config): Json<FirehoseConfig>,
 ) -> impl IntoResponse {
-    if payload.enabled {
-        // Enable multi-spindle mode
-        match server_state
-            .observable_state
-            .enable_multi_spindle(payload.spindle_count, payload.sync_id)
-        {
-            Ok(_) => {
-                println!(
+    if !config.is_valid() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "rate_per_sec must be > 0 and nok_rate between 0.0 and 1.0"
+            })),
+        );
+    }
+
+    if !server_state.firehose.try_start() {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Firehose already running. Stop it first."
+            })),
+        );
+    }
+
+    // Snapshot identity, PSET limits and VIN once; the run does not follow later changes
+    let generator = {
+        let state = server_state.ctx.observable_state.read();
+        let params =
+            get_tightening_params(state.current_pset_id, &server_state.ctx.pset_repository, 0);
+        let template = TighteningResult::builder()
+            .device(&state)
+            .params(&params)
+            .batch_size(0)
+            .build();
+        ResultGenerator::new(template, params, config.nok_rate, config.distribution)
+    };
+
+    let message = format!("Firehose started at {} results/s", config.rate_per_sec);
+    let task = server_state.ctx.resources.track("firehose");
+    tokio::spawn(run_firehose(
+        task,
+        server_state.ctx.clone(),
+        Arc::downgrade(&server_state.firehose),
+        config,
+        generator,
+    ));
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": message
+        })),
+    )
+}
+
+/// Background task of the firehose: every tick, emit as many results as are
+/// due for the configured rate
+async fn run_firehose(
+    _task: TaskGuard,
+    ctx: AppContext,
+    status: Weak<FirehoseStatus>,
+    config: FirehoseConfig,
+    mut generator: ResultGenerator,
+) {
+    let started = std::time::Instant::now();
+    let deadline = config.duration_ms.map(Duration::from_millis);
+    let mut ticker = tokio::time::interval(FIREHOSE_TICK);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut emitted = 0u64;
+
+    while status.upgrade().is_some_and(|status| status.is_active()) {
+        ticker.tick().await;
+
+        let elapsed = started.elapsed();
+        if deadline.is_some_and(|d| elapsed >= d) {
+            break;
+        }
+
+        let pending = config.due(elapsed.as_secs_f64()).saturating_sub(emitted);
+        if pending > 0 {
+            let first_id = ctx
+                .observable_state
+                .write()
+                .tightening_tracker
+                .reserve_tightening_ids(pending as u32);
+            let timestamp = chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string();
+            for offset in 0..pending as u32 {
+                let result = generator.generate(first_id + offset, timestamp.clone());
+                record_tightening(&ctx, result, None, None);
+            }
+            emitted += pending;
+            if let Some(status) = status.upgrade() {
+                status.add_generated(pending);
+            }
+        }
+
+        if config.count.is_some_and(|count| emitted >= count) {
+            break;
+        }
+    }
+
+    if let Some(status) = status.upgrade() {
+        status.stop();
+    }
+    println!("Firehose finished: {} results generated", emitted);
+}
+
+/// Handler for POST /firehose/stop endpoint
+async fn stop_firehose(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let message = if server_state.firehose.stop() {
+        "Firehose stopped"
+    } else {
+        "Firehose was not running"
+    };
+    Json(serde_json::json!({
+        "success": true,
+        "message": message
+    }))
+}
+
+/// Handler for GET /firehose/status endpoint
+async fn get_firehose_status(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "running": server_state.firehose.is_active(),
+        "generated": server_state.firehose.generated()
+    }))
+}
+
+// ============================================================================
+// Multi-Spindle Configuration
+// ============================================================================
+
+#[derive(Deserialize)]
+struct MultiSpindleConfigRequest {
+    /// Enable or disable multi-spindle mode
+    enabled: bool,
+    /// Number of spindles (2-16, only used if enabled=true)
+    #[serde(default = "default_spindle_count")]
+    spindle_count: u8,
+    /// Sync tightening ID (only used if enabled=true)
+    #[serde(default = "default_sync_id")]
+    sync_id: u32,
+}
+
+fn default_spindle_count() -> u8 {
+    2
+}
+fn default_sync_id() -> u32 {
+    1
+}
+
+#[derive(Serialize)]
+struct MultiSpindleConfigResponse {
+    success: bool,
+    message: String,
+    enabled: bool,
+    spindle_count: u8,
+    sync_id: u32,
+}
+
+/// Handler for POST /config/multi-spindle endpoint
+/// Configures multi-spindle mode (enable/disable)
+async fn configure_multi_spindle(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(payload): Json<MultiSpindleConfigRequest>,
+) -> impl IntoResponse {
+    if payload.enabled {
+        // Enable multi-spindle mode
+        match server_state
+            .ctx
+            .observable_state
+            .enable_multi_spindle(payload.spindle_count, payload.sync_id)
+        {
+            Ok(_) => {
+                println!(
                     "Multi-spindle mode enabled: {} spindles, sync_id={}",
                     payload.spindle_count, payload.sync_id
                 );
@@ -871,7 +1577,7 @@ async fn configure_multi_spindle(
         }
     } else {
         // Disable multi-spindle mode
-        server_state.observable_state.disable_multi_spindle();
+        server_state.ctx.observable_state.disable_multi_spindle();
         println!("Multi-spindle mode disabled");
         (
             StatusCode::OK,
@@ -890,7 +1596,7 @@ async fn configure_multi_spindle(
 // Failure Injection Configuration
 // ============================================================================
 
-#[derive(Deserialize)]
+#[derive(Default, Deserialize)]
 struct FailureConfigRequest {
     /// Optional: set connection health directly (0-100)
     /// If provided, this recalculates all other failure rates
@@ -904,6 +1610,9 @@ struct FailureConfigRequest {
     delay_max_ms: Option<u64>,
     corruption_rate: Option<f64>,
     force_disconnect_rate: Option<f64>,
+    /// Per-connection link bandwidth; `null` removes the limit
+    #[serde(default, deserialize_with = "crate::state::nullable")]
+    bandwidth: Option<Option<BandwidthLimit>>,
 }
 
 /// Handler for GET /config/failure endpoint
@@ -911,7 +1620,7 @@ struct FailureConfigRequest {
 async fn get_failure_config(
     AxumState(server_state): AxumState<ServerState>,
 ) -> Json<FailureConfig> {
-    let state = server_state.observable_state.read();
+    let state = server_state.ctx.observable_state.read();
     Json(state.failure_config.clone())
 }
 
@@ -932,7 +1641,7 @@ async fn update_failure_config(
     } else {
         // Advanced mode: update individual fields
         let mut config = {
-            let state = server_state.observable_state.read();
+            let state = server_state.ctx.observable_state.read();
             state.failure_config.clone()
         };
 
@@ -954,6 +1663,9 @@ async fn update_failure_config(
         if let Some(rate) = payload.force_disconnect_rate {
             config.force_disconnect_rate = rate.clamp(0.0, 1.0);
         }
+        if let Some(bandwidth) = payload.bandwidth {
+            config.bandwidth = bandwidth;
+        }
 
         println!("Updating failure config via individual fields");
         config
@@ -973,8 +1685,15 @@ async fn update_failure_config(
 
     // Update the state
     {
-        let mut state = server_state.observable_state.write();
+        let mut state = server_state.ctx.observable_state.write();
         state.failure_config = new_config.clone();
+        state.audit_log.record(
+            AuditCategory::Configuration,
+            format!(
+                "Failure injection updated (health {}%)",
+                new_config.connection_health
+            ),
+        );
     }
 
     println!("Failure injection config updated:");
@@ -990,6 +1709,12 @@ async fn update_failure_config(
         "  Disconnect: {:.1}%",
         new_config.force_disconnect_rate * 100.0
     );
+    if let Some(limit) = new_config.bandwidth {
+        println!(
+            "  Bandwidth: {} B/s per connection (burst {} B)",
+            limit.bytes_per_sec, limit.burst_bytes
+        );
+    }
 
     (
         StatusCode::OK,
@@ -1002,6 +1727,87 @@ async fn update_failure_config(
         .into_response()
 }
 
+// ============================================================================
+// Event Long-Poll
+// ============================================================================
+
+/// Longest `timeout_ms` accepted by `GET /events/next`
+const MAX_EVENT_WAIT_MS: u64 = 300_000;
+
+fn default_event_wait_ms() -> u64 {
+    30_000
+}
+
+#[derive(Deserialize)]
+struct NextEventQuery {
+    /// Event types to wait for, comma-separated (default: any)
+    #[serde(rename = "type")]
+    types: Option<String>,
+    /// How long to wait (default: 30000, at most 300000)
+    #[serde(default = "default_event_wait_ms")]
+    timeout_ms: u64,
+}
+
+/// Handler for GET /events/next endpoint
+/// Waits for the next event of one of the given types and returns it as JSON
+/// (408 if none occurs in time); events before the request are not considered
+async fn next_event(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<NextEventQuery>,
+) -> impl IntoResponse {
+    let types: Vec<&str> = query
+        .types
+        .as_deref()
+        .map(|types| {
+            types
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let timeout_ms = query.timeout_ms.min(MAX_EVENT_WAIT_MS);
+    let mut events = server_state.ctx.observable_state.subscribe();
+
+    let wait = async {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let Ok(json) = serde_json::to_value(&event) else {
+                        continue;
+                    };
+                    let matches = types.is_empty()
+                        || json["type"]
+                            .as_str()
+                            .is_some_and(|event_type| types.contains(&event_type));
+                    if matches {
+                        return Some(json);
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    };
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), wait).await {
+        Ok(Some(event)) => (StatusCode::OK, Json(event)),
+        Ok(None) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Event stream closed"
+            })),
+        ),
+        Err(_) => (
+            StatusCode::REQUEST_TIMEOUT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No matching event within {} ms", timeout_ms)
+            })),
+        ),
+    }
+}
+
 // ============================================================================
 // WebSocket Event Stream
 // ============================================================================
@@ -1016,18 +1822,19 @@ async fn websocket_handler(
 }
 
 /// WebSocket connection handler
-/// Subscribes to the event broadcaster and sends all events to the WebSocket client
+/// Subscribes to the event broadcaster and sends all events to the WebSocket client,
+/// and dispatches control commands received from the client (see [`WsCommand`])
 async fn handle_websocket(socket: WebSocket, server_state: ServerState) {
     let (mut sender, mut receiver) = socket.split();
 
     // Subscribe to the event broadcaster
-    let mut event_rx = server_state.observable_state.subscribe();
+    let mut event_rx = server_state.ctx.observable_state.subscribe();
 
     println!("WebSocket client connected");
 
     // Send initial device state
     let state_json = {
-        let state = server_state.observable_state.read();
+        let state = server_state.ctx.observable_state.read();
         serde_json::to_string(&*state).ok()
     };
 
@@ -1035,21 +1842,19 @@ async fn handle_websocket(socket: WebSocket, server_state: ServerState) {
         let _ = sender.send(Message::Text(json.into())).await;
     }
 
-    // Clone sender for recv_task (need to share between tasks)
-    let (pong_tx, mut pong_rx) = tokio::sync::mpsc::channel::<String>(10);
+    // Replies to client commands are funneled through the send task
+    let (reply_tx, mut reply_rx) = tokio::sync::mpsc::channel::<String>(10);
 
-    // Spawn task to receive messages from client (handle ping/pong)
+    // Spawn task to receive messages from client (ping and control commands)
+    let command_state = server_state.clone();
+    let _task = server_state.ctx.resources.track("websocket");
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
                 Message::Text(text) => {
-                    // Try to parse as JSON to check if it's a ping message
-                    if let Ok(value) = serde_json::from_str::<serde_json::Value>(&text)
-                        && value.get("type").and_then(|t| t.as_str()) == Some("ping")
-                    {
-                        // Send pong response
-                        let pong_msg = r#"{"type":"pong"}"#.to_string();
-                        let _ = pong_tx.send(pong_msg).await;
+                    let reply = handle_ws_command(&command_state, &text).await;
+                    if reply_tx.send(reply).await.is_err() {
+                        break;
                     }
                 }
                 Message::Close(_) => {
@@ -1089,9 +1894,9 @@ async fn handle_websocket(socket: WebSocket, server_state: ServerState) {
                         }
                     }
                 }
-                // Handle pong responses from recv_task
-                Some(pong_msg) = pong_rx.recv() => {
-                    if sender.send(Message::Text(pong_msg.into())).await.is_err() {
+                // Handle command replies from recv_task
+                Some(reply) = reply_rx.recv() => {
+                    if sender.send(Message::Text(reply.into())).await.is_err() {
                         // Client disconnected
                         break;
                     }
@@ -1113,121 +1918,2220 @@ async fn handle_websocket(socket: WebSocket, server_state: ServerState) {
     println!("WebSocket client disconnected");
 }
 
-/// Handler for GET /psets endpoint
-/// Returns all available PSETs
-async fn get_psets(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
-    let repo = server_state.pset_repository.read().unwrap();
-    let psets = repo.get_all();
-    Json(psets)
+/// Query of `GET /ws/protocol`
+#[derive(Deserialize)]
+struct ProtocolTapQuery {
+    connection_id: SessionId,
+    /// Only frames travelling this way (default: both)
+    #[serde(default)]
+    direction: Option<FrameDirection>,
 }
 
-/// Handler for GET /psets/:id endpoint
-/// Returns a specific PSET by ID
-async fn get_pset_by_id(
+/// Handler for GET /ws/protocol endpoint
+/// Streams the raw Open Protocol frames of one TCP connection as binary messages
+async fn protocol_tap_handler(
+    ws: WebSocketUpgrade,
+    Query(query): Query<ProtocolTapQuery>,
     AxumState(server_state): AxumState<ServerState>,
-    Path(id): Path<u32>,
-) -> impl IntoResponse {
-    let repo = server_state.pset_repository.read().unwrap();
-    match repo.get_by_id(id) {
-        Some(pset) => (StatusCode::OK, Json(pset)).into_response(),
-        None => (
+) -> axum::response::Response {
+    let Some(frames) = server_state.ctx.sessions.tap_frames(query.connection_id) else {
+        return (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({
-                "error": format!("PSET with id {} not found", id)
+                "success": false,
+                "error": format!("Connection {} not found", query.connection_id)
             })),
         )
-            .into_response(),
+            .into_response();
+    };
+    let task = server_state.ctx.resources.track("protocol_tap");
+    ws.on_upgrade(move |socket| async move {
+        let _task = task;
+        handle_protocol_tap(socket, frames, query.direction).await;
+    })
+}
+
+/// Forward tapped frames until the TCP connection or the WebSocket closes
+///
+/// Each binary message is one frame without its NUL terminator.
+async fn handle_protocol_tap(
+    socket: WebSocket,
+    mut frames: tokio::sync::broadcast::Receiver<TappedFrame>,
+    direction: Option<FrameDirection>,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => {
+                    if direction.is_some_and(|direction| direction != frame.direction) {
+                        continue;
+                    }
+                    if sender.send(Message::Binary(frame.bytes)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("Protocol tap lagged behind, {} frames skipped", skipped);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                    // The TCP connection ended
+                    let _ = sender.send(Message::Close(None)).await;
+                    break;
+                }
+            },
+            message = receiver.next() => match message {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
     }
 }
 
-/// Handler for POST /psets/:id/select endpoint
-/// Selects the specified PSET as the active parameter set
-async fn select_pset(
-    AxumState(server_state): AxumState<ServerState>,
-    Path(id): Path<u32>,
-) -> impl IntoResponse {
-    // Check if PSET exists
-    let pset_name = {
-        let repo = server_state.pset_repository.read().unwrap();
-        match repo.get_by_id(id) {
-            Some(pset) => pset.name.clone(),
-            None => {
-                return (
-                    StatusCode::NOT_FOUND,
-                    Json(serde_json::json!({
-                        "success": false,
-                        "error": format!("PSET with id {} not found", id)
-                    })),
-                )
-                    .into_response();
-            }
+/// Control command sent by a WebSocket client over `/ws/events`
+///
+/// Commands are dispatched to the same handlers as the REST endpoints.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsCommand {
+    Ping,
+    SelectPset { pset_id: u32 },
+    SimulateTightening(TighteningRequest),
+    StartAutoTightening(AutoTighteningRequest),
+    StopAutoTightening,
+    SetHealth { connection_health: u8 },
+}
+
+impl WsCommand {
+    fn name(&self) -> &'static str {
+        match self {
+            WsCommand::Ping => "ping",
+            WsCommand::SelectPset { .. } => "select_pset",
+            WsCommand::SimulateTightening(_) => "simulate_tightening",
+            WsCommand::StartAutoTightening(_) => "start_auto_tightening",
+            WsCommand::StopAutoTightening => "stop_auto_tightening",
+            WsCommand::SetHealth { .. } => "set_health",
+        }
+    }
+}
+
+/// Envelope of a WebSocket command; `id` is echoed back in the reply
+#[derive(Deserialize)]
+struct WsCommandMessage {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    command: WsCommand,
+}
+
+/// Parse, validate and dispatch one WebSocket command, returning the reply
+///
+/// Replies are `{"type":"command_result","id":..,"command":..,"status":..,"response":..}`
+/// where `status`/`response` mirror the equivalent REST call.
+async fn handle_ws_command(server_state: &ServerState, text: &str) -> String {
+    let message = match serde_json::from_str::<WsCommandMessage>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            let id = serde_json::from_str::<serde_json::Value>(text)
+                .ok()
+                .and_then(|v| v.get("id").cloned());
+            return serde_json::json!({
+                "type": "command_result",
+                "id": id,
+                "success": false,
+                "status": StatusCode::BAD_REQUEST.as_u16(),
+                "error": format!("Invalid command: {}", e)
+            })
+            .to_string();
         }
     };
 
-    // Set the PSET in device state and broadcast the change
-    server_state
-        .observable_state
-        .set_pset(id, Some(pset_name.clone()));
+    let command = message.command.name();
+    let state = AxumState(server_state.clone());
+    let response = match message.command {
+        WsCommand::Ping => return r#"{"type":"pong"}"#.to_string(),
+        WsCommand::SelectPset { pset_id } => {
+            select_pset(state, Path(pset_id)).await.into_response()
+        }
+        WsCommand::SimulateTightening(request) => simulate_tightening(state, Json(request))
+            .await
+            .into_response(),
+        WsCommand::StartAutoTightening(request) => start_auto_tightening(state, Json(request))
+            .await
+            .into_response(),
+        WsCommand::StopAutoTightening => stop_auto_tightening(state).await.into_response(),
+        WsCommand::SetHealth { connection_health } => {
+            let request = FailureConfigRequest {
+                connection_health: Some(connection_health),
+                ..Default::default()
+            };
+            update_failure_config(state, Json(request))
+                .await
+                .into_response()
+        }
+    };
 
-    (
-        StatusCode::OK,
-        Json(serde_json::json!({
-            "success": true,
-            "message": format!("PSET {} '{}' selected", id, pset_name),
-            "pset_id": id,
-            "pset_name": pset_name
-        })),
-    )
-        .into_response()
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok())
+        .unwrap_or(serde_json::Value::Null);
+
+    serde_json::json!({
+        "type": "command_result",
+        "id": message.id,
+        "command": command,
+        "success": status.is_success(),
+        "status": status.as_u16(),
+        "response": body
+    })
+    .to_string()
 }
 
-/// Handler for POST /psets endpoint
-/// Creates a new PSET
-async fn create_pset(
+// ============================================================================
+// Results History
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ResultsQuery {
+    /// Maximum number of (most recent) results to return
+    limit: Option<usize>,
+    /// Only results of this cycle (see `DeviceState::claim_cycle`)
+    cycle_id: Option<u64>,
+}
+
+/// Handler for GET /results endpoint
+/// Returns the most recent tightening results (oldest first)
+async fn get_results(
     AxumState(server_state): AxumState<ServerState>,
-    Json(pset): Json<pset::Pset>,
+    Query(query): Query<ResultsQuery>,
 ) -> impl IntoResponse {
-    let mut repo = server_state.pset_repository.write().unwrap();
+    let repo = server_state.ctx.result_repository.read().unwrap();
+    let mut results = match (query.limit, query.cycle_id) {
+        (Some(limit), None) => repo.get_recent(limit),
+        _ => repo.get_all(),
+    };
+    if let Some(cycle_id) = query.cycle_id {
+        results.retain(|result| result.cycle_id == cycle_id);
+        if let Some(limit) = query.limit {
+            results.drain(..results.len().saturating_sub(limit));
+        }
+    }
+    Json(results)
+}
 
-    match repo.create(pset) {
-        Ok(created_pset) => (
-            StatusCode::CREATED,
-            Json(serde_json::json!({
-                "success": true,
-                "message": "PSET created successfully",
-                "pset": created_pset
-            })),
-        )
-            .into_response(),
-        Err(err) => (
-            StatusCode::BAD_REQUEST,
+/// Handler for GET /results/:id endpoint
+/// Returns one result by tightening ID, including per-stage results
+async fn get_result_by_id(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+) -> impl IntoResponse {
+    let repo = server_state.ctx.result_repository.read().unwrap();
+    match repo.get_by_id(id) {
+        Some(result) => (StatusCode::OK, Json(result)).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
             Json(serde_json::json!({
-                "success": false,
-                "error": err
+                "error": format!("Result with tightening id {} not found", id)
             })),
         )
             .into_response(),
     }
 }
 
-/// Handler for PUT /psets/:id endpoint
-/// Updates an existing PSET
-async fn update_pset(
+#[derive(Deserialize)]
+struct VoidRequest {
+    reason: String,
+}
+
+/// Handler for POST /results/:id/void endpoint
+/// Voids a stored result and resends the correction to MID 0061 subscribers
+async fn void_result(
     AxumState(server_state): AxumState<ServerState>,
     Path(id): Path<u32>,
-    Json(pset): Json<pset::Pset>,
+    Json(payload): Json<VoidRequest>,
 ) -> impl IntoResponse {
-    let mut repo = server_state.pset_repository.write().unwrap();
-
-    match repo.update(id, pset) {
-        Ok(updated_pset) => {
-            // If this is the currently selected PSET, update the state
-            let current_pset_id = server_state.observable_state.read().current_pset_id;
-            if current_pset_id == Some(id) {
-                server_state
-                    .observable_state
-                    .set_pset(id, Some(updated_pset.name.clone()));
-            }
+    let ctx = &server_state.ctx;
+    if payload.reason.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": "reason must not be empty"})),
+        );
+    }
+    let result = {
+        let mut repo = ctx.result_repository.write().unwrap();
+        let Some(mut result) = repo.get_by_id(id) else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": format!("Result with tightening id {} not found", id)
+                })),
+            );
+        };
+        if result.voided.is_some() {
+            return (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": format!("Result with tightening id {} is already voided", id)
+                })),
+            );
+        }
+        result.voided = Some(results::ResultVoid::now(payload.reason.clone()));
+        result.tightening_status = false;
+        if let Some(key) = &ctx.settings.results.signing_key {
+            results::sign(&mut result, key);
+        }
+        repo.replace(result.clone());
+        result
+    };
+    println!("Result {} voided: {}", id, payload.reason);
+    ctx.observable_state
+        .broadcast(SimulatorEvent::ResultVoided {
+            result: result.clone(),
+        });
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({"success": true, "result": result})),
+    )
+}
+
+#[derive(Deserialize)]
+struct CurveQuery {
+    /// Response format: "json" (default) or "csv"
+    format: Option<String>,
+    /// Downsample to at most this many points
+    max_points: Option<usize>,
+}
+
+/// Handler for GET /results/:id/curve endpoint
+/// Returns the stored torque/angle trace of a result as JSON or CSV
+async fn get_result_curve(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+    Query(query): Query<CurveQuery>,
+) -> impl IntoResponse {
+    let curve = server_state
+        .ctx
+        .result_repository
+        .read()
+        .unwrap()
+        .get_curve(id);
+    let Some(mut curve) = curve else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": format!("No curve stored for tightening id {}", id)
+            })),
+        )
+            .into_response();
+    };
+    if let Some(max_points) = query.max_points {
+        curve = curve.downsample(max_points);
+    }
+
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => Json(curve).into_response(),
+        "csv" => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/csv")],
+            curve.to_csv(),
+        )
+            .into_response(),
+        other => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("Unsupported curve format '{}'", other)
+            })),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    /// Export format: "json" (default) or "csv"
+    format: Option<String>,
+    /// Parquet only: "results" (default) or "curves"
+    #[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+    table: Option<String>,
+}
+
+/// Handler for GET /results/export endpoint
+/// Exports the full results history, including metadata, as JSON or CSV
+/// (or Parquet with the `parquet` feature)
+async fn export_results(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<ExportQuery>,
+) -> impl IntoResponse {
+    let results = server_state.ctx.result_repository.read().unwrap().get_all();
+
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => Json(results).into_response(),
+        "csv" => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/csv")],
+            results::to_csv(&results),
+        )
+            .into_response(),
+        #[cfg(feature = "parquet")]
+        "parquet" => {
+            let mut data = Vec::new();
+            let written = match query.table.as_deref().unwrap_or("results") {
+                "results" => parquet_export::write_results(&results, &mut data),
+                "curves" => {
+                    let curves =
+                        parquet_export::curves_for(&server_state.ctx.result_repository, &results);
+                    parquet_export::write_curves(&curves, &mut data)
+                }
+                other => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(serde_json::json!({
+                            "success": false,
+                            "error": format!("Unknown export table '{}'", other)
+                        })),
+                    )
+                        .into_response();
+                }
+            };
+            match written {
+                Ok(()) => (
+                    StatusCode::OK,
+                    [(header::CONTENT_TYPE, "application/vnd.apache.parquet")],
+                    data,
+                )
+                    .into_response(),
+                Err(e) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({
+                        "success": false,
+                        "error": e.to_string()
+                    })),
+                )
+                    .into_response(),
+            }
+        }
+        other => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("Unsupported export format '{}'", other)
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for POST /results/generate endpoint
+/// Bulk-generates a historical dataset directly into the results history
+/// (nothing is broadcast; subscribers only see live results)
+async fn generate_results(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(config): Json<BackfillConfig>,
+) -> impl IntoResponse {
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": error
+            })),
+        )
+    };
+
+    if let Err(e) = config.validate() {
+        return bad_request(e);
+    }
+    let range = match config.time_range(chrono::Local::now().naive_local()) {
+        Ok(range) => range,
+        Err(e) => return bad_request(e),
+    };
+
+    let mut generators = std::collections::BTreeMap::new();
+    let first_id = {
+        let mut state = server_state.ctx.observable_state.write();
+        let pset_ids: Vec<u32> = if config.pset_mix.is_empty() {
+            vec![state.current_pset_id.unwrap_or(1)]
+        } else {
+            config.pset_mix.keys().copied().collect()
+        };
+
+        for pset_id in pset_ids {
+            let known = server_state
+                .ctx
+                .pset_repository
+                .read()
+                .unwrap()
+                .get_by_id(pset_id)
+                .is_some();
+            if !known && !config.pset_mix.is_empty() {
+                return bad_request(format!("PSET {} not found", pset_id));
+            }
+            let params = get_tightening_params(Some(pset_id), &server_state.ctx.pset_repository, 0);
+            let template = TighteningResult::builder()
+                .device(&state)
+                .params(&params)
+                .pset_id(pset_id)
+                .batch_size(0)
+                .build();
+            generators.insert(
+                pset_id,
+                ResultGenerator::new(template, params, config.nok_rate, config.distribution),
+            );
+        }
+
+        state
+            .tightening_tracker
+            .reserve_tightening_ids(config.count)
+    };
+
+    let history = generate_history(&config, &mut generators, range, first_id);
+    let generated = history.len();
+    {
+        let signing_key = server_state.ctx.settings.results.signing_key.as_deref();
+        let mut repo = server_state.ctx.result_repository.write().unwrap();
+        for mut result in history {
+            if let Some(key) = signing_key {
+                results::sign(&mut result, key);
+            }
+            repo.add(result);
+        }
+    }
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "generated": generated,
+            "from": range.0.format(crate::generator::RESULT_TIMESTAMP_FORMAT).to_string(),
+            "to": range.1.format(crate::generator::RESULT_TIMESTAMP_FORMAT).to_string()
+        })),
+    )
+}
+
+// ============================================================================
+// Audit Log
+// ============================================================================
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    /// Only entries with a greater ID (for incremental polling)
+    since: Option<u64>,
+    /// Only entries of this category (pset, tool, batch, ...)
+    category: Option<String>,
+    /// Maximum number of (most recent) entries to return
+    limit: Option<usize>,
+}
+
+/// Handler for GET /audit endpoint
+/// Returns controller audit log entries, oldest first
+async fn get_audit_log(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<AuditQuery>,
+) -> impl IntoResponse {
+    let category = match query.category.as_deref() {
+        Some(name) => match AuditCategory::from_name(name) {
+            Some(category) => Some(category),
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "success": false,
+                        "error": format!("Unknown audit category '{}'", name)
+                    })),
+                )
+                    .into_response();
+            }
+        },
+        None => None,
+    };
+
+    let mut entries = server_state
+        .ctx
+        .observable_state
+        .read()
+        .audit_log
+        .since(query.since.unwrap_or(0));
+    if let Some(category) = category {
+        entries.retain(|e| e.category == category);
+    }
+    if let Some(limit) = query.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+
+    Json(entries).into_response()
+}
+
+#[derive(Deserialize)]
+struct VinHistoryQuery {
+    /// Maximum number of (most recent) entries to return
+    limit: Option<usize>,
+}
+
+/// Handler for GET /vehicle-ids/history endpoint
+/// Returns the job instances (VIN downloads and job selections), oldest first
+async fn get_vin_history(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<VinHistoryQuery>,
+) -> impl IntoResponse {
+    let (current, mut history) = {
+        let state = server_state.ctx.observable_state.read();
+        (state.job_sequence_number, state.vin_history.entries())
+    };
+    if let Some(limit) = query.limit {
+        history.drain(..history.len().saturating_sub(limit));
+    }
+    Json(serde_json::json!({
+        "job_sequence_number": current,
+        "count": history.len(),
+        "history": history
+    }))
+}
+
+#[derive(Deserialize)]
+struct CountersRequest {
+    /// Tightening ID the next result gets (at least 1)
+    next_tightening_id: Option<u32>,
+    /// Sequence number of the current job instance; the next VIN or job gets the one after
+    job_sequence_number: Option<u32>,
+}
+
+/// Next tightening ID and current job sequence number as reported over HTTP
+fn counters_json(counters: Counters) -> serde_json::Value {
+    serde_json::json!({
+        "next_tightening_id": u64::from(counters.tightening_id) + 1,
+        "job_sequence_number": counters.job_sequence_number
+    })
+}
+
+/// Handler for GET /counters endpoint
+async fn get_counters(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    Json(counters_json(
+        server_state.ctx.observable_state.read().counters(),
+    ))
+}
+
+/// Handler for PUT /counters endpoint
+/// Sets the next tightening ID and the job sequence number, saved right away
+async fn set_counters(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(payload): Json<CountersRequest>,
+) -> impl IntoResponse {
+    let ctx = &server_state.ctx;
+    let counters = {
+        let mut state = ctx.observable_state.write();
+        let mut counters = state.counters();
+        if let Some(next) = payload.next_tightening_id {
+            counters.tightening_id = next.saturating_sub(1);
+        }
+        if let Some(number) = payload.job_sequence_number {
+            counters.job_sequence_number = number;
+        }
+        let valid = if payload.next_tightening_id == Some(0) {
+            Err("next_tightening_id must be at least 1".to_string())
+        } else {
+            counters.validate()
+        };
+        if let Err(e) = valid {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "success": false, "error": e })),
+            );
+        }
+        state.restore_counters(counters);
+        counters
+    };
+    if let Err(e) = ctx.counters.write().unwrap().save(counters) {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "success": false, "error": e })),
+        );
+    }
+    println!(
+        "Counters set: next tightening ID {}, job sequence number {}",
+        u64::from(counters.tightening_id) + 1,
+        counters.job_sequence_number
+    );
+    let mut body = counters_json(counters);
+    body["success"] = true.into();
+    (StatusCode::OK, Json(body))
+}
+
+// ============================================================================
+// Lifetime Statistics
+// ============================================================================
+
+/// Handler for POST /tool/service-reset endpoint
+/// Restarts the service counter, clears service alarms and unlocks the tool
+async fn reset_tool_service(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let reset = server_state.ctx.observable_state.reset_service();
+    println!(
+        "Tool serviced after {} tightenings{}",
+        reset.tightenings_since_service,
+        if reset.unlocked {
+            ", tool unlocked"
+        } else {
+            ""
+        }
+    );
+    Json(serde_json::json!({
+        "success": true,
+        "tightenings_since_service": reset.tightenings_since_service,
+        "cleared_alarms": reset.cleared_alarms,
+        "unlocked": reset.unlocked
+    }))
+}
+
+/// Handler for GET /stats/lifetime endpoint
+/// Returns the persisted lifetime tool counters with derived values
+async fn get_lifetime_stats(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let stats = server_state
+        .ctx
+        .observable_state
+        .read()
+        .lifetime_stats
+        .clone();
+    Json(serde_json::json!({
+        "total_tightenings": stats.total_tightenings,
+        "ok_tightenings": stats.ok_tightenings,
+        "nok_tightenings": stats.nok_tightenings,
+        "nok_rate": stats.nok_rate(),
+        "tightenings_since_service": stats.tightenings_since_service,
+        "last_service_at": stats.last_service_at,
+        "tool_runtime_secs": stats.tool_runtime_secs(),
+        "energy_j": stats.energy_j,
+        "energy_wh": stats.energy_wh()
+    }))
+}
+
+#[derive(Deserialize)]
+struct CycleTimeQuery {
+    /// Only the most recent results (default: the whole history)
+    limit: Option<usize>,
+}
+
+/// Handler for GET /stats/cycle-time endpoint
+/// Summarizes cycle times of the results history overall and per PSET and job
+async fn get_cycle_time_stats(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<CycleTimeQuery>,
+) -> impl IntoResponse {
+    let results = {
+        let repo = server_state.ctx.result_repository.read().unwrap();
+        match query.limit {
+            Some(limit) => repo.get_recent(limit),
+            None => repo.get_all(),
+        }
+    };
+    Json(CycleTimeReport::from_results(&results))
+}
+
+#[derive(Deserialize)]
+struct HandlerStatsQuery {
+    /// `p99` (slowest first, default), `errors` (most failures first) or `mid`
+    sort: Option<String>,
+    /// Only the first handlers after sorting
+    limit: Option<usize>,
+}
+
+/// Handler for GET /stats/handlers endpoint
+/// Per-MID latency percentiles and error breakdown from the metrics middleware
+async fn get_handler_stats(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<HandlerStatsQuery>,
+) -> impl IntoResponse {
+    let mut handlers = server_state.ctx.handler_metrics.stats();
+    match query.sort.as_deref().unwrap_or("p99") {
+        "p99" => handlers.sort_by_key(|h| (std::cmp::Reverse(h.p99_micros), h.mid)),
+        "errors" => handlers.sort_by_key(|h| (std::cmp::Reverse(h.errors + h.naks), h.mid)),
+        "mid" => {}
+        other => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": format!("Unknown sort '{}' (expected p99, errors or mid)", other)
+                })),
+            )
+                .into_response();
+        }
+    }
+    if let Some(limit) = query.limit {
+        handlers.truncate(limit);
+    }
+    Json(serde_json::json!({ "handlers": handlers })).into_response()
+}
+
+// ============================================================================
+// Alarms
+// ============================================================================
+
+#[derive(Deserialize)]
+struct AlarmQuery {
+    /// Only alarms in this state (active, acknowledged, cleared)
+    state: Option<String>,
+    /// Only alarms of this severity (info, warning, error, critical)
+    severity: Option<String>,
+    /// Language of the alarm texts (default: the controller language)
+    lang: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RaiseAlarmRequest {
+    /// Four-character Open Protocol error code
+    error_code: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    severity: AlarmSeverity,
+}
+
+fn alarm_error_response(error: AlarmError) -> axum::response::Response {
+    let status = match error {
+        AlarmError::NotFound(_) => StatusCode::NOT_FOUND,
+        AlarmError::InvalidTransition { .. } => StatusCode::CONFLICT,
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "error": error.to_string()
+        })),
+    )
+        .into_response()
+}
+
+/// Handler for GET /alarms endpoint
+/// Returns controller alarms, oldest first
+async fn get_alarms(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<AlarmQuery>,
+) -> impl IntoResponse {
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "success": false, "error": error })),
+        )
+            .into_response()
+    };
+    let state = match query
+        .state
+        .as_deref()
+        .map(|name| (name, AlarmState::from_name(name)))
+    {
+        Some((name, None)) => return bad_request(format!("Unknown alarm state '{}'", name)),
+        Some((_, state)) => state,
+        None => None,
+    };
+    let severity = match query
+        .severity
+        .as_deref()
+        .map(|name| (name, AlarmSeverity::from_name(name)))
+    {
+        Some((name, None)) => return bad_request(format!("Unknown alarm severity '{}'", name)),
+        Some((_, severity)) => severity,
+        None => None,
+    };
+
+    if let Some(lang) = query
+        .lang
+        .as_deref()
+        .filter(|lang| !alarm_texts::is_valid_language(lang))
+    {
+        return bad_request(format!("Invalid language '{}'", lang));
+    }
+
+    let (mut alarms, texts) = {
+        let device = server_state.ctx.observable_state.read();
+        (device.alarms.alarms(), device.alarm_texts.clone())
+    };
+    alarms.retain(|a| state.is_none_or(|s| a.state == s));
+    alarms.retain(|a| severity.is_none_or(|s| a.severity == s));
+    if let Some(lang) = &query.lang {
+        for alarm in &mut alarms {
+            alarm.text = texts.localize(&alarm.error_code, lang, &alarm.description);
+        }
+    }
+
+    Json(alarms).into_response()
+}
+
+/// Handler for GET /alarms/texts endpoint
+/// Returns the localized alarm texts by error code and the controller language
+async fn get_alarm_texts(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let state = server_state.ctx.observable_state.read();
+    Json(serde_json::json!({
+        "language": state.alarm_language,
+        "languages": state.alarm_texts.languages(),
+        "texts": state.alarm_texts.entries(),
+    }))
+}
+
+/// Handler for POST /alarms endpoint
+/// Raises an alarm and pushes MID 0071 to alarm subscribers
+async fn raise_alarm(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(payload): Json<RaiseAlarmRequest>,
+) -> impl IntoResponse {
+    let error_code = payload.error_code.trim();
+    if error_code.is_empty() || error_code.len() > 4 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "error_code must be 1 to 4 characters"
+            })),
+        )
+            .into_response();
+    }
+
+    let observable_state = &server_state.ctx.observable_state;
+    let id = observable_state.raise_alarm(error_code, &payload.description, payload.severity);
+    let alarm = observable_state.read().alarms.get(id).cloned();
+    (StatusCode::CREATED, Json(alarm)).into_response()
+}
+
+/// Handler for POST /alarms/:id/ack endpoint
+/// Acknowledges the alarm on the controller and pushes MID 0074 to alarm subscribers
+async fn acknowledge_alarm(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match server_state
+        .ctx
+        .observable_state
+        .acknowledge_alarm(id, AckSource::Http)
+    {
+        Ok(alarm) => Json(alarm).into_response(),
+        Err(e) => alarm_error_response(e),
+    }
+}
+
+/// Handler for POST /alarms/:id/clear endpoint
+async fn clear_alarm(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match server_state.ctx.observable_state.clear_alarm(id) {
+        Ok(alarm) => Json(alarm).into_response(),
+        Err(e) => alarm_error_response(e),
+    }
+}
+
+// ============================================================================
+// Operator Login
+// ============================================================================
+
+#[derive(Deserialize)]
+struct OperatorLoginRequest {
+    /// Operator badge / ID
+    operator_id: String,
+    /// Optional display name
+    name: Option<String>,
+}
+
+/// Handler for GET /operator endpoint
+/// Returns the logged-in operator and whether login is required
+async fn get_operator(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let operator = server_state.ctx.observable_state.read().operator.clone();
+    Json(serde_json::json!({
+        "logged_in": operator.is_some(),
+        "operator": operator,
+        "login_required": server_state.ctx.settings.defaults.require_operator_login
+    }))
+}
+
+/// Handler for POST /operator/login endpoint
+async fn login_operator(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(payload): Json<OperatorLoginRequest>,
+) -> impl IntoResponse {
+    let operator_id = payload.operator_id.trim().to_string();
+    if operator_id.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "operator_id must not be empty"
+            })),
+        );
+    }
+
+    println!("Operator {} logged in", operator_id);
+    server_state
+        .ctx
+        .observable_state
+        .login_operator(operator_id.clone(), payload.name);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": format!("Operator {} logged in", operator_id)
+        })),
+    )
+}
+
+/// Handler for POST /operator/logout endpoint
+async fn logout_operator(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let message = if server_state.ctx.observable_state.logout_operator() {
+        "Operator logged out"
+    } else {
+        "No operator was logged in"
+    };
+    Json(serde_json::json!({
+        "success": true,
+        "message": message
+    }))
+}
+
+// ============================================================================
+// Gateway Mode
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ExchangeQuery {
+    /// Only frames with a greater sequence number (for incremental polling)
+    since: Option<u64>,
+    /// Maximum number of (most recent) frames to return
+    limit: Option<usize>,
+}
+
+/// Handler for GET /proxy/exchanges endpoint
+/// Returns the frames recorded in gateway mode, oldest first
+async fn get_proxy_exchanges(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<ExchangeQuery>,
+) -> impl IntoResponse {
+    let ctx = &server_state.ctx;
+    let mut exchanges = ctx.exchange_log.since(query.since.unwrap_or(0));
+    if let Some(limit) = query.limit {
+        exchanges.drain(..exchanges.len().saturating_sub(limit));
+    }
+    Json(serde_json::json!({
+        "upstream": ctx.settings.proxy.upstream,
+        "count": exchanges.len(),
+        "exchanges": exchanges
+    }))
+}
+
+/// Handler for GET /debug/injection-log endpoint
+/// Returns the frames failure injection interfered with, oldest first
+async fn get_injection_log(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<ExchangeQuery>,
+) -> impl IntoResponse {
+    let mut injections = server_state
+        .ctx
+        .injection_log
+        .since(query.since.unwrap_or(0));
+    if let Some(limit) = query.limit {
+        injections.drain(..injections.len().saturating_sub(limit));
+    }
+    Json(serde_json::json!({
+        "count": injections.len(),
+        "injections": injections
+    }))
+}
+
+/// Handler for GET /debug/resources endpoint
+async fn get_resources(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    Json(ResourceReport::collect(&server_state.ctx))
+}
+
+// ============================================================================
+// Integrations
+// ============================================================================
+
+#[derive(Deserialize)]
+struct OutboxQuery {
+    /// Only entries in this state
+    status: Option<crate::outbox::OutboxStatus>,
+    /// Maximum number of (most recent) entries to return (default: 100)
+    limit: Option<usize>,
+}
+
+/// Handler for GET /integrations/outbox endpoint
+/// Returns the most recent outbox entries, newest first, and counts per state
+async fn get_outbox(
+    AxumState(server_state): AxumState<ServerState>,
+    Query(query): Query<OutboxQuery>,
+) -> impl IntoResponse {
+    let ctx = &server_state.ctx;
+    let outbox = ctx.outbox.read().unwrap();
+    let entries = outbox.list(query.status, query.limit.unwrap_or(100));
+    Json(serde_json::json!({
+        "webhook_url": ctx.settings.integrations.webhook_url,
+        "counts": outbox.counts(),
+        "entries": entries
+    }))
+}
+
+// ============================================================================
+// Tenants
+// ============================================================================
+
+/// Tenants and the routers serving their APIs (built on first use)
+#[derive(Clone)]
+struct TenantState {
+    registry: Arc<TenantRegistry>,
+    routers: Arc<std::sync::RwLock<std::collections::HashMap<String, Router>>>,
+    ui_base: String,
+}
+
+impl TenantState {
+    /// Router of tenant `name`, built on first use
+    fn router(&self, name: &str) -> Result<Router, TenantError> {
+        if let Some(router) = self.routers.read().unwrap().get(name) {
+            return Ok(router.clone());
+        }
+        let ctx = self.registry.get(name)?;
+        let router = api_router(ctx, format!("{}/tenants/{}", self.ui_base, name));
+        Ok(self
+            .routers
+            .write()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert(router)
+            .clone())
+    }
+}
+
+fn tenant_error_response(error: TenantError) -> axum::response::Response {
+    let status = match error {
+        TenantError::InvalidName(_) | TenantError::InvalidDefinition { .. } => {
+            StatusCode::BAD_REQUEST
+        }
+        TenantError::AlreadyExists(_) | TenantError::Bind { .. } => StatusCode::CONFLICT,
+        TenantError::NotFound(_) => StatusCode::NOT_FOUND,
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "error": error.to_string()
+        })),
+    )
+        .into_response()
+}
+
+/// Handler for GET /tenants endpoint
+async fn list_tenants(AxumState(tenants): AxumState<TenantState>) -> impl IntoResponse {
+    let list = tenants.registry.list();
+    Json(serde_json::json!({
+        "count": list.len(),
+        "tenants": list
+    }))
+}
+
+#[derive(Deserialize)]
+struct CreateTenantRequest {
+    name: String,
+    /// Open Protocol port; a free one is picked when absent or 0
+    tcp_port: Option<u16>,
+}
+
+/// Handler for POST /tenants endpoint
+/// Creates a namespace with its own controller state, database and TCP port
+async fn create_tenant(
+    AxumState(tenants): AxumState<TenantState>,
+    Json(payload): Json<CreateTenantRequest>,
+) -> axum::response::Response {
+    match tenants
+        .registry
+        .create(&payload.name, payload.tcp_port)
+        .await
+    {
+        Ok(tenant) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "success": true,
+                "tenant": tenant
+            })),
+        )
+            .into_response(),
+        Err(e) => tenant_error_response(e),
+    }
+}
+
+/// Handler for GET /tenants/{name} endpoint
+async fn get_tenant(
+    AxumState(tenants): AxumState<TenantState>,
+    Path(name): Path<String>,
+) -> axum::response::Response {
+    match tenants.registry.info(&name) {
+        Ok(tenant) => Json(tenant).into_response(),
+        Err(e) => tenant_error_response(e),
+    }
+}
+
+/// Handler for POST /controllers/bulk endpoint
+/// Provisions a fleet of controllers (tenants) in one call, all or none
+async fn create_controllers(
+    AxumState(tenants): AxumState<TenantState>,
+    Json(definitions): Json<Vec<ControllerDefinition>>,
+) -> axum::response::Response {
+    match tenants.registry.create_bulk(&definitions).await {
+        Ok(controllers) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "success": true,
+                "count": controllers.len(),
+                "controllers": controllers
+            })),
+        )
+            .into_response(),
+        Err(e) => tenant_error_response(e),
+    }
+}
+
+/// Handler for DELETE /controllers/{id} endpoint
+/// Stops a controller's Open Protocol port and closes its connections
+async fn delete_controller(
+    AxumState(tenants): AxumState<TenantState>,
+    Path(id): Path<String>,
+) -> axum::response::Response {
+    match tenants.registry.remove(&id) {
+        Ok(controller) => {
+            tenants.routers.write().unwrap().remove(&id);
+            Json(serde_json::json!({
+                "success": true,
+                "controller": controller
+            }))
+            .into_response()
+        }
+        Err(e) => tenant_error_response(e),
+    }
+}
+
+/// Handler for /tenants/{name}/ (the tenant's web UI)
+async fn tenant_index(
+    AxumState(tenants): AxumState<TenantState>,
+    Path(name): Path<String>,
+    request: axum::extract::Request,
+) -> axum::response::Response {
+    forward_to_tenant(&tenants, &name, "", request).await
+}
+
+/// Handler for /tenants/{name}/{*rest}: any endpoint of a tenant
+async fn tenant_api(
+    AxumState(tenants): AxumState<TenantState>,
+    Path((name, rest)): Path<(String, String)>,
+    request: axum::extract::Request,
+) -> axum::response::Response {
+    forward_to_tenant(&tenants, &name, &rest, request).await
+}
+
+/// Pass `request` to the tenant's router with the namespace prefix removed
+async fn forward_to_tenant(
+    tenants: &TenantState,
+    name: &str,
+    rest: &str,
+    request: axum::extract::Request,
+) -> axum::response::Response {
+    use tower::ServiceExt;
+
+    let router = match tenants.router(name) {
+        Ok(router) => router,
+        Err(e) => return tenant_error_response(e),
+    };
+    let (mut parts, body) = request.into_parts();
+    let path = match parts.uri.query() {
+        Some(query) => format!("/{}?{}", rest, query),
+        None => format!("/{}", rest),
+    };
+    parts.uri = match path.parse() {
+        Ok(uri) => uri,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    match router
+        .oneshot(axum::extract::Request::from_parts(parts, body))
+        .await
+    {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+// ============================================================================
+// Firmware Upgrade
+// ============================================================================
+
+#[derive(Deserialize)]
+struct FirmwareUpgradeRequest {
+    #[serde(flatten)]
+    upgrade: FirmwareUpgrade,
+    /// How long the controller stays down (milliseconds)
+    #[serde(default = "default_reboot_ms")]
+    reboot_ms: u64,
+    /// Announce the reboot this many milliseconds ahead (default: `[maintenance] notice_ms`)
+    #[serde(default)]
+    notice_ms: Option<u64>,
+}
+
+fn default_reboot_ms() -> u64 {
+    3000
+}
+
+/// Check a new identity value against the width of its MID 0002/0041 field
+fn check_field_width(name: &str, value: &str, width: usize) -> Result<(), String> {
+    if value.trim().is_empty() {
+        Err(format!("{} must not be empty", name))
+    } else if value.chars().count() > width {
+        Err(format!("{} must be at most {} characters", name, width))
+    } else {
+        Ok(())
+    }
+}
+
+/// Handler for POST /simulate/firmware-upgrade endpoint
+/// Reboots the virtual controller and brings it back with the new software
+/// version (and optionally a new controller name and supplier code)
+async fn simulate_firmware_upgrade(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(payload): Json<FirmwareUpgradeRequest>,
+) -> impl IntoResponse {
+    let upgrade = payload.upgrade;
+    let checks = [
+        Some(("software_version", upgrade.software_version.as_str(), 19)),
+        upgrade
+            .tool_software_version
+            .as_deref()
+            .map(|v| ("tool_software_version", v, 19)),
+        upgrade
+            .controller_name
+            .as_deref()
+            .map(|v| ("controller_name", v, 25)),
+        upgrade
+            .supplier_code
+            .as_deref()
+            .map(|v| ("supplier_code", v, 3)),
+    ];
+    for (name, value, width) in checks.into_iter().flatten() {
+        if let Err(error) = check_field_width(name, value, width) {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "success": false, "error": error })),
+            );
+        }
+    }
+
+    let state = server_state.ctx.observable_state.clone();
+    let reboot_ms = payload.reboot_ms;
+    let maintenance = &server_state.ctx.settings.maintenance;
+    let notice_ms = payload.notice_ms.unwrap_or(maintenance.notice_ms);
+    let conflict = || {
+        (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "Controller is already rebooting"
+            })),
+        )
+    };
+
+    let previous_version = if notice_ms > 0 {
+        let notice = MaintenanceNotice {
+            kind: MaintenanceKind::FirmwareUpgrade,
+            reason: format!("Firmware upgrade to {}", upgrade.software_version),
+            starts_in_ms: notice_ms,
+            duration_ms: reboot_ms,
+            accepting_sessions: !maintenance.refuse_new_sessions,
+        };
+        if !state.announce_maintenance(notice) {
+            return conflict();
+        }
+        state.read().software_version.clone()
+    } else {
+        match state.begin_firmware_upgrade(&upgrade.software_version, reboot_ms) {
+            Some(previous_version) => previous_version,
+            None => return conflict(),
+        }
+    };
+
+    println!(
+        "Firmware upgrade {} -> {} in {} ms, rebooting for {} ms",
+        previous_version, upgrade.software_version, notice_ms, reboot_ms
+    );
+    let software_version = upgrade.software_version.clone();
+    tokio::spawn(async move {
+        if notice_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(notice_ms)).await;
+            if state
+                .begin_firmware_upgrade(&upgrade.software_version, reboot_ms)
+                .is_none()
+            {
+                return;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(reboot_ms)).await;
+        state.finish_firmware_upgrade(upgrade);
+        println!("Controller back up");
+    });
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "success": true,
+            "previous_version": previous_version,
+            "software_version": software_version,
+            "notice_ms": notice_ms,
+            "reboot_ms": reboot_ms
+        })),
+    )
+}
+
+// ============================================================================
+// Connections
+// ============================================================================
+
+/// Handler for GET /connections endpoint
+/// Returns the connected TCP clients, their subscriptions and subscriber counts
+async fn get_connections(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let sessions = &server_state.ctx.sessions;
+    let connections = sessions.snapshot();
+    Json(serde_json::json!({
+        "count": connections.len(),
+        "subscribers": sessions.subscriber_counts(),
+        "connections": connections
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CloseConnectionRequest {
+    /// How to end the connection (default: graceful)
+    mode: Option<CloseMode>,
+}
+
+/// Handler for POST /connections/{id}/close endpoint
+/// Asks a connection to close; it is gone from GET /connections once it did
+async fn close_connection(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<SessionId>,
+    body: Option<Json<serde_json::Value>>,
+) -> impl IntoResponse {
+    let error = |status: StatusCode, error: String| {
+        (
+            status,
+            Json(serde_json::json!({ "success": false, "error": error })),
+        )
+    };
+    let request = match body.map(|Json(value)| serde_json::from_value(value)) {
+        None => CloseConnectionRequest { mode: None },
+        Some(Ok(request)) => request,
+        Some(Err(e)) => return error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    let mode = request.mode.unwrap_or(CloseMode::Graceful);
+
+    let sessions = &server_state.ctx.sessions;
+    if !sessions.contains(id) {
+        return error(
+            StatusCode::NOT_FOUND,
+            format!("Connection {} not found", id),
+        );
+    }
+    if !sessions.request_close(id, mode) {
+        return error(
+            StatusCode::CONFLICT,
+            format!("Connection {} cannot be closed by the controller", id),
+        );
+    }
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "success": true, "id": id, "mode": mode })),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObserverRequest {
+    observer: bool,
+}
+
+/// Handler for PUT /connections/{id}/observer endpoint
+/// In observer mode the client keeps its subscriptions but state-changing MIDs get MID 0004
+async fn set_connection_observer(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<SessionId>,
+    Json(request): Json<ObserverRequest>,
+) -> impl IntoResponse {
+    if !server_state.ctx.sessions.set_observer(id, request.observer) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("Connection {} not found", id)
+            })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "success": true, "id": id, "observer": request.observer })),
+    )
+}
+
+/// Handler for GET /schedules endpoint
+/// Lists the chaos schedule rules with their next run
+async fn get_schedules(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let schedules = server_state.ctx.schedules.list();
+    Json(serde_json::json!({
+        "count": schedules.len(),
+        "schedules": schedules
+    }))
+}
+
+/// Handler for DELETE /schedules/{id} endpoint
+/// Cancels a schedule rule; actions already in progress still end on time
+async fn cancel_schedule(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match server_state.ctx.schedules.cancel(id) {
+        Some(schedule) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "success": true, "schedule": schedule })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("Schedule {} not found", id)
+            })),
+        ),
+    }
+}
+
+// ============================================================================
+// Fieldbus I/O
+// ============================================================================
+
+/// Inputs and outputs of the fieldbus image
+fn fieldbus_image(observable_state: &ObservableState) -> serde_json::Value {
+    let state = observable_state.read();
+    serde_json::json!({
+        "inputs": state.fieldbus.inputs(),
+        "outputs": state.fieldbus_outputs()
+    })
+}
+
+/// Handler for GET /io/fieldbus endpoint
+async fn get_fieldbus(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    Json(fieldbus_image(&server_state.ctx.observable_state))
+}
+
+/// Handler for PUT /io/fieldbus endpoint
+/// Writes PLC inputs: new PSET bits select a PSET, a start edge runs a tightening
+async fn set_fieldbus_inputs(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(patch): Json<FieldbusInputsPatch>,
+) -> axum::response::Response {
+    let observable_state = &server_state.ctx.observable_state;
+    let (previous, inputs) = {
+        let state = observable_state.read();
+        (state.fieldbus.inputs(), state.fieldbus.patched(patch))
+    };
+
+    // Select bits must name an existing PSET before any input is taken over
+    let mut pset_name = None;
+    if inputs.pset != 0 && inputs.pset != previous.pset {
+        let repo = server_state.ctx.pset_repository.read().unwrap();
+        match repo.get_by_id(u32::from(inputs.pset)) {
+            Some(pset) => pset_name = Some(pset.name.clone()),
+            None => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({
+                        "success": false,
+                        "error": format!("PSET with id {} not found", inputs.pset)
+                    })),
+                )
+                    .into_response();
+            }
+        }
+    }
+
+    let edges = observable_state.write().fieldbus.write_inputs(inputs);
+    if edges.release {
+        observable_state.release_interlock(ReleaseSource::Fieldbus);
+    }
+    if let Some(pset) = edges.pset {
+        observable_state.set_pset(u32::from(pset), pset_name, PsetChangeSource::Fieldbus);
+    }
+    if edges.start {
+        let request = TighteningRequest {
+            torque: None,
+            angle: None,
+            ok: None,
+            metadata: TighteningMetadata::default(),
+        };
+        let response = simulate_tightening(AxumState(server_state.clone()), Json(request))
+            .await
+            .into_response();
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap_or_default();
+            let message = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|json| json["message"].as_str().map(str::to_string))
+                .unwrap_or_else(|| "tightening refused".to_string());
+            let mut image = fieldbus_image(observable_state);
+            image["success"] = false.into();
+            image["error"] = format!("Start ignored: {}", message).into();
+            return (status, Json(image)).into_response();
+        }
+    }
+
+    let mut image = fieldbus_image(observable_state);
+    image["success"] = true.into();
+    (StatusCode::OK, Json(image)).into_response()
+}
+
+// ============================================================================
+// Interlocks
+// ============================================================================
+
+/// Handler for GET /interlock endpoint
+async fn get_interlock(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "jobs": server_state.ctx.settings.interlock.jobs,
+        "waiting": server_state.ctx.observable_state.read().interlock
+    }))
+}
+
+/// Handler for POST /interlock/release endpoint
+/// Releases the job position waiting for its interlock and enables the tool
+async fn release_interlock(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    match server_state
+        .ctx
+        .observable_state
+        .release_interlock(ReleaseSource::Http)
+    {
+        Some(wait) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "success": true, "released": wait })),
+        ),
+        None => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "success": false,
+                "error": "No job position is waiting for an interlock release"
+            })),
+        ),
+    }
+}
+
+/// Request body for PUT /user-data/{slot}
+#[derive(Debug, Deserialize)]
+struct UserDataPayload {
+    data: String,
+}
+
+/// Handler for GET /user-data endpoint
+async fn get_user_data(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let slots = server_state.ctx.observable_state.read().user_data.slots();
+    Json(serde_json::json!({
+        "count": slots.len(),
+        "slots": slots
+    }))
+}
+
+/// Write or clear a user data slot, answering like the MID handlers would
+fn write_user_data(
+    server_state: &ServerState,
+    slot: u16,
+    data: String,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match server_state
+        .ctx
+        .observable_state
+        .set_user_data(slot, data.clone())
+    {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "success": true, "slot": slot, "data": data })),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "success": false, "error": e })),
+        ),
+    }
+}
+
+/// Handler for PUT /user-data/{slot} endpoint
+/// Stores user data as if entered on the controller; subscribers get MID 0242
+async fn set_user_data(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(slot): Path<u16>,
+    Json(payload): Json<UserDataPayload>,
+) -> impl IntoResponse {
+    write_user_data(&server_state, slot, payload.data)
+}
+
+/// Handler for DELETE /user-data/{slot} endpoint
+async fn clear_user_data(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(slot): Path<u16>,
+) -> impl IntoResponse {
+    write_user_data(&server_state, slot, String::new())
+}
+
+/// Parse the subscription family named in a `/throttles/{kind}` or `/ack-policies/{kind}` path
+fn parse_subscription_kind(
+    kind: &str,
+) -> Result<SubscriptionKind, (StatusCode, Json<serde_json::Value>)> {
+    serde_json::from_value(serde_json::Value::String(kind.to_string())).map_err(|_| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("Unknown subscription {:?}", kind)
+            })),
+        )
+    })
+}
+
+/// Handler for GET /throttles endpoint
+/// Lists the broadcast rate limits by subscription family
+async fn get_throttles(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let throttles = server_state.ctx.throttles.list();
+    Json(serde_json::json!({
+        "count": throttles.len(),
+        "throttles": throttles
+    }))
+}
+
+/// Handler for PUT /throttles/{kind} endpoint
+/// Caps pushes of one subscription family per client; excess is queued or coalesced
+async fn set_throttle(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(kind): Path<String>,
+    Json(rule): Json<ThrottleRule>,
+) -> impl IntoResponse {
+    let kind = match parse_subscription_kind(&kind) {
+        Ok(kind) => kind,
+        Err(error) => return error,
+    };
+    match server_state.ctx.throttles.set(kind, rule.clone()) {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "success": true, "kind": kind, "throttle": rule })),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "success": false, "error": e.to_string() })),
+        ),
+    }
+}
+
+/// Handler for DELETE /throttles/{kind} endpoint
+/// Removes a rate limit; pushes held back for it are sent right away
+async fn remove_throttle(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    let kind = match parse_subscription_kind(&name) {
+        Ok(kind) => kind,
+        Err(error) => return error,
+    };
+    if !server_state.ctx.throttles.remove(kind) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No throttle for {}", name)
+            })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "success": true, "kind": kind })),
+    )
+}
+
+/// Handler for GET /ack-policies endpoint
+/// Lists the retransmission policies by subscription family
+async fn get_ack_policies(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let policies = server_state.ctx.ack_policies.list();
+    Json(serde_json::json!({
+        "count": policies.len(),
+        "policies": policies
+    }))
+}
+
+/// Handler for PUT /ack-policies/{kind} endpoint
+/// Resends unacknowledged pushes of one subscription family, then drops,
+/// disconnects or buffers
+async fn set_ack_policy(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(kind): Path<String>,
+    Json(policy): Json<AckPolicy>,
+) -> impl IntoResponse {
+    let kind = match parse_subscription_kind(&kind) {
+        Ok(kind) => kind,
+        Err(error) => return error,
+    };
+    match server_state.ctx.ack_policies.set(kind, policy.clone()) {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "success": true, "kind": kind, "policy": policy })),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "success": false, "error": e.to_string() })),
+        ),
+    }
+}
+
+/// Handler for DELETE /ack-policies/{kind} endpoint
+/// Makes the family fire-and-forget again; pushes waiting for an ack are sent right away
+async fn remove_ack_policy(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    let kind = match parse_subscription_kind(&name) {
+        Ok(kind) => kind,
+        Err(error) => return error,
+    };
+    if !server_state.ctx.ack_policies.remove(kind) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No ack policy for {}", name)
+            })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "success": true, "kind": kind })),
+    )
+}
+
+/// Handler for GET /assertions endpoint
+/// Lists every registered assertion with its current status
+async fn list_assertions(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let assertions = server_state.ctx.assertions.list(std::time::Instant::now());
+    Json(serde_json::json!({
+        "count": assertions.len(),
+        "assertions": assertions
+    }))
+}
+
+/// Handler for POST /assertions endpoint
+/// Starts observing an expectation on the clients' traffic
+async fn create_assertion(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(spec): Json<AssertionSpec>,
+) -> impl IntoResponse {
+    match server_state
+        .ctx
+        .assertions
+        .register(spec, std::time::Instant::now())
+    {
+        Ok(id) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({ "success": true, "id": id })),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "success": false, "error": e.to_string() })),
+        ),
+    }
+}
+
+/// Handler for GET /assertions/{id} endpoint
+/// Reports pending, passed or failed with the observations behind it
+async fn get_assertion(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match server_state
+        .ctx
+        .assertions
+        .get(id, std::time::Instant::now())
+    {
+        Some(report) => (StatusCode::OK, Json(serde_json::json!(report))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No assertion {}", id)
+            })),
+        ),
+    }
+}
+
+/// Handler for DELETE /assertions/{id} endpoint
+async fn remove_assertion(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    if !server_state.ctx.assertions.remove(id) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("No assertion {}", id)
+            })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "success": true, "id": id })),
+    )
+}
+
+// ============================================================================
+// PSET Management
+// ============================================================================
+
+/// Handler for GET /psets endpoint
+/// Returns all available PSETs
+async fn get_psets(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let repo = server_state.ctx.pset_repository.read().unwrap();
+    let psets = repo.get_all();
+    Json(psets)
+}
+
+/// Handler for GET /psets/:id endpoint
+/// Returns a specific PSET by ID
+async fn get_pset_by_id(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+) -> impl IntoResponse {
+    let repo = server_state.ctx.pset_repository.read().unwrap();
+    match repo.get_by_id(id) {
+        Some(pset) => (StatusCode::OK, Json(pset)).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": format!("PSET with id {} not found", id)
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler for GET /psets/:id/history endpoint
+/// Returns the recorded changes of a PSET (oldest first) and its last-change date
+async fn get_pset_history(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+) -> impl IntoResponse {
+    let exists = server_state
+        .ctx
+        .pset_repository
+        .read()
+        .unwrap()
+        .get_by_id(id)
+        .is_some();
+    let (history, last_change) = {
+        let state = server_state.ctx.observable_state.read();
+        (state.pset_history.for_pset(id), state.last_pset_change(id))
+    };
+    // Deleted PSETs keep their history
+    if !exists && history.is_empty() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("PSET with id {} not found", id)
+            })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "pset_id": id,
+            "last_change": last_change,
+            "count": history.len(),
+            "history": history
+        })),
+    )
+}
+
+/// Most runs of one `GET /psets/{id}/simulate` request
+const MAX_PREVIEW_COUNT: usize = 100_000;
+
+#[derive(Deserialize)]
+struct PsetSimulateQuery {
+    /// Tightenings to simulate (default 1000)
+    count: Option<usize>,
+    /// Probability of a forced NOK (uses config default if not specified)
+    failure_rate: Option<f64>,
+    /// Limits replacing the PSET's own for this run
+    torque_min: Option<f64>,
+    torque_max: Option<f64>,
+    angle_min: Option<f64>,
+    angle_max: Option<f64>,
+    strategy: Option<pset::TighteningStrategy>,
+    /// Seed for a reproducible run
+    seed: Option<u64>,
+}
+
+/// Handler for GET /psets/:id/simulate endpoint
+/// Runs the tightening model for a PSET without storing or broadcasting anything
+async fn simulate_pset(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+    Query(query): Query<PsetSimulateQuery>,
+) -> impl IntoResponse {
+    let Some(mut pset) = server_state
+        .ctx
+        .pset_repository
+        .read()
+        .unwrap()
+        .get_by_id(id)
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": format!("PSET with id {} not found", id)
+            })),
+        );
+    };
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "success": false, "error": error })),
+        )
+    };
+
+    let count = query.count.unwrap_or(1000);
+    if count == 0 || count > MAX_PREVIEW_COUNT {
+        return bad_request(format!("count must be 1-{}", MAX_PREVIEW_COUNT));
+    }
+    let failure_rate = query
+        .failure_rate
+        .unwrap_or(server_state.ctx.settings.defaults.failure_rate);
+    if !(0.0..=1.0).contains(&failure_rate) {
+        return bad_request("failure_rate must be between 0.0 and 1.0".to_string());
+    }
+    pset.torque_min = query.torque_min.unwrap_or(pset.torque_min);
+    pset.torque_max = query.torque_max.unwrap_or(pset.torque_max);
+    pset.angle_min = query.angle_min.unwrap_or(pset.angle_min);
+    pset.angle_max = query.angle_max.unwrap_or(pset.angle_max);
+    pset.strategy = query.strategy.unwrap_or(pset.strategy);
+    if pset.torque_min > pset.torque_max || pset.angle_min > pset.angle_max {
+        return bad_request("Minimum limits must not exceed the maximum limits".to_string());
+    }
+
+    let params = TighteningParams::for_pset(
+        &pset,
+        server_state
+            .ctx
+            .settings
+            .defaults
+            .auto_tightening_duration_ms,
+    );
+    let mut rng = match query.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+    let report = pset_preview::simulate(&params, count, failure_rate, &mut rng);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "pset_id": id,
+            "failure_rate": failure_rate,
+            "torque_min": pset.torque_min,
+            "torque_max": pset.torque_max,
+            "angle_min": pset.angle_min,
+            "angle_max": pset.angle_max,
+            "strategy": pset.strategy,
+            "report": report
+        })),
+    )
+}
+
+/// Handler for POST /psets/:id/select endpoint
+/// Selects the specified PSET as the active parameter set
+async fn select_pset(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+) -> impl IntoResponse {
+    // Check if PSET exists
+    let pset_name = {
+        let repo = server_state.ctx.pset_repository.read().unwrap();
+        match repo.get_by_id(id) {
+            Some(pset) => pset.name.clone(),
+            None => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({
+                        "success": false,
+                        "error": format!("PSET with id {} not found", id)
+                    })),
+                )
+                    .into_response();
+            }
+        }
+    };
+
+    // Set the PSET in device state and broadcast the change
+    server_state
+        .ctx
+        .observable_state
+        .set_pset(id, Some(pset_name.clone()), PsetChangeSource::Http);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": format!("PSET {} '{}' selected", id, pset_name),
+            "pset_id": id,
+            "pset_name": pset_name
+        })),
+    )
+        .into_response()
+}
+
+/// Handler for POST /psets endpoint
+/// Creates a new PSET
+async fn create_pset(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(pset): Json<pset::Pset>,
+) -> impl IntoResponse {
+    let mut repo = server_state.ctx.pset_repository.write().unwrap();
+
+    match repo.create(pset) {
+        Ok(created_pset) => {
+            server_state.ctx.observable_state.audit(
+                AuditCategory::Configuration,
+                format!("Pset {} created", created_pset.id),
+            );
+            server_state
+                .ctx
+                .observable_state
+                .write()
+                .record_pset_change(
+                    created_pset.id,
+                    PsetChangeKind::Created,
+                    PsetChangeSource::Http,
+                );
+            (
+                StatusCode::CREATED,
+                Json(serde_json::json!({
+                    "success": true,
+                    "message": "PSET created successfully",
+                    "pset": created_pset
+                })),
+            )
+                .into_response()
+        }
+        Err(err) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": err
+            })),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct PsetImportRequest {
+    psets: Vec<pset::Pset>,
+    /// `reject` (default), `skip`, `offset` or `next_free`
+    #[serde(default)]
+    on_conflict: pset::import::IdConflict,
+    /// Added to every ID with `on_conflict = "offset"`
+    #[serde(default)]
+    offset: u32,
+}
+
+/// Handler for POST /psets/import endpoint
+/// Stores a PSET library under its own IDs, resolving collisions per `on_conflict`
+async fn import_psets(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(payload): Json<PsetImportRequest>,
+) -> impl IntoResponse {
+    let count = payload.psets.len();
+    let report = match pset::import::import(
+        &server_state.ctx.pset_repository,
+        payload.psets,
+        payload.on_conflict,
+        payload.offset,
+    ) {
+        Ok(report) => report,
+        Err(pset::import::Conflicts(ids)) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": format!("PSET ids already taken: {:?}", ids),
+                    "conflicts": ids
+                })),
+            );
+        }
+    };
+
+    {
+        let mut state = server_state.ctx.observable_state.write();
+        for id in report.mappings.iter().filter_map(|mapping| mapping.id) {
+            state.record_pset_change(id, PsetChangeKind::Created, PsetChangeSource::Http);
+        }
+    }
+    server_state.ctx.observable_state.audit(
+        AuditCategory::Configuration,
+        format!(
+            "Imported {} of {} PSETs ({} remapped)",
+            report.imported, count, report.remapped
+        ),
+    );
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": report.failed == 0,
+            "message": format!("Imported {} of {} PSETs", report.imported, count),
+            "imported": report.imported,
+            "remapped": report.remapped,
+            "skipped": report.skipped,
+            "failed": report.failed,
+            "mappings": report.mappings
+        })),
+    )
+}
+
+/// Handler for PUT /psets/:id endpoint
+/// Updates an existing PSET
+async fn update_pset(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+    Json(pset): Json<pset::Pset>,
+) -> impl IntoResponse {
+    let mut repo = server_state.ctx.pset_repository.write().unwrap();
+
+    match repo.update(id, pset) {
+        Ok(updated_pset) => {
+            server_state
+                .ctx
+                .observable_state
+                .audit(AuditCategory::Configuration, format!("Pset {} updated", id));
+            server_state
+                .ctx
+                .observable_state
+                .write()
+                .record_pset_change(id, PsetChangeKind::Updated, PsetChangeSource::Http);
+
+            // If this is the currently selected PSET, update the state
+            let current_pset_id = server_state.ctx.observable_state.read().current_pset_id;
+            if current_pset_id == Some(id) {
+                server_state.ctx.observable_state.set_pset(
+                    id,
+                    Some(updated_pset.name.clone()),
+                    PsetChangeSource::Http,
+                );
+            }
 
             (
                 StatusCode::OK,
@@ -1257,7 +4161,7 @@ async fn delete_pset(
     Path(id): Path<u32>,
 ) -> impl IntoResponse {
     // Check if this PSET is currently selected
-    let current_pset_id = server_state.observable_state.read().current_pset_id;
+    let current_pset_id = server_state.ctx.observable_state.read().current_pset_id;
     if current_pset_id == Some(id) {
         return (
             StatusCode::CONFLICT,
@@ -1269,24 +4173,176 @@ async fn delete_pset(
             .into_response();
     }
 
-    let mut repo = server_state.pset_repository.write().unwrap();
+    let mut repo = server_state.ctx.pset_repository.write().unwrap();
 
     match repo.delete(id) {
-        Ok(()) => (
-            StatusCode::OK,
+        Ok(()) => {
+            server_state
+                .ctx
+                .observable_state
+                .audit(AuditCategory::Configuration, format!("Pset {} deleted", id));
+            server_state
+                .ctx
+                .observable_state
+                .write()
+                .record_pset_change(id, PsetChangeKind::Deleted, PsetChangeSource::Http);
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "success": true,
+                    "message": "PSET deleted successfully"
+                })),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            let status = if err.contains("not found") {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+
+            (
+                status,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": err
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+// ============================================================================
+// Job Management
+// ============================================================================
+
+/// Steps of `job` whose PSET does not exist, as an error message
+fn unknown_job_psets(server_state: &ServerState, job: &job::Job) -> Option<String> {
+    let repo = server_state.ctx.pset_repository.read().unwrap();
+    let missing: Vec<String> = job
+        .steps
+        .iter()
+        .filter(|step| repo.get_by_id(step.pset_id).is_none())
+        .map(|step| step.pset_id.to_string())
+        .collect();
+    (!missing.is_empty()).then(|| format!("Unknown PSET(s): {}", missing.join(", ")))
+}
+
+/// Handler for GET /jobs endpoint
+/// Returns all jobs
+async fn get_jobs(AxumState(server_state): AxumState<ServerState>) -> impl IntoResponse {
+    let jobs = server_state.ctx.job_repository.read().unwrap().get_all();
+    Json(jobs)
+}
+
+/// Handler for GET /jobs/:id endpoint
+/// Returns a specific job by ID
+async fn get_job_by_id(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+) -> impl IntoResponse {
+    match server_state
+        .ctx
+        .job_repository
+        .read()
+        .unwrap()
+        .get_by_id(id)
+    {
+        Some(job) => (StatusCode::OK, Json(job)).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
             Json(serde_json::json!({
-                "success": true,
-                "message": "PSET deleted successfully"
+                "error": format!("Job with id {} not found", id)
             })),
         )
             .into_response(),
+    }
+}
+
+/// Handler for POST /jobs endpoint
+/// Creates a job under the ID in the body
+async fn create_job(
+    AxumState(server_state): AxumState<ServerState>,
+    Json(job): Json<job::Job>,
+) -> impl IntoResponse {
+    let result = match unknown_job_psets(&server_state, &job) {
+        Some(err) => Err(err),
+        None => server_state.ctx.job_repository.write().unwrap().create(job),
+    };
+    match result {
+        Ok(created_job) => {
+            server_state.ctx.observable_state.audit(
+                AuditCategory::Configuration,
+                format!("Job {} created", created_job.id),
+            );
+            (
+                StatusCode::CREATED,
+                Json(serde_json::json!({
+                    "success": true,
+                    "message": "Job created successfully",
+                    "job": created_job
+                })),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            let status = if err.contains("already exists") {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+            (
+                status,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": err
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Handler for PUT /jobs/:id endpoint
+/// Updates an existing job
+async fn update_job(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+    Json(job): Json<job::Job>,
+) -> impl IntoResponse {
+    let result = match unknown_job_psets(&server_state, &job) {
+        Some(err) => Err(err),
+        None => server_state
+            .ctx
+            .job_repository
+            .write()
+            .unwrap()
+            .update(id, job),
+    };
+    match result {
+        Ok(updated_job) => {
+            server_state
+                .ctx
+                .observable_state
+                .audit(AuditCategory::Configuration, format!("Job {} updated", id));
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "success": true,
+                    "message": "Job updated successfully",
+                    "job": updated_job
+                })),
+            )
+                .into_response()
+        }
         Err(err) => {
             let status = if err.contains("not found") {
                 StatusCode::NOT_FOUND
             } else {
                 StatusCode::BAD_REQUEST
             };
-
             (
                 status,
                 Json(serde_json::json!({
@@ -1298,3 +4354,157 @@ async fn delete_pset(
         }
     }
 }
+
+/// Handler for DELETE /jobs/:id endpoint
+/// Deletes a job
+async fn delete_job(
+    AxumState(server_state): AxumState<ServerState>,
+    Path(id): Path<u32>,
+) -> impl IntoResponse {
+    match server_state.ctx.job_repository.write().unwrap().delete(id) {
+        Ok(()) => {
+            server_state
+                .ctx
+                .observable_state
+                .audit(AuditCategory::Configuration, format!("Job {} deleted", id));
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "success": true,
+                    "message": "Job deleted successfully"
+                })),
+            )
+                .into_response()
+        }
+        Err(err) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "error": err
+            })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DeviceState;
+    use std::sync::RwLock;
+
+    fn server_state() -> ServerState {
+        let (broadcaster, _) = tokio::sync::broadcast::channel(16);
+        ServerState {
+            ctx: AppContext {
+                settings: Arc::new(Settings::default()),
+                observable_state: ObservableState::new(
+                    Arc::new(RwLock::new(DeviceState::new())),
+                    broadcaster.clone(),
+                ),
+                pset_repository: pset::create_default_repository(),
+                job_repository: crate::job::create_default_job_repository(),
+                result_repository: results::create_result_repository(10),
+                events: broadcaster,
+                handler_metrics: Default::default(),
+                sessions: Default::default(),
+                exchange_log: Arc::new(crate::proxy::ExchangeLog::new(10)),
+                injection_log: Default::default(),
+                broadcast_latency: Arc::new(crate::broadcast_latency::BroadcastLatency::new(
+                    Default::default(),
+                )),
+                outbox: crate::outbox::create_in_memory_outbox(),
+                resources: Default::default(),
+                schedules: Default::default(),
+                throttles: Default::default(),
+                assertions: Default::default(),
+                ack_policies: Default::default(),
+                counters: crate::counters::create_in_memory_counter_store(),
+                user_data: crate::user_data::create_in_memory_user_data_store(),
+            },
+            auto_tightening_active: Arc::new(AtomicBool::new(false)),
+            firehose: Arc::new(FirehoseStatus::new()),
+        }
+    }
+
+    async fn command(state: &ServerState, text: &str) -> serde_json::Value {
+        serde_json::from_str(&handle_ws_command(state, text).await).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ws_ping_still_answers_pong() {
+        let reply = command(
+            &server_state(),
+            r#"{"type":"ping","timestamp":1700000000000}"#,
+        )
+        .await;
+        assert_eq!(reply["type"], "pong");
+    }
+
+    #[tokio::test]
+    async fn test_ws_select_pset_dispatches_and_echoes_id() {
+        let state = server_state();
+        let reply = command(&state, r#"{"type":"select_pset","id":7,"pset_id":2}"#).await;
+        assert_eq!(reply["type"], "command_result");
+        assert_eq!(reply["id"], 7);
+        assert_eq!(reply["command"], "select_pset");
+        assert_eq!(reply["status"], 200);
+        assert_eq!(state.ctx.observable_state.read().current_pset_id, Some(2));
+
+        let reply = command(&state, r#"{"type":"select_pset","pset_id":999}"#).await;
+        assert_eq!(reply["success"], false);
+        assert_eq!(reply["status"], 404);
+    }
+
+    #[tokio::test]
+    async fn test_ws_set_health_updates_failure_config() {
+        let state = server_state();
+        let reply = command(&state, r#"{"type":"set_health","connection_health":50}"#).await;
+        assert_eq!(reply["success"], true);
+        assert_eq!(
+            state
+                .ctx
+                .observable_state
+                .read()
+                .failure_config
+                .connection_health,
+            50
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ws_invalid_command_is_rejected() {
+        let state = server_state();
+        let reply = command(&state, r#"{"type":"launch_rocket","id":"x"}"#).await;
+        assert_eq!(reply["success"], false);
+        assert_eq!(reply["status"], 400);
+        assert_eq!(reply["id"], "x");
+
+        let reply = command(&state, r#"{"type":"set_health"}"#).await;
+        assert_eq!(reply["status"], 400);
+    }
+
+    #[tokio::test]
+    async fn test_auto_tightening_stops_when_http_state_is_dropped() {
+        let state = server_state();
+        let resources = Arc::clone(&state.ctx.resources);
+        let request = serde_json::from_value(serde_json::json!({
+            "interval_ms": 5,
+            "duration_ms": 5,
+            "random_alarms_per_minute": 0.0
+        }))
+        .unwrap();
+        start_auto_tightening(AxumState(state.clone()), Json(request)).await;
+        assert_eq!(resources.running("auto_tightening"), 1);
+
+        // Never stopped through the API
+        drop(state);
+        for _ in 0..200 {
+            if resources.running("auto_tightening") == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("auto-tightening kept running after its HTTP state was dropped");
+    }
+}
@@ -0,0 +1,218 @@
+//! Global or per-connection identification state
+//!
+//! An Open Protocol controller has one selected PSET and one VIN: when two
+//! masters select different PSETs, the last one wins and the other is
+//! overruled without notice. `[device] identification_scope` keeps that
+//! behavior (`global`, the default) and reports a `PsetConflict` event
+//! whenever a client selects a PSET over the one another connected client
+//! selected. With `per_connection` every client gets a sandbox instead:
+//! MID 0018 and MID 0050 only change what that client sees, and results
+//! pushed to it carry its own PSET and VIN, so test cases can share one
+//! simulator without interfering. The sandboxed selections are dispatched
+//! through the handler registry like any other MID, so the middleware layers
+//! (authorization, strictness, metrics, response delay) still apply.
+
+use crate::events::SimulatorEvent;
+use crate::handler::data::TighteningResult;
+use crate::handler::data::command_accepted::CommandAccepted;
+use crate::handler::{HandlerError, MidHandler, pset_select, vehicle_id_download};
+use crate::observable_state::ObservableState;
+use crate::protocol::{Message, Mid, Response};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::Mutex;
+
+/// Whether the selected PSET and VIN are shared by all clients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentificationScope {
+    /// One PSET and VIN for the controller, as the specification has it
+    #[default]
+    Global,
+    /// Every connection selects its own PSET and VIN
+    PerConnection,
+}
+
+/// PSET and VIN one connection selected in `per_connection` scope
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SessionIdentification {
+    pub pset_id: Option<u32>,
+    pub vin: Option<String>,
+}
+
+impl SessionIdentification {
+    /// Whether `mid` selects something that stays with the connection
+    pub fn is_selection(mid: u16) -> bool {
+        matches!(Mid(mid), Mid::SELECT_PSET | Mid::VEHICLE_ID_DOWNLOAD)
+    }
+
+    /// Take over a selection made by a [`SelectionHandler`]
+    pub fn select(&mut self, selection: &SimulatorEvent) {
+        match selection {
+            SimulatorEvent::PsetChanged { pset_id, .. } => self.pset_id = Some(*pset_id),
+            SimulatorEvent::VehicleIdChanged { vin, .. } => self.vin = Some(vin.clone()),
+            _ => {}
+        }
+    }
+
+    /// The event as this connection sees it; `None` when its own selection hides it
+    pub fn view<'a>(&self, event: &'a SimulatorEvent) -> Option<Cow<'a, SimulatorEvent>> {
+        match event {
+            SimulatorEvent::PsetChanged { .. } if self.pset_id.is_some() => None,
+            SimulatorEvent::VehicleIdChanged { .. } if self.vin.is_some() => None,
+            SimulatorEvent::TighteningCompleted { result, delivery } if self.is_sandboxed() => {
+                Some(Cow::Owned(SimulatorEvent::TighteningCompleted {
+                    result: self.apply(result),
+                    delivery: *delivery,
+                }))
+            }
+            SimulatorEvent::ResultVoided { result } if self.is_sandboxed() => {
+                Some(Cow::Owned(SimulatorEvent::ResultVoided {
+                    result: self.apply(result),
+                }))
+            }
+            _ => Some(Cow::Borrowed(event)),
+        }
+    }
+
+    fn is_sandboxed(&self) -> bool {
+        self.pset_id.is_some() || self.vin.is_some()
+    }
+
+    /// The result with this connection's PSET and VIN
+    fn apply(&self, result: &TighteningResult) -> TighteningResult {
+        let mut result = result.clone();
+        if let Some(pset_id) = self.pset_id {
+            result.pset_id = pset_id;
+        }
+        if let Some(vin) = &self.vin {
+            result.vin_number = Some(vin.clone());
+        }
+        result
+    }
+}
+
+/// MID 0018 and MID 0050 for one connection in `per_connection` scope
+///
+/// Used in place of the registered handlers with
+/// [`HandlerRegistry::handle_message_with`](crate::handler::HandlerRegistry::handle_message_with).
+/// Nothing changes for the connection until it applies the selection (the
+/// event the client would see from a real one) with
+/// [`SessionIdentification::select`]; a message the layers reject selects
+/// nothing.
+pub struct SelectionHandler<'a> {
+    state: &'a ObservableState,
+    selection: Mutex<Option<SimulatorEvent>>,
+}
+
+impl<'a> SelectionHandler<'a> {
+    pub fn new(state: &'a ObservableState) -> Self {
+        Self {
+            state,
+            selection: Mutex::new(None),
+        }
+    }
+
+    /// The selection of the handled message, if it reached the handler
+    pub fn into_selection(self) -> Option<SimulatorEvent> {
+        self.selection.into_inner().unwrap()
+    }
+}
+
+impl MidHandler for SelectionHandler<'_> {
+    fn handle(&self, message: &Message) -> Result<Response, HandlerError> {
+        let (reply, selection) = match Mid(message.mid) {
+            Mid::SELECT_PSET => {
+                let pset_id = pset_select::requested_pset(message);
+                println!(
+                    "MID 0018: Parameter set select for this connection - Pset ID: {}",
+                    pset_id
+                );
+                let event = SimulatorEvent::PsetChanged {
+                    pset_id,
+                    pset_name: format!("Pset_{}", pset_id),
+                    last_change: self.state.read().last_pset_change(pset_id),
+                };
+                let reply = Response::new(Mid::PSET_SELECTED_ACK, message.revision, Vec::new());
+                (reply, event)
+            }
+            Mid::VEHICLE_ID_DOWNLOAD => {
+                let vin = vehicle_id_download::requested_vin(message);
+                println!(
+                    "MID 0050: Vehicle ID download for this connection - VIN: {}",
+                    vin
+                );
+                let event = SimulatorEvent::VehicleIdChanged {
+                    vin,
+                    cycle_id: self.state.read().cycle_id,
+                };
+                let ack = CommandAccepted::with_mid(Mid::VEHICLE_ID_DOWNLOAD);
+                let reply = Response::from_data(Mid::COMMAND_ACCEPTED, message.revision, ack)?;
+                (reply, event)
+            }
+            _ => return Err(HandlerError::UnknownMid(message.mid)),
+        };
+        *self.selection.lock().unwrap() = Some(selection);
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::DeviceState;
+    use std::sync::{Arc, RwLock};
+
+    #[test]
+    fn test_sandbox_selection_stays_with_the_connection() {
+        let (broadcaster, mut events) = tokio::sync::broadcast::channel(16);
+        let state = ObservableState::new(Arc::new(RwLock::new(DeviceState::new())), broadcaster);
+        let mut identification = SessionIdentification::default();
+        let mut select = |message: Message| {
+            let handler = SelectionHandler::new(&state);
+            let reply = handler.handle(&message).unwrap();
+            let selection = handler.into_selection().unwrap();
+            identification.select(&selection);
+            (reply, selection)
+        };
+
+        let (reply, event) = select(Message::new(Mid::SELECT_PSET.value(), 1, b"007".to_vec()));
+        assert_eq!(reply.mid, Mid::PSET_SELECTED_ACK.value());
+        assert!(matches!(
+            event,
+            SimulatorEvent::PsetChanged { pset_id: 7, .. }
+        ));
+        select(Message::new(
+            Mid::VEHICLE_ID_DOWNLOAD.value(),
+            1,
+            b"WVW123".to_vec(),
+        ));
+        // Nothing reaches the shared state or the other clients
+        assert_ne!(state.read().current_pset_id, Some(7));
+        assert!(events.try_recv().is_err());
+
+        let result = TighteningResult::example();
+        let pushed = SimulatorEvent::TighteningCompleted {
+            result,
+            delivery: None,
+        };
+        match identification.view(&pushed).unwrap().as_ref() {
+            SimulatorEvent::TighteningCompleted { result, .. } => {
+                assert_eq!(result.pset_id, 7);
+                assert_eq!(result.vin_number.as_deref(), Some("WVW123"));
+            }
+            other => panic!("expected a result, got {:?}", other),
+        }
+        let other_selection = SimulatorEvent::PsetChanged {
+            pset_id: 2,
+            pset_name: "Pset_2".to_string(),
+            last_change: String::new(),
+        };
+        assert!(identification.view(&other_selection).is_none());
+        assert!(
+            SessionIdentification::default()
+                .view(&other_selection)
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Station interlocks between job positions
+//!
+//! On a sequenced line a station often may not start the next position of a
+//! job until another station or the PLC releases it (a part has moved, a
+//! fixture is clamped). Jobs listed in `[interlock] jobs` behave that way:
+//! after every OK tightening the tool is disabled and the controller waits
+//! for a release, either `POST /interlock/release` or a rising edge of the
+//! fieldbus `release` input. `InterlockWaiting` and `InterlockReleased`
+//! events mark both ends of the wait, so line-sequencing clients can be
+//! tested against it.
+
+use serde::{Deserialize, Serialize};
+
+/// Where a release came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseSource {
+    Http,
+    Fieldbus,
+}
+
+impl ReleaseSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReleaseSource::Http => "http",
+            ReleaseSource::Fieldbus => "fieldbus",
+        }
+    }
+}
+
+/// A job position waiting for its release
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterlockWait {
+    pub job_id: u32,
+    /// Batch counter of the completed position (0 outside batch mode); the
+    /// next one waits
+    pub position: u32,
+    /// Local time the wait began, Open Protocol format
+    pub since: String,
+}
+
+impl InterlockWait {
+    pub fn new(job_id: u32, position: u32) -> Self {
+        Self {
+            job_id,
+            position,
+            since: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Jobs: sequences of PSETs run with a batch size each
+//!
+//! A job tells the operator which programs to run in which order, e.g. four
+//! tightenings with PSET 1 followed by two with PSET 2. Jobs are managed over
+//! `/jobs` and listed to clients by MID 0031 (job ID upload). They are kept in
+//! the SQLite database with the `sqlite` feature.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteJobRepository, create_sqlite_job_repository};
+
+/// Highest job ID (4-digit field)
+pub const MAX_JOB_ID: u32 = 9999;
+
+/// Highest batch size of a job step (2-digit field)
+pub const MAX_STEP_BATCH_SIZE: u32 = 99;
+
+/// One program of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobStep {
+    pub pset_id: u32,
+    /// Tightenings to run with this PSET
+    pub batch_size: u32,
+}
+
+/// Job configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u32,
+    pub name: String,
+    /// PSETs in the order they are run
+    pub steps: Vec<JobStep>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl Job {
+    /// Check the fields before the job is stored
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id == 0 || self.id > MAX_JOB_ID {
+            return Err(format!("Job id must be 1-{}", MAX_JOB_ID));
+        }
+        if self.name.trim().is_empty() {
+            return Err("Job name must not be empty".to_string());
+        }
+        if self.steps.is_empty() {
+            return Err("A job needs at least one step".to_string());
+        }
+        if self
+            .steps
+            .iter()
+            .any(|step| step.batch_size == 0 || step.batch_size > MAX_STEP_BATCH_SIZE)
+        {
+            return Err(format!(
+                "Step batch sizes must be 1-{}",
+                MAX_STEP_BATCH_SIZE
+            ));
+        }
+        Ok(())
+    }
+
+    /// Tightenings needed to finish the job
+    pub fn total_batch_size(&self) -> u32 {
+        self.steps.iter().map(|step| step.batch_size).sum()
+    }
+}
+
+/// Repository trait for job persistence
+pub trait JobRepository: Send + Sync {
+    fn get_all(&self) -> Vec<Job>;
+    fn get_by_id(&self, id: u32) -> Option<Job>;
+    /// Store a new job under its own ID
+    fn create(&mut self, job: Job) -> Result<Job, String>;
+    fn update(&mut self, id: u32, job: Job) -> Result<Job, String>;
+    fn delete(&mut self, id: u32) -> Result<(), String>;
+}
+
+/// In-memory implementation of JobRepository
+pub struct InMemoryJobRepository {
+    jobs: Vec<Job>,
+}
+
+impl InMemoryJobRepository {
+    pub fn new() -> Self {
+        Self {
+            jobs: Self::default_jobs(),
+        }
+    }
+
+    /// Two sample jobs built from the default PSETs
+    fn default_jobs() -> Vec<Job> {
+        vec![
+            Job {
+                id: 1,
+                name: "Standard Assembly".to_string(),
+                steps: vec![
+                    JobStep {
+                        pset_id: 1,
+                        batch_size: 4,
+                    },
+                    JobStep {
+                        pset_id: 2,
+                        batch_size: 2,
+                    },
+                ],
+                description: Some("Cover screws followed by the bracket".to_string()),
+            },
+            Job {
+                id: 2,
+                name: "Heavy Assembly".to_string(),
+                steps: vec![
+                    JobStep {
+                        pset_id: 3,
+                        batch_size: 6,
+                    },
+                    JobStep {
+                        pset_id: 5,
+                        batch_size: 2,
+                    },
+                ],
+                description: Some("Frame bolts followed by the mounts".to_string()),
+            },
+        ]
+    }
+}
+
+impl Default for InMemoryJobRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobRepository for InMemoryJobRepository {
+    fn get_all(&self) -> Vec<Job> {
+        let mut jobs = self.jobs.clone();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+
+    fn get_by_id(&self, id: u32) -> Option<Job> {
+        self.jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    fn create(&mut self, job: Job) -> Result<Job, String> {
+        job.validate()?;
+        if self.jobs.iter().any(|existing| existing.id == job.id) {
+            return Err(format!("Job with id {} already exists", job.id));
+        }
+        self.jobs.push(job.clone());
+        Ok(job)
+    }
+
+    fn update(&mut self, id: u32, mut job: Job) -> Result<Job, String> {
+        job.id = id;
+        job.validate()?;
+        match self.jobs.iter_mut().find(|existing| existing.id == id) {
+            Some(existing) => {
+                *existing = job.clone();
+                Ok(job)
+            }
+            None => Err(format!("Job with id {} not found", id)),
+        }
+    }
+
+    fn delete(&mut self, id: u32) -> Result<(), String> {
+        let initial_len = self.jobs.len();
+        self.jobs.retain(|job| job.id != id);
+
+        if self.jobs.len() < initial_len {
+            Ok(())
+        } else {
+            Err(format!("Job with id {} not found", id))
+        }
+    }
+}
+
+/// Thread-safe wrapper for JobRepository
+pub type SharedJobRepository = Arc<RwLock<Box<dyn JobRepository>>>;
+
+pub fn create_default_job_repository() -> SharedJobRepository {
+    Arc::new(RwLock::new(Box::new(InMemoryJobRepository::new())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: u32, steps: &[(u32, u32)]) -> Job {
+        Job {
+            id,
+            name: format!("Job {}", id),
+            steps: steps
+                .iter()
+                .map(|&(pset_id, batch_size)| JobStep {
+                    pset_id,
+                    batch_size,
+                })
+                .collect(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(job(1, &[(1, 4), (2, 2)]).validate().is_ok());
+        assert_eq!(job(1, &[(1, 4), (2, 2)]).total_batch_size(), 6);
+        assert!(job(0, &[(1, 1)]).validate().is_err());
+        assert!(job(MAX_JOB_ID + 1, &[(1, 1)]).validate().is_err());
+        assert!(job(1, &[]).validate().is_err());
+        assert!(job(1, &[(1, 0)]).validate().is_err());
+        assert!(job(1, &[(1, MAX_STEP_BATCH_SIZE + 1)]).validate().is_err());
+    }
+
+    #[test]
+    fn test_in_memory_repository() {
+        let mut repo = InMemoryJobRepository::new();
+        assert_eq!(repo.get_all().len(), 2);
+
+        assert!(repo.create(job(1, &[(1, 1)])).is_err(), "duplicate id");
+        repo.create(job(7, &[(4, 3)])).unwrap();
+        let updated = repo.update(7, job(99, &[(4, 5)])).unwrap();
+        assert_eq!(updated.id, 7, "the path ID wins");
+        assert_eq!(repo.get_by_id(7).unwrap().steps[0].batch_size, 5);
+
+        repo.delete(7).unwrap();
+        assert!(repo.get_by_id(7).is_none());
+        assert!(repo.delete(7).is_err());
+    }
+}
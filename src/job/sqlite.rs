@@ -0,0 +1,170 @@
+//! SQLite persistence for jobs (`sqlite` feature)
+
+use super::{InMemoryJobRepository, Job, JobRepository, JobStep, SharedJobRepository};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, params};
+use std::sync::{Arc, RwLock};
+
+/// SQLite-backed job repository; the steps are stored as a JSON array
+pub struct SqliteJobRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteJobRepository {
+    /// Open the jobs table in the given database file
+    pub fn new(db_path: &str) -> Result<Self, String> {
+        let manager = SqliteConnectionManager::file(db_path);
+        let pool = Pool::new(manager).map_err(|e| format!("Failed to create pool: {}", e))?;
+
+        let repo = Self { pool };
+        repo.init_schema()?;
+        repo.seed_if_empty()?;
+        Ok(repo)
+    }
+
+    fn init_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                steps TEXT NOT NULL DEFAULT '[]',
+                description TEXT,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )
+        .map_err(|e| format!("Failed to create table: {}", e))?;
+        Ok(())
+    }
+
+    /// Seed the sample jobs into an empty table
+    fn seed_if_empty(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM jobs", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to count jobs: {}", e))?;
+        if count == 0 {
+            for job in InMemoryJobRepository::default_jobs() {
+                Self::insert(&conn, &job)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn connection(&self) -> Result<r2d2::PooledConnection<SqliteConnectionManager>, String> {
+        self.pool
+            .get()
+            .map_err(|e| format!("Failed to get connection: {}", e))
+    }
+
+    fn insert(conn: &rusqlite::Connection, job: &Job) -> Result<(), String> {
+        conn.execute(
+            "INSERT INTO jobs (id, name, steps, description) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                job.id as i64,
+                job.name,
+                Self::steps_to_json(&job.steps),
+                job.description
+            ],
+        )
+        .map_err(|e| format!("Failed to create job: {}", e))?;
+        Ok(())
+    }
+
+    fn row_to_job(row: &rusqlite::Row) -> rusqlite::Result<Job> {
+        Ok(Job {
+            id: row.get::<_, i64>(0)? as u32,
+            name: row.get(1)?,
+            steps: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+            description: row.get(3)?,
+        })
+    }
+
+    fn steps_to_json(steps: &[JobStep]) -> String {
+        serde_json::to_string(steps).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+impl JobRepository for SqliteJobRepository {
+    fn get_all(&self) -> Vec<Job> {
+        let result = self.connection().and_then(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT id, name, steps, description FROM jobs ORDER BY id")
+                .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+            stmt.query_map([], Self::row_to_job)
+                .map(|rows| rows.filter_map(Result::ok).collect())
+                .map_err(|e| format!("Query failed: {}", e))
+        });
+        result.unwrap_or_else(|e| {
+            eprintln!("Failed to load jobs: {}", e);
+            Vec::new()
+        })
+    }
+
+    fn get_by_id(&self, id: u32) -> Option<Job> {
+        let conn = self.connection().ok()?;
+        conn.query_row(
+            "SELECT id, name, steps, description FROM jobs WHERE id = ?1",
+            params![id as i64],
+            Self::row_to_job,
+        )
+        .optional()
+        .ok()
+        .flatten()
+    }
+
+    fn create(&mut self, job: Job) -> Result<Job, String> {
+        job.validate()?;
+        if self.get_by_id(job.id).is_some() {
+            return Err(format!("Job with id {} already exists", job.id));
+        }
+        let conn = self.connection()?;
+        Self::insert(&conn, &job)?;
+        Ok(job)
+    }
+
+    fn update(&mut self, id: u32, mut job: Job) -> Result<Job, String> {
+        job.id = id;
+        job.validate()?;
+        let rows_affected = self
+            .connection()?
+            .execute(
+                "UPDATE jobs SET name = ?1, steps = ?2, description = ?3,
+                 updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?4",
+                params![
+                    job.name,
+                    Self::steps_to_json(&job.steps),
+                    job.description,
+                    id as i64
+                ],
+            )
+            .map_err(|e| format!("Failed to update job: {}", e))?;
+        if rows_affected == 0 {
+            return Err(format!("Job with id {} not found", id));
+        }
+        Ok(job)
+    }
+
+    fn delete(&mut self, id: u32) -> Result<(), String> {
+        let rows_affected = self
+            .connection()?
+            .execute("DELETE FROM jobs WHERE id = ?1", params![id as i64])
+            .map_err(|e| format!("Failed to delete job: {}", e))?;
+        if rows_affected == 0 {
+            Err(format!("Job with id {} not found", id))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Open the SQLite job repository in `db_path`
+pub fn create_sqlite_job_repository(db_path: &str) -> Result<SharedJobRepository, String> {
+    Ok(Arc::new(RwLock::new(Box::new(SqliteJobRepository::new(
+        db_path,
+    )?))))
+}
@@ -0,0 +1,322 @@
+//! Event journal (`[logging.journal]`)
+//!
+//! Appends every [`SimulatorEvent`] to an NDJSON file, one object per line:
+//! `{"at": <RFC 3339>, "event": {...}}`. With `frames` enabled the raw frames
+//! of every Open Protocol connection are journaled too, as
+//! `{"at", "session_id", "direction", "frame"}`. Events the journal fell
+//! behind on are recorded as `{"at", "missed": <count>}`, so gaps are visible.
+//!
+//! The file is rotated once it reaches `max_bytes` or is `max_age_secs` old:
+//! it is renamed to `<stem>-<YYYYMMDDTHHMMSSmmm>.<ext>` next to itself and
+//! only the newest `max_files` rotated files are kept.
+
+use crate::codec::frame_tap::{FRAME_TAP_CAPACITY, FrameDirection, TappedFrame};
+use crate::config::JournalConfig;
+use crate::context::AppContext;
+use crate::events::SimulatorEvent;
+use crate::session_registry::SessionId;
+use serde_json::json;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+
+/// Timestamp format of rotated file names
+const ROTATED_FORMAT: &str = "%Y%m%dT%H%M%S%3f";
+
+/// Length of a formatted [`ROTATED_FORMAT`] timestamp
+const ROTATED_LEN: usize = 18;
+
+/// Append-only NDJSON file with size- and age-based rotation
+pub struct Journal {
+    path: PathBuf,
+    max_bytes: u64,
+    max_age: Option<Duration>,
+    max_files: usize,
+    file: File,
+    size: u64,
+    opened_at: Instant,
+}
+
+impl Journal {
+    /// Open (or continue) the journal at `path`, creating its directory
+    pub fn open(path: &Path, config: &JournalConfig) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes: config.max_bytes,
+            max_age: (config.max_age_secs > 0).then(|| Duration::from_secs(config.max_age_secs)),
+            max_files: config.max_files,
+            file,
+            size,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Append one entry as a line, rotating first if it is due
+    pub fn append(&mut self, entry: &serde_json::Value) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        if self.rotation_due(line.len() as u64) {
+            self.rotate()?;
+        }
+        self.file.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Whether the file must be rotated before `incoming` more bytes
+    fn rotation_due(&self, incoming: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_big = self.max_bytes > 0 && self.size + incoming > self.max_bytes;
+        let too_old = self
+            .max_age
+            .is_some_and(|max_age| self.opened_at.elapsed() >= max_age);
+        too_big || too_old
+    }
+
+    /// Move the current file aside, start a new one and drop the oldest rotated files
+    fn rotate(&mut self) -> io::Result<()> {
+        let (stem, ext) = self.name_parts();
+        let stamp = chrono::Local::now().format(ROTATED_FORMAT);
+        let rotated = self
+            .path
+            .with_file_name(format!("{}-{}{}", stem, stamp, ext));
+        std::fs::rename(&self.path, &rotated)?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.opened_at = Instant::now();
+        if self.max_files > 0 {
+            let mut rotated_files = self.rotated_files()?;
+            rotated_files.sort();
+            let excess = rotated_files.len().saturating_sub(self.max_files);
+            for old in &rotated_files[..excess] {
+                std::fs::remove_file(old)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rotated files of this journal, in no particular order
+    pub fn rotated_files(&self) -> io::Result<Vec<PathBuf>> {
+        let (stem, ext) = self.name_parts();
+        let prefix = format!("{}-", stem);
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Only our own timestamps, not e.g. a tenant's `<stem>-<name>` journal
+            let is_rotated = name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(ext.as_str()))
+                .is_some_and(|stamp| {
+                    stamp.len() == ROTATED_LEN
+                        && stamp
+                            .char_indices()
+                            .all(|(i, c)| if i == 8 { c == 'T' } else { c.is_ascii_digit() })
+                });
+            if is_rotated {
+                files.push(self.path.with_file_name(name));
+            }
+        }
+        Ok(files)
+    }
+
+    /// File stem and extension (with its dot, or empty)
+    fn name_parts(&self) -> (String, String) {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let ext = self
+            .path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        (stem, ext)
+    }
+}
+
+/// One raw frame on its way to the journal
+struct JournalFrame {
+    session_id: SessionId,
+    direction: FrameDirection,
+    frame: String,
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false)
+}
+
+/// Pass the frames of one connection to the journal until it closes
+async fn forward_frames(
+    session_id: SessionId,
+    mut tap: broadcast::Receiver<TappedFrame>,
+    journal: mpsc::Sender<JournalFrame>,
+) {
+    loop {
+        match tap.recv().await {
+            Ok(frame) => {
+                let frame = JournalFrame {
+                    session_id,
+                    direction: frame.direction,
+                    frame: String::from_utf8_lossy(&frame.bytes).into_owned(),
+                };
+                if journal.send(frame).await.is_err() {
+                    break;
+                }
+            }
+            Err(RecvError::Lagged(missed)) => {
+                eprintln!("Journal missed {} frames of session {}", missed, session_id);
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Start journaling the events (and frames) of `ctx` if `[logging.journal]` sets a path
+pub fn spawn_journal(ctx: &AppContext) -> Option<AbortHandle> {
+    let config = ctx.settings.logging.journal.clone();
+    let path = config.path.clone()?;
+    let mut journal = match Journal::open(&path, &config) {
+        Ok(journal) => journal,
+        Err(e) => {
+            eprintln!("Journal disabled: cannot open {:?}: {}", path, e);
+            return None;
+        }
+    };
+    println!("Journaling events to {:?}", path);
+
+    let mut events = ctx.events.subscribe();
+    let sessions = ctx.sessions.clone();
+    let (frame_tx, mut frames) = mpsc::channel::<JournalFrame>(FRAME_TAP_CAPACITY);
+    let task = ctx.resources.track("journal");
+    let handle = tokio::spawn(async move {
+        let _task = task;
+        loop {
+            let entry = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        if config.frames
+                            && let SimulatorEvent::ClientConnected { session_id, .. } = &event
+                        {
+                            // Frames sent before the journal sees the connection are not journaled
+                            if let Some(tap) = sessions.tap_frames(*session_id) {
+                                tokio::spawn(forward_frames(*session_id, tap, frame_tx.clone()));
+                            }
+                        }
+                        json!({"at": now(), "event": event})
+                    }
+                    Err(RecvError::Lagged(missed)) => json!({"at": now(), "missed": missed}),
+                    Err(RecvError::Closed) => break,
+                },
+                Some(frame) = frames.recv() => json!({
+                    "at": now(),
+                    "session_id": frame.session_id,
+                    "direction": frame.direction,
+                    "frame": frame.frame,
+                }),
+            };
+            if let Err(e) = journal.append(&entry) {
+                eprintln!("Failed to write journal {:?}: {}", path, e);
+            }
+        }
+    });
+    Some(handle.abort_handle())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_max_files() {
+        let dir = temp_dir("journal_rotation_test");
+        let path = dir.join("events.ndjson");
+        // A tenant's journal next to ours is not one of our rotated files
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("events-team-a.ndjson"), "").unwrap();
+
+        let config = JournalConfig {
+            path: Some(path.clone()),
+            max_bytes: 100,
+            max_files: 2,
+            ..JournalConfig::default()
+        };
+        let mut journal = Journal::open(&path, &config).unwrap();
+        let entry = json!({"event": "x".repeat(30)});
+        for _ in 0..8 {
+            journal.append(&entry).unwrap();
+            // Rotated names have millisecond resolution
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        assert_eq!(journal.rotated_files().unwrap().len(), 2);
+        assert!(dir.join("events-team-a.ndjson").exists());
+        let current = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(current.lines().count(), 2, "two lines fit in 100 bytes");
+        for line in current.lines() {
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(line).unwrap(),
+                entry
+            );
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_journals_events() {
+        let dir = temp_dir("journal_events_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut settings = crate::config::Settings::default();
+        settings.database.path = dir.join("simulator.db");
+        settings.logging.journal.path = Some(dir.join("events.ndjson"));
+        let ctx = AppContext::new(settings);
+        spawn_journal(&ctx);
+
+        ctx.observable_state
+            .broadcast(SimulatorEvent::ToolStateChanged { enabled: false });
+        let mut lines = Vec::new();
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            lines = std::fs::read_to_string(dir.join("events.ndjson"))
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .collect();
+            if !lines.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["event"]["type"], "ToolStateChanged");
+        assert_eq!(lines[0]["event"]["enabled"], false);
+        assert!(lines[0]["at"].is_string());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,127 @@
+//! Keep-alive accounting and controller-side probing
+//!
+//! Not every integration implements MID 9999 symmetrically: some clients
+//! send keep-alives and expect an echo, others wait for the controller to
+//! probe them. Each connection keeps a [`KeepAliveTracker`] that tells the
+//! two apart: a MID 9999 arriving while a probe of the controller is
+//! outstanding is the answer to that probe, any other one is a client
+//! keep-alive. `[keep_alive]` delays or suppresses the echo and enables
+//! probing; the counters show up per connection in `GET /connections`.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// What a received MID 9999 was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepAliveKind {
+    /// Keep-alive sent by the client; answered with an echo
+    ClientPing,
+    /// Answer to a probe of the controller; not echoed
+    ProbeReply,
+}
+
+/// Keep-alive counters of one connection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct KeepAliveStats {
+    /// Keep-alives sent by the client
+    pub received: u64,
+    /// Echoes sent for them
+    pub replied: u64,
+    /// When the last client keep-alive arrived (Open Protocol timestamp format)
+    pub last_received_at: Option<String>,
+    /// Longest time between two client keep-alives
+    pub max_gap_ms: u64,
+    /// Probes sent by the controller
+    pub probes_sent: u64,
+    pub probes_answered: u64,
+    /// Probes still unanswered when the next one was due
+    pub probes_missed: u64,
+    /// Round trip of the last answered probe
+    pub last_round_trip_ms: Option<u64>,
+    pub max_round_trip_ms: u64,
+}
+
+/// Keep-alive state of one connection
+#[derive(Debug, Default)]
+pub struct KeepAliveTracker {
+    stats: KeepAliveStats,
+    last_ping: Option<Instant>,
+    probe_sent: Option<Instant>,
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+impl KeepAliveTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account for a MID 9999 received at `now`
+    pub fn on_keep_alive(&mut self, now: Instant) -> KeepAliveKind {
+        if let Some(sent) = self.probe_sent.take() {
+            let round_trip = millis(now.saturating_duration_since(sent));
+            self.stats.probes_answered += 1;
+            self.stats.last_round_trip_ms = Some(round_trip);
+            self.stats.max_round_trip_ms = self.stats.max_round_trip_ms.max(round_trip);
+            return KeepAliveKind::ProbeReply;
+        }
+        if let Some(last) = self.last_ping {
+            let gap = millis(now.saturating_duration_since(last));
+            self.stats.max_gap_ms = self.stats.max_gap_ms.max(gap);
+        }
+        self.last_ping = Some(now);
+        self.stats.received += 1;
+        self.stats.last_received_at =
+            Some(chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string());
+        KeepAliveKind::ClientPing
+    }
+
+    /// The echo of a client keep-alive was sent
+    pub fn on_reply_sent(&mut self) {
+        self.stats.replied += 1;
+    }
+
+    /// The controller sent a probe at `now`
+    pub fn on_probe_sent(&mut self, now: Instant) {
+        if self.probe_sent.replace(now).is_some() {
+            self.stats.probes_missed += 1;
+        }
+        self.stats.probes_sent += 1;
+    }
+
+    pub fn stats(&self) -> &KeepAliveStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pings_and_probes_are_told_apart() {
+        let start = Instant::now();
+        let mut tracker = KeepAliveTracker::new();
+
+        assert_eq!(tracker.on_keep_alive(start), KeepAliveKind::ClientPing);
+        tracker.on_reply_sent();
+        let later = start + Duration::from_millis(1500);
+        assert_eq!(tracker.on_keep_alive(later), KeepAliveKind::ClientPing);
+
+        tracker.on_probe_sent(later);
+        tracker.on_probe_sent(later + Duration::from_millis(100));
+        let answer = later + Duration::from_millis(140);
+        assert_eq!(tracker.on_keep_alive(answer), KeepAliveKind::ProbeReply);
+
+        let stats = tracker.stats();
+        assert_eq!(stats.received, 2);
+        assert_eq!(stats.replied, 1);
+        assert_eq!(stats.max_gap_ms, 1500);
+        assert_eq!(stats.probes_sent, 2);
+        assert_eq!(stats.probes_missed, 1);
+        assert_eq!(stats.probes_answered, 1);
+        assert_eq!(stats.last_round_trip_ms, Some(40));
+    }
+}
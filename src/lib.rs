@@ -1,22 +1,71 @@
 // Library exports for integration testing
+pub mod ack_policy;
+pub mod alarm_generator;
+pub mod alarm_texts;
+pub mod alarms;
+pub mod assertions;
+pub mod audit;
 pub mod batch_manager;
+pub mod broadcast_latency;
 pub mod codec;
+pub mod compare;
 pub mod config;
+pub mod conformance;
+pub mod context;
+pub mod counters;
+pub mod curve;
+pub mod cycle_time;
+pub mod delay_queue;
 pub mod device_fsm;
+pub mod disconnects;
 pub mod events;
 pub mod failure_simulator;
+pub mod fieldbus;
+pub mod generator;
+pub mod greeting;
 pub mod handler;
+#[cfg(feature = "http")]
 pub mod http_server;
+pub mod identification;
+pub mod interlock;
+pub mod job;
+pub mod journal;
+pub mod keep_alive;
+pub mod lifetime;
 pub mod multi_spindle;
 pub mod observable_state;
+pub mod outbox;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+pub mod persistence;
 pub mod protocol;
+pub mod proxy;
 pub mod pset;
+pub mod pset_history;
+pub mod pset_preview;
+pub mod resources;
+pub mod results;
+pub mod schedules;
+pub mod self_test;
+pub mod service;
 pub mod session;
+pub mod session_registry;
 pub mod state;
 pub mod subscriptions;
+pub mod telemetry;
+pub mod tenants;
+pub mod testing;
+pub mod throttle;
 pub mod tightening_tracker;
+pub mod user_data;
+pub mod vin_history;
+#[cfg(feature = "http")]
+pub mod web_ui;
+pub mod webhook;
+pub mod write_batch;
 
 // Re-export commonly used types
+pub use context::AppContext;
 pub use events::SimulatorEvent;
 pub use observable_state::ObservableState;
 pub use state::DeviceState;
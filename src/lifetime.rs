@@ -0,0 +1,242 @@
+//! Lifetime tool statistics
+//!
+//! Counts every tightening the simulated tool performs (OK/NOK), the time the
+//! motor ran and an estimate of the energy used, the way a controller keeps
+//! tool utilization counters across power cycles. The counters are saved as
+//! JSON under `[stats] lifetime_path`, reported by `GET /stats/lifetime` and
+//! by the tool data upload (MID 0041). The service counter restarts from zero
+//! with `POST /tool/service-reset`.
+
+use crate::context::AppContext;
+use crate::persistence::{self, Persister};
+use crate::state::DeviceState;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::AbortHandle;
+
+/// Share of the electrical energy that ends up as work on the joint
+const MOTOR_EFFICIENCY: f64 = 0.5;
+
+/// Power drawn by the idle motor electronics while running down (W)
+const RUNDOWN_POWER_W: f64 = 15.0;
+
+/// Failure while loading or saving the statistics file
+#[derive(Debug, Error)]
+pub enum LifetimeStatsError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid statistics file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Counters kept for the lifetime of the simulated tool
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LifetimeStats {
+    pub total_tightenings: u64,
+    pub ok_tightenings: u64,
+    pub nok_tightenings: u64,
+    /// Accumulated rundown time in milliseconds
+    pub tool_runtime_ms: u64,
+    /// Estimated electrical energy in joules
+    pub energy_j: f64,
+    /// Tightenings since the last tool service
+    #[serde(default)]
+    pub tightenings_since_service: u64,
+    /// Local time of the last tool service, Open Protocol format (None = never)
+    #[serde(default)]
+    pub last_service_at: Option<String>,
+    /// Set whenever the counters change and cleared once saved
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl LifetimeStats {
+    /// Count one tightening
+    ///
+    /// The energy estimate is the work on the joint (torque rising roughly
+    /// linearly over the final angle, so half of torque × angle) divided by
+    /// the motor efficiency, plus the electronics' draw over the rundown.
+    pub fn record_tightening(&mut self, ok: bool, torque: f64, angle: f64, duration_ms: u64) {
+        self.total_tightenings += 1;
+        self.tightenings_since_service += 1;
+        if ok {
+            self.ok_tightenings += 1;
+        } else {
+            self.nok_tightenings += 1;
+        }
+        self.tool_runtime_ms += duration_ms;
+        let work = 0.5 * torque.abs() * angle.abs().to_radians();
+        self.energy_j += work / MOTOR_EFFICIENCY + RUNDOWN_POWER_W * duration_ms as f64 / 1000.0;
+        self.dirty = true;
+    }
+
+    /// Restart the service counter; returns the tightenings it had counted
+    pub fn reset_service(&mut self, at: String) -> u64 {
+        self.last_service_at = Some(at);
+        self.dirty = true;
+        std::mem::take(&mut self.tightenings_since_service)
+    }
+
+    pub fn tool_runtime_secs(&self) -> f64 {
+        self.tool_runtime_ms as f64 / 1000.0
+    }
+
+    pub fn energy_wh(&self) -> f64 {
+        self.energy_j / 3600.0
+    }
+
+    /// Share of NOK tightenings (0.0 when nothing was tightened yet)
+    pub fn nok_rate(&self) -> f64 {
+        if self.total_tightenings == 0 {
+            0.0
+        } else {
+            self.nok_tightenings as f64 / self.total_tightenings as f64
+        }
+    }
+
+    /// Load saved counters; a missing file starts from zero
+    pub fn load(path: &Path) -> Result<Self, LifetimeStatsError> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the counters atomically (temp file + rename)
+    pub fn save(&self, path: &Path) -> Result<(), LifetimeStatsError> {
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Whether the counters changed since the last `mark_saved`
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+}
+
+/// Save the counters every `[stats] save_interval_secs` while they change
+pub fn spawn_persistence(ctx: &AppContext) -> Option<AbortHandle> {
+    let config = ctx.settings.stats.clone();
+    if config.save_interval_secs == 0 {
+        return None;
+    }
+    Some(persistence::spawn_persistence(
+        ctx,
+        LifetimePersister::new(ctx),
+        Duration::from_secs(config.save_interval_secs),
+    ))
+}
+
+/// Save the counters once more on shutdown, unless persistence is disabled
+pub fn save_on_shutdown(ctx: &AppContext) {
+    let config = &ctx.settings.stats;
+    if config.save_interval_secs > 0 {
+        persistence::save_changes(&mut LifetimePersister::new(ctx));
+    }
+}
+
+/// Saves the statistics file when the counters are dirty
+struct LifetimePersister {
+    state: Arc<RwLock<DeviceState>>,
+    path: PathBuf,
+}
+
+impl LifetimePersister {
+    fn new(ctx: &AppContext) -> Self {
+        Self {
+            state: Arc::clone(ctx.observable_state.state()),
+            path: ctx.settings.stats.lifetime_path.clone(),
+        }
+    }
+}
+
+impl Persister for LifetimePersister {
+    const NAME: &'static str = "lifetime statistics";
+
+    fn save_changes(&mut self) -> Result<(), String> {
+        let snapshot = {
+            let mut state = self.state.write().unwrap();
+            if !state.lifetime_stats.is_dirty() {
+                return Ok(());
+            }
+            state.lifetime_stats.mark_saved();
+            state.lifetime_stats.clone()
+        };
+        snapshot
+            .save(&self.path)
+            .map_err(|e| format!("{:?}: {}", self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_and_energy() {
+        let mut stats = LifetimeStats::default();
+        stats.record_tightening(true, 40.0, 180.0, 500);
+        stats.record_tightening(false, 20.0, 90.0, 1500);
+
+        assert_eq!(stats.total_tightenings, 2);
+        assert_eq!((stats.ok_tightenings, stats.nok_tightenings), (1, 1));
+        assert_eq!(stats.tool_runtime_secs(), 2.0);
+        assert_eq!(stats.nok_rate(), 0.5);
+        // 0.5 * 40 * pi / 0.5 + 0.5 * 20 * pi/2 / 0.5 + 15 W * 2 s
+        let expected = 40.0 * std::f64::consts::PI + 10.0 * std::f64::consts::PI + 30.0;
+        assert!((stats.energy_j - expected).abs() < 1e-9);
+        assert!(stats.is_dirty());
+    }
+
+    #[test]
+    fn test_service_reset_keeps_lifetime_counters() {
+        let mut stats = LifetimeStats::default();
+        stats.record_tightening(true, 40.0, 180.0, 500);
+        stats.record_tightening(true, 40.0, 180.0, 500);
+        stats.mark_saved();
+
+        assert_eq!(stats.reset_service("2025-01-15:10:00:00".to_string()), 2);
+        assert_eq!(stats.tightenings_since_service, 0);
+        assert_eq!(stats.total_tightenings, 2);
+        assert_eq!(
+            stats.last_service_at.as_deref(),
+            Some("2025-01-15:10:00:00")
+        );
+        assert!(stats.is_dirty());
+
+        stats.record_tightening(false, 40.0, 180.0, 500);
+        assert_eq!(stats.tightenings_since_service, 1);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("lifetime_stats_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            LifetimeStats::load(&path).unwrap(),
+            LifetimeStats::default()
+        );
+
+        let mut stats = LifetimeStats::default();
+        stats.record_tightening(true, 10.0, 45.0, 300);
+        stats.save(&path).unwrap();
+        let loaded = LifetimeStats::load(&path).unwrap();
+        assert_eq!(loaded.total_tightenings, 1);
+        assert_eq!(loaded.tool_runtime_ms, 300);
+        assert!((loaded.energy_j - stats.energy_j).abs() < 1e-9);
+        assert!(!loaded.is_dirty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
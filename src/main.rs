@@ -1,28 +1,50 @@
 use futures_util::sink::SinkExt;
 use futures_util::stream::StreamExt;
+#[cfg(feature = "http")]
+use open_protocol_device_simulator::http_server;
 use open_protocol_device_simulator::{
-    codec, config, events, failure_simulator, handler, http_server, observable_state, protocol,
-    session, state,
+    AppContext, ack_policy, audit, broadcast_latency, codec, compare, config, conformance,
+    counters, cycle_time, delay_queue, disconnects, events, failure_simulator, greeting, handler,
+    identification, journal, keep_alive, lifetime, observable_state, protocol, proxy, resources,
+    schedules, self_test, service, session, session_registry, subscriptions, telemetry, throttle,
+    user_data, webhook, write_batch,
 };
+use std::path::Path;
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::io::AsyncWriteExt;
 
+use audit::AuditCategory;
+use codec::frame_tap::FrameDirection;
 use config::Settings;
 use events::SimulatorEvent;
-use failure_simulator::FailureSimulator;
+use failure_simulator::{FailureSimulator, Injection, InjectionLog, InjectionTrace, LinkShaper};
+use handler::UnknownMidPolicy;
+use identification::IdentificationScope;
 use observable_state::ObservableState;
-use state::DeviceState;
+use protocol::Mid;
+use protocol::mid::{SubscriptionKind, SubscriptionRequest};
+use resources::ResourceMonitor;
+use session_registry::{
+    CloseMode, DeliveryId, DeliveryStatus, DisconnectReason, SessionId, SessionRegistry,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::AbortHandle;
+use write_batch::WriteBatcher;
 
-/// Send a message with failure injection
+/// Send a message with failure injection; without `flush` it is only queued in the write buffer
 /// Returns Ok(true) if message was sent, Ok(false) if dropped, Err if connection should close
 async fn send_with_failure_injection(
     framed: &mut tokio_util::codec::Framed<
         tokio::net::TcpStream,
         codec::null_delimited_codec::NullDelimitedCodec,
     >,
+    shaper: &mut LinkShaper,
     message_bytes: Vec<u8>,
     observable_state: &ObservableState,
+    injection_log: &InjectionLog,
     context: &str,
+    flush: bool,
 ) -> Result<bool, std::io::Error> {
     // Read failure config from device state
     let failure_config = {
@@ -32,45 +54,46 @@ async fn send_with_failure_injection(
 
     // Check if failure injection is enabled
     if !failure_config.enabled {
-        return framed
-            .send(message_bytes.as_slice().into())
-            .await
-            .map(|_| true);
-    }
-
-    // Make all random decisions first (before any awaits to avoid Send issues with ThreadRng)
-    let (should_disconnect, should_drop, delay, should_corrupt, bytes_to_send) = {
-        let mut simulator = FailureSimulator::new(failure_config.clone());
-
-        // Make all decisions
-        let disconnect = simulator.should_disconnect();
-        let drop_packet = simulator.should_drop_packet();
-        let delay = simulator.get_delay();
-        let corrupt = simulator.should_corrupt_message();
-
-        let bytes = if corrupt {
-            simulator.corrupt_message(&message_bytes)
+        let frame = message_bytes.as_slice().into();
+        let sent = if flush {
+            framed.send(frame).await
         } else {
-            message_bytes
+            framed.feed(frame).await
         };
+        return sent.map(|_| true);
+    }
 
-        // Drop simulator here (before any awaits)
-        (disconnect, drop_packet, delay, corrupt, bytes)
-    };
+    let bandwidth = failure_config.active_bandwidth();
 
-    // Now handle the decisions (simulator is dropped, safe to await)
-    if should_disconnect {
-        println!("[FAILURE INJECTION] Force disconnect during: {}", context);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::ConnectionAborted,
-            "Simulated connection drop",
-        ));
-    }
+    // Make all random decisions first (before any awaits to avoid Send issues with ThreadRng)
+    let injection = FailureSimulator::new(failure_config).decide(message_bytes.clone());
+    let trace = framed
+        .get_ref()
+        .peer_addr()
+        .ok()
+        .and_then(|client| InjectionTrace::new(client, &message_bytes, context, &injection));
 
-    if should_drop {
-        println!("[FAILURE INJECTION] Packet dropped: {}", context);
-        return Ok(false);
-    }
+    // Now handle the decisions (simulator is dropped, safe to await)
+    let (bytes_to_send, delay, corrupted) = match injection {
+        Injection::Disconnect => {
+            println!("[FAILURE INJECTION] Force disconnect during: {}", context);
+            injection_log.finish(trace, false);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "Simulated connection drop",
+            ));
+        }
+        Injection::Drop => {
+            println!("[FAILURE INJECTION] Packet dropped: {}", context);
+            injection_log.finish(trace, false);
+            return Ok(false);
+        }
+        Injection::Send {
+            bytes,
+            delay,
+            corrupted,
+        } => (bytes, delay, corrupted),
+    };
 
     if delay.as_millis() > 0 {
         println!(
@@ -81,70 +104,786 @@ async fn send_with_failure_injection(
         tokio::time::sleep(delay).await;
     }
 
-    if should_corrupt {
+    if corrupted {
         println!("[FAILURE INJECTION] Corrupting message: {}", context);
     }
 
-    framed.send(bytes_to_send.as_slice().into()).await?;
-    Ok(true)
+    // A slow link holds the frame until the bytes before it are transmitted
+    if let Some(limit) = bandwidth {
+        let wait = shaper.delay(limit, bytes_to_send.len() + 1, std::time::Instant::now());
+        if !wait.is_zero() {
+            println!(
+                "[FAILURE INJECTION] Link busy {}ms before: {}",
+                wait.as_millis(),
+                context
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    let frame = bytes_to_send.as_slice().into();
+    let sent = if flush {
+        framed.send(frame).await
+    } else {
+        framed.feed(frame).await
+    };
+    injection_log.finish(trace, sent.is_ok());
+    sent.map(|_| true)
+}
+
+/// Send a response with failure injection, split into parts if it does not fit one frame
+/// Returns Ok(true) if every part was sent, Ok(false) if a part was dropped or the data is too large
+async fn send_response(
+    framed: &mut tokio_util::codec::Framed<
+        tokio::net::TcpStream,
+        codec::null_delimited_codec::NullDelimitedCodec,
+    >,
+    shaper: &mut LinkShaper,
+    response: &protocol::Response,
+    observable_state: &ObservableState,
+    injection_log: &InjectionLog,
+    context: &str,
+) -> Result<bool, std::io::Error> {
+    write_response(
+        framed,
+        shaper,
+        response,
+        observable_state,
+        injection_log,
+        context,
+        true,
+    )
+    .await
+}
+
+/// Queue a broadcast like [`send_response`] without flushing it; see [`write_batch`]
+async fn queue_response(
+    framed: &mut tokio_util::codec::Framed<
+        tokio::net::TcpStream,
+        codec::null_delimited_codec::NullDelimitedCodec,
+    >,
+    shaper: &mut LinkShaper,
+    response: &protocol::Response,
+    observable_state: &ObservableState,
+    injection_log: &InjectionLog,
+    context: &str,
+) -> Result<bool, std::io::Error> {
+    write_response(
+        framed,
+        shaper,
+        response,
+        observable_state,
+        injection_log,
+        context,
+        false,
+    )
+    .await
+}
+
+async fn write_response(
+    framed: &mut tokio_util::codec::Framed<
+        tokio::net::TcpStream,
+        codec::null_delimited_codec::NullDelimitedCodec,
+    >,
+    shaper: &mut LinkShaper,
+    response: &protocol::Response,
+    observable_state: &ObservableState,
+    injection_log: &InjectionLog,
+    context: &str,
+    flush: bool,
+) -> Result<bool, std::io::Error> {
+    let parts = match protocol::serializer::serialize_parts(response) {
+        Ok(parts) => parts,
+        Err(e) => {
+            eprintln!("Cannot send {}: {e}", context);
+            return Ok(false);
+        }
+    };
+    let mut all_sent = true;
+    for part in parts {
+        all_sent &= send_with_failure_injection(
+            framed,
+            shaper,
+            part,
+            observable_state,
+            injection_log,
+            context,
+            flush,
+        )
+        .await?;
+    }
+    Ok(all_sent)
+}
+
+/// Write the queued broadcasts of a connection and record the write
+async fn flush_writes(
+    framed: &mut tokio_util::codec::Framed<
+        tokio::net::TcpStream,
+        codec::null_delimited_codec::NullDelimitedCodec,
+    >,
+    batcher: &mut WriteBatcher,
+    sessions: &SessionRegistry,
+    session_id: SessionId,
+) -> Result<(), std::io::Error> {
+    framed.flush().await?;
+    batcher.flushed(std::time::Instant::now());
+    sessions.update_writes(session_id, batcher.stats());
+    Ok(())
+}
+
+/// MID the client acknowledges a push with; none for NoAck pushes
+fn push_ack_mid(response: &protocol::Response) -> Option<Mid> {
+    if response.header.no_ack {
+        return None;
+    }
+    Mid(response.mid).info().and_then(|info| info.ack)
+}
+
+/// Report pushes dropped from an ack policy queue
+fn report_ack_drops(
+    sessions: &SessionRegistry,
+    session_id: SessionId,
+    dropped: Vec<(protocol::Response, Option<DeliveryId>)>,
+) {
+    for (_, delivery) in dropped {
+        if let Some(delivery) = delivery {
+            sessions.report_delivery(delivery, session_id, DeliveryStatus::DroppedByAckPolicy);
+        }
+    }
+}
+
+/// Count a connection lifecycle event and broadcast it
+fn emit_lifecycle(
+    resources: &ResourceMonitor,
+    observable_state: &ObservableState,
+    event: SimulatorEvent,
+) {
+    resources.record_lifecycle(&event);
+    observable_state.broadcast(event);
+}
+
+fn main() {
+    let cli = config::CliArgs::parse_args();
+    if cli.service {
+        // The service control manager starts the binary; the body re-reads the arguments
+        if let Err(e) = service::run_as_service(run_service) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let settings = config::load_config_from(&cli).expect("Failed to load configuration");
+    let Some(command) = &cli.command else {
+        // Detach before the runtime starts its worker threads
+        let pid_file = detach(&cli).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        let code = serve(settings, service::shutdown_signal());
+        drop(pid_file);
+        std::process::exit(code)
+    };
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the Tokio runtime");
+    let code = runtime.block_on(run_command(command, settings));
+    std::process::exit(code)
+}
+
+/// Run one of the tools instead of the simulator and return its exit code
+async fn run_command(command: &config::Command, settings: Settings) -> i32 {
+    match command {
+        config::Command::Compare {
+            capture,
+            ignore,
+            report,
+        } => run_compare(settings, capture, ignore, report.as_deref()),
+        config::Command::Conformance {
+            report,
+            junit,
+            step_timeout_secs,
+            ack_timeout_secs,
+            keep_alive_secs,
+        } => {
+            let config = conformance::ConformanceConfig {
+                step_timeout: std::time::Duration::from_secs(*step_timeout_secs),
+                ack_timeout: std::time::Duration::from_secs(*ack_timeout_secs),
+                keep_alive_interval: std::time::Duration::from_secs(*keep_alive_secs),
+            };
+            run_conformance(settings, &config, report.as_deref(), junit.as_deref()).await
+        }
+        config::Command::SelfTest {
+            report,
+            step_timeout_secs,
+        } => {
+            let config = self_test::SelfTestConfig {
+                step_timeout: std::time::Duration::from_secs(*step_timeout_secs),
+                ..Default::default()
+            };
+            run_self_test(settings, &config, report.as_deref()).await
+        }
+    }
+}
+
+/// Daemonize, redirect output and write the pid file as the command line asks
+fn detach(cli: &config::CliArgs) -> Result<Option<service::PidFile>, service::ServiceError> {
+    if cli.daemon {
+        service::daemonize()?;
+    }
+    if let Some(path) = &cli.log_file {
+        service::redirect_output(path)?;
+    }
+    cli.pid_file
+        .as_deref()
+        .map(service::PidFile::create)
+        .transpose()
+}
+
+/// Run the simulator until `shutdown` resolves; returns the exit code
+fn serve(settings: Settings, shutdown: impl std::future::Future<Output = ()>) -> i32 {
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the Tokio runtime");
+    let code = match runtime.block_on(serve_tcp_client(settings, shutdown)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Simulator stopped: {}", e);
+            1
+        }
+    };
+    // Connection and background tasks are cancelled rather than awaited
+    runtime.shutdown_timeout(std::time::Duration::from_secs(1));
+    code
 }
 
-#[tokio::main]
-async fn main() {
-    let settings = config::load_config().expect("Failed to load configuration");
-    serve_tcp_client(settings).await.unwrap();
+/// Body of the Windows service: the simulator until the service is stopped
+fn run_service(stop: tokio::sync::oneshot::Receiver<()>) {
+    let cli = config::CliArgs::parse_args();
+    // Without a console, a log file is the only place errors can go
+    let pid_file = match detach(&cli) {
+        Ok(pid_file) => pid_file,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    match config::load_config_from(&cli) {
+        Ok(settings) => {
+            serve(settings, async {
+                let _ = stop.await;
+            });
+        }
+        Err(e) => eprintln!("Failed to load configuration: {}", e),
+    }
+    drop(pid_file);
 }
 
-async fn serve_tcp_client(settings: Settings) -> Result<(), ServeError> {
+/// Run the conformance script against the first client that connects; the
+/// exit code is 1 when a check fails and 2 on I/O errors
+async fn run_conformance(
+    settings: Settings,
+    config: &conformance::ConformanceConfig,
+    report_path: Option<&Path>,
+    junit_path: Option<&Path>,
+) -> i32 {
     let bind_addr = format!(
         "{}:{}",
         settings.server.bind_address, settings.server.tcp_port
     );
-    let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
+    let listener = match tokio::net::TcpListener::bind(&bind_addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on {}: {}", bind_addr, e);
+            return 2;
+        }
+    };
+    println!(
+        "Conformance run: waiting for the client under test on {}",
+        bind_addr
+    );
 
-    println!("Open Protocol TCP server listening on {}", bind_addr);
+    let ctx = AppContext::new(settings);
+    let report = conformance::run(&listener, &ctx.handler_registry(), config).await;
+    print!("{}", report);
 
-    // Create device state from configuration (shared across all connections)
-    let device_state = DeviceState::new_shared_from_config(&settings.device);
+    let outputs = [
+        (
+            report_path,
+            serde_json::to_string_pretty(&report).unwrap_or_default(),
+        ),
+        (junit_path, report.to_junit()),
+    ];
+    for (path, contents) in outputs {
+        if let Some(path) = path
+            && let Err(e) = std::fs::write(path, contents)
+        {
+            eprintln!("Failed to write report to {:?}: {}", path, e);
+            return 2;
+        }
+    }
+    if report.passed() { 0 } else { 1 }
+}
 
-    // Create event broadcast channel
-    let (event_tx, _event_rx) =
-        tokio::sync::broadcast::channel::<SimulatorEvent>(settings.server.event_channel_capacity);
+/// Start the simulator on ephemeral loopback ports and run the smoke
+/// sequence against it; the exit code is 1 when a step fails and 2 when the
+/// simulator cannot start
+async fn run_self_test(
+    mut settings: Settings,
+    config: &self_test::SelfTestConfig,
+    report_path: Option<&Path>,
+) -> i32 {
+    // Keep the run away from the real database and statistics files
+    let scratch =
+        std::env::temp_dir().join(format!("op-simulator-self-test-{}", std::process::id()));
+    if let Err(e) = std::fs::create_dir_all(&scratch) {
+        eprintln!("Failed to create {:?}: {}", scratch, e);
+        return 2;
+    }
+    settings.server.bind_address = "127.0.0.1".to_string();
+    settings.database.path = scratch.join("simulator.db");
+    settings.stats.lifetime_path = scratch.join("lifetime_stats.json");
+    settings.proxy.upstream = None;
+    settings.proxy.record_path = None;
+    settings.logging.journal.path = None;
 
-    // Create observable state wrapper that broadcasts events on state changes
-    let observable_state = ObservableState::new(device_state, event_tx.clone());
+    let listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on a loopback port: {}", e);
+            return 2;
+        }
+    };
+    let Ok(tcp_addr) = listener.local_addr() else {
+        return 2;
+    };
+    let ctx = AppContext::new(settings);
 
-    // Spawn HTTP server for state inspection and event generation
-    let http_observable = observable_state.clone();
-    let http_settings = settings.clone();
+    #[cfg(feature = "http")]
+    let http_addr = {
+        let http_listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("Failed to listen on a loopback port: {}", e);
+                return 2;
+            }
+        };
+        let http_addr = http_listener.local_addr().ok();
+        let app = http_server::create_router(ctx.clone());
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(http_listener, app).await {
+                eprintln!("Self-test HTTP server stopped: {}", e);
+            }
+        });
+        http_addr
+    };
+    #[cfg(not(feature = "http"))]
+    let http_addr = None;
+
+    spawn_background_tasks(&ctx);
     tokio::spawn(async move {
-        http_server::start_http_server(http_observable, http_settings).await;
+        if let Err(e) = serve_connections(listener, ctx, false).await {
+            eprintln!("Self-test TCP server stopped: {}", e);
+        }
     });
+    println!("Self-test: simulator listening on {}", tcp_addr);
+
+    let report = self_test::run(tcp_addr, http_addr, config).await;
+    print!("{}", report);
+    if let Err(e) = std::fs::remove_dir_all(&scratch) {
+        eprintln!("Failed to remove {:?}: {}", scratch, e);
+    }
+
+    if let Some(path) = report_path {
+        let written = serde_json::to_vec_pretty(&report)
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(path, json));
+        if let Err(e) = written {
+            eprintln!("Failed to write report to {:?}: {}", path, e);
+            return 2;
+        }
+    }
+    if report.passed() { 0 } else { 1 }
+}
+
+/// Replay a capture against a fresh simulator; the exit code is 1 when
+/// responses differ and 2 when the capture cannot be read
+fn run_compare(
+    settings: Settings,
+    capture: &Path,
+    ignore: &[(u16, String)],
+    report_path: Option<&Path>,
+) -> i32 {
+    let exchanges = match compare::load_capture(capture) {
+        Ok(exchanges) => exchanges,
+        Err(e) => {
+            eprintln!("Failed to read capture {:?}: {}", capture, e);
+            return 2;
+        }
+    };
+    let ctx = AppContext::new(settings);
+    let report = compare::compare(&exchanges, &ctx.handler_registry(), ignore);
+    print!("{}", report);
+
+    if let Some(path) = report_path {
+        let written = serde_json::to_vec_pretty(&report)
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(path, json));
+        if let Err(e) = written {
+            eprintln!("Failed to write report to {:?}: {}", path, e);
+            return 2;
+        }
+    }
+    if report.is_faithful() { 0 } else { 1 }
+}
+
+/// Longest wait for clients to take their pending broadcasts on shutdown
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+async fn serve_tcp_client(
+    settings: Settings,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> Result<(), ServeError> {
+    let bind_addr = format!(
+        "{}:{}",
+        settings.server.bind_address, settings.server.tcp_port
+    );
+    let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
+
+    println!("Open Protocol TCP server listening on {}", bind_addr);
+
+    // Create shared state, repositories and event channel once for all servers
+    let ctx = AppContext::new(settings);
+
+    // Spawn HTTP server for state inspection and event generation
+    #[cfg(feature = "http")]
+    {
+        let http_ctx = ctx.clone();
+        let tenants = Arc::new(
+            open_protocol_device_simulator::tenants::TenantRegistry::new(
+                ctx.settings.as_ref().clone(),
+            )
+            .with_launcher(Arc::new(|listener, tenant_ctx: AppContext| {
+                let mut tasks = spawn_background_tasks(&tenant_ctx);
+                let server = tokio::spawn(async move {
+                    if let Err(e) = serve_connections(listener, tenant_ctx, false).await {
+                        eprintln!("Tenant TCP server stopped: {}", e);
+                    }
+                });
+                tasks.push(server.abort_handle());
+                tasks
+            })),
+        );
+        tokio::spawn(async move {
+            http_server::start_http_server(http_ctx, tenants).await;
+        });
+    }
+
+    spawn_background_tasks(&ctx);
+
+    // Gateway mode: relay to the real controller instead of simulating one
+    if let Some(upstream) = ctx.settings.proxy.upstream.clone() {
+        tokio::select! {
+            result = proxy::serve(listener, upstream, ctx.clone()) => result?,
+            _ = shutdown => {
+                // Relayed connections end with the process
+                println!("Shutting down");
+                lifetime::save_on_shutdown(&ctx);
+                counters::save_on_shutdown(&ctx);
+                user_data::save_on_shutdown(&ctx);
+            }
+        }
+        return Ok(());
+    }
+
+    // Read-only port for monitoring taps
+    let observer_port = ctx.settings.server.observer_tcp_port;
+    if observer_port > 0 {
+        let observer_addr = format!("{}:{}", ctx.settings.server.bind_address, observer_port);
+        let observer_listener = tokio::net::TcpListener::bind(&observer_addr).await?;
+        println!("Open Protocol observer port listening on {}", observer_addr);
+        let observer_ctx = ctx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_connections(observer_listener, observer_ctx, true).await {
+                eprintln!("Observer TCP server stopped: {}", e);
+            }
+        });
+    }
+
+    tokio::select! {
+        result = serve_connections(listener, ctx.clone(), false) => result,
+        _ = shutdown => {
+            shut_down(&ctx).await;
+            Ok(())
+        }
+    }
+}
+
+/// Close every client gracefully and save the counters before exiting
+async fn shut_down(ctx: &AppContext) {
+    println!(
+        "Shutting down: closing {} client connection(s)",
+        ctx.sessions.len()
+    );
+    for session in ctx.sessions.snapshot() {
+        ctx.sessions.request_close(session.id, CloseMode::Graceful);
+    }
+    let deadline = tokio::time::Instant::now() + SHUTDOWN_GRACE;
+    while !ctx.sessions.is_empty() && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    lifetime::save_on_shutdown(ctx);
+    counters::save_on_shutdown(ctx);
+    user_data::save_on_shutdown(ctx);
+    println!("Simulator stopped");
+}
+
+/// Start the periodic tasks of a simulated controller
+///
+/// Returns their handles, so a removed tenant can stop them.
+fn spawn_background_tasks(ctx: &AppContext) -> Vec<AbortHandle> {
+    let mut tasks = webhook::spawn_webhook(ctx);
+    #[cfg(feature = "parquet")]
+    tasks.extend(open_protocol_device_simulator::parquet_export::spawn_scheduled_export(ctx));
+
+    tasks.extend(telemetry::spawn_telemetry(ctx));
+    tasks.extend(broadcast_latency::spawn_load_monitor(ctx));
+    tasks.extend(lifetime::spawn_persistence(ctx));
+    tasks.push(counters::spawn_persistence(ctx));
+    tasks.push(user_data::spawn_persistence(ctx));
+    tasks.extend(disconnects::spawn_random_disconnects(ctx));
+    tasks.extend(schedules::spawn_schedules(ctx));
+    tasks.extend(cycle_time::spawn_cycle_time_summary(ctx));
+    tasks.extend(journal::spawn_journal(ctx));
+    tasks
+}
 
-    // Create handler registry (shared across all connections)
-    let registry = Arc::new(handler::create_default_registry(observable_state.clone()));
+/// Accept Open Protocol clients of one simulated controller
+///
+/// Clients of an `observer` listener start in read-only observer mode.
+async fn serve_connections(
+    listener: tokio::net::TcpListener,
+    ctx: AppContext,
+    observer: bool,
+) -> Result<(), ServeError> {
+    let observable_state = ctx.observable_state.clone();
+    let event_tx = ctx.events.clone();
+
+    // Create handler registries (shared across all connections)
+    let registry = Arc::new(ctx.handler_registry());
+    let observer_registry = Arc::new(ctx.observer_registry());
 
     loop {
         let (stream, addr) = listener.accept().await?;
+        let (rebooting, outage, draining) = {
+            let state = observable_state.read();
+            let draining = state
+                .maintenance
+                .as_ref()
+                .is_some_and(|notice| !notice.accepting_sessions);
+            (state.rebooting, state.outage, draining)
+        };
+        if rebooting || outage {
+            // A rebooting or unreachable controller does not answer; refuse until it is back up
+            let reason = if rebooting {
+                "rebooting"
+            } else {
+                "in an outage"
+            };
+            println!("Refusing connection from {} while {}", addr, reason);
+            drop(stream);
+            continue;
+        }
+        if draining {
+            // Planned maintenance is imminent; let the current sessions wind down
+            println!("Refusing connection from {}: maintenance imminent", addr);
+            observable_state.audit(
+                AuditCategory::Connection,
+                format!("Refused connection from {} (maintenance imminent)", addr),
+            );
+            drop(stream);
+            continue;
+        }
+        let max_connections = ctx.settings.server.max_connections;
+        if max_connections > 0 && ctx.sessions.len() >= max_connections {
+            // Degrade by turning new clients away rather than serving everyone slowly
+            println!(
+                "Refusing connection from {}: {} clients connected",
+                addr, max_connections
+            );
+            ctx.resources.record_refused_connection();
+            observable_state.audit(
+                AuditCategory::Connection,
+                format!("Refused connection from {} (max_connections reached)", addr),
+            );
+            drop(stream);
+            continue;
+        }
         println!("Incoming connection from {}", addr);
+        observable_state.audit(
+            AuditCategory::Connection,
+            format!("Client connected from {}", addr),
+        );
 
         let registry = Arc::clone(&registry);
+        let observer_registry = Arc::clone(&observer_registry);
+        let sessions = Arc::clone(&ctx.sessions);
         let conn_observable_state = observable_state.clone();
         let mut event_rx = event_tx.subscribe();
-        tokio::spawn(async move {
-            let codec = codec::null_delimited_codec::NullDelimitedCodec::new();
+        let wire_format = ctx.settings.device.wire_format();
+        let push_on_connect = ctx.settings.device.push_on_connect.clone();
+        let push_revision = ctx.settings.device.push_revision;
+        let stamp_cycle_id = ctx.settings.device.stamp_cycle_id;
+        let identification_scope = ctx.settings.device.identification_scope;
+        let unknown_mid = ctx.settings.handlers.unknown_mid;
+        let greeting = ctx
+            .settings
+            .device
+            .greeting
+            .as_ref()
+            .map(|frame| frame.render(&observable_state.read()));
+        let injection_log = Arc::clone(&ctx.injection_log);
+        let latency = Arc::clone(&ctx.broadcast_latency);
+        let throttles = Arc::clone(&ctx.throttles);
+        let ack_policies = Arc::clone(&ctx.ack_policies);
+        let assertions = Arc::clone(&ctx.assertions);
+        let resources = Arc::clone(&ctx.resources);
+        let task = ctx.resources.track("connection");
+        let max_frame_bytes = ctx.settings.server.max_frame_bytes;
+        let keep_alive_config = ctx.settings.keep_alive.clone();
+        let flush_interval =
+            std::time::Duration::from_millis(ctx.settings.server.flush_interval_ms);
+        let max_batch_frames = ctx.settings.server.max_batch_frames;
+        // Clients pushed to without a handshake are not expected to send MID 0001
+        let handshake_timeout = (ctx.settings.server.handshake_timeout_ms > 0
+            && push_on_connect.is_empty())
+        .then(|| std::time::Duration::from_millis(ctx.settings.server.handshake_timeout_ms));
+        let handshake_timeout_error = ctx.settings.server.handshake_timeout_error;
+        let accepted_at = tokio::time::Instant::now();
+        tokio::spawn(wire_format.scope(async move {
+            let _task = task;
+            let session_id = sessions.register(addr);
+            let codec =
+                codec::null_delimited_codec::NullDelimitedCodec::with_max_length(max_frame_bytes)
+                    .with_tap(sessions.frame_tap(session_id));
             let mut framed = tokio_util::codec::Framed::new(stream, codec);
+            // Bandwidth bucket of this client's simulated link
+            let mut shaper = LinkShaper::new(std::time::Instant::now());
+            // Broadcasts queued in the write buffer for a batched write
+            let mut batcher = WriteBatcher::new(flush_interval, max_batch_frames);
 
             // Create connection session with typestate pattern
             // Transitions: Disconnected → Connected → Ready
             let session = session::ConnectionSession::new();
             let session = session.connect(addr);
             let mut session = session.authenticate(); // Immediate transition to Ready state
+            emit_lifecycle(
+                &resources,
+                &conn_observable_state,
+                SimulatorEvent::ClientConnected { session_id, addr },
+            );
+            if observer {
+                sessions.set_observer(session_id, true);
+                println!("Client {} connected as observer", addr);
+            }
+
+            // Some controllers greet the client before it sends MID 0001
+            let greeted = match greeting {
+                Some(greeting::Greeting::Raw(bytes)) => {
+                    println!("Sending {} greeting bytes to {}", bytes.len(), addr);
+                    sessions.frame_tap(session_id).record(FrameDirection::Outbound, &bytes);
+                    framed.get_mut().write_all(&bytes).await.map(|_| true)
+                }
+                Some(greeting::Greeting::Message(response)) => {
+                    println!("Sending greeting MID {} to {}", response.mid, addr);
+                    send_response(&mut framed, &mut shaper, &response, &conn_observable_state, &injection_log, "greeting").await
+                }
+                None => Ok(true),
+            };
+            if let Err(e) = greeted {
+                eprintln!("send error during greeting: {e}");
+                sessions.remove(session_id);
+                emit_lifecycle(
+                    &resources,
+                    &conn_observable_state,
+                    SimulatorEvent::ClientDisconnected {
+                        session_id,
+                        addr,
+                        reason: DisconnectReason::SendFailed,
+                    },
+                );
+                return;
+            }
+
+            // Gateway personalities push their streams without a handshake
+            for kind in &push_on_connect {
+                session
+                    .subscriptions_mut()
+                    .subscribe(*kind, subscriptions::SubscriptionInfo::new(push_revision, ""));
+            }
+            if !push_on_connect.is_empty() {
+                println!("Pushing {:?} to {} without subscription", push_on_connect, addr);
+                sessions.update_subscriptions(session_id, session.subscriptions());
+            }
+
+            // Controller-initiated close: a graceful one first sends the broadcasts already queued
+            let mut close_rx = sessions.close_requests(session_id);
+            let mut draining: Option<usize> = None;
+            let mut close_mode: Option<CloseMode> = None;
+
+            let mut keep_alive = keep_alive::KeepAliveTracker::new();
+            let probing = keep_alive_config.probe_interval_ms > 0;
+            let probe_period = std::time::Duration::from_millis(keep_alive_config.probe_interval_ms.max(1));
+            let mut probe_timer = tokio::time::interval_at(tokio::time::Instant::now() + probe_period, probe_period);
+
+            // Broadcasts held back by [`throttle`] rules, with their family and delivery IDs
+            let mut throttle: throttle::ClientThrottle<(SubscriptionKind, protocol::Response, Option<DeliveryId>)> =
+                throttle::ClientThrottle::new();
+            // Pushes waiting for their acknowledgment under an [`ack_policy`]
+            let mut acks: ack_policy::ClientAcks<(protocol::Response, Option<DeliveryId>)> =
+                ack_policy::ClientAcks::new();
+            // Pushes held back by the load-latency model, and keep-alive replies by reply_delay_ms
+            let mut delayed_pushes: delay_queue::DelayQueue<(SubscriptionKind, protocol::Response, Option<DeliveryId>)> =
+                delay_queue::DelayQueue::new();
+            let mut delayed_replies: delay_queue::DelayQueue<protocol::Response> = delay_queue::DelayQueue::new();
+
+            // Own PSET and VIN in per-connection identification scope
+            let mut identification = identification::SessionIdentification::default();
+
+            // Most exits are failed sends; the other ones set their reason
+            let mut disconnect_reason = DisconnectReason::SendFailed;
+
+            // Cleared once MID 0001 is answered with MID 0002
+            let mut handshake_deadline = handshake_timeout.map(|timeout| accepted_at + timeout);
 
             loop {
+                if draining == Some(0) && delayed_pushes.is_empty() {
+                    close_mode = Some(CloseMode::Graceful);
+                    break;
+                }
+                sessions.update_buffers(
+                    session_id,
+                    framed.read_buffer().capacity(),
+                    framed.write_buffer().capacity(),
+                );
+                // A reply written meanwhile took the queued broadcasts with it
+                if batcher.pending() > 0 && framed.write_buffer().is_empty() {
+                    batcher.flushed(std::time::Instant::now());
+                    sessions.update_writes(session_id, batcher.stats());
+                }
+                let next_flush = batcher.deadline();
+                let next_release = throttle.next_release(&throttles);
+                let next_ack_deadline = acks.next_deadline(&ack_policies);
+                let next_delayed_push = delayed_pushes.deadline();
+                let next_delayed_reply = delayed_replies.deadline();
+                // Branches are polled in order, so control traffic (requests such as
+                // MID 9999, close requests, probes) is served before the next bulk push
+                // and a broadcast storm cannot hold back keep-alive replies
                 tokio::select! {
+                    biased;
+
                     // Handle incoming TCP messages (requests from client)
-                    Some(result) = framed.next() => {
+                    next = framed.next() => {
+                        let Some(result) = next else {
+                            // The client closed its side of the connection
+                            disconnect_reason = DisconnectReason::ClientClosed;
+                            break;
+                        };
                         match result {
                             Ok(raw_message) => {
                                 println!("Received: {:?}", raw_message);
@@ -154,35 +893,98 @@ async fn serve_tcp_client(settings: Settings) -> Result<(), ServeError> {
 
                                 // Parse the message
                                 match protocol::parser::parse_message(&raw_message) {
-                                    Ok(message) => {
-                                        println!("Parsed MID {}, revision {}", message.mid, message.revision);
-
-                                        // Track subscription state based on MID using session
-                                        match message.mid {
-                                            60 => session.subscribe_tightening_result(),
-                                            63 => session.unsubscribe_tightening_result(),
-                                            14 => session.subscribe_pset_selection(),
-                                            17 => session.unsubscribe_pset_selection(),
-                                            51 => session.subscribe_vehicle_id(),
-                                            54 => session.unsubscribe_vehicle_id(),
-                                            90 => session.subscribe_multi_spindle_status(),
-                                            92 => session.unsubscribe_multi_spindle_status(),
-                                            100 => session.subscribe_multi_spindle_result(),
-                                            103 => session.unsubscribe_multi_spindle_result(),
-                                            _ => {}
+                                    Ok(mut message) => {
+                                        // Names and VINs arrive in the personality's text encoding
+                                        message.data = wire_format.decode(&message.data);
+                                        assertions.record_received(addr, Mid(message.mid), std::time::Instant::now());
+                                        // An acknowledgment lets the next push of its family go out
+                                        if acks.acknowledge(Mid(message.mid)) {
+                                            sessions.update_acks(session_id, acks.stats());
+                                        }
+
+                                        // Answers to our own probes are not echoed; client keep-alives per [keep_alive]
+                                        let is_keep_alive = message.mid == Mid::KEEP_ALIVE.value();
+                                        if is_keep_alive {
+                                            let kind = keep_alive.on_keep_alive(std::time::Instant::now());
+                                            sessions.update_keep_alive(session_id, keep_alive.stats());
+                                            if kind == keep_alive::KeepAliveKind::ProbeReply || !keep_alive_config.reply {
+                                                continue;
+                                            }
+                                        }
+
+                                        // Track subscription state (subscribe/unsubscribe MIDs come from the MID table)
+                                        if let Some(request) = session.track_subscription(&message) {
+                                            sessions.update_subscriptions(session_id, session.subscriptions());
+                                            if let SubscriptionRequest::Subscribe(kind) = request {
+                                                emit_lifecycle(&resources, &conn_observable_state, SimulatorEvent::ClientSubscribed { session_id, kind });
+                                            }
                                         }
 
-                                        // Handle the message
-                                        match registry.handle_message(&message) {
+                                        // Handle the message (observer mode can be switched over HTTP at any time)
+                                        let registry = if sessions.is_observer(session_id) {
+                                            &observer_registry
+                                        } else {
+                                            &registry
+                                        };
+                                        // Per-connection scope: PSET and VIN selections stay with this client
+                                        let (result, own_selection) = if identification_scope == IdentificationScope::PerConnection
+                                            && !sessions.is_observer(session_id)
+                                            && identification::SessionIdentification::is_selection(message.mid)
+                                        {
+                                            let handler = identification::SelectionHandler::new(&conn_observable_state);
+                                            let result = registry.handle_message_with(&message, &handler);
+                                            (result, handler.into_selection())
+                                        } else {
+                                            (registry.handle_message(&message), None)
+                                        };
+                                        if let Some(selection) = &own_selection {
+                                            identification.select(selection);
+                                            sessions.update_identification(session_id, &identification);
+                                        }
+                                        match result {
                                             Ok(response) => {
+                                                if message.mid == Mid::COMMUNICATION_START.value()
+                                                    && response.mid == Mid::COMMUNICATION_START_ACK.value()
+                                                {
+                                                    handshake_deadline = None;
+                                                    emit_lifecycle(&resources, &conn_observable_state, SimulatorEvent::ClientAuthenticated { session_id, addr });
+                                                }
+                                                // Masters fighting over the PSET of one controller
+                                                if own_selection.is_none()
+                                                    && message.mid == Mid::SELECT_PSET.value()
+                                                    && response.mid == Mid::PSET_SELECTED_ACK.value()
+                                                {
+                                                    let pset_id = handler::pset_select::requested_pset(&message);
+                                                    if let Some((overruled_session_id, overruled_pset_id)) = sessions.claim_pset(session_id, pset_id) {
+                                                        println!("PSET conflict: {} selected PSET {} over PSET {} of session {}", addr, pset_id, overruled_pset_id, overruled_session_id);
+                                                        conn_observable_state.broadcast(SimulatorEvent::PsetConflict {
+                                                            session_id,
+                                                            pset_id,
+                                                            overruled_session_id,
+                                                            overruled_pset_id,
+                                                        });
+                                                    }
+                                                }
+
                                                 // Serialize and send response
-                                                let response_bytes = protocol::serializer::serialize_response(&response);
+                                                let delay = registry.response_delay();
+                                                // A delayed keep-alive reply waits without holding up the connection
+                                                if is_keep_alive && keep_alive_config.reply_delay_ms > 0 {
+                                                    let reply_delay = std::time::Duration::from_millis(keep_alive_config.reply_delay_ms);
+                                                    delayed_replies.hold(response, delay + reply_delay, std::time::Instant::now());
+                                                    continue;
+                                                }
+                                                if !delay.is_zero() {
+                                                    tokio::time::sleep(delay).await;
+                                                }
                                                 println!("Sending response: MID {}", response.mid);
 
-                                                match send_with_failure_injection(
+                                                match send_response(
                                                     &mut framed,
-                                                    response_bytes,
+                                                    &mut shaper,
+                                                    &response,
                                                     &conn_observable_state,
+                                                    &injection_log,
                                                     &format!("MID {} response", response.mid),
                                                 ).await {
                                                     Ok(false) => {
@@ -193,25 +995,58 @@ async fn serve_tcp_client(settings: Settings) -> Result<(), ServeError> {
                                                         break;
                                                     }
                                                     Ok(true) => {
-                                                        // Success
+                                                        if is_keep_alive {
+                                                            keep_alive.on_reply_sent();
+                                                            sessions.update_keep_alive(session_id, keep_alive.stats());
+                                                        }
                                                     }
                                                 }
 
+                                                // A subscribed client still gets its own selection pushed
+                                                let subscribed = own_selection.as_ref().and_then(|event| {
+                                                    let kind = event.subscription_kind()?;
+                                                    let info = session.subscriptions().get(kind)?;
+                                                    let (revision, data) = event.subscription_payload(info.revision)?;
+                                                    let header = protocol::HeaderOptions { no_ack: info.no_ack, ..Default::default() };
+                                                    let data_mid = event.data_mid().unwrap_or(kind.data_mid());
+                                                    Some(protocol::Response::new(data_mid, revision, data).with_header(header))
+                                                });
+                                                if let Some(push) = subscribed
+                                                    && let Err(e) = send_response(
+                                                        &mut framed,
+                                                        &mut shaper,
+                                                        &push,
+                                                        &conn_observable_state,
+                                                        &injection_log,
+                                                        &format!("MID {} broadcast", Mid(push.mid)),
+                                                    ).await
+                                                {
+                                                    eprintln!("send error during broadcast: {e}");
+                                                    break;
+                                                }
+
                                                 // Special handling for MID 51 (vehicle ID subscription)
                                                 // Send VIN immediately after subscription is confirmed
-                                                if message.mid == 51 {
+                                                if message.mid == Mid::VEHICLE_ID_SUBSCRIBE.value() {
                                                     // VIN is empty because handlers don't have direct state access
                                                     // VIN changes are broadcast via SimulatorEvent::VehicleIdChanged
                                                     let current_vin = String::new();
                                                     let vin_data = handler::data::VehicleIdBroadcast::new(current_vin.clone());
-                                                    let vin_response = protocol::Response::from_data(52, 1, vin_data);
-                                                    let vin_response_bytes = protocol::serializer::serialize_response(&vin_response);
+                                                    let vin_response = match protocol::Response::from_data(Mid::VEHICLE_ID, 1, vin_data) {
+                                                        Ok(response) => response,
+                                                        Err(e) => {
+                                                            eprintln!("Cannot serialize initial VIN: {e}");
+                                                            continue;
+                                                        }
+                                                    };
                                                     println!("Sending initial MID 0052 with current VIN: {}", current_vin);
 
-                                                    match send_with_failure_injection(
+                                                    match send_response(
                                                         &mut framed,
-                                                        vin_response_bytes,
+                                                        &mut shaper,
+                                                        &vin_response,
                                                         &conn_observable_state,
+                                                        &injection_log,
                                                         "MID 0052 initial VIN",
                                                     ).await {
                                                         Ok(false) => {}
@@ -222,19 +1057,59 @@ async fn serve_tcp_client(settings: Settings) -> Result<(), ServeError> {
                                                         Ok(true) => {}
                                                     }
                                                 }
+
+                                                // MID 0241 (user data subscription): upload every stored slot
+                                                if message.mid == Mid::USER_DATA_SUBSCRIBE.value() {
+                                                    let slots = conn_observable_state.read().user_data.slots();
+                                                    let mut send_failed = false;
+                                                    for slot in slots {
+                                                        let upload = handler::data::UserDataUpload::new(slot.slot, slot.data);
+                                                        let upload_response = match protocol::Response::from_data(Mid::USER_DATA_UPLOAD, 1, upload) {
+                                                            Ok(response) => response,
+                                                            Err(e) => {
+                                                                eprintln!("Cannot serialize user data slot {}: {e}", slot.slot);
+                                                                continue;
+                                                            }
+                                                        };
+                                                        println!("Sending initial MID 0242 for user data slot {}", slot.slot);
+                                                        if let Err(e) = send_response(
+                                                            &mut framed,
+                                                            &mut shaper,
+                                                            &upload_response,
+                                                            &conn_observable_state,
+                                                            &injection_log,
+                                                            "MID 0242 initial user data",
+                                                        ).await {
+                                                            eprintln!("send error during initial user data upload: {e}");
+                                                            send_failed = true;
+                                                            break;
+                                                        }
+                                                    }
+                                                    if send_failed {
+                                                        break;
+                                                    }
+                                                }
+                                            }
+                                            Err(handler::HandlerError::UnknownMid(mid)) if unknown_mid != UnknownMidPolicy::Error => {
+                                                if unknown_mid == UnknownMidPolicy::Disconnect {
+                                                    println!("Closing connection {}: MID {} is not supported", addr, Mid(mid));
+                                                    disconnect_reason = DisconnectReason::UnknownMid;
+                                                    break;
+                                                }
+                                                println!("Ignoring unsupported MID {}", Mid(mid));
                                             }
                                             Err(e) => {
                                                 eprintln!("Handler error: {e}");
                                                 // Send error response (MID 0004)
-                                                let error_response = handler::data::ErrorResponse::generic(message.mid);
-                                                let response = protocol::Response::from_data(4, message.revision, error_response);
-                                                let response_bytes = protocol::serializer::serialize_response(&response);
+                                                let response = handler::data::ErrorResponse::generic(message.mid).into_response(message.revision);
                                                 println!("Sending error response: MID 0004 for failed MID {}", message.mid);
 
-                                                match send_with_failure_injection(
+                                                match send_response(
                                                     &mut framed,
-                                                    response_bytes,
+                                                    &mut shaper,
+                                                    &response,
                                                     &conn_observable_state,
+                                                    &injection_log,
                                                     &format!("MID 0004 error for MID {}", message.mid),
                                                 ).await {
                                                     Ok(false) => {}
@@ -249,160 +1124,439 @@ async fn serve_tcp_client(settings: Settings) -> Result<(), ServeError> {
                                     }
                                     Err(e) => {
                                         eprintln!("Parse error: {e}");
+                                        assertions.record_parse_error(addr, &e.to_string(), std::time::Instant::now());
                                     }
                                 }
                             }
                             Err(e) => {
+                                let too_long = e
+                                    .get_ref()
+                                    .is_some_and(|inner| inner.is::<codec::null_delimited_codec::FrameTooLong>());
+                                if too_long {
+                                    resources.record_oversized_frame();
+                                    disconnect_reason = DisconnectReason::FrameTooLong;
+                                } else {
+                                    disconnect_reason = DisconnectReason::ClientClosed;
+                                }
                                 eprintln!("framed read error: {e}");
                                 break;
                             }
                         }
                     }
 
+                    Some(mode) = close_rx.recv(), if draining.is_none() => {
+                        println!("Closing client {} ({})", session.addr(), mode.as_str());
+                        if mode == CloseMode::Graceful {
+                            draining = Some(event_rx.len());
+                        } else {
+                            close_mode = Some(mode);
+                            break;
+                        }
+                    }
+
+                    // Clients that connect but never start communicating
+                    _ = tokio::time::sleep_until(handshake_deadline.unwrap_or_else(tokio::time::Instant::now)), if handshake_deadline.is_some() => {
+                        println!("Closing client {}: no MID 0001 within the handshake deadline", addr);
+                        conn_observable_state.audit(
+                            AuditCategory::Connection,
+                            format!("Dropped client {} (handshake deadline missed)", addr),
+                        );
+                        if handshake_timeout_error {
+                            let response = handler::data::ErrorResponse::new(Mid::COMMUNICATION_START, handler::data::ErrorCode::HandshakeTimeout).into_response(1);
+                            if let Err(e) = send_response(
+                                &mut framed,
+                                &mut shaper,
+                                &response,
+                                &conn_observable_state,
+                                &injection_log,
+                                "handshake timeout error",
+                            ).await {
+                                eprintln!("send error during handshake timeout: {e}");
+                            }
+                        }
+                        disconnect_reason = DisconnectReason::HandshakeTimeout;
+                        break;
+                    }
+
+                    // Controller-side keep-alive probing
+                    _ = probe_timer.tick(), if probing => {
+                        keep_alive.on_probe_sent(std::time::Instant::now());
+                        sessions.update_keep_alive(session_id, keep_alive.stats());
+                        let probe = protocol::Response::new(Mid::KEEP_ALIVE, 1, Vec::new());
+                        if let Err(e) = send_response(
+                            &mut framed,
+                            &mut shaper,
+                            &probe,
+                            &conn_observable_state,
+                            &injection_log,
+                            "MID 9999 keep-alive probe",
+                        ).await {
+                            eprintln!("send error during keep-alive probe: {e}");
+                            break;
+                        }
+                    }
+
+                    // Keep-alive replies whose reply_delay_ms is over
+                    _ = tokio::time::sleep_until(tokio::time::Instant::from_std(next_delayed_reply.unwrap_or_else(std::time::Instant::now))), if next_delayed_reply.is_some() => {
+                        let mut failed = false;
+                        for response in delayed_replies.release(std::time::Instant::now()) {
+                            println!("Sending response: MID {}", response.mid);
+                            match send_response(
+                                &mut framed,
+                                &mut shaper,
+                                &response,
+                                &conn_observable_state,
+                                &injection_log,
+                                &format!("MID {} response", response.mid),
+                            ).await {
+                                Ok(false) => {}
+                                Err(e) => {
+                                    eprintln!("send error: {e}");
+                                    failed = true;
+                                    break;
+                                }
+                                Ok(true) => {
+                                    keep_alive.on_reply_sent();
+                                    sessions.update_keep_alive(session_id, keep_alive.stats());
+                                }
+                            }
+                        }
+                        if failed {
+                            break;
+                        }
+                    }
+
+                    // Queued broadcasts whose flush interval is over
+                    _ = tokio::time::sleep_until(tokio::time::Instant::from_std(next_flush.unwrap_or_else(std::time::Instant::now))), if next_flush.is_some() => {
+                        if let Err(e) = flush_writes(&mut framed, &mut batcher, &sessions, session_id).await {
+                            eprintln!("send error during batched write: {e}");
+                            break;
+                        }
+                    }
+
+                    // Throttled broadcasts whose tokens are available
+                    _ = tokio::time::sleep_until(tokio::time::Instant::from_std(next_release.unwrap_or_else(std::time::Instant::now))), if next_release.is_some() => {
+                        let mut failed = false;
+                        for (kind, response, delivery) in throttle.release(&throttles, std::time::Instant::now()) {
+                            let data_mid = Mid(response.mid);
+                            let admission = acks.admit(&ack_policies, kind, push_ack_mid(&response), (response, delivery), std::time::Instant::now());
+                            report_ack_drops(&sessions, session_id, admission.dropped);
+                            let Some((response, delivery)) = admission.send else {
+                                continue;
+                            };
+                            let sent = queue_response(
+                                &mut framed,
+                                &mut shaper,
+                                &response,
+                                &conn_observable_state,
+                                &injection_log,
+                                &format!("MID {} throttled broadcast", data_mid),
+                            ).await;
+                            if matches!(sent, Ok(true)) {
+                                batcher.queued(std::time::Instant::now());
+                            }
+                            if let Some(delivery) = delivery {
+                                let status = match &sent {
+                                    Ok(true) => DeliveryStatus::Sent,
+                                    Ok(false) => DeliveryStatus::DroppedByInjection,
+                                    Err(_) => DeliveryStatus::SendFailed,
+                                };
+                                sessions.report_delivery(delivery, session_id, status);
+                            }
+                            if matches!(sent, Ok(true)) && !response.header.no_ack {
+                                assertions.record_sent(addr, data_mid, std::time::Instant::now());
+                            }
+                            if let Err(e) = sent {
+                                eprintln!("send error during throttled broadcast: {e}");
+                                failed = true;
+                                break;
+                            }
+                        }
+                        sessions.update_throttled(session_id, throttle.queued());
+                        sessions.update_acks(session_id, acks.stats());
+                        if failed {
+                            break;
+                        }
+                        if batcher.flush_now() && let Err(e) = flush_writes(&mut framed, &mut batcher, &sessions, session_id).await {
+                            eprintln!("send error during throttled broadcast: {e}");
+                            break;
+                        }
+                    }
+
+                    // Unacknowledged pushes due for a resend, and pushes that waited behind acked ones
+                    _ = tokio::time::sleep_until(tokio::time::Instant::from_std(next_ack_deadline.unwrap_or_else(std::time::Instant::now))), if next_ack_deadline.is_some() => {
+                        let mut close = false;
+                        for action in acks.poll(&ack_policies, std::time::Instant::now()) {
+                            let (response, delivery, context) = match action {
+                                ack_policy::AckAction::Send((response, delivery)) => (response, delivery, "broadcast"),
+                                ack_policy::AckAction::Resend((response, _)) => {
+                                    println!("Resending unacknowledged MID {} to {}", Mid(response.mid), session.addr());
+                                    (response, None, "retransmission")
+                                }
+                                ack_policy::AckAction::Dropped((response, _)) => {
+                                    println!("Giving up on unacknowledged MID {} to {}", Mid(response.mid), session.addr());
+                                    continue;
+                                }
+                                ack_policy::AckAction::Disconnect(kind) => {
+                                    println!("Client {} did not acknowledge {:?} data, disconnecting", session.addr(), kind);
+                                    disconnect_reason = DisconnectReason::AckTimeout;
+                                    close = true;
+                                    break;
+                                }
+                            };
+                            let data_mid = Mid(response.mid);
+                            let sent = queue_response(
+                                &mut framed,
+                                &mut shaper,
+                                &response,
+                                &conn_observable_state,
+                                &injection_log,
+                                &format!("MID {} {}", data_mid, context),
+                            ).await;
+                            if matches!(sent, Ok(true)) {
+                                batcher.queued(std::time::Instant::now());
+                            }
+                            if let Some(delivery) = delivery {
+                                let status = match &sent {
+                                    Ok(true) => DeliveryStatus::Sent,
+                                    Ok(false) => DeliveryStatus::DroppedByInjection,
+                                    Err(_) => DeliveryStatus::SendFailed,
+                                };
+                                sessions.report_delivery(delivery, session_id, status);
+                            }
+                            if matches!(sent, Ok(true)) {
+                                assertions.record_sent(addr, data_mid, std::time::Instant::now());
+                            }
+                            if let Err(e) = sent {
+                                eprintln!("send error during {}: {e}", context);
+                                close = true;
+                                break;
+                            }
+                        }
+                        sessions.update_acks(session_id, acks.stats());
+                        if close {
+                            break;
+                        }
+                        if batcher.flush_now() && let Err(e) = flush_writes(&mut framed, &mut batcher, &sessions, session_id).await {
+                            eprintln!("send error during retransmission: {e}");
+                            break;
+                        }
+                    }
+
+                    // Pushes whose load-latency delay is over
+                    _ = tokio::time::sleep_until(tokio::time::Instant::from_std(next_delayed_push.unwrap_or_else(std::time::Instant::now))), if next_delayed_push.is_some() => {
+                        let mut failed = false;
+                        for (kind, response, delivery) in delayed_pushes.release(std::time::Instant::now()) {
+                            let data_mid = Mid(response.mid);
+                            // Under an ack policy the push waits while an earlier one is unacknowledged
+                            let admission = acks.admit(&ack_policies, kind, push_ack_mid(&response), (response, delivery), std::time::Instant::now());
+                            report_ack_drops(&sessions, session_id, admission.dropped);
+                            let Some((response, delivery)) = admission.send else {
+                                println!("Holding MID {} for {} until the previous one is acknowledged", data_mid, session.addr());
+                                continue;
+                            };
+                            let sent = queue_response(
+                                &mut framed,
+                                &mut shaper,
+                                &response,
+                                &conn_observable_state,
+                                &injection_log,
+                                &format!("MID {} broadcast", data_mid),
+                            ).await;
+                            if matches!(sent, Ok(true)) {
+                                batcher.queued(std::time::Instant::now());
+                            }
+                            if let Some(delivery) = delivery {
+                                let status = match &sent {
+                                    Ok(true) => DeliveryStatus::Sent,
+                                    Ok(false) => DeliveryStatus::DroppedByInjection,
+                                    Err(_) => DeliveryStatus::SendFailed,
+                                };
+                                sessions.report_delivery(delivery, session_id, status);
+                            }
+                            if matches!(sent, Ok(true)) && !response.header.no_ack {
+                                assertions.record_sent(addr, data_mid, std::time::Instant::now());
+                            }
+                            if let Err(e) = sent {
+                                eprintln!("send error during broadcast: {e}");
+                                failed = true;
+                                break;
+                            }
+                        }
+                        sessions.update_acks(session_id, acks.stats());
+                        if failed {
+                            break;
+                        }
+                        if batcher.flush_now() && let Err(e) = flush_writes(&mut framed, &mut batcher, &sessions, session_id).await {
+                            eprintln!("send error during broadcast: {e}");
+                            break;
+                        }
+                    }
+
                     // Handle broadcast events (push notifications)
-                    Ok(event) = event_rx.recv() => {
-                        match event {
-                            SimulatorEvent::TighteningCompleted { result } => {
-                                if session.subscriptions().is_subscribed_to_tightening_result() {
-                                    println!("Broadcasting MID 0061 to subscribed client ({})", session.addr());
-                                    let response = protocol::Response::from_data(61, 1, result);
-                                    let response_bytes = protocol::serializer::serialize_response(&response);
-
-                                    match send_with_failure_injection(
-                                        &mut framed,
-                                        response_bytes,
-                                        &conn_observable_state,
-                                        "MID 0061 tightening broadcast",
-                                    ).await {
-                                        Ok(false) => {}
-                                        Err(e) => {
-                                            eprintln!("send error during broadcast: {e}");
-                                            break;
-                                        }
-                                        Ok(true) => {}
-                                    }
+                    event = event_rx.recv() => {
+                        let event = match event {
+                            Ok(event) => {
+                                if let Some(pending) = &mut draining {
+                                    *pending = pending.saturating_sub(1);
                                 }
+                                event
                             }
-                            SimulatorEvent::PsetChanged { pset_id, pset_name: _ } => {
-                                if session.subscriptions().is_subscribed_to_pset_selection() {
-                                    println!("Broadcasting MID 0015 to subscribed client ({}): pset {}", session.addr(), pset_id);
-                                    let pset_data = handler::data::PsetSelected::new(pset_id);
-                                    let response = protocol::Response::from_data(15, 1, pset_data);
-                                    let response_bytes = protocol::serializer::serialize_response(&response);
-
-                                    match send_with_failure_injection(
-                                        &mut framed,
-                                        response_bytes,
-                                        &conn_observable_state,
-                                        "MID 0015 PSET broadcast",
-                                    ).await {
-                                        Ok(false) => {}
-                                        Err(e) => {
-                                            eprintln!("send error during broadcast: {e}");
-                                            break;
-                                        }
-                                        Ok(true) => {}
-                                    }
+                            Err(RecvError::Lagged(skipped)) => {
+                                eprintln!("Client {} lagged behind, {} events skipped", session.addr(), skipped);
+                                sessions.report_lag(session_id);
+                                if let Some(pending) = &mut draining {
+                                    *pending = (*pending).min(event_rx.len());
                                 }
+                                continue;
+                            }
+                            Err(RecvError::Closed) => {
+                                disconnect_reason = DisconnectReason::Shutdown;
+                                break;
                             }
+                        };
+
+                        match &event {
                             SimulatorEvent::ToolStateChanged { enabled } => {
-                                println!("Tool state changed: {}", if enabled { "enabled" } else { "disabled" });
                                 // No standard MID for tool state broadcasts in Open Protocol
+                                println!("Tool state changed: {}", if *enabled { "enabled" } else { "disabled" });
                             }
-                            SimulatorEvent::BatchCompleted { total } => {
+                            SimulatorEvent::BatchCompleted { total, .. } => {
                                 println!("Batch completed: {} tightenings", total);
-                                // Could send MID 0061 with batch status if subscribed
                             }
-                            SimulatorEvent::VehicleIdChanged { vin } => {
-                                if session.subscriptions().is_subscribed_to_vehicle_id() {
-                                    println!("Broadcasting MID 0052 to subscribed client ({}): VIN {}", session.addr(), vin);
-                                    let vin_data = handler::data::VehicleIdBroadcast::new(vin);
-                                    let response = protocol::Response::from_data(52, 1, vin_data);
-                                    let response_bytes = protocol::serializer::serialize_response(&response);
-
-                                    match send_with_failure_injection(
-                                        &mut framed,
-                                        response_bytes,
-                                        &conn_observable_state,
-                                        "MID 0052 VIN broadcast",
-                                    ).await {
-                                        Ok(false) => {}
-                                        Err(e) => {
-                                            eprintln!("send error during broadcast: {e}");
-                                            break;
-                                        }
-                                        Ok(true) => {}
-                                    }
-                                }
-                            }
-                            SimulatorEvent::MultiSpindleStatusCompleted { status } => {
-                                if session.subscriptions().is_subscribed_to_multi_spindle_status() {
-                                    println!("Broadcasting MID 0091 to subscribed client ({}): sync_id {}, status {}",
-                                        session.addr(), status.sync_id, status.status);
-                                    let status_data = handler::data::MultiSpindleStatusBroadcast::new(status);
-                                    let response = protocol::Response::from_data(91, 1, status_data);
-                                    let response_bytes = protocol::serializer::serialize_response(&response);
-
-                                    match send_with_failure_injection(
-                                        &mut framed,
-                                        response_bytes,
-                                        &conn_observable_state,
-                                        "MID 0091 multi-spindle status broadcast",
-                                    ).await {
-                                        Ok(false) => {}
-                                        Err(e) => {
-                                            eprintln!("send error during broadcast: {e}");
-                                            break;
-                                        }
-                                        Ok(true) => {}
-                                    }
-                                }
+                            SimulatorEvent::ControllerRebooting { .. } => {
+                                println!("Controller rebooting, dropping client {}", session.addr());
+                                disconnect_reason = DisconnectReason::ControllerRebooting;
+                                break;
                             }
-                            SimulatorEvent::MultiSpindleResultCompleted { result } => {
-                                if session.subscriptions().is_subscribed_to_multi_spindle_result() {
-                                    println!("Broadcasting MID 0101 to subscribed client ({}): result_id {}, sync_id {}, status {}",
-                                        session.addr(), result.result_id, result.sync_id,
-                                        if result.is_ok() { "OK" } else { "NOK" });
-
-                                    // Create MID 0101 broadcast with multi-spindle result data
-                                    let result_data = handler::data::MultiSpindleResultBroadcast::new(
-                                        result,
-                                        String::new(), // VIN (not available in session context)
-                                        1,             // job_id
-                                        1,             // pset_id
-                                        0,             // batch_size
-                                        0,             // batch_counter
-                                        2,             // batch_status
-                                    );
-                                    let response = protocol::Response::from_data(101, 1, result_data);
-                                    let response_bytes = protocol::serializer::serialize_response(&response);
-
-                                    match send_with_failure_injection(
-                                        &mut framed,
-                                        response_bytes,
-                                        &conn_observable_state,
-                                        "MID 0101 multi-spindle result broadcast",
-                                    ).await {
-                                        Ok(false) => {}
-                                        Err(e) => {
-                                            eprintln!("send error during broadcast: {e}");
-                                            break;
-                                        }
-                                        Ok(true) => {}
-                                    }
-                                }
+                            _ => {}
+                        }
+
+                        // A sandboxed client sees its own PSET and VIN
+                        let Some(event) = identification.view(&event) else {
+                            continue;
+                        };
+
+                        // Route the event to its subscription family; the data MID comes from the MID table
+                        let Some(kind) = event.subscription_kind() else {
+                            continue;
+                        };
+                        let delivery = event.delivery_id();
+                        let Some((subscribed_revision, no_ack)) = session.subscriptions().get(kind).map(|info| (info.revision, info.no_ack)) else {
+                            if let Some(delivery) = delivery {
+                                sessions.report_delivery(delivery, session_id, DeliveryStatus::NotSubscribed);
                             }
-                            SimulatorEvent::AutoTighteningProgress { .. } => {
-                                // Auto-tightening progress is only sent to WebSocket clients, not TCP
-                                // No MID exists in Open Protocol for auto-tightening progress
+                            continue;
+                        };
+                        let Some((revision, mut data)) = event.subscription_payload(subscribed_revision) else {
+                            continue;
+                        };
+                        if stamp_cycle_id && let Some(cycle_id) = event.cycle_id() {
+                            data.extend_from_slice(&events::cycle_id_field(cycle_id));
+                        }
+
+                        let data_mid = event.data_mid().unwrap_or(kind.data_mid());
+                        println!("Broadcasting MID {} ({}) rev {} to subscribed client ({})", data_mid, data_mid.name(), revision, session.addr());
+                        // A NoAck subscription gets fire-and-forget pushes
+                        let header = protocol::HeaderOptions { no_ack, ..Default::default() };
+                        let response = protocol::Response::new(data_mid, revision, data).with_header(header);
+
+                        // A throttled family holds the push back (or drops it when coalescing)
+                        let admission = throttle.admit(&throttles, kind, (kind, response, delivery), std::time::Instant::now());
+                        for (_, _, dropped) in admission.dropped {
+                            if let Some(dropped) = dropped {
+                                sessions.report_delivery(dropped, session_id, DeliveryStatus::DroppedByThrottle);
                             }
                         }
+                        sessions.update_throttled(session_id, throttle.queued());
+                        let Some((_, response, delivery)) = admission.send else {
+                            println!("Throttled MID {} to {} ({} waiting)", data_mid, session.addr(), throttle.queued());
+                            continue;
+                        };
+
+                        // A busy controller takes longer to get the data out
+                        if latency.is_enabled() {
+                            let delay = latency.delay(latency.load(sessions.len()), &mut rand::rng());
+                            delayed_pushes.hold((kind, response, delivery), delay, std::time::Instant::now());
+                            continue;
+                        }
+
+                        // Under an ack policy the push waits while an earlier one is unacknowledged
+                        let admission = acks.admit(&ack_policies, kind, push_ack_mid(&response), (response, delivery), std::time::Instant::now());
+                        report_ack_drops(&sessions, session_id, admission.dropped);
+                        sessions.update_acks(session_id, acks.stats());
+                        let Some((response, delivery)) = admission.send else {
+                            println!("Holding MID {} for {} until the previous one is acknowledged", data_mid, session.addr());
+                            continue;
+                        };
+
+                        let sent = queue_response(
+                            &mut framed,
+                            &mut shaper,
+                            &response,
+                            &conn_observable_state,
+                            &injection_log,
+                            &format!("MID {} broadcast", data_mid),
+                        ).await;
+                        if matches!(sent, Ok(true)) {
+                            batcher.queued(std::time::Instant::now());
+                        }
+                        if let Some(delivery) = delivery {
+                            let status = match &sent {
+                                Ok(true) => DeliveryStatus::Sent,
+                                Ok(false) => DeliveryStatus::DroppedByInjection,
+                                Err(_) => DeliveryStatus::SendFailed,
+                            };
+                            sessions.report_delivery(delivery, session_id, status);
+                        }
+                        if matches!(sent, Ok(true)) && !response.header.no_ack {
+                            assertions.record_sent(addr, data_mid, std::time::Instant::now());
+                        }
+                        if let Err(e) = sent {
+                            eprintln!("send error during broadcast: {e}");
+                            break;
+                        }
+                        if batcher.flush_now() && let Err(e) = flush_writes(&mut framed, &mut batcher, &sessions, session_id).await {
+                            eprintln!("send error during broadcast: {e}");
+                            break;
+                        }
                     }
                 }
             }
             // This runs when the loop exits (disconnect)
-            println!("Client disconnected: {}", session.addr());
-        });
+            sessions.remove(session_id);
+            let closed_by = match close_mode {
+                Some(mode) => {
+                    if mode == CloseMode::Graceful
+                        && let Err(e) = framed.flush().await
+                    {
+                        eprintln!("flush error during graceful close: {e}");
+                    }
+                    if let Err(e) = disconnects::terminate(framed.into_inner(), mode).await {
+                        eprintln!("error closing client: {e}");
+                    }
+                    format!(" (closed by controller, {})", mode.as_str())
+                }
+                None => String::new(),
+            };
+            println!("Client disconnected: {}{}", session.addr(), closed_by);
+            conn_observable_state.audit(
+                AuditCategory::Connection,
+                format!("Client disconnected: {}{}", session.addr(), closed_by),
+            );
+            if close_mode.is_some() {
+                disconnect_reason = DisconnectReason::ClosedByController;
+            }
+            emit_lifecycle(
+                &resources,
+                &conn_observable_state,
+                SimulatorEvent::ClientDisconnected {
+                    session_id,
+                    addr,
+                    reason: disconnect_reason,
+                },
+            );
+        }));
     }
 }
 
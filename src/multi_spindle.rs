@@ -1,5 +1,22 @@
+use crate::protocol::units::{AngleDeciDeg, TorqueCnm};
 use serde::{Deserialize, Serialize};
 
+/// Most spindles MID 0101 can carry (two-digit spindle number)
+pub const MAX_SPINDLES_PER_MESSAGE: u8 = 99;
+
+/// Torque samples reported per spindle while an auto cycle runs down
+pub const SPINDLE_PROGRESS_SAMPLES: u32 = 5;
+
+/// Phase of one spindle in a multi-spindle cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpindlePhase {
+    /// Running down; the torque is a sample on the way to the target
+    Running,
+    /// Finished; the torque is the final result
+    Completed,
+}
+
 /// Configuration for multi-spindle operation mode
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MultiSpindleConfig {
@@ -58,11 +75,11 @@ pub struct SpindleResult {
     /// Channel ID (same as spindle_id typically)
     pub channel_id: u8,
 
-    /// Final torque value achieved (Nm * 100)
-    pub torque: i32,
+    /// Final torque value achieved
+    pub torque: TorqueCnm,
 
-    /// Final angle value achieved (degrees * 10)
-    pub angle: i32,
+    /// Final angle value achieved
+    pub angle: AngleDeciDeg,
 
     /// Torque status: OK (0) or NOK (1)
     pub torque_status: u8,
@@ -77,7 +94,7 @@ impl SpindleResult {
     /// Convenience constructor for manual result creation in testing
     /// or programmatic generation scenarios (e.g., webUI custom result creation).
     #[allow(dead_code)]
-    pub fn ok(spindle_id: u8, torque: i32, angle: i32) -> Self {
+    pub fn ok(spindle_id: u8, torque: TorqueCnm, angle: AngleDeciDeg) -> Self {
         Self {
             spindle_id,
             channel_id: spindle_id,
@@ -95,8 +112,8 @@ impl SpindleResult {
     #[allow(dead_code)]
     pub fn nok(
         spindle_id: u8,
-        torque: i32,
-        angle: i32,
+        torque: TorqueCnm,
+        angle: AngleDeciDeg,
         torque_failed: bool,
         angle_failed: bool,
     ) -> Self {
@@ -137,6 +154,10 @@ pub struct MultiSpindleResult {
 
     /// Individual results for each spindle
     pub spindle_results: Vec<SpindleResult>,
+
+    /// Correlation ID of the operation (see `DeviceState::claim_cycle`)
+    #[serde(default)]
+    pub cycle_id: u64,
 }
 
 impl MultiSpindleResult {
@@ -163,6 +184,7 @@ impl MultiSpindleResult {
             overall_status,
             spindle_count,
             spindle_results,
+            cycle_id: 0,
         }
     }
 
@@ -205,6 +227,10 @@ pub struct MultiSpindleStatus {
 
     /// Timestamp when status changed
     pub timestamp: String,
+
+    /// Correlation ID of the operation (see `DeviceState::claim_cycle`)
+    #[serde(default)]
+    pub cycle_id: u64,
 }
 
 impl MultiSpindleStatus {
@@ -219,6 +245,7 @@ impl MultiSpindleStatus {
             status: 0,
             spindle_count,
             timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            cycle_id: 0,
         }
     }
 
@@ -233,6 +260,7 @@ impl MultiSpindleStatus {
             status: 1,
             spindle_count,
             timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            cycle_id: 0,
         }
     }
 
@@ -247,6 +275,7 @@ impl MultiSpindleStatus {
             status: 2,
             spindle_count,
             timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            cycle_id: 0,
         }
     }
 }
@@ -266,15 +295,14 @@ pub fn generate_multi_spindle_results(
 ) -> MultiSpindleResult {
     let mut spindle_results = Vec::new();
 
-    // Base values (will vary per spindle)
-    let base_torque = 5000; // 50.00 Nm
+    // Base angle (will vary per spindle)
     let base_angle = 1800; // 180.0 degrees
 
     for spindle_id in 1..=config.spindle_count {
         // Add slight variation per spindle (±10%)
         let variation = (spindle_id as i32 - 1) * 5;
-        let torque = base_torque + (variation * 10);
-        let angle = base_angle + (variation * 2);
+        let torque = nominal_spindle_torque(spindle_id);
+        let angle = AngleDeciDeg(base_angle + (variation * 2));
 
         // Simulate 90% success rate (last spindle might fail occasionally)
         let is_ok = spindle_id != config.spindle_count || !result_id.is_multiple_of(10);
@@ -283,7 +311,7 @@ pub fn generate_multi_spindle_results(
             SpindleResult::ok(spindle_id, torque, angle)
         } else {
             // Simulate torque failure on last spindle occasionally
-            SpindleResult::nok(spindle_id, torque - 500, angle, true, false)
+            SpindleResult::nok(spindle_id, TorqueCnm(torque.0 - 500), angle, true, false)
         };
 
         spindle_results.push(result);
@@ -292,10 +320,39 @@ pub fn generate_multi_spindle_results(
     MultiSpindleResult::new(result_id, config.sync_id, spindle_results)
 }
 
+/// Torque a spindle runs down to in generated cycles
+pub fn nominal_spindle_torque(spindle_id: u8) -> TorqueCnm {
+    // 50.00 Nm, with slight variation per spindle
+    TorqueCnm(5000 + (spindle_id as i32 - 1) * 50)
+}
+
+/// Torque sample `step` of `steps` on a spindle's ramp towards its nominal torque
+pub fn spindle_torque_sample(spindle_id: u8, step: u32, steps: u32) -> TorqueCnm {
+    let nominal = nominal_spindle_torque(spindle_id).as_nm();
+    TorqueCnm::from_nm(nominal * f64::from(step) / f64::from(steps.max(1)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_max_spindles_fit_mid_0101() {
+        use crate::handler::data::MultiSpindleResultBroadcast;
+        use crate::protocol::response_data::ResponseData;
+
+        let config = MultiSpindleConfig::new(MAX_SPINDLES_PER_MESSAGE, 1);
+        let result = generate_multi_spindle_results(&config, 1, 1);
+        let data = MultiSpindleResultBroadcast::new(result, String::new(), 0, 1, 0, 0, 2)
+            .serialize()
+            .unwrap();
+        assert!(data.starts_with(b"0199"));
+        // The last 18-byte spindle entry (spindle 99 on channel 99) precedes the "180" marker
+        let text = String::from_utf8(data).unwrap();
+        let last = text.rfind("9999").expect("spindle 99 present");
+        assert_eq!(text[last..].len(), 18 + "180".len());
+    }
+
     #[test]
     fn test_multi_spindle_config_default() {
         let config = MultiSpindleConfig::default();
@@ -327,16 +384,16 @@ mod tests {
 
     #[test]
     fn test_spindle_result_ok() {
-        let result = SpindleResult::ok(1, 5000, 1800);
+        let result = SpindleResult::ok(1, TorqueCnm(5000), AngleDeciDeg(1800));
         assert_eq!(result.spindle_id, 1);
-        assert_eq!(result.torque, 5000);
-        assert_eq!(result.angle, 1800);
+        assert_eq!(result.torque, TorqueCnm(5000));
+        assert_eq!(result.angle, AngleDeciDeg(1800));
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_spindle_result_nok() {
-        let result = SpindleResult::nok(2, 4500, 1750, true, false);
+        let result = SpindleResult::nok(2, TorqueCnm(4500), AngleDeciDeg(1750), true, false);
         assert_eq!(result.spindle_id, 2);
         assert_eq!(result.torque_status, 1); // NOK
         assert_eq!(result.angle_status, 0); // OK
@@ -346,8 +403,8 @@ mod tests {
     #[test]
     fn test_multi_spindle_result_all_ok() {
         let spindles = vec![
-            SpindleResult::ok(1, 5000, 1800),
-            SpindleResult::ok(2, 5100, 1810),
+            SpindleResult::ok(1, TorqueCnm(5000), AngleDeciDeg(1800)),
+            SpindleResult::ok(2, TorqueCnm(5100), AngleDeciDeg(1810)),
         ];
 
         let result = MultiSpindleResult::new(1, 100, spindles);
@@ -360,8 +417,8 @@ mod tests {
     #[test]
     fn test_multi_spindle_result_with_failure() {
         let spindles = vec![
-            SpindleResult::ok(1, 5000, 1800),
-            SpindleResult::nok(2, 4500, 1750, true, false),
+            SpindleResult::ok(1, TorqueCnm(5000), AngleDeciDeg(1800)),
+            SpindleResult::nok(2, TorqueCnm(4500), AngleDeciDeg(1750), true, false),
         ];
 
         let result = MultiSpindleResult::new(1, 100, spindles);
@@ -400,13 +457,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_spindle_torque_samples_ramp_to_the_result() {
+        let config = MultiSpindleConfig::new(3, 1);
+        let result = generate_multi_spindle_results(&config, 1, 1);
+        for spindle in &result.spindle_results {
+            let samples: Vec<TorqueCnm> = (1..=SPINDLE_PROGRESS_SAMPLES)
+                .map(|step| {
+                    spindle_torque_sample(spindle.spindle_id, step, SPINDLE_PROGRESS_SAMPLES)
+                })
+                .collect();
+            assert!(samples.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(samples.last(), Some(&spindle.torque));
+        }
+    }
+
     #[test]
     fn test_generate_multi_spindle_results_variation() {
         let config = MultiSpindleConfig::new(3, 200);
         let result = generate_multi_spindle_results(&config, 5, 10);
 
         // Each spindle should have different torque/angle values
-        let torques: Vec<i32> = result.spindle_results.iter().map(|s| s.torque).collect();
+        let torques: Vec<TorqueCnm> = result.spindle_results.iter().map(|s| s.torque).collect();
         assert_ne!(torques[0], torques[1]);
         assert_ne!(torques[1], torques[2]);
     }
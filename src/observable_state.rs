@@ -4,10 +4,34 @@
 //! event broadcasting, keeping DeviceState pure while allowing automatic event
 //! notifications to WebSocket clients.
 
+use crate::alarms::{AckSource, Alarm, AlarmError, AlarmSeverity, AlarmState, ProtocolAck};
+use crate::audit::AuditCategory;
 use crate::events::{EventBroadcaster, SimulatorEvent};
-use crate::state::DeviceState;
+use crate::interlock::{InterlockWait, ReleaseSource};
+use crate::job::{Job, JobStep};
+use crate::pset::RetryAction;
+use crate::pset_history::{PsetChangeKind, PsetChangeSource};
+use crate::state::{DeviceState, FirmwareUpgrade, MaintenanceNotice, StatePatch};
+use crate::user_data::UserData;
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+/// Alarm code raised when a batch position exceeds its NOK retry limit
+pub const RETRY_LIMIT_ALARM_CODE: &str = "E501";
+
+/// Alarm code raised when the tool reaches its service interval
+pub const SERVICE_DUE_ALARM_CODE: &str = "E502";
+
+/// Outcome of a tool service reset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceReset {
+    /// Tightenings counted since the previous service
+    pub tightenings_since_service: u64,
+    /// Service alarms cleared by the reset
+    pub cleared_alarms: Vec<u64>,
+    /// Whether the tool was locked for service and is enabled again
+    pub unlocked: bool,
+}
+
 /// Wrapper around DeviceState that automatically broadcasts events when state changes
 #[derive(Clone)]
 pub struct ObservableState {
@@ -27,8 +51,12 @@ impl ObservableState {
     }
 
     /// Get mutable access to the underlying state (use sparingly, prefer observable methods)
+    ///
+    /// Bumps the state version, whether or not anything is changed.
     pub fn write(&self) -> RwLockWriteGuard<'_, DeviceState> {
-        self.state.write().unwrap()
+        let mut state = self.state.write().unwrap();
+        state.touch();
+        state
     }
 
     /// Get direct access to the state Arc (for passing to components that need raw access)
@@ -37,20 +65,25 @@ impl ObservableState {
     }
 
     /// Enable the tool and broadcast the event
-    pub fn enable_tool(&self) {
+    ///
+    /// Returns false (and broadcasts nothing) while the tool is locked for service.
+    pub fn enable_tool(&self) -> bool {
         {
-            let mut state = self.state.write().unwrap();
-            state.enable_tool();
+            let mut state = self.write();
+            if !state.enable_tool() {
+                return false;
+            }
         }
         let _ = self
             .broadcaster
             .send(SimulatorEvent::ToolStateChanged { enabled: true });
+        true
     }
 
     /// Disable the tool and broadcast the event
     pub fn disable_tool(&self) {
         {
-            let mut state = self.state.write().unwrap();
+            let mut state = self.write();
             state.disable_tool();
         }
         let _ = self
@@ -58,33 +91,167 @@ impl ObservableState {
             .send(SimulatorEvent::ToolStateChanged { enabled: false });
     }
 
-    /// Set the parameter set and broadcast the event
-    pub fn set_pset(&self, pset_id: u32, pset_name: Option<String>) {
+    /// Broadcast a completed batch, locking the tool first under `lock_at_batch_done`
+    pub fn complete_batch(&self, total: u32) {
+        let tool_locked = self.read().tightening_tracker.lock_at_batch_done();
+        if tool_locked {
+            println!("Batch done: locking tool");
+            self.disable_tool();
+        }
+        let _ = self
+            .broadcaster
+            .send(SimulatorEvent::BatchCompleted { total, tool_locked });
+    }
+
+    /// Set the parameter set, record the selection and broadcast the event
+    pub fn set_pset(&self, pset_id: u32, pset_name: Option<String>, source: PsetChangeSource) {
         let name_for_broadcast = pset_name.clone().unwrap_or_else(|| "Unknown".to_string());
-        {
-            let mut state = self.state.write().unwrap();
+        let last_change = {
+            let mut state = self.write();
             state.set_pset(pset_id, pset_name);
-        }
+            state.record_pset_change(pset_id, PsetChangeKind::Selected, source);
+            state.last_pset_change(pset_id)
+        };
         let _ = self.broadcaster.send(SimulatorEvent::PsetChanged {
             pset_id,
             pset_name: name_for_broadcast,
+            last_change,
+        });
+    }
+
+    /// Make `job` the active job and select its first PSET with that step's
+    /// batch size, broadcasting the PSET selection and the job selection
+    pub fn select_job(&self, job: &Job, pset_name: Option<String>) {
+        let Some(step) = self.start_job(job, pset_name, PsetChangeSource::Mid0038) else {
+            return;
+        };
+        let _ = self.broadcaster.send(SimulatorEvent::JobSelected {
+            job_id: job.id,
+            job_name: job.name.clone(),
+            pset_id: step.pset_id,
+            batch_size: step.batch_size,
+            job_batch_size: job.total_batch_size(),
         });
     }
 
+    /// Run `job` again from its first step, like [`select_job`](Self::select_job),
+    /// and broadcast the restart
+    pub fn restart_job(&self, job: &Job, pset_name: Option<String>) {
+        let Some(step) = self.start_job(job, pset_name, PsetChangeSource::Mid0039) else {
+            return;
+        };
+        let _ = self.broadcaster.send(SimulatorEvent::JobRestarted {
+            job_id: job.id,
+            job_name: job.name.clone(),
+            pset_id: step.pset_id,
+            batch_size: step.batch_size,
+            job_batch_size: job.total_batch_size(),
+        });
+    }
+
+    /// Start a new instance of `job`: every step's batch starts over at its
+    /// configured size and the first PSET is selected
+    ///
+    /// Returns the first step (None for a job without steps).
+    fn start_job(
+        &self,
+        job: &Job,
+        pset_name: Option<String>,
+        source: PsetChangeSource,
+    ) -> Option<JobStep> {
+        {
+            let mut state = self.write();
+            state.select_job(job.id);
+            // Reversed, so the first step wins for a PSET used twice
+            for step in job.steps.iter().rev() {
+                state.set_pset_batch_size(step.pset_id, step.batch_size);
+            }
+        }
+        let step = job.steps.first().copied()?;
+        self.set_pset(step.pset_id, pset_name, source);
+        Some(step)
+    }
+
     /// Set the vehicle ID and broadcast the event
-    pub fn set_vehicle_id(&self, vin: String) {
+    pub fn login_operator(&self, operator_id: String, name: Option<String>) {
         {
-            let mut state = self.state.write().unwrap();
-            state.set_vehicle_id(vin.clone());
+            let mut state = self.write();
+            state.login_operator(operator_id.clone(), name);
+        }
+        let _ = self.broadcaster.send(SimulatorEvent::OperatorChanged {
+            operator_id: Some(operator_id),
+        });
+    }
+
+    /// Log the current operator out; returns false if nobody was logged in
+    pub fn logout_operator(&self) -> bool {
+        let logged_out = self.write().logout_operator().is_some();
+        if logged_out {
+            let _ = self
+                .broadcaster
+                .send(SimulatorEvent::OperatorChanged { operator_id: None });
         }
+        logged_out
+    }
+
+    pub fn set_vehicle_id(&self, vin: String) {
+        let cycle_id = {
+            let mut state = self.write();
+            state.set_vehicle_id(vin.clone());
+            state.cycle_id
+        };
         let _ = self
             .broadcaster
-            .send(SimulatorEvent::VehicleIdChanged { vin });
+            .send(SimulatorEvent::VehicleIdChanged { vin, cycle_id });
+    }
+
+    /// Validate and apply a `PATCH /state` patch, broadcasting one event per changed field
+    ///
+    /// Identity fields are broadcast together as one `IdentityChanged`.
+    /// Returns the names of the changed fields.
+    pub fn apply_patch(&self, patch: StatePatch) -> Result<Vec<&'static str>, String> {
+        patch.validate()?;
+        let (changed, identity, cycle_id) = {
+            let mut state = self.write();
+            let before = state.identity();
+            let changed = state.apply_patch(patch);
+            let identity = state.identity();
+            (
+                changed,
+                (identity != before).then_some(identity),
+                state.cycle_id,
+            )
+        };
+        if let Some(identity) = identity {
+            let _ = self
+                .broadcaster
+                .send(SimulatorEvent::IdentityChanged { identity });
+        }
+        for (field, value) in &changed {
+            let event = match (*field, value) {
+                ("cell_id" | "channel_id" | "controller_name" | "supplier_code", _) => continue,
+                ("tool_enabled", serde_json::Value::Bool(enabled)) => {
+                    SimulatorEvent::ToolStateChanged { enabled: *enabled }
+                }
+                ("vehicle_id", serde_json::Value::String(vin)) => {
+                    SimulatorEvent::VehicleIdChanged {
+                        vin: vin.clone(),
+                        cycle_id,
+                    }
+                }
+                _ => SimulatorEvent::StateChanged {
+                    field: field.to_string(),
+                    value: value.clone(),
+                },
+            };
+            let _ = self.broadcaster.send(event);
+        }
+        Ok(changed.into_iter().map(|(field, _)| field).collect())
     }
 
     /// Set batch size (does not broadcast an event as this is internal config)
     pub fn set_batch_size(&self, size: u32) {
-        let mut state = self.state.write().unwrap();
+        let mut state = self.write();
         state.set_batch_size(size);
     }
 
@@ -101,16 +268,457 @@ impl ObservableState {
 
     /// Enable multi-spindle mode (does not broadcast as it's config change)
     pub fn enable_multi_spindle(&self, spindle_count: u8, sync_id: u32) -> Result<(), String> {
-        let mut state = self.state.write().unwrap();
+        let mut state = self.write();
         state.enable_multi_spindle(spindle_count, sync_id)
     }
 
     /// Disable multi-spindle mode (does not broadcast as it's config change)
     pub fn disable_multi_spindle(&self) {
-        let mut state = self.state.write().unwrap();
+        let mut state = self.write();
         state.disable_multi_spindle();
     }
 
+    /// Raise a controller alarm and broadcast it (MID 0071 to alarm subscribers)
+    ///
+    /// Returns the ID of the new alarm.
+    pub fn raise_alarm(&self, error_code: &str, description: &str, severity: AlarmSeverity) -> u64 {
+        let (alarm, tool_ready) = {
+            let mut state = self.write();
+            let text = state.alarm_text(error_code, description);
+            let alarm = state
+                .alarms
+                .raise_with_text(error_code, description, text, severity);
+            state.audit_log.record(
+                AuditCategory::Alarm,
+                format!(
+                    "Alarm {} ({}) raised: {}",
+                    error_code,
+                    severity.as_str(),
+                    description
+                ),
+            );
+            (alarm, state.tool_enabled)
+        };
+        let _ = self.broadcaster.send(SimulatorEvent::AlarmRaised {
+            id: alarm.id,
+            error_code: alarm.error_code,
+            description: alarm.description,
+            text: alarm.text,
+            severity,
+            tool_ready,
+        });
+        alarm.id
+    }
+
+    /// Acknowledge an active alarm and broadcast it (MID 0074 to alarm subscribers)
+    pub fn acknowledge_alarm(&self, id: u64, source: AckSource) -> Result<Alarm, AlarmError> {
+        let alarm = {
+            let mut state = self.write();
+            let alarm = state.alarms.acknowledge(id, source)?;
+            state.audit_log.record(
+                AuditCategory::Alarm,
+                format!("Alarm {} acknowledged", alarm.error_code),
+            );
+            alarm
+        };
+        self.broadcast_acknowledged(&alarm, source);
+        Ok(alarm)
+    }
+
+    /// Apply a MID 0075 received from a client (see `AlarmList::acknowledge_by_protocol`)
+    pub fn acknowledge_alarm_by_protocol(&self) -> ProtocolAck {
+        let outcome = {
+            let mut state = self.write();
+            let outcome = state.alarms.acknowledge_by_protocol();
+            if let ProtocolAck::Acknowledged(alarm) = &outcome {
+                state.audit_log.record(
+                    AuditCategory::Alarm,
+                    format!("Alarm {} acknowledged by client", alarm.error_code),
+                );
+            }
+            outcome
+        };
+        if let ProtocolAck::Acknowledged(alarm) = &outcome {
+            self.broadcast_acknowledged(alarm, AckSource::Protocol);
+        }
+        outcome
+    }
+
+    fn broadcast_acknowledged(&self, alarm: &Alarm, source: AckSource) {
+        let _ = self.broadcaster.send(SimulatorEvent::AlarmAcknowledged {
+            id: alarm.id,
+            error_code: alarm.error_code.clone(),
+            source,
+        });
+    }
+
+    /// Clear an active or acknowledged alarm and broadcast it
+    pub fn clear_alarm(&self, id: u64) -> Result<Alarm, AlarmError> {
+        let alarm = {
+            let mut state = self.write();
+            let alarm = state.alarms.clear(id)?;
+            state.audit_log.record(
+                AuditCategory::Alarm,
+                format!("Alarm {} cleared", alarm.error_code),
+            );
+            alarm
+        };
+        let _ = self.broadcaster.send(SimulatorEvent::AlarmCleared {
+            id: alarm.id,
+            error_code: alarm.error_code.clone(),
+        });
+        Ok(alarm)
+    }
+
+    /// Apply a NOK retry limit to the current batch position
+    ///
+    /// When the position has reached `max_retries` consecutive NOK results,
+    /// either skips it (like MID 0128) or locks the tool and raises an alarm.
+    /// Returns the action taken, or None if the limit was not reached.
+    pub fn enforce_retry_limit(
+        &self,
+        max_retries: u32,
+        action: RetryAction,
+    ) -> Option<RetryAction> {
+        {
+            let mut state = self.write();
+            if state.tightening_tracker.consecutive_nok() < max_retries.max(1) {
+                return None;
+            }
+            state.tightening_tracker.clear_consecutive_nok();
+            if action == RetryAction::Skip {
+                let counter = state.increment_batch();
+                println!(
+                    "NOK retry limit ({}) reached: skipping position, batch counter now {}",
+                    max_retries, counter
+                );
+            }
+        }
+
+        match action {
+            RetryAction::Skip => {
+                let (counter, completed) = {
+                    let state = self.state.read().unwrap();
+                    (
+                        state.tightening_tracker.counter(),
+                        state.tightening_tracker.is_complete(),
+                    )
+                };
+                if completed {
+                    self.complete_batch(counter);
+                }
+            }
+            RetryAction::LockTool => {
+                println!("NOK retry limit ({}) reached: locking tool", max_retries);
+                self.disable_tool();
+                self.raise_alarm(
+                    RETRY_LIMIT_ALARM_CODE,
+                    "Maximum NOK retries reached at batch position",
+                    AlarmSeverity::Critical,
+                );
+            }
+        }
+
+        Some(action)
+    }
+
+    /// Hold the next position of an interlocked job after an OK tightening
+    ///
+    /// Disables the tool and broadcasts `InterlockWaiting` when the current
+    /// job is one of `jobs`. Returns the wait that began, if any.
+    pub fn hold_for_interlock(&self, jobs: &[u32], ok: bool) -> Option<InterlockWait> {
+        if !ok {
+            return None;
+        }
+        let wait = {
+            let mut state = self.write();
+            let job_id = state.current_job_id.filter(|job| jobs.contains(job))?;
+            if state.interlock.is_some() {
+                return None;
+            }
+            let wait = InterlockWait::new(job_id, state.tightening_tracker.counter());
+            state.audit_log.record(
+                AuditCategory::Tool,
+                format!(
+                    "Job {} position {} waiting for interlock release",
+                    wait.job_id, wait.position
+                ),
+            );
+            state.interlock = Some(wait.clone());
+            wait
+        };
+
+        println!(
+            "Interlock: job {} position {} done, waiting for release",
+            wait.job_id, wait.position
+        );
+        self.disable_tool();
+        let _ = self
+            .broadcaster
+            .send(SimulatorEvent::InterlockWaiting { wait: wait.clone() });
+        Some(wait)
+    }
+
+    /// Release the position held by an interlock and enable the tool again
+    ///
+    /// Returns None when nothing is waiting. The tool stays disabled if it
+    /// is also locked for service.
+    pub fn release_interlock(&self, source: ReleaseSource) -> Option<InterlockWait> {
+        let wait = {
+            let mut state = self.write();
+            let wait = state.interlock.take()?;
+            state.audit_log.record(
+                AuditCategory::Tool,
+                format!(
+                    "Job {} released after position {} ({})",
+                    wait.job_id,
+                    wait.position,
+                    source.as_str()
+                ),
+            );
+            wait
+        };
+
+        println!(
+            "Interlock: job {} released after position {} ({})",
+            wait.job_id,
+            wait.position,
+            source.as_str()
+        );
+        self.enable_tool();
+        let _ = self.broadcaster.send(SimulatorEvent::InterlockReleased {
+            wait: wait.clone(),
+            source,
+        });
+        Some(wait)
+    }
+
+    /// Write a user data slot (MID 0240/0245, `PUT /user-data/{slot}`)
+    ///
+    /// Empty data clears the slot. Subscribers get the new contents as
+    /// MID 0242; writing the same data again is not re-broadcast.
+    pub fn set_user_data(&self, slot: u16, data: String) -> Result<(), String> {
+        UserData::validate(slot, &data)?;
+        if self.read().user_data.get(slot).unwrap_or("") == data {
+            return Ok(());
+        }
+        {
+            let mut state = self.write();
+            state.user_data.set(slot, data.clone());
+            state.audit_log.record(
+                AuditCategory::Configuration,
+                if data.is_empty() {
+                    format!("User data slot {} cleared", slot)
+                } else {
+                    format!("User data slot {} written", slot)
+                },
+            );
+        }
+        let _ = self
+            .broadcaster
+            .send(SimulatorEvent::UserDataChanged { slot, data });
+        Ok(())
+    }
+
+    /// Raise the service alarm once the tool reaches `interval` tightenings
+    /// since its last service, and lock the tool if `lockout` is set
+    ///
+    /// The alarm is raised once per service interval. Returns true when this
+    /// call found the service due.
+    pub fn enforce_service_interval(&self, interval: u64, lockout: bool) -> bool {
+        let due = |state: &DeviceState| {
+            interval > 0
+                && !state.service_due
+                && state.lifetime_stats.tightenings_since_service >= interval
+        };
+        if !due(&self.read()) {
+            return false;
+        }
+        let tightenings = {
+            let mut state = self.write();
+            // Another tightening may have got here first
+            if !due(&state) {
+                return false;
+            }
+            state.service_due = true;
+            state.service_locked = lockout;
+            state.lifetime_stats.tightenings_since_service
+        };
+
+        println!(
+            "Tool service due after {} tightenings{}",
+            tightenings,
+            if lockout { ": locking tool" } else { "" }
+        );
+        if lockout {
+            self.disable_tool();
+        }
+        self.raise_alarm(
+            SERVICE_DUE_ALARM_CODE,
+            &format!("Tool service due after {} tightenings", tightenings),
+            if lockout {
+                AlarmSeverity::Critical
+            } else {
+                AlarmSeverity::Warning
+            },
+        );
+        true
+    }
+
+    /// Record a tool service: restart the service counter, acknowledge
+    /// (MID 0074 to alarm subscribers) and clear open service alarms, and
+    /// enable the tool again if it was locked for service
+    pub fn reset_service(&self) -> ServiceReset {
+        let (tightenings_since_service, open_alarms, unlocked) = {
+            let mut state = self.write();
+            let at = chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string();
+            let tightenings = state.lifetime_stats.reset_service(at);
+            state.service_due = false;
+            let unlocked = std::mem::take(&mut state.service_locked);
+            state.audit_log.record(
+                AuditCategory::Tool,
+                format!("Tool serviced after {} tightenings", tightenings),
+            );
+            let open_alarms: Vec<Alarm> = state
+                .alarms
+                .alarms()
+                .into_iter()
+                .filter(|a| {
+                    a.error_code == SERVICE_DUE_ALARM_CODE && a.state != AlarmState::Cleared
+                })
+                .collect();
+            (tightenings, open_alarms, unlocked)
+        };
+
+        let mut cleared_alarms = Vec::new();
+        for alarm in open_alarms {
+            if alarm.state == AlarmState::Active {
+                let _ = self.acknowledge_alarm(alarm.id, AckSource::Http);
+            }
+            if self.clear_alarm(alarm.id).is_ok() {
+                cleared_alarms.push(alarm.id);
+            }
+        }
+        if unlocked {
+            self.enable_tool();
+        }
+        ServiceReset {
+            tightenings_since_service,
+            cleared_alarms,
+            unlocked,
+        }
+    }
+
+    /// Announce a planned interruption and broadcast `MaintenanceImminent`
+    ///
+    /// Returns false while the controller is already down or another
+    /// interruption is pending. The notice is withdrawn when the firmware
+    /// upgrade or outage begins.
+    pub fn announce_maintenance(&self, notice: MaintenanceNotice) -> bool {
+        {
+            let mut state = self.write();
+            if state.rebooting || state.outage || state.maintenance.is_some() {
+                return false;
+            }
+            state.audit_log.record(
+                AuditCategory::Connection,
+                format!(
+                    "Maintenance announced: {} in {} ms",
+                    notice.reason, notice.starts_in_ms
+                ),
+            );
+            state.maintenance = Some(notice.clone());
+        }
+        let _ = self
+            .broadcaster
+            .send(SimulatorEvent::MaintenanceImminent { notice });
+        true
+    }
+
+    /// Take the controller down for a firmware upgrade
+    ///
+    /// Returns the running software version, or `None` when a reboot is
+    /// already in progress. Connection tasks drop their client on the
+    /// `ControllerRebooting` event and new connections are refused until
+    /// `finish_firmware_upgrade` brings the controller back.
+    pub fn begin_firmware_upgrade(&self, new_version: &str, reboot_ms: u64) -> Option<String> {
+        let old_version = {
+            let mut state = self.write();
+            if state.rebooting {
+                return None;
+            }
+            state.rebooting = true;
+            state.maintenance = None;
+            state.audit_log.record(
+                AuditCategory::Configuration,
+                format!("Rebooting for firmware upgrade to {}", new_version),
+            );
+            state.software_version.clone()
+        };
+        let _ = self.broadcaster.send(SimulatorEvent::ControllerRebooting {
+            software_version: new_version.to_string(),
+            reboot_ms,
+        });
+        Some(old_version)
+    }
+
+    /// Install the new firmware and bring the controller back up
+    pub fn finish_firmware_upgrade(&self, upgrade: FirmwareUpgrade) {
+        let software_version = upgrade.software_version.clone();
+        let identity = {
+            let mut state = self.write();
+            let before = state.identity();
+            state.apply_firmware_upgrade(upgrade);
+            state.rebooting = false;
+            let identity = state.identity();
+            (identity != before).then_some(identity)
+        };
+        let _ = self
+            .broadcaster
+            .send(SimulatorEvent::ControllerRebooted { software_version });
+        if let Some(identity) = identity {
+            let _ = self
+                .broadcaster
+                .send(SimulatorEvent::IdentityChanged { identity });
+        }
+    }
+
+    /// Take the controller off the network for a scheduled outage
+    ///
+    /// Returns false when an outage is already in progress. New connections
+    /// are refused until `end_outage`.
+    pub fn begin_outage(&self, reason: &str) -> bool {
+        let mut state = self.write();
+        if state.outage {
+            return false;
+        }
+        state.outage = true;
+        state.maintenance = None;
+        state.audit_log.record(
+            AuditCategory::Connection,
+            format!("Outage started ({})", reason),
+        );
+        true
+    }
+
+    /// Bring the controller back after a scheduled outage
+    pub fn end_outage(&self) {
+        let mut state = self.write();
+        state.outage = false;
+        state
+            .audit_log
+            .record(AuditCategory::Connection, "Outage ended");
+    }
+
+    /// Append an entry to the controller audit log
+    pub fn audit(&self, category: AuditCategory, description: impl Into<String>) {
+        self.state
+            .write()
+            .unwrap()
+            .audit_log
+            .record(category, description);
+    }
+
     /// Broadcast a simulator event (for complex operations that need manual broadcasting)
     pub fn broadcast(&self, event: SimulatorEvent) {
         let _ = self.broadcaster.send(event);
@@ -120,4 +728,155 @@ impl ObservableState {
     pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<SimulatorEvent> {
         self.broadcaster.subscribe()
     }
+
+    /// The event channel state changes are broadcast on
+    pub fn broadcaster(&self) -> &EventBroadcaster {
+        &self.broadcaster
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observable() -> (
+        ObservableState,
+        tokio::sync::broadcast::Receiver<SimulatorEvent>,
+    ) {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+        (ObservableState::new(DeviceState::new_shared(), tx), rx)
+    }
+
+    #[test]
+    fn test_retry_limit_skip_advances_position() {
+        let (state, _rx) = observable();
+        state.set_batch_size(3);
+        state.write().tightening_tracker.add_tightening(false);
+        assert_eq!(state.enforce_retry_limit(2, RetryAction::Skip), None);
+
+        state.write().tightening_tracker.add_tightening(false);
+        assert_eq!(
+            state.enforce_retry_limit(2, RetryAction::Skip),
+            Some(RetryAction::Skip)
+        );
+        assert_eq!(state.read().tightening_tracker.counter(), 1);
+        assert_eq!(state.read().tightening_tracker.consecutive_nok(), 0);
+    }
+
+    #[test]
+    fn test_retry_limit_lock_disables_tool_and_alarms() {
+        let (state, mut rx) = observable();
+        state.set_batch_size(3);
+        state.write().tightening_tracker.add_tightening(false);
+
+        assert_eq!(
+            state.enforce_retry_limit(1, RetryAction::LockTool),
+            Some(RetryAction::LockTool)
+        );
+        assert!(!state.read().tool_enabled);
+        assert_eq!(state.read().tightening_tracker.counter(), 0);
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(SimulatorEvent::ToolStateChanged { enabled: false })
+        ));
+        match rx.try_recv() {
+            Ok(SimulatorEvent::AlarmRaised { error_code, .. }) => {
+                assert_eq!(error_code, RETRY_LIMIT_ALARM_CODE)
+            }
+            other => panic!("expected alarm, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lock_at_batch_done_disables_tool() {
+        let (state, mut rx) = observable();
+        state.complete_batch(3);
+        assert!(state.read().tool_enabled);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(SimulatorEvent::BatchCompleted {
+                total: 3,
+                tool_locked: false
+            })
+        ));
+
+        state
+            .write()
+            .tightening_tracker
+            .set_lock_at_batch_done(true);
+        state.complete_batch(3);
+        assert!(!state.read().tool_enabled);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(SimulatorEvent::ToolStateChanged { enabled: false })
+        ));
+        let event = rx.try_recv().unwrap();
+        assert!(matches!(
+            event,
+            SimulatorEvent::BatchCompleted {
+                tool_locked: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            event.subscription_kind(),
+            Some(crate::subscriptions::SubscriptionKind::LockAtBatchDone)
+        );
+        assert_eq!(event.subscription_payload(1), Some((1, b"1".to_vec())));
+    }
+
+    #[test]
+    fn test_service_interval_locks_tool_until_reset() {
+        let (state, mut rx) = observable();
+        state.write().record_tool_cycle(true, 40.0, 180.0, 500);
+        assert!(!state.enforce_service_interval(2, true));
+
+        state.write().record_tool_cycle(true, 40.0, 180.0, 500);
+        assert!(state.enforce_service_interval(2, true));
+        assert!(!state.read().tool_enabled);
+        // Raised once per interval
+        state.write().record_tool_cycle(true, 40.0, 180.0, 500);
+        assert!(!state.enforce_service_interval(2, true));
+        assert!(!state.enable_tool(), "locked until serviced");
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(SimulatorEvent::ToolStateChanged { enabled: false })
+        ));
+        let alarm_id = match rx.try_recv() {
+            Ok(SimulatorEvent::AlarmRaised { id, error_code, .. }) => {
+                assert_eq!(error_code, SERVICE_DUE_ALARM_CODE);
+                id
+            }
+            other => panic!("expected alarm, got {:?}", other),
+        };
+
+        let reset = state.reset_service();
+        assert_eq!(
+            reset,
+            ServiceReset {
+                tightenings_since_service: 3,
+                cleared_alarms: vec![alarm_id],
+                unlocked: true,
+            }
+        );
+        assert!(state.read().tool_enabled);
+        assert_eq!(state.read().lifetime_stats.tightenings_since_service, 0);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(SimulatorEvent::AlarmAcknowledged {
+                source: AckSource::Http,
+                ..
+            })
+        ));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(SimulatorEvent::AlarmCleared { .. })
+        ));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(SimulatorEvent::ToolStateChanged { enabled: true })
+        ));
+    }
 }
@@ -0,0 +1,285 @@
+//! Outbox of deliveries to external integrations
+//!
+//! Results bound for the webhook sink are written to the outbox first and
+//! only marked delivered once the consumer acknowledged them, so nothing is
+//! lost when the consumer is down or the simulator restarts. A batch whose
+//! acknowledgment got lost is sent again: delivery is at-least-once and
+//! consumers see the same entry ID twice, which is exactly what they need to
+//! test their duplicate handling against.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteOutbox, create_sqlite_outbox};
+
+/// Delivered entries kept for inspection; older ones are pruned
+pub const DELIVERED_RETENTION: usize = 1000;
+
+/// Delivery state of an outbox entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    /// Waiting for its first or next attempt
+    Pending,
+    /// Acknowledged by the consumer
+    Delivered,
+    /// Given up on after the maximum number of attempts
+    Failed,
+}
+
+impl OutboxStatus {
+    /// Name used for storage and the HTTP API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Delivered => "delivered",
+            OutboxStatus::Failed => "failed",
+        }
+    }
+
+    /// Parse a stored name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pending" => Some(OutboxStatus::Pending),
+            "delivered" => Some(OutboxStatus::Delivered),
+            "failed" => Some(OutboxStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// One delivery in the outbox
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutboxEntry {
+    /// Stable ID, sent along so consumers can deduplicate
+    pub id: u64,
+    /// What the payload is, e.g. "tightening_result"
+    pub topic: String,
+    pub payload: serde_json::Value,
+    pub status: OutboxStatus,
+    /// Delivery attempts made so far
+    pub attempts: u32,
+    /// Error of the last failed attempt
+    pub last_error: Option<String>,
+    /// Timestamps are RFC 3339 in UTC, so they also sort as text
+    pub created_at: String,
+    /// Earliest time of the next attempt (pending entries)
+    pub next_attempt_at: String,
+    pub delivered_at: Option<String>,
+}
+
+/// Result of a delivery attempt
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttemptOutcome {
+    Delivered,
+    /// Failed; try again at the given time
+    Retry {
+        error: String,
+        next_attempt_at: DateTime<Utc>,
+    },
+    /// Failed for the last time
+    GaveUp {
+        error: String,
+    },
+}
+
+/// Format a time the way outbox entries store it
+pub fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Storage of outbox entries
+pub trait Outbox: Send + Sync {
+    /// Add a pending entry, due immediately
+    fn enqueue(&mut self, topic: &str, payload: serde_json::Value) -> Result<OutboxEntry, String>;
+
+    /// Pending entries due at `now`, oldest first
+    fn due(&self, now: DateTime<Utc>, limit: usize) -> Vec<OutboxEntry>;
+
+    /// Record the outcome of an attempt to deliver entry `id`
+    fn record_attempt(&mut self, id: u64, outcome: AttemptOutcome) -> Result<(), String>;
+
+    /// Most recent entries (optionally only those in `status`), newest first
+    fn list(&self, status: Option<OutboxStatus>, limit: usize) -> Vec<OutboxEntry>;
+
+    /// Number of entries per status
+    fn counts(&self) -> BTreeMap<OutboxStatus, usize>;
+}
+
+/// In-memory outbox (lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryOutbox {
+    entries: Vec<OutboxEntry>,
+    next_id: u64,
+}
+
+impl InMemoryOutbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn prune_delivered(&mut self) {
+        let delivered = self
+            .entries
+            .iter()
+            .filter(|e| e.status == OutboxStatus::Delivered)
+            .count();
+        let mut excess = delivered.saturating_sub(DELIVERED_RETENTION);
+        self.entries.retain(|e| {
+            if excess > 0 && e.status == OutboxStatus::Delivered {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+impl Outbox for InMemoryOutbox {
+    fn enqueue(&mut self, topic: &str, payload: serde_json::Value) -> Result<OutboxEntry, String> {
+        self.next_id += 1;
+        let now = timestamp(Utc::now());
+        let entry = OutboxEntry {
+            id: self.next_id,
+            topic: topic.to_string(),
+            payload,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now.clone(),
+            next_attempt_at: now,
+            delivered_at: None,
+        };
+        self.entries.push(entry.clone());
+        Ok(entry)
+    }
+
+    fn due(&self, now: DateTime<Utc>, limit: usize) -> Vec<OutboxEntry> {
+        let now = timestamp(now);
+        self.entries
+            .iter()
+            .filter(|e| e.status == OutboxStatus::Pending && e.next_attempt_at <= now)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn record_attempt(&mut self, id: u64, outcome: AttemptOutcome) -> Result<(), String> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .ok_or_else(|| format!("Outbox entry {} not found", id))?;
+        entry.attempts += 1;
+        match outcome {
+            AttemptOutcome::Delivered => {
+                entry.status = OutboxStatus::Delivered;
+                entry.delivered_at = Some(timestamp(Utc::now()));
+            }
+            AttemptOutcome::Retry {
+                error,
+                next_attempt_at,
+            } => {
+                entry.last_error = Some(error);
+                entry.next_attempt_at = timestamp(next_attempt_at);
+            }
+            AttemptOutcome::GaveUp { error } => {
+                entry.status = OutboxStatus::Failed;
+                entry.last_error = Some(error);
+            }
+        }
+        self.prune_delivered();
+        Ok(())
+    }
+
+    fn list(&self, status: Option<OutboxStatus>, limit: usize) -> Vec<OutboxEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|e| status.is_none_or(|s| e.status == s))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn counts(&self) -> BTreeMap<OutboxStatus, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in &self.entries {
+            *counts.entry(entry.status).or_default() += 1;
+        }
+        counts
+    }
+}
+
+/// Thread-safe wrapper for Outbox
+pub type SharedOutbox = Arc<RwLock<Box<dyn Outbox>>>;
+
+pub fn create_in_memory_outbox() -> SharedOutbox {
+    Arc::new(RwLock::new(Box::new(InMemoryOutbox::new())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_entry_lifecycle() {
+        let mut outbox = InMemoryOutbox::new();
+        let first = outbox
+            .enqueue("tightening_result", serde_json::json!({"id": 1}))
+            .unwrap();
+        let second = outbox
+            .enqueue("tightening_result", serde_json::json!({"id": 2}))
+            .unwrap();
+        let now = Utc::now() + Duration::milliseconds(1);
+        assert_eq!(outbox.due(now, 10).len(), 2);
+
+        outbox
+            .record_attempt(
+                first.id,
+                AttemptOutcome::Retry {
+                    error: "HTTP 503".into(),
+                    next_attempt_at: now + Duration::seconds(5),
+                },
+            )
+            .unwrap();
+        outbox
+            .record_attempt(second.id, AttemptOutcome::Delivered)
+            .unwrap();
+
+        assert!(outbox.due(now, 10).is_empty());
+        let retried = outbox.due(now + Duration::seconds(5), 10);
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].attempts, 1);
+        assert_eq!(retried[0].last_error.as_deref(), Some("HTTP 503"));
+
+        outbox
+            .record_attempt(
+                first.id,
+                AttemptOutcome::GaveUp {
+                    error: "HTTP 503".into(),
+                },
+            )
+            .unwrap();
+        let counts = outbox.counts();
+        assert_eq!(counts.get(&OutboxStatus::Failed), Some(&1));
+        assert_eq!(counts.get(&OutboxStatus::Delivered), Some(&1));
+        assert_eq!(
+            outbox.list(Some(OutboxStatus::Delivered), 10)[0].id,
+            second.id
+        );
+        assert!(
+            outbox
+                .record_attempt(99, AttemptOutcome::Delivered)
+                .is_err()
+        );
+    }
+}
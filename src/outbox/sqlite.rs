@@ -0,0 +1,204 @@
+//! SQLite persistence for the outbox (`sqlite` feature)
+
+use super::{
+    AttemptOutcome, DELIVERED_RETENTION, Outbox, OutboxEntry, OutboxStatus, SharedOutbox, timestamp,
+};
+use chrono::{DateTime, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Result as SqliteResult, params};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+const COLUMNS: &str = "id, topic, payload, status, attempts, last_error, created_at, \
+                       next_attempt_at, delivered_at";
+
+/// SQLite-backed outbox; pending entries survive restarts
+pub struct SqliteOutbox {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteOutbox {
+    /// Open the outbox table in the given database file
+    pub fn new(db_path: &str) -> Result<Self, String> {
+        let manager = SqliteConnectionManager::file(db_path);
+        let pool = Pool::new(manager).map_err(|e| format!("Failed to create pool: {}", e))?;
+
+        let outbox = Self { pool };
+        outbox.init_schema()?;
+        Ok(outbox)
+    }
+
+    fn init_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS outbox (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                topic TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                created_at TEXT NOT NULL,
+                next_attempt_at TEXT NOT NULL,
+                delivered_at TEXT
+            )",
+            [],
+        )
+        .map_err(|e| format!("Failed to create table: {}", e))?;
+        Ok(())
+    }
+
+    fn connection(&self) -> Result<r2d2::PooledConnection<SqliteConnectionManager>, String> {
+        self.pool
+            .get()
+            .map_err(|e| format!("Failed to get connection: {}", e))
+    }
+
+    fn row_to_entry(row: &rusqlite::Row) -> SqliteResult<OutboxEntry> {
+        let payload: String = row.get(2)?;
+        let status: String = row.get(3)?;
+        Ok(OutboxEntry {
+            id: row.get::<_, i64>(0)? as u64,
+            topic: row.get(1)?,
+            payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+            status: OutboxStatus::from_name(&status).unwrap_or(OutboxStatus::Failed),
+            attempts: row.get(4)?,
+            last_error: row.get(5)?,
+            created_at: row.get(6)?,
+            next_attempt_at: row.get(7)?,
+            delivered_at: row.get(8)?,
+        })
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Vec<OutboxEntry> {
+        let result = self.connection().and_then(|conn| {
+            let mut stmt = conn
+                .prepare(sql)
+                .map_err(|e| format!("Failed to prepare query: {}", e))?;
+            stmt.query_map(params, Self::row_to_entry)
+                .and_then(|rows| rows.collect::<SqliteResult<Vec<_>>>())
+                .map_err(|e| format!("Failed to read outbox: {}", e))
+        });
+        result.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Vec::new()
+        })
+    }
+}
+
+impl Outbox for SqliteOutbox {
+    fn enqueue(&mut self, topic: &str, payload: serde_json::Value) -> Result<OutboxEntry, String> {
+        let conn = self.connection()?;
+        let now = timestamp(Utc::now());
+        conn.execute(
+            "INSERT INTO outbox (topic, payload, created_at, next_attempt_at)
+             VALUES (?1, ?2, ?3, ?3)",
+            params![topic, payload.to_string(), now],
+        )
+        .map_err(|e| format!("Failed to enqueue: {}", e))?;
+
+        Ok(OutboxEntry {
+            id: conn.last_insert_rowid() as u64,
+            topic: topic.to_string(),
+            payload,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now.clone(),
+            next_attempt_at: now,
+            delivered_at: None,
+        })
+    }
+
+    fn due(&self, now: DateTime<Utc>, limit: usize) -> Vec<OutboxEntry> {
+        self.query(
+            &format!(
+                "SELECT {COLUMNS} FROM outbox
+                 WHERE status = 'pending' AND next_attempt_at <= ?1
+                 ORDER BY id LIMIT ?2"
+            ),
+            params![timestamp(now), limit as i64],
+        )
+    }
+
+    fn record_attempt(&mut self, id: u64, outcome: AttemptOutcome) -> Result<(), String> {
+        let conn = self.connection()?;
+        let updated = match outcome {
+            AttemptOutcome::Delivered => conn.execute(
+                "UPDATE outbox SET status = 'delivered', attempts = attempts + 1,
+                 delivered_at = ?2 WHERE id = ?1",
+                params![id as i64, timestamp(Utc::now())],
+            ),
+            AttemptOutcome::Retry {
+                error,
+                next_attempt_at,
+            } => conn.execute(
+                "UPDATE outbox SET attempts = attempts + 1, last_error = ?2,
+                 next_attempt_at = ?3 WHERE id = ?1",
+                params![id as i64, error, timestamp(next_attempt_at)],
+            ),
+            AttemptOutcome::GaveUp { error } => conn.execute(
+                "UPDATE outbox SET status = 'failed', attempts = attempts + 1,
+                 last_error = ?2 WHERE id = ?1",
+                params![id as i64, error],
+            ),
+        }
+        .map_err(|e| format!("Failed to update outbox entry {}: {}", id, e))?;
+        if updated == 0 {
+            return Err(format!("Outbox entry {} not found", id));
+        }
+
+        conn.execute(
+            "DELETE FROM outbox WHERE status = 'delivered' AND id NOT IN (
+                SELECT id FROM outbox WHERE status = 'delivered' ORDER BY id DESC LIMIT ?1
+             )",
+            params![DELIVERED_RETENTION as i64],
+        )
+        .map_err(|e| format!("Failed to prune outbox: {}", e))?;
+        Ok(())
+    }
+
+    fn list(&self, status: Option<OutboxStatus>, limit: usize) -> Vec<OutboxEntry> {
+        match status {
+            Some(status) => self.query(
+                &format!(
+                    "SELECT {COLUMNS} FROM outbox WHERE status = ?1 ORDER BY id DESC LIMIT ?2"
+                ),
+                params![status.as_str(), limit as i64],
+            ),
+            None => self.query(
+                &format!("SELECT {COLUMNS} FROM outbox ORDER BY id DESC LIMIT ?1"),
+                params![limit as i64],
+            ),
+        }
+    }
+
+    fn counts(&self) -> BTreeMap<OutboxStatus, usize> {
+        let result = self.connection().and_then(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT status, COUNT(*) FROM outbox GROUP BY status")
+                .map_err(|e| format!("Failed to prepare query: {}", e))?;
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })
+            .and_then(|rows| rows.collect::<SqliteResult<Vec<_>>>())
+            .map_err(|e| format!("Failed to count outbox: {}", e))
+        });
+        match result {
+            Ok(rows) => rows
+                .into_iter()
+                .filter_map(|(status, count)| Some((OutboxStatus::from_name(&status)?, count)))
+                .collect(),
+            Err(e) => {
+                eprintln!("{}", e);
+                BTreeMap::new()
+            }
+        }
+    }
+}
+
+/// Open the SQLite outbox in `db_path`
+pub fn create_sqlite_outbox(db_path: &str) -> Result<SharedOutbox, String> {
+    Ok(Arc::new(RwLock::new(Box::new(SqliteOutbox::new(db_path)?))))
+}
@@ -0,0 +1,378 @@
+//! Parquet export of the results history (`parquet` feature)
+//!
+//! Results are written one row per tightening with the same columns as the
+//! CSV export, and curves in long format (one row per sample), so the files
+//! can be loaded straight into a lakehouse table. Exports run on demand over
+//! HTTP or on a schedule configured in `[export]`.
+
+use crate::context::AppContext;
+use crate::curve::TighteningCurve;
+use crate::handler::data::TighteningResult;
+use crate::results::SharedResultRepository;
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, UInt32Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::AbortHandle;
+
+/// Failure while building or writing a Parquet file
+#[derive(Debug, Error)]
+pub enum ParquetExportError {
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] ArrowError),
+
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] ParquetError),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Write results (one row per tightening) as Parquet
+pub fn write_results<W: Write + Send>(
+    results: &[TighteningResult],
+    writer: W,
+) -> Result<(), ParquetExportError> {
+    let u32_column = |f: fn(&TighteningResult) -> u32| -> ArrayRef {
+        Arc::new(UInt32Array::from_iter_values(results.iter().map(f)))
+    };
+    let f64_column = |f: fn(&TighteningResult) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(results.iter().map(f)))
+    };
+    let bool_column = |f: fn(&TighteningResult) -> bool| -> ArrayRef {
+        Arc::new(BooleanArray::from_iter(results.iter().map(|r| Some(f(r)))))
+    };
+    let str_column = |f: fn(&TighteningResult) -> Option<&str>| -> ArrayRef {
+        Arc::new(StringArray::from_iter(results.iter().map(f)))
+    };
+
+    let columns: Vec<(&str, DataType, bool, ArrayRef)> = vec![
+        (
+            "tightening_id",
+            DataType::UInt32,
+            true,
+            Arc::new(UInt32Array::from_iter(
+                results.iter().map(|r| r.tightening_id),
+            )),
+        ),
+        (
+            "timestamp",
+            DataType::Utf8,
+            false,
+            str_column(|r| Some(&r.timestamp)),
+        ),
+        (
+            "pset_id",
+            DataType::UInt32,
+            false,
+            u32_column(|r| r.pset_id),
+        ),
+        ("job_id", DataType::UInt32, false, u32_column(|r| r.job_id)),
+        (
+            "vin",
+            DataType::Utf8,
+            true,
+            str_column(|r| r.vin_number.as_deref()),
+        ),
+        (
+            "batch_size",
+            DataType::UInt32,
+            false,
+            u32_column(|r| r.batch_size),
+        ),
+        (
+            "batch_counter",
+            DataType::UInt32,
+            false,
+            u32_column(|r| r.batch_counter),
+        ),
+        (
+            "tightening_status",
+            DataType::Boolean,
+            false,
+            bool_column(|r| r.tightening_status),
+        ),
+        (
+            "torque_status",
+            DataType::Boolean,
+            false,
+            bool_column(|r| r.torque_status),
+        ),
+        (
+            "angle_status",
+            DataType::Boolean,
+            false,
+            bool_column(|r| r.angle_status),
+        ),
+        ("torque", DataType::Float64, false, f64_column(|r| r.torque)),
+        (
+            "torque_min",
+            DataType::Float64,
+            false,
+            f64_column(|r| r.torque_min),
+        ),
+        (
+            "torque_max",
+            DataType::Float64,
+            false,
+            f64_column(|r| r.torque_max),
+        ),
+        (
+            "torque_target",
+            DataType::Float64,
+            false,
+            f64_column(|r| r.torque_target),
+        ),
+        ("angle", DataType::Float64, false, f64_column(|r| r.angle)),
+        (
+            "angle_min",
+            DataType::Float64,
+            false,
+            f64_column(|r| r.angle_min),
+        ),
+        (
+            "angle_max",
+            DataType::Float64,
+            false,
+            f64_column(|r| r.angle_max),
+        ),
+        (
+            "angle_target",
+            DataType::Float64,
+            false,
+            f64_column(|r| r.angle_target),
+        ),
+        (
+            "strategy",
+            DataType::Utf8,
+            false,
+            str_column(|r| Some(r.strategy.as_str())),
+        ),
+        (
+            "operator_id",
+            DataType::Utf8,
+            true,
+            str_column(|r| r.metadata.operator_id.as_deref()),
+        ),
+        (
+            "station",
+            DataType::Utf8,
+            true,
+            str_column(|r| r.metadata.station.as_deref()),
+        ),
+        (
+            "metadata",
+            DataType::Utf8,
+            false,
+            Arc::new(StringArray::from_iter_values(
+                results.iter().map(|r| r.metadata.extra_as_pairs()),
+            )),
+        ),
+        (
+            "signature",
+            DataType::Utf8,
+            true,
+            str_column(|r| r.signature.as_deref()),
+        ),
+    ];
+
+    write_batch(columns, writer)
+}
+
+/// Write curves in long format (one row per sample) as Parquet
+pub fn write_curves<W: Write + Send>(
+    curves: &[TighteningCurve],
+    writer: W,
+) -> Result<(), ParquetExportError> {
+    let samples = || {
+        curves.iter().flat_map(|curve| {
+            curve
+                .points
+                .iter()
+                .map(move |point| (curve.tightening_id, point))
+        })
+    };
+
+    let columns: Vec<(&str, DataType, bool, ArrayRef)> = vec![
+        (
+            "tightening_id",
+            DataType::UInt32,
+            false,
+            Arc::new(UInt32Array::from_iter_values(samples().map(|(id, _)| id))),
+        ),
+        (
+            "time_ms",
+            DataType::Float64,
+            false,
+            Arc::new(Float64Array::from_iter_values(
+                samples().map(|(_, p)| p.time_ms),
+            )),
+        ),
+        (
+            "angle",
+            DataType::Float64,
+            false,
+            Arc::new(Float64Array::from_iter_values(
+                samples().map(|(_, p)| p.angle),
+            )),
+        ),
+        (
+            "torque",
+            DataType::Float64,
+            false,
+            Arc::new(Float64Array::from_iter_values(
+                samples().map(|(_, p)| p.torque),
+            )),
+        ),
+    ];
+
+    write_batch(columns, writer)
+}
+
+fn write_batch<W: Write + Send>(
+    columns: Vec<(&str, DataType, bool, ArrayRef)>,
+    writer: W,
+) -> Result<(), ParquetExportError> {
+    let (fields, arrays): (Vec<_>, Vec<_>) = columns
+        .into_iter()
+        .map(|(name, data_type, nullable, array)| (Field::new(name, data_type, nullable), array))
+        .unzip();
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?;
+
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Curves stored for the given results, in the same order
+pub fn curves_for(
+    result_repo: &SharedResultRepository,
+    results: &[TighteningResult],
+) -> Vec<TighteningCurve> {
+    let repo = result_repo.read().unwrap();
+    results
+        .iter()
+        .filter_map(|r| r.tightening_id)
+        .filter_map(|id| repo.get_curve(id))
+        .collect()
+}
+
+/// Write the whole history into `dir` as `results-<timestamp>.parquet`
+/// (and `curves-<timestamp>.parquet` when `include_curves` is set)
+///
+/// Returns the paths of the written files.
+pub fn export_to_dir(
+    result_repo: &SharedResultRepository,
+    dir: &Path,
+    include_curves: bool,
+) -> Result<Vec<PathBuf>, ParquetExportError> {
+    std::fs::create_dir_all(dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let results = result_repo.read().unwrap().get_all();
+
+    let results_path = dir.join(format!("results-{}.parquet", stamp));
+    write_results(&results, std::fs::File::create(&results_path)?)?;
+    let mut written = vec![results_path];
+
+    if include_curves {
+        let curves_path = dir.join(format!("curves-{}.parquet", stamp));
+        let curves = curves_for(result_repo, &results);
+        write_curves(&curves, std::fs::File::create(&curves_path)?)?;
+        written.push(curves_path);
+    }
+
+    Ok(written)
+}
+
+/// Start the scheduled export if `[export] parquet_interval_secs` is set
+pub fn spawn_scheduled_export(ctx: &AppContext) -> Option<AbortHandle> {
+    let config = ctx.settings.export.clone();
+    if config.parquet_interval_secs == 0 {
+        return None;
+    }
+    let result_repo = Arc::clone(&ctx.result_repository);
+
+    println!(
+        "Parquet export every {}s into {:?}",
+        config.parquet_interval_secs, config.parquet_dir
+    );
+    let task = ctx.resources.track("parquet_export");
+    let handle = tokio::spawn(async move {
+        let _task = task;
+        let mut interval = tokio::time::interval(Duration::from_secs(config.parquet_interval_secs));
+        interval.tick().await; // the first tick completes immediately
+        loop {
+            interval.tick().await;
+            match export_to_dir(
+                &result_repo,
+                &config.parquet_dir,
+                config.parquet_include_curves,
+            ) {
+                Ok(paths) => println!("Parquet export written: {:?}", paths),
+                Err(e) => eprintln!("Parquet export failed: {}", e),
+            }
+        }
+    });
+    Some(handle.abort_handle())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::create_result_repository;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn row_count(data: Vec<u8>, name: &str) -> i64 {
+        let path = std::env::temp_dir().join(format!("{}_{}.parquet", name, std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let rows = reader.metadata().file_metadata().num_rows();
+        let _ = std::fs::remove_file(&path);
+        rows
+    }
+
+    #[test]
+    fn test_results_and_curves_round_trip_row_counts() {
+        let results: Vec<_> = (1..=3)
+            .map(|id| TighteningResult {
+                tightening_id: Some(id),
+                ..TighteningResult::example()
+            })
+            .collect();
+        let curves: Vec<_> = results
+            .iter()
+            .map(|r| TighteningCurve::generate(r, 500, 10))
+            .collect();
+
+        let mut data = Vec::new();
+        write_results(&results, &mut data).unwrap();
+        assert_eq!(row_count(data, "results"), 3);
+
+        let mut data = Vec::new();
+        write_curves(&curves, &mut data).unwrap();
+        assert_eq!(row_count(data, "curves"), 30);
+    }
+
+    #[test]
+    fn test_export_to_dir_writes_both_files() {
+        let dir = std::env::temp_dir().join(format!("parquet_export_{}", std::process::id()));
+        let repo = create_result_repository(10);
+        let result = TighteningResult::example();
+        repo.write()
+            .unwrap()
+            .add_curve(TighteningCurve::generate(&result, 500, 10));
+        repo.write().unwrap().add(result);
+
+        let paths = export_to_dir(&repo, &dir, true).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|path| path.exists()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
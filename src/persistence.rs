@@ -0,0 +1,46 @@
+//! Write-behind saving of state that survives restarts
+//!
+//! Lifetime statistics, sequence counters and user data live in
+//! [`DeviceState`](crate::state::DeviceState) and are saved behind it: a task
+//! saves whatever changed on every tick, and the same step runs once more on
+//! shutdown so changes made after the last tick are not lost.
+
+use crate::context::AppContext;
+use std::time::Duration;
+use tokio::task::AbortHandle;
+
+/// State saved to a store when it changes
+pub trait Persister: Send + 'static {
+    /// What is saved, for error messages
+    const NAME: &'static str;
+
+    /// Save what changed since the last successful save
+    ///
+    /// On error the changes are attempted again on the next call.
+    fn save_changes(&mut self) -> Result<(), String>;
+}
+
+/// Save the changes of `persister` every `interval`
+pub fn spawn_persistence(
+    ctx: &AppContext,
+    mut persister: impl Persister,
+    interval: Duration,
+) -> AbortHandle {
+    let task = ctx.resources.track("persistence");
+    tokio::spawn(async move {
+        let _task = task;
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            save_changes(&mut persister);
+        }
+    })
+    .abort_handle()
+}
+
+/// Save the changes of `persister` once, reporting a failure
+pub fn save_changes<P: Persister>(persister: &mut P) {
+    if let Err(e) = persister.save_changes() {
+        eprintln!("Failed to save {}: {}", P::NAME, e);
+    }
+}
@@ -1,3 +1,7 @@
+use crate::protocol::ProtocolError;
+use crate::protocol::units::{AngleDeciDeg, TorqueCnm};
+use crate::protocol::wire::{MAX_SIZE_FILL, TIMESTAMP_WIDTH, WireFormat};
+
 /// Represents a parameter field in Open Protocol data section
 #[derive(Debug, Clone)]
 pub struct Field {
@@ -18,51 +22,158 @@ impl Field {
         }
     }
 
-    /// Create a field from an integer value
-    pub fn from_int(id: Option<u8>, value: i32, width: usize) -> Self {
+    /// Create a field from an integer value, padded per the current [`WireFormat`]
+    ///
+    /// Fails with [`ProtocolError::ValueOverflow`] if the value does not fit
+    /// `width` characters; see [`Field::from_int_with`] for other policies.
+    pub fn from_int(id: Option<u8>, value: i64, width: usize) -> Result<Self, ProtocolError> {
+        Self::from_int_with(id, value, width, OverflowPolicy::Error)
+    }
+
+    /// Create a field from an integer value, fitting it to `width` per `policy`
+    pub fn from_int_with(
+        id: Option<u8>,
+        value: i64,
+        width: usize,
+        policy: OverflowPolicy,
+    ) -> Result<Self, ProtocolError> {
+        let value = policy.fit(value, width)?;
+        let id = id.map(|v| format!("{:02}", v));
+        Ok(Self {
+            id,
+            value: WireFormat::current().pad_int(value, width),
+        })
+    }
+
+    /// Create a torque field, written per the current [`WireFormat`]
+    ///
+    /// Nm × 100 by default; a personality may use another resolution or a
+    /// decimal mark (see [`WireFormat::format_torque`]). Measurements saturate:
+    /// a reading beyond the field is sent as its limit.
+    pub fn from_torque(id: Option<u8>, value: TorqueCnm, width: usize) -> Self {
         let id = id.map(|v| format!("{:02}", v));
         Self {
             id,
-            value: format!("{:0width$}", value, width = width),
+            value: WireFormat::current().format_torque(value, width),
         }
     }
 
     /// Create a field from a string value with fixed width (space-padded)
+    ///
+    /// The width counts characters, so a name with Latin-1 characters keeps its
+    /// layout when sent as ISO 8859-1.
     pub fn from_str(id: Option<u8>, value: impl AsRef<str>, width: usize) -> Self {
         let s = value.as_ref();
-        let padded = if s.len() >= width {
-            s[..width].to_string()
-        } else {
-            format!("{:<width$}", s, width = width)
+        let padded = match s.char_indices().nth(width) {
+            Some((end, _)) => s[..end].to_string(),
+            None => format!("{:<width$}", s, width = width),
         };
         let id = id.map(|v| format!("{:02}", v));
         Self { id, value: padded }
     }
 
+    /// Create a 19-character timestamp field in the layout of the current [`WireFormat`]
+    ///
+    /// `value` is `YYYY-MM-DD:HH:MM:SS`; a personality may separate date and
+    /// time with a space or leave out the zero padding (see [`TimestampFormat`](super::wire::TimestampFormat)).
+    pub fn from_timestamp(id: Option<u8>, value: impl AsRef<str>) -> Self {
+        let value = WireFormat::current().timestamp.render(value.as_ref());
+        Self::from_str(id, value, TIMESTAMP_WIDTH)
+    }
+
+    /// Create a free-text field (name, VIN, message text) with fixed width
+    ///
+    /// Space-padded like [`Field::from_str`], except in max-size mode (see
+    /// [`WireFormat::max_size_text`]) where the text is filled to the full width.
+    pub fn from_text(id: Option<u8>, value: impl AsRef<str>, width: usize) -> Self {
+        let value = value.as_ref();
+        if !WireFormat::current().max_size_text {
+            return Self::from_str(id, value, width);
+        }
+        let filled: String = value
+            .chars()
+            .chain(std::iter::repeat(MAX_SIZE_FILL))
+            .take(width)
+            .collect();
+        Self::from_str(id, filled, width)
+    }
+
     /// Serialize this field in Open Protocol format
     /// Format: [ParamID][Value]
     /// Note: Some Open Protocol versions include a length field, but this implementation
-    /// uses direct field serialization
+    /// uses direct field serialization. The value is encoded per the current [`WireFormat`].
     pub fn serialize(&self) -> Vec<u8> {
         let mut result = Vec::new();
         if let Some(id) = &self.id {
             result.extend_from_slice(id.as_bytes());
         }
 
-        result.extend_from_slice(self.value.as_bytes());
+        result.extend_from_slice(&WireFormat::current().encode(&self.value));
 
         result
     }
 }
 
+/// How an integer that does not fit its field width is written
+///
+/// A negative value spends one character of the width on its sign, so a
+/// six-character torque field holds -99999 to 999999.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Fail with [`ProtocolError::ValueOverflow`]
+    #[default]
+    Error,
+    /// Clamp to the largest (or most negative) value the width can hold
+    Saturate,
+    /// Keep the low-order digits and the sign, like a rolling counter
+    Wrap,
+}
+
+impl OverflowPolicy {
+    /// Fit `value` into `width` characters
+    pub fn fit(self, value: i64, width: usize) -> Result<i64, ProtocolError> {
+        let digits = |n: usize| u32::try_from(n).ok().and_then(|n| 10i64.checked_pow(n));
+        let max = digits(width).map(|limit| limit - 1);
+        let min = digits(width.saturating_sub(1)).map(|limit| 1 - limit);
+        let too_big = max.is_some_and(|max| value > max);
+        let too_small = min.is_some_and(|min| value < min);
+        if !too_big && !too_small {
+            return Ok(value);
+        }
+
+        match self {
+            OverflowPolicy::Error => Err(ProtocolError::ValueOverflow { value, width }),
+            OverflowPolicy::Saturate if too_big => Ok(max.unwrap_or(i64::MAX)),
+            OverflowPolicy::Saturate => Ok(min.unwrap_or(i64::MIN)),
+            OverflowPolicy::Wrap if too_big => Ok(value % digits(width).unwrap_or(i64::MAX)),
+            OverflowPolicy::Wrap => Ok(value % digits(width.saturating_sub(1)).unwrap_or(i64::MAX)),
+        }
+    }
+}
+
 /// Builder for constructing parameter fields
+///
+/// Integer fields follow the builder's [`OverflowPolicy`] (by default
+/// [`OverflowPolicy::Error`]); the first overflow is reported by [`build`](Self::build).
 pub struct FieldBuilder {
     fields: Vec<Field>,
+    overflow: OverflowPolicy,
+    error: Option<ProtocolError>,
 }
 
 impl FieldBuilder {
     pub fn new() -> Self {
-        Self { fields: Vec::new() }
+        Self {
+            fields: Vec::new(),
+            overflow: OverflowPolicy::default(),
+            error: None,
+        }
+    }
+
+    /// Use `policy` for integer fields added without an explicit one
+    pub fn with_overflow(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow = policy;
+        self
     }
 
     pub fn add_field(mut self, field: Field) -> Self {
@@ -70,20 +181,66 @@ impl FieldBuilder {
         self
     }
 
-    pub fn add_int(self, id: Option<u8>, value: i32, width: usize) -> Self {
-        self.add_field(Field::from_int(id, value, width))
+    pub fn add_int(self, id: Option<u8>, value: impl Into<i64>, width: usize) -> Self {
+        let policy = self.overflow;
+        self.add_int_with(id, value, width, policy)
+    }
+
+    /// Add an integer field with its own overflow policy
+    pub fn add_int_with(
+        mut self,
+        id: Option<u8>,
+        value: impl Into<i64>,
+        width: usize,
+        policy: OverflowPolicy,
+    ) -> Self {
+        match Field::from_int_with(id, value.into(), width, policy) {
+            Ok(field) => self.add_field(field),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self
+            }
+        }
     }
 
     pub fn add_str(self, id: Option<u8>, value: impl AsRef<str>, width: usize) -> Self {
         self.add_field(Field::from_str(id, value, width))
     }
 
-    pub fn build(self) -> Vec<u8> {
+    /// Add a free-text field, filled to its width in max-size mode
+    pub fn add_text(self, id: Option<u8>, value: impl AsRef<str>, width: usize) -> Self {
+        self.add_field(Field::from_text(id, value, width))
+    }
+
+    /// Add a timestamp field (`YYYY-MM-DD:HH:MM:SS` unless the [`WireFormat`] says otherwise)
+    pub fn add_timestamp(self, id: Option<u8>, value: impl AsRef<str>) -> Self {
+        self.add_field(Field::from_timestamp(id, value))
+    }
+
+    /// Add a torque field (Nm × 100 unless the [`WireFormat`] says otherwise)
+    ///
+    /// Measurements saturate: a reading beyond the field is sent as its limit.
+    pub fn add_torque(self, id: Option<u8>, value: TorqueCnm, width: usize) -> Self {
+        self.add_field(Field::from_torque(id, value, width))
+    }
+
+    /// Add an angle field (whole degrees)
+    ///
+    /// Measurements saturate: a reading beyond the field is sent as its limit.
+    pub fn add_angle(self, id: Option<u8>, value: AngleDeciDeg, width: usize) -> Self {
+        self.add_int_with(id, value.wire_value(), width, OverflowPolicy::Saturate)
+    }
+
+    /// Concatenate the fields, or report the first value that did not fit
+    pub fn build(self) -> Result<Vec<u8>, ProtocolError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
         let mut result = Vec::new();
         for field in self.fields {
             result.extend_from_slice(&field.serialize());
         }
-        result
+        Ok(result)
     }
 }
 
@@ -93,6 +250,104 @@ impl Default for FieldBuilder {
     }
 }
 
+/// Reader for fixed-width parameter fields, the counterpart of [`FieldBuilder`]
+///
+/// Each read takes the same `(id, width)` pair the builder was given, checks
+/// the two-digit parameter ID when one is expected, and advances past the value.
+pub struct FieldParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> FieldParser<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Offset of the next unread byte
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of unread bytes
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Read a space-padded string field (trailing spaces removed)
+    pub fn read_str(&mut self, id: Option<u8>, width: usize) -> Result<String, ProtocolError> {
+        self.expect_id(id)?;
+        let offset = self.pos;
+        let raw = self.take(width)?;
+        let value = std::str::from_utf8(raw).map_err(|_| ProtocolError::InvalidField {
+            offset,
+            value: String::from_utf8_lossy(raw).into_owned(),
+        })?;
+        Ok(value.trim_end().to_string())
+    }
+
+    /// Read a zero-padded integer field
+    pub fn read_int(&mut self, id: Option<u8>, width: usize) -> Result<i32, ProtocolError> {
+        let offset = self.pos + if id.is_some() { 2 } else { 0 };
+        let value = self.read_str(id, width)?;
+        value
+            .trim()
+            .parse()
+            .map_err(|_| ProtocolError::InvalidField { offset, value })
+    }
+
+    /// Read a torque field (Nm × 100)
+    pub fn read_torque(
+        &mut self,
+        id: Option<u8>,
+        width: usize,
+    ) -> Result<TorqueCnm, ProtocolError> {
+        self.read_int(id, width).map(TorqueCnm)
+    }
+
+    /// Read an angle field (whole degrees)
+    pub fn read_angle(
+        &mut self,
+        id: Option<u8>,
+        width: usize,
+    ) -> Result<AngleDeciDeg, ProtocolError> {
+        self.read_int(id, width)
+            .map(|degrees| AngleDeciDeg(degrees * 10))
+    }
+
+    fn expect_id(&mut self, id: Option<u8>) -> Result<(), ProtocolError> {
+        let Some(expected) = id else {
+            return Ok(());
+        };
+        let offset = self.pos;
+        let raw = self.take(2)?;
+        if raw != format!("{:02}", expected).as_bytes() {
+            return Err(ProtocolError::UnexpectedParameter {
+                expected,
+                found: String::from_utf8_lossy(raw).into_owned(),
+                offset,
+            });
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, width: usize) -> Result<&'a [u8], ProtocolError> {
+        if self.remaining() < width {
+            return Err(ProtocolError::UnexpectedEnd {
+                offset: self.pos,
+                needed: width - self.remaining(),
+            });
+        }
+        let raw = &self.data[self.pos..self.pos + width];
+        self.pos += width;
+        Ok(raw)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,7 +363,7 @@ mod tests {
 
     #[test]
     fn test_int_field() {
-        let field = Field::from_int(Some(2), 123, 5);
+        let field = Field::from_int(Some(2), 123, 5).unwrap();
         let serialized = field.serialize();
         assert_eq!(serialized, b"0200123");
     }
@@ -125,7 +380,8 @@ mod tests {
         let data = FieldBuilder::new()
             .add_int(Some(1), 100, 3)
             .add_str(Some(2), "TEST", 5)
-            .build();
+            .build()
+            .unwrap();
 
         // Verify the data contains both fields
         assert!(!data.is_empty());
@@ -142,4 +398,176 @@ mod tests {
         // Should contain the test string
         assert!(data_str.contains("TEST"));
     }
+
+    #[test]
+    fn test_str_field_counts_characters() {
+        let field = Field::from_str(None, "Müller", 8);
+        assert_eq!(field.value, "Müller  ");
+        let field = Field::from_str(None, "ÄÖÜäöü", 3);
+        assert_eq!(field.value, "ÄÖÜ");
+    }
+
+    #[test]
+    fn test_builder_applies_wire_format() {
+        use crate::protocol::wire::{NumericPadding, TextEncoding};
+
+        let format = WireFormat {
+            encoding: TextEncoding::Latin1,
+            numeric_padding: NumericPadding::Space,
+            ..WireFormat::default()
+        };
+        let data = format.sync_scope(|| {
+            FieldBuilder::new()
+                .add_int(Some(1), 7, 3)
+                .add_str(Some(2), "Zoë", 5)
+                .build()
+                .unwrap()
+        });
+        assert_eq!(data, b"01  702Zo\xEB  ");
+    }
+
+    #[test]
+    fn test_timestamp_field_follows_wire_format() {
+        use crate::protocol::wire::{DateTimeSeparator, TimestampFormat};
+
+        let format = WireFormat {
+            timestamp: TimestampFormat {
+                separator: DateTimeSeparator::Space,
+                zero_padded: false,
+            },
+            ..WireFormat::default()
+        };
+        let build = || {
+            FieldBuilder::new()
+                .add_timestamp(Some(20), "2025-01-05:08:03:09")
+                .build()
+                .unwrap()
+        };
+        assert_eq!(build(), b"202025-01-05:08:03:09");
+        assert_eq!(format.sync_scope(build), b"202025-1-5 8:3:9     ");
+    }
+
+    #[test]
+    fn test_max_size_mode_fills_text_fields() {
+        let format = WireFormat {
+            max_size_text: true,
+            ..WireFormat::default()
+        };
+        let data = format.sync_scope(|| {
+            FieldBuilder::new()
+                .add_text(Some(1), "VIN1", 8)
+                .add_text(Some(2), "", 3)
+                .add_str(Some(3), "2024", 6)
+                .build()
+                .unwrap()
+        });
+        // Timestamps and other formatted fields keep their padding
+        assert_eq!(data, b"01VIN1XXXX02XXX032024  ");
+
+        let data = FieldBuilder::new()
+            .add_text(None, "VIN1", 6)
+            .build()
+            .unwrap();
+        assert_eq!(data, b"VIN1  ");
+    }
+
+    #[test]
+    fn test_parser_reads_builder_output() {
+        let data = FieldBuilder::new()
+            .add_int(Some(1), 42, 4)
+            .add_str(Some(2), "SIM", 10)
+            .add_torque(Some(3), TorqueCnm(1250), 6)
+            .add_angle(None, AngleDeciDeg(400), 5)
+            .build()
+            .unwrap();
+
+        let mut parser = FieldParser::new(&data);
+        assert_eq!(parser.read_int(Some(1), 4).unwrap(), 42);
+        assert_eq!(parser.read_str(Some(2), 10).unwrap(), "SIM");
+        assert_eq!(parser.read_torque(Some(3), 6).unwrap(), TorqueCnm(1250));
+        assert_eq!(parser.read_angle(None, 5).unwrap(), AngleDeciDeg(400));
+        assert!(parser.is_empty());
+    }
+
+    #[test]
+    fn test_overflow_policies() {
+        assert_eq!(OverflowPolicy::Error.fit(99999, 5).unwrap(), 99999);
+        assert_eq!(OverflowPolicy::Error.fit(-9999, 5).unwrap(), -9999);
+        assert!(matches!(
+            OverflowPolicy::Error.fit(100000, 5),
+            Err(ProtocolError::ValueOverflow {
+                value: 100000,
+                width: 5
+            })
+        ));
+        assert!(OverflowPolicy::Error.fit(-10000, 5).is_err());
+
+        assert_eq!(OverflowPolicy::Saturate.fit(123456, 5).unwrap(), 99999);
+        assert_eq!(OverflowPolicy::Saturate.fit(-123456, 5).unwrap(), -9999);
+        assert_eq!(OverflowPolicy::Saturate.fit(-1, 1).unwrap(), 0);
+
+        assert_eq!(OverflowPolicy::Wrap.fit(123456, 5).unwrap(), 23456);
+        assert_eq!(OverflowPolicy::Wrap.fit(-123456, 5).unwrap(), -3456);
+        assert_eq!(
+            OverflowPolicy::Wrap.fit(12_345_678_901, 10).unwrap(),
+            2_345_678_901
+        );
+    }
+
+    #[test]
+    fn test_negative_torque_offsets() {
+        let data = FieldBuilder::new()
+            .add_torque(Some(1), TorqueCnm(-150), 6)
+            .add_torque(Some(2), TorqueCnm(-1_234_567), 6)
+            .add_angle(Some(3), AngleDeciDeg(-50), 5)
+            .build()
+            .unwrap();
+        assert_eq!(data, b"01-0015002-9999903-0005");
+
+        let mut parser = FieldParser::new(&data);
+        assert_eq!(parser.read_torque(Some(1), 6).unwrap(), TorqueCnm(-150));
+    }
+
+    #[test]
+    fn test_builder_reports_first_overflow() {
+        let result = FieldBuilder::new()
+            .add_int(Some(1), 1234, 3)
+            .add_int(Some(2), 56789, 4)
+            .build();
+        assert!(matches!(
+            result,
+            Err(ProtocolError::ValueOverflow {
+                value: 1234,
+                width: 3
+            })
+        ));
+
+        let data = FieldBuilder::new()
+            .with_overflow(OverflowPolicy::Wrap)
+            .add_int(None, 1234, 3)
+            .build()
+            .unwrap();
+        assert_eq!(data, b"234");
+    }
+
+    #[test]
+    fn test_parser_reports_mismatches() {
+        let mut parser = FieldParser::new(b"0212");
+        assert!(matches!(
+            parser.read_int(Some(1), 2),
+            Err(ProtocolError::UnexpectedParameter { expected: 1, .. })
+        ));
+
+        let mut parser = FieldParser::new(b"01AB");
+        assert!(matches!(
+            parser.read_int(Some(1), 2),
+            Err(ProtocolError::InvalidField { offset: 2, .. })
+        ));
+
+        let mut parser = FieldParser::new(b"0112");
+        assert!(matches!(
+            parser.read_int(Some(1), 4),
+            Err(ProtocolError::UnexpectedEnd { needed: 2, .. })
+        ));
+    }
 }
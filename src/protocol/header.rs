@@ -0,0 +1,106 @@
+//! Optional header fields (bytes 11-19)
+//!
+//! After length, MID and revision the header carries a No Ack flag, station
+//! and spindle IDs, a sequence number and the part numbering of multi-part
+//! messages. Most clients leave them blank; a blank field is `None` here and
+//! is written back as spaces.
+
+use std::fmt::Write;
+
+/// Optional header fields of a message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeaderOptions {
+    /// No Ack flag: the data needs no acknowledgment. On a subscribe MID the
+    /// client asks for the pushed data to be fire-and-forget.
+    pub no_ack: bool,
+    /// Station ID (00-99)
+    pub station_id: Option<u8>,
+    /// Spindle ID (00-99)
+    pub spindle_id: Option<u8>,
+    /// Sequence number (00-99) for link-level acknowledgment
+    pub sequence: Option<u8>,
+    /// Number of parts and this message's part number (1-9) of a multi-part message
+    pub parts: Option<(u8, u8)>,
+}
+
+impl HeaderOptions {
+    /// Read the optional fields of a header
+    ///
+    /// Lenient like most controllers: a field that is blank or not a number
+    /// is treated as absent.
+    pub fn parse(header: &[u8]) -> Self {
+        let field = |range: std::ops::Range<usize>| {
+            header
+                .get(range)
+                .and_then(|raw| std::str::from_utf8(raw).ok())
+                .and_then(|s| s.trim().parse::<u8>().ok())
+        };
+        let parts = match (field(18..19), field(19..20)) {
+            (Some(count), Some(part)) => Some((count, part)),
+            _ => None,
+        };
+        Self {
+            no_ack: header.get(11) == Some(&b'1'),
+            station_id: field(12..14),
+            spindle_id: field(14..16),
+            sequence: field(16..18),
+            parts,
+        }
+    }
+
+    /// The nine header bytes following the revision
+    pub fn encode(&self) -> [u8; 9] {
+        let mut text = String::with_capacity(9);
+        text.push(if self.no_ack { '1' } else { ' ' });
+        for value in [self.station_id, self.spindle_id, self.sequence] {
+            match value {
+                Some(value) => write!(text, "{:02}", value % 100).unwrap(),
+                None => text.push_str("  "),
+            }
+        }
+        match self.parts {
+            Some((count, part)) => write!(text, "{}{}", count % 10, part % 10).unwrap(),
+            None => text.push_str("  "),
+        }
+
+        let mut bytes = [b' '; 9];
+        bytes.copy_from_slice(text.as_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blank_options_round_trip() {
+        let options = HeaderOptions::parse(b"00200001001         ");
+        assert_eq!(options, HeaderOptions::default());
+        assert_eq!(&options.encode(), b"         ");
+    }
+
+    #[test]
+    fn test_options_round_trip() {
+        let options = HeaderOptions {
+            no_ack: true,
+            station_id: Some(1),
+            spindle_id: Some(2),
+            sequence: Some(42),
+            parts: Some((3, 2)),
+        };
+        let mut header = b"00200061001".to_vec();
+        header.extend_from_slice(&options.encode());
+        assert_eq!(&header[11..], b"101024232");
+        assert_eq!(HeaderOptions::parse(&header), options);
+    }
+
+    #[test]
+    fn test_parse_ignores_garbage() {
+        let options = HeaderOptions::parse(b"00200060001 XX 7    ");
+        assert!(!options.no_ack);
+        assert_eq!(options.station_id, None);
+        assert_eq!(options.spindle_id, Some(7));
+        assert_eq!(options.parts, None);
+    }
+}
@@ -0,0 +1,648 @@
+//! Open Protocol message IDs
+//!
+//! [`Mid`] wraps the raw header value so unknown MIDs can still be carried,
+//! while the associated constants name the MIDs the simulator knows about.
+//! [`MID_TABLE`] records how they relate: which MID acknowledges a pushed
+//! data MID and which subscribe/unsubscribe pair controls it. Session
+//! subscription tracking and broadcast routing are derived from the table,
+//! so a new subscription family only needs its entries here.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An Open Protocol message ID (the 4-digit MID header field)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Mid(pub u16);
+
+impl Mid {
+    pub const COMMUNICATION_START: Mid = Mid(1);
+    pub const COMMUNICATION_START_ACK: Mid = Mid(2);
+    pub const COMMUNICATION_STOP: Mid = Mid(3);
+    pub const COMMAND_ERROR: Mid = Mid(4);
+    pub const COMMAND_ACCEPTED: Mid = Mid(5);
+
+    pub const PSET_ID_UPLOAD_REQUEST: Mid = Mid(10);
+    pub const PSET_ID_UPLOAD: Mid = Mid(11);
+
+    pub const PSET_SELECTED_SUBSCRIBE: Mid = Mid(14);
+    pub const PSET_SELECTED: Mid = Mid(15);
+    pub const PSET_SELECTED_ACK: Mid = Mid(16);
+    pub const PSET_SELECTED_UNSUBSCRIBE: Mid = Mid(17);
+    pub const SELECT_PSET: Mid = Mid(18);
+    pub const SET_PSET_BATCH_SIZE: Mid = Mid(19);
+    pub const RESET_PSET_BATCH_COUNTER: Mid = Mid(20);
+    pub const LOCK_AT_BATCH_DONE_SUBSCRIBE: Mid = Mid(21);
+    pub const LOCK_AT_BATCH_DONE: Mid = Mid(22);
+    pub const LOCK_AT_BATCH_DONE_ACK: Mid = Mid(23);
+    pub const LOCK_AT_BATCH_DONE_UNSUBSCRIBE: Mid = Mid(24);
+
+    pub const JOB_ID_UPLOAD_REQUEST: Mid = Mid(30);
+    pub const JOB_ID_UPLOAD: Mid = Mid(31);
+    pub const JOB_DATA_UPLOAD_REQUEST: Mid = Mid(32);
+    pub const JOB_DATA_UPLOAD: Mid = Mid(33);
+    pub const JOB_INFO_SUBSCRIBE: Mid = Mid(34);
+    pub const JOB_INFO: Mid = Mid(35);
+    pub const JOB_INFO_ACK: Mid = Mid(36);
+    pub const JOB_INFO_UNSUBSCRIBE: Mid = Mid(37);
+    pub const SELECT_JOB: Mid = Mid(38);
+    pub const RESTART_JOB: Mid = Mid(39);
+
+    pub const TOOL_DATA_UPLOAD_REQUEST: Mid = Mid(40);
+    pub const TOOL_DATA_UPLOAD: Mid = Mid(41);
+    pub const DISABLE_TOOL: Mid = Mid(42);
+    pub const ENABLE_TOOL: Mid = Mid(43);
+
+    pub const VEHICLE_ID_DOWNLOAD: Mid = Mid(50);
+    pub const VEHICLE_ID_SUBSCRIBE: Mid = Mid(51);
+    pub const VEHICLE_ID: Mid = Mid(52);
+    pub const VEHICLE_ID_ACK: Mid = Mid(53);
+    pub const VEHICLE_ID_UNSUBSCRIBE: Mid = Mid(54);
+
+    pub const TIGHTENING_RESULT_SUBSCRIBE: Mid = Mid(60);
+    pub const TIGHTENING_RESULT: Mid = Mid(61);
+    pub const TIGHTENING_RESULT_ACK: Mid = Mid(62);
+    pub const TIGHTENING_RESULT_UNSUBSCRIBE: Mid = Mid(63);
+
+    pub const ALARM_SUBSCRIBE: Mid = Mid(70);
+    pub const ALARM: Mid = Mid(71);
+    pub const ALARM_ACK: Mid = Mid(72);
+    pub const ALARM_UNSUBSCRIBE: Mid = Mid(73);
+    pub const ALARM_ACKNOWLEDGED: Mid = Mid(74);
+    pub const ALARM_ACKNOWLEDGED_ACK: Mid = Mid(75);
+
+    pub const MULTI_SPINDLE_STATUS_SUBSCRIBE: Mid = Mid(90);
+    pub const MULTI_SPINDLE_STATUS: Mid = Mid(91);
+    pub const MULTI_SPINDLE_STATUS_UNSUBSCRIBE: Mid = Mid(92);
+    pub const MULTI_SPINDLE_STATUS_ACK: Mid = Mid(93);
+
+    pub const MULTI_SPINDLE_RESULT_SUBSCRIBE: Mid = Mid(100);
+    pub const MULTI_SPINDLE_RESULT: Mid = Mid(101);
+    pub const MULTI_SPINDLE_RESULT_ACK: Mid = Mid(102);
+    pub const MULTI_SPINDLE_RESULT_UNSUBSCRIBE: Mid = Mid(103);
+
+    pub const JOB_BATCH_INCREMENT: Mid = Mid(128);
+
+    pub const USER_DATA_DOWNLOAD: Mid = Mid(240);
+    pub const USER_DATA_SUBSCRIBE: Mid = Mid(241);
+    pub const USER_DATA_UPLOAD: Mid = Mid(242);
+    pub const USER_DATA_ACK: Mid = Mid(243);
+    pub const USER_DATA_UNSUBSCRIBE: Mid = Mid(244);
+    pub const USER_DATA_DOWNLOAD_WITH_OFFSET: Mid = Mid(245);
+
+    /// Simulator extension (see `handler::event_log_upload`)
+    pub const EVENT_LOG_UPLOAD_REQUEST: Mid = Mid(9100);
+    /// Simulator extension (see `handler::event_log_upload`)
+    pub const EVENT_LOG_UPLOAD: Mid = Mid(9101);
+    /// Simulator extension (see `handler::operator_login`)
+    pub const OPERATOR_LOGIN: Mid = Mid(9110);
+    /// Simulator extension (see `handler::operator_logout`)
+    pub const OPERATOR_LOGOUT: Mid = Mid(9111);
+    /// Simulator extension (see `handler::telemetry_upload`)
+    pub const TELEMETRY_UPLOAD_REQUEST: Mid = Mid(9120);
+    /// Simulator extension (see `handler::telemetry_upload`)
+    pub const TELEMETRY_UPLOAD: Mid = Mid(9121);
+    /// Simulator extension (see `handler::alarm_language`)
+    pub const ALARM_LANGUAGE_SELECT: Mid = Mid(9130);
+
+    pub const KEEP_ALIVE: Mid = Mid(9999);
+
+    pub const fn new(mid: u16) -> Self {
+        Self(mid)
+    }
+
+    pub const fn value(self) -> u16 {
+        self.0
+    }
+
+    /// Metadata for this MID, if it is in [`MID_TABLE`]
+    pub fn info(self) -> Option<&'static MidInfo> {
+        MID_TABLE.iter().find(|info| info.mid == self)
+    }
+
+    /// Human-readable name, `"Unknown"` for MIDs outside the table
+    pub fn name(self) -> &'static str {
+        self.info().map_or("Unknown", |info| info.name)
+    }
+
+    /// Whether this MID asks the controller to change its state
+    ///
+    /// Observer connections are refused these (see
+    /// [`ObserverLayer`](crate::handler::middleware::ObserverLayer)).
+    pub fn changes_state(self) -> bool {
+        STATE_CHANGING_MIDS.contains(&self)
+    }
+
+    /// Subscription change requested by receiving this MID, if any
+    pub fn subscription_request(self) -> Option<SubscriptionRequest> {
+        let family = self.info()?.subscription?;
+        if self == family.subscribe {
+            Some(SubscriptionRequest::Subscribe(family.kind))
+        } else if self == family.unsubscribe {
+            Some(SubscriptionRequest::Unsubscribe(family.kind))
+        } else {
+            None
+        }
+    }
+}
+
+impl From<u16> for Mid {
+    fn from(mid: u16) -> Self {
+        Self(mid)
+    }
+}
+
+impl From<Mid> for u16 {
+    fn from(mid: Mid) -> Self {
+        mid.0
+    }
+}
+
+/// Formats as the zero-padded header value, e.g. `0061`
+impl fmt::Display for Mid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}", self.0)
+    }
+}
+
+/// Event stream a client can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionKind {
+    PsetSelection,
+    VehicleId,
+    TighteningResult,
+    Alarm,
+    MultiSpindleStatus,
+    MultiSpindleResult,
+    UserData,
+    LockAtBatchDone,
+    JobInfo,
+}
+
+impl SubscriptionKind {
+    /// The subscribe/unsubscribe/data MIDs of this family
+    pub fn mids(self) -> SubscriptionMids {
+        MID_TABLE
+            .iter()
+            .filter_map(|info| info.subscription)
+            .find(|family| family.kind == self)
+            .expect("every subscription kind has a family in the MID table")
+    }
+
+    /// MID pushed to subscribers
+    pub fn data_mid(self) -> Mid {
+        self.mids().data
+    }
+
+    /// MID the client acknowledges pushed data with
+    pub fn ack_mid(self) -> Option<Mid> {
+        self.data_mid().info().and_then(|info| info.ack)
+    }
+}
+
+/// Subscription change carried by a subscribe or unsubscribe MID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionRequest {
+    Subscribe(SubscriptionKind),
+    Unsubscribe(SubscriptionKind),
+}
+
+/// The MIDs that make up a subscription family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionMids {
+    pub kind: SubscriptionKind,
+    pub subscribe: Mid,
+    pub unsubscribe: Mid,
+    /// Data MID pushed to subscribers
+    pub data: Mid,
+}
+
+/// Static description of a MID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MidInfo {
+    pub mid: Mid,
+    pub name: &'static str,
+    /// MID the receiver acknowledges this message with (pushed data MIDs)
+    pub ack: Option<Mid>,
+    /// Subscription family this MID belongs to
+    pub subscription: Option<SubscriptionMids>,
+}
+
+const fn info(mid: Mid, name: &'static str) -> MidInfo {
+    MidInfo {
+        mid,
+        name,
+        ack: None,
+        subscription: None,
+    }
+}
+
+const fn acked(mid: Mid, name: &'static str, ack: Mid) -> MidInfo {
+    MidInfo {
+        mid,
+        name,
+        ack: Some(ack),
+        subscription: None,
+    }
+}
+
+const fn family(
+    kind: SubscriptionKind,
+    subscribe: Mid,
+    unsubscribe: Mid,
+    data: Mid,
+) -> SubscriptionMids {
+    SubscriptionMids {
+        kind,
+        subscribe,
+        unsubscribe,
+        data,
+    }
+}
+
+const PSET_SELECTED_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::PsetSelection,
+    Mid::PSET_SELECTED_SUBSCRIBE,
+    Mid::PSET_SELECTED_UNSUBSCRIBE,
+    Mid::PSET_SELECTED,
+);
+const LOCK_AT_BATCH_DONE_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::LockAtBatchDone,
+    Mid::LOCK_AT_BATCH_DONE_SUBSCRIBE,
+    Mid::LOCK_AT_BATCH_DONE_UNSUBSCRIBE,
+    Mid::LOCK_AT_BATCH_DONE,
+);
+const JOB_INFO_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::JobInfo,
+    Mid::JOB_INFO_SUBSCRIBE,
+    Mid::JOB_INFO_UNSUBSCRIBE,
+    Mid::JOB_INFO,
+);
+const VEHICLE_ID_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::VehicleId,
+    Mid::VEHICLE_ID_SUBSCRIBE,
+    Mid::VEHICLE_ID_UNSUBSCRIBE,
+    Mid::VEHICLE_ID,
+);
+const TIGHTENING_RESULT_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::TighteningResult,
+    Mid::TIGHTENING_RESULT_SUBSCRIBE,
+    Mid::TIGHTENING_RESULT_UNSUBSCRIBE,
+    Mid::TIGHTENING_RESULT,
+);
+const ALARM_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::Alarm,
+    Mid::ALARM_SUBSCRIBE,
+    Mid::ALARM_UNSUBSCRIBE,
+    Mid::ALARM,
+);
+const MULTI_SPINDLE_STATUS_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::MultiSpindleStatus,
+    Mid::MULTI_SPINDLE_STATUS_SUBSCRIBE,
+    Mid::MULTI_SPINDLE_STATUS_UNSUBSCRIBE,
+    Mid::MULTI_SPINDLE_STATUS,
+);
+const MULTI_SPINDLE_RESULT_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::MultiSpindleResult,
+    Mid::MULTI_SPINDLE_RESULT_SUBSCRIBE,
+    Mid::MULTI_SPINDLE_RESULT_UNSUBSCRIBE,
+    Mid::MULTI_SPINDLE_RESULT,
+);
+
+const USER_DATA_FAMILY: SubscriptionMids = family(
+    SubscriptionKind::UserData,
+    Mid::USER_DATA_SUBSCRIBE,
+    Mid::USER_DATA_UNSUBSCRIBE,
+    Mid::USER_DATA_UPLOAD,
+);
+
+const fn member(mid: Mid, name: &'static str, family: SubscriptionMids) -> MidInfo {
+    MidInfo {
+        mid,
+        name,
+        ack: None,
+        subscription: Some(family),
+    }
+}
+
+const fn pushed(mid: Mid, name: &'static str, ack: Mid, family: SubscriptionMids) -> MidInfo {
+    MidInfo {
+        mid,
+        name,
+        ack: Some(ack),
+        subscription: Some(family),
+    }
+}
+
+/// Requests that change the controller state rather than read or subscribe
+const STATE_CHANGING_MIDS: &[Mid] = &[
+    Mid::SELECT_PSET,
+    Mid::SET_PSET_BATCH_SIZE,
+    Mid::RESET_PSET_BATCH_COUNTER,
+    Mid::SELECT_JOB,
+    Mid::RESTART_JOB,
+    Mid::DISABLE_TOOL,
+    Mid::ENABLE_TOOL,
+    Mid::VEHICLE_ID_DOWNLOAD,
+    Mid::JOB_BATCH_INCREMENT,
+    Mid::USER_DATA_DOWNLOAD,
+    Mid::USER_DATA_DOWNLOAD_WITH_OFFSET,
+    Mid::OPERATOR_LOGIN,
+    Mid::OPERATOR_LOGOUT,
+];
+
+/// Every MID the simulator sends or understands
+pub const MID_TABLE: &[MidInfo] = &[
+    info(Mid::COMMUNICATION_START, "Communication start"),
+    info(
+        Mid::COMMUNICATION_START_ACK,
+        "Communication start acknowledge",
+    ),
+    info(Mid::COMMUNICATION_STOP, "Communication stop"),
+    info(Mid::COMMAND_ERROR, "Command error"),
+    info(Mid::COMMAND_ACCEPTED, "Command accepted"),
+    acked(
+        Mid::PSET_ID_UPLOAD_REQUEST,
+        "Parameter set ID upload request",
+        Mid::PSET_ID_UPLOAD,
+    ),
+    info(Mid::PSET_ID_UPLOAD, "Parameter set ID upload reply"),
+    member(
+        Mid::PSET_SELECTED_SUBSCRIBE,
+        "Parameter set selected subscribe",
+        PSET_SELECTED_FAMILY,
+    ),
+    pushed(
+        Mid::PSET_SELECTED,
+        "Parameter set selected",
+        Mid::PSET_SELECTED_ACK,
+        PSET_SELECTED_FAMILY,
+    ),
+    info(Mid::PSET_SELECTED_ACK, "Parameter set selected acknowledge"),
+    member(
+        Mid::PSET_SELECTED_UNSUBSCRIBE,
+        "Parameter set selected unsubscribe",
+        PSET_SELECTED_FAMILY,
+    ),
+    info(Mid::SELECT_PSET, "Select parameter set"),
+    info(Mid::SET_PSET_BATCH_SIZE, "Set parameter set batch size"),
+    info(
+        Mid::RESET_PSET_BATCH_COUNTER,
+        "Reset parameter set batch counter",
+    ),
+    member(
+        Mid::LOCK_AT_BATCH_DONE_SUBSCRIBE,
+        "Lock at batch done subscribe",
+        LOCK_AT_BATCH_DONE_FAMILY,
+    ),
+    pushed(
+        Mid::LOCK_AT_BATCH_DONE,
+        "Lock at batch done upload",
+        Mid::LOCK_AT_BATCH_DONE_ACK,
+        LOCK_AT_BATCH_DONE_FAMILY,
+    ),
+    info(
+        Mid::LOCK_AT_BATCH_DONE_ACK,
+        "Lock at batch done upload acknowledge",
+    ),
+    member(
+        Mid::LOCK_AT_BATCH_DONE_UNSUBSCRIBE,
+        "Lock at batch done unsubscribe",
+        LOCK_AT_BATCH_DONE_FAMILY,
+    ),
+    acked(
+        Mid::JOB_ID_UPLOAD_REQUEST,
+        "Job ID upload request",
+        Mid::JOB_ID_UPLOAD,
+    ),
+    info(Mid::JOB_ID_UPLOAD, "Job ID upload reply"),
+    acked(
+        Mid::JOB_DATA_UPLOAD_REQUEST,
+        "Job data upload request",
+        Mid::JOB_DATA_UPLOAD,
+    ),
+    info(Mid::JOB_DATA_UPLOAD, "Job data upload reply"),
+    member(
+        Mid::JOB_INFO_SUBSCRIBE,
+        "Job info subscribe",
+        JOB_INFO_FAMILY,
+    ),
+    pushed(
+        Mid::JOB_INFO,
+        "Job info",
+        Mid::JOB_INFO_ACK,
+        JOB_INFO_FAMILY,
+    ),
+    info(Mid::JOB_INFO_ACK, "Job info acknowledge"),
+    member(
+        Mid::JOB_INFO_UNSUBSCRIBE,
+        "Job info unsubscribe",
+        JOB_INFO_FAMILY,
+    ),
+    info(Mid::SELECT_JOB, "Select job"),
+    info(Mid::RESTART_JOB, "Job restart"),
+    acked(
+        Mid::TOOL_DATA_UPLOAD_REQUEST,
+        "Tool data upload request",
+        Mid::TOOL_DATA_UPLOAD,
+    ),
+    info(Mid::TOOL_DATA_UPLOAD, "Tool data upload"),
+    info(Mid::DISABLE_TOOL, "Disable tool"),
+    info(Mid::ENABLE_TOOL, "Enable tool"),
+    info(Mid::VEHICLE_ID_DOWNLOAD, "Vehicle ID download"),
+    member(
+        Mid::VEHICLE_ID_SUBSCRIBE,
+        "Vehicle ID subscribe",
+        VEHICLE_ID_FAMILY,
+    ),
+    pushed(
+        Mid::VEHICLE_ID,
+        "Vehicle ID",
+        Mid::VEHICLE_ID_ACK,
+        VEHICLE_ID_FAMILY,
+    ),
+    info(Mid::VEHICLE_ID_ACK, "Vehicle ID acknowledge"),
+    member(
+        Mid::VEHICLE_ID_UNSUBSCRIBE,
+        "Vehicle ID unsubscribe",
+        VEHICLE_ID_FAMILY,
+    ),
+    member(
+        Mid::TIGHTENING_RESULT_SUBSCRIBE,
+        "Last tightening result subscribe",
+        TIGHTENING_RESULT_FAMILY,
+    ),
+    pushed(
+        Mid::TIGHTENING_RESULT,
+        "Last tightening result",
+        Mid::TIGHTENING_RESULT_ACK,
+        TIGHTENING_RESULT_FAMILY,
+    ),
+    info(
+        Mid::TIGHTENING_RESULT_ACK,
+        "Last tightening result acknowledge",
+    ),
+    member(
+        Mid::TIGHTENING_RESULT_UNSUBSCRIBE,
+        "Last tightening result unsubscribe",
+        TIGHTENING_RESULT_FAMILY,
+    ),
+    member(Mid::ALARM_SUBSCRIBE, "Alarm subscribe", ALARM_FAMILY),
+    pushed(Mid::ALARM, "Alarm", Mid::ALARM_ACK, ALARM_FAMILY),
+    info(Mid::ALARM_ACK, "Alarm acknowledge"),
+    member(Mid::ALARM_UNSUBSCRIBE, "Alarm unsubscribe", ALARM_FAMILY),
+    acked(
+        Mid::ALARM_ACKNOWLEDGED,
+        "Alarm acknowledged on controller",
+        Mid::ALARM_ACKNOWLEDGED_ACK,
+    ),
+    info(
+        Mid::ALARM_ACKNOWLEDGED_ACK,
+        "Alarm acknowledged on controller acknowledge",
+    ),
+    member(
+        Mid::MULTI_SPINDLE_STATUS_SUBSCRIBE,
+        "Multi-spindle status subscribe",
+        MULTI_SPINDLE_STATUS_FAMILY,
+    ),
+    pushed(
+        Mid::MULTI_SPINDLE_STATUS,
+        "Multi-spindle status",
+        Mid::MULTI_SPINDLE_STATUS_ACK,
+        MULTI_SPINDLE_STATUS_FAMILY,
+    ),
+    member(
+        Mid::MULTI_SPINDLE_STATUS_UNSUBSCRIBE,
+        "Multi-spindle status unsubscribe",
+        MULTI_SPINDLE_STATUS_FAMILY,
+    ),
+    info(
+        Mid::MULTI_SPINDLE_STATUS_ACK,
+        "Multi-spindle status acknowledge",
+    ),
+    member(
+        Mid::MULTI_SPINDLE_RESULT_SUBSCRIBE,
+        "Multi-spindle result subscribe",
+        MULTI_SPINDLE_RESULT_FAMILY,
+    ),
+    pushed(
+        Mid::MULTI_SPINDLE_RESULT,
+        "Multi-spindle result",
+        Mid::MULTI_SPINDLE_RESULT_ACK,
+        MULTI_SPINDLE_RESULT_FAMILY,
+    ),
+    info(
+        Mid::MULTI_SPINDLE_RESULT_ACK,
+        "Multi-spindle result acknowledge",
+    ),
+    member(
+        Mid::MULTI_SPINDLE_RESULT_UNSUBSCRIBE,
+        "Multi-spindle result unsubscribe",
+        MULTI_SPINDLE_RESULT_FAMILY,
+    ),
+    info(Mid::JOB_BATCH_INCREMENT, "Job batch increment"),
+    info(Mid::USER_DATA_DOWNLOAD, "User data download"),
+    member(
+        Mid::USER_DATA_SUBSCRIBE,
+        "User data upload subscribe",
+        USER_DATA_FAMILY,
+    ),
+    pushed(
+        Mid::USER_DATA_UPLOAD,
+        "User data upload",
+        Mid::USER_DATA_ACK,
+        USER_DATA_FAMILY,
+    ),
+    info(Mid::USER_DATA_ACK, "User data upload acknowledge"),
+    member(
+        Mid::USER_DATA_UNSUBSCRIBE,
+        "User data upload unsubscribe",
+        USER_DATA_FAMILY,
+    ),
+    info(
+        Mid::USER_DATA_DOWNLOAD_WITH_OFFSET,
+        "User data download with offset",
+    ),
+    acked(
+        Mid::EVENT_LOG_UPLOAD_REQUEST,
+        "Event log upload request",
+        Mid::EVENT_LOG_UPLOAD,
+    ),
+    info(Mid::EVENT_LOG_UPLOAD, "Event log upload"),
+    info(Mid::OPERATOR_LOGIN, "Operator login"),
+    info(Mid::OPERATOR_LOGOUT, "Operator logout"),
+    acked(
+        Mid::TELEMETRY_UPLOAD_REQUEST,
+        "Telemetry upload request",
+        Mid::TELEMETRY_UPLOAD,
+    ),
+    info(Mid::TELEMETRY_UPLOAD, "Telemetry upload"),
+    info(Mid::ALARM_LANGUAGE_SELECT, "Select alarm text language"),
+    info(Mid::KEEP_ALIVE, "Keep alive"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_table_has_no_duplicates() {
+        let mids: HashSet<Mid> = MID_TABLE.iter().map(|info| info.mid).collect();
+        assert_eq!(mids.len(), MID_TABLE.len());
+    }
+
+    #[test]
+    fn test_subscription_families_are_consistent() {
+        for info in MID_TABLE {
+            if let Some(family) = info.subscription {
+                assert!(
+                    [family.subscribe, family.unsubscribe, family.data].contains(&info.mid),
+                    "MID {} is not part of its own family",
+                    info.mid
+                );
+                assert!(family.subscribe.info().is_some());
+                assert!(family.unsubscribe.info().is_some());
+            }
+            if let Some(ack) = info.ack {
+                assert!(ack.info().is_some(), "ack MID {} missing", ack);
+            }
+        }
+    }
+
+    #[test]
+    fn test_names_and_display() {
+        assert_eq!(Mid::TIGHTENING_RESULT.to_string(), "0061");
+        assert_eq!(Mid::TIGHTENING_RESULT.name(), "Last tightening result");
+        assert_eq!(
+            Mid::TIGHTENING_RESULT.info().unwrap().ack,
+            Some(Mid::TIGHTENING_RESULT_ACK)
+        );
+        assert_eq!(Mid(4242).name(), "Unknown");
+    }
+
+    #[test]
+    fn test_subscription_wiring_from_table() {
+        assert_eq!(
+            Mid::ALARM_SUBSCRIBE.subscription_request(),
+            Some(SubscriptionRequest::Subscribe(SubscriptionKind::Alarm))
+        );
+        assert_eq!(
+            Mid::MULTI_SPINDLE_RESULT_UNSUBSCRIBE.subscription_request(),
+            Some(SubscriptionRequest::Unsubscribe(
+                SubscriptionKind::MultiSpindleResult
+            ))
+        );
+        assert_eq!(Mid::TIGHTENING_RESULT.subscription_request(), None);
+        assert_eq!(Mid::KEEP_ALIVE.subscription_request(), None);
+
+        let kind = SubscriptionKind::TighteningResult;
+        assert_eq!(kind.data_mid(), Mid::TIGHTENING_RESULT);
+        assert_eq!(kind.ack_mid(), Some(Mid::TIGHTENING_RESULT_ACK));
+        assert_eq!(
+            SubscriptionKind::VehicleId.mids().subscribe,
+            Mid::VEHICLE_ID_SUBSCRIBE
+        );
+    }
+}
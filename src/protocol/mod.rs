@@ -1,9 +1,40 @@
+//! Open Protocol message layer
+//!
+//! This module does not depend on the simulator's state, handlers or servers
+//! and is meant to be shared with client implementations. Everything needed to
+//! frame, parse and build messages is re-exported here:
+//!
+//! - [`Message`] / [`Response`] with [`parse_message`] and [`serialize_response`]
+//! - [`HeaderOptions`] for the No Ack flag, station/spindle, sequence and parts
+//! - [`FieldBuilder`] / [`FieldParser`] for parameter fields
+//! - [`Mid`], [`TorqueCnm`] and [`AngleDeciDeg`] value types
+//! - [`WireFormat`] for text encoding, padding and torque quirks
+//! - [`NullDelimitedCodec`] for framing over TCP
+//!
+//! These re-exports are the stable surface; the submodules may be reorganized.
+
 pub mod field;
+pub mod header;
+pub mod mid;
 pub mod parser;
 pub mod response_data;
 pub mod serializer;
+pub mod units;
+pub mod wire;
+
+pub use crate::codec::null_delimited_codec::NullDelimitedCodec;
+pub use field::{Field, FieldBuilder, FieldParser, OverflowPolicy};
+pub use header::HeaderOptions;
+pub use mid::Mid;
+pub use parser::parse_message;
+pub use response_data::ResponseData;
+pub use serializer::serialize_response;
+pub use units::{AngleDeciDeg, TorqueCnm};
+pub use wire::{
+    DateTimeSeparator, DecimalSeparator, NumericPadding, TextEncoding, TimestampFormat,
+    TorqueFormat, TorqueResolution, WireFormat,
+};
 
-use response_data::ResponseData;
 use thiserror::Error;
 
 /// Open Protocol message structure
@@ -13,40 +44,78 @@ use thiserror::Error;
 pub struct Message {
     #[allow(dead_code)]
     pub length: u32, // Total message length (bytes 0-3)
-    pub mid: u16,      // Message ID (bytes 4-7)
-    pub revision: u8,  // Protocol revision (bytes 8-10)
-    pub data: Vec<u8>, // Optional MID-specific data (bytes 20+)
+    pub mid: u16,              // Message ID (bytes 4-7)
+    pub revision: u16,         // Protocol revision (bytes 8-10)
+    pub header: HeaderOptions, // Optional header fields (bytes 11-19)
+    pub data: Vec<u8>,         // Optional MID-specific data (bytes 20+)
+}
+
+impl Message {
+    /// Create a message; the length field is derived from the data
+    pub fn new(mid: u16, revision: u16, data: Vec<u8>) -> Self {
+        Self {
+            length: (serializer::HEADER_SIZE + data.len()) as u32,
+            mid,
+            revision,
+            header: HeaderOptions::default(),
+            data,
+        }
+    }
+
+    /// Encode header and data (the codec appends the NUL terminator)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serializer::serialize_frame_with(self.mid, self.revision, &self.header, &self.data)
+    }
 }
 
 /// Response message to be sent back
 #[derive(Debug, Clone)]
 pub struct Response {
     pub mid: u16,
-    pub revision: u8,
+    pub revision: u16,
+    pub header: HeaderOptions,
     pub data: Vec<u8>,
 }
 
 impl Response {
     /// Create a new response with raw data
-    pub fn new(mid: u16, revision: u8, data: Vec<u8>) -> Self {
+    pub fn new(mid: impl Into<Mid>, revision: u16, data: Vec<u8>) -> Self {
         Self {
-            mid,
+            mid: mid.into().value(),
             revision,
+            header: HeaderOptions::default(),
             data,
         }
     }
 
     /// Create a response from a type that implements ResponseData
-    pub fn from_data(mid: u16, revision: u8, data: impl ResponseData) -> Self {
-        Self {
-            mid,
+    pub fn from_data(
+        mid: impl Into<Mid>,
+        revision: u16,
+        data: impl ResponseData,
+    ) -> Result<Self, ProtocolError> {
+        Ok(Self {
+            mid: mid.into().value(),
             revision,
-            data: data.serialize(),
-        }
+            header: HeaderOptions::default(),
+            data: data.serialize()?,
+        })
+    }
+
+    /// Replace the optional header fields
+    pub fn with_header(mut self, header: HeaderOptions) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Encode header and data (the codec appends the NUL terminator)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serialize_response(self)
     }
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ProtocolError {
     #[error("Message too short: expected at least 20 bytes, got {0}")]
     MessageTooShort(usize),
@@ -62,4 +131,23 @@ pub enum ProtocolError {
 
     #[error("Length mismatch: header says {expected}, actual message is {actual}")]
     LengthMismatch { expected: usize, actual: usize },
+
+    #[error("Unexpected end of data at offset {offset}: {needed} more bytes needed")]
+    UnexpectedEnd { offset: usize, needed: usize },
+
+    #[error("Expected parameter {expected:02} at offset {offset}, found {found:?}")]
+    UnexpectedParameter {
+        expected: u8,
+        found: String,
+        offset: usize,
+    },
+
+    #[error("Invalid field value at offset {offset}: {value:?}")]
+    InvalidField { offset: usize, value: String },
+
+    #[error("Value {value} does not fit a {width}-character field")]
+    ValueOverflow { value: i64, width: usize },
+
+    #[error("Data of {size} bytes does not fit in {max_parts} message parts")]
+    MessageTooLarge { size: usize, max_parts: usize },
 }
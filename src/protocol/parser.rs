@@ -1,4 +1,4 @@
-use super::{Message, ProtocolError};
+use super::{HeaderOptions, Message, ProtocolError};
 use std::str;
 
 const HEADER_SIZE: usize = 20;
@@ -35,7 +35,7 @@ pub fn parse_message(data: &[u8]) -> Result<Message, ProtocolError> {
     let revision_str = str::from_utf8(&data[8..11])
         .map_err(|_| ProtocolError::InvalidRevision("not valid UTF-8".to_string()))?;
     let revision = revision_str
-        .parse::<u8>()
+        .parse::<u16>()
         .map_err(|_| ProtocolError::InvalidRevision(revision_str.to_string()))?;
 
     // Extract optional data payload (bytes 20+)
@@ -49,6 +49,8 @@ pub fn parse_message(data: &[u8]) -> Result<Message, ProtocolError> {
         length,
         mid,
         revision,
+        // Optional header fields (bytes 11-19)
+        header: HeaderOptions::parse(&data[..HEADER_SIZE]),
         data: data_payload,
     })
 }
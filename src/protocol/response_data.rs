@@ -1,3 +1,5 @@
+use super::ProtocolError;
+
 /// Trait for MID-specific response data structures
 ///
 /// Implement this trait on structs that represent the data payload
@@ -6,27 +8,28 @@
 pub trait ResponseData {
     /// Serialize this data structure into Open Protocol format
     ///
-    /// Returns the byte representation of the data section (after the 20-byte header)
-    fn serialize(&self) -> Vec<u8>;
+    /// Returns the byte representation of the data section (after the 20-byte header),
+    /// or the error of a value that does not fit its field
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError>;
 }
 
 /// Implement ResponseData for empty responses (no data payload)
 impl ResponseData for () {
-    fn serialize(&self) -> Vec<u8> {
-        Vec::new()
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        Ok(Vec::new())
     }
 }
 
 /// Implement ResponseData for raw byte vectors (pass-through)
 impl ResponseData for Vec<u8> {
-    fn serialize(&self) -> Vec<u8> {
-        self.clone()
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        Ok(self.clone())
     }
 }
 
 /// Implement ResponseData for byte slices (pass-through)
 impl ResponseData for &[u8] {
-    fn serialize(&self) -> Vec<u8> {
-        self.to_vec()
+    fn serialize(&self) -> Result<Vec<u8>, ProtocolError> {
+        Ok(self.to_vec())
     }
 }
@@ -1,11 +1,72 @@
-use super::Response;
+use super::{HeaderOptions, ProtocolError, Response};
 
-const HEADER_SIZE: usize = 20;
+/// Size of the fixed message header
+pub const HEADER_SIZE: usize = 20;
+
+/// Largest frame the four-digit length field can describe
+pub const MAX_FRAME_LENGTH: usize = 9999;
+
+/// Most parts a multi-part message can be split into
+pub const MAX_PARTS: usize = 9;
 
 /// Serialize a response into Open Protocol format
+///
+/// Produces a single frame; use [`serialize_parts`] for data that may not
+/// fit in one.
 pub fn serialize_response(response: &Response) -> Vec<u8> {
+    serialize_frame_with(
+        response.mid,
+        response.revision,
+        &response.header,
+        &response.data,
+    )
+}
+
+/// Serialize a response, splitting data too large for one frame into parts
+///
+/// Each part carries its own length and the part numbering in header bytes
+/// 18-19; a response that fits in one frame is returned unchanged.
+pub fn serialize_parts(response: &Response) -> Result<Vec<Vec<u8>>, ProtocolError> {
+    let max_data = MAX_FRAME_LENGTH - HEADER_SIZE;
+    if response.data.len() <= max_data {
+        return Ok(vec![serialize_response(response)]);
+    }
+
+    let chunks: Vec<&[u8]> = response.data.chunks(max_data).collect();
+    if chunks.len() > MAX_PARTS {
+        return Err(ProtocolError::MessageTooLarge {
+            size: response.data.len(),
+            max_parts: MAX_PARTS,
+        });
+    }
+    let count = chunks.len() as u8;
+    Ok(chunks
+        .into_iter()
+        .zip(1..)
+        .map(|(chunk, part)| {
+            let header = HeaderOptions {
+                parts: Some((count, part)),
+                ..response.header
+            };
+            serialize_frame_with(response.mid, response.revision, &header, chunk)
+        })
+        .collect())
+}
+
+/// Serialize a header and data payload
+pub fn serialize_frame(mid: u16, revision: u16, data: &[u8]) -> Vec<u8> {
+    serialize_frame_with(mid, revision, &HeaderOptions::default(), data)
+}
+
+/// Serialize a header with optional fields and a data payload
+pub fn serialize_frame_with(
+    mid: u16,
+    revision: u16,
+    header: &HeaderOptions,
+    data: &[u8],
+) -> Vec<u8> {
     // Calculate total length: 20 byte header + data
-    let total_length = HEADER_SIZE + response.data.len();
+    let total_length = HEADER_SIZE + data.len();
 
     let mut buffer = Vec::with_capacity(total_length);
 
@@ -13,16 +74,16 @@ pub fn serialize_response(response: &Response) -> Vec<u8> {
     buffer.extend_from_slice(format!("{:04}", total_length).as_bytes());
 
     // MID field (4 bytes, zero-padded)
-    buffer.extend_from_slice(format!("{:04}", response.mid).as_bytes());
+    buffer.extend_from_slice(format!("{:04}", mid).as_bytes());
 
     // Revision field (3 bytes, zero-padded)
-    buffer.extend_from_slice(format!("{:03}", response.revision).as_bytes());
+    buffer.extend_from_slice(format!("{:03}", revision).as_bytes());
 
-    // Reserved/padding (9 bytes of spaces)
-    buffer.extend_from_slice(b"         ");
+    // No Ack flag, station, spindle, sequence and parts (9 bytes, spaces if unset)
+    buffer.extend_from_slice(&header.encode());
 
     // Optional data payload
-    buffer.extend_from_slice(&response.data);
+    buffer.extend_from_slice(data);
 
     buffer
 }
@@ -36,6 +97,7 @@ mod tests {
         let response = Response {
             mid: 1,
             revision: 1,
+            header: HeaderOptions::default(),
             data: Vec::new(),
         };
         let serialized = serialize_response(&response);
@@ -47,9 +109,56 @@ mod tests {
         let response = Response {
             mid: 50,
             revision: 1,
+            header: HeaderOptions::default(),
             data: b"TEST".to_vec(),
         };
         let serialized = serialize_response(&response);
         assert_eq!(serialized, b"00240050001         TEST");
     }
+
+    #[test]
+    fn test_message_round_trip() {
+        let message = crate::protocol::Message::new(18, 1, b"002".to_vec());
+        let parsed = crate::protocol::parse_message(&message.to_bytes()).unwrap();
+
+        assert_eq!(parsed.length, 23);
+        assert_eq!(parsed.mid, 18);
+        assert_eq!(parsed.data, b"002");
+    }
+
+    #[test]
+    fn test_serialize_header_options() {
+        let response = Response::new(61, 2, b"DATA".to_vec()).with_header(HeaderOptions {
+            no_ack: true,
+            sequence: Some(7),
+            ..HeaderOptions::default()
+        });
+        assert_eq!(serialize_response(&response), b"002400610021    07  DATA");
+    }
+
+    #[test]
+    fn test_serialize_parts_computes_length_per_part() {
+        let data = vec![b'x'; (MAX_FRAME_LENGTH - HEADER_SIZE) * 2 + 100];
+        let response = Response::new(61, 1, data);
+        let parts = serialize_parts(&response).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(&parts[0][..20], b"99990061001       31");
+        assert_eq!(&parts[2][..20], b"01200061001       33");
+        for part in &parts {
+            let parsed = crate::protocol::parse_message(part).unwrap();
+            assert_eq!(parsed.length as usize, part.len());
+        }
+
+        let small = Response::new(61, 1, b"DATA".to_vec());
+        assert_eq!(
+            serialize_parts(&small).unwrap(),
+            vec![serialize_response(&small)]
+        );
+
+        let huge = Response::new(61, 1, vec![b'x'; MAX_FRAME_LENGTH * MAX_PARTS]);
+        assert!(matches!(
+            serialize_parts(&huge),
+            Err(ProtocolError::MessageTooLarge { max_parts: 9, .. })
+        ));
+    }
 }
@@ -0,0 +1,79 @@
+//! Fixed-point units used in Open Protocol payloads
+//!
+//! Torque and angle values travel as zero-padded integers at a fixed
+//! resolution. Keeping the scaling in these newtypes means every MID rounds
+//! the same way, and a revision with a different resolution only needs a
+//! change here.
+
+use serde::{Deserialize, Serialize};
+
+/// Torque in centinewton metres (Nm × 100), the resolution of all torque fields
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct TorqueCnm(pub i32);
+
+impl TorqueCnm {
+    /// Convert from Nm, rounding to the nearest 0.01 Nm
+    pub fn from_nm(nm: f64) -> Self {
+        Self((nm * 100.0).round() as i32)
+    }
+
+    pub fn as_nm(self) -> f64 {
+        f64::from(self.0) / 100.0
+    }
+
+    /// Integer written into torque fields
+    pub fn wire_value(self) -> i32 {
+        self.0
+    }
+}
+
+/// Angle in tenths of a degree
+///
+/// Results keep one decimal internally; angle fields are sent in whole
+/// degrees, rounded rather than truncated.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct AngleDeciDeg(pub i32);
+
+impl AngleDeciDeg {
+    /// Convert from degrees, rounding to the nearest 0.1°
+    pub fn from_degrees(degrees: f64) -> Self {
+        Self((degrees * 10.0).round() as i32)
+    }
+
+    pub fn as_degrees(self) -> f64 {
+        f64::from(self.0) / 10.0
+    }
+
+    /// Integer written into angle fields (whole degrees, half away from zero)
+    pub fn wire_value(self) -> i32 {
+        (f64::from(self.0) / 10.0).round() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_torque_rounds_instead_of_truncating() {
+        // 12.35 * 100.0 is 1234.999... in floating point
+        assert_eq!(TorqueCnm::from_nm(12.35).wire_value(), 1235);
+        assert_eq!(TorqueCnm::from_nm(-0.5).wire_value(), -50);
+        assert_eq!(TorqueCnm(1250).as_nm(), 12.5);
+    }
+
+    #[test]
+    fn test_angle_keeps_fraction_until_serialized() {
+        let angle = AngleDeciDeg::from_degrees(39.6);
+        assert_eq!(angle, AngleDeciDeg(396));
+        assert_eq!(angle.as_degrees(), 39.6);
+        assert_eq!(angle.wire_value(), 40);
+        assert_eq!(AngleDeciDeg::from_degrees(39.4).wire_value(), 39);
+    }
+}
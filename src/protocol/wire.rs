@@ -0,0 +1,535 @@
+//! Text encoding and padding conventions of the wire format
+//!
+//! Controllers differ in how they put text and numbers on the wire: some send
+//! names and VINs in ISO 8859-1, others in UTF-8, and a few pad numeric fields
+//! with spaces instead of zeros. Controllers set up for some locales even
+//! write torque with a decimal comma or at another resolution than Nm × 100,
+//! and firmware versions disagree on the layout of timestamps. A
+//! [`WireFormat`] captures those choices.
+//!
+//! [`Field`](super::Field) picks up the format of the surrounding
+//! [`WireFormat::scope`], so a connection task can serve a different
+//! personality without threading the format through every handler. Outside a
+//! scope the default (UTF-8, zero padding) applies.
+
+use super::Mid;
+use super::field::OverflowPolicy;
+use super::units::TorqueCnm;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// Character encoding of text fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    /// UTF-8; characters outside ASCII take more than one byte of the field
+    #[default]
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// ISO 8859-1 (Latin-1); one byte per character, others sent as `?`
+    #[serde(rename = "iso-8859-1")]
+    Latin1,
+}
+
+impl TextEncoding {
+    /// Configuration name of the encoding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Latin1 => "iso-8859-1",
+        }
+    }
+}
+
+/// Fill character of numeric fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumericPadding {
+    /// `00042`, as the specification requires
+    #[default]
+    Zero,
+    /// `   42`, right-aligned with leading spaces
+    Space,
+}
+
+impl NumericPadding {
+    /// Configuration name of the padding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Zero => "zero",
+            Self::Space => "space",
+        }
+    }
+}
+
+/// Fill character of free-text fields in max-size mode
+pub const MAX_SIZE_FILL: char = 'X';
+
+/// Decimal places of torque fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TorqueResolution {
+    /// Nm × 10
+    Deci,
+    /// Nm × 100, as the specification requires
+    #[default]
+    Centi,
+    /// Nm × 1000
+    Milli,
+}
+
+impl TorqueResolution {
+    /// Configuration name of the resolution
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deci => "deci",
+            Self::Centi => "centi",
+            Self::Milli => "milli",
+        }
+    }
+
+    /// Digits after the decimal mark
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Self::Deci => 1,
+            Self::Centi => 2,
+            Self::Milli => 3,
+        }
+    }
+
+    /// Torque as an integer at this resolution, rounded
+    pub fn scale(&self, torque: TorqueCnm) -> i64 {
+        (torque.as_nm() * 10f64.powi(self.decimals() as i32)).round() as i64
+    }
+}
+
+/// Decimal mark written into torque fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecimalSeparator {
+    /// No mark: an implied-decimal integer, as the specification requires
+    #[default]
+    None,
+    /// `12.50`
+    Point,
+    /// `12,50`, as controllers set up for German or French locales send it
+    Comma,
+}
+
+impl DecimalSeparator {
+    /// Configuration name of the separator
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Point => "point",
+            Self::Comma => "comma",
+        }
+    }
+
+    fn as_char(&self) -> Option<char> {
+        match self {
+            Self::None => None,
+            Self::Point => Some('.'),
+            Self::Comma => Some(','),
+        }
+    }
+}
+
+/// MIDs with torque fields a [`TorqueFormat`] can be limited to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<u16>", into = "Vec<u16>")]
+pub struct TorqueMids {
+    tightening_result: bool,
+    multi_spindle_result: bool,
+}
+
+impl TorqueMids {
+    const SUPPORTED: [Mid; 2] = [Mid::TIGHTENING_RESULT, Mid::MULTI_SPINDLE_RESULT];
+
+    /// Whether no MID was named, so the format applies to all of them
+    pub fn is_empty(&self) -> bool {
+        !self.tightening_result && !self.multi_spindle_result
+    }
+
+    /// Whether the format applies to `mid`
+    pub fn contains(&self, mid: Mid) -> bool {
+        match mid {
+            Mid::TIGHTENING_RESULT => self.tightening_result,
+            Mid::MULTI_SPINDLE_RESULT => self.multi_spindle_result,
+            _ => false,
+        }
+    }
+}
+
+impl TryFrom<Vec<u16>> for TorqueMids {
+    type Error = String;
+
+    fn try_from(mids: Vec<u16>) -> Result<Self, Self::Error> {
+        let mut set = Self::default();
+        for mid in mids {
+            match Mid(mid) {
+                Mid::TIGHTENING_RESULT => set.tightening_result = true,
+                Mid::MULTI_SPINDLE_RESULT => set.multi_spindle_result = true,
+                other => {
+                    return Err(format!(
+                        "MID {} has no torque fields (supported: {}, {})",
+                        other,
+                        Self::SUPPORTED[0],
+                        Self::SUPPORTED[1]
+                    ));
+                }
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl From<TorqueMids> for Vec<u16> {
+    fn from(mids: TorqueMids) -> Self {
+        TorqueMids::SUPPORTED
+            .into_iter()
+            .filter(|mid| mids.contains(*mid))
+            .map(Mid::value)
+            .collect()
+    }
+}
+
+/// Resolution and decimal mark of torque fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorqueFormat {
+    #[serde(default)]
+    pub resolution: TorqueResolution,
+    #[serde(default)]
+    pub decimal_separator: DecimalSeparator,
+    /// MIDs the format applies to; empty for every MID with torque fields
+    #[serde(default)]
+    pub mids: TorqueMids,
+}
+
+impl TorqueFormat {
+    /// Whether torque is sent as the specification requires
+    pub fn is_standard(&self) -> bool {
+        self.resolution == TorqueResolution::Centi
+            && self.decimal_separator == DecimalSeparator::None
+    }
+}
+
+/// Character between the date and the time of a timestamp
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateTimeSeparator {
+    /// `2025-01-15:10:30:45`, as the specification requires
+    #[default]
+    Colon,
+    /// `2025-01-15 10:30:45`
+    Space,
+}
+
+impl DateTimeSeparator {
+    /// Configuration name of the separator
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Colon => "colon",
+            Self::Space => "space",
+        }
+    }
+}
+
+/// Width of timestamp fields
+pub const TIMESTAMP_WIDTH: usize = 19;
+
+/// Layout of timestamp fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimestampFormat {
+    #[serde(default)]
+    pub separator: DateTimeSeparator,
+    /// Two digits for month, day, hour, minute and second; without padding
+    /// `2025-01-05:08:03:09` is sent as `2025-1-5:8:3:9`, space-filled to the field width
+    #[serde(default = "default_zero_padded")]
+    pub zero_padded: bool,
+}
+
+fn default_zero_padded() -> bool {
+    true
+}
+
+impl Default for TimestampFormat {
+    fn default() -> Self {
+        Self {
+            separator: DateTimeSeparator::default(),
+            zero_padded: true,
+        }
+    }
+}
+
+impl TimestampFormat {
+    /// Whether timestamps are sent as the specification requires
+    pub fn is_standard(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Rewrite a `YYYY-MM-DD:HH:MM:SS` (or `YYYY-MM-DD HH:MM:SS`) timestamp
+    /// in this layout; anything else is sent unchanged
+    pub fn render(&self, timestamp: &str) -> String {
+        let parsed = ["%Y-%m-%d:%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+            .into_iter()
+            .find_map(|format| chrono::NaiveDateTime::parse_from_str(timestamp, format).ok());
+        let Some(parsed) = parsed else {
+            return timestamp.to_string();
+        };
+        let separator = match self.separator {
+            DateTimeSeparator::Colon => ':',
+            DateTimeSeparator::Space => ' ',
+        };
+        let format = if self.zero_padded {
+            format!("%Y-%m-%d{}%H:%M:%S", separator)
+        } else {
+            format!("%Y-%-m-%-d{}%-H:%-M:%-S", separator)
+        };
+        parsed.format(&format).to_string()
+    }
+}
+
+/// Encoding and padding conventions for one connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WireFormat {
+    pub encoding: TextEncoding,
+    pub numeric_padding: NumericPadding,
+    /// Fill free-text fields (names, VINs, alarm texts) to their full width
+    /// instead of padding them with spaces, to expose client buffer-size assumptions
+    pub max_size_text: bool,
+    /// Torque resolution and decimal mark
+    pub torque: TorqueFormat,
+    /// Date/time separator and padding of timestamps
+    pub timestamp: TimestampFormat,
+}
+
+tokio::task_local! {
+    static CURRENT: WireFormat;
+}
+
+impl WireFormat {
+    /// Format of the enclosing scope, or the default outside one
+    pub fn current() -> Self {
+        CURRENT.try_with(|format| *format).unwrap_or_default()
+    }
+
+    /// Run `future` with this format applied to every field it serializes
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// Run `f` with this format applied to every field it serializes
+    pub fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        CURRENT.sync_scope(self, f)
+    }
+
+    /// This format as it applies to the data of `mid`
+    ///
+    /// A torque format limited to other MIDs is replaced by the standard one.
+    pub fn for_mid(mut self, mid: Mid) -> Self {
+        if !self.torque.mids.is_empty() && !self.torque.mids.contains(mid) {
+            self.torque = TorqueFormat::default();
+        }
+        self
+    }
+
+    /// Torque in `width` characters, saturated to the largest value that fits
+    pub fn format_torque(&self, torque: TorqueCnm, width: usize) -> String {
+        let resolution = self.torque.resolution;
+        let value = resolution.scale(torque);
+        let Some(separator) = self.torque.decimal_separator.as_char() else {
+            let value = OverflowPolicy::Saturate.fit(value, width).unwrap_or(value);
+            return self.pad_int(value, width);
+        };
+
+        // The decimal mark takes one character of the field
+        let digits = width.saturating_sub(1);
+        let value = OverflowPolicy::Saturate.fit(value, digits).unwrap_or(value);
+        let decimals = resolution.decimals();
+        let unit = 10u64.pow(decimals);
+        let sign = if value < 0 { "-" } else { "" };
+        let number = format!(
+            "{}{}{:0decimals$}",
+            value.unsigned_abs() / unit,
+            separator,
+            value.unsigned_abs() % unit,
+            decimals = decimals as usize
+        );
+        match self.numeric_padding {
+            NumericPadding::Zero => format!(
+                "{sign}{number:0>width$}",
+                width = width.saturating_sub(sign.len())
+            ),
+            NumericPadding::Space => format!("{:>width$}", format!("{sign}{number}")),
+        }
+    }
+
+    /// Right-align `value` in `width` characters with the numeric fill
+    pub fn pad_int(&self, value: i64, width: usize) -> String {
+        match self.numeric_padding {
+            NumericPadding::Zero => format!("{:0width$}", value, width = width),
+            NumericPadding::Space => format!("{:>width$}", value, width = width),
+        }
+    }
+
+    /// Encode text for the wire
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self.encoding {
+            TextEncoding::Utf8 => text.as_bytes().to_vec(),
+            TextEncoding::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+                .collect(),
+        }
+    }
+
+    /// Decode received bytes into UTF-8
+    ///
+    /// Every byte is a valid ISO 8859-1 character, so decoding cannot fail;
+    /// UTF-8 input is passed through unchanged.
+    pub fn decode(&self, bytes: &[u8]) -> Vec<u8> {
+        match self.encoding {
+            TextEncoding::Utf8 => bytes.to_vec(),
+            TextEncoding::Latin1 => bytes
+                .iter()
+                .map(|&b| char::from(b))
+                .collect::<String>()
+                .into_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LATIN1: WireFormat = WireFormat {
+        encoding: TextEncoding::Latin1,
+        numeric_padding: NumericPadding::Zero,
+        max_size_text: false,
+        torque: TorqueFormat {
+            resolution: TorqueResolution::Centi,
+            decimal_separator: DecimalSeparator::None,
+            mids: TorqueMids {
+                tightening_result: false,
+                multi_spindle_result: false,
+            },
+        },
+        timestamp: TimestampFormat {
+            separator: DateTimeSeparator::Colon,
+            zero_padded: true,
+        },
+    };
+
+    #[test]
+    fn test_latin1_round_trip() {
+        let bytes = LATIN1.encode("Müller Straße");
+        assert_eq!(bytes.len(), 13);
+        assert_eq!(bytes[1], 0xFC);
+        assert_eq!(LATIN1.decode(&bytes), "Müller Straße".as_bytes());
+        assert_eq!(LATIN1.encode("Zoë €"), b"Zo\xEB ?");
+    }
+
+    #[test]
+    fn test_numeric_padding() {
+        let space = WireFormat {
+            numeric_padding: NumericPadding::Space,
+            ..WireFormat::default()
+        };
+        assert_eq!(WireFormat::default().pad_int(42, 5), "00042");
+        assert_eq!(space.pad_int(42, 5), "   42");
+        assert_eq!(space.pad_int(-7, 4), "  -7");
+    }
+
+    #[test]
+    fn test_torque_quirks() {
+        let comma = |resolution| WireFormat {
+            torque: TorqueFormat {
+                resolution,
+                decimal_separator: DecimalSeparator::Comma,
+                mids: TorqueMids::default(),
+            },
+            ..WireFormat::default()
+        };
+        let torque = TorqueCnm(1250);
+        assert_eq!(WireFormat::default().format_torque(torque, 6), "001250");
+        assert_eq!(
+            comma(TorqueResolution::Centi).format_torque(torque, 6),
+            "012,50"
+        );
+        assert_eq!(
+            comma(TorqueResolution::Milli).format_torque(torque, 6),
+            "12,500"
+        );
+        assert_eq!(
+            comma(TorqueResolution::Deci).format_torque(TorqueCnm(-150), 6),
+            "-001,5"
+        );
+        // Too large for the field once the mark takes a character
+        assert_eq!(
+            comma(TorqueResolution::Milli).format_torque(TorqueCnm(10_000), 6),
+            "99,999"
+        );
+
+        let milli = WireFormat {
+            numeric_padding: NumericPadding::Space,
+            torque: TorqueFormat {
+                resolution: TorqueResolution::Milli,
+                ..TorqueFormat::default()
+            },
+            ..WireFormat::default()
+        };
+        assert_eq!(milli.format_torque(torque, 6), " 12500");
+    }
+
+    #[test]
+    fn test_torque_format_limited_to_mids() {
+        let format = WireFormat {
+            torque: TorqueFormat {
+                decimal_separator: DecimalSeparator::Point,
+                mids: TorqueMids::try_from(vec![101]).unwrap(),
+                ..TorqueFormat::default()
+            },
+            ..WireFormat::default()
+        };
+        assert!(format.for_mid(Mid::TIGHTENING_RESULT).torque.is_standard());
+        assert!(
+            !format
+                .for_mid(Mid::MULTI_SPINDLE_RESULT)
+                .torque
+                .is_standard()
+        );
+        assert!(TorqueMids::try_from(vec![61, 2]).is_err());
+        assert_eq!(Vec::from(format.torque.mids), vec![101]);
+    }
+
+    #[test]
+    fn test_timestamp_formats() {
+        let format = |separator, zero_padded| TimestampFormat {
+            separator,
+            zero_padded,
+        };
+        let timestamp = "2025-01-05:08:03:09";
+        assert_eq!(TimestampFormat::default().render(timestamp), timestamp);
+        assert_eq!(
+            format(DateTimeSeparator::Space, true).render(timestamp),
+            "2025-01-05 08:03:09"
+        );
+        assert_eq!(
+            format(DateTimeSeparator::Colon, false).render("2025-01-05 08:03:09"),
+            "2025-1-5:8:3:9"
+        );
+        assert_eq!(
+            format(DateTimeSeparator::Space, false).render("2025-12-31:23:59:00"),
+            "2025-12-31 23:59:0"
+        );
+        // Not a timestamp: left alone
+        assert_eq!(format(DateTimeSeparator::Space, false).render(""), "");
+    }
+
+    #[test]
+    fn test_scope_sets_current_format() {
+        assert_eq!(WireFormat::current(), WireFormat::default());
+        assert_eq!(LATIN1.sync_scope(WireFormat::current), LATIN1);
+    }
+}
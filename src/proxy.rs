@@ -0,0 +1,416 @@
+//! Gateway mode: a fault-injecting proxy in front of a real controller
+//!
+//! With `[proxy] upstream` set the simulator stops answering Open Protocol
+//! itself. Every client connection is paired with a connection to the real
+//! controller, and each frame travelling either way passes through the same
+//! failure injection as simulated responses (`POST /config/failure`). Every
+//! frame is recorded with what the injection did to it; the recording is
+//! served by `GET /proxy/exchanges` and optionally appended to
+//! `[proxy] record_path` as JSON lines.
+
+use crate::audit::AuditCategory;
+use crate::codec::null_delimited_codec::NullDelimitedCodec;
+use crate::context::AppContext;
+use crate::failure_simulator::{FailureSimulator, Injection, InjectionTrace};
+use crate::protocol::parser;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::bytes::BytesMut;
+use tokio_util::codec::Framed;
+
+type FramedStream = Framed<TcpStream, NullDelimitedCodec>;
+
+/// Which way a frame travelled through the proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    ClientToController,
+    ControllerToClient,
+}
+
+/// What failure injection did with a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExchangeOutcome {
+    Forwarded,
+    Corrupted,
+    Dropped,
+    /// The proxy closed both connections instead of forwarding
+    Disconnected,
+}
+
+/// One recorded frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    /// Sequence number, increasing for the lifetime of the simulator
+    pub seq: u64,
+    /// Local time with milliseconds (RFC 3339)
+    pub timestamp: String,
+    /// Address of the client the frame belongs to
+    pub client: SocketAddr,
+    pub direction: Direction,
+    /// MID from the header, when the frame parses
+    pub mid: Option<u16>,
+    pub outcome: ExchangeOutcome,
+    /// Injected delay before forwarding
+    pub delay_ms: u64,
+    /// Frame as received (without the NUL terminator)
+    pub frame: String,
+    /// Frame as forwarded, when corruption altered it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_frame: Option<String>,
+}
+
+#[derive(Debug)]
+struct ExchangeLogInner {
+    entries: VecDeque<Exchange>,
+    capacity: usize,
+    next_seq: u64,
+    file: Option<File>,
+}
+
+/// Bounded recording of the frames passing through the proxy
+#[derive(Debug)]
+pub struct ExchangeLog {
+    inner: Mutex<ExchangeLogInner>,
+}
+
+/// Exchange log shared by the proxy connections and the HTTP layer
+pub type SharedExchangeLog = Arc<ExchangeLog>;
+
+impl ExchangeLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(ExchangeLogInner {
+                entries: VecDeque::new(),
+                capacity: capacity.max(1),
+                next_seq: 1,
+                file: None,
+            }),
+        }
+    }
+
+    /// Also append every recorded frame to `path` (one JSON object per line)
+    pub fn record_to_file(&self, path: &Path) -> std::io::Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.inner.lock().unwrap().file = Some(file);
+        Ok(())
+    }
+
+    fn record(
+        &self,
+        client: SocketAddr,
+        direction: Direction,
+        frame: &[u8],
+        outcome: ExchangeOutcome,
+        delay: Duration,
+        forwarded: Option<&[u8]>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let exchange = Exchange {
+            seq: inner.next_seq,
+            timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+            client,
+            direction,
+            mid: parser::parse_message(frame).ok().map(|m| m.mid),
+            outcome,
+            delay_ms: delay.as_millis() as u64,
+            frame: String::from_utf8_lossy(frame).into_owned(),
+            forwarded_frame: forwarded.map(|f| String::from_utf8_lossy(f).into_owned()),
+        };
+        inner.next_seq += 1;
+
+        if let Some(file) = inner.file.as_mut() {
+            let written = serde_json::to_string(&exchange)
+                .map_err(std::io::Error::from)
+                .and_then(|line| writeln!(file, "{}", line));
+            if let Err(e) = written {
+                eprintln!("Failed to write proxy recording: {}", e);
+            }
+        }
+        if inner.entries.len() >= inner.capacity {
+            inner.entries.pop_front();
+        }
+        inner.entries.push_back(exchange);
+    }
+
+    /// Recorded frames with a sequence number greater than `after_seq`, oldest first
+    pub fn since(&self, after_seq: u64) -> Vec<Exchange> {
+        self.inner
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|e| e.seq > after_seq)
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Accept client connections and relay each to `upstream`
+pub async fn serve(
+    listener: TcpListener,
+    upstream: String,
+    ctx: AppContext,
+) -> std::io::Result<()> {
+    if let Some(path) = &ctx.settings.proxy.record_path {
+        ctx.exchange_log.record_to_file(path)?;
+        println!("Recording proxied frames to {:?}", path);
+    }
+    println!("Gateway mode: forwarding connections to {}", upstream);
+
+    loop {
+        let (client, addr) = listener.accept().await?;
+        println!(
+            "Incoming connection from {}, connecting to {}",
+            addr, upstream
+        );
+        let ctx = ctx.clone();
+        let upstream = upstream.clone();
+        tokio::spawn(async move {
+            relay(client, addr, &upstream, &ctx).await;
+        });
+    }
+}
+
+/// Pair a client with a fresh controller connection until either side closes
+async fn relay(client: TcpStream, addr: SocketAddr, upstream: &str, ctx: &AppContext) {
+    let timeout = Duration::from_millis(ctx.settings.proxy.connect_timeout_ms);
+    let controller = match tokio::time::timeout(timeout, TcpStream::connect(upstream)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            eprintln!("Failed to connect to controller {}: {}", upstream, e);
+            return;
+        }
+        Err(_) => {
+            eprintln!(
+                "Controller {} did not accept within {} ms",
+                upstream,
+                timeout.as_millis()
+            );
+            return;
+        }
+    };
+
+    let state = &ctx.observable_state;
+    state.audit(
+        AuditCategory::Connection,
+        format!("Client {} proxied to {}", addr, upstream),
+    );
+    let session_id = ctx.sessions.register(addr);
+    let mut client = Framed::new(client, NullDelimitedCodec::new());
+    let mut controller = Framed::new(controller, NullDelimitedCodec::new());
+
+    loop {
+        let (frame, out, direction) = tokio::select! {
+            frame = client.next() => (frame, &mut controller, Direction::ClientToController),
+            frame = controller.next() => (frame, &mut client, Direction::ControllerToClient),
+        };
+        let frame = match frame {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => {
+                eprintln!("Proxy read error ({:?}): {}", direction, e);
+                break;
+            }
+            None => break,
+        };
+        if let Err(e) = forward(out, frame, direction, addr, ctx).await {
+            eprintln!("Proxy closed {} ({:?}): {}", addr, direction, e);
+            break;
+        }
+    }
+
+    ctx.sessions.remove(session_id);
+    println!("Proxied client disconnected: {}", addr);
+    state.audit(
+        AuditCategory::Connection,
+        format!("Proxied client disconnected: {}", addr),
+    );
+}
+
+/// Pass one frame through failure injection, record it and forward what is left
+async fn forward(
+    out: &mut FramedStream,
+    frame: BytesMut,
+    direction: Direction,
+    client: SocketAddr,
+    ctx: &AppContext,
+) -> std::io::Result<()> {
+    let failure_config = ctx.observable_state.read().failure_config.clone();
+    let injection = FailureSimulator::new(failure_config).decide(frame.to_vec());
+    let context = format!("proxy {:?}", direction);
+    let trace = InjectionTrace::new(client, &frame, &context, &injection);
+    let log = &ctx.exchange_log;
+
+    match injection {
+        Injection::Disconnect => {
+            log.record(
+                client,
+                direction,
+                &frame,
+                ExchangeOutcome::Disconnected,
+                Duration::ZERO,
+                None,
+            );
+            ctx.injection_log.finish(trace, false);
+            Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "Simulated connection drop",
+            ))
+        }
+        Injection::Drop => {
+            log.record(
+                client,
+                direction,
+                &frame,
+                ExchangeOutcome::Dropped,
+                Duration::ZERO,
+                None,
+            );
+            ctx.injection_log.finish(trace, false);
+            Ok(())
+        }
+        Injection::Send {
+            bytes,
+            delay,
+            corrupted,
+        } => {
+            let (outcome, forwarded) = if corrupted {
+                (ExchangeOutcome::Corrupted, Some(bytes.as_slice()))
+            } else {
+                (ExchangeOutcome::Forwarded, None)
+            };
+            log.record(client, direction, &frame, outcome, delay, forwarded);
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            let sent = out.send(bytes.as_slice().into()).await;
+            ctx.injection_log.finish(trace, sent.is_ok());
+            sent
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::failure_simulator::FailureConfig;
+    use crate::observable_state::ObservableState;
+    use crate::state::DeviceState;
+
+    const START: &[u8] = b"00200001001         ";
+    const START_ACK: &[u8] = b"00200002001         ";
+
+    async fn connect(addr: SocketAddr) -> FramedStream {
+        Framed::new(
+            TcpStream::connect(addr).await.unwrap(),
+            NullDelimitedCodec::new(),
+        )
+    }
+
+    /// Fake controller answering one MID 0001 with MID 0002
+    async fn spawn_controller() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = Framed::new(stream, NullDelimitedCodec::new());
+            let frame = stream.next().await.unwrap().unwrap();
+            assert_eq!(&frame[..], START);
+            stream.send(START_ACK.into()).await.unwrap();
+            // Keep the connection open until the proxy closes it
+            let _ = stream.next().await;
+        });
+        addr
+    }
+
+    fn context() -> AppContext {
+        let (tx, _rx) = tokio::sync::broadcast::channel(16);
+        AppContext::with_state(
+            ObservableState::new(DeviceState::new_shared(), tx),
+            Settings::default(),
+        )
+    }
+
+    async fn spawn_proxy(ctx: &AppContext, upstream: SocketAddr) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, upstream.to_string(), ctx.clone()));
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_relays_and_records_both_directions() {
+        let ctx = context();
+        let proxy = spawn_proxy(&ctx, spawn_controller().await).await;
+
+        let mut client = connect(proxy).await;
+        client.send(START.into()).await.unwrap();
+        let frame = client.next().await.unwrap().unwrap();
+        assert_eq!(&frame[..], START_ACK);
+
+        let exchanges = ctx.exchange_log.since(0);
+        assert_eq!(exchanges.len(), 2);
+        assert_eq!(exchanges[0].direction, Direction::ClientToController);
+        assert_eq!(exchanges[0].mid, Some(1));
+        assert_eq!(exchanges[1].direction, Direction::ControllerToClient);
+        assert_eq!(exchanges[1].mid, Some(2));
+        assert!(
+            exchanges
+                .iter()
+                .all(|e| e.outcome == ExchangeOutcome::Forwarded)
+        );
+        assert_eq!(ctx.exchange_log.since(1).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_injected_packet_loss_drops_client_frames() {
+        let ctx = context();
+        ctx.observable_state.write().failure_config = FailureConfig {
+            enabled: true,
+            packet_loss_rate: 1.0,
+            ..FailureConfig::default()
+        };
+        let proxy = spawn_proxy(&ctx, spawn_controller().await).await;
+
+        let mut client = connect(proxy).await;
+        client.send(START.into()).await.unwrap();
+        let read = tokio::time::timeout(Duration::from_millis(200), client.next()).await;
+        assert!(read.is_err(), "nothing should come back");
+
+        let exchanges = ctx.exchange_log.since(0);
+        assert_eq!(exchanges.len(), 1);
+        assert_eq!(exchanges[0].outcome, ExchangeOutcome::Dropped);
+
+        let injections = ctx.injection_log.since(0);
+        assert_eq!(injections.len(), 1);
+        assert_eq!(injections[0].mid, Some(1));
+        assert_eq!(
+            injections[0].action,
+            crate::failure_simulator::InjectionAction::Dropped
+        );
+        assert!(
+            ctx.injection_log
+                .since(0)
+                .iter()
+                .all(|i| i.actual_send_at.is_none())
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Importing PSET libraries
+//!
+//! PSETs exported from several real stations usually reuse the same IDs.
+//! `POST /psets/import` stores a batch of PSETs under their own IDs where
+//! possible and resolves collisions (with stored PSETs or earlier entries of
+//! the batch) per [`IdConflict`]. The response maps every original ID to the
+//! ID it was stored under; MID 0011 lists the stored IDs.
+
+use super::{Pset, SharedPsetRepository};
+use crate::handler::data::pset_id_upload::MAX_PSET_ID;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// What to do with an imported PSET whose ID is taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdConflict {
+    /// Import nothing if any ID is taken
+    #[default]
+    Reject,
+    /// Leave the colliding PSETs out
+    Skip,
+    /// Add `offset` to every imported ID (e.g. 100 for the second station)
+    Offset,
+    /// Give colliding PSETs the lowest free ID
+    NextFree,
+}
+
+/// Where one imported PSET ended up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IdMapping {
+    /// ID in the imported library
+    pub original_id: u32,
+    /// ID it is stored under; None when it was not imported
+    pub id: Option<u32>,
+    pub name: String,
+    /// Left out on purpose by `on_conflict = "skip"`
+    pub skipped: bool,
+    /// Why it was not imported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Entries stored under another ID than their own
+    pub remapped: usize,
+    /// Entries `Skip` left out because their ID is taken
+    pub skipped: usize,
+    /// Entries that could not be stored
+    pub failed: usize,
+    /// One entry per imported PSET, in request order
+    pub mappings: Vec<IdMapping>,
+}
+
+/// Original IDs of a batch that collide, when `strategy` is `Reject`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflicts(pub Vec<u32>);
+
+/// Target ID of each PSET in `psets` (None when `Skip` leaves it out), or
+/// why it has none
+///
+/// `taken` holds the IDs already stored. Every planned ID is 1-999, the
+/// range MID 0011 can list.
+pub fn plan(
+    taken: &BTreeSet<u32>,
+    psets: &[Pset],
+    strategy: IdConflict,
+    offset: u32,
+) -> Result<Vec<Result<Option<u32>, String>>, Conflicts> {
+    let mut used = taken.clone();
+    if strategy == IdConflict::Reject {
+        let mut seen = BTreeSet::new();
+        let conflicts: Vec<u32> = psets
+            .iter()
+            .map(|pset| pset.id)
+            .filter(|id| used.contains(id) || !seen.insert(*id))
+            .collect();
+        if !conflicts.is_empty() {
+            return Err(Conflicts(conflicts));
+        }
+    }
+
+    Ok(psets
+        .iter()
+        .map(|pset| {
+            let wanted = match strategy {
+                IdConflict::Offset => pset.id.saturating_add(offset),
+                _ => pset.id,
+            };
+            let id = if !used.contains(&wanted) {
+                wanted
+            } else if strategy == IdConflict::NextFree {
+                (1..=MAX_PSET_ID)
+                    .find(|id| !used.contains(id))
+                    .ok_or_else(|| "No free PSET ID left".to_string())?
+            } else if strategy == IdConflict::Skip {
+                return Ok(None);
+            } else {
+                return Err(format!("PSET id {} is already taken", wanted));
+            };
+            if id == 0 || id > MAX_PSET_ID {
+                return Err(format!("PSET id {} is outside 1-{}", id, MAX_PSET_ID));
+            }
+            used.insert(id);
+            Ok(Some(id))
+        })
+        .collect())
+}
+
+/// Store `psets` per `strategy`; returns the conflicting IDs instead when
+/// `Reject` finds any
+pub fn import(
+    repository: &SharedPsetRepository,
+    psets: Vec<Pset>,
+    strategy: IdConflict,
+    offset: u32,
+) -> Result<ImportReport, Conflicts> {
+    let mut repository = repository.write().unwrap();
+    let taken = repository.get_all().iter().map(|pset| pset.id).collect();
+    let targets = plan(&taken, &psets, strategy, offset)?;
+
+    let mappings: Vec<IdMapping> = psets
+        .into_iter()
+        .zip(targets)
+        .map(|(pset, target)| {
+            let original_id = pset.id;
+            let name = pset.name.clone();
+            let stored = match target {
+                Ok(Some(id)) => repository
+                    .create_with_id(Pset { id, ..pset })
+                    .map(|stored| Some(stored.id)),
+                target => target,
+            };
+            match stored {
+                Ok(Some(id)) => IdMapping {
+                    original_id,
+                    id: Some(id),
+                    name,
+                    skipped: false,
+                    error: None,
+                },
+                Ok(None) => IdMapping {
+                    original_id,
+                    id: None,
+                    name,
+                    skipped: true,
+                    error: Some(format!("PSET id {} is already taken", original_id)),
+                },
+                Err(error) => IdMapping {
+                    original_id,
+                    id: None,
+                    name,
+                    skipped: false,
+                    error: Some(error),
+                },
+            }
+        })
+        .collect();
+
+    let imported = mappings.iter().filter(|m| m.id.is_some()).count();
+    let skipped = mappings.iter().filter(|m| m.skipped).count();
+    Ok(ImportReport {
+        imported,
+        remapped: mappings
+            .iter()
+            .filter(|m| m.id.is_some_and(|id| id != m.original_id))
+            .count(),
+        skipped,
+        failed: mappings.len() - imported - skipped,
+        mappings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pset::create_default_repository;
+
+    fn pset(id: u32, name: &str) -> Pset {
+        Pset::new(id, name.to_string(), 5.0, 10.0, 30.0, 45.0, None)
+    }
+
+    #[test]
+    fn test_plan_strategies() {
+        let taken = BTreeSet::from([1, 2, 3]);
+        let psets = [pset(2, "a"), pset(7, "b"), pset(7, "c")];
+
+        assert_eq!(
+            plan(&taken, &psets, IdConflict::Reject, 0),
+            Err(Conflicts(vec![2, 7]))
+        );
+        assert_eq!(
+            plan(&taken, &psets, IdConflict::NextFree, 0).unwrap(),
+            vec![Ok(Some(4)), Ok(Some(7)), Ok(Some(5))]
+        );
+        assert_eq!(
+            plan(&taken, &psets, IdConflict::Offset, 100).unwrap(),
+            vec![
+                Ok(Some(102)),
+                Ok(Some(107)),
+                Err("PSET id 107 is already taken".to_string())
+            ]
+        );
+        assert_eq!(
+            plan(&taken, &psets, IdConflict::Skip, 0).unwrap(),
+            vec![Ok(None), Ok(Some(7)), Ok(None)]
+        );
+
+        assert!(plan(&taken, &[pset(950, "d")], IdConflict::Offset, 100).unwrap()[0].is_err());
+    }
+
+    #[test]
+    fn test_import_stores_under_planned_ids() {
+        let repository = create_default_repository();
+        let report = import(
+            &repository,
+            vec![pset(1, "Station B Light"), pset(40, "Station B Special")],
+            IdConflict::NextFree,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            (
+                report.imported,
+                report.remapped,
+                report.skipped,
+                report.failed
+            ),
+            (2, 1, 0, 0)
+        );
+        assert_eq!(report.mappings[0].id, Some(6));
+        assert_eq!(report.mappings[1].id, Some(40));
+        let repository = repository.read().unwrap();
+        assert_eq!(repository.get_by_id(6).unwrap().name, "Station B Light");
+        assert_eq!(repository.get_by_id(40).unwrap().name, "Station B Special");
+    }
+
+    #[test]
+    fn test_skipped_psets_are_not_failures() {
+        let repository = create_default_repository();
+        let report = import(
+            &repository,
+            vec![pset(1, "Station B Light"), pset(1000, "Out of range")],
+            IdConflict::Skip,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!((report.imported, report.skipped, report.failed), (0, 1, 1));
+        assert!(report.mappings[0].skipped);
+        assert!(!report.mappings[1].skipped);
+        assert!(report.mappings[1].error.is_some());
+    }
+}
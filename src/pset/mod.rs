@@ -0,0 +1,340 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+pub mod import;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqlitePsetRepository, create_sqlite_repository};
+
+/// Action taken when a batch position exceeds the PSET's NOK retry limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryAction {
+    /// Skip the position: the batch counter is incremented as with MID 0128
+    #[default]
+    Skip,
+    /// Lock (disable) the tool and raise an alarm until the integrator intervenes
+    LockTool,
+}
+
+impl RetryAction {
+    /// Name used for storage and the HTTP API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetryAction::Skip => "skip",
+            RetryAction::LockTool => "lock_tool",
+        }
+    }
+
+    /// Parse a stored name, falling back to `Skip` for unknown values
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "lock_tool" => RetryAction::LockTool,
+            _ => RetryAction::Skip,
+        }
+    }
+}
+
+/// Tightening strategy of a PSET
+///
+/// Decides which measured values drive the OK/NOK verdict and which strategy
+/// code is reported in MID 0061 revision 2 and later.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TighteningStrategy {
+    /// Torque control: only torque decides OK/NOK (strategy 01)
+    TorqueControl,
+    /// Angle control with torque monitoring: only angle decides (strategy 04)
+    AngleControl,
+    /// Torque control and angle control: both must be OK (strategy 03)
+    #[default]
+    TorqueAndAngle,
+    /// Reverse angle (loosening): only angle decides (strategy 07)
+    Reverse,
+}
+
+impl TighteningStrategy {
+    /// Name used for storage and the HTTP API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TighteningStrategy::TorqueControl => "torque_control",
+            TighteningStrategy::AngleControl => "angle_control",
+            TighteningStrategy::TorqueAndAngle => "torque_and_angle",
+            TighteningStrategy::Reverse => "reverse",
+        }
+    }
+
+    /// Parse a stored name, falling back to `TorqueAndAngle` for unknown values
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "torque_control" => TighteningStrategy::TorqueControl,
+            "angle_control" => TighteningStrategy::AngleControl,
+            "reverse" => TighteningStrategy::Reverse,
+            _ => TighteningStrategy::TorqueAndAngle,
+        }
+    }
+
+    /// Strategy code sent in MID 0061 (revision 2+)
+    pub fn code(&self) -> u8 {
+        match self {
+            TighteningStrategy::TorqueControl => 1,
+            TighteningStrategy::TorqueAndAngle => 3,
+            TighteningStrategy::AngleControl => 4,
+            TighteningStrategy::Reverse => 7,
+        }
+    }
+
+    /// Overall verdict from the individual torque and angle verdicts
+    pub fn evaluate(&self, torque_ok: bool, angle_ok: bool) -> bool {
+        match self {
+            TighteningStrategy::TorqueControl => torque_ok,
+            TighteningStrategy::AngleControl | TighteningStrategy::Reverse => angle_ok,
+            TighteningStrategy::TorqueAndAngle => torque_ok && angle_ok,
+        }
+    }
+}
+
+/// One intermediate step of a multistage PSET
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsetStage {
+    pub torque_min: f64,
+    pub torque_max: f64,
+    pub angle_min: f64,
+    pub angle_max: f64,
+}
+
+/// How long a tightening with this PSET takes
+///
+/// Replaces the global tightening duration for the FSM and auto-tightening,
+/// so takt-time analysis sees different cycle times per program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CycleTime {
+    /// Rundown until the joint snugs, in milliseconds
+    pub rundown_ms: u64,
+    /// Tightening from snug to the final torque, in milliseconds
+    pub tightening_ms: u64,
+    /// Each cycle is up to this many milliseconds shorter or longer (uniform)
+    #[serde(default)]
+    pub variance_ms: u64,
+}
+
+impl CycleTime {
+    /// Cycle time without variance
+    pub fn nominal_ms(&self) -> u64 {
+        self.rundown_ms.saturating_add(self.tightening_ms)
+    }
+
+    /// Duration of one cycle, at least 1 ms
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        let nominal = self.nominal_ms();
+        if self.variance_ms == 0 {
+            return nominal.max(1);
+        }
+        let variance = self.variance_ms.min(i64::MAX as u64) as i64;
+        let offset = rng.random_range(-variance..=variance);
+        nominal.saturating_add_signed(offset).max(1)
+    }
+}
+
+/// Parameter Set (PSET) configuration for tightening operations
+/// Each PSET defines the target ranges for torque and angle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pset {
+    pub id: u32,
+    pub name: String,
+    pub torque_min: f64,
+    pub torque_max: f64,
+    pub angle_min: f64,
+    pub angle_max: f64,
+    pub description: Option<String>,
+    /// Maximum consecutive NOK results at one batch position (None = unlimited)
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    /// What happens when `max_retries` is exceeded
+    #[serde(default)]
+    pub retry_action: RetryAction,
+    /// Which values drive OK/NOK
+    #[serde(default)]
+    pub strategy: TighteningStrategy,
+    /// Steps run in order before the final step (the PSET's own limits);
+    /// empty for single-stage PSETs
+    #[serde(default)]
+    pub stages: Vec<PsetStage>,
+    /// Cycle time model (None = the configured tightening duration)
+    #[serde(default)]
+    pub cycle_time: Option<CycleTime>,
+}
+
+impl Pset {
+    pub fn new(
+        id: u32,
+        name: String,
+        torque_min: f64,
+        torque_max: f64,
+        angle_min: f64,
+        angle_max: f64,
+        description: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            torque_min,
+            torque_max,
+            angle_min,
+            angle_max,
+            description,
+            max_retries: None,
+            retry_action: RetryAction::default(),
+            strategy: TighteningStrategy::default(),
+            stages: Vec::new(),
+            cycle_time: None,
+        }
+    }
+
+    /// Check if a tightening result is OK under this PSET's limits and strategy
+    pub fn is_within_range(&self, torque: f64, angle: f64) -> bool {
+        let torque_ok = torque >= self.torque_min && torque <= self.torque_max;
+        let angle_ok = angle >= self.angle_min && angle <= self.angle_max;
+        self.strategy.evaluate(torque_ok, angle_ok)
+    }
+}
+
+/// Repository trait for PSET persistence
+/// This abstraction allows for easy switching between in-memory and database storage
+pub trait PsetRepository: Send + Sync {
+    fn get_all(&self) -> Vec<Pset>;
+    fn get_by_id(&self, id: u32) -> Option<Pset>;
+    fn create(&mut self, pset: Pset) -> Result<Pset, String>;
+    /// Store a PSET under its own ID instead of the next free one
+    fn create_with_id(&mut self, pset: Pset) -> Result<Pset, String>;
+    fn update(&mut self, id: u32, pset: Pset) -> Result<Pset, String>;
+    fn delete(&mut self, id: u32) -> Result<(), String>;
+}
+
+/// In-memory implementation of PsetRepository
+/// Future: Replace with SQLite-backed implementation
+pub struct InMemoryPsetRepository {
+    psets: Vec<Pset>,
+}
+
+impl InMemoryPsetRepository {
+    pub fn new() -> Self {
+        Self {
+            psets: Self::default_psets(),
+        }
+    }
+
+    /// Returns 5 hardcoded default PSETs
+    fn default_psets() -> Vec<Pset> {
+        vec![
+            Pset::new(
+                1,
+                "Light Duty".to_string(),
+                5.0,
+                10.0,
+                30.0,
+                45.0,
+                Some("Low torque applications (e.g., electronics, small assemblies)".to_string()),
+            ),
+            Pset::new(
+                2,
+                "Standard".to_string(),
+                10.0,
+                15.0,
+                35.0,
+                50.0,
+                Some("General purpose tightening operations".to_string()),
+            ),
+            Pset::new(
+                3,
+                "Heavy Duty".to_string(),
+                15.0,
+                25.0,
+                40.0,
+                60.0,
+                Some("High torque applications (e.g., automotive, machinery)".to_string()),
+            ),
+            Pset::new(
+                4,
+                "Precision".to_string(),
+                8.0,
+                12.0,
+                20.0,
+                30.0,
+                Some("Tight tolerance requirements".to_string()),
+            ),
+            Pset::new(
+                5,
+                "Extra Heavy".to_string(),
+                25.0,
+                40.0,
+                50.0,
+                90.0,
+                Some("Maximum torque applications (e.g., industrial equipment)".to_string()),
+            ),
+        ]
+    }
+}
+
+impl Default for InMemoryPsetRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PsetRepository for InMemoryPsetRepository {
+    fn get_all(&self) -> Vec<Pset> {
+        self.psets.clone()
+    }
+
+    fn get_by_id(&self, id: u32) -> Option<Pset> {
+        self.psets.iter().find(|p| p.id == id).cloned()
+    }
+
+    fn create(&mut self, mut pset: Pset) -> Result<Pset, String> {
+        // Generate new ID
+        let max_id = self.psets.iter().map(|p| p.id).max().unwrap_or(0);
+        pset.id = max_id + 1;
+
+        self.psets.push(pset.clone());
+        Ok(pset)
+    }
+
+    fn create_with_id(&mut self, pset: Pset) -> Result<Pset, String> {
+        if self.psets.iter().any(|p| p.id == pset.id) {
+            return Err(format!("PSET with id {} already exists", pset.id));
+        }
+        self.psets.push(pset.clone());
+        Ok(pset)
+    }
+
+    fn update(&mut self, id: u32, pset: Pset) -> Result<Pset, String> {
+        if let Some(existing) = self.psets.iter_mut().find(|p| p.id == id) {
+            *existing = pset.clone();
+            Ok(pset)
+        } else {
+            Err(format!("PSET with id {} not found", id))
+        }
+    }
+
+    fn delete(&mut self, id: u32) -> Result<(), String> {
+        let initial_len = self.psets.len();
+        self.psets.retain(|p| p.id != id);
+
+        if self.psets.len() < initial_len {
+            Ok(())
+        } else {
+            Err(format!("PSET with id {} not found", id))
+        }
+    }
+}
+
+/// Thread-safe wrapper for PsetRepository
+pub type SharedPsetRepository = Arc<RwLock<Box<dyn PsetRepository>>>;
+
+pub fn create_default_repository() -> SharedPsetRepository {
+    Arc::new(RwLock::new(Box::new(InMemoryPsetRepository::new())))
+}
@@ -1,172 +1,14 @@
+//! SQLite persistence for parameter sets (`sqlite` feature)
+
+use super::{
+    CycleTime, InMemoryPsetRepository, Pset, PsetRepository, PsetStage, RetryAction,
+    SharedPsetRepository, TighteningStrategy,
+};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{Result as SqliteResult, params};
-use serde::{Deserialize, Serialize};
 use std::sync::{Arc, RwLock};
 
-/// Parameter Set (PSET) configuration for tightening operations
-/// Each PSET defines the target ranges for torque and angle
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Pset {
-    pub id: u32,
-    pub name: String,
-    pub torque_min: f64,
-    pub torque_max: f64,
-    pub angle_min: f64,
-    pub angle_max: f64,
-    pub description: Option<String>,
-}
-
-impl Pset {
-    pub fn new(
-        id: u32,
-        name: String,
-        torque_min: f64,
-        torque_max: f64,
-        angle_min: f64,
-        angle_max: f64,
-        description: Option<String>,
-    ) -> Self {
-        Self {
-            id,
-            name,
-            torque_min,
-            torque_max,
-            angle_min,
-            angle_max,
-            description,
-        }
-    }
-
-    /// Check if a tightening result is within this PSET's parameters
-    pub fn is_within_range(&self, torque: f64, angle: f64) -> bool {
-        torque >= self.torque_min
-            && torque <= self.torque_max
-            && angle >= self.angle_min
-            && angle <= self.angle_max
-    }
-}
-
-/// Repository trait for PSET persistence
-/// This abstraction allows for easy switching between in-memory and database storage
-pub trait PsetRepository: Send + Sync {
-    fn get_all(&self) -> Vec<Pset>;
-    fn get_by_id(&self, id: u32) -> Option<Pset>;
-    fn create(&mut self, pset: Pset) -> Result<Pset, String>;
-    fn update(&mut self, id: u32, pset: Pset) -> Result<Pset, String>;
-    fn delete(&mut self, id: u32) -> Result<(), String>;
-}
-
-/// In-memory implementation of PsetRepository
-/// Future: Replace with SQLite-backed implementation
-pub struct InMemoryPsetRepository {
-    psets: Vec<Pset>,
-}
-
-impl InMemoryPsetRepository {
-    pub fn new() -> Self {
-        Self {
-            psets: Self::default_psets(),
-        }
-    }
-
-    /// Returns 5 hardcoded default PSETs
-    fn default_psets() -> Vec<Pset> {
-        vec![
-            Pset::new(
-                1,
-                "Light Duty".to_string(),
-                5.0,
-                10.0,
-                30.0,
-                45.0,
-                Some("Low torque applications (e.g., electronics, small assemblies)".to_string()),
-            ),
-            Pset::new(
-                2,
-                "Standard".to_string(),
-                10.0,
-                15.0,
-                35.0,
-                50.0,
-                Some("General purpose tightening operations".to_string()),
-            ),
-            Pset::new(
-                3,
-                "Heavy Duty".to_string(),
-                15.0,
-                25.0,
-                40.0,
-                60.0,
-                Some("High torque applications (e.g., automotive, machinery)".to_string()),
-            ),
-            Pset::new(
-                4,
-                "Precision".to_string(),
-                8.0,
-                12.0,
-                20.0,
-                30.0,
-                Some("Tight tolerance requirements".to_string()),
-            ),
-            Pset::new(
-                5,
-                "Extra Heavy".to_string(),
-                25.0,
-                40.0,
-                50.0,
-                90.0,
-                Some("Maximum torque applications (e.g., industrial equipment)".to_string()),
-            ),
-        ]
-    }
-}
-
-impl Default for InMemoryPsetRepository {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl PsetRepository for InMemoryPsetRepository {
-    fn get_all(&self) -> Vec<Pset> {
-        self.psets.clone()
-    }
-
-    fn get_by_id(&self, id: u32) -> Option<Pset> {
-        self.psets.iter().find(|p| p.id == id).cloned()
-    }
-
-    fn create(&mut self, mut pset: Pset) -> Result<Pset, String> {
-        // Generate new ID
-        let max_id = self.psets.iter().map(|p| p.id).max().unwrap_or(0);
-        pset.id = max_id + 1;
-
-        self.psets.push(pset.clone());
-        Ok(pset)
-    }
-
-    fn update(&mut self, id: u32, pset: Pset) -> Result<Pset, String> {
-        if let Some(existing) = self.psets.iter_mut().find(|p| p.id == id) {
-            *existing = pset.clone();
-            Ok(pset)
-        } else {
-            Err(format!("PSET with id {} not found", id))
-        }
-    }
-
-    fn delete(&mut self, id: u32) -> Result<(), String> {
-        let initial_len = self.psets.len();
-        self.psets.retain(|p| p.id != id);
-
-        if self.psets.len() < initial_len {
-            Ok(())
-        } else {
-            Err(format!("PSET with id {} not found", id))
-        }
-    }
-}
-
 /// SQLite-backed implementation of PsetRepository
 pub struct SqlitePsetRepository {
     pool: Pool<SqliteConnectionManager>,
@@ -201,6 +43,11 @@ impl SqlitePsetRepository {
                 angle_min REAL NOT NULL,
                 angle_max REAL NOT NULL,
                 description TEXT,
+                max_retries INTEGER,
+                retry_action TEXT NOT NULL DEFAULT 'skip',
+                strategy TEXT NOT NULL DEFAULT 'torque_and_angle',
+                stages TEXT NOT NULL DEFAULT '[]',
+                cycle_time TEXT,
                 is_default INTEGER NOT NULL DEFAULT 0,
                 created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
                 updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
@@ -209,6 +56,43 @@ impl SqlitePsetRepository {
         )
         .map_err(|e| format!("Failed to create table: {}", e))?;
 
+        // Databases created before the retry limit, strategy, stages and cycle time existed lack these columns
+        Self::ensure_column(&conn, "max_retries", "INTEGER")?;
+        Self::ensure_column(&conn, "retry_action", "TEXT NOT NULL DEFAULT 'skip'")?;
+        Self::ensure_column(
+            &conn,
+            "strategy",
+            "TEXT NOT NULL DEFAULT 'torque_and_angle'",
+        )?;
+        Self::ensure_column(&conn, "stages", "TEXT NOT NULL DEFAULT '[]'")?;
+        Self::ensure_column(&conn, "cycle_time", "TEXT")?;
+
+        Ok(())
+    }
+
+    /// Add a column to the psets table if it does not exist yet
+    fn ensure_column(
+        conn: &rusqlite::Connection,
+        column: &str,
+        definition: &str,
+    ) -> Result<(), String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM pragma_table_info('psets')")
+            .map_err(|e| format!("Failed to read schema: {}", e))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to read schema: {}", e))?
+            .filter_map(Result::ok)
+            .any(|name| name == column);
+
+        if !exists {
+            conn.execute(
+                &format!("ALTER TABLE psets ADD COLUMN {} {}", column, definition),
+                [],
+            )
+            .map_err(|e| format!("Failed to add column {}: {}", column, e))?;
+        }
+
         Ok(())
     }
 
@@ -256,49 +140,32 @@ impl SqlitePsetRepository {
             angle_min: row.get(4)?,
             angle_max: row.get(5)?,
             description: row.get(6)?,
+            max_retries: row.get::<_, Option<i64>>(7)?.map(|v| v as u32),
+            retry_action: RetryAction::from_name(&row.get::<_, String>(8)?),
+            strategy: TighteningStrategy::from_name(&row.get::<_, String>(9)?),
+            stages: Self::stages_from_json(&row.get::<_, String>(10)?),
+            cycle_time: row
+                .get::<_, Option<String>>(11)?
+                .and_then(|json| serde_json::from_str(&json).ok()),
         })
     }
-}
-
-impl PsetRepository for SqlitePsetRepository {
-    fn get_all(&self) -> Vec<Pset> {
-        let conn = match self.pool.get() {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("Failed to get connection: {}", e);
-                return vec![];
-            }
-        };
-
-        let mut stmt = match conn.prepare("SELECT id, name, torque_min, torque_max, angle_min, angle_max, description FROM psets ORDER BY id") {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("Failed to prepare statement: {}", e);
-                return vec![];
-            }
-        };
 
-        match stmt.query_map([], Self::row_to_pset) {
-            Ok(rows) => rows.filter_map(Result::ok).collect(),
-            Err(e) => {
-                eprintln!("Query failed: {}", e);
-                vec![]
-            }
-        }
+    /// Stages are stored as a JSON array; unreadable values load as single-stage
+    fn stages_from_json(json: &str) -> Vec<PsetStage> {
+        serde_json::from_str(json).unwrap_or_default()
     }
 
-    fn get_by_id(&self, id: u32) -> Option<Pset> {
-        let conn = self.pool.get().ok()?;
+    fn stages_to_json(stages: &[PsetStage]) -> String {
+        serde_json::to_string(stages).unwrap_or_else(|_| "[]".to_string())
+    }
 
-        conn.query_row(
-            "SELECT id, name, torque_min, torque_max, angle_min, angle_max, description FROM psets WHERE id = ?1",
-            params![id as i64],
-            Self::row_to_pset,
-        )
-        .ok()
+    /// The cycle time is stored as a JSON object; NULL uses the configured duration
+    fn cycle_time_to_json(cycle_time: Option<CycleTime>) -> Option<String> {
+        cycle_time.and_then(|cycle_time| serde_json::to_string(&cycle_time).ok())
     }
 
-    fn create(&mut self, pset: Pset) -> Result<Pset, String> {
+    /// Insert a PSET under `id`, or under the next free row ID when None
+    fn insert(&mut self, pset: Pset, id: Option<u32>) -> Result<Pset, String> {
         let conn = self
             .pool
             .get()
@@ -317,17 +184,36 @@ impl PsetRepository for SqlitePsetRepository {
         if pset.angle_max > 360.0 {
             return Err("angle_max cannot exceed 360 degrees".to_string());
         }
+        if pset
+            .stages
+            .iter()
+            .any(|stage| stage.torque_min > stage.torque_max || stage.angle_min > stage.angle_max)
+        {
+            return Err("Stage limits must have min <= max".to_string());
+        }
+        if pset
+            .cycle_time
+            .is_some_and(|cycle_time| cycle_time.nominal_ms() == 0)
+        {
+            return Err("cycle_time must be at least 1 ms".to_string());
+        }
 
         conn.execute(
-            "INSERT INTO psets (name, torque_min, torque_max, angle_min, angle_max, description)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO psets (id, name, torque_min, torque_max, angle_min, angle_max, description, max_retries, retry_action, strategy, stages, cycle_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
+                id.map(|id| id as i64),
                 pset.name,
                 pset.torque_min,
                 pset.torque_max,
                 pset.angle_min,
                 pset.angle_max,
-                pset.description
+                pset.description,
+                pset.max_retries.map(|v| v as i64),
+                pset.retry_action.as_str(),
+                pset.strategy.as_str(),
+                Self::stages_to_json(&pset.stages),
+                Self::cycle_time_to_json(pset.cycle_time)
             ],
         )
         .map_err(|e| {
@@ -342,6 +228,57 @@ impl PsetRepository for SqlitePsetRepository {
         self.get_by_id(id)
             .ok_or_else(|| "Failed to retrieve created PSET".to_string())
     }
+}
+
+impl PsetRepository for SqlitePsetRepository {
+    fn get_all(&self) -> Vec<Pset> {
+        let conn = match self.pool.get() {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Failed to get connection: {}", e);
+                return vec![];
+            }
+        };
+
+        let mut stmt = match conn.prepare("SELECT id, name, torque_min, torque_max, angle_min, angle_max, description, max_retries, retry_action, strategy, stages, cycle_time FROM psets ORDER BY id") {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Failed to prepare statement: {}", e);
+                return vec![];
+            }
+        };
+
+        match stmt.query_map([], Self::row_to_pset) {
+            Ok(rows) => rows.filter_map(Result::ok).collect(),
+            Err(e) => {
+                eprintln!("Query failed: {}", e);
+                vec![]
+            }
+        }
+    }
+
+    fn get_by_id(&self, id: u32) -> Option<Pset> {
+        let conn = self.pool.get().ok()?;
+
+        conn.query_row(
+            "SELECT id, name, torque_min, torque_max, angle_min, angle_max, description, max_retries, retry_action, strategy, stages, cycle_time FROM psets WHERE id = ?1",
+            params![id as i64],
+            Self::row_to_pset,
+        )
+        .ok()
+    }
+
+    fn create(&mut self, pset: Pset) -> Result<Pset, String> {
+        self.insert(pset, None)
+    }
+
+    fn create_with_id(&mut self, pset: Pset) -> Result<Pset, String> {
+        if self.get_by_id(pset.id).is_some() {
+            return Err(format!("PSET with id {} already exists", pset.id));
+        }
+        let id = pset.id;
+        self.insert(pset, Some(id))
+    }
 
     fn update(&mut self, id: u32, pset: Pset) -> Result<Pset, String> {
         let conn = self
@@ -362,12 +299,27 @@ impl PsetRepository for SqlitePsetRepository {
         if pset.angle_max > 360.0 {
             return Err("angle_max cannot exceed 360 degrees".to_string());
         }
+        if pset
+            .stages
+            .iter()
+            .any(|stage| stage.torque_min > stage.torque_max || stage.angle_min > stage.angle_max)
+        {
+            return Err("Stage limits must have min <= max".to_string());
+        }
+        if pset
+            .cycle_time
+            .is_some_and(|cycle_time| cycle_time.nominal_ms() == 0)
+        {
+            return Err("cycle_time must be at least 1 ms".to_string());
+        }
 
         let rows_affected = conn
             .execute(
                 "UPDATE psets SET name = ?1, torque_min = ?2, torque_max = ?3,
-                 angle_min = ?4, angle_max = ?5, description = ?6, updated_at = CURRENT_TIMESTAMP
-                 WHERE id = ?7",
+                 angle_min = ?4, angle_max = ?5, description = ?6, max_retries = ?7,
+                 retry_action = ?8, strategy = ?9, stages = ?10, cycle_time = ?11,
+                 updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?12",
                 params![
                     pset.name,
                     pset.torque_min,
@@ -375,6 +327,11 @@ impl PsetRepository for SqlitePsetRepository {
                     pset.angle_min,
                     pset.angle_max,
                     pset.description,
+                    pset.max_retries.map(|v| v as i64),
+                    pset.retry_action.as_str(),
+                    pset.strategy.as_str(),
+                    Self::stages_to_json(&pset.stages),
+                    Self::cycle_time_to_json(pset.cycle_time),
                     id as i64
                 ],
             )
@@ -424,14 +381,6 @@ impl PsetRepository for SqlitePsetRepository {
         }
     }
 }
-
-/// Thread-safe wrapper for PsetRepository
-pub type SharedPsetRepository = Arc<RwLock<Box<dyn PsetRepository>>>;
-
-pub fn create_default_repository() -> SharedPsetRepository {
-    Arc::new(RwLock::new(Box::new(InMemoryPsetRepository::new())))
-}
-
 pub fn create_sqlite_repository(db_path: &str) -> Result<SharedPsetRepository, String> {
     let repo = SqlitePsetRepository::new(db_path)?;
     Ok(Arc::new(RwLock::new(Box::new(repo))))
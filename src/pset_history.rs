@@ -0,0 +1,170 @@
+//! History of parameter set changes
+//!
+//! Every PSET creation, update, deletion and selection is recorded with its
+//! time and source. The latest creation or update of a PSET is its "date of
+//! last change in parameter set settings" reported in MID 0015 and MID 0061;
+//! PSETs unchanged since startup report the startup time. Traceability tests
+//! read the history of one PSET from `GET /psets/{id}/history`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// PSET changes kept in memory
+pub const PSET_HISTORY_CAPACITY: usize = 1000;
+
+/// What happened to the PSET
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PsetChangeKind {
+    Created,
+    Updated,
+    Deleted,
+    Selected,
+}
+
+impl PsetChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PsetChangeKind::Created => "created",
+            PsetChangeKind::Updated => "updated",
+            PsetChangeKind::Deleted => "deleted",
+            PsetChangeKind::Selected => "selected",
+        }
+    }
+
+    /// Whether the change alters the PSET settings (and so its last-change date)
+    pub fn changes_settings(self) -> bool {
+        matches!(self, PsetChangeKind::Created | PsetChangeKind::Updated)
+    }
+}
+
+/// Who made the change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PsetChangeSource {
+    #[serde(rename = "http")]
+    Http,
+    /// Parameter set selection by an Open Protocol client
+    #[serde(rename = "mid_0018")]
+    Mid0018,
+    /// First PSET of a job selected by an Open Protocol client (MID 0038)
+    #[serde(rename = "mid_0038")]
+    Mid0038,
+    /// First PSET of a job restarted by an Open Protocol client (MID 0039)
+    #[serde(rename = "mid_0039")]
+    Mid0039,
+    /// Select bits of the fieldbus I/O image
+    #[serde(rename = "fieldbus")]
+    Fieldbus,
+}
+
+/// One recorded change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsetChange {
+    pub pset_id: u32,
+    pub kind: PsetChangeKind,
+    pub source: PsetChangeSource,
+    /// Local time, Open Protocol format (YYYY-MM-DD:HH:MM:SS)
+    pub timestamp: String,
+}
+
+/// Bounded history of PSET changes and the last settings change per PSET
+#[derive(Debug, Clone)]
+pub struct PsetHistory {
+    entries: VecDeque<PsetChange>,
+    capacity: usize,
+    last_change: HashMap<u32, String>,
+    started_at: String,
+}
+
+fn now() -> String {
+    chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string()
+}
+
+impl PsetHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+            last_change: HashMap::new(),
+            started_at: now(),
+        }
+    }
+
+    /// Record a change made now
+    pub fn record(
+        &mut self,
+        pset_id: u32,
+        kind: PsetChangeKind,
+        source: PsetChangeSource,
+    ) -> PsetChange {
+        let change = PsetChange {
+            pset_id,
+            kind,
+            source,
+            timestamp: now(),
+        };
+        if kind.changes_settings() {
+            self.last_change.insert(pset_id, change.timestamp.clone());
+        }
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(change.clone());
+        change
+    }
+
+    /// Date of the last settings change of a PSET (startup time if none)
+    pub fn last_change(&self, pset_id: u32) -> &str {
+        self.last_change
+            .get(&pset_id)
+            .map_or(&self.started_at, String::as_str)
+    }
+
+    /// Retained changes of one PSET, oldest first
+    pub fn for_pset(&self, pset_id: u32) -> Vec<PsetChange> {
+        self.entries
+            .iter()
+            .filter(|change| change.pset_id == pset_id)
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for PsetHistory {
+    fn default() -> Self {
+        Self::new(PSET_HISTORY_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_does_not_change_settings_date() {
+        let mut history = PsetHistory::new(2);
+        let started_at = history.last_change(1).to_string();
+
+        history
+            .last_change
+            .insert(1, "2024-01-01:08:00:00".to_string());
+        history.record(1, PsetChangeKind::Selected, PsetChangeSource::Mid0018);
+        assert_eq!(history.last_change(1), "2024-01-01:08:00:00");
+
+        let updated = history.record(1, PsetChangeKind::Updated, PsetChangeSource::Http);
+        assert_eq!(history.last_change(1), updated.timestamp);
+        assert_eq!(history.last_change(2), started_at);
+
+        history.record(2, PsetChangeKind::Created, PsetChangeSource::Http);
+        assert_eq!(history.len(), 2, "oldest change evicted");
+        assert_eq!(history.for_pset(1), vec![updated]);
+    }
+}
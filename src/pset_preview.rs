@@ -0,0 +1,230 @@
+//! Dry runs of the tightening model
+//!
+//! `GET /psets/{id}/simulate` runs the model behind the FSM and
+//! auto-tightening a number of times for one PSET, optionally with different
+//! limits, and reports the torque and angle distribution and how many
+//! results come out NOK. Nothing is stored or broadcast, so PSET limits and
+//! failure rates can be tuned before a long scenario is started.
+
+use crate::device_fsm::{NokReason, TighteningOutcome, TighteningParams};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Buckets of each histogram
+pub const HISTOGRAM_BUCKETS: usize = 10;
+
+/// Spread of the simulated values of one quantity
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Distribution {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Population standard deviation
+    pub std_dev: f64,
+    /// Equal-width buckets from `min` to `max`
+    pub histogram: Vec<HistogramBucket>,
+}
+
+/// Values in `[from, to)`; the last bucket includes `to`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    pub from: f64,
+    pub to: f64,
+    pub count: usize,
+}
+
+impl Distribution {
+    /// None for an empty sample
+    fn of(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+
+        let width = (max - min) / HISTOGRAM_BUCKETS as f64;
+        let mut histogram: Vec<HistogramBucket> = (0..HISTOGRAM_BUCKETS)
+            .map(|i| HistogramBucket {
+                from: min + width * i as f64,
+                to: min + width * (i + 1) as f64,
+                count: 0,
+            })
+            .collect();
+        for value in values {
+            let index = if width > 0.0 {
+                (((value - min) / width) as usize).min(HISTOGRAM_BUCKETS - 1)
+            } else {
+                0
+            };
+            histogram[index].count += 1;
+        }
+
+        Some(Self {
+            min,
+            max,
+            mean,
+            std_dev: variance.sqrt(),
+            histogram,
+        })
+    }
+}
+
+/// Outcome of a dry run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PreviewReport {
+    pub count: usize,
+    pub ok: usize,
+    pub nok: usize,
+    /// Share of NOK results (0.0-1.0)
+    pub nok_rate: f64,
+    /// NOKs forced by the failure rate; the rest come from the limits
+    pub forced_nok: usize,
+    /// How often each limit was violated by a NOK result
+    pub nok_reasons: BTreeMap<NokReason, usize>,
+    /// None when `count` is zero
+    pub torque: Option<Distribution>,
+    pub angle: Option<Distribution>,
+}
+
+/// Run the tightening model `count` times
+///
+/// Each run draws its variation like the FSM does and, when it comes out OK,
+/// is forced NOK with probability `failure_rate`, as during auto-tightening.
+pub fn simulate(
+    params: &TighteningParams,
+    count: usize,
+    failure_rate: f64,
+    rng: &mut impl Rng,
+) -> PreviewReport {
+    let failure_rate = failure_rate.clamp(0.0, 1.0);
+    let duration = Duration::from_millis(params.duration_ms);
+    let mut torques = Vec::with_capacity(count);
+    let mut angles = Vec::with_capacity(count);
+    let mut ok = 0;
+    let mut forced_nok = 0;
+    let mut nok_reasons = BTreeMap::new();
+
+    for _ in 0..count {
+        let torque_variation = rng.random_range(0.0..0.1);
+        let angle_variation = rng.random_range(0.0..0.1);
+        let mut outcome =
+            TighteningOutcome::model(params, torque_variation, angle_variation, duration);
+        if outcome.ok && rng.random_bool(failure_rate) {
+            outcome.force_nok(params, NokReason::choose(params, rng));
+            forced_nok += 1;
+        }
+
+        if outcome.ok {
+            ok += 1;
+        } else {
+            for reason in violated_limits(params, &outcome) {
+                *nok_reasons.entry(reason).or_insert(0) += 1;
+            }
+        }
+        torques.push(outcome.actual_torque);
+        angles.push(outcome.actual_angle);
+    }
+
+    PreviewReport {
+        count,
+        ok,
+        nok: count - ok,
+        nok_rate: if count == 0 {
+            0.0
+        } else {
+            (count - ok) as f64 / count as f64
+        },
+        forced_nok,
+        nok_reasons,
+        torque: Distribution::of(&torques),
+        angle: Distribution::of(&angles),
+    }
+}
+
+/// Final limits the outcome is outside of
+fn violated_limits(params: &TighteningParams, outcome: &TighteningOutcome) -> Vec<NokReason> {
+    let mut reasons = Vec::new();
+    if outcome.actual_torque < params.torque_min {
+        reasons.push(NokReason::TorqueLow);
+    } else if outcome.actual_torque > params.torque_max {
+        reasons.push(NokReason::TorqueHigh);
+    }
+    if outcome.actual_angle < params.angle_min {
+        reasons.push(NokReason::AngleLow);
+    } else if outcome.actual_angle > params.angle_max {
+        reasons.push(NokReason::AngleHigh);
+    }
+    reasons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_wide_limits_are_always_ok() {
+        let params = TighteningParams::default_test();
+        let report = simulate(&params, 500, 0.0, &mut StdRng::seed_from_u64(1));
+
+        assert_eq!((report.count, report.ok, report.nok), (500, 500, 0));
+        assert!(report.nok_reasons.is_empty());
+        let torque = report.torque.unwrap();
+        assert!(torque.min >= 12.5 * 0.95 && torque.max < 12.5 * 1.05);
+        assert_eq!(torque.histogram.iter().map(|b| b.count).sum::<usize>(), 500);
+        assert_eq!(torque.histogram.len(), HISTOGRAM_BUCKETS);
+    }
+
+    #[test]
+    fn test_tight_limits_produce_noks() {
+        // Only the upper half of the +/- 5% band is within the torque limits
+        let params = TighteningParams {
+            torque_min: 12.5,
+            ..TighteningParams::default_test()
+        };
+        let report = simulate(&params, 2000, 0.0, &mut StdRng::seed_from_u64(2));
+
+        assert!(
+            (0.4..0.6).contains(&report.nok_rate),
+            "nok rate {}",
+            report.nok_rate
+        );
+        assert_eq!(report.forced_nok, 0);
+        assert_eq!(
+            report.nok_reasons.get(&NokReason::TorqueLow),
+            Some(&report.nok)
+        );
+    }
+
+    #[test]
+    fn test_failure_rate_forces_noks() {
+        let params = TighteningParams::default_test();
+        let report = simulate(&params, 2000, 0.25, &mut StdRng::seed_from_u64(3));
+
+        assert_eq!(report.forced_nok, report.nok);
+        assert!(
+            (0.2..0.3).contains(&report.nok_rate),
+            "nok rate {}",
+            report.nok_rate
+        );
+        let all = simulate(&params, 100, 1.0, &mut StdRng::seed_from_u64(3));
+        assert_eq!(all.nok, 100);
+    }
+
+    #[test]
+    fn test_empty_run() {
+        let report = simulate(
+            &TighteningParams::default_test(),
+            0,
+            0.5,
+            &mut StdRng::seed_from_u64(4),
+        );
+        assert_eq!(report.nok_rate, 0.0);
+        assert!(report.torque.is_none() && report.angle.is_none());
+    }
+}
@@ -0,0 +1,283 @@
+//! Resource accounting for long-running (soak) simulations
+//!
+//! Spawned tasks register a [`TaskGuard`] for as long as they run, connection
+//! tasks report their codec buffer sizes to the session registry, and the
+//! caps of `[server]` (`max_connections`, `max_frame_bytes`) are enforced by
+//! refusing clients and closing connections instead of letting memory grow.
+//! `GET /debug/resources` puts it all together with the event queue and the
+//! sizes of the in-memory stores, so a leak shows up as a number that keeps
+//! climbing.
+
+use crate::context::AppContext;
+use crate::events::SimulatorEvent;
+use crate::outbox::OutboxStatus;
+use crate::session_registry::DisconnectReason;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Running and total started tasks of one kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TaskCounts {
+    pub running: usize,
+    pub started: u64,
+}
+
+/// Connection lifecycle events seen so far
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LifecycleCounts {
+    pub connected: u64,
+    pub authenticated: u64,
+    pub subscribed: u64,
+    pub disconnected: BTreeMap<DisconnectReason, u64>,
+}
+
+/// Counters of spawned tasks and degraded operations
+#[derive(Debug, Default)]
+pub struct ResourceMonitor {
+    tasks: Mutex<BTreeMap<&'static str, TaskCounts>>,
+    refused_connections: AtomicU64,
+    oversized_frames: AtomicU64,
+    lifecycle: Mutex<LifecycleCounts>,
+}
+
+/// Counts a task as running until dropped
+#[derive(Debug)]
+pub struct TaskGuard {
+    monitor: Arc<ResourceMonitor>,
+    kind: &'static str,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if let Some(counts) = self.monitor.tasks.lock().unwrap().get_mut(self.kind) {
+            counts.running = counts.running.saturating_sub(1);
+        }
+    }
+}
+
+impl ResourceMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a task of `kind` as running; move the guard into the task
+    pub fn track(self: &Arc<Self>, kind: &'static str) -> TaskGuard {
+        let mut tasks = self.tasks.lock().unwrap();
+        let counts = tasks.entry(kind).or_default();
+        counts.running += 1;
+        counts.started += 1;
+        TaskGuard {
+            monitor: Arc::clone(self),
+            kind,
+        }
+    }
+
+    /// Running tasks of `kind`
+    pub fn running(&self, kind: &str) -> usize {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(kind)
+            .map_or(0, |counts| counts.running)
+    }
+
+    /// Task counts per kind
+    pub fn tasks(&self) -> BTreeMap<&'static str, TaskCounts> {
+        self.tasks.lock().unwrap().clone()
+    }
+
+    /// A client was turned away because `max_connections` was reached
+    pub fn record_refused_connection(&self) {
+        self.refused_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A connection was closed for exceeding `max_frame_bytes`
+    pub fn record_oversized_frame(&self) {
+        self.oversized_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a connection lifecycle event; other events are ignored
+    pub fn record_lifecycle(&self, event: &SimulatorEvent) {
+        let mut counts = self.lifecycle.lock().unwrap();
+        match event {
+            SimulatorEvent::ClientConnected { .. } => counts.connected += 1,
+            SimulatorEvent::ClientAuthenticated { .. } => counts.authenticated += 1,
+            SimulatorEvent::ClientSubscribed { .. } => counts.subscribed += 1,
+            SimulatorEvent::ClientDisconnected { reason, .. } => {
+                *counts.disconnected.entry(*reason).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn lifecycle(&self) -> LifecycleCounts {
+        self.lifecycle.lock().unwrap().clone()
+    }
+}
+
+/// Connection totals of a resource report
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionResources {
+    pub count: usize,
+    /// 0 means unlimited
+    pub max_connections: usize,
+    pub refused: u64,
+    /// Allocated codec read buffers over all connections
+    pub read_buffer_bytes: usize,
+    /// Allocated codec write buffers over all connections
+    pub write_buffer_bytes: usize,
+    pub largest_read_buffer_bytes: usize,
+    pub max_frame_bytes: usize,
+    /// Connections closed for sending an oversized frame
+    pub oversized_frames: u64,
+    pub lifecycle: LifecycleCounts,
+}
+
+/// Event channel usage of a resource report
+#[derive(Debug, Clone, Serialize)]
+pub struct EventQueueResources {
+    /// Events not yet received by the slowest receiver
+    pub queued: usize,
+    pub capacity: usize,
+    pub receivers: usize,
+}
+
+/// Entries held by the in-memory stores
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreResources {
+    pub results: usize,
+    pub audit_entries: usize,
+    pub alarms: usize,
+    pub proxy_exchanges: usize,
+    pub injection_log: usize,
+    pub outbox: BTreeMap<OutboxStatus, usize>,
+}
+
+/// Snapshot of everything `GET /debug/resources` reports
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceReport {
+    pub tasks: BTreeMap<&'static str, TaskCounts>,
+    pub connections: ConnectionResources,
+    pub events: EventQueueResources,
+    pub stores: StoreResources,
+}
+
+impl ResourceReport {
+    /// Collect the current resource usage of a simulator
+    pub fn collect(ctx: &AppContext) -> Self {
+        let server = &ctx.settings.server;
+        let sessions = ctx.sessions.snapshot();
+        let connections = ConnectionResources {
+            count: sessions.len(),
+            max_connections: server.max_connections,
+            refused: ctx.resources.refused_connections.load(Ordering::Relaxed),
+            read_buffer_bytes: sessions.iter().map(|s| s.read_buffer_bytes).sum(),
+            write_buffer_bytes: sessions.iter().map(|s| s.write_buffer_bytes).sum(),
+            largest_read_buffer_bytes: sessions
+                .iter()
+                .map(|s| s.read_buffer_bytes)
+                .max()
+                .unwrap_or(0),
+            max_frame_bytes: server.max_frame_bytes,
+            oversized_frames: ctx.resources.oversized_frames.load(Ordering::Relaxed),
+            lifecycle: ctx.resources.lifecycle(),
+        };
+        let events = EventQueueResources {
+            queued: ctx.events.len(),
+            capacity: server.event_channel_capacity,
+            receivers: ctx.events.receiver_count(),
+        };
+        let (audit_entries, alarms) = {
+            let state = ctx.observable_state.read();
+            (state.audit_log.len(), state.alarms.len())
+        };
+        let stores = StoreResources {
+            results: ctx.result_repository.read().unwrap().len(),
+            audit_entries,
+            alarms,
+            proxy_exchanges: ctx.exchange_log.len(),
+            injection_log: ctx.injection_log.len(),
+            outbox: ctx.outbox.read().unwrap().counts(),
+        };
+        Self {
+            tasks: ctx.resources.tasks(),
+            connections,
+            events,
+            stores,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_guard_counts_running_tasks() {
+        let monitor = Arc::new(ResourceMonitor::new());
+        let first = monitor.track("connection");
+        let second = monitor.track("connection");
+        assert_eq!(monitor.running("connection"), 2);
+        drop(first);
+        drop(second);
+        let _auto = monitor.track("auto_tightening");
+
+        let tasks = monitor.tasks();
+        assert_eq!(
+            tasks["connection"],
+            TaskCounts {
+                running: 0,
+                started: 2
+            }
+        );
+        assert_eq!(tasks["auto_tightening"].running, 1);
+    }
+
+    #[test]
+    fn test_lifecycle_events_are_counted() {
+        let monitor = ResourceMonitor::new();
+        let addr = "127.0.0.1:4545".parse().unwrap();
+        let events = [
+            SimulatorEvent::ClientConnected {
+                session_id: 1,
+                addr,
+            },
+            SimulatorEvent::ClientAuthenticated {
+                session_id: 1,
+                addr,
+            },
+            SimulatorEvent::ClientSubscribed {
+                session_id: 1,
+                kind: crate::subscriptions::SubscriptionKind::TighteningResult,
+            },
+            SimulatorEvent::ClientDisconnected {
+                session_id: 1,
+                addr,
+                reason: DisconnectReason::ClosedByController,
+            },
+            SimulatorEvent::BatchCompleted {
+                total: 3,
+                tool_locked: false,
+            },
+        ];
+        for event in &events {
+            monitor.record_lifecycle(event);
+        }
+
+        let counts = monitor.lifecycle();
+        assert_eq!(counts.connected, 1);
+        assert_eq!(counts.authenticated, 1);
+        assert_eq!(counts.subscribed, 1);
+        assert_eq!(
+            counts.disconnected,
+            BTreeMap::from([(DisconnectReason::ClosedByController, 1)])
+        );
+
+        let json = serde_json::to_value(&events[3]).unwrap();
+        assert_eq!(json["type"], "ClientDisconnected");
+        assert_eq!(json["addr"], "127.0.0.1:4545");
+        assert_eq!(json["reason"], "closed_by_controller");
+    }
+}
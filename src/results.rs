@@ -0,0 +1,371 @@
+//! Tightening results history
+//!
+//! Every tightening produced by the simulator is recorded here so it can be
+//! listed and exported over HTTP. Storage follows the same repository pattern
+//! as PSETs, so a persistent implementation can be swapped in later.
+//!
+//! With `[results] signing_key` set, every result carries an HMAC-SHA256
+//! `signature` over its JSON form, so quality systems that verify result
+//! integrity end to end can include the simulator in their chain.
+//!
+//! A stored result can be voided afterwards (`POST /results/{id}/void`), as
+//! controllers allow for rejected joints; see [`ResultVoid`].
+
+use crate::curve::TighteningCurve;
+use crate::handler::data::TighteningResult;
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+
+/// Contextual metadata attached to a simulated tightening
+///
+/// Not part of MID 0061; carried in events, the results history and exports
+/// so downstream pipelines receive the fields they expect.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TighteningMetadata {
+    /// Operator badge / ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_id: Option<String>,
+
+    /// Station name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub station: Option<String>,
+
+    /// Free-form key/values (trace IDs, order numbers, ...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl TighteningMetadata {
+    /// True when no metadata has been set
+    pub fn is_empty(&self) -> bool {
+        self.operator_id.is_none() && self.station.is_none() && self.extra.is_empty()
+    }
+
+    /// Render `extra` as `key=value` pairs separated by `;` (for flat exports)
+    pub fn extra_as_pairs(&self) -> String {
+        self.extra
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+/// Controller-side correction of a stored result
+///
+/// A voided result stays in the history with its tightening status set to
+/// NOK and is sent again on MID 0061. Every revision the simulator produces
+/// carries the tightening ID, so subscribers can match the correction to
+/// the result they already have.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultVoid {
+    pub reason: String,
+    /// Format: YYYY-MM-DD:HH:MM:SS
+    pub voided_at: String,
+}
+
+impl ResultVoid {
+    pub fn now(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            voided_at: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+        }
+    }
+}
+
+/// Repository trait for the tightening results history
+pub trait ResultRepository: Send + Sync {
+    /// Store a result (oldest entries may be evicted)
+    fn add(&mut self, result: TighteningResult);
+    /// All stored results, oldest first
+    fn get_all(&self) -> Vec<TighteningResult>;
+    /// The most recent `limit` results, oldest first
+    fn get_recent(&self, limit: usize) -> Vec<TighteningResult>;
+    /// Look up a result by tightening ID
+    fn get_by_id(&self, tightening_id: u32) -> Option<TighteningResult>;
+    /// Overwrite the stored result with the same tightening ID; false if there is none
+    fn replace(&mut self, result: TighteningResult) -> bool;
+    /// Store the trace of a stored result (dropped together with the result)
+    fn add_curve(&mut self, curve: TighteningCurve);
+    /// Trace of a result by tightening ID
+    fn get_curve(&self, tightening_id: u32) -> Option<TighteningCurve>;
+    /// Number of stored results
+    fn len(&self) -> usize;
+    /// Whether the history is empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// In-memory results history bounded to a fixed number of entries
+pub struct InMemoryResultRepository {
+    results: VecDeque<TighteningResult>,
+    curves: HashMap<u32, TighteningCurve>,
+    capacity: usize,
+}
+
+impl InMemoryResultRepository {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            results: VecDeque::new(),
+            curves: HashMap::new(),
+            capacity: capacity.max(1),
+        }
+    }
+}
+
+impl ResultRepository for InMemoryResultRepository {
+    fn add(&mut self, result: TighteningResult) {
+        if self.results.len() >= self.capacity
+            && let Some(evicted) = self.results.pop_front()
+            && let Some(id) = evicted.tightening_id
+        {
+            self.curves.remove(&id);
+        }
+        self.results.push_back(result);
+    }
+
+    fn get_all(&self) -> Vec<TighteningResult> {
+        self.results.iter().cloned().collect()
+    }
+
+    fn get_recent(&self, limit: usize) -> Vec<TighteningResult> {
+        let skip = self.results.len().saturating_sub(limit);
+        self.results.iter().skip(skip).cloned().collect()
+    }
+
+    fn get_by_id(&self, tightening_id: u32) -> Option<TighteningResult> {
+        self.results
+            .iter()
+            .rev()
+            .find(|r| r.tightening_id == Some(tightening_id))
+            .cloned()
+    }
+
+    fn replace(&mut self, result: TighteningResult) -> bool {
+        let stored = self
+            .results
+            .iter_mut()
+            .rev()
+            .find(|r| r.tightening_id.is_some() && r.tightening_id == result.tightening_id);
+        match stored {
+            Some(stored) => {
+                *stored = result;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn add_curve(&mut self, curve: TighteningCurve) {
+        self.curves.insert(curve.tightening_id, curve);
+    }
+
+    fn get_curve(&self, tightening_id: u32) -> Option<TighteningCurve> {
+        self.curves.get(&tightening_id).cloned()
+    }
+
+    fn len(&self) -> usize {
+        self.results.len()
+    }
+}
+
+/// Thread-safe wrapper for ResultRepository
+pub type SharedResultRepository = Arc<RwLock<Box<dyn ResultRepository>>>;
+
+pub fn create_result_repository(capacity: usize) -> SharedResultRepository {
+    Arc::new(RwLock::new(Box::new(InMemoryResultRepository::new(
+        capacity,
+    ))))
+}
+
+/// HMAC-SHA256 of `result` under `key`, hex encoded
+///
+/// Covers the JSON form of the result (as served by `GET /results`) with
+/// the signature left out.
+pub fn signature(result: &TighteningResult, key: &str) -> String {
+    let unsigned = TighteningResult {
+        signature: None,
+        ..result.clone()
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(&serde_json::to_vec(&unsigned).expect("results serialize to JSON"));
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Attach the signature of `result` under `key`
+pub fn sign(result: &mut TighteningResult, key: &str) {
+    result.signature = Some(signature(result, key));
+}
+
+/// Whether `result` carries a valid signature under `key`
+pub fn verify(result: &TighteningResult, key: &str) -> bool {
+    result
+        .signature
+        .as_ref()
+        .is_some_and(|signed| *signed == signature(result, key))
+}
+
+/// Column header of the CSV export
+const CSV_HEADER: &str = "tightening_id,timestamp,pset_id,job_id,vin,batch_size,batch_counter,\
+status,torque,torque_min,torque_max,angle,angle_min,angle_max,operator_id,station,metadata,\
+signature";
+
+/// Render results as CSV (one row per tightening, metadata flattened)
+pub fn to_csv(results: &[TighteningResult]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+
+    for r in results {
+        let row = [
+            r.tightening_id.map(|id| id.to_string()).unwrap_or_default(),
+            csv_escape(&r.timestamp),
+            r.pset_id.to_string(),
+            r.job_id.to_string(),
+            csv_escape(r.vin_number.as_deref().unwrap_or("")),
+            r.batch_size.to_string(),
+            r.batch_counter.to_string(),
+            if r.tightening_status { "OK" } else { "NOK" }.to_string(),
+            format!("{:.2}", r.torque),
+            format!("{:.2}", r.torque_min),
+            format!("{:.2}", r.torque_max),
+            format!("{:.1}", r.angle),
+            format!("{:.1}", r.angle_min),
+            format!("{:.1}", r.angle_max),
+            csv_escape(r.metadata.operator_id.as_deref().unwrap_or("")),
+            csv_escape(r.metadata.station.as_deref().unwrap_or("")),
+            csv_escape(&r.metadata.extra_as_pairs()),
+            r.signature.clone().unwrap_or_default(),
+        ];
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Quote a CSV field if it contains separators, quotes or newlines
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_with_id(id: u32) -> TighteningResult {
+        TighteningResult {
+            tightening_id: Some(id),
+            ..TighteningResult::example()
+        }
+    }
+
+    #[test]
+    fn test_history_evicts_oldest() {
+        let mut repo = InMemoryResultRepository::new(2);
+        repo.add(result_with_id(1));
+        repo.add(result_with_id(2));
+        repo.add(result_with_id(3));
+
+        assert_eq!(repo.len(), 2);
+        assert!(repo.get_by_id(1).is_none());
+        assert!(repo.get_by_id(3).is_some());
+    }
+
+    #[test]
+    fn test_curves_are_evicted_with_their_result() {
+        let mut repo = InMemoryResultRepository::new(1);
+        let first = result_with_id(1);
+        repo.add(first.clone());
+        repo.add_curve(TighteningCurve::generate(&first, 500, 10));
+        assert!(repo.get_curve(1).is_some());
+
+        repo.add(result_with_id(2));
+        assert!(repo.get_curve(1).is_none());
+    }
+
+    #[test]
+    fn test_get_recent_returns_latest_oldest_first() {
+        let mut repo = InMemoryResultRepository::new(10);
+        for id in 1..=5 {
+            repo.add(result_with_id(id));
+        }
+
+        let recent: Vec<_> = repo
+            .get_recent(2)
+            .into_iter()
+            .map(|r| r.tightening_id.unwrap())
+            .collect();
+        assert_eq!(recent, vec![4, 5]);
+    }
+
+    #[test]
+    fn test_csv_export_includes_metadata() {
+        let mut result = result_with_id(7);
+        result.metadata.operator_id = Some("badge-42".to_string());
+        result.metadata.station = Some("Station, 3".to_string());
+        result
+            .metadata
+            .extra
+            .insert("trace".to_string(), "abc".to_string());
+
+        let csv = to_csv(&[result]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        let row = lines.next().unwrap();
+        assert!(row.starts_with("7,"));
+        assert!(row.ends_with(",badge-42,\"Station, 3\",trace=abc,"));
+    }
+
+    #[test]
+    fn test_signature_detects_changes() {
+        let mut result = result_with_id(7);
+        assert!(!verify(&result, "secret"));
+        sign(&mut result, "secret");
+        assert_eq!(result.signature.as_ref().unwrap().len(), 64);
+        assert!(verify(&result, "secret"));
+        assert!(!verify(&result, "other"));
+
+        // Survives the JSON round trip of GET /results and exports
+        let json = serde_json::to_string(&result).unwrap();
+        let parsed: TighteningResult = serde_json::from_str(&json).unwrap();
+        assert!(verify(&parsed, "secret"));
+
+        result.torque += 0.01;
+        assert!(!verify(&result, "secret"));
+    }
+
+    #[test]
+    fn test_signature_survives_float_round_trip() {
+        // Measured values with all 17 significant digits parse back exactly
+        for i in 0..2000 {
+            let mut result = result_with_id(i);
+            result.torque = 12.5 + i as f64 * 0.001_234_567_890_123_4;
+            result.angle = 45.0 / (i as f64 + 7.0);
+            sign(&mut result, "secret");
+            let json = serde_json::to_string(&result).unwrap();
+            let parsed: TighteningResult = serde_json::from_str(&json).unwrap();
+            assert!(verify(&parsed, "secret"), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_metadata_is_empty() {
+        let mut metadata = TighteningMetadata::default();
+        assert!(metadata.is_empty());
+        metadata.station = Some("S1".to_string());
+        assert!(!metadata.is_empty());
+    }
+}
@@ -0,0 +1,555 @@
+//! Chaos schedules
+//!
+//! Long-running client tests need disturbances that recur at known times: an
+//! alarm every hour, a network outage at shift change, a flaky connection
+//! over lunch. Each `[[schedules]]` rule pairs a cron expression (minute,
+//! hour, day of month, month, day of week; local time) with an action. The
+//! scheduler task runs due rules; `GET /schedules` lists them with their next
+//! run and `DELETE /schedules/{id}` cancels one (an outage or degradation
+//! already in progress still ends on time).
+
+use crate::alarms::AlarmSeverity;
+use crate::audit::AuditCategory;
+use crate::context::AppContext;
+use crate::failure_simulator::FailureConfig;
+use crate::session_registry::CloseMode;
+use crate::state::{MaintenanceKind, MaintenanceNotice};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDateTime, TimeZone, Timelike,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::AbortHandle;
+
+/// Longest the scheduler sleeps before looking at the rules again
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// How far ahead a run is searched before a rule is considered never due
+const SEARCH_YEARS: i32 = 5;
+
+/// Error parsing a cron expression
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CronError {
+    #[error("expected 5 fields (minute hour day month weekday), got {0}")]
+    FieldCount(usize),
+
+    #[error("invalid {field} field {value:?}")]
+    InvalidField { field: &'static str, value: String },
+}
+
+/// Allowed values of one cron field as a bit set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FieldSet {
+    bits: u64,
+    /// `*` (possibly with a step), which matters for the day fields
+    wildcard: bool,
+}
+
+impl FieldSet {
+    fn contains(self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+
+    /// Parse `spec` (`*`, `5`, `1-5`, `*/15`, `10-40/10`, comma-separated lists)
+    fn parse(field: &'static str, spec: &str, min: u32, max: u32) -> Result<Self, CronError> {
+        let invalid = || CronError::InvalidField {
+            field,
+            value: spec.to_string(),
+        };
+        let number = |text: &str| -> Result<u32, CronError> {
+            text.parse::<u32>()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .ok_or_else(invalid)
+        };
+
+        let mut set = FieldSet {
+            bits: 0,
+            wildcard: spec.starts_with('*'),
+        };
+        for part in spec.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                return Err(invalid());
+            }
+            let (first, last) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((first, last)) => (number(first)?, number(last)?),
+                    None if part.contains('/') => (number(range)?, max),
+                    None => (number(range)?, number(range)?),
+                },
+            };
+            if first > last {
+                return Err(invalid());
+            }
+            for value in (first..=last).step_by(step as usize) {
+                set.bits |= 1 << value;
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Five-field cron expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: FieldSet,
+    hours: FieldSet,
+    days: FieldSet,
+    months: FieldSet,
+    weekdays: FieldSet,
+}
+
+impl CronExpr {
+    /// Parse `minute hour day-of-month month day-of-week`
+    ///
+    /// Day of week runs from 0 (Sunday) to 6; 7 is Sunday as well. Like cron,
+    /// a time matches when either day field matches if both are restricted.
+    pub fn parse(expression: &str) -> Result<Self, CronError> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(CronError::FieldCount(fields.len()));
+        };
+        let mut weekdays = FieldSet::parse("weekday", weekdays, 0, 7)?;
+        if weekdays.contains(7) {
+            weekdays.bits = (weekdays.bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: FieldSet::parse("minute", minutes, 0, 59)?,
+            hours: FieldSet::parse("hour", hours, 0, 23)?,
+            days: FieldSet::parse("day", days, 1, 31)?,
+            months: FieldSet::parse("month", months, 1, 12)?,
+            weekdays,
+        })
+    }
+
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        let day = self.days.contains(time.day());
+        let weekday = self
+            .weekdays
+            .contains(time.weekday().num_days_from_sunday());
+        match (self.days.wildcard, self.weekdays.wildcard) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// First matching minute after `after`, if any within the next years
+    pub fn next_after_naive(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let limit = after.with_year(after.year() + SEARCH_YEARS)?;
+        while time < limit {
+            if !self.months.contains(time.month()) || !self.matches_day(&time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !self.hours.contains(time.hour()) {
+                time = time.with_minute(0)? + ChronoDuration::hours(1);
+            } else if !self.minutes.contains(time.minute()) {
+                time += ChronoDuration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    /// First matching minute after `after` in local time
+    ///
+    /// Minutes skipped by a daylight saving change never match.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut time = after.naive_local();
+        loop {
+            time = self.next_after_naive(time)?;
+            if let Some(local) = Local.from_local_datetime(&time).earliest() {
+                return Some(local);
+            }
+        }
+    }
+}
+
+/// What a schedule rule does when it is due
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScheduleAction {
+    /// Raise a controller alarm (MID 0071 to alarm subscribers)
+    RaiseAlarm {
+        error_code: String,
+        #[serde(default = "default_alarm_description")]
+        description: String,
+        #[serde(default)]
+        severity: AlarmSeverity,
+    },
+
+    /// Drop every client and refuse connections for `duration_secs`
+    Outage { duration_secs: u64 },
+
+    /// Run failure injection at connection `health` (0-100) for
+    /// `duration_secs`, then restore the previous configuration
+    DegradeHealth { health: u8, duration_secs: u64 },
+}
+
+fn default_alarm_description() -> String {
+    "Scheduled alarm".to_string()
+}
+
+/// One `[[schedules]]` entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    /// Name shown in `GET /schedules` and the audit log
+    pub name: String,
+
+    /// Cron expression (local time)
+    pub cron: String,
+
+    #[serde(flatten)]
+    pub action: ScheduleAction,
+}
+
+/// A scheduled rule as listed by `GET /schedules`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduleInfo {
+    pub id: u64,
+    #[serde(flatten)]
+    pub rule: ScheduleRule,
+    /// Next run (Open Protocol timestamp format); `None` if never due again
+    pub next_run: Option<String>,
+    pub runs: u64,
+    pub last_run: Option<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    rule: ScheduleRule,
+    cron: CronExpr,
+    next_run: Option<DateTime<Local>>,
+    runs: u64,
+    last_run: Option<DateTime<Local>>,
+}
+
+fn format_time(time: &DateTime<Local>) -> String {
+    time.format("%Y-%m-%d:%H:%M:%S").to_string()
+}
+
+/// Active schedule rules by ID
+#[derive(Debug, Default)]
+pub struct Schedules {
+    entries: Mutex<BTreeMap<u64, Entry>>,
+    next_id: Mutex<u64>,
+}
+
+impl Schedules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedules of the valid `rules`; invalid ones are logged and skipped
+    pub fn from_rules(rules: &[ScheduleRule], now: DateTime<Local>) -> Self {
+        let schedules = Self::new();
+        for rule in rules {
+            if let Err(e) = schedules.add(rule.clone(), now) {
+                eprintln!("Ignoring schedule {:?}: {}", rule.name, e);
+            }
+        }
+        schedules
+    }
+
+    /// Schedule `rule`, returning its ID
+    pub fn add(&self, rule: ScheduleRule, now: DateTime<Local>) -> Result<u64, CronError> {
+        let cron = CronExpr::parse(&rule.cron)?;
+        let next_run = cron.next_after(now);
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+        self.entries.lock().unwrap().insert(
+            id,
+            Entry {
+                rule,
+                cron,
+                next_run,
+                runs: 0,
+                last_run: None,
+            },
+        );
+        Ok(id)
+    }
+
+    /// All rules, by ID
+    pub fn list(&self) -> Vec<ScheduleInfo> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, entry)| Self::info(*id, entry))
+            .collect()
+    }
+
+    /// Remove a rule, returning it
+    pub fn cancel(&self, id: u64) -> Option<ScheduleInfo> {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .map(|entry| Self::info(id, &entry))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    /// Earliest next run of any rule
+    pub fn next_run(&self) -> Option<DateTime<Local>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|entry| entry.next_run)
+            .min()
+    }
+
+    /// Rules due at `now`, moved on to their following run
+    pub fn take_due(&self, now: DateTime<Local>) -> Vec<ScheduleRule> {
+        let mut due = Vec::new();
+        for entry in self.entries.lock().unwrap().values_mut() {
+            if entry.next_run.is_some_and(|next| next <= now) {
+                entry.runs += 1;
+                entry.last_run = Some(now);
+                entry.next_run = entry.cron.next_after(now);
+                due.push(entry.rule.clone());
+            }
+        }
+        due
+    }
+
+    fn info(id: u64, entry: &Entry) -> ScheduleInfo {
+        ScheduleInfo {
+            id,
+            rule: entry.rule.clone(),
+            next_run: entry.next_run.as_ref().map(format_time),
+            runs: entry.runs,
+            last_run: entry.last_run.as_ref().map(format_time),
+        }
+    }
+}
+
+/// Run the rules of `ctx.schedules` when they are due
+pub fn spawn_schedules(ctx: &AppContext) -> Option<AbortHandle> {
+    if ctx.schedules.is_empty() {
+        return None;
+    }
+    let ctx = ctx.clone();
+    let task = ctx.resources.track("schedules");
+    let handle = tokio::spawn(async move {
+        let _task = task;
+        loop {
+            let now = Local::now();
+            let sleep = ctx
+                .schedules
+                .next_run()
+                .and_then(|next| (next - now).to_std().ok())
+                .map_or(MAX_SLEEP, |until| until.min(MAX_SLEEP));
+            tokio::time::sleep(sleep).await;
+            for rule in ctx.schedules.take_due(Local::now()) {
+                run(&ctx, rule);
+            }
+        }
+    });
+    Some(handle.abort_handle())
+}
+
+/// Carry out the action of a due rule
+fn run(ctx: &AppContext, rule: ScheduleRule) {
+    println!("Schedule {:?} is due", rule.name);
+    let state = &ctx.observable_state;
+    match rule.action {
+        ScheduleAction::RaiseAlarm {
+            error_code,
+            description,
+            severity,
+        } => {
+            state.raise_alarm(&error_code, &description, severity);
+        }
+        ScheduleAction::Outage { duration_secs } => {
+            let maintenance = &ctx.settings.maintenance;
+            let notice_ms = maintenance.notice_ms;
+            if notice_ms > 0 {
+                let notice = MaintenanceNotice {
+                    kind: MaintenanceKind::Outage,
+                    reason: rule.name.clone(),
+                    starts_in_ms: notice_ms,
+                    duration_ms: duration_secs.saturating_mul(1000),
+                    accepting_sessions: !maintenance.refuse_new_sessions,
+                };
+                if !state.announce_maintenance(notice) {
+                    println!("Controller down or busy, skipping {:?}", rule.name);
+                    return;
+                }
+            } else if !start_outage(ctx, &rule.name) {
+                return;
+            }
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                if notice_ms > 0 {
+                    tokio::time::sleep(Duration::from_millis(notice_ms)).await;
+                    if !start_outage(&ctx, &rule.name) {
+                        return;
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(duration_secs)).await;
+                ctx.observable_state.end_outage();
+            });
+        }
+        ScheduleAction::DegradeHealth {
+            health,
+            duration_secs,
+        } => {
+            let degraded = FailureConfig::from_health(health.min(100));
+            let previous = {
+                let mut state = state.write();
+                let previous = std::mem::replace(&mut state.failure_config, degraded.clone());
+                state.audit_log.record(
+                    AuditCategory::Configuration,
+                    format!(
+                        "Schedule {} degraded connection health to {}%",
+                        rule.name, health
+                    ),
+                );
+                previous
+            };
+            let state = state.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(duration_secs)).await;
+                let mut state = state.write();
+                // Leave the configuration alone if someone changed it meanwhile
+                if state.failure_config == degraded {
+                    state.failure_config = previous;
+                    state.audit_log.record(
+                        AuditCategory::Configuration,
+                        format!("Schedule {} restored connection health", rule.name),
+                    );
+                }
+            });
+        }
+    }
+}
+
+/// Drop every client and refuse connections; false if an outage is already running
+fn start_outage(ctx: &AppContext, name: &str) -> bool {
+    if !ctx.observable_state.begin_outage(name) {
+        println!("Outage already in progress, skipping {:?}", name);
+        return false;
+    }
+    for session in ctx.sessions.snapshot() {
+        ctx.sessions.request_close(session.id, CloseMode::Abort);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(date: (i32, u32, u32), hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+            .unwrap()
+            .and_hms_opt(hour, minute, 30)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_run() {
+        let hourly = CronExpr::parse("0 * * * *").unwrap();
+        assert_eq!(
+            hourly.next_after_naive(at((2024, 3, 5), 10, 15)),
+            Some(at((2024, 3, 5), 11, 0).with_second(0).unwrap())
+        );
+
+        // Shift change on weekdays at 06:00 and 14:00 (2024-03-09 is a Saturday)
+        let shifts = CronExpr::parse("0 6,14 * * 1-5").unwrap();
+        assert_eq!(
+            shifts.next_after_naive(at((2024, 3, 8), 14, 0)),
+            Some(at((2024, 3, 11), 6, 0).with_second(0).unwrap())
+        );
+
+        let steps = CronExpr::parse("*/20 12 * * *").unwrap();
+        assert_eq!(
+            steps.next_after_naive(at((2024, 3, 5), 12, 20)),
+            Some(at((2024, 3, 5), 12, 40).with_second(0).unwrap())
+        );
+
+        // Either day field matches when both are restricted; 7 is Sunday
+        let days = CronExpr::parse("0 0 1 * 7").unwrap();
+        assert_eq!(
+            days.next_after_naive(at((2024, 3, 5), 0, 0)),
+            Some(at((2024, 3, 10), 0, 0).with_second(0).unwrap())
+        );
+
+        assert_eq!(
+            CronExpr::parse("0 0 30 2 *")
+                .unwrap()
+                .next_after_naive(at((2024, 1, 1), 0, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cron_parse_errors() {
+        assert_eq!(CronExpr::parse("0 * * *"), Err(CronError::FieldCount(4)));
+        for expression in [
+            "60 * * * *",
+            "* 5-1 * * *",
+            "*/0 * * * *",
+            "* * 0 * *",
+            "x * * * *",
+        ] {
+            assert!(
+                matches!(
+                    CronExpr::parse(expression),
+                    Err(CronError::InvalidField { .. })
+                ),
+                "{expression}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_due_rules_move_on() {
+        let now = Local::now();
+        let schedules = Schedules::new();
+        let rule = ScheduleRule {
+            name: "outage".to_string(),
+            cron: "* * * * *".to_string(),
+            action: ScheduleAction::Outage { duration_secs: 120 },
+        };
+        let id = schedules.add(rule.clone(), now).unwrap();
+        assert!(
+            schedules
+                .add(
+                    ScheduleRule {
+                        cron: "bad".to_string(),
+                        ..rule.clone()
+                    },
+                    now
+                )
+                .is_err()
+        );
+
+        let next = schedules.next_run().unwrap();
+        assert!(schedules.take_due(now).is_empty());
+        assert_eq!(schedules.take_due(next), vec![rule]);
+        assert!(schedules.next_run().unwrap() > next);
+
+        let info = schedules.cancel(id).unwrap();
+        assert_eq!(info.runs, 1);
+        assert!(schedules.is_empty());
+        assert!(schedules.cancel(id).is_none());
+    }
+}
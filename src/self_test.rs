@@ -0,0 +1,415 @@
+//! Smoke test of a running simulator
+//!
+//! `open-protocol-device-simulator self-test` starts the simulator on
+//! ephemeral loopback ports and drives it as an Open Protocol client would:
+//! communication start, PSET selection, result subscription, a tightening
+//! triggered over the HTTP API, the pushed MID 0061 and a keep-alive. The
+//! exit code is non-zero when a step fails, so the command works as a
+//! container health gate or to verify an installation.
+
+use crate::codec::null_delimited_codec::NullDelimitedCodec;
+use crate::conformance::CheckStatus;
+use crate::protocol::{Message, Mid, parser, serializer};
+use crate::webhook::{WebhookUrl, post_json};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+
+/// Steps in script order: (name, what the simulator must do)
+pub const STEPS: [(&str, &str); 7] = [
+    ("connect", "Accepts a TCP connection"),
+    ("communication_start", "Answers MID 0001 with MID 0002"),
+    ("pset_select", "Confirms MID 0018 with MID 0016"),
+    ("result_subscription", "Accepts MID 0060 with MID 0005"),
+    ("simulate_tightening", "POST /simulate/tightening succeeds"),
+    (
+        "tightening_result",
+        "Pushes a well-formed MID 0061 for the selected PSET",
+    ),
+    ("keep_alive", "Echoes MID 9999"),
+];
+
+/// Settings of a self-test run
+#[derive(Debug, Clone)]
+pub struct SelfTestConfig {
+    /// Time allowed for each step
+    pub step_timeout: Duration,
+    /// PSET selected with MID 0018
+    pub pset_id: u32,
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            step_timeout: Duration::from_secs(5),
+            pset_id: 1,
+        }
+    }
+}
+
+/// Outcome of one step
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+/// Outcome of a self-test run
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub steps: Vec<StepResult>,
+}
+
+impl SelfTestReport {
+    fn push(&mut self, name: &'static str, status: CheckStatus, detail: String, duration_ms: u64) {
+        let description = STEPS
+            .iter()
+            .find(|(step, _)| *step == name)
+            .map_or("", |(_, description)| description);
+        self.steps.push(StepResult {
+            name,
+            description,
+            status,
+            detail,
+            duration_ms,
+        });
+    }
+
+    /// Record a finished step; false when it failed
+    fn record(
+        &mut self,
+        name: &'static str,
+        started: Instant,
+        outcome: Result<String, String>,
+    ) -> bool {
+        let passed = outcome.is_ok();
+        let (status, detail) = match outcome {
+            Ok(detail) => (CheckStatus::Passed, detail),
+            Err(detail) => (CheckStatus::Failed, detail),
+        };
+        self.push(name, status, detail, started.elapsed().as_millis() as u64);
+        passed
+    }
+
+    /// Mark every step that did not run as skipped
+    fn skip_remaining(&mut self, reason: &str) {
+        for (name, _) in STEPS {
+            if !self.steps.iter().any(|step| step.name == name) {
+                self.push(name, CheckStatus::Skipped, reason.to_string(), 0);
+            }
+        }
+    }
+
+    /// True when no step failed
+    pub fn passed(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.status != CheckStatus::Failed)
+    }
+
+    fn count(&self, status: CheckStatus) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| step.status == status)
+            .count()
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Self-test report")?;
+        for step in &self.steps {
+            let status = match step.status {
+                CheckStatus::Passed => "PASS",
+                CheckStatus::Failed => "FAIL",
+                CheckStatus::Skipped => "SKIP",
+            };
+            writeln!(f, "  [{}] {:<20} {}", status, step.name, step.detail)?;
+        }
+        writeln!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.count(CheckStatus::Passed),
+            self.count(CheckStatus::Failed),
+            self.count(CheckStatus::Skipped)
+        )
+    }
+}
+
+/// Open Protocol connection to the simulator under test
+struct Client {
+    framed: Framed<TcpStream, NullDelimitedCodec>,
+    timeout: Duration,
+}
+
+impl Client {
+    async fn send(&mut self, mid: Mid, revision: u16, data: &[u8]) -> Result<(), String> {
+        let frame = serializer::serialize_frame(mid.value(), revision, data);
+        self.framed
+            .send(frame.as_slice().into())
+            .await
+            .map_err(|e| format!("Sending MID {} failed: {}", mid, e))
+    }
+
+    /// Next message from the simulator
+    async fn receive(&mut self, what: &str) -> Result<Message, String> {
+        match tokio::time::timeout(self.timeout, self.framed.next()).await {
+            Err(_) => Err(format!(
+                "No {} within {} ms",
+                what,
+                self.timeout.as_millis()
+            )),
+            Ok(Some(Ok(frame))) => {
+                parser::parse_message(&frame).map_err(|e| format!("Malformed {}: {}", what, e))
+            }
+            Ok(_) => Err(format!("Connection closed while waiting for {}", what)),
+        }
+    }
+
+    /// Send a request and expect `reply` back
+    async fn request(&mut self, mid: Mid, data: &[u8], reply: Mid) -> Result<Message, String> {
+        self.send(mid, 1, data).await?;
+        let message = self.receive(&format!("MID {}", reply)).await?;
+        if message.mid == Mid::COMMAND_ERROR.value() {
+            return Err(format!(
+                "MID {} refused with MID 0004 ({})",
+                mid,
+                String::from_utf8_lossy(&message.data)
+            ));
+        }
+        if message.mid != reply.value() {
+            return Err(format!(
+                "Got MID {:04} instead of MID {}",
+                message.mid, reply
+            ));
+        }
+        Ok(message)
+    }
+
+    /// Send a request the simulator accepts with MID 0005
+    async fn command(&mut self, mid: Mid, data: &[u8]) -> Result<String, String> {
+        let accepted = self.request(mid, data, Mid::COMMAND_ACCEPTED).await?;
+        if !accepted.data.starts_with(format!("{}", mid).as_bytes()) {
+            return Err(format!(
+                "MID 0005 accepted {} instead of MID {}",
+                String::from_utf8_lossy(&accepted.data),
+                mid
+            ));
+        }
+        Ok(format!("MID {} accepted", mid))
+    }
+}
+
+/// Field widths of a revision 1 MID 0061, parameters 01-23
+const RESULT_REV1_WIDTHS: [usize; 23] = [
+    4, 2, 25, 25, 2, 3, 4, 4, 1, 1, 1, 6, 6, 6, 6, 5, 5, 5, 5, 19, 19, 1, 10,
+];
+
+/// Check the parameter IDs of a revision 1 MID 0061 and return its PSET
+fn result_pset(data: &[u8]) -> Result<u32, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "MID 0061 is not ASCII".to_string())?;
+    let expected: usize = RESULT_REV1_WIDTHS.iter().map(|width| width + 2).sum();
+    if text.len() != expected {
+        return Err(format!(
+            "MID 0061 has {} data bytes, expected {}",
+            text.len(),
+            expected
+        ));
+    }
+    let mut offset = 0;
+    let mut pset = None;
+    for (id, width) in (1..).zip(RESULT_REV1_WIDTHS) {
+        if text[offset..offset + 2] != format!("{:02}", id) {
+            return Err(format!(
+                "MID 0061 parameter {:02} missing at byte {}",
+                id, offset
+            ));
+        }
+        let value = &text[offset + 2..offset + 2 + width];
+        if id == 6 {
+            pset = value.trim().parse().ok();
+        }
+        offset += 2 + width;
+    }
+    pset.ok_or_else(|| "MID 0061 PSET is not a number".to_string())
+}
+
+/// Run the smoke sequence against the TCP port `tcp`; the tightening is
+/// triggered through the HTTP API at `http`, or skipped without one
+pub async fn run(
+    tcp: SocketAddr,
+    http: Option<SocketAddr>,
+    config: &SelfTestConfig,
+) -> SelfTestReport {
+    let mut report = SelfTestReport { steps: Vec::new() };
+    script(tcp, http, config, &mut report).await;
+    report.skip_remaining("Not run: an earlier step failed");
+    report
+}
+
+async fn script(
+    tcp: SocketAddr,
+    http: Option<SocketAddr>,
+    config: &SelfTestConfig,
+    report: &mut SelfTestReport,
+) {
+    let started = Instant::now();
+    let stream = match tokio::time::timeout(config.step_timeout, TcpStream::connect(tcp)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            report.record(
+                "connect",
+                started,
+                Err(format!("Connecting to {} failed: {}", tcp, e)),
+            );
+            return;
+        }
+        Err(_) => {
+            report.record(
+                "connect",
+                started,
+                Err(format!(
+                    "No connection to {} within {} ms",
+                    tcp,
+                    config.step_timeout.as_millis()
+                )),
+            );
+            return;
+        }
+    };
+    report.record("connect", started, Ok(format!("Connected to {}", tcp)));
+    let mut client = Client {
+        framed: Framed::new(stream, NullDelimitedCodec::new()),
+        timeout: config.step_timeout,
+    };
+
+    let started = Instant::now();
+    let outcome = client
+        .request(Mid::COMMUNICATION_START, b"", Mid::COMMUNICATION_START_ACK)
+        .await
+        .map(|_| "Communication started".to_string());
+    if !report.record("communication_start", started, outcome) {
+        return;
+    }
+
+    let started = Instant::now();
+    let pset = format!("{:03}", config.pset_id);
+    // The simulator confirms the selection with MID 0016 like a real controller
+    let outcome = client
+        .request(Mid::SELECT_PSET, pset.as_bytes(), Mid::PSET_SELECTED_ACK)
+        .await
+        .map(|_| format!("PSET {} selected", config.pset_id));
+    if !report.record("pset_select", started, outcome) {
+        return;
+    }
+
+    let started = Instant::now();
+    let outcome = client.command(Mid::TIGHTENING_RESULT_SUBSCRIBE, b"").await;
+    if !report.record("result_subscription", started, outcome) {
+        return;
+    }
+
+    match http {
+        Some(http) => {
+            if !tightening(&mut client, http, config, report).await {
+                return;
+            }
+        }
+        None => {
+            for name in ["simulate_tightening", "tightening_result"] {
+                let reason = "Not run: no HTTP API to trigger a tightening".to_string();
+                report.push(name, CheckStatus::Skipped, reason, 0);
+            }
+        }
+    }
+
+    let started = Instant::now();
+    let outcome = async {
+        client.send(Mid::KEEP_ALIVE, 1, b"").await?;
+        loop {
+            // The MID 0062 above may still be answered with MID 0005
+            let message = client.receive("MID 9999").await?;
+            match Mid(message.mid) {
+                Mid::KEEP_ALIVE => return Ok("MID 9999 echoed".to_string()),
+                Mid::COMMAND_ACCEPTED => continue,
+                other => return Err(format!("Got MID {} instead of MID 9999", other)),
+            }
+        }
+    }
+    .await;
+    report.record("keep_alive", started, outcome);
+}
+
+/// Trigger a tightening over HTTP and check the pushed MID 0061; false when a step failed
+async fn tightening(
+    client: &mut Client,
+    http: SocketAddr,
+    config: &SelfTestConfig,
+    report: &mut SelfTestReport,
+) -> bool {
+    let started = Instant::now();
+    let url = WebhookUrl {
+        host: http.ip().to_string(),
+        port: http.port(),
+        path: "/simulate/tightening".to_string(),
+    };
+    let outcome = match tokio::time::timeout(config.step_timeout, post_json(&url, b"{}")).await {
+        Ok(Ok(())) => Ok(format!("Tightening simulated via {}", http)),
+        Ok(Err(e)) => Err(format!("POST /simulate/tightening failed: {}", e)),
+        Err(_) => Err(format!(
+            "POST /simulate/tightening did not answer within {} ms",
+            config.step_timeout.as_millis()
+        )),
+    };
+    if !report.record("simulate_tightening", started, outcome) {
+        return false;
+    }
+
+    let started = Instant::now();
+    let outcome = async {
+        let message = client.receive("MID 0061").await?;
+        if message.mid != Mid::TIGHTENING_RESULT.value() {
+            return Err(format!("Got MID {:04} instead of MID 0061", message.mid));
+        }
+        let pset = result_pset(&message.data)?;
+        if pset != config.pset_id {
+            return Err(format!(
+                "MID 0061 reports PSET {} instead of {}",
+                pset, config.pset_id
+            ));
+        }
+        client.send(Mid::TIGHTENING_RESULT_ACK, 1, b"").await?;
+        Ok(format!(
+            "MID 0061 for PSET {} received and acknowledged",
+            pset
+        ))
+    }
+    .await;
+    report.record("tightening_result", started, outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::data::TighteningResult;
+
+    #[test]
+    fn test_result_pset_checks_parameter_ids() {
+        let mut result = TighteningResult::example();
+        result.pset_id = 7;
+        let data = result.serialize_revision(1).unwrap();
+        assert_eq!(result_pset(&data), Ok(7));
+
+        let mut broken = data.clone();
+        broken[0] = b'9';
+        assert!(result_pset(&broken).unwrap_err().contains("parameter 01"));
+        assert!(result_pset(&data[..data.len() - 1]).is_err());
+    }
+}
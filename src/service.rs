@@ -0,0 +1,340 @@
+//! Running the simulator without a terminal session
+//!
+//! Lab machines keep the simulator running for days, started by the init
+//! system rather than from a shell. `--daemon` detaches it from the terminal
+//! on Unix (fork and new session; the working directory is kept so relative
+//! paths in the configuration still resolve), `--pid-file` records the process
+//! ID for init scripts and `--log-file` appends everything the simulator
+//! prints to a file. On Windows `--service` runs it under the service control
+//! manager.
+//!
+//! SIGTERM, SIGINT (Ctrl+C) or a service stop request shut the simulator down
+//! gracefully: no new clients are accepted, connected clients are closed after
+//! their pending broadcasts, the lifetime counters are saved and the pid file
+//! is removed.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ServiceError {
+    #[error("Cannot write pid file {path:?}: {source}")]
+    PidFile { path: PathBuf, source: io::Error },
+
+    #[error("Simulator already running with pid {pid} (pid file {path:?})")]
+    AlreadyRunning { path: PathBuf, pid: u32 },
+
+    #[error("Cannot open log file {path:?}: {source}")]
+    LogFile { path: PathBuf, source: io::Error },
+
+    #[error("Cannot detach from the terminal: {0}")]
+    Daemonize(io::Error),
+
+    #[error("{0} is not supported on this platform")]
+    Unsupported(&'static str),
+
+    #[error("Service control manager: {0}")]
+    Service(String),
+}
+
+/// Pid file of the running simulator, removed again when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Write the current process ID to `path`
+    ///
+    /// A pid file naming a live process is refused; a stale one left by a
+    /// crash is overwritten.
+    pub fn create(path: &Path) -> Result<Self, ServiceError> {
+        if let Some(pid) = running_pid(path) {
+            return Err(ServiceError::AlreadyRunning {
+                path: path.to_path_buf(),
+                pid,
+            });
+        }
+        std::fs::write(path, format!("{}\n", std::process::id())).map_err(|source| {
+            ServiceError::PidFile {
+                path: path.to_path_buf(),
+                source,
+            }
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Process ID recorded in `path` if that process is still alive
+fn running_pid(path: &Path) -> Option<u32> {
+    let pid: u32 = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    (pid != std::process::id() && process_alive(pid)).then_some(pid)
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks that the process exists; EPERM means it belongs to another user
+    // SAFETY: kill with signal 0 has no side effects
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    alive || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    // The service control manager already refuses to start a service twice
+    false
+}
+
+/// Append stdout and stderr to `path` from now on
+pub fn redirect_output(path: &Path) -> Result<(), ServiceError> {
+    let log_error = |source| ServiceError::LogFile {
+        path: path.to_path_buf(),
+        source,
+    };
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(log_error)?;
+    redirect_std(file).map_err(log_error)
+}
+
+#[cfg(unix)]
+fn redirect_std(file: File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    for target in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        // SAFETY: both descriptors are valid; dup2 replaces the target atomically
+        if unsafe { libc::dup2(file.as_raw_fd(), target) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn redirect_std(file: File) -> io::Result<()> {
+    use std::os::windows::io::IntoRawHandle;
+    use windows_sys::Win32::System::Console::{STD_ERROR_HANDLE, STD_OUTPUT_HANDLE, SetStdHandle};
+
+    // The handle stays open for the rest of the process
+    let handle = file.into_raw_handle();
+    for target in [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE] {
+        // SAFETY: the handle is a valid file handle owned by the process
+        if unsafe { SetStdHandle(target, handle) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn redirect_std(_file: File) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Detach from the controlling terminal
+///
+/// Must run before the Tokio runtime (or any other thread) is started: only
+/// the forking thread survives in the child. The parent exits right away;
+/// stdin, stdout and stderr of the child point to `/dev/null` until
+/// [`redirect_output`] sends the latter two to a log file.
+#[cfg(unix)]
+pub fn daemonize() -> Result<(), ServiceError> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: single-threaded at this point, so the child is in a consistent state
+    match unsafe { libc::fork() } {
+        -1 => return Err(ServiceError::Daemonize(io::Error::last_os_error())),
+        0 => {}
+        _ => std::process::exit(0),
+    }
+    // SAFETY: the child is not a process group leader, so setsid cannot fail with EPERM
+    if unsafe { libc::setsid() } < 0 {
+        return Err(ServiceError::Daemonize(io::Error::last_os_error()));
+    }
+    let null = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/null")
+        .map_err(ServiceError::Daemonize)?;
+    for target in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        // SAFETY: both descriptors are valid
+        if unsafe { libc::dup2(null.as_raw_fd(), target) } < 0 {
+            return Err(ServiceError::Daemonize(io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn daemonize() -> Result<(), ServiceError> {
+    Err(ServiceError::Unsupported(
+        "--daemon (use --service on Windows)",
+    ))
+}
+
+/// Resolves once the simulator is asked to stop: SIGTERM or SIGINT on Unix,
+/// Ctrl+C elsewhere
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => return,
+                    _ = ctrl_c() => return,
+                }
+            }
+            Err(e) => eprintln!("Cannot listen for SIGTERM: {}", e),
+        }
+    }
+    ctrl_c().await
+}
+
+async fn ctrl_c() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        eprintln!("Cannot listen for Ctrl+C: {}", e);
+        // Without a signal to wait for, only a service stop or a kill ends the simulator
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(windows)]
+pub use windows::{SERVICE_NAME, run_as_service};
+
+#[cfg(not(windows))]
+pub fn run_as_service(_body: fn(tokio::sync::oneshot::Receiver<()>)) -> Result<(), ServiceError> {
+    Err(ServiceError::Unsupported("--service"))
+}
+
+/// Windows service wrapper
+///
+/// Register it once with
+/// `sc create OpenProtocolSimulator binPath= "<exe> --service --config <toml>"`;
+/// the service control manager then starts the binary with those arguments.
+#[cfg(windows)]
+mod windows {
+    use super::ServiceError;
+    use std::ffi::OsString;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+    use windows_service::service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    };
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+    use windows_service::{define_windows_service, service_dispatcher};
+
+    pub const SERVICE_NAME: &str = "OpenProtocolSimulator";
+
+    /// Runs the simulator until the receiver resolves
+    static SERVICE_BODY: OnceLock<fn(oneshot::Receiver<()>)> = OnceLock::new();
+
+    /// Hand the process to the service control manager, which calls `body`
+    /// on a service thread; returns once the service has stopped
+    pub fn run_as_service(body: fn(oneshot::Receiver<()>)) -> Result<(), ServiceError> {
+        let _ = SERVICE_BODY.set(body);
+        service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+            .map_err(|e| ServiceError::Service(e.to_string()))
+    }
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let Some(body) = SERVICE_BODY.get() else {
+            return;
+        };
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let stop_tx = Mutex::new(Some(stop_tx));
+        let handler = move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                if let Some(stop_tx) = stop_tx.lock().unwrap().take() {
+                    let _ = stop_tx.send(());
+                }
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        };
+        let status = match service_control_handler::register(SERVICE_NAME, handler) {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("Failed to register the service control handler: {}", e);
+                return;
+            }
+        };
+        let report = |current_state, controls_accepted| {
+            let _ = status.set_service_status(ServiceStatus {
+                service_type: ServiceType::OWN_PROCESS,
+                current_state,
+                controls_accepted,
+                exit_code: ServiceExitCode::Win32(0),
+                checkpoint: 0,
+                wait_hint: Duration::default(),
+                process_id: None,
+            });
+        };
+
+        report(
+            ServiceState::Running,
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        );
+        body(stop_rx);
+        report(ServiceState::Stopped, ServiceControlAccept::empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file_written_and_removed() {
+        let path = std::env::temp_dir().join(format!("simulator-{}.pid", std::process::id()));
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap().trim(),
+            std::process::id().to_string()
+        );
+        // Our own pid is not "another simulator"
+        drop(PidFile::create(&path).unwrap());
+        assert!(!path.exists());
+        drop(pid_file);
+
+        std::fs::write(&path, "not a pid").unwrap();
+        drop(PidFile::create(&path).unwrap());
+        assert!(!path.exists(), "stale pid file replaced and removed");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pid_file_of_live_process_refused() {
+        let path = std::env::temp_dir().join(format!("simulator-live-{}.pid", std::process::id()));
+        // pid 1 (init) is always alive
+        std::fs::write(&path, "1\n").unwrap();
+        let result = PidFile::create(&path);
+        assert!(matches!(
+            result,
+            Err(ServiceError::AlreadyRunning { pid: 1, .. })
+        ));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
-use crate::subscriptions::Subscriptions;
+use crate::protocol::{Message, Mid};
+use crate::subscriptions::{SubscriptionInfo, SubscriptionRequest, Subscriptions};
 use std::net::SocketAddr;
 use std::time::Instant;
 
@@ -137,7 +138,6 @@ impl ConnectionSession<Ready> {
     }
 
     /// Get mutable reference to subscriptions
-    #[allow(dead_code)]
     pub fn subscriptions_mut(&mut self) -> &mut Subscriptions {
         &mut self.state.subscriptions
     }
@@ -147,54 +147,29 @@ impl ConnectionSession<Ready> {
         &self.state.subscriptions
     }
 
-    /// Subscribe to tightening result events (MID 60)
-    pub fn subscribe_tightening_result(&mut self) {
-        self.state.subscriptions.subscribe_tightening_result();
-    }
-
-    /// Unsubscribe from tightening result events (MID 63)
-    pub fn unsubscribe_tightening_result(&mut self) {
-        self.state.subscriptions.unsubscribe_tightening_result();
-    }
-
-    /// Subscribe to parameter set selection events (MID 14)
-    pub fn subscribe_pset_selection(&mut self) {
-        self.state.subscriptions.subscribe_pset_selection();
-    }
-
-    /// Unsubscribe from parameter set selection events (MID 16)
-    pub fn unsubscribe_pset_selection(&mut self) {
-        self.state.subscriptions.unsubscribe_pset_selection();
-    }
-
-    /// Subscribe to vehicle ID events (MID 51)
-    pub fn subscribe_vehicle_id(&mut self) {
-        self.state.subscriptions.subscribe_vehicle_id();
-    }
-
-    /// Unsubscribe from vehicle ID events (MID 54)
-    pub fn unsubscribe_vehicle_id(&mut self) {
-        self.state.subscriptions.unsubscribe_vehicle_id();
-    }
-
-    /// Subscribe to multi-spindle status events (MID 90)
-    pub fn subscribe_multi_spindle_status(&mut self) {
-        self.state.subscriptions.subscribe_multi_spindle_status();
-    }
-
-    /// Unsubscribe from multi-spindle status events (MID 92)
-    pub fn unsubscribe_multi_spindle_status(&mut self) {
-        self.state.subscriptions.unsubscribe_multi_spindle_status();
-    }
-
-    /// Subscribe to multi-spindle result events (MID 100)
-    pub fn subscribe_multi_spindle_result(&mut self) {
-        self.state.subscriptions.subscribe_multi_spindle_result();
-    }
-
-    /// Unsubscribe from multi-spindle result events (MID 102)
-    pub fn unsubscribe_multi_spindle_result(&mut self) {
-        self.state.subscriptions.unsubscribe_multi_spindle_result();
+    /// Update subscriptions if `message` is a subscribe or unsubscribe MID
+    ///
+    /// The mapping comes from the MID table, so every subscription family
+    /// listed there is tracked without per-MID code. The subscribe revision
+    /// and data are kept as subscription metadata.
+    pub fn track_subscription(&mut self, message: &Message) -> Option<SubscriptionRequest> {
+        let request = Mid(message.mid).subscription_request()?;
+        match request {
+            SubscriptionRequest::Subscribe(kind) => {
+                let filters = String::from_utf8_lossy(&message.data);
+                self.state.subscriptions.subscribe(
+                    kind,
+                    SubscriptionInfo {
+                        no_ack: message.header.no_ack,
+                        ..SubscriptionInfo::new(message.revision, filters.trim_end())
+                    },
+                );
+            }
+            SubscriptionRequest::Unsubscribe(kind) => {
+                self.state.subscriptions.unsubscribe(kind);
+            }
+        }
+        Some(request)
     }
 
     /// Disconnect and return to initial state
@@ -207,6 +182,7 @@ impl ConnectionSession<Ready> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::subscriptions::SubscriptionKind;
     use std::net::{IpAddr, Ipv4Addr};
     use std::thread;
     use std::time::Duration;
@@ -215,6 +191,10 @@ mod tests {
         SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080)
     }
 
+    fn message(mid: Mid) -> Message {
+        Message::new(mid.value(), 1, Vec::new())
+    }
+
     #[test]
     fn test_state_transition_disconnected_to_connected() {
         let session = ConnectionSession::new();
@@ -230,7 +210,11 @@ mod tests {
         let session = session.connect(test_addr());
         let session = session.authenticate();
 
-        assert!(!session.subscriptions().is_subscribed_to_tightening_result());
+        assert!(
+            !session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::TighteningResult)
+        );
         assert_eq!(session.subscriptions().active_count(), 0);
     }
 
@@ -269,25 +253,84 @@ mod tests {
         let mut session = session.authenticate();
 
         // Initially no subscriptions
-        assert!(!session.subscriptions().is_subscribed_to_tightening_result());
-        assert!(!session.subscriptions().is_subscribed_to_pset_selection());
+        assert!(
+            !session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::TighteningResult)
+        );
+        assert!(
+            !session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::PsetSelection)
+        );
 
         // Subscribe to tightening results
-        session.subscribe_tightening_result();
-        assert!(session.subscriptions().is_subscribed_to_tightening_result());
+        session.track_subscription(&message(Mid::TIGHTENING_RESULT_SUBSCRIBE));
+        assert!(
+            session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::TighteningResult)
+        );
         assert_eq!(session.subscriptions().active_count(), 1);
 
         // Subscribe to pset selection
-        session.subscribe_pset_selection();
-        assert!(session.subscriptions().is_subscribed_to_pset_selection());
+        session.track_subscription(&message(Mid::PSET_SELECTED_SUBSCRIBE));
+        assert!(
+            session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::PsetSelection)
+        );
         assert_eq!(session.subscriptions().active_count(), 2);
 
         // Unsubscribe from tightening results
-        session.unsubscribe_tightening_result();
-        assert!(!session.subscriptions().is_subscribed_to_tightening_result());
+        session.track_subscription(&message(Mid::TIGHTENING_RESULT_UNSUBSCRIBE));
+        assert!(
+            !session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::TighteningResult)
+        );
         assert_eq!(session.subscriptions().active_count(), 1);
     }
 
+    #[test]
+    fn test_track_subscription_from_mid() {
+        let mut session = ConnectionSession::new().connect(test_addr()).authenticate();
+
+        assert!(
+            session
+                .track_subscription(&message(Mid::KEEP_ALIVE))
+                .is_none()
+        );
+        session.track_subscription(&message(Mid::MULTI_SPINDLE_STATUS_SUBSCRIBE));
+        assert!(
+            session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::MultiSpindleStatus)
+        );
+
+        session.track_subscription(&message(Mid::MULTI_SPINDLE_STATUS_UNSUBSCRIBE));
+        assert_eq!(session.subscriptions().active_count(), 0);
+
+        let subscribe = Message::new(Mid::ALARM_SUBSCRIBE.value(), 2, b"0001".to_vec());
+        session.track_subscription(&subscribe);
+        let alarm = session
+            .subscriptions()
+            .get(SubscriptionKind::Alarm)
+            .unwrap();
+        assert_eq!(alarm.revision, 2);
+        assert_eq!(alarm.filters, "0001");
+        assert!(!alarm.no_ack);
+
+        let mut subscribe = message(Mid::TIGHTENING_RESULT_SUBSCRIBE);
+        subscribe.header.no_ack = true;
+        session.track_subscription(&subscribe);
+        let results = session
+            .subscriptions()
+            .get(SubscriptionKind::TighteningResult)
+            .unwrap();
+        assert!(results.no_ack);
+    }
+
     #[test]
     fn test_timeout_detection() {
         let session = ConnectionSession::new();
@@ -342,11 +385,19 @@ mod tests {
         // Phase 4: Manage subscriptions in Ready state
         assert_eq!(session.subscriptions().active_count(), 0);
 
-        session.subscribe_tightening_result();
-        assert!(session.subscriptions().is_subscribed_to_tightening_result());
+        session.track_subscription(&message(Mid::TIGHTENING_RESULT_SUBSCRIBE));
+        assert!(
+            session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::TighteningResult)
+        );
 
-        session.subscribe_pset_selection();
-        assert!(session.subscriptions().is_subscribed_to_pset_selection());
+        session.track_subscription(&message(Mid::PSET_SELECTED_SUBSCRIBE));
+        assert!(
+            session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::PsetSelection)
+        );
         assert_eq!(session.subscriptions().active_count(), 2);
 
         // Phase 5: Keep-alive management
@@ -354,8 +405,12 @@ mod tests {
         assert!(!session.is_timed_out(15));
 
         // Phase 6: Unsubscribe
-        session.unsubscribe_tightening_result();
-        assert!(!session.subscriptions().is_subscribed_to_tightening_result());
+        session.track_subscription(&message(Mid::TIGHTENING_RESULT_UNSUBSCRIBE));
+        assert!(
+            !session
+                .subscriptions()
+                .is_subscribed(SubscriptionKind::TighteningResult)
+        );
         assert_eq!(session.subscriptions().active_count(), 1);
 
         // Phase 7: Disconnect
@@ -385,18 +440,18 @@ mod tests {
         let session2 = ConnectionSession::new().connect(test_addr()).authenticate();
 
         // Subscribe session1 but not session2
-        session1.subscribe_tightening_result();
+        session1.track_subscription(&message(Mid::TIGHTENING_RESULT_SUBSCRIBE));
 
         // Verify isolation
         assert!(
             session1
                 .subscriptions()
-                .is_subscribed_to_tightening_result()
+                .is_subscribed(SubscriptionKind::TighteningResult)
         );
         assert!(
             !session2
                 .subscriptions()
-                .is_subscribed_to_tightening_result()
+                .is_subscribed(SubscriptionKind::TighteningResult)
         );
     }
 }
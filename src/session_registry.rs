@@ -0,0 +1,580 @@
+//! Registry of live TCP sessions
+//!
+//! Each connection task owns its `ConnectionSession`; the registry holds a
+//! copy of what other parts of the simulator need to see (peer address and
+//! subscriptions), updated by the connection whenever its subscriptions
+//! change. The HTTP layer reads it for `GET /connections` and subscriber
+//! counts.
+//!
+//! It also collects delivery receipts: the HTTP layer opens a delivery for
+//! the clients subscribed at that moment, tags the broadcast event with its
+//! [`DeliveryId`], and each connection reports what happened to the event.
+//!
+//! Connections that can be closed by the controller (`POST
+//! /connections/{id}/close`, random disconnects) take a [`CloseMode`]
+//! receiver from [`SessionRegistry::close_requests`].
+
+use crate::ack_policy::AckStats;
+use crate::codec::frame_tap::{FrameTap, TappedFrame};
+use crate::identification::SessionIdentification;
+use crate::keep_alive::KeepAliveStats;
+use crate::subscriptions::{SubscriptionKind, Subscriptions};
+use crate::write_batch::WriteStats;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+/// Identifier assigned to a connection when it registers
+pub type SessionId = u64;
+
+/// What the registry knows about one connection
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSnapshot {
+    pub id: SessionId,
+    pub addr: SocketAddr,
+    /// When the client connected (Open Protocol timestamp format)
+    pub connected_at: String,
+    pub subscriptions: Subscriptions,
+    /// Allocated codec read buffer
+    pub read_buffer_bytes: usize,
+    /// Allocated codec write buffer
+    pub write_buffer_bytes: usize,
+    /// MID 9999 traffic in both directions
+    pub keep_alive: KeepAliveStats,
+    /// Read-only connection: state-changing MIDs are refused
+    pub observer: bool,
+    /// Broadcasts held back by throttling
+    pub throttled: usize,
+    /// Pushes waiting for an acknowledgment and their retransmissions
+    pub acks: AckStats,
+    /// Batched broadcast writes (`[server] flush_interval_ms`)
+    pub writes: WriteStats,
+    /// Own PSET and VIN in `per_connection` identification scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identification: Option<SessionIdentification>,
+}
+
+/// How the controller ends a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseMode {
+    /// Send pending broadcasts, then close with FIN
+    Graceful,
+    /// Reset the connection (RST) without sending anything more
+    Abort,
+    /// Stop sending (FIN) but keep reading until the client closes
+    HalfClose,
+}
+
+impl CloseMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CloseMode::Graceful => "graceful",
+            CloseMode::Abort => "abort",
+            CloseMode::HalfClose => "half_close",
+        }
+    }
+}
+
+/// Why a connection ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisconnectReason {
+    /// The client closed the connection or reading from it failed
+    ClientClosed,
+    /// The client sent a frame larger than `max_frame_bytes`
+    FrameTooLong,
+    /// Sending to the client failed
+    SendFailed,
+    /// Closed by the controller (HTTP request, random disconnects, outage)
+    ClosedByController,
+    /// Dropped for a firmware upgrade reboot
+    ControllerRebooting,
+    /// A push stayed unacknowledged after all retries and the ack policy disconnects
+    AckTimeout,
+    /// The client sent a MID without a handler and `unknown_mid = "disconnect"`
+    UnknownMid,
+    /// The simulator's event stream ended
+    Shutdown,
+    /// The client did not complete MID 0001 within `handshake_timeout_ms`
+    HandshakeTimeout,
+}
+
+/// Identifier of a tracked broadcast
+pub type DeliveryId = u64;
+
+/// What happened to a tracked broadcast on one connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Written to the socket
+    Sent,
+    /// Dropped by failure injection
+    DroppedByInjection,
+    /// Dropped by broadcast throttling (queue overflow or coalescing)
+    DroppedByThrottle,
+    /// Dropped while waiting behind an unacknowledged push (ack policy queue overflow)
+    DroppedByAckPolicy,
+    /// Writing to the socket failed (the connection is closed)
+    SendFailed,
+    /// The client unsubscribed before the event reached it
+    NotSubscribed,
+    /// The connection fell behind the event channel and skipped events
+    ClientLagged,
+    /// No report arrived before the wait timed out
+    TimedOut,
+}
+
+/// Delivery outcome for one client
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryReceipt {
+    pub session_id: SessionId,
+    pub addr: SocketAddr,
+    pub status: DeliveryStatus,
+}
+
+/// Handle for waiting on the receipts of one broadcast
+pub struct DeliveryTicket {
+    pub id: DeliveryId,
+    done: oneshot::Receiver<()>,
+}
+
+#[derive(Debug)]
+struct PendingDelivery {
+    /// Sessions that have not reported yet, and whether they lagged meanwhile
+    outstanding: HashMap<SessionId, (SocketAddr, bool)>,
+    receipts: Vec<DeliveryReceipt>,
+    done: Option<oneshot::Sender<()>>,
+}
+
+impl PendingDelivery {
+    fn finish_if_complete(&mut self) {
+        if self.outstanding.is_empty()
+            && let Some(done) = self.done.take()
+        {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// Shared registry of connected clients
+#[derive(Debug, Default)]
+pub struct SessionRegistry {
+    sessions: RwLock<HashMap<SessionId, SessionSnapshot>>,
+    next_id: AtomicU64,
+    deliveries: Mutex<HashMap<DeliveryId, PendingDelivery>>,
+    next_delivery_id: AtomicU64,
+    closers: Mutex<HashMap<SessionId, mpsc::UnboundedSender<CloseMode>>>,
+    taps: Mutex<HashMap<SessionId, FrameTap>>,
+    /// Connection that selected the current PSET with MID 0018, and that PSET
+    pset_owner: Mutex<Option<(SessionId, u32)>>,
+}
+
+/// Type alias for the registry shared between connection tasks and HTTP
+pub type SharedSessionRegistry = Arc<SessionRegistry>;
+
+impl SessionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new connection and return its ID
+    pub fn register(&self, addr: SocketAddr) -> SessionId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let snapshot = SessionSnapshot {
+            id,
+            addr,
+            connected_at: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+            subscriptions: Subscriptions::new(),
+            read_buffer_bytes: 0,
+            write_buffer_bytes: 0,
+            keep_alive: KeepAliveStats::default(),
+            observer: false,
+            throttled: 0,
+            acks: AckStats::default(),
+            writes: WriteStats::default(),
+            identification: None,
+        };
+        self.sessions.write().unwrap().insert(id, snapshot);
+        id
+    }
+
+    /// Replace the recorded subscriptions of a connection
+    pub fn update_subscriptions(&self, id: SessionId, subscriptions: &Subscriptions) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(&id) {
+            session.subscriptions = subscriptions.clone();
+        }
+    }
+
+    /// Record the buffer sizes of a connection (for GET /debug/resources)
+    pub fn update_buffers(&self, id: SessionId, read_bytes: usize, write_bytes: usize) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(&id) {
+            session.read_buffer_bytes = read_bytes;
+            session.write_buffer_bytes = write_bytes;
+        }
+    }
+
+    /// Record the keep-alive counters of a connection
+    pub fn update_keep_alive(&self, id: SessionId, stats: &KeepAliveStats) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(&id) {
+            session.keep_alive = stats.clone();
+        }
+    }
+
+    /// Switch a connection to or from observer mode; false if it is not registered
+    pub fn set_observer(&self, id: SessionId, observer: bool) -> bool {
+        match self.sessions.write().unwrap().get_mut(&id) {
+            Some(session) => {
+                session.observer = observer;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Record how many broadcasts a connection holds back for throttling
+    pub fn update_throttled(&self, id: SessionId, queued: usize) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(&id) {
+            session.throttled = queued;
+        }
+    }
+
+    /// Record the ack tracking counters of a connection
+    pub fn update_acks(&self, id: SessionId, stats: AckStats) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(&id) {
+            session.acks = stats;
+        }
+    }
+
+    /// Record the broadcast write counters of a connection
+    pub fn update_writes(&self, id: SessionId, stats: &WriteStats) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(&id) {
+            session.writes = stats.clone();
+        }
+    }
+
+    /// Record the sandboxed PSET and VIN of a connection
+    pub fn update_identification(&self, id: SessionId, identification: &SessionIdentification) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(&id) {
+            session.identification = Some(identification.clone());
+        }
+    }
+
+    /// Record that a connection selected `pset_id` with MID 0018
+    ///
+    /// Returns the other connected client and its PSET when this selection
+    /// overrules that client's one.
+    pub fn claim_pset(&self, id: SessionId, pset_id: u32) -> Option<(SessionId, u32)> {
+        let previous = self.pset_owner.lock().unwrap().replace((id, pset_id));
+        previous.filter(|(owner, owner_pset)| {
+            *owner != id
+                && *owner_pset != pset_id
+                && self.sessions.read().unwrap().contains_key(owner)
+        })
+    }
+
+    /// Whether a connection is in observer mode
+    pub fn is_observer(&self, id: SessionId) -> bool {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|session| session.observer)
+    }
+
+    /// Remove a connection once it has closed
+    pub fn remove(&self, id: SessionId) {
+        self.sessions.write().unwrap().remove(&id);
+        self.closers.lock().unwrap().remove(&id);
+        self.taps.lock().unwrap().remove(&id);
+    }
+
+    /// Tap for the codec of a connection, published to [`tap_frames`](Self::tap_frames)
+    pub fn frame_tap(&self, id: SessionId) -> FrameTap {
+        self.taps.lock().unwrap().entry(id).or_default().clone()
+    }
+
+    /// Raw frames of a connection from now on; `None` if it has no tap
+    pub fn tap_frames(&self, id: SessionId) -> Option<broadcast::Receiver<TappedFrame>> {
+        self.taps.lock().unwrap().get(&id).map(FrameTap::subscribe)
+    }
+
+    /// Receiver of close requests for a connection
+    pub fn close_requests(&self, id: SessionId) -> mpsc::UnboundedReceiver<CloseMode> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.closers.lock().unwrap().insert(id, tx);
+        rx
+    }
+
+    /// Ask a connection to close; false if it does not take close requests
+    pub fn request_close(&self, id: SessionId, mode: CloseMode) -> bool {
+        self.closers
+            .lock()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|closer| closer.send(mode).is_ok())
+    }
+
+    /// Whether a connection with this ID is registered
+    pub fn contains(&self, id: SessionId) -> bool {
+        self.sessions.read().unwrap().contains_key(&id)
+    }
+
+    /// Start tracking a broadcast to the clients currently subscribed to `kind`
+    ///
+    /// Tag the broadcast event with the ticket's ID, then pass the ticket to
+    /// [`wait_for_delivery`](Self::wait_for_delivery).
+    pub fn begin_delivery(&self, kind: SubscriptionKind) -> DeliveryTicket {
+        let id = self.next_delivery_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let outstanding = self
+            .sessions
+            .read()
+            .unwrap()
+            .values()
+            .filter(|session| session.subscriptions.is_subscribed(kind))
+            .map(|session| (session.id, (session.addr, false)))
+            .collect();
+        let (tx, rx) = oneshot::channel();
+        let mut pending = PendingDelivery {
+            outstanding,
+            receipts: Vec::new(),
+            done: Some(tx),
+        };
+        pending.finish_if_complete();
+        self.deliveries.lock().unwrap().insert(id, pending);
+        DeliveryTicket { id, done: rx }
+    }
+
+    /// Report what a connection did with a tracked broadcast
+    ///
+    /// Reports for unknown deliveries or unexpected sessions are ignored.
+    pub fn report_delivery(&self, id: DeliveryId, session_id: SessionId, status: DeliveryStatus) {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        if let Some(pending) = deliveries.get_mut(&id)
+            && let Some((addr, _)) = pending.outstanding.remove(&session_id)
+        {
+            pending.receipts.push(DeliveryReceipt {
+                session_id,
+                addr,
+                status,
+            });
+            pending.finish_if_complete();
+        }
+    }
+
+    /// Note that a connection skipped events because it fell behind
+    pub fn report_lag(&self, session_id: SessionId) {
+        for pending in self.deliveries.lock().unwrap().values_mut() {
+            if let Some((_, lagged)) = pending.outstanding.get_mut(&session_id) {
+                *lagged = true;
+            }
+        }
+    }
+
+    /// Wait until every expected client reported, or `timeout` elapsed
+    ///
+    /// Clients that did not report are returned as `ClientLagged` if they
+    /// skipped events meanwhile, `TimedOut` otherwise. Receipts are ordered
+    /// by session ID.
+    pub async fn wait_for_delivery(
+        &self,
+        ticket: DeliveryTicket,
+        timeout: Duration,
+    ) -> Vec<DeliveryReceipt> {
+        let _ = tokio::time::timeout(timeout, ticket.done).await;
+
+        let Some(pending) = self.deliveries.lock().unwrap().remove(&ticket.id) else {
+            return Vec::new();
+        };
+        let mut receipts = pending.receipts;
+        receipts.extend(
+            pending
+                .outstanding
+                .into_iter()
+                .map(|(session_id, (addr, lagged))| DeliveryReceipt {
+                    session_id,
+                    addr,
+                    status: if lagged {
+                        DeliveryStatus::ClientLagged
+                    } else {
+                        DeliveryStatus::TimedOut
+                    },
+                }),
+        );
+        receipts.sort_by_key(|receipt| receipt.session_id);
+        receipts
+    }
+
+    /// Number of connected clients
+    pub fn len(&self) -> usize {
+        self.sessions.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All connections, oldest first
+    pub fn snapshot(&self) -> Vec<SessionSnapshot> {
+        let mut sessions: Vec<_> = self.sessions.read().unwrap().values().cloned().collect();
+        sessions.sort_by_key(|session| session.id);
+        sessions
+    }
+
+    /// Number of clients subscribed to `kind`
+    pub fn subscriber_count(&self, kind: SubscriptionKind) -> usize {
+        self.sessions
+            .read()
+            .unwrap()
+            .values()
+            .filter(|session| session.subscriptions.is_subscribed(kind))
+            .count()
+    }
+
+    /// Subscriber count per event type (types without subscribers are omitted)
+    pub fn subscriber_counts(&self) -> BTreeMap<SubscriptionKind, usize> {
+        let mut counts = BTreeMap::new();
+        for session in self.sessions.read().unwrap().values() {
+            for (kind, _) in session.subscriptions.iter() {
+                *counts.entry(kind).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscriptions::SubscriptionInfo;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_register_update_and_remove() {
+        let registry = SessionRegistry::new();
+        let first = registry.register(addr(5000));
+        let second = registry.register(addr(5001));
+        assert_eq!(registry.len(), 2);
+
+        let mut subscriptions = Subscriptions::new();
+        subscriptions.subscribe(
+            SubscriptionKind::TighteningResult,
+            SubscriptionInfo::new(1, ""),
+        );
+        registry.update_subscriptions(second, &subscriptions);
+
+        assert_eq!(
+            registry.subscriber_count(SubscriptionKind::TighteningResult),
+            1
+        );
+        assert_eq!(
+            registry
+                .subscriber_counts()
+                .get(&SubscriptionKind::TighteningResult),
+            Some(&1)
+        );
+        assert_eq!(registry.subscriber_count(SubscriptionKind::Alarm), 0);
+
+        registry.remove(second);
+        assert_eq!(
+            registry.subscriber_count(SubscriptionKind::TighteningResult),
+            0
+        );
+        assert_eq!(registry.snapshot()[0].id, first);
+    }
+
+    #[test]
+    fn test_claim_pset_reports_the_overruled_session() {
+        let registry = SessionRegistry::new();
+        let first = registry.register(addr(5000));
+        let second = registry.register(addr(5001));
+
+        assert_eq!(registry.claim_pset(first, 1), None);
+        // Reselecting its own PSET or the same PSET is no conflict
+        assert_eq!(registry.claim_pset(first, 2), None);
+        assert_eq!(registry.claim_pset(second, 2), None);
+        assert_eq!(registry.claim_pset(first, 3), Some((second, 2)));
+
+        registry.remove(first);
+        assert_eq!(registry.claim_pset(second, 4), None);
+    }
+
+    #[test]
+    fn test_frame_tap_ends_with_the_connection() {
+        use crate::codec::frame_tap::FrameDirection;
+        use tokio::sync::broadcast::error::TryRecvError;
+
+        let registry = SessionRegistry::new();
+        let id = registry.register(addr(5000));
+        assert!(
+            registry.tap_frames(id).is_none(),
+            "no tap before the codec asks"
+        );
+
+        let tap = registry.frame_tap(id);
+        let mut frames = registry.tap_frames(id).unwrap();
+        tap.record(FrameDirection::Inbound, b"00200001001         ");
+        assert_eq!(
+            frames.try_recv().unwrap().direction,
+            FrameDirection::Inbound
+        );
+
+        registry.remove(id);
+        assert!(registry.tap_frames(id).is_none());
+        drop(tap);
+        assert_eq!(frames.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_receipts() {
+        let registry = SessionRegistry::new();
+        let mut subscriptions = Subscriptions::new();
+        subscriptions.subscribe(
+            SubscriptionKind::TighteningResult,
+            SubscriptionInfo::new(1, ""),
+        );
+        let ids: Vec<_> = (0..3)
+            .map(|n| {
+                let id = registry.register(addr(6000 + n));
+                registry.update_subscriptions(id, &subscriptions);
+                id
+            })
+            .collect();
+        registry.register(addr(6100)); // not subscribed, not expected
+
+        let ticket = registry.begin_delivery(SubscriptionKind::TighteningResult);
+        registry.report_delivery(ticket.id, ids[0], DeliveryStatus::Sent);
+        registry.report_delivery(ticket.id, ids[1], DeliveryStatus::DroppedByInjection);
+        registry.report_lag(ids[2]);
+
+        let receipts = registry
+            .wait_for_delivery(ticket, Duration::from_millis(20))
+            .await;
+        let statuses: Vec<_> = receipts.iter().map(|receipt| receipt.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                DeliveryStatus::Sent,
+                DeliveryStatus::DroppedByInjection,
+                DeliveryStatus::ClientLagged
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_without_subscribers_completes_immediately() {
+        let registry = SessionRegistry::new();
+        let ticket = registry.begin_delivery(SubscriptionKind::Alarm);
+        let receipts = registry
+            .wait_for_delivery(ticket, Duration::from_secs(60))
+            .await;
+        assert!(receipts.is_empty());
+    }
+}
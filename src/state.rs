@@ -1,19 +1,185 @@
+use crate::alarm_texts::{self, AlarmTexts};
+use crate::alarms::AlarmList;
+use crate::audit::{AuditCategory, AuditLog};
 use crate::config::DeviceConfig;
+use crate::counters::Counters;
 use crate::device_fsm::DeviceFSMState;
 use crate::failure_simulator::FailureConfig;
+use crate::fieldbus::{self, FieldbusImage, FieldbusOutputs};
+use crate::interlock::InterlockWait;
+use crate::lifetime::LifetimeStats;
 use crate::multi_spindle::MultiSpindleConfig;
+use crate::pset_history::{PsetChangeKind, PsetChangeSource, PsetHistory};
+use crate::telemetry::Telemetry;
 use crate::tightening_tracker::TighteningTracker;
-use serde::Serialize;
+use crate::user_data::UserData;
+use crate::vin_history::VinHistory;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::sync::{Arc, RwLock};
 
+/// Operator currently logged in at the station
+#[derive(Debug, Clone, Serialize)]
+pub struct OperatorSession {
+    pub operator_id: String,
+    pub name: Option<String>,
+    /// Local time of login (YYYY-MM-DD:HH:MM:SS)
+    pub logged_in_at: String,
+}
+
+/// New firmware (and optionally a new identity) installed by a simulated upgrade
+#[derive(Debug, Clone, Deserialize)]
+pub struct FirmwareUpgrade {
+    pub software_version: String,
+    #[serde(default)]
+    pub tool_software_version: Option<String>,
+    #[serde(default)]
+    pub controller_name: Option<String>,
+    #[serde(default)]
+    pub supplier_code: Option<String>,
+}
+
+/// Planned interruption of the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceKind {
+    /// Reboot into new firmware (`POST /simulate/firmware-upgrade`)
+    FirmwareUpgrade,
+    /// Scheduled outage (`[[schedules]]` with `action = "outage"`)
+    Outage,
+}
+
+/// Announcement of a planned interruption, sent ahead of it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceNotice {
+    pub kind: MaintenanceKind,
+    pub reason: String,
+    /// Milliseconds from the announcement until the controller goes down
+    pub starts_in_ms: u64,
+    /// Expected downtime in milliseconds
+    pub duration_ms: u64,
+    /// Whether new Open Protocol sessions are accepted until then
+    pub accepting_sessions: bool,
+}
+
+/// Controller identity reported in MID 0002 and MID 0061
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControllerIdentity {
+    pub cell_id: u32,
+    pub channel_id: u32,
+    pub controller_name: String,
+    pub supplier_code: String,
+}
+
+/// Writable fields of `PATCH /identity`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IdentityPatch {
+    pub cell_id: Option<u32>,
+    pub channel_id: Option<u32>,
+    pub controller_name: Option<String>,
+    pub supplier_code: Option<String>,
+}
+
+impl From<IdentityPatch> for StatePatch {
+    fn from(patch: IdentityPatch) -> Self {
+        StatePatch {
+            cell_id: patch.cell_id,
+            channel_id: patch.channel_id,
+            controller_name: patch.controller_name,
+            supplier_code: patch.supplier_code,
+            ..StatePatch::default()
+        }
+    }
+}
+
+/// Writable fields of `PATCH /state`, applied as a JSON merge patch
+///
+/// Absent fields are left alone; `null` clears the optional ones.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatePatch {
+    pub cell_id: Option<u32>,
+    pub channel_id: Option<u32>,
+    pub controller_name: Option<String>,
+    pub supplier_code: Option<String>,
+    pub tool_enabled: Option<bool>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub vehicle_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub current_job_id: Option<Option<u32>>,
+    pub alarm_language: Option<String>,
+}
+
+/// Tell a present `null` (`Some(None)`) from an absent field (`None`)
+pub(crate) fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+impl StatePatch {
+    /// Check every value fits the Open Protocol field it is sent in
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cell_id.is_some_and(|id| id > 9999) {
+            return Err("cell_id must be 0-9999".to_string());
+        }
+        if self.channel_id.is_some_and(|id| id > 99) {
+            return Err("channel_id must be 0-99".to_string());
+        }
+        if let Some(name) = &self.controller_name
+            && (name.is_empty() || name.chars().count() > 25)
+        {
+            return Err("controller_name must be 1-25 characters".to_string());
+        }
+        if let Some(code) = &self.supplier_code
+            && code.chars().count() != 3
+        {
+            return Err("supplier_code must be 3 characters".to_string());
+        }
+        if let Some(Some(vin)) = &self.vehicle_id
+            && (vin.is_empty() || vin.chars().count() > 25)
+        {
+            return Err("vehicle_id must be 1-25 characters".to_string());
+        }
+        if self
+            .current_job_id
+            .is_some_and(|id| id.is_some_and(|id| id > 9999))
+        {
+            return Err("current_job_id must be 0-9999".to_string());
+        }
+        if let Some(language) = &self.alarm_language
+            && !alarm_texts::is_valid_language(language)
+        {
+            return Err("alarm_language must be a two-letter lowercase language code".to_string());
+        }
+        Ok(())
+    }
+}
+
 /// Represents the internal state of the simulated device
 #[derive(Debug, Clone, Serialize)]
 pub struct DeviceState {
+    // Change counter, bumped on every mutation (see `touch`)
+    pub version: u64,
+
     // Controller identification
     pub cell_id: u32,
     pub channel_id: u32,
     pub controller_name: String,
     pub supplier_code: String,
+    pub software_version: String,
+    pub tool_software_version: String,
+
+    // Set while a simulated firmware upgrade reboots the controller
+    pub rebooting: bool,
+
+    // Set while a scheduled outage keeps the controller off the network
+    pub outage: bool,
+
+    // Planned interruption announced but not started yet
+    pub maintenance: Option<MaintenanceNotice>,
 
     // Parameter set (pset) state
     pub current_pset_id: Option<u32>,
@@ -27,54 +193,175 @@ pub struct DeviceState {
 
     // Tool state
     pub tool_enabled: bool,
+    // Tool service interval reached (service alarm raised)
+    pub service_due: bool,
+    // Tool locked until the service counter is reset (POST /tool/service-reset)
+    pub service_locked: bool,
+    // Job position waiting for its interlock release
+    pub interlock: Option<InterlockWait>,
+
+    // Logged-in operator (None = logged out)
+    pub operator: Option<OperatorSession>,
 
     // Vehicle/Job identification
     pub vehicle_id: Option<String>,
     pub current_job_id: Option<u32>,
+    // Sequence number of the current job instance (0 before the first VIN or job)
+    pub job_sequence_number: u32,
+    // Correlation ID of the open cycle: the VIN broadcast announces it, the
+    // next tightening (or multi-spindle operation) claims it
+    pub cycle_id: u64,
 
     // Multi-spindle configuration
     pub multi_spindle_config: MultiSpindleConfig,
 
     // Communication failure injection configuration
     pub failure_config: FailureConfig,
+
+    // Simulated motor temperature, current draw and supply voltage
+    pub telemetry: Telemetry,
+
+    // Language of alarm texts sent to clients (MID 0071 revision 2)
+    pub alarm_language: String,
+
+    // Localized alarm texts (served separately via GET /alarms/texts)
+    #[serde(skip)]
+    pub alarm_texts: Arc<AlarmTexts>,
+
+    // Lifetime tool counters (served separately via GET /stats/lifetime)
+    #[serde(skip)]
+    pub lifetime_stats: LifetimeStats,
+
+    // Controller event log (served separately via GET /audit)
+    #[serde(skip)]
+    pub audit_log: AuditLog,
+
+    // Controller alarms (served separately via GET /alarms)
+    #[serde(skip)]
+    pub alarms: AlarmList,
+
+    // Job instances by sequence number (served separately via GET /vehicle-ids/history)
+    #[serde(skip)]
+    pub vin_history: VinHistory,
+
+    // PSET changes (served separately via GET /psets/{id}/history)
+    #[serde(skip)]
+    pub pset_history: PsetHistory,
+
+    // PLC I/O image (served separately via GET /io/fieldbus)
+    #[serde(skip)]
+    pub fieldbus: FieldbusImage,
+
+    // User data slots written with MID 0240/0245 (served separately via GET /user-data)
+    #[serde(skip)]
+    pub user_data: UserData,
 }
 
 impl DeviceState {
     /// Create a new device state with default values
     pub fn new() -> Self {
         Self {
+            version: 0,
             cell_id: 1,
             channel_id: 1,
             controller_name: "OpenProtocolSimulator".to_string(),
             supplier_code: "SIM".to_string(),
+            software_version: format!("SIM {}", env!("CARGO_PKG_VERSION")),
+            tool_software_version: format!("SIM {}", env!("CARGO_PKG_VERSION")),
+            rebooting: false,
+            outage: false,
+            maintenance: None,
             current_pset_id: Some(1),
             current_pset_name: Some("Default".to_string()),
             tightening_tracker: TighteningTracker::new(),
             device_fsm_state: DeviceFSMState::idle(),
             tool_enabled: true,
+            service_due: false,
+            service_locked: false,
+            interlock: None,
+            operator: None,
             vehicle_id: None,
             current_job_id: Some(1),
+            job_sequence_number: 0,
+            cycle_id: 1,
             multi_spindle_config: MultiSpindleConfig::default(),
             failure_config: FailureConfig::default(),
+            telemetry: Telemetry::default(),
+            alarm_language: alarm_texts::DEFAULT_LANGUAGE.to_string(),
+            alarm_texts: Arc::default(),
+            lifetime_stats: LifetimeStats::default(),
+            audit_log: AuditLog::default(),
+            alarms: AlarmList::default(),
+            vin_history: VinHistory::default(),
+            pset_history: PsetHistory::default(),
+            fieldbus: FieldbusImage::default(),
+            user_data: UserData::default(),
         }
     }
 
     /// Create a new device state from configuration
     pub fn new_from_config(config: &DeviceConfig) -> Self {
         Self {
+            version: 0,
             cell_id: config.cell_id,
             channel_id: config.channel_id,
             controller_name: config.controller_name.clone(),
             supplier_code: config.supplier_code.clone(),
+            software_version: config.software_version.clone(),
+            tool_software_version: config.tool_software_version.clone(),
+            rebooting: false,
+            outage: false,
+            maintenance: None,
             current_pset_id: Some(1),
             current_pset_name: Some("Default".to_string()),
             tightening_tracker: TighteningTracker::new(),
             device_fsm_state: DeviceFSMState::idle(),
             tool_enabled: true,
+            service_due: false,
+            service_locked: false,
+            interlock: None,
+            operator: None,
             vehicle_id: None,
             current_job_id: Some(1),
+            job_sequence_number: 0,
+            cycle_id: 1,
             multi_spindle_config: MultiSpindleConfig::default(),
             failure_config: FailureConfig::default(),
+            telemetry: Telemetry::default(),
+            alarm_language: alarm_texts::DEFAULT_LANGUAGE.to_string(),
+            alarm_texts: Arc::default(),
+            lifetime_stats: LifetimeStats::default(),
+            audit_log: AuditLog::default(),
+            alarms: AlarmList::default(),
+            vin_history: VinHistory::default(),
+            pset_history: PsetHistory::default(),
+            fieldbus: FieldbusImage::default(),
+            user_data: UserData::default(),
+        }
+    }
+
+    /// Record a mutation so pollers see a new version
+    ///
+    /// `ObservableState::write` does this; code locking the raw state for
+    /// writing has to call it itself.
+    pub fn touch(&mut self) {
+        self.version = self.version.wrapping_add(1);
+    }
+
+    /// Take the open cycle ID for a tightening and open the next cycle
+    pub fn claim_cycle(&mut self) -> u64 {
+        let cycle_id = self.cycle_id;
+        self.cycle_id = self.cycle_id.wrapping_add(1).max(1);
+        cycle_id
+    }
+
+    /// Identity currently reported to clients
+    pub fn identity(&self) -> ControllerIdentity {
+        ControllerIdentity {
+            cell_id: self.cell_id,
+            channel_id: self.channel_id,
+            controller_name: self.controller_name.clone(),
+            supplier_code: self.supplier_code.clone(),
         }
     }
 
@@ -92,11 +379,65 @@ impl DeviceState {
     pub fn set_pset(&mut self, pset_id: u32, pset_name: Option<String>) {
         self.current_pset_id = Some(pset_id);
         self.current_pset_name = pset_name;
+        self.tightening_tracker.select_pset(pset_id);
+        self.audit_log
+            .record(AuditCategory::Pset, format!("Pset {} selected", pset_id));
     }
 
-    /// Set batch size (enables batch mode)
+    /// Record a PSET change in the history
+    pub fn record_pset_change(
+        &mut self,
+        pset_id: u32,
+        kind: PsetChangeKind,
+        source: PsetChangeSource,
+    ) {
+        self.pset_history.record(pset_id, kind, source);
+    }
+
+    /// Date of the last settings change of a PSET (MID 0015, MID 0061)
+    pub fn last_pset_change(&self, pset_id: u32) -> String {
+        self.pset_history.last_change(pset_id).to_string()
+    }
+
+    /// Fieldbus outputs matching the current state
+    pub fn fieldbus_outputs(&self) -> FieldbusOutputs {
+        let result = self.fieldbus.result();
+        FieldbusOutputs {
+            ready: self.tool_enabled && !self.rebooting && !self.outage,
+            tool_enabled: self.tool_enabled,
+            tightening_ok: result == Some(true),
+            tightening_nok: result == Some(false),
+            batch_complete: self.tightening_tracker.is_complete(),
+            interlock_waiting: self.interlock.is_some(),
+            alarm: self.alarms.open_count() > 0,
+            pset: fieldbus::pset_bits(self.current_pset_id),
+        }
+    }
+
+    /// Make a job the active one and start a new job instance (MID 0038)
+    pub fn select_job(&mut self, job_id: u32) {
+        self.current_job_id = Some(job_id);
+        self.start_job_instance();
+        self.audit_log.record(
+            AuditCategory::Identification,
+            format!("Job {} selected", job_id),
+        );
+    }
+
+    /// Set batch size (enables batch mode for the active pset)
     pub fn set_batch_size(&mut self, size: u32) {
         self.tightening_tracker.enable_batch(size);
+        self.audit_log
+            .record(AuditCategory::Batch, format!("Batch size set to {}", size));
+    }
+
+    /// Set batch size for a specific pset (MID 0019)
+    pub fn set_pset_batch_size(&mut self, pset_id: u32, size: u32) {
+        self.tightening_tracker.enable_batch_for_pset(pset_id, size);
+        self.audit_log.record(
+            AuditCategory::Batch,
+            format!("Batch size for pset {} set to {}", pset_id, size),
+        );
     }
 
     /// Increment batch counter without tightening (MID 0128 - skip bolt)
@@ -107,26 +448,216 @@ impl DeviceState {
     /// Reset batch counter (MID 0020)
     /// Returns true if in batch mode, false otherwise
     pub fn reset_batch(&mut self) -> bool {
-        self.tightening_tracker.reset_batch()
+        let reset = self.tightening_tracker.reset_batch();
+        if reset {
+            self.audit_log.record(AuditCategory::Batch, "Batch reset");
+        }
+        reset
+    }
+
+    /// Account for a tool rundown in the telemetry and lifetime counters
+    pub fn record_tool_cycle(&mut self, ok: bool, torque: f64, angle: f64, duration_ms: u64) {
+        self.telemetry.record_tightening(torque, duration_ms);
+        self.lifetime_stats
+            .record_tightening(ok, torque, angle, duration_ms);
+    }
+
+    /// Install upgraded firmware; the tool keeps its version unless one is given
+    pub fn apply_firmware_upgrade(&mut self, upgrade: FirmwareUpgrade) {
+        self.audit_log.record(
+            AuditCategory::Configuration,
+            format!(
+                "Firmware upgraded from {} to {}",
+                self.software_version, upgrade.software_version
+            ),
+        );
+        self.software_version = upgrade.software_version;
+        if let Some(version) = upgrade.tool_software_version {
+            self.tool_software_version = version;
+        }
+        if let Some(name) = upgrade.controller_name {
+            self.controller_name = name;
+        }
+        if let Some(code) = upgrade.supplier_code {
+            self.supplier_code = code;
+        }
+    }
+
+    /// Apply a validated patch; returns the fields that changed with their new values
+    pub fn apply_patch(&mut self, patch: StatePatch) -> Vec<(&'static str, serde_json::Value)> {
+        let mut changed = Vec::new();
+        if let Some(cell_id) = patch.cell_id.filter(|id| *id != self.cell_id) {
+            self.audit_log.record(
+                AuditCategory::Configuration,
+                format!("Cell ID changed from {} to {}", self.cell_id, cell_id),
+            );
+            self.cell_id = cell_id;
+            changed.push(("cell_id", cell_id.into()));
+        }
+        if let Some(channel_id) = patch.channel_id.filter(|id| *id != self.channel_id) {
+            self.audit_log.record(
+                AuditCategory::Configuration,
+                format!(
+                    "Channel ID changed from {} to {}",
+                    self.channel_id, channel_id
+                ),
+            );
+            self.channel_id = channel_id;
+            changed.push(("channel_id", channel_id.into()));
+        }
+        if let Some(name) = patch
+            .controller_name
+            .filter(|name| *name != self.controller_name)
+        {
+            self.audit_log.record(
+                AuditCategory::Configuration,
+                format!("Controller name changed to {}", name),
+            );
+            changed.push(("controller_name", name.clone().into()));
+            self.controller_name = name;
+        }
+        if let Some(code) = patch
+            .supplier_code
+            .filter(|code| *code != self.supplier_code)
+        {
+            self.audit_log.record(
+                AuditCategory::Configuration,
+                format!("Supplier code changed to {}", code),
+            );
+            changed.push(("supplier_code", code.clone().into()));
+            self.supplier_code = code;
+        }
+        if let Some(enabled) = patch.tool_enabled.filter(|e| *e != self.tool_enabled) {
+            if enabled {
+                self.enable_tool();
+            } else {
+                self.disable_tool();
+            }
+            // A tool locked for service or by an interlock stays disabled
+            if self.tool_enabled == enabled {
+                changed.push(("tool_enabled", enabled.into()));
+            }
+        }
+        if let Some(vin) = patch.vehicle_id.filter(|vin| *vin != self.vehicle_id) {
+            match &vin {
+                Some(vin) => self.set_vehicle_id(vin.clone()),
+                None => {
+                    self.audit_log
+                        .record(AuditCategory::Identification, "VIN cleared");
+                    self.clear_vehicle_id();
+                }
+            }
+            changed.push(("vehicle_id", vin.into()));
+        }
+        if let Some(job_id) = patch.current_job_id.filter(|id| *id != self.current_job_id) {
+            let description = match job_id {
+                Some(id) => format!("Job {} selected", id),
+                None => "Job cleared".to_string(),
+            };
+            self.audit_log
+                .record(AuditCategory::Identification, description);
+            self.current_job_id = job_id;
+            self.start_job_instance();
+            changed.push(("current_job_id", job_id.into()));
+        }
+        if let Some(language) = patch
+            .alarm_language
+            .filter(|language| *language != self.alarm_language)
+        {
+            self.audit_log.record(
+                AuditCategory::Configuration,
+                format!("Alarm text language changed to {}", language),
+            );
+            changed.push(("alarm_language", language.clone().into()));
+            self.alarm_language = language;
+        }
+        changed
     }
 
-    /// Enable the tool
-    pub fn enable_tool(&mut self) {
+    /// Enable the tool; returns false while it is locked for service or
+    /// waiting for an interlock release
+    pub fn enable_tool(&mut self) -> bool {
+        if self.service_locked || self.interlock.is_some() {
+            return false;
+        }
+        if !self.tool_enabled {
+            self.audit_log.record(AuditCategory::Tool, "Tool enabled");
+        }
         self.tool_enabled = true;
+        true
     }
 
     /// Disable the tool
     pub fn disable_tool(&mut self) {
+        if self.tool_enabled {
+            self.audit_log.record(AuditCategory::Tool, "Tool disabled");
+        }
         self.tool_enabled = false;
     }
 
+    /// Log an operator in, replacing any operator already logged in
+    pub fn login_operator(&mut self, operator_id: String, name: Option<String>) {
+        self.audit_log.record(
+            AuditCategory::Operator,
+            format!("Operator {} logged in", operator_id),
+        );
+        self.operator = Some(OperatorSession {
+            operator_id,
+            name,
+            logged_in_at: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+        });
+    }
+
+    /// Log the current operator out; returns the session that ended
+    pub fn logout_operator(&mut self) -> Option<OperatorSession> {
+        let session = self.operator.take();
+        if let Some(session) = &session {
+            self.audit_log.record(
+                AuditCategory::Operator,
+                format!("Operator {} logged out", session.operator_id),
+            );
+        }
+        session
+    }
+
+    /// Alarm text of `error_code` in the controller language
+    pub fn alarm_text(&self, error_code: &str, description: &str) -> String {
+        self.alarm_texts
+            .localize(error_code, &self.alarm_language, description)
+    }
+
+    /// Last issued tightening ID and job sequence number
+    pub fn counters(&self) -> Counters {
+        Counters {
+            tightening_id: self.tightening_tracker.tightening_sequence(),
+            job_sequence_number: self.vin_history.current(),
+        }
+    }
+
+    /// Continue the tightening ID and job sequence after `counters`
+    pub fn restore_counters(&mut self, counters: Counters) {
+        self.tightening_tracker
+            .set_tightening_sequence(counters.tightening_id);
+        self.vin_history.set_current(counters.job_sequence_number);
+        self.job_sequence_number = self.vin_history.current();
+    }
+
     /// Set vehicle ID
     pub fn set_vehicle_id(&mut self, vin: String) {
+        self.audit_log
+            .record(AuditCategory::Identification, format!("VIN set to {}", vin));
         self.vehicle_id = Some(vin);
+        self.start_job_instance();
+    }
+
+    /// Number a new job instance of the current VIN and job
+    fn start_job_instance(&mut self) {
+        self.job_sequence_number = self
+            .vin_history
+            .start(self.vehicle_id.clone(), self.current_job_id);
     }
 
     /// Clear vehicle ID
-    #[allow(dead_code)]
     pub fn clear_vehicle_id(&mut self) {
         self.vehicle_id = None;
     }
@@ -141,12 +672,18 @@ impl DeviceState {
             ));
         }
         self.multi_spindle_config = config;
+        self.audit_log.record(
+            AuditCategory::Configuration,
+            format!("Multi-spindle enabled ({} spindles)", spindle_count),
+        );
         Ok(())
     }
 
     /// Disable multi-spindle mode (revert to single-spindle)
     pub fn disable_multi_spindle(&mut self) {
         self.multi_spindle_config = MultiSpindleConfig::disable();
+        self.audit_log
+            .record(AuditCategory::Configuration, "Multi-spindle disabled");
     }
 
     /// Check if multi-spindle mode is enabled
@@ -201,6 +738,47 @@ mod tests {
         assert_eq!(info.counter, 1);
     }
 
+    #[test]
+    fn test_claim_cycle_opens_the_next_cycle() {
+        let mut state = DeviceState::new();
+        assert_eq!(state.cycle_id, 1);
+        assert_eq!(state.claim_cycle(), 1);
+        assert_eq!(state.claim_cycle(), 2);
+        assert_eq!(state.cycle_id, 3);
+    }
+
+    #[test]
+    fn test_pset_change_switches_batch() {
+        let mut state = DeviceState::new();
+        state.set_pset_batch_size(1, 3);
+        state.set_pset_batch_size(7, 8);
+        assert_eq!(state.tightening_tracker.batch_size(), 3);
+
+        state.set_pset(7, None);
+        assert_eq!(state.tightening_tracker.batch_size(), 8);
+
+        state.set_pset(1, None);
+        assert_eq!(state.tightening_tracker.batch_size(), 3);
+    }
+
+    #[test]
+    fn test_firmware_upgrade_keeps_unchanged_identity() {
+        let mut state = DeviceState::new();
+        state.apply_firmware_upgrade(FirmwareUpgrade {
+            software_version: "FW 2.0".to_string(),
+            tool_software_version: None,
+            controller_name: Some("Upgraded".to_string()),
+            supplier_code: None,
+        });
+        assert_eq!(state.software_version, "FW 2.0");
+        assert_eq!(
+            state.tool_software_version,
+            format!("SIM {}", env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(state.controller_name, "Upgraded");
+        assert_eq!(state.supplier_code, "SIM");
+    }
+
     #[test]
     fn test_tool_state() {
         let mut state = DeviceState::new();
@@ -1,28 +1,37 @@
 use serde::Serialize;
+use std::collections::HashMap;
+
+pub use crate::protocol::mid::{SubscriptionKind, SubscriptionRequest};
+
+/// Details recorded when a client subscribes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubscriptionInfo {
+    /// Revision requested in the subscribe MID header
+    pub revision: u16,
+    /// Data sent with the subscribe MID (e.g. selection filters), empty if none
+    pub filters: String,
+    /// When the subscription was made (Open Protocol timestamp format)
+    pub subscribed_at: String,
+    /// Subscribed with the No Ack header flag: pushes are not acknowledged
+    pub no_ack: bool,
+}
+
+impl SubscriptionInfo {
+    pub fn new(revision: u16, filters: impl Into<String>) -> Self {
+        Self {
+            revision,
+            filters: filters.into(),
+            subscribed_at: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+            no_ack: false,
+        }
+    }
+}
 
-/// Manages client subscription state for various event types
+/// Manages client subscription state for the event streams in the MID table
 #[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
 pub struct Subscriptions {
-    /// Subscribed to tightening result events (MID 0061)
-    pub tightening_result: bool,
-
-    /// Subscribed to parameter set selection events (MID 0015)
-    pub pset_selection: bool,
-
-    /// Subscribed to vehicle ID events (MID 0052)
-    pub vehicle_id: bool,
-
-    /// Subscribed to multi-spindle status events (MID 0091)
-    pub multi_spindle_status: bool,
-
-    /// Subscribed to multi-spindle result events (MID 0101)
-    pub multi_spindle_result: bool,
-
-    /// Subscribed to alarm events (not yet implemented)
-    pub alarm: bool,
-
-    /// Subscribed to job info events (not yet implemented)
-    pub job_info: bool,
+    active: HashMap<SubscriptionKind, SubscriptionInfo>,
 }
 
 impl Subscriptions {
@@ -31,79 +40,29 @@ impl Subscriptions {
         Self::default()
     }
 
-    /// Subscribe to tightening result events
-    pub fn subscribe_tightening_result(&mut self) {
-        self.tightening_result = true;
-    }
-
-    /// Unsubscribe from tightening result events
-    pub fn unsubscribe_tightening_result(&mut self) {
-        self.tightening_result = false;
-    }
-
-    /// Subscribe to parameter set selection events
-    pub fn subscribe_pset_selection(&mut self) {
-        self.pset_selection = true;
-    }
-
-    /// Unsubscribe from parameter set selection events
-    pub fn unsubscribe_pset_selection(&mut self) {
-        self.pset_selection = false;
-    }
-
-    /// Check if subscribed to tightening results
-    pub fn is_subscribed_to_tightening_result(&self) -> bool {
-        self.tightening_result
-    }
-
-    /// Check if subscribed to pset selection
-    pub fn is_subscribed_to_pset_selection(&self) -> bool {
-        self.pset_selection
-    }
-
-    /// Subscribe to vehicle ID events
-    pub fn subscribe_vehicle_id(&mut self) {
-        self.vehicle_id = true;
+    /// Subscribe to an event stream, replacing any earlier subscription details
+    pub fn subscribe(&mut self, kind: SubscriptionKind, info: SubscriptionInfo) {
+        self.active.insert(kind, info);
     }
 
-    /// Unsubscribe from vehicle ID events
-    pub fn unsubscribe_vehicle_id(&mut self) {
-        self.vehicle_id = false;
+    /// Unsubscribe from an event stream; returns whether it was subscribed
+    pub fn unsubscribe(&mut self, kind: SubscriptionKind) -> bool {
+        self.active.remove(&kind).is_some()
     }
 
-    /// Check if subscribed to vehicle ID
-    pub fn is_subscribed_to_vehicle_id(&self) -> bool {
-        self.vehicle_id
+    /// Check if subscribed to the given event stream
+    pub fn is_subscribed(&self, kind: SubscriptionKind) -> bool {
+        self.active.contains_key(&kind)
     }
 
-    /// Subscribe to multi-spindle status events
-    pub fn subscribe_multi_spindle_status(&mut self) {
-        self.multi_spindle_status = true;
+    /// Details of an active subscription
+    pub fn get(&self, kind: SubscriptionKind) -> Option<&SubscriptionInfo> {
+        self.active.get(&kind)
     }
 
-    /// Unsubscribe from multi-spindle status events
-    pub fn unsubscribe_multi_spindle_status(&mut self) {
-        self.multi_spindle_status = false;
-    }
-
-    /// Check if subscribed to multi-spindle status
-    pub fn is_subscribed_to_multi_spindle_status(&self) -> bool {
-        self.multi_spindle_status
-    }
-
-    /// Subscribe to multi-spindle result events
-    pub fn subscribe_multi_spindle_result(&mut self) {
-        self.multi_spindle_result = true;
-    }
-
-    /// Unsubscribe from multi-spindle result events
-    pub fn unsubscribe_multi_spindle_result(&mut self) {
-        self.multi_spindle_result = false;
-    }
-
-    /// Check if subscribed to multi-spindle result
-    pub fn is_subscribed_to_multi_spindle_result(&self) -> bool {
-        self.multi_spindle_result
+    /// Iterate over active subscriptions
+    pub fn iter(&self) -> impl Iterator<Item = (SubscriptionKind, &SubscriptionInfo)> {
+        self.active.iter().map(|(kind, info)| (*kind, info))
     }
 
     /// Get count of active subscriptions
@@ -111,31 +70,8 @@ impl Subscriptions {
     /// Diagnostic method for subscription statistics.
     /// Used by webUI connection dashboard to display per-client
     /// subscription counts and by monitoring/metrics endpoints.
-    #[allow(dead_code)]
     pub fn active_count(&self) -> usize {
-        let mut count = 0;
-        if self.tightening_result {
-            count += 1;
-        }
-        if self.pset_selection {
-            count += 1;
-        }
-        if self.vehicle_id {
-            count += 1;
-        }
-        if self.multi_spindle_status {
-            count += 1;
-        }
-        if self.multi_spindle_result {
-            count += 1;
-        }
-        if self.alarm {
-            count += 1;
-        }
-        if self.job_info {
-            count += 1;
-        }
-        count
+        self.active.len()
     }
 
     /// Check if any subscriptions are active
@@ -145,7 +81,7 @@ impl Subscriptions {
     /// keep idle connections alive, and by webUI for client status display.
     #[allow(dead_code)]
     pub fn has_any_subscription(&self) -> bool {
-        self.active_count() > 0
+        !self.active.is_empty()
     }
 }
 
@@ -153,11 +89,15 @@ impl Subscriptions {
 mod tests {
     use super::*;
 
+    fn info() -> SubscriptionInfo {
+        SubscriptionInfo::new(1, "")
+    }
+
     #[test]
     fn test_default_no_subscriptions() {
         let subs = Subscriptions::new();
-        assert!(!subs.is_subscribed_to_tightening_result());
-        assert!(!subs.is_subscribed_to_pset_selection());
+        assert!(!subs.is_subscribed(SubscriptionKind::TighteningResult));
+        assert!(!subs.is_subscribed(SubscriptionKind::PsetSelection));
         assert_eq!(subs.active_count(), 0);
         assert!(!subs.has_any_subscription());
     }
@@ -165,9 +105,9 @@ mod tests {
     #[test]
     fn test_subscribe_tightening_result() {
         let mut subs = Subscriptions::new();
-        subs.subscribe_tightening_result();
+        subs.subscribe(SubscriptionKind::TighteningResult, info());
 
-        assert!(subs.is_subscribed_to_tightening_result());
+        assert!(subs.is_subscribed(SubscriptionKind::TighteningResult));
         assert_eq!(subs.active_count(), 1);
         assert!(subs.has_any_subscription());
     }
@@ -175,31 +115,43 @@ mod tests {
     #[test]
     fn test_unsubscribe_tightening_result() {
         let mut subs = Subscriptions::new();
-        subs.subscribe_tightening_result();
-        subs.unsubscribe_tightening_result();
+        subs.subscribe(SubscriptionKind::TighteningResult, info());
 
-        assert!(!subs.is_subscribed_to_tightening_result());
+        assert!(subs.unsubscribe(SubscriptionKind::TighteningResult));
+        assert!(!subs.is_subscribed(SubscriptionKind::TighteningResult));
         assert_eq!(subs.active_count(), 0);
+        assert!(!subs.unsubscribe(SubscriptionKind::TighteningResult));
     }
 
     #[test]
     fn test_multiple_subscriptions() {
         let mut subs = Subscriptions::new();
-        subs.subscribe_tightening_result();
-        subs.subscribe_pset_selection();
+        subs.subscribe(SubscriptionKind::TighteningResult, info());
+        subs.subscribe(SubscriptionKind::PsetSelection, info());
 
-        assert!(subs.is_subscribed_to_tightening_result());
-        assert!(subs.is_subscribed_to_pset_selection());
+        assert!(subs.is_subscribed(SubscriptionKind::TighteningResult));
+        assert!(subs.is_subscribed(SubscriptionKind::PsetSelection));
         assert_eq!(subs.active_count(), 2);
     }
 
+    #[test]
+    fn test_subscription_keeps_metadata() {
+        let mut subs = Subscriptions::new();
+        subs.subscribe(SubscriptionKind::Alarm, SubscriptionInfo::new(2, "0001"));
+
+        let alarm = subs.get(SubscriptionKind::Alarm).unwrap();
+        assert_eq!(alarm.revision, 2);
+        assert_eq!(alarm.filters, "0001");
+        assert!(!alarm.subscribed_at.is_empty());
+    }
+
     #[test]
     fn test_subscribe_idempotent() {
         let mut subs = Subscriptions::new();
-        subs.subscribe_tightening_result();
-        subs.subscribe_tightening_result();
+        subs.subscribe(SubscriptionKind::TighteningResult, info());
+        subs.subscribe(SubscriptionKind::TighteningResult, info());
 
-        assert!(subs.is_subscribed_to_tightening_result());
+        assert!(subs.is_subscribed(SubscriptionKind::TighteningResult));
         assert_eq!(subs.active_count(), 1);
     }
 }
@@ -0,0 +1,145 @@
+//! Simulated tool telemetry
+//!
+//! Motor temperature, current draw and supply voltage change slowly over time
+//! and react to tightening activity: every tightening heats the motor and
+//! causes a current peak, and both relax back towards idle between cycles.
+//! The values are part of `GET /state`, streamed to WebSocket clients as
+//! `Telemetry` events and served over Open Protocol by MID 9120/9121.
+
+use crate::context::AppContext;
+use crate::events::SimulatorEvent;
+use crate::observable_state::ObservableState;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+
+/// Motor temperature with the tool at rest (°C)
+pub const AMBIENT_TEMPERATURE_C: f64 = 25.0;
+
+/// Temperature the motor approaches under continuous load (°C)
+pub const MAX_TEMPERATURE_C: f64 = 95.0;
+
+/// Current drawn by the idle tool (A)
+pub const IDLE_CURRENT_A: f64 = 0.4;
+
+/// Nominal supply voltage (V)
+pub const NOMINAL_VOLTAGE_V: f64 = 24.0;
+
+/// Time constant of the motor cooling down (seconds)
+const COOLING_TIME_CONSTANT_S: f64 = 300.0;
+
+/// Time constant of the current falling back to idle (seconds)
+const CURRENT_TIME_CONSTANT_S: f64 = 2.0;
+
+/// Supply voltage drop per ampere drawn (V/A, cable and source resistance)
+const VOLTAGE_DROP_PER_AMP: f64 = 0.05;
+
+/// Current snapshot of the tool telemetry
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Telemetry {
+    /// Motor temperature in °C
+    pub motor_temperature_c: f64,
+    /// Current draw in A
+    pub current_a: f64,
+    /// Supply voltage in V
+    pub supply_voltage_v: f64,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self {
+            motor_temperature_c: AMBIENT_TEMPERATURE_C,
+            current_a: IDLE_CURRENT_A,
+            supply_voltage_v: NOMINAL_VOLTAGE_V - IDLE_CURRENT_A * VOLTAGE_DROP_PER_AMP,
+        }
+    }
+}
+
+impl Telemetry {
+    /// Account for a completed tightening
+    ///
+    /// Heat grows with torque and rundown time and shrinks as the motor
+    /// approaches its maximum temperature, so sustained load saturates.
+    pub fn record_tightening(&mut self, torque: f64, duration_ms: u64) {
+        let heat = 0.02 * torque.abs() * (duration_ms as f64 / 1000.0).max(0.1);
+        let headroom = (MAX_TEMPERATURE_C - self.motor_temperature_c).max(0.0)
+            / (MAX_TEMPERATURE_C - AMBIENT_TEMPERATURE_C);
+        self.motor_temperature_c += heat * headroom;
+        self.current_a = self.current_a.max(IDLE_CURRENT_A + 0.3 * torque.abs());
+        self.update_voltage(0.0);
+    }
+
+    /// Let `elapsed` pass: cool down, relax the current and add a little noise
+    pub fn advance<R: Rng>(&mut self, elapsed: Duration, rng: &mut R) {
+        let secs = elapsed.as_secs_f64();
+        let cooling = (-secs / COOLING_TIME_CONSTANT_S).exp();
+        self.motor_temperature_c =
+            AMBIENT_TEMPERATURE_C + (self.motor_temperature_c - AMBIENT_TEMPERATURE_C) * cooling;
+        let relax = (-secs / CURRENT_TIME_CONSTANT_S).exp();
+        self.current_a = IDLE_CURRENT_A + (self.current_a - IDLE_CURRENT_A) * relax;
+        self.update_voltage(rng.random_range(-0.05..=0.05));
+    }
+
+    fn update_voltage(&mut self, noise: f64) {
+        self.supply_voltage_v = NOMINAL_VOLTAGE_V - self.current_a * VOLTAGE_DROP_PER_AMP + noise;
+    }
+}
+
+/// Advance the telemetry and broadcast it every `[telemetry] interval_ms`
+pub fn spawn_telemetry(ctx: &AppContext) -> Option<AbortHandle> {
+    let interval_ms = ctx.settings.telemetry.interval_ms;
+    if interval_ms == 0 {
+        return None;
+    }
+    let state = ctx.observable_state.clone();
+
+    let task = ctx.resources.track("telemetry");
+    let handle = tokio::spawn(async move {
+        let _task = task;
+        let period = Duration::from_millis(interval_ms);
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            tick(&state, period);
+        }
+    });
+    Some(handle.abort_handle())
+}
+
+/// Advance the telemetry by `elapsed` and broadcast the new values
+pub fn tick(state: &ObservableState, elapsed: Duration) {
+    let telemetry = {
+        let mut state = state.write();
+        state.telemetry.advance(elapsed, &mut rand::rng());
+        state.telemetry
+    };
+    state.broadcast(SimulatorEvent::Telemetry { telemetry });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_tightenings_heat_and_idle_cools() {
+        let mut telemetry = Telemetry::default();
+        for _ in 0..50 {
+            telemetry.record_tightening(40.0, 500);
+        }
+        let hot = telemetry.motor_temperature_c;
+        assert!(hot > AMBIENT_TEMPERATURE_C + 10.0, "{}", hot);
+        assert!(hot < MAX_TEMPERATURE_C);
+        assert!(telemetry.current_a > 10.0);
+        assert!(telemetry.supply_voltage_v < NOMINAL_VOLTAGE_V - 0.5);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        telemetry.advance(Duration::from_secs(600), &mut rng);
+        assert!(telemetry.motor_temperature_c < hot);
+        assert!(telemetry.motor_temperature_c > AMBIENT_TEMPERATURE_C);
+        assert!((telemetry.current_a - IDLE_CURRENT_A).abs() < 0.01);
+        assert!((telemetry.supply_voltage_v - NOMINAL_VOLTAGE_V).abs() < 0.1);
+    }
+}
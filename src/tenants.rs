@@ -0,0 +1,577 @@
+//! Simulation namespaces (tenants)
+//!
+//! A shared lab simulator can host several independent controllers: each
+//! tenant created with `POST /tenants` gets its own [`AppContext`] (device
+//! state, event channel, results and audit log), its own SQLite database,
+//! lifetime statistics file and Parquet export directory next to the
+//! configured ones, and its own Open Protocol port. Its HTTP API is served under `/tenants/{name}/...` on the
+//! shared HTTP port.
+//!
+//! Fleet setups provision many tenants at once from [`ControllerDefinition`]s
+//! (`POST /controllers/bulk`) and remove them again (`DELETE /controllers/{id}`).
+
+use crate::config::Settings;
+use crate::context::AppContext;
+use crate::pset::Pset;
+use crate::pset_history::PsetChangeSource;
+use crate::session_registry::CloseMode;
+use crate::state::{IdentityPatch, StatePatch};
+use crate::{counters, lifetime, user_data};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio::task::AbortHandle;
+
+/// Longest accepted tenant name
+pub const MAX_NAME_LEN: usize = 32;
+
+/// Starts serving a tenant: its Open Protocol listener and background tasks
+///
+/// Returns the handles of the started tasks, aborted when the tenant is removed.
+pub type TenantLauncher = Arc<dyn Fn(TcpListener, AppContext) -> Vec<AbortHandle> + Send + Sync>;
+
+/// Error creating or looking up a tenant
+#[derive(Debug, Error)]
+pub enum TenantError {
+    #[error("invalid tenant name {0:?} (1-{MAX_NAME_LEN} letters, digits, '-' or '_')")]
+    InvalidName(String),
+    #[error("tenant {0:?} already exists")]
+    AlreadyExists(String),
+    #[error("tenant {0:?} not found")]
+    NotFound(String),
+    #[error("controller {id:?}: {reason}")]
+    InvalidDefinition { id: String, reason: String },
+    #[error("cannot listen on port {port}: {source}")]
+    Bind {
+        port: u16,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Description of a tenant for the HTTP API
+#[derive(Debug, Clone, Serialize)]
+pub struct TenantInfo {
+    pub name: String,
+    /// Open Protocol port, if the tenant is served over TCP
+    pub tcp_port: Option<u16>,
+    pub database: PathBuf,
+    pub created_at: String,
+}
+
+struct Tenant {
+    info: TenantInfo,
+    ctx: AppContext,
+    /// Listener and background tasks started by the launcher
+    tasks: Vec<AbortHandle>,
+}
+
+/// One simulated controller of a `POST /controllers/bulk` request
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControllerDefinition {
+    /// Tenant name the controller is served under
+    pub id: String,
+    /// Open Protocol port; a free one is picked when absent or 0
+    #[serde(default)]
+    pub tcp_port: Option<u16>,
+    /// Identity reported in MID 0002 and MID 0061
+    #[serde(default)]
+    pub identity: IdentityPatch,
+    /// PSETs to add, or to update where the ID or name exists
+    #[serde(default)]
+    pub psets: Vec<Pset>,
+    /// State the controller starts in
+    #[serde(default)]
+    pub preset: ControllerPreset,
+}
+
+/// Start state of a provisioned controller
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControllerPreset {
+    pub pset_id: Option<u32>,
+    pub batch_size: Option<u32>,
+    pub vehicle_id: Option<String>,
+    pub tool_enabled: Option<bool>,
+}
+
+impl ControllerDefinition {
+    fn invalid(&self, reason: impl Into<String>) -> TenantError {
+        TenantError::InvalidDefinition {
+            id: self.id.clone(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Apply the identity to the tenant's settings, so MID 0002 reports it from the start
+    fn configure(&self, settings: &mut Settings) -> Result<(), TenantError> {
+        let identity = &self.identity;
+        let patch = StatePatch {
+            cell_id: identity.cell_id,
+            channel_id: identity.channel_id,
+            controller_name: identity.controller_name.clone(),
+            supplier_code: identity.supplier_code.clone(),
+            ..StatePatch::default()
+        };
+        patch.validate().map_err(|reason| self.invalid(reason))?;
+        let device = &mut settings.device;
+        device.cell_id = identity.cell_id.unwrap_or(device.cell_id);
+        device.channel_id = identity.channel_id.unwrap_or(device.channel_id);
+        if let Some(name) = &identity.controller_name {
+            device.controller_name = name.clone();
+        }
+        if let Some(code) = &identity.supplier_code {
+            device.supplier_code = code.clone();
+        }
+        Ok(())
+    }
+
+    /// Store the PSETs and apply the preset
+    ///
+    /// New PSETs get the next free ID; `preset.pset_id` may name them by the
+    /// ID given in the definition.
+    fn provision(&self, ctx: &AppContext) -> Result<(), TenantError> {
+        let mut stored_ids = BTreeMap::new();
+        {
+            let mut repo = ctx.pset_repository.write().unwrap();
+            for pset in &self.psets {
+                let existing = repo
+                    .get_all()
+                    .into_iter()
+                    .find(|p| p.id == pset.id)
+                    .or_else(|| repo.get_all().into_iter().find(|p| p.name == pset.name));
+                let stored = match existing {
+                    Some(existing) => repo.update(
+                        existing.id,
+                        Pset {
+                            id: existing.id,
+                            ..pset.clone()
+                        },
+                    ),
+                    None => repo.create(pset.clone()),
+                }
+                .map_err(|reason| self.invalid(format!("PSET {}: {}", pset.id, reason)))?;
+                stored_ids.insert(pset.id, stored.id);
+            }
+        }
+
+        let preset = &self.preset;
+        if let Some(pset_id) = preset.pset_id {
+            let pset_id = stored_ids.get(&pset_id).copied().unwrap_or(pset_id);
+            let pset = ctx.pset_repository.read().unwrap().get_by_id(pset_id);
+            let Some(pset) = pset else {
+                return Err(self.invalid(format!("PSET {} does not exist", pset_id)));
+            };
+            ctx.observable_state
+                .set_pset(pset_id, Some(pset.name), PsetChangeSource::Http);
+        }
+        if let Some(size) = preset.batch_size {
+            ctx.observable_state.set_batch_size(size);
+        }
+        if let Some(vin) = &preset.vehicle_id {
+            ctx.observable_state.set_vehicle_id(vin.clone());
+        }
+        if let Some(enabled) = preset.tool_enabled {
+            ctx.observable_state.write().tool_enabled = enabled;
+        }
+        Ok(())
+    }
+}
+
+/// Tenants of a simulator
+pub struct TenantRegistry {
+    base: Settings,
+    launcher: Option<TenantLauncher>,
+    tenants: RwLock<BTreeMap<String, Tenant>>,
+}
+
+impl TenantRegistry {
+    /// Registry deriving tenant settings from `base`
+    ///
+    /// Without a launcher tenants are only reachable over HTTP.
+    pub fn new(base: Settings) -> Self {
+        Self {
+            base,
+            launcher: None,
+            tenants: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Serve each new tenant's Open Protocol port with `launcher`
+    pub fn with_launcher(mut self, launcher: TenantLauncher) -> Self {
+        self.launcher = Some(launcher);
+        self
+    }
+
+    /// Create a tenant; `tcp_port` 0 or `None` picks a free port
+    pub async fn create(
+        &self,
+        name: &str,
+        tcp_port: Option<u16>,
+    ) -> Result<TenantInfo, TenantError> {
+        self.create_with(name, tcp_port, None).await
+    }
+
+    /// Create the controllers of a fleet, all or none
+    ///
+    /// Every definition is checked before the first one is created; if one
+    /// still fails (e.g. its port is taken), those created so far are removed.
+    pub async fn create_bulk(
+        &self,
+        definitions: &[ControllerDefinition],
+    ) -> Result<Vec<TenantInfo>, TenantError> {
+        let mut ids = HashSet::new();
+        for definition in definitions {
+            validate_name(&definition.id)?;
+            if !ids.insert(definition.id.as_str())
+                || self.tenants.read().unwrap().contains_key(&definition.id)
+            {
+                return Err(TenantError::AlreadyExists(definition.id.clone()));
+            }
+            definition.configure(&mut self.base.clone())?;
+        }
+
+        let mut created = Vec::new();
+        for definition in definitions {
+            match self
+                .create_with(&definition.id, definition.tcp_port, Some(definition))
+                .await
+            {
+                Ok(info) => created.push(info),
+                Err(e) => {
+                    for info in &created {
+                        let _ = self.remove(&info.name);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(created)
+    }
+
+    async fn create_with(
+        &self,
+        name: &str,
+        tcp_port: Option<u16>,
+        definition: Option<&ControllerDefinition>,
+    ) -> Result<TenantInfo, TenantError> {
+        validate_name(name)?;
+        if self.tenants.read().unwrap().contains_key(name) {
+            return Err(TenantError::AlreadyExists(name.to_string()));
+        }
+
+        let mut settings = self.base.clone();
+        settings.database.path = suffixed(&settings.database.path, name);
+        settings.stats.lifetime_path = suffixed(&settings.stats.lifetime_path, name);
+        settings.export.parquet_dir = suffixed(&settings.export.parquet_dir, name);
+        if let Some(journal) = &settings.logging.journal.path {
+            settings.logging.journal.path = Some(suffixed(journal, name));
+        }
+        settings.proxy.upstream = None;
+        if let Some(definition) = definition {
+            definition.configure(&mut settings)?;
+        }
+
+        let listener = match &self.launcher {
+            Some(_) => {
+                let port = tcp_port.unwrap_or(0);
+                let addr = format!("{}:{}", settings.server.bind_address, port);
+                let listener = TcpListener::bind(&addr)
+                    .await
+                    .map_err(|source| TenantError::Bind { port, source })?;
+                settings.server.tcp_port = listener
+                    .local_addr()
+                    .map_err(|source| TenantError::Bind { port, source })?
+                    .port();
+                Some(listener)
+            }
+            None => None,
+        };
+
+        let info = TenantInfo {
+            name: name.to_string(),
+            tcp_port: listener.as_ref().map(|_| settings.server.tcp_port),
+            database: settings.database.path.clone(),
+            created_at: chrono::Local::now().to_rfc3339(),
+        };
+        let ctx = AppContext::new(settings);
+        if let Some(definition) = definition {
+            definition.provision(&ctx)?;
+        }
+
+        let mut tenants = self.tenants.write().unwrap();
+        // Another request may have won the race while we were binding
+        if tenants.contains_key(name) {
+            return Err(TenantError::AlreadyExists(name.to_string()));
+        }
+        let tasks = match (&self.launcher, listener) {
+            (Some(launcher), Some(listener)) => launcher(listener, ctx.clone()),
+            _ => Vec::new(),
+        };
+        tenants.insert(
+            name.to_string(),
+            Tenant {
+                info: info.clone(),
+                ctx,
+                tasks,
+            },
+        );
+        Ok(info)
+    }
+
+    /// Remove tenant `name`: close its Open Protocol port and its connections
+    /// and stop its background tasks
+    ///
+    /// The database stays on disk, so a tenant created again under the same
+    /// name finds its PSETs and counters.
+    pub fn remove(&self, name: &str) -> Result<TenantInfo, TenantError> {
+        let tenant = self
+            .tenants
+            .write()
+            .unwrap()
+            .remove(name)
+            .ok_or_else(|| TenantError::NotFound(name.to_string()))?;
+        for task in &tenant.tasks {
+            task.abort();
+        }
+        // Keep what the stopped persistence tasks had not saved yet
+        lifetime::save_on_shutdown(&tenant.ctx);
+        counters::save_on_shutdown(&tenant.ctx);
+        user_data::save_on_shutdown(&tenant.ctx);
+        for session in tenant.ctx.sessions.snapshot() {
+            tenant
+                .ctx
+                .sessions
+                .request_close(session.id, CloseMode::Graceful);
+        }
+        Ok(tenant.info)
+    }
+
+    /// Context of tenant `name`
+    pub fn get(&self, name: &str) -> Result<AppContext, TenantError> {
+        self.tenants
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|tenant| tenant.ctx.clone())
+            .ok_or_else(|| TenantError::NotFound(name.to_string()))
+    }
+
+    /// Description of tenant `name`
+    pub fn info(&self, name: &str) -> Result<TenantInfo, TenantError> {
+        self.tenants
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|tenant| tenant.info.clone())
+            .ok_or_else(|| TenantError::NotFound(name.to_string()))
+    }
+
+    /// All tenants, sorted by name
+    pub fn list(&self) -> Vec<TenantInfo> {
+        self.tenants
+            .read()
+            .unwrap()
+            .values()
+            .map(|tenant| tenant.info.clone())
+            .collect()
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), TenantError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(TenantError::InvalidName(name.to_string()))
+    }
+}
+
+/// `dir/stem.ext` → `dir/stem-{tenant}.ext`
+fn suffixed(path: &Path, tenant: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, tenant, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, tenant),
+    };
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_files_sit_next_to_the_configured_ones() {
+        assert_eq!(
+            suffixed(Path::new("data/simulator.db"), "team-a"),
+            PathBuf::from("data/simulator-team-a.db")
+        );
+        assert_eq!(
+            suffixed(Path::new("lifetime"), "b"),
+            PathBuf::from("lifetime-b")
+        );
+        assert_eq!(
+            suffixed(Path::new("data/exports"), "st010"),
+            PathBuf::from("data/exports-st010")
+        );
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("team_a-1").is_ok());
+        for name in ["", "a/b", "../x", "tëam", &"x".repeat(MAX_NAME_LEN + 1)] {
+            assert!(validate_name(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_isolated() {
+        let mut base = Settings::default();
+        base.database.path = std::env::temp_dir().join("tenant_isolation.db");
+        let registry = TenantRegistry::new(base);
+
+        let a = registry.create("a", None).await.unwrap();
+        registry.create("b", Some(4545)).await.unwrap();
+        assert_eq!(a.tcp_port, None, "no launcher, no TCP port");
+        assert!(matches!(
+            registry.create("a", None).await,
+            Err(TenantError::AlreadyExists(_))
+        ));
+
+        let ctx_a = registry.get("a").unwrap();
+        let ctx_b = registry.get("b").unwrap();
+        ctx_a.observable_state.write().tool_enabled = false;
+        assert!(ctx_b.observable_state.read().tool_enabled);
+        assert_ne!(ctx_a.settings.database.path, ctx_b.settings.database.path);
+        assert_eq!(
+            registry
+                .list()
+                .iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert!(matches!(registry.get("c"), Err(TenantError::NotFound(_))));
+
+        for tenant in registry.list() {
+            let _ = std::fs::remove_file(tenant.database);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remove_stops_the_tenant_tasks() {
+        let mut base = Settings::default();
+        base.database.path = std::env::temp_dir().join("tenant_remove.db");
+        base.server.bind_address = "127.0.0.1".to_string();
+        let registry = TenantRegistry::new(base).with_launcher(Arc::new(|listener, ctx| {
+            let task = ctx.resources.track("listener");
+            let server = tokio::spawn(async move {
+                let _task = task;
+                let _listener = listener;
+                std::future::pending::<()>().await;
+            });
+            vec![
+                server.abort_handle(),
+                counters::spawn_persistence(&ctx),
+                user_data::spawn_persistence(&ctx),
+            ]
+        }));
+        let running = |ctx: &AppContext| -> usize {
+            ctx.resources
+                .tasks()
+                .values()
+                .map(|counts| counts.running)
+                .sum()
+        };
+
+        let info = registry.create("a", None).await.unwrap();
+        let ctx = registry.get("a").unwrap();
+        assert_eq!(running(&ctx), 3);
+
+        registry.remove("a").unwrap();
+        for _ in 0..50 {
+            if running(&ctx) == 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(running(&ctx), 0);
+
+        let _ = std::fs::remove_file(info.database);
+    }
+
+    #[tokio::test]
+    async fn test_create_bulk_is_all_or_none() {
+        let mut base = Settings::default();
+        base.database.path = std::env::temp_dir().join("tenant_bulk.db");
+        let registry = TenantRegistry::new(base);
+        let definitions = |json: serde_json::Value| -> Vec<ControllerDefinition> {
+            serde_json::from_value(json).unwrap()
+        };
+
+        // The second controller presets a PSET nobody defined
+        let result = registry
+            .create_bulk(&definitions(serde_json::json!([
+                {"id": "st010", "identity": {"cell_id": 10}},
+                {"id": "st020", "preset": {"pset_id": 42}}
+            ])))
+            .await;
+        assert!(matches!(
+            result,
+            Err(TenantError::InvalidDefinition { id, .. }) if id == "st020"
+        ));
+        assert!(registry.list().is_empty());
+
+        let result = registry
+            .create_bulk(&definitions(serde_json::json!([
+                {"id": "st010"},
+                {"id": "st010"}
+            ])))
+            .await;
+        assert!(matches!(result, Err(TenantError::AlreadyExists(_))));
+        assert!(registry.list().is_empty());
+
+        let created = registry
+            .create_bulk(&definitions(serde_json::json!([
+                {"id": "st010", "identity": {"cell_id": 10, "controller_name": "ST010"},
+                 "preset": {"pset_id": 1, "batch_size": 4, "vehicle_id": "VIN001"}},
+                {"id": "st020"}
+            ])))
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 2);
+        let ctx = registry.get("st010").unwrap();
+        assert_eq!(ctx.settings.device.cell_id, 10);
+        assert_eq!(ctx.settings.device.controller_name, "ST010");
+        let state = ctx.observable_state.read();
+        assert_eq!(state.current_pset_id, Some(1));
+        assert_eq!(state.vehicle_id.as_deref(), Some("VIN001"));
+        drop(state);
+
+        registry.remove("st010").unwrap();
+        assert!(matches!(
+            registry.get("st010"),
+            Err(TenantError::NotFound(_))
+        ));
+        assert!(matches!(
+            registry.remove("st010"),
+            Err(TenantError::NotFound(_))
+        ));
+
+        for tenant in created {
+            let _ = std::fs::remove_file(tenant.database);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Golden-file tests of handler responses
+//!
+//! A fixture is a pair of files in one directory: `<name>.request` holds an
+//! Open Protocol frame as a client sends it (without the NUL terminator) and
+//! `<name>.response` the exact frame the handler must answer with. A `?` in a
+//! golden response matches any byte, for fields such as timestamps that change
+//! from run to run.
+//!
+//! With `UPDATE_GOLDEN=1` in the environment, mismatching or missing response
+//! files are rewritten from the actual output instead of failing; `?` masks
+//! are kept where the new response has the same length. Review the diff
+//! before checking updated files in.
+
+use crate::handler::{HandlerError, HandlerRegistry};
+use crate::protocol::{ProtocolError, parse_message, serialize_response};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Environment variable that switches to update mode
+pub const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+/// Byte in a golden file that matches any byte
+pub const WILDCARD: u8 = b'?';
+
+#[derive(Error, Debug)]
+pub enum GoldenError {
+    #[error("{path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("invalid request frame: {0}")]
+    Parse(#[from] ProtocolError),
+
+    #[error("handler failed: {0}")]
+    Handler(#[from] HandlerError),
+
+    #[error("{path:?} does not match\n  expected: {expected}\n  actual:   {actual}")]
+    Mismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, GoldenError> {
+    std::fs::read(path).map_err(|source| GoldenError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Printable form of a frame, with non-printable bytes escaped
+fn escape(bytes: &[u8]) -> String {
+    bytes.escape_ascii().to_string()
+}
+
+fn update_mode() -> bool {
+    std::env::var_os(UPDATE_ENV).is_some_and(|value| value != "0")
+}
+
+/// Answer a request frame with `registry` and return the response frame
+pub fn respond(registry: &HandlerRegistry, request: &[u8]) -> Result<Vec<u8>, GoldenError> {
+    let message = parse_message(request)?;
+    let response = registry.handle_message(&message)?;
+    Ok(serialize_response(&response))
+}
+
+/// Whether `actual` matches `golden`, where [`WILDCARD`] matches any byte
+pub fn matches(golden: &[u8], actual: &[u8]) -> bool {
+    golden.len() == actual.len()
+        && golden
+            .iter()
+            .zip(actual)
+            .all(|(expected, actual)| *expected == WILDCARD || expected == actual)
+}
+
+/// `actual` with the wildcards of `golden` carried over, for update mode
+fn masked(golden: &[u8], actual: &[u8]) -> Vec<u8> {
+    if golden.len() != actual.len() {
+        return actual.to_vec();
+    }
+    golden
+        .iter()
+        .zip(actual)
+        .map(|(expected, actual)| {
+            if *expected == WILDCARD {
+                WILDCARD
+            } else {
+                *actual
+            }
+        })
+        .collect()
+}
+
+/// Compare `actual` with the golden file at `path` (or rewrite it in update mode)
+pub fn check(path: &Path, actual: &[u8]) -> Result<(), GoldenError> {
+    let golden = match std::fs::read(path) {
+        Ok(golden) => Some(golden),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(source) => {
+            return Err(GoldenError::Io {
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+    };
+    if golden
+        .as_deref()
+        .is_some_and(|golden| matches(golden, actual))
+    {
+        return Ok(());
+    }
+    if update_mode() {
+        let contents = masked(golden.as_deref().unwrap_or_default(), actual);
+        return std::fs::write(path, contents).map_err(|source| GoldenError::Io {
+            path: path.to_path_buf(),
+            source,
+        });
+    }
+    Err(GoldenError::Mismatch {
+        path: path.to_path_buf(),
+        expected: golden.as_deref().map_or("(missing)".to_string(), escape),
+        actual: escape(actual),
+    })
+}
+
+/// Run the fixture `<name>.request` in `dir` and check the golden `<name>.response`
+pub fn check_fixture(
+    registry: &HandlerRegistry,
+    dir: &Path,
+    name: &str,
+) -> Result<(), GoldenError> {
+    let request = read(&dir.join(format!("{name}.request")))?;
+    let actual = respond(registry, &request)?;
+    check(&dir.join(format!("{name}.response")), &actual)
+}
+
+/// Names of the fixtures in `dir` (request files without extension), sorted
+pub fn fixtures(dir: &Path) -> Result<Vec<String>, GoldenError> {
+    let entries = std::fs::read_dir(dir).map_err(|source| GoldenError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "request"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcards_match_any_byte() {
+        assert!(matches(b"0024??????????", b"00242024-05-01"));
+        assert!(matches(b"0020?", b"00201"));
+        assert!(!matches(b"0020?", b"0020"));
+        assert!(!matches(b"0021?", b"00201"));
+        assert_eq!(masked(b"AB??E", b"ABCDX"), b"AB??X");
+        assert_eq!(masked(b"AB?", b"ABCD"), b"ABCD");
+    }
+}
@@ -0,0 +1,3 @@
+//! Helpers for testing handlers and serializers
+
+pub mod golden;
@@ -0,0 +1,389 @@
+//! Token-bucket throttling of subscription broadcasts
+//!
+//! A rule caps how many pushes of one subscription family (e.g. MID 0061) a
+//! single client receives per second. Every connection keeps its own token
+//! bucket per family, so the limit applies per client. A push that finds the
+//! bucket empty is either queued until a token is available or coalesced
+//! (only the newest waiting push is kept). Rules are set at runtime through
+//! `PUT /throttles/{kind}`, which lets tests show how a client copes with a
+//! throttling controller or a burst of results after reconnecting.
+
+use crate::subscriptions::SubscriptionKind;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+fn default_burst() -> u32 {
+    1
+}
+
+fn default_max_queued() -> usize {
+    1000
+}
+
+/// What happens to a push that exceeds the rate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleOverflow {
+    /// Send it later, in order
+    #[default]
+    Queue,
+    /// Keep only the newest waiting push; older ones are dropped
+    Coalesce,
+}
+
+/// Rate limit for one subscription family
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThrottleRule {
+    /// Sustained pushes per second per client
+    pub max_per_sec: f64,
+    /// Pushes a client may receive back to back before the rate applies (default: 1)
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    #[serde(default)]
+    pub overflow: ThrottleOverflow,
+    /// Queued pushes per client before the oldest is dropped (default: 1000)
+    #[serde(default = "default_max_queued")]
+    pub max_queued: usize,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ThrottleError {
+    #[error("max_per_sec must be a positive number, got {0}")]
+    InvalidRate(f64),
+
+    #[error("burst must be at least 1")]
+    InvalidBurst,
+
+    #[error("max_queued must be at least 1")]
+    InvalidQueue,
+}
+
+impl ThrottleRule {
+    pub fn validate(&self) -> Result<(), ThrottleError> {
+        if !(self.max_per_sec.is_finite() && self.max_per_sec > 0.0) {
+            return Err(ThrottleError::InvalidRate(self.max_per_sec));
+        }
+        if self.burst == 0 {
+            return Err(ThrottleError::InvalidBurst);
+        }
+        if self.max_queued == 0 {
+            return Err(ThrottleError::InvalidQueue);
+        }
+        Ok(())
+    }
+}
+
+/// Throttle rules shared by all connections
+#[derive(Debug, Default)]
+pub struct BroadcastThrottles {
+    rules: RwLock<BTreeMap<SubscriptionKind, ThrottleRule>>,
+}
+
+impl BroadcastThrottles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set or replace the rule of a subscription family
+    pub fn set(&self, kind: SubscriptionKind, rule: ThrottleRule) -> Result<(), ThrottleError> {
+        rule.validate()?;
+        self.rules.write().unwrap().insert(kind, rule);
+        Ok(())
+    }
+
+    /// Remove the rule of a subscription family; false if there was none
+    pub fn remove(&self, kind: SubscriptionKind) -> bool {
+        self.rules.write().unwrap().remove(&kind).is_some()
+    }
+
+    pub fn get(&self, kind: SubscriptionKind) -> Option<ThrottleRule> {
+        self.rules.read().unwrap().get(&kind).cloned()
+    }
+
+    pub fn list(&self) -> BTreeMap<SubscriptionKind, ThrottleRule> {
+        self.rules.read().unwrap().clone()
+    }
+}
+
+/// Token bucket refilled continuously at the rule's rate
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    rule: ThrottleRule,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rule: ThrottleRule, now: Instant) -> Self {
+        Self {
+            tokens: rule.burst as f64,
+            rule,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rule.max_per_sec).min(self.rule.burst as f64);
+        self.refilled_at = now;
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// When the next token is available
+    fn next_token_at(&self) -> Instant {
+        let missing = (1.0 - self.tokens).max(0.0);
+        self.refilled_at + Duration::from_secs_f64(missing / self.rule.max_per_sec)
+    }
+}
+
+/// Outcome of offering a push to the throttle
+#[derive(Debug, PartialEq)]
+pub struct Admission<T> {
+    /// Send this now
+    pub send: Option<T>,
+    /// Pushes dropped by queue overflow or coalescing
+    pub dropped: Vec<T>,
+}
+
+/// Buckets and waiting pushes of one connection
+#[derive(Debug)]
+pub struct ClientThrottle<T> {
+    buckets: HashMap<SubscriptionKind, TokenBucket>,
+    queued: BTreeMap<SubscriptionKind, VecDeque<T>>,
+}
+
+impl<T> Default for ClientThrottle<T> {
+    fn default() -> Self {
+        Self {
+            buckets: HashMap::new(),
+            queued: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> ClientThrottle<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bucket for the current rule, restarted when the rule changed
+    fn bucket(
+        &mut self,
+        kind: SubscriptionKind,
+        rule: ThrottleRule,
+        now: Instant,
+    ) -> &mut TokenBucket {
+        let bucket = self
+            .buckets
+            .entry(kind)
+            .or_insert_with(|| TokenBucket::new(rule.clone(), now));
+        if bucket.rule != rule {
+            *bucket = TokenBucket::new(rule, now);
+        }
+        bucket
+    }
+
+    /// Offer a push of `kind`; it is sent now, queued, or replaces a waiting one
+    pub fn admit(
+        &mut self,
+        rules: &BroadcastThrottles,
+        kind: SubscriptionKind,
+        item: T,
+        now: Instant,
+    ) -> Admission<T> {
+        // Pushes already waiting go first
+        let waiting = self
+            .queued
+            .get(&kind)
+            .is_some_and(|queue| !queue.is_empty());
+        let Some(rule) = rules.get(kind) else {
+            if waiting {
+                // Rule just removed: the next release sends everything in order
+                self.queued.entry(kind).or_default().push_back(item);
+                return Admission {
+                    send: None,
+                    dropped: Vec::new(),
+                };
+            }
+            return Admission {
+                send: Some(item),
+                dropped: Vec::new(),
+            };
+        };
+        if !waiting && self.bucket(kind, rule.clone(), now).try_take(now) {
+            return Admission {
+                send: Some(item),
+                dropped: Vec::new(),
+            };
+        }
+
+        let queue = self.queued.entry(kind).or_default();
+        let mut dropped = Vec::new();
+        if rule.overflow == ThrottleOverflow::Coalesce {
+            dropped.extend(queue.drain(..));
+        }
+        while queue.len() >= rule.max_queued {
+            dropped.extend(queue.pop_front());
+        }
+        queue.push_back(item);
+        Admission {
+            send: None,
+            dropped,
+        }
+    }
+
+    /// Waiting pushes that may go out now, in order per family
+    ///
+    /// Pushes of a family whose rule was removed are all released.
+    pub fn release(&mut self, rules: &BroadcastThrottles, now: Instant) -> Vec<T> {
+        let mut released = Vec::new();
+        let kinds: Vec<SubscriptionKind> = self.queued.keys().copied().collect();
+        for kind in kinds {
+            let rule = rules.get(kind);
+            loop {
+                if self.queued.get(&kind).is_none_or(VecDeque::is_empty) {
+                    break;
+                }
+                let allowed = match &rule {
+                    Some(rule) => self.bucket(kind, rule.clone(), now).try_take(now),
+                    None => true,
+                };
+                if !allowed {
+                    break;
+                }
+                released.extend(self.queued.get_mut(&kind).and_then(VecDeque::pop_front));
+            }
+        }
+        self.queued.retain(|_, queue| !queue.is_empty());
+        released
+    }
+
+    /// When [`release`](Self::release) has something to send next, if anything waits
+    pub fn next_release(&self, rules: &BroadcastThrottles) -> Option<Instant> {
+        self.queued
+            .keys()
+            .map(|kind| match (rules.get(*kind), self.buckets.get(kind)) {
+                (Some(rule), Some(bucket)) if bucket.rule == rule => bucket.next_token_at(),
+                // Rule removed or replaced: try right away
+                _ => Instant::now(),
+            })
+            .min()
+    }
+
+    /// Pushes waiting for a token
+    pub fn queued(&self) -> usize {
+        self.queued.values().map(VecDeque::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(max_per_sec: f64, burst: u32, overflow: ThrottleOverflow) -> ThrottleRule {
+        ThrottleRule {
+            max_per_sec,
+            burst,
+            overflow,
+            max_queued: 3,
+        }
+    }
+
+    #[test]
+    fn test_burst_then_queue_at_rate() {
+        let rules = BroadcastThrottles::new();
+        rules
+            .set(
+                SubscriptionKind::TighteningResult,
+                rule(2.0, 2, ThrottleOverflow::Queue),
+            )
+            .unwrap();
+        let kind = SubscriptionKind::TighteningResult;
+        let start = Instant::now();
+        let mut throttle = ClientThrottle::new();
+
+        assert_eq!(throttle.admit(&rules, kind, 1, start).send, Some(1));
+        assert_eq!(throttle.admit(&rules, kind, 2, start).send, Some(2));
+        assert_eq!(throttle.admit(&rules, kind, 3, start).send, None);
+        assert_eq!(throttle.admit(&rules, kind, 4, start).send, None);
+        assert_eq!(throttle.queued(), 2);
+        assert_eq!(
+            throttle.next_release(&rules),
+            Some(start + Duration::from_millis(500))
+        );
+
+        assert!(throttle.release(&rules, start).is_empty());
+        let later = start + Duration::from_millis(500);
+        assert_eq!(throttle.release(&rules, later), vec![3]);
+        // Waiting pushes keep their place ahead of new ones
+        assert_eq!(throttle.admit(&rules, kind, 5, later).send, None);
+        assert_eq!(
+            throttle.release(&rules, later + Duration::from_secs(1)),
+            vec![4, 5]
+        );
+
+        // Other families are not throttled
+        let vin = throttle.admit(&rules, SubscriptionKind::VehicleId, 6, later);
+        assert_eq!(vin.send, Some(6));
+    }
+
+    #[test]
+    fn test_coalesce_and_overflow_drop_older_pushes() {
+        let rules = BroadcastThrottles::new();
+        let kind = SubscriptionKind::Alarm;
+        rules
+            .set(kind, rule(1.0, 1, ThrottleOverflow::Coalesce))
+            .unwrap();
+        let now = Instant::now();
+        let mut throttle = ClientThrottle::new();
+
+        throttle.admit(&rules, kind, 1, now);
+        throttle.admit(&rules, kind, 2, now);
+        let admission = throttle.admit(&rules, kind, 3, now);
+        assert_eq!(admission.dropped, vec![2]);
+        assert_eq!(throttle.queued(), 1);
+
+        rules
+            .set(kind, rule(1.0, 1, ThrottleOverflow::Queue))
+            .unwrap();
+        throttle.admit(&rules, kind, 4, now);
+        throttle.admit(&rules, kind, 5, now);
+        let admission = throttle.admit(&rules, kind, 6, now);
+        assert_eq!(admission.dropped, vec![3]);
+
+        // Removing the rule releases everything that waits
+        assert!(rules.remove(kind));
+        assert_eq!(throttle.release(&rules, now), vec![4, 5, 6]);
+        assert_eq!(throttle.next_release(&rules), None);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        let rules = BroadcastThrottles::new();
+        let kind = SubscriptionKind::TighteningResult;
+        assert_eq!(
+            rules.set(kind, rule(0.0, 1, ThrottleOverflow::Queue)),
+            Err(ThrottleError::InvalidRate(0.0))
+        );
+        assert_eq!(
+            rules.set(kind, rule(1.0, 0, ThrottleOverflow::Queue)),
+            Err(ThrottleError::InvalidBurst)
+        );
+        assert!(rules.list().is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::batch_manager::{BatchManager, BatchStatus, TighteningInfo};
 use serde::Serialize;
+use std::collections::BTreeMap;
 
 /// Operating mode for tightening operations
 #[derive(Debug, Clone, Serialize)]
@@ -15,25 +16,110 @@ pub enum TighteningMode {
 }
 
 /// Tracks tightening operations across both single and batch modes
+///
+/// Batches are configured per parameter set (MID 0019 carries the pset ID).
+/// `mode` always belongs to the active pset; batches of the other psets are
+/// parked in `pset_batches` and restored when their pset is selected again.
 #[derive(Debug, Clone, Serialize)]
 pub struct TighteningTracker {
     mode: TighteningMode,
-    tightening_sequence: u32, // Global counter across all modes
+    active_pset: u32,
+    pset_batches: BTreeMap<u32, BatchManager>, // Batches of inactive psets
+    tightening_sequence: u32,                  // Global counter across all modes
+    auto_reset_batch: bool,                    // Applied to newly configured batches
+    lock_at_batch_done: bool,                  // Disable the tool when a batch completes
 }
 
 impl TighteningTracker {
-    /// Create new tracker in single mode (default)
+    /// Create new tracker in single mode (default) with pset 1 active
     pub fn new() -> Self {
         Self {
             mode: TighteningMode::Single,
+            active_pset: 1,
+            pset_batches: BTreeMap::new(),
             tightening_sequence: 0,
+            auto_reset_batch: false,
+            lock_at_batch_done: false,
         }
     }
 
-    /// Enable batch mode with specified size (triggered by MID 0019)
+    /// Whether completed batches restart automatically (`[defaults] auto_reset_batch`)
+    pub fn auto_reset_batch(&self) -> bool {
+        self.auto_reset_batch
+    }
+
+    /// Whether the tool is locked when a batch completes (`[defaults] lock_at_batch_done`)
+    pub fn lock_at_batch_done(&self) -> bool {
+        self.lock_at_batch_done
+    }
+
+    pub fn set_lock_at_batch_done(&mut self, lock: bool) {
+        self.lock_at_batch_done = lock;
+    }
+
+    /// Set the auto-reset behavior for new and existing batches
+    pub fn set_auto_reset_batch(&mut self, auto_reset: bool) {
+        self.auto_reset_batch = auto_reset;
+        if let TighteningMode::Batch(batch) = &mut self.mode {
+            batch.set_auto_reset(auto_reset);
+        }
+        for batch in self.pset_batches.values_mut() {
+            batch.set_auto_reset(auto_reset);
+        }
+    }
+
+    /// Enable batch mode with specified size for the active pset
     /// Always resets batch state - MID 0019 = "start new batch"
     pub fn enable_batch(&mut self, size: u32) {
-        self.mode = TighteningMode::Batch(BatchManager::new(size));
+        self.mode =
+            TighteningMode::Batch(BatchManager::with_auto_reset(size, self.auto_reset_batch));
+    }
+
+    /// Configure the batch size of a specific pset (MID 0019)
+    ///
+    /// If the pset is active the batch starts immediately, otherwise it is
+    /// stored and becomes active when the pset is selected.
+    pub fn enable_batch_for_pset(&mut self, pset_id: u32, size: u32) {
+        if pset_id == self.active_pset {
+            self.enable_batch(size);
+        } else {
+            self.pset_batches.insert(
+                pset_id,
+                BatchManager::with_auto_reset(size, self.auto_reset_batch),
+            );
+        }
+    }
+
+    /// Switch to another pset, swapping in its batch (or single mode if none)
+    pub fn select_pset(&mut self, pset_id: u32) {
+        if pset_id == self.active_pset {
+            return;
+        }
+
+        let next_mode = match self.pset_batches.remove(&pset_id) {
+            Some(batch) => TighteningMode::Batch(batch),
+            None => TighteningMode::Single,
+        };
+        if let TighteningMode::Batch(previous) = std::mem::replace(&mut self.mode, next_mode) {
+            self.pset_batches.insert(self.active_pset, previous);
+        }
+        self.active_pset = pset_id;
+    }
+
+    /// Pset whose batch is currently active
+    pub fn active_pset(&self) -> u32 {
+        self.active_pset
+    }
+
+    /// Configured batch size for a pset (0 when the pset has no batch)
+    pub fn batch_size_for_pset(&self, pset_id: u32) -> u32 {
+        if pset_id == self.active_pset {
+            return self.batch_size();
+        }
+        self.pset_batches
+            .get(&pset_id)
+            .map(|batch| batch.target_size())
+            .unwrap_or(0)
     }
 
     /// Check if in batch mode
@@ -89,11 +175,11 @@ impl TighteningTracker {
 
     /// Check if should wait for new batch configuration
     /// Returns false in single mode (never waits, integrator controls via tool enable/disable)
-    /// Returns true in batch mode when batch is complete
+    /// Returns true in batch mode when batch is complete and auto-reset is off
     pub fn should_wait_for_config(&self) -> bool {
         match &self.mode {
             TighteningMode::Single => false, // Never wait in single mode
-            TighteningMode::Batch(batch) => batch.is_complete(),
+            TighteningMode::Batch(batch) => batch.is_waiting_for_config(),
         }
     }
 
@@ -102,9 +188,23 @@ impl TighteningTracker {
     pub fn remaining_work(&self) -> Option<u32> {
         match &self.mode {
             TighteningMode::Single => None, // No concept of "remaining" in single mode
-            TighteningMode::Batch(batch) => {
-                Some(batch.target_size().saturating_sub(batch.counter()))
-            }
+            TighteningMode::Batch(batch) => Some(batch.remaining()),
+        }
+    }
+
+    /// Consecutive NOK tightenings at the current batch position
+    /// Always 0 in single mode (no positions to retry)
+    pub fn consecutive_nok(&self) -> u32 {
+        match &self.mode {
+            TighteningMode::Single => 0,
+            TighteningMode::Batch(batch) => batch.consecutive_nok(),
+        }
+    }
+
+    /// Clear the consecutive NOK count of the active batch
+    pub fn clear_consecutive_nok(&mut self) {
+        if let TighteningMode::Batch(batch) = &mut self.mode {
+            batch.clear_consecutive_nok();
         }
     }
 
@@ -116,6 +216,14 @@ impl TighteningTracker {
         }
     }
 
+    /// Reserve a block of tightening IDs for results generated outside the
+    /// tracker (synthetic generators). Returns the first reserved ID.
+    pub fn reserve_tightening_ids(&mut self, count: u32) -> u32 {
+        let first = self.tightening_sequence + 1;
+        self.tightening_sequence += count;
+        first
+    }
+
     /// Get global tightening sequence number
     ///
     /// Global counter query for statistics and reporting.
@@ -126,6 +234,11 @@ impl TighteningTracker {
         self.tightening_sequence
     }
 
+    /// Continue the sequence after `last` (restored counters, test setup)
+    pub fn set_tightening_sequence(&mut self, last: u32) {
+        self.tightening_sequence = last;
+    }
+
     /// Increment the batch counter without a tightening result (MID 0128).
     /// Used to skip a bolt position (e.g., after max retries on integrator side).
     /// Returns the new counter value, or 0 if not in batch mode.
@@ -285,6 +398,62 @@ mod tests {
         assert_eq!(tracker.remaining_work(), None);
     }
 
+    #[test]
+    fn test_batch_size_for_inactive_pset_is_parked() {
+        let mut tracker = TighteningTracker::new();
+        tracker.enable_batch_for_pset(2, 6);
+
+        // Active pset 1 is still in single mode
+        assert!(!tracker.is_batch_mode());
+        assert_eq!(tracker.batch_size_for_pset(2), 6);
+
+        tracker.select_pset(2);
+        assert!(tracker.is_batch_mode());
+        assert_eq!(tracker.batch_size(), 6);
+        assert_eq!(tracker.active_pset(), 2);
+    }
+
+    #[test]
+    fn test_pset_switch_preserves_batch_progress() {
+        let mut tracker = TighteningTracker::new();
+        tracker.enable_batch_for_pset(1, 4);
+        tracker.enable_batch_for_pset(2, 2);
+
+        tracker.add_tightening(true);
+        tracker.add_tightening(true);
+        assert_eq!(tracker.counter(), 2);
+
+        tracker.select_pset(2);
+        assert_eq!(tracker.batch_size(), 2);
+        assert_eq!(tracker.counter(), 0);
+        tracker.add_tightening(true);
+
+        tracker.select_pset(1);
+        assert_eq!(tracker.batch_size(), 4);
+        assert_eq!(tracker.counter(), 2);
+
+        tracker.select_pset(3);
+        assert!(!tracker.is_batch_mode());
+        assert_eq!(tracker.batch_size(), 0);
+    }
+
+    #[test]
+    fn test_auto_reset_batch_never_waits() {
+        let mut tracker = TighteningTracker::new();
+        tracker.set_auto_reset_batch(true);
+        tracker.enable_batch(2);
+
+        tracker.add_tightening(true);
+        let info = tracker.add_tightening(true);
+        assert_eq!(info.batch_status, BatchStatus::CompletedOk);
+        assert!(tracker.is_complete());
+        assert!(!tracker.should_wait_for_config());
+        assert_eq!(tracker.remaining_work(), Some(2));
+
+        let info = tracker.add_tightening(true);
+        assert_eq!(info.counter, 1);
+    }
+
     #[test]
     fn test_batch_completion_signals_wait() {
         let mut tracker = TighteningTracker::new();
@@ -0,0 +1,246 @@
+//! Controller user data (MID 0240-0245)
+//!
+//! Integrations often stash small pieces of station metadata on the
+//! controller and read them back later. The simulator keeps them as text
+//! blobs in numbered slots: MID 0240 writes slot 0, MID 0245 writes the slot
+//! given by its offset parameter, and clients subscribed with MID 0241 get
+//! every stored slot as MID 0242 right away and again whenever a slot
+//! changes. Slots are kept in the SQLite database (`sqlite` feature) and
+//! restored when the simulator starts.
+
+use crate::context::AppContext;
+use crate::persistence::{self, Persister};
+use crate::state::DeviceState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteUserDataStore, create_sqlite_user_data_store};
+
+/// How often changed slots are saved
+pub const SAVE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Highest slot number (the MID 0245 offset field has three digits)
+pub const MAX_SLOT: u16 = 999;
+
+/// Longest blob a slot holds, in characters
+pub const MAX_DATA_LEN: usize = 200;
+
+/// One stored slot, as listed by `GET /user-data`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserDataSlot {
+    pub slot: u16,
+    pub data: String,
+}
+
+/// User data held by the controller
+#[derive(Debug, Clone, Default)]
+pub struct UserData {
+    slots: BTreeMap<u16, String>,
+    /// Bumped on every change, so persistence can tell when to save
+    revision: u64,
+}
+
+impl UserData {
+    /// Check a write before it is applied
+    pub fn validate(slot: u16, data: &str) -> Result<(), String> {
+        if slot > MAX_SLOT {
+            return Err(format!("slot must be at most {}", MAX_SLOT));
+        }
+        if data.chars().count() > MAX_DATA_LEN {
+            return Err(format!(
+                "user data must be at most {} characters",
+                MAX_DATA_LEN
+            ));
+        }
+        if !data.is_ascii() {
+            return Err("user data must be ASCII".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, slot: u16) -> Option<&str> {
+        self.slots.get(&slot).map(String::as_str)
+    }
+
+    /// Stored slots in ascending order
+    pub fn slots(&self) -> Vec<UserDataSlot> {
+        self.slots
+            .iter()
+            .map(|(&slot, data)| UserDataSlot {
+                slot,
+                data: data.clone(),
+            })
+            .collect()
+    }
+
+    /// Store `data` in `slot`; empty data clears the slot
+    ///
+    /// Returns whether the slot changed.
+    pub fn set(&mut self, slot: u16, data: String) -> bool {
+        let changed = if data.is_empty() {
+            self.slots.remove(&slot).is_some()
+        } else {
+            self.slots.insert(slot, data.clone()).as_ref() != Some(&data)
+        };
+        if changed {
+            self.revision += 1;
+        }
+        changed
+    }
+
+    /// Replace the slots with ones loaded from storage
+    pub fn restore(&mut self, slots: BTreeMap<u16, String>) {
+        self.slots = slots;
+        self.revision += 1;
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+/// Storage of the user data slots
+pub trait UserDataStore: Send + Sync {
+    /// Saved slots; empty when nothing was saved yet
+    fn load(&self) -> Result<BTreeMap<u16, String>, String>;
+
+    /// Save one slot; `None` deletes it
+    fn save(&mut self, slot: u16, data: Option<&str>) -> Result<(), String>;
+}
+
+/// In-memory user data store (lost on restart)
+#[derive(Debug, Default)]
+pub struct InMemoryUserDataStore {
+    slots: BTreeMap<u16, String>,
+}
+
+impl UserDataStore for InMemoryUserDataStore {
+    fn load(&self) -> Result<BTreeMap<u16, String>, String> {
+        Ok(self.slots.clone())
+    }
+
+    fn save(&mut self, slot: u16, data: Option<&str>) -> Result<(), String> {
+        match data {
+            Some(data) => self.slots.insert(slot, data.to_string()),
+            None => self.slots.remove(&slot),
+        };
+        Ok(())
+    }
+}
+
+/// Thread-safe wrapper for UserDataStore
+pub type SharedUserDataStore = Arc<RwLock<Box<dyn UserDataStore>>>;
+
+pub fn create_in_memory_user_data_store() -> SharedUserDataStore {
+    Arc::new(RwLock::new(Box::new(InMemoryUserDataStore::default())))
+}
+
+/// Save changed slots every [`SAVE_INTERVAL`]
+///
+/// Only slots that differ from the last saved copy are written, so each
+/// slot is its own row in the store.
+pub fn spawn_persistence(ctx: &AppContext) -> AbortHandle {
+    persistence::spawn_persistence(ctx, UserDataPersister::new(ctx), SAVE_INTERVAL)
+}
+
+/// Save changed slots once more on shutdown
+pub fn save_on_shutdown(ctx: &AppContext) {
+    persistence::save_changes(&mut UserDataPersister::new(ctx));
+}
+
+/// Saves the slots that differ from the stored ones
+struct UserDataPersister {
+    state: Arc<RwLock<DeviceState>>,
+    store: SharedUserDataStore,
+    saved: BTreeMap<u16, String>,
+    /// Revision of `saved`; `None` compares against the store on the first save
+    saved_revision: Option<u64>,
+}
+
+impl UserDataPersister {
+    fn new(ctx: &AppContext) -> Self {
+        Self {
+            state: Arc::clone(ctx.observable_state.state()),
+            store: Arc::clone(&ctx.user_data),
+            saved: ctx.user_data.read().unwrap().load().unwrap_or_default(),
+            saved_revision: None,
+        }
+    }
+}
+
+impl Persister for UserDataPersister {
+    const NAME: &'static str = "user data";
+
+    fn save_changes(&mut self) -> Result<(), String> {
+        let (revision, current) = {
+            let state = self.state.read().unwrap();
+            if Some(state.user_data.revision()) == self.saved_revision {
+                return Ok(());
+            }
+            (state.user_data.revision(), state.user_data.slots.clone())
+        };
+
+        let saved = &self.saved;
+        let removed = saved.keys().filter(|slot| !current.contains_key(slot));
+        let changed = current
+            .iter()
+            .filter(|(slot, data)| saved.get(slot) != Some(data));
+        let writes: Vec<(u16, Option<&str>)> = removed
+            .map(|&slot| (slot, None))
+            .chain(changed.map(|(&slot, data)| (slot, Some(data.as_str()))))
+            .collect();
+
+        let failures: Vec<String> = {
+            let mut store = self.store.write().unwrap();
+            writes
+                .into_iter()
+                .filter_map(|(slot, data)| {
+                    let result = store.save(slot, data);
+                    result.err().map(|e| format!("slot {}: {}", slot, e))
+                })
+                .collect()
+        };
+        // Everything is compared again on the next save if anything failed
+        if !failures.is_empty() {
+            return Err(failures.join("; "));
+        }
+        self.saved = current;
+        self.saved_revision = Some(revision);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_clear_slots() {
+        let mut user_data = UserData::default();
+        assert!(user_data.set(3, "STATION-7".to_string()));
+        assert!(!user_data.set(3, "STATION-7".to_string()));
+        assert!(user_data.set(0, "LINE A".to_string()));
+        let slots: Vec<u16> = user_data.slots().iter().map(|slot| slot.slot).collect();
+        assert_eq!(slots, [0, 3]);
+
+        let revision = user_data.revision();
+        assert!(user_data.set(3, String::new()));
+        assert_eq!(user_data.get(3), None);
+        assert!(user_data.revision() > revision);
+    }
+
+    #[test]
+    fn test_validate_limits() {
+        assert!(UserData::validate(MAX_SLOT, &"x".repeat(MAX_DATA_LEN)).is_ok());
+        assert!(UserData::validate(MAX_SLOT + 1, "x").is_err());
+        assert!(UserData::validate(0, &"x".repeat(MAX_DATA_LEN + 1)).is_err());
+        assert!(UserData::validate(0, "Größe").is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! SQLite persistence for the user data slots (`sqlite` feature)
+
+use super::{SharedUserDataStore, UserDataStore};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// SQLite-backed user data store, one row per slot
+pub struct SqliteUserDataStore {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteUserDataStore {
+    /// Open the user data table in the given database file
+    pub fn new(db_path: &str) -> Result<Self, String> {
+        let manager = SqliteConnectionManager::file(db_path);
+        let pool = Pool::new(manager).map_err(|e| format!("Failed to create pool: {}", e))?;
+
+        let store = Self { pool };
+        store.init_schema()?;
+        Ok(store)
+    }
+
+    fn init_schema(&self) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS user_data (
+                slot INTEGER PRIMARY KEY,
+                data TEXT NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| format!("Failed to create table: {}", e))?;
+        Ok(())
+    }
+
+    fn connection(&self) -> Result<r2d2::PooledConnection<SqliteConnectionManager>, String> {
+        self.pool
+            .get()
+            .map_err(|e| format!("Failed to get connection: {}", e))
+    }
+}
+
+impl UserDataStore for SqliteUserDataStore {
+    fn load(&self) -> Result<BTreeMap<u16, String>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare("SELECT slot, data FROM user_data ORDER BY slot")
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)? as u16, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| format!("Failed to read user data: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read user data: {}", e))
+    }
+
+    fn save(&mut self, slot: u16, data: Option<&str>) -> Result<(), String> {
+        let conn = self.connection()?;
+        match data {
+            Some(data) => conn.execute(
+                "INSERT INTO user_data (slot, data) VALUES (?1, ?2)
+                 ON CONFLICT(slot) DO UPDATE SET data = excluded.data",
+                params![slot as i64, data],
+            ),
+            None => conn.execute(
+                "DELETE FROM user_data WHERE slot = ?1",
+                params![slot as i64],
+            ),
+        }
+        .map(|_| ())
+        .map_err(|e| format!("Failed to save user data slot {}: {}", slot, e))
+    }
+}
+
+/// Open the SQLite user data store in `db_path`
+pub fn create_sqlite_user_data_store(db_path: &str) -> Result<SharedUserDataStore, String> {
+    Ok(Arc::new(RwLock::new(Box::new(SqliteUserDataStore::new(
+        db_path,
+    )?))))
+}
@@ -0,0 +1,115 @@
+//! Job sequence numbers and the history of vehicle IDs
+//!
+//! Every VIN download and every job selection starts a new job instance with
+//! the next job sequence number. Results carry the number (MID 0061 revision
+//! 2+, parameter 42), and the history maps it back to the VIN and job, so
+//! traceability clients that join results to vehicles by sequence number can
+//! be checked against `GET /vehicle-ids/history`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// VIN history entries kept in memory
+pub const VIN_HISTORY_CAPACITY: usize = 1000;
+
+/// Largest job sequence number; the next one wraps back to 1
+pub const MAX_JOB_SEQUENCE_NUMBER: u32 = 99_999;
+
+/// One job instance: a VIN download or a job selection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VinRecord {
+    pub job_sequence_number: u32,
+    /// VIN in effect for the job instance (None after a job change without VIN)
+    pub vin: Option<String>,
+    pub job_id: Option<u32>,
+    /// Local time, Open Protocol format (YYYY-MM-DD:HH:MM:SS)
+    pub timestamp: String,
+}
+
+/// Job sequence counter and bounded history of the job instances it numbered
+#[derive(Debug, Clone)]
+pub struct VinHistory {
+    entries: VecDeque<VinRecord>,
+    capacity: usize,
+    current: u32,
+}
+
+impl VinHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+            current: 0,
+        }
+    }
+
+    /// Sequence number of the current job instance (0 before the first one)
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    /// Continue numbering after `current` (restored counters, test setup)
+    pub fn set_current(&mut self, current: u32) {
+        self.current = current.min(MAX_JOB_SEQUENCE_NUMBER);
+    }
+
+    /// Start a new job instance; returns its sequence number
+    pub fn start(&mut self, vin: Option<String>, job_id: Option<u32>) -> u32 {
+        self.current = if self.current >= MAX_JOB_SEQUENCE_NUMBER {
+            1
+        } else {
+            self.current + 1
+        };
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(VinRecord {
+            job_sequence_number: self.current,
+            vin,
+            job_id,
+            timestamp: chrono::Local::now().format("%Y-%m-%d:%H:%M:%S").to_string(),
+        });
+        self.current
+    }
+
+    /// Retained job instances, oldest first
+    pub fn entries(&self) -> Vec<VinRecord> {
+        self.entries.iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for VinHistory {
+    fn default() -> Self {
+        Self::new(VIN_HISTORY_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_numbers_increase_and_wrap() {
+        let mut history = VinHistory::new(2);
+        assert_eq!(history.current(), 0);
+        assert_eq!(history.start(Some("VIN1".into()), Some(1)), 1);
+        assert_eq!(history.start(Some("VIN2".into()), Some(1)), 2);
+        assert_eq!(history.start(None, Some(2)), 3);
+
+        let entries = history.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].vin.as_deref(), Some("VIN2"));
+        assert_eq!(entries[1].job_id, Some(2));
+
+        history.current = MAX_JOB_SEQUENCE_NUMBER;
+        assert_eq!(history.start(None, None), 1);
+    }
+}
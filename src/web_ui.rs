@@ -0,0 +1,48 @@
+//! Embedded web UI
+//!
+//! A small single-page dashboard (`web/`) compiled into the binary so the
+//! simulator can be driven from a browser without running the SvelteKit
+//! frontend. It only uses the public HTTP and WebSocket endpoints.
+
+use axum::{
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "web/"]
+struct Assets;
+
+/// Handler for GET / endpoint
+///
+/// Injects a `<base>` element so the page's relative asset and API URLs
+/// resolve under `base_path` when the simulator sits behind a proxy prefix.
+pub async fn index(base_path: String) -> Response {
+    let Some(file) = Assets::get("index.html") else {
+        return (StatusCode::NOT_FOUND, "Not found").into_response();
+    };
+    let html = String::from_utf8_lossy(&file.data).replacen(
+        "<head>",
+        &format!("<head>\n  <base href=\"{}/\">", base_path),
+        1,
+    );
+    ([(header::CONTENT_TYPE, "text/html")], html).into_response()
+}
+
+/// Handler for GET /ui/{*path} endpoint (scripts, styles)
+pub async fn asset(Path(path): Path<String>) -> Response {
+    serve(&path)
+}
+
+fn serve(path: &str) -> Response {
+    match Assets::get(path) {
+        Some(file) => (
+            [(header::CONTENT_TYPE, file.metadata.mimetype().to_string())],
+            file.data,
+        )
+            .into_response(),
+        None => (StatusCode::NOT_FOUND, "Not found").into_response(),
+    }
+}
@@ -0,0 +1,347 @@
+//! Webhook sink for tightening results
+//!
+//! With `[integrations] webhook_url` set, every completed tightening (and
+//! every `MaintenanceImminent` announcement, topic `maintenance_imminent`) is
+//! put into the [outbox](crate::outbox) and a background task POSTs due
+//! entries in batches:
+//!
+//! ```json
+//! {"deliveries": [{"id": 7, "topic": "tightening_result", "attempt": 1,
+//!                  "created_at": "...", "payload": { ... }}]}
+//! ```
+//!
+//! Any 2xx answer acknowledges the whole batch. Anything else (including no
+//! answer within `timeout_ms`) schedules a retry with exponential backoff, so
+//! a consumer that processed a batch but failed to answer gets it again.
+//! Only plain `http://` URLs are supported.
+
+use crate::config::IntegrationsConfig;
+use crate::context::AppContext;
+use crate::events::SimulatorEvent;
+use crate::outbox::{AttemptOutcome, OutboxEntry, SharedOutbox};
+use chrono::Utc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::AbortHandle;
+
+/// Outbox topic of tightening results
+pub const TIGHTENING_RESULT_TOPIC: &str = "tightening_result";
+
+/// Outbox topic of voided results (the corrected result)
+pub const RESULT_VOIDED_TOPIC: &str = "result_voided";
+
+/// Outbox topic of planned restart and outage announcements
+pub const MAINTENANCE_TOPIC: &str = "maintenance_imminent";
+
+/// Error delivering to the webhook
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("unsupported webhook URL {0:?} (expected http://host[:port]/path)")]
+    InvalidUrl(String),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("no answer within {0} ms")]
+    Timeout(u64),
+    #[error("HTTP {0}")]
+    Status(u16),
+    #[error("malformed HTTP response")]
+    MalformedResponse,
+}
+
+/// Parsed `http://` webhook URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookUrl {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl WebhookUrl {
+    pub fn parse(url: &str) -> Result<Self, WebhookError> {
+        let invalid = || WebhookError::InvalidUrl(url.to_string());
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// POST a JSON body and wait for a 2xx status
+pub async fn post_json(url: &WebhookUrl, body: &[u8]) -> Result<(), WebhookError> {
+    let mut stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+    let head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        url.path,
+        url.host,
+        url.port,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line).await?;
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or(WebhookError::MalformedResponse)?;
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(WebhookError::Status(status))
+    }
+}
+
+/// Wait before the next attempt after `attempts` failed ones
+pub fn retry_delay(config: &IntegrationsConfig, attempts: u32) -> Duration {
+    let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+    Duration::from_millis(
+        config
+            .retry_base_ms
+            .saturating_mul(factor)
+            .min(config.retry_max_ms),
+    )
+}
+
+/// Request body of a batch
+fn batch_body(entries: &[OutboxEntry]) -> Vec<u8> {
+    let deliveries: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "id": entry.id,
+                "topic": entry.topic,
+                "attempt": entry.attempts + 1,
+                "created_at": entry.created_at,
+                "payload": entry.payload,
+            })
+        })
+        .collect();
+    serde_json::json!({ "deliveries": deliveries })
+        .to_string()
+        .into_bytes()
+}
+
+/// Send one batch of due entries; returns how many were acknowledged
+pub async fn deliver_due(
+    outbox: &SharedOutbox,
+    url: &WebhookUrl,
+    config: &IntegrationsConfig,
+) -> usize {
+    let due = outbox
+        .read()
+        .unwrap()
+        .due(Utc::now(), config.batch_size.max(1));
+    if due.is_empty() {
+        return 0;
+    }
+
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let sent = match tokio::time::timeout(timeout, post_json(url, &batch_body(&due))).await {
+        Ok(sent) => sent,
+        Err(_) => Err(WebhookError::Timeout(config.timeout_ms)),
+    };
+
+    let mut outbox = outbox.write().unwrap();
+    for entry in &due {
+        let outcome = match &sent {
+            Ok(()) => AttemptOutcome::Delivered,
+            Err(e) => {
+                let attempts = entry.attempts + 1;
+                if config.max_attempts > 0 && attempts >= config.max_attempts {
+                    AttemptOutcome::GaveUp {
+                        error: e.to_string(),
+                    }
+                } else {
+                    let delay = chrono::Duration::from_std(retry_delay(config, attempts))
+                        .unwrap_or(chrono::Duration::MAX);
+                    AttemptOutcome::Retry {
+                        error: e.to_string(),
+                        next_attempt_at: Utc::now() + delay,
+                    }
+                }
+            }
+        };
+        if let Err(e) = outbox.record_attempt(entry.id, outcome) {
+            eprintln!("Webhook: {}", e);
+        }
+    }
+    match sent {
+        Ok(()) => due.len(),
+        Err(e) => {
+            eprintln!("Webhook delivery of {} entries failed: {}", due.len(), e);
+            0
+        }
+    }
+}
+
+/// Queue completed tightenings and maintenance notices and deliver them to
+/// `[integrations] webhook_url`
+pub fn spawn_webhook(ctx: &AppContext) -> Vec<AbortHandle> {
+    let config = ctx.settings.integrations.clone();
+    let Some(raw_url) = config.webhook_url.clone() else {
+        return Vec::new();
+    };
+    let url = match WebhookUrl::parse(&raw_url) {
+        Ok(url) => url,
+        Err(e) => {
+            eprintln!("Webhook disabled: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let outbox = ctx.outbox.clone();
+    let mut events = ctx.events.subscribe();
+    let task = ctx.resources.track("webhook_outbox");
+    let queue = tokio::spawn(async move {
+        let _task = task;
+        loop {
+            let (topic, payload) = match events.recv().await {
+                Ok(SimulatorEvent::TighteningCompleted { result, .. }) => {
+                    (TIGHTENING_RESULT_TOPIC, serde_json::to_value(&result))
+                }
+                Ok(SimulatorEvent::ResultVoided { result }) => {
+                    (RESULT_VOIDED_TOPIC, serde_json::to_value(&result))
+                }
+                Ok(SimulatorEvent::MaintenanceImminent { notice }) => {
+                    (MAINTENANCE_TOPIC, serde_json::to_value(&notice))
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => {
+                    eprintln!("Webhook outbox missed {} events", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let payload = match payload {
+                Ok(payload) => payload,
+                Err(e) => {
+                    eprintln!("Webhook: cannot serialize {}: {}", topic, e);
+                    continue;
+                }
+            };
+            if let Err(e) = outbox.write().unwrap().enqueue(topic, payload) {
+                eprintln!("Webhook: {}", e);
+            }
+        }
+    });
+
+    let outbox = ctx.outbox.clone();
+    let task = ctx.resources.track("webhook_delivery");
+    let delivery = tokio::spawn(async move {
+        let _task = task;
+        let mut interval = tokio::time::interval(Duration::from_millis(config.poll_interval_ms));
+        loop {
+            interval.tick().await;
+            // Drain a backlog without waiting for the next tick
+            while deliver_due(&outbox, &url, &config).await == config.batch_size.max(1) {}
+        }
+    });
+    vec![queue.abort_handle(), delivery.abort_handle()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbox::{OutboxStatus, create_in_memory_outbox};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            WebhookUrl::parse("http://localhost:9000/hooks/results").unwrap(),
+            WebhookUrl {
+                host: "localhost".into(),
+                port: 9000,
+                path: "/hooks/results".into()
+            }
+        );
+        assert_eq!(WebhookUrl::parse("http://example.com").unwrap().port, 80);
+        assert!(WebhookUrl::parse("https://example.com/").is_err());
+        assert!(WebhookUrl::parse("http://host:port/").is_err());
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        let config = IntegrationsConfig {
+            retry_base_ms: 100,
+            retry_max_ms: 1000,
+            ..IntegrationsConfig::default()
+        };
+        assert_eq!(retry_delay(&config, 1), Duration::from_millis(100));
+        assert_eq!(retry_delay(&config, 3), Duration::from_millis(400));
+        assert_eq!(retry_delay(&config, 10), Duration::from_millis(1000));
+    }
+
+    /// Webhook answering each request with the next status
+    async fn spawn_webhook_server(statuses: Vec<u16>) -> WebhookUrl {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let answer = format!("HTTP/1.1 {} X\r\nContent-Length: 0\r\n\r\n", status);
+                stream.write_all(answer.as_bytes()).await.unwrap();
+            }
+        });
+        WebhookUrl {
+            host: "127.0.0.1".into(),
+            port,
+            path: "/results".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_is_retried_until_acknowledged() {
+        let url = spawn_webhook_server(vec![503, 200]).await;
+        let config = IntegrationsConfig {
+            retry_base_ms: 0,
+            ..IntegrationsConfig::default()
+        };
+        let outbox = create_in_memory_outbox();
+        for id in 1..=2 {
+            outbox
+                .write()
+                .unwrap()
+                .enqueue(TIGHTENING_RESULT_TOPIC, serde_json::json!({ "id": id }))
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(2)).await;
+
+        assert_eq!(deliver_due(&outbox, &url, &config).await, 0);
+        let pending = outbox.read().unwrap().list(Some(OutboxStatus::Pending), 10);
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].last_error.as_deref(), Some("HTTP 503"));
+
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        assert_eq!(deliver_due(&outbox, &url, &config).await, 2);
+        let delivered = outbox
+            .read()
+            .unwrap()
+            .list(Some(OutboxStatus::Delivered), 10);
+        assert_eq!(delivered.len(), 2);
+        assert!(delivered.iter().all(|e| e.attempts == 2));
+    }
+}
@@ -0,0 +1,139 @@
+//! Outbound write batching
+//!
+//! Flushing every broadcast costs a write syscall per frame, which limits
+//! subscribers consuming thousands of results per second. With
+//! `[server] flush_interval_ms` a connection queues its broadcasts in the
+//! codec write buffer and writes them together once the oldest one has
+//! waited the interval or `max_batch_frames` are queued. Replies to requests
+//! are still written at once and take the queued broadcasts with them. The
+//! counters show up per connection in `GET /connections`.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Broadcast write counters of one connection
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WriteStats {
+    /// Writes of queued broadcasts
+    pub writes: u64,
+    /// Broadcast frames written by them
+    pub frames: u64,
+    /// Mean frames per write
+    pub frames_per_write: f64,
+    pub max_frames_per_write: u64,
+    /// Mean time from queueing the first frame of a write to its completion
+    pub mean_flush_latency_us: u64,
+    pub max_flush_latency_us: u64,
+}
+
+/// Write batching state of one connection
+#[derive(Debug)]
+pub struct WriteBatcher {
+    interval: Duration,
+    max_frames: u64,
+    pending: u64,
+    oldest: Option<Instant>,
+    total_latency: Duration,
+    stats: WriteStats,
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros().try_into().unwrap_or(u64::MAX)
+}
+
+impl WriteBatcher {
+    /// Batcher flushing after `interval` (every frame when zero) or `max_frames`
+    pub fn new(interval: Duration, max_frames: usize) -> Self {
+        Self {
+            interval,
+            max_frames: (max_frames as u64).max(1),
+            pending: 0,
+            oldest: None,
+            total_latency: Duration::ZERO,
+            stats: WriteStats::default(),
+        }
+    }
+
+    /// Account for a broadcast frame queued in the write buffer at `now`
+    pub fn queued(&mut self, now: Instant) {
+        self.pending += 1;
+        self.oldest.get_or_insert(now);
+    }
+
+    /// Frames queued since the last write
+    pub fn pending(&self) -> u64 {
+        self.pending
+    }
+
+    /// Whether the queued frames must be written right away
+    pub fn flush_now(&self) -> bool {
+        self.pending > 0 && (self.interval.is_zero() || self.pending >= self.max_frames)
+    }
+
+    /// When the queued frames are due, if any are waiting
+    pub fn deadline(&self) -> Option<Instant> {
+        self.oldest.map(|oldest| oldest + self.interval)
+    }
+
+    /// Account for the queued frames having been written at `now`
+    pub fn flushed(&mut self, now: Instant) {
+        let Some(oldest) = self.oldest.take() else {
+            return;
+        };
+        let latency = now.saturating_duration_since(oldest);
+        self.total_latency += latency;
+        self.stats.writes += 1;
+        self.stats.frames += self.pending;
+        self.stats.frames_per_write = self.stats.frames as f64 / self.stats.writes as f64;
+        self.stats.max_frames_per_write = self.stats.max_frames_per_write.max(self.pending);
+        self.stats.mean_flush_latency_us = micros(self.total_latency) / self.stats.writes;
+        self.stats.max_flush_latency_us = self.stats.max_flush_latency_us.max(micros(latency));
+        self.pending = 0;
+    }
+
+    pub fn stats(&self) -> &WriteStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_until_interval_or_max_frames() {
+        let start = Instant::now();
+        let mut batcher = WriteBatcher::new(Duration::from_millis(2), 3);
+        assert_eq!(batcher.deadline(), None);
+
+        batcher.queued(start);
+        batcher.queued(start + Duration::from_micros(500));
+        assert!(!batcher.flush_now());
+        assert_eq!(batcher.deadline(), Some(start + Duration::from_millis(2)));
+        batcher.flushed(start + Duration::from_millis(2));
+
+        for _ in 0..3 {
+            batcher.queued(start + Duration::from_millis(3));
+        }
+        assert!(batcher.flush_now(), "max_frames reached");
+        batcher.flushed(start + Duration::from_millis(3));
+
+        let stats = batcher.stats();
+        assert_eq!(stats.writes, 2);
+        assert_eq!(stats.frames, 5);
+        assert_eq!(stats.frames_per_write, 2.5);
+        assert_eq!(stats.max_frames_per_write, 3);
+        assert_eq!(stats.mean_flush_latency_us, 1000);
+        assert_eq!(stats.max_flush_latency_us, 2000);
+        assert_eq!(batcher.pending(), 0);
+        assert_eq!(batcher.deadline(), None);
+    }
+
+    #[test]
+    fn test_zero_interval_flushes_every_frame() {
+        let mut batcher = WriteBatcher::new(Duration::ZERO, 64);
+        assert!(!batcher.flush_now());
+        batcher.queued(Instant::now());
+        assert!(batcher.flush_now());
+    }
+}
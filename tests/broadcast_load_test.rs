@@ -0,0 +1,214 @@
+#![cfg(feature = "http")]
+
+//! Connection handling against the simulator binary: broadcast storms, the
+//! handshake deadline and clients closing their connection
+
+mod common;
+
+use common::simulator::{Connection, Simulator, connect, get_json, receive, send, start_simulator};
+use futures_util::StreamExt;
+use open_protocol_device_simulator::protocol::Mid;
+use open_protocol_device_simulator::webhook::{WebhookUrl, post_json};
+use std::time::{Duration, Instant};
+
+/// Connect, subscribe to results and start a firehose of `rate` results per second
+async fn subscribe_to_firehose(simulator: &Simulator, rate: u32) -> Connection {
+    let mut framed = connect(simulator.tcp_port()).await;
+    send(&mut framed, Mid::COMMUNICATION_START).await;
+    assert_eq!(receive(&mut framed).await.mid, 2);
+    send(&mut framed, Mid::TIGHTENING_RESULT_SUBSCRIBE).await;
+    assert_eq!(receive(&mut framed).await.mid, 5);
+
+    let url = WebhookUrl::parse(&format!(
+        "http://127.0.0.1:{}/firehose/start",
+        simulator.http_port()
+    ))
+    .unwrap();
+    let body = format!(r#"{{"rate_per_sec": {}, "duration_ms": 3000}}"#, rate);
+    post_json(&url, body.as_bytes()).await.unwrap();
+    framed
+}
+
+/// Keep-alives stay prompt while thousands of MID 0061 pushes per second are queued
+#[tokio::test]
+async fn test_keep_alive_latency_is_bounded_under_firehose() {
+    let simulator = start_simulator("keep_alive_priority_test", "");
+    let mut framed = subscribe_to_firehose(&simulator, 5000).await;
+
+    let mut results = 0;
+    let mut worst = Duration::ZERO;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        send(&mut framed, Mid::KEEP_ALIVE).await;
+        let sent = Instant::now();
+        loop {
+            let message = receive(&mut framed).await;
+            if message.mid == Mid::KEEP_ALIVE.value() {
+                break;
+            }
+            assert_eq!(message.mid, 61);
+            results += 1;
+        }
+        worst = worst.max(sent.elapsed());
+    }
+
+    assert!(results > 1000, "only {} results were pushed", results);
+    assert!(
+        worst < Duration::from_millis(500),
+        "keep-alive took {:?} under load",
+        worst
+    );
+}
+
+/// A push delayed by the load-latency model does not hold up keep-alive replies
+#[tokio::test]
+async fn test_delayed_push_does_not_stall_the_connection() {
+    let simulator = start_simulator(
+        "delayed_push_test",
+        "\n[broadcast_latency]\nbase_ms = 1000\nmax_ms = 1000",
+    );
+    let mut framed = subscribe_to_firehose(&simulator, 5).await;
+    // Let the first result get delayed
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    send(&mut framed, Mid::KEEP_ALIVE).await;
+    let sent = Instant::now();
+    assert_eq!(receive(&mut framed).await.mid, Mid::KEEP_ALIVE.value());
+    assert!(
+        sent.elapsed() < Duration::from_millis(500),
+        "keep-alive waited {:?} behind a delayed push",
+        sent.elapsed()
+    );
+    // The delayed results still arrive
+    assert_eq!(receive(&mut framed).await.mid, 61);
+}
+
+/// A delayed keep-alive reply does not hold up other requests
+#[tokio::test]
+async fn test_delayed_keep_alive_reply_does_not_stall_requests() {
+    let simulator = start_simulator(
+        "delayed_keep_alive_test",
+        "\n[keep_alive]\nreply_delay_ms = 1000",
+    );
+    let mut framed = connect(simulator.tcp_port()).await;
+    send(&mut framed, Mid::KEEP_ALIVE).await;
+    send(&mut framed, Mid::COMMUNICATION_START).await;
+
+    let sent = Instant::now();
+    assert_eq!(receive(&mut framed).await.mid, 2);
+    assert!(sent.elapsed() < Duration::from_millis(500));
+    assert_eq!(receive(&mut framed).await.mid, Mid::KEEP_ALIVE.value());
+    assert!(sent.elapsed() >= Duration::from_millis(900));
+}
+
+/// With a flush interval, broadcasts are written several at a time
+#[tokio::test]
+async fn test_broadcasts_are_batched_with_flush_interval() {
+    let simulator = start_simulator(
+        "write_batching_test",
+        "flush_interval_ms = 5\nmax_batch_frames = 32",
+    );
+    let mut framed = subscribe_to_firehose(&simulator, 5000).await;
+
+    let mut results = 0;
+    while results < 2000 {
+        assert_eq!(receive(&mut framed).await.mid, 61);
+        results += 1;
+    }
+
+    let connections = get_json(&simulator, "/connections").await;
+    let writes = &connections["connections"][0]["writes"];
+    assert!(writes["writes"].as_u64().unwrap() > 0);
+    let frames_per_write = writes["frames_per_write"].as_f64().unwrap();
+    assert!(
+        frames_per_write > 1.0,
+        "{} frames per write",
+        frames_per_write
+    );
+    assert!(writes["max_frames_per_write"].as_u64().unwrap() <= 32);
+    assert!(writes["max_flush_latency_us"].as_u64().unwrap() > 0);
+}
+
+/// Clients that do not send MID 0001 in time are told so and dropped
+#[tokio::test]
+async fn test_handshake_deadline_drops_silent_clients() {
+    let simulator = start_simulator(
+        "handshake_deadline_test",
+        "handshake_timeout_ms = 300\nhandshake_timeout_error = true",
+    );
+    let mut silent = connect(simulator.tcp_port()).await;
+    let mut client = connect(simulator.tcp_port()).await;
+    send(&mut client, Mid::COMMUNICATION_START).await;
+    assert_eq!(receive(&mut client).await.mid, 2);
+
+    let connected = Instant::now();
+    let error = receive(&mut silent).await;
+    assert_eq!(error.mid, 4);
+    assert_eq!(&error.data[..], b"000198");
+    let closed = tokio::time::timeout(Duration::from_secs(5), silent.next())
+        .await
+        .expect("the silent client should be disconnected");
+    assert!(closed.is_none(), "closed, got {:?}", closed);
+    assert!(connected.elapsed() < Duration::from_secs(2));
+
+    // The client that completed the handshake stays connected past the deadline
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    send(&mut client, Mid::KEEP_ALIVE).await;
+    assert_eq!(receive(&mut client).await.mid, 9999);
+}
+
+/// Poll `GET /connections` until it lists `count` sessions
+async fn wait_for_connections(simulator: &Simulator, count: u64) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let connections = get_json(simulator, "/connections").await;
+        if connections["count"] == count {
+            return;
+        }
+        assert!(Instant::now() < deadline, "connections: {}", connections);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// A client closing its socket frees its session and its connection slot
+#[tokio::test]
+async fn test_clean_close_removes_the_session() {
+    let simulator = start_simulator("clean_close_test", "max_connections = 1");
+    for _ in 0..3 {
+        let mut client = connect(simulator.tcp_port()).await;
+        send(&mut client, Mid::COMMUNICATION_START).await;
+        assert_eq!(receive(&mut client).await.mid, 2);
+        wait_for_connections(&simulator, 1).await;
+
+        drop(client);
+        wait_for_connections(&simulator, 0).await;
+    }
+}
+
+/// A plain socket close is reported as `client_closed` and counted
+#[tokio::test]
+async fn test_clean_close_is_reported_as_client_closed() {
+    let simulator = start_simulator("client_closed_event_test", "");
+    let mut client = connect(simulator.tcp_port()).await;
+    send(&mut client, Mid::COMMUNICATION_START).await;
+    assert_eq!(receive(&mut client).await.mid, 2);
+
+    let next_disconnect = get_json(
+        &simulator,
+        "/events/next?type=ClientDisconnected&timeout_ms=5000",
+    );
+    let close = async {
+        // Let the long poll subscribe before the connection ends
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        drop(client);
+    };
+    let (event, ()) = tokio::join!(next_disconnect, close);
+    assert_eq!(event["type"], "ClientDisconnected");
+    assert_eq!(event["reason"], "client_closed");
+
+    let resources = get_json(&simulator, "/debug/resources").await;
+    assert_eq!(
+        resources["connections"]["lifecycle"]["disconnected"]["client_closed"],
+        1
+    );
+}
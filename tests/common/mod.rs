@@ -1,4 +1,5 @@
 pub mod protocol_helpers;
+pub mod simulator;
 
 // Test infrastructure module
 //
@@ -0,0 +1,158 @@
+// Harness for tests that run the simulator binary
+//
+// Each simulator gets its own directory (config, database, stats) under the
+// temp dir and free ports; it is killed and the directory removed on drop.
+#![allow(dead_code)]
+
+use futures_util::{SinkExt, StreamExt};
+use open_protocol_device_simulator::codec::null_delimited_codec::NullDelimitedCodec;
+use open_protocol_device_simulator::protocol::{Message, Mid, parser, serializer};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+
+pub type Connection = Framed<TcpStream, NullDelimitedCodec>;
+
+pub fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Test directory holding a `config.toml` for the simulator
+pub struct SimulatorConfig {
+    pub dir: PathBuf,
+    pub tcp_port: u16,
+    pub http_port: u16,
+}
+
+impl SimulatorConfig {
+    /// Fresh directory `name` with `[server]` settings added to the test ports
+    pub fn new(name: &str, server: &str) -> Self {
+        let (tcp_port, http_port) = (free_port(), free_port());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = format!(
+            r#"
+[server]
+bind_address = "127.0.0.1"
+tcp_port = {tcp_port}
+http_port = {http_port}
+{server}
+
+[database]
+path = "{db}"
+
+[stats]
+lifetime_path = "{stats}"
+"#,
+            db = dir.join("simulator.db").display(),
+            stats = dir.join("lifetime_stats.json").display(),
+        );
+        std::fs::write(dir.join("config.toml"), config).unwrap();
+        Self {
+            dir,
+            tcp_port,
+            http_port,
+        }
+    }
+
+    /// The simulator binary, run in the test directory
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_open-protocol-device-simulator"));
+        command
+            .current_dir(&self.dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    }
+
+    /// Run the simulator on this configuration
+    pub fn start(self) -> Simulator {
+        let child = self
+            .command()
+            .arg("--config")
+            .arg(self.dir.join("config.toml"))
+            .spawn()
+            .expect("simulator should start");
+        Simulator {
+            child,
+            config: self,
+        }
+    }
+}
+
+/// Simulator process, killed when the test ends
+pub struct Simulator {
+    child: Child,
+    pub config: SimulatorConfig,
+}
+
+impl Simulator {
+    pub fn tcp_port(&self) -> u16 {
+        self.config.tcp_port
+    }
+
+    pub fn http_port(&self) -> u16 {
+        self.config.http_port
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.config.dir);
+    }
+}
+
+/// Start the simulator with `[server]` settings added to the test ports
+pub fn start_simulator(name: &str, server: &str) -> Simulator {
+    SimulatorConfig::new(name, server).start()
+}
+
+pub async fn connect(tcp_port: u16) -> Connection {
+    for _ in 0..100 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", tcp_port)).await {
+            return Framed::new(stream, NullDelimitedCodec::new());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("simulator did not listen on port {}", tcp_port);
+}
+
+pub async fn send(framed: &mut Connection, mid: Mid) {
+    let frame = serializer::serialize_frame(mid.value(), 1, &[]);
+    framed.send(frame.as_slice().into()).await.unwrap();
+}
+
+pub async fn receive(framed: &mut Connection) -> Message {
+    let frame = tokio::time::timeout(Duration::from_secs(5), framed.next())
+        .await
+        .expect("simulator should answer")
+        .expect("connection should stay open")
+        .unwrap();
+    parser::parse_message(&frame).unwrap()
+}
+
+/// GET a JSON endpoint of the simulator
+pub async fn get_json(simulator: &Simulator, path: &str) -> serde_json::Value {
+    let mut stream = TcpStream::connect(("127.0.0.1", simulator.http_port()))
+        .await
+        .unwrap();
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n",
+        path
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    serde_json::from_str(body).unwrap()
+}
@@ -0,0 +1,75 @@
+#![cfg(unix)]
+
+//! Daemon mode and graceful shutdown, against the simulator binary
+
+mod common;
+
+use common::simulator::SimulatorConfig;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+/// Poll `condition` every 50 ms for up to ten seconds
+async fn wait_for(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+fn read(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_daemon_shuts_down_gracefully_on_sigterm() {
+    let config = SimulatorConfig::new("daemon_test", "");
+    let (dir, tcp_port) = (config.dir.clone(), config.tcp_port);
+    let (pid_path, log_path) = (dir.join("simulator.pid"), dir.join("simulator.log"));
+
+    // The launching process returns at once, leaving the daemon behind
+    let status = config
+        .command()
+        .args(["--config", "config.toml", "--daemon"])
+        .args(["--pid-file", "simulator.pid", "--log-file", "simulator.log"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    wait_for("the pid file", || read(&pid_path).ends_with('\n')).await;
+    let pid: libc::pid_t = read(&pid_path).trim().parse().unwrap();
+    wait_for("the TCP listener", || {
+        read(&log_path).contains("Open Protocol TCP server listening")
+    })
+    .await;
+    let mut client = TcpStream::connect(("127.0.0.1", tcp_port)).await.unwrap();
+    wait_for("the connection", || {
+        read(&log_path).contains("Incoming connection")
+    })
+    .await;
+
+    // SAFETY: pid names the daemon started above
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGTERM) }, 0);
+
+    let mut buf = [0u8; 64];
+    let read_result = tokio::time::timeout(Duration::from_secs(10), client.read(&mut buf))
+        .await
+        .expect("the client should be disconnected");
+    assert!(
+        matches!(read_result, Ok(0)),
+        "closed, got {:?}",
+        read_result
+    );
+    wait_for("the pid file to be removed", || !pid_path.exists()).await;
+    let log = read(&log_path);
+    assert!(
+        log.contains("Shutting down: closing 1 client connection(s)"),
+        "{}",
+        log
+    );
+    assert!(log.contains("Simulator stopped"), "{}", log);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,54 @@
+use open_protocol_device_simulator::testing::golden;
+use open_protocol_device_simulator::{
+    DeviceState, ObservableState, SimulatorEvent, handler, job, pset,
+};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Default handlers on a freshly started controller
+fn registry() -> handler::HandlerRegistry {
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    handler::create_default_registry(
+        ObservableState::new(state, broadcaster),
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    )
+}
+
+/// Test every fixture against its golden response (UPDATE_GOLDEN=1 rewrites them)
+#[test]
+fn test_golden_responses() {
+    let dir = golden_dir();
+    let failures: Vec<String> = golden::fixtures(&dir)
+        .unwrap()
+        .into_iter()
+        .filter_map(|name| {
+            golden::check_fixture(&registry(), &dir, &name)
+                .err()
+                .map(|e| format!("{name}: {e}"))
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// Test that every handled MID has a fixture named after it
+#[test]
+fn test_every_handled_mid_has_a_fixture() {
+    let fixtures = golden::fixtures(&golden_dir()).unwrap();
+    let missing: Vec<String> = registry()
+        .mids()
+        .into_iter()
+        .map(|mid| format!("mid_{:04}", mid.value()))
+        .filter(|prefix| {
+            !fixtures
+                .iter()
+                .any(|name| name.starts_with(prefix.as_str()))
+        })
+        .collect();
+    assert!(missing.is_empty(), "no fixture for {:?}", missing);
+}
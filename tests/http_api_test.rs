@@ -93,35 +93,19 @@ async fn test_tightening_simulation() {
     assert_eq!(counter, 1);
 
     // Create and broadcast a tightening result event
-    let result = TighteningResult {
-        cell_id: 1,
-        channel_id: 1,
-        controller_name: "Test".to_string(),
-        vin_number: None,
-        job_id: 1,
-        pset_id: 1,
-        batch_size: 5,
-        batch_counter: tightening_info.counter,
-        tightening_status: true,
-        torque_status: true,
-        angle_status: true,
-        torque_min: 10.0,
-        torque_max: 15.0,
-        torque_target: 12.5,
-        torque: 12.5,
-        angle_min: 30.0,
-        angle_max: 50.0,
-        angle_target: 40.0,
-        angle: 40.0,
-        timestamp: "2024-01-01 12:00:00".to_string(),
-        last_pset_change: None,
-        batch_status: None,
-        tightening_id: Some(tightening_info.tightening_id),
+    let result = {
+        let s = state.read().unwrap();
+        TighteningResult::builder()
+            .device(&s)
+            .tightening(&tightening_info)
+            .try_build()
+            .unwrap()
     };
 
     // Broadcast the tightening completed event
     let _ = broadcaster.send(SimulatorEvent::TighteningCompleted {
         result: result.clone(),
+        delivery: None,
     });
 
     // Verify event was received
@@ -129,6 +113,7 @@ async fn test_tightening_simulation() {
     match event {
         SimulatorEvent::TighteningCompleted {
             result: received_result,
+            ..
         } => {
             assert_eq!(received_result.batch_counter, 1);
             assert_eq!(received_result.tightening_id, Some(1));
@@ -223,12 +208,17 @@ async fn test_vehicle_id_management() {
         s.vehicle_id.clone().unwrap()
     };
 
-    let _ = broadcaster.send(SimulatorEvent::VehicleIdChanged { vin: vin.clone() });
+    let _ = broadcaster.send(SimulatorEvent::VehicleIdChanged {
+        vin: vin.clone(),
+        cycle_id: 1,
+    });
 
     // Verify event was broadcast
     let event = receiver.recv().await.unwrap();
     match event {
-        SimulatorEvent::VehicleIdChanged { vin: received_vin } => {
+        SimulatorEvent::VehicleIdChanged {
+            vin: received_vin, ..
+        } => {
             assert_eq!(received_vin, "TEST12345");
         }
         _ => panic!("Expected VehicleIdChanged event"),
@@ -244,35 +234,17 @@ async fn test_event_broadcasting() {
     let mut receiver2 = broadcaster.subscribe();
 
     // Create a mock tightening result
-    let result = TighteningResult {
-        cell_id: 1,
-        channel_id: 1,
-        controller_name: "Test".to_string(),
-        vin_number: None,
-        job_id: 1,
-        pset_id: 1,
-        batch_size: 1,
-        batch_counter: 1,
-        tightening_status: true,
-        torque_status: true,
-        angle_status: true,
-        torque_min: 10.0,
-        torque_max: 15.0,
-        torque_target: 12.5,
-        torque: 12.5,
-        angle_min: 30.0,
-        angle_max: 50.0,
-        angle_target: 40.0,
-        angle: 40.0,
-        timestamp: "2024-01-01 12:00:00".to_string(),
-        last_pset_change: None,
-        batch_status: None,
-        tightening_id: Some(1),
-    };
+    let result = TighteningResult::builder()
+        .batch_size(1)
+        .batch_counter(1)
+        .tightening_id(1)
+        .try_build()
+        .unwrap();
 
     // Broadcast event
     let _ = broadcaster.send(SimulatorEvent::TighteningCompleted {
         result: result.clone(),
+        delivery: None,
     });
 
     // Both subscribers should receive it
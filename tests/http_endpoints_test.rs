@@ -1,3 +1,5 @@
+#![cfg(feature = "http")]
+
 mod common;
 
 use axum::{
@@ -13,14 +15,17 @@ use tower::ServiceExt;
 #[tokio::test]
 async fn test_get_state_endpoint() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
     };
 
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
 
-    let app = http_server::create_router(observable_state, config::Settings::default());
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
 
     let response = app
         .oneshot(
@@ -45,341 +50,701 @@ async fn test_get_state_endpoint() {
     assert_eq!(state_json["tool_enabled"], true);
 }
 
-/// Test POST /simulate/tightening endpoint
+/// Test GET /state change detection via version and ETag
 #[tokio::test]
-async fn test_simulate_tightening_endpoint() {
+async fn test_get_state_unchanged_since_version() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
     };
 
     let state = Arc::new(RwLock::new(DeviceState::new()));
-    {
-        let mut s = state.write().unwrap();
-        s.set_batch_size(5); // Enable batch mode for counter tracking
-    }
-
-    let (broadcaster, _receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let app = http_server::create_router(observable_state, config::Settings::default());
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state.clone(),
+        config::Settings::default(),
+    ));
+    let get = |uri: &str, etag: Option<&str>| {
+        let mut request = Request::builder().uri(uri);
+        if let Some(etag) = etag {
+            request = request.header("If-None-Match", etag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
 
-    let payload = json!({
-        "torque": 12.5,
-        "angle": 40.0,
-        "ok": true
-    });
+    let response = get("/state", None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let version = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["version"]
+        .as_u64()
+        .unwrap();
+    assert_eq!(etag, format!("\"{}\"", version));
 
-    let response = app
-        .oneshot(
-            Request::builder()
-                .uri("/simulate/tightening")
-                .method("POST")
-                .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
-                .unwrap(),
-        )
+    let response = get(&format!("/state?if_version_gt={}", version), None)
         .await
         .unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
-
-    // Parse response
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
     let body = response.into_body().collect().await.unwrap().to_bytes();
-    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(body.is_empty());
+    let response = get("/state", Some(&etag)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
 
-    assert_eq!(result["success"], true);
-    assert_eq!(result["batch_counter"], 1);
+    observable_state.disable_tool();
+    let response = get(&format!("/state?if_version_gt={}", version), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()["etag"], etag.as_str());
+    let response = get("/state", Some(&etag)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 }
 
-/// Test POST /simulate/tightening endpoint when tool is disabled
+/// Test PATCH /state applies writable fields and broadcasts per field
 #[tokio::test]
-async fn test_simulate_tightening_endpoint_rejects_when_tool_disabled() {
+async fn test_patch_state_endpoint() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
     };
 
     let state = Arc::new(RwLock::new(DeviceState::new()));
-    {
-        let mut s = state.write().unwrap();
-        s.set_batch_size(5);
-        s.disable_tool();
-    }
-
+    state.write().unwrap().set_vehicle_id("OLDVIN".to_string());
     let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
-    let _keepalive_sender = broadcaster.clone();
     let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let app = http_server::create_router(observable_state, config::Settings::default());
-
-    let payload = json!({
-        "torque": 12.5,
-        "angle": 40.0,
-        "ok": true
-    });
-
-    let response = app
-        .oneshot(
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+    let patch = |body: serde_json::Value| {
+        app.clone().oneshot(
             Request::builder()
-                .uri("/simulate/tightening")
-                .method("POST")
+                .uri("/state")
+                .method("PATCH")
                 .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
                 .unwrap(),
         )
-        .await
-        .unwrap();
-
-    assert_eq!(response.status(), StatusCode::CONFLICT);
+    };
 
+    let response = patch(json!({
+        "cell_id": 12,
+        "tool_enabled": false,
+        "vehicle_id": null,
+        "current_job_id": 7
+    }))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
     let body = response.into_body().collect().await.unwrap().to_bytes();
     let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        result["changed"],
+        json!(["cell_id", "tool_enabled", "vehicle_id", "current_job_id"])
+    );
+    {
+        let s = state.read().unwrap();
+        assert_eq!(s.cell_id, 12);
+        assert!(!s.tool_enabled);
+        assert_eq!(s.vehicle_id, None);
+        assert_eq!(s.current_job_id, Some(7));
+        assert_eq!(s.controller_name, "OpenProtocolSimulator");
+    }
 
-    assert_eq!(result["success"], false);
-    assert!(result["message"]
-        .as_str()
-        .unwrap()
-        .contains("tool is disabled"));
-
-    let s = state.read().unwrap();
-    assert_eq!(s.tightening_tracker.counter(), 0);
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        events.push(serde_json::to_value(event).unwrap());
+    }
+    assert_eq!(events.len(), 4);
+    assert_eq!(events[0]["type"], "IdentityChanged");
+    assert_eq!(events[0]["identity"]["cell_id"], 12);
+    assert_eq!(
+        events[1],
+        json!({"type": "ToolStateChanged", "enabled": false})
+    );
+    assert_eq!(events[2]["value"], serde_json::Value::Null);
 
+    // Unchanged values are no change
+    let response = patch(json!({"cell_id": 12, "vehicle_id": "VIN1"}))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(result["changed"], json!(["vehicle_id"]));
     assert!(matches!(
         receiver.try_recv(),
-        Err(tokio::sync::broadcast::error::TryRecvError::Empty)
+        Ok(SimulatorEvent::VehicleIdChanged { vin, .. }) if vin == "VIN1"
     ));
+
+    for invalid in [
+        json!({"channel_id": 100}),
+        json!({"supplier_code": "TOOLONG"}),
+        json!({"version": 1}),
+        json!({"tool_enabled": "yes"}),
+    ] {
+        let response = patch(invalid.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{invalid}");
+    }
+    assert_eq!(state.read().unwrap().channel_id, 1);
 }
 
-/// Test POST /auto-tightening/start endpoint
+/// Test PATCH /identity reaches MID 0002 and new results
 #[tokio::test]
-async fn test_start_auto_tightening_endpoint() {
+async fn test_patch_identity_endpoint() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, handler, http_server,
+        job, protocol, pset,
     };
 
     let state = Arc::new(RwLock::new(DeviceState::new()));
-    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let app = http_server::create_router(observable_state, config::Settings::default());
-
-    let payload = json!({
-        "interval_ms": 1000,
-        "duration_ms": 100,
-        "failure_rate": 0.0
-    });
+    let registry = handler::create_default_registry(
+        observable_state.clone(),
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state.clone(),
+        config::Settings::default(),
+    ));
 
     let response = app
+        .clone()
         .oneshot(
             Request::builder()
-                .uri("/auto-tightening/start")
-                .method("POST")
+                .uri("/identity")
+                .method("PATCH")
                 .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .body(Body::from(
+                    json!({"cell_id": 42, "channel_id": 3, "controller_name": "Station 7", "supplier_code": "ACM"})
+                        .to_string(),
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
-
     assert_eq!(response.status(), StatusCode::OK);
-
-    // Parse response
     let body = response.into_body().collect().await.unwrap().to_bytes();
     let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(result["identity"]["controller_name"], "Station 7");
 
-    assert_eq!(result["success"], true);
-    assert!(result["message"].as_str().unwrap().contains("started"));
-}
-
-/// Test POST /auto-tightening/start conflict (already running)
-#[tokio::test]
-async fn test_start_auto_tightening_conflict() {
-    use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
-    };
-    use std::sync::atomic::AtomicBool;
-
-    let state = Arc::new(RwLock::new(DeviceState::new()));
-    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
-    let observable_state = ObservableState::new(state, broadcaster);
+    match receiver.try_recv().unwrap() {
+        SimulatorEvent::IdentityChanged { identity } => {
+            assert_eq!(identity.cell_id, 42);
+            assert_eq!(identity.supplier_code, "ACM");
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+    assert!(
+        receiver.try_recv().is_err(),
+        "one event for the whole identity"
+    );
 
-    // Create server state with auto-tightening already active
-    let pset_repository = open_protocol_device_simulator::pset::create_default_repository();
-    let server_state = http_server::ServerState {
-        observable_state,
-        auto_tightening_active: Arc::new(AtomicBool::new(true)), // Already running
-        pset_repository,
-        settings: config::Settings::default(),
+    let message = protocol::Message {
+        length: 20,
+        mid: 1,
+        revision: 1,
+        data: vec![],
+        header: Default::default(),
     };
-
-    let app = axum::Router::new()
-        .route(
-            "/auto-tightening/start",
-            axum::routing::post(
-                |_state: axum::extract::State<http_server::ServerState>,
-                 _payload: axum::Json<serde_json::Value>| async {
-                    (
-                        StatusCode::CONFLICT,
-                        axum::Json(json!({"success": false, "message": "Already running"})),
-                    )
-                },
-            ),
-        )
-        .with_state(server_state);
-
-    let payload = json!({
-        "interval_ms": 1000,
-        "duration_ms": 100
-    });
+    let ack = String::from_utf8(registry.handle_message(&message).unwrap().data).unwrap();
+    assert!(ack.starts_with("010042020303Station 7"), "{ack}");
 
     let response = app
+        .clone()
         .oneshot(
             Request::builder()
-                .uri("/auto-tightening/start")
+                .uri("/simulate/tightening")
                 .method("POST")
                 .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .body(Body::from("{}"))
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let result = std::iter::from_fn(|| receiver.try_recv().ok())
+        .find_map(|event| match event {
+            SimulatorEvent::TighteningCompleted { result, .. } => Some(result),
+            _ => None,
+        })
+        .expect("tightening result broadcast");
+    assert_eq!(result.cell_id, 42);
+    assert_eq!(result.controller_name, "Station 7");
 
-    assert_eq!(response.status(), StatusCode::CONFLICT);
+    // Only identity fields
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/identity")
+                .method("PATCH")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"tool_enabled": false}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 }
 
-/// Test POST /auto-tightening/stop endpoint
+/// Test GET /vehicle-ids/history numbers job instances
 #[tokio::test]
-async fn test_stop_auto_tightening_endpoint() {
+async fn test_vin_history_endpoint() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
     };
 
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
-    let observable_state = ObservableState::new(state, broadcaster);
-    let app = http_server::create_router(observable_state, config::Settings::default());
+    let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+    for patch in [
+        json!({"vehicle_id": "VIN1"}),
+        json!({"vehicle_id": "VIN2"}),
+        json!({"current_job_id": 5}),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/state")
+                    .method("PATCH")
+                    .header("content-type", "application/json")
+                    .body(Body::from(patch.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
 
     let response = app
         .oneshot(
             Request::builder()
-                .uri("/auto-tightening/stop")
-                .method("POST")
+                .uri("/vehicle-ids/history?limit=2")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
-
-    // Parse response
     let body = response.into_body().collect().await.unwrap().to_bytes();
-    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
-
-    assert_eq!(result["success"], true);
+    let history: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(history["job_sequence_number"], 3);
+    assert_eq!(history["count"], 2);
+    assert_eq!(history["history"][0]["vin"], "VIN2");
+    assert_eq!(history["history"][0]["job_sequence_number"], 2);
+    assert_eq!(history["history"][1]["vin"], "VIN2");
+    assert_eq!(history["history"][1]["job_id"], 5);
+    assert_eq!(state.read().unwrap().job_sequence_number, 3);
 }
 
-/// Test GET /auto-tightening/status endpoint
+/// Test GET /psets/{id}/history and the last-change date of MID 0015
 #[tokio::test]
-async fn test_get_auto_tightening_status_endpoint() {
+async fn test_pset_history_endpoint() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
     };
 
+    let db_path = std::env::temp_dir().join(format!("pset_history_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let mut settings = config::Settings::default();
+    settings.database.path = db_path.clone();
+
     let state = Arc::new(RwLock::new(DeviceState::new()));
-    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let (broadcaster, mut events) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let app = http_server::create_router(observable_state, config::Settings::default());
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+    let request = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+        let builder = Request::builder().uri(uri).method(method);
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    };
+
+    let pset = json!({
+        "id": 2,
+        "name": "Standard v2",
+        "torque_min": 10.0,
+        "torque_max": 15.0,
+        "angle_min": 35.0,
+        "angle_max": 50.0,
+        "description": null
+    });
+    for (method, uri, body) in [
+        ("POST", "/psets/2/select", None),
+        ("PUT", "/psets/2", Some(pset)),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(request(method, uri, body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
 
     let response = app
-        .oneshot(
-            Request::builder()
-                .uri("/auto-tightening/status")
-                .body(Body::empty())
-                .unwrap(),
-        )
+        .clone()
+        .oneshot(request("GET", "/psets/2/history", None))
         .await
         .unwrap();
-
     assert_eq!(response.status(), StatusCode::OK);
-
-    // Parse response
     let body = response.into_body().collect().await.unwrap().to_bytes();
-    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let history: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let kinds: Vec<&str> = history["history"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| change["kind"].as_str().unwrap())
+        .collect();
+    // Updating the selected PSET re-broadcasts its selection
+    assert_eq!(kinds, ["selected", "updated", "selected"]);
+    assert_eq!(history["history"][0]["source"], "http");
+    assert_eq!(history["last_change"], history["history"][1]["timestamp"]);
 
-    assert_eq!(result["running"], false);
-    assert!(result["counter"].is_number());
-    assert!(result["target_size"].is_number());
+    // The MID 0015 broadcast after the update carries the new date
+    let last_change = std::iter::from_fn(|| events.try_recv().ok())
+        .filter_map(|event| match event {
+            SimulatorEvent::PsetChanged { last_change, .. } => Some(last_change),
+            _ => None,
+        })
+        .last()
+        .unwrap();
+    assert_eq!(history["last_change"], last_change);
+
+    let response = app
+        .oneshot(request("GET", "/psets/999/history", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let _ = std::fs::remove_file(&db_path);
 }
 
-/// Test POST /config/multi-spindle endpoint (enable)
+/// Test GET /psets/{id}/simulate: a dry run that leaves no results behind
 #[tokio::test]
-async fn test_configure_multi_spindle_enable() {
+async fn test_pset_simulate_preview() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
     };
 
+    let db_path = std::env::temp_dir().join(format!("pset_preview_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let mut settings = config::Settings::default();
+    settings.database.path = db_path.clone();
+
     let state = Arc::new(RwLock::new(DeviceState::new()));
-    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
-    let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let app = http_server::create_router(observable_state, config::Settings::default());
+    let (broadcaster, mut events) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let ctx = AppContext::with_state(ObservableState::new(state, broadcaster), settings);
+    let app = http_server::create_router(ctx.clone());
+    let get = |uri: &str| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
 
-    let payload = json!({
-        "enabled": true,
-        "spindle_count": 4,
-        "sync_id": 100
-    });
+    for (uri, status) in [
+        ("/psets/99/simulate", StatusCode::NOT_FOUND),
+        ("/psets/2/simulate?count=0", StatusCode::BAD_REQUEST),
+        (
+            "/psets/2/simulate?failure_rate=1.5",
+            StatusCode::BAD_REQUEST,
+        ),
+        ("/psets/2/simulate?torque_min=20", StatusCode::BAD_REQUEST),
+    ] {
+        assert_eq!(get(uri).await.unwrap().status(), status, "{}", uri);
+    }
 
-    let response = app
-        .oneshot(
+    // The target follows the limits (12.25 Nm); torque lands within +/- 5% of
+    // it, so a 12-12.5 Nm window fails about 60% of the tightenings
+    let response =
+        get("/psets/2/simulate?count=2000&failure_rate=0&torque_min=12&torque_max=12.5&seed=7")
+            .await
+            .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let preview: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(preview["torque_min"], 12.0);
+    let report = &preview["report"];
+    assert_eq!(report["count"], 2000);
+    let nok_rate = report["nok_rate"].as_f64().unwrap();
+    assert!((0.5..0.7).contains(&nok_rate), "nok rate {}", nok_rate);
+    let reasons = &report["nok_reasons"];
+    assert_eq!(
+        reasons["torque_low"].as_u64().unwrap() + reasons["torque_high"].as_u64().unwrap(),
+        report["nok"].as_u64().unwrap()
+    );
+    assert_eq!(report["torque"]["histogram"].as_array().unwrap().len(), 10);
+
+    // Nothing was stored or broadcast
+    assert!(ctx.result_repository.read().unwrap().get_all().is_empty());
+    assert!(events.try_recv().is_err());
+
+    let _ = std::fs::remove_file(&db_path);
+}
+
+/// Test POST /psets/import with colliding IDs and the MID 0011 list afterwards
+#[tokio::test]
+async fn test_pset_import_remaps_ids() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server, protocol,
+    };
+
+    let db_path = std::env::temp_dir().join(format!("pset_import_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let mut settings = config::Settings::default();
+    settings.database.path = db_path.clone();
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let ctx = AppContext::with_state(ObservableState::new(state, broadcaster), settings);
+    let app = http_server::create_router(ctx.clone());
+    let import = |body: serde_json::Value| {
+        app.clone().oneshot(
             Request::builder()
-                .uri("/config/multi-spindle")
+                .uri("/psets/import")
                 .method("POST")
                 .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .body(Body::from(body.to_string()))
                 .unwrap(),
         )
-        .await
-        .unwrap();
+    };
+    let library = |on_conflict: &str| {
+        json!({
+            "on_conflict": on_conflict,
+            "offset": 100,
+            "psets": [
+                { "id": 1, "name": format!("B Light ({})", on_conflict), "torque_min": 4.0,
+                  "torque_max": 8.0, "angle_min": 20.0, "angle_max": 40.0 },
+                { "id": 20, "name": format!("B Frame ({})", on_conflict), "torque_min": 30.0,
+                  "torque_max": 40.0, "angle_min": 60.0, "angle_max": 90.0 }
+            ]
+        })
+    };
+
+    // PSET 1 exists, so the default strategy imports nothing
+    let response = import(library("reject")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let rejected: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(rejected["conflicts"], json!([1]));
+    assert!(ctx.pset_repository.read().unwrap().get_by_id(20).is_none());
 
+    let response = import(library("next_free")).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let imported: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(imported["imported"], 2);
+    assert_eq!(imported["remapped"], 1);
+    assert_eq!(imported["mappings"][0]["original_id"], 1);
+    assert_eq!(imported["mappings"][0]["id"], 6);
+    assert_eq!(imported["mappings"][1]["id"], 20);
 
-    // Parse response
+    let response = import(library("offset")).await.unwrap();
     let body = response.into_body().collect().await.unwrap().to_bytes();
-    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let offset: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(offset["mappings"][0]["id"], 101);
+    assert_eq!(offset["mappings"][1]["id"], 120);
 
-    assert_eq!(result["success"], true);
-    assert_eq!(result["enabled"], true);
-    assert_eq!(result["spindle_count"], 4);
-    assert_eq!(result["sync_id"], 100);
+    // Both IDs are taken now; skipping them is what was asked for
+    let response = import(library("skip")).await.unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let skipped: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(skipped["success"], true);
+    assert_eq!(skipped["skipped"], 2);
+    assert_eq!(skipped["failed"], 0);
+    assert_eq!(skipped["mappings"][0]["skipped"], true);
 
-    // Verify state was updated
-    let device_state = state.read().unwrap();
-    assert!(device_state.multi_spindle_config.enabled);
-    assert_eq!(device_state.multi_spindle_config.spindle_count, 4);
+    // MID 0011 lists the IDs the PSETs were stored under
+    let response = ctx
+        .handler_registry()
+        .handle_message(&protocol::Message::new(10, 1, Vec::new()))
+        .unwrap();
+    assert_eq!(response.mid, 11);
+    assert_eq!(&response.data[..], b"009001002003004005006020101120");
+    let _ = std::fs::remove_file(&db_path);
 }
 
-/// Test POST /config/multi-spindle endpoint (disable)
+/// Test the /jobs endpoints and the MID 0031 job list
 #[tokio::test]
-async fn test_configure_multi_spindle_disable() {
+async fn test_jobs_endpoints() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server, protocol,
+    };
+
+    let db_path = std::env::temp_dir().join(format!("jobs_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let mut settings = config::Settings::default();
+    settings.database.path = db_path.clone();
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let ctx = AppContext::with_state(ObservableState::new(state, broadcaster), settings);
+    let app = http_server::create_router(ctx.clone());
+    let request = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+        let builder = Request::builder().uri(uri).method(method);
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    };
+    let job = |pset_id: u32| {
+        json!({
+            "id": 120,
+            "name": "Door module",
+            "steps": [
+                { "pset_id": 1, "batch_size": 3 },
+                { "pset_id": pset_id, "batch_size": 1 }
+            ]
+        })
+    };
+
+    for (method, uri, body, status) in [
+        ("POST", "/jobs", Some(job(77)), StatusCode::BAD_REQUEST),
+        ("POST", "/jobs", Some(job(4)), StatusCode::CREATED),
+        ("POST", "/jobs", Some(job(4)), StatusCode::CONFLICT),
+        ("PUT", "/jobs/120", Some(job(2)), StatusCode::OK),
+        ("PUT", "/jobs/121", Some(job(2)), StatusCode::NOT_FOUND),
+        ("DELETE", "/jobs/2", None, StatusCode::OK),
+        ("GET", "/jobs/2", None, StatusCode::NOT_FOUND),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(request(method, uri, body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "{} {}", method, uri);
+    }
+
+    let response = app.oneshot(request("GET", "/jobs", None)).await.unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let jobs: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(jobs.as_array().unwrap().len(), 2);
+    assert_eq!(jobs[1]["id"], 120);
+    assert_eq!(jobs[1]["steps"][1]["pset_id"], 2);
+
+    // Revision 1 cannot carry job 120; revision 2 lists both
+    let registry = ctx.handler_registry();
+    for (revision, expected) in [(1, &b"0101"[..]), (2, &b"000200010120"[..])] {
+        let response = registry
+            .handle_message(&protocol::Message::new(30, revision, Vec::new()))
+            .unwrap();
+        assert_eq!(response.mid, 31);
+        assert_eq!(&response.data[..], expected);
+    }
+    let _ = std::fs::remove_file(&db_path);
+}
+
+/// Test that the VIN broadcast and the next tightening share a cycle ID
+#[tokio::test]
+async fn test_cycle_id_links_vin_and_result() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut events) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+    let request = |method: &str, uri: &str, body: serde_json::Value| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(request("PATCH", "/state", json!({"vehicle_id": "VIN1"})))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let vin_cycle = loop {
+        match events.try_recv() {
+            Ok(SimulatorEvent::VehicleIdChanged { cycle_id, .. }) => break cycle_id,
+            Ok(_) => continue,
+            Err(e) => panic!("no VehicleIdChanged event: {e}"),
+        }
+    };
+
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "/simulate/tightening",
+                json!({"torque": 12.5, "angle": 40.0, "ok": true}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let result_cycles: Vec<u64> = std::iter::from_fn(|| events.try_recv().ok())
+        .filter_map(|event| match event {
+            SimulatorEvent::TighteningCompleted { result, .. } => Some(result.cycle_id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(result_cycles, [vin_cycle, vin_cycle + 1]);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/results?cycle_id={vin_cycle}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let results: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(results.as_array().unwrap().len(), 1);
+    assert_eq!(results[0]["cycle_id"], vin_cycle);
+    assert_eq!(results[0]["vin_number"], "VIN1");
+}
+
+/// Test POST /simulate/tightening endpoint
+#[tokio::test]
+async fn test_simulate_tightening_endpoint() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
     };
 
     let state = Arc::new(RwLock::new(DeviceState::new()));
     {
         let mut s = state.write().unwrap();
-        s.enable_multi_spindle(4, 100).unwrap();
+        s.set_batch_size(5); // Enable batch mode for counter tracking
     }
 
-    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
-    let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let app = http_server::create_router(observable_state, config::Settings::default());
+    let (broadcaster, _receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
 
     let payload = json!({
-        "enabled": false
+        "torque": 12.5,
+        "angle": 40.0,
+        "ok": true
     });
 
     let response = app
         .oneshot(
             Request::builder()
-                .uri("/config/multi-spindle")
+                .uri("/simulate/tightening")
                 .method("POST")
                 .header("content-type", "application/json")
                 .body(Body::from(serde_json::to_vec(&payload).unwrap()))
@@ -395,35 +760,94 @@ async fn test_configure_multi_spindle_disable() {
     let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
 
     assert_eq!(result["success"], true);
-    assert_eq!(result["enabled"], false);
+    assert_eq!(result["batch_counter"], 1);
+}
 
-    // Verify state was updated
-    let device_state = state.read().unwrap();
-    assert!(!device_state.multi_spindle_config.enabled);
+/// Test POST /simulate/tightening endpoint when tool is disabled
+#[tokio::test]
+async fn test_simulate_tightening_endpoint_rejects_when_tool_disabled() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    {
+        let mut s = state.write().unwrap();
+        s.set_batch_size(5);
+        s.disable_tool();
+    }
+
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let _keepalive_sender = broadcaster.clone();
+    let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let payload = json!({
+        "torque": 12.5,
+        "angle": 40.0,
+        "ok": true
+    });
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/simulate/tightening")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(result["success"], false);
+    assert!(result["message"]
+        .as_str()
+        .unwrap()
+        .contains("tool is disabled"));
+
+    let s = state.read().unwrap();
+    assert_eq!(s.tightening_tracker.counter(), 0);
+
+    assert!(matches!(
+        receiver.try_recv(),
+        Err(tokio::sync::broadcast::error::TryRecvError::Empty)
+    ));
 }
 
-/// Test POST /config/multi-spindle endpoint (invalid config)
+/// Test POST /auto-tightening/start endpoint
 #[tokio::test]
-async fn test_configure_multi_spindle_invalid() {
+async fn test_start_auto_tightening_endpoint() {
     use open_protocol_device_simulator::{
-        DeviceState, ObservableState, SimulatorEvent, config, http_server,
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
     };
 
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let app = http_server::create_router(observable_state, config::Settings::default());
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
 
     let payload = json!({
-        "enabled": true,
-        "spindle_count": 1,  // Too few - invalid
-        "sync_id": 100
+        "interval_ms": 1000,
+        "duration_ms": 100,
+        "failure_rate": 0.0
     });
 
     let response = app
         .oneshot(
             Request::builder()
-                .uri("/config/multi-spindle")
+                .uri("/auto-tightening/start")
                 .method("POST")
                 .header("content-type", "application/json")
                 .body(Body::from(serde_json::to_vec(&payload).unwrap()))
@@ -432,12 +856,3391 @@ async fn test_configure_multi_spindle_invalid() {
         .await
         .unwrap();
 
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.status(), StatusCode::OK);
 
     // Parse response
     let body = response.into_body().collect().await.unwrap().to_bytes();
     let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
 
-    assert_eq!(result["success"], false);
-    assert_eq!(result["enabled"], false);
+    assert_eq!(result["success"], true);
+    assert!(result["message"].as_str().unwrap().contains("started"));
+}
+
+/// Test POST /auto-tightening/start conflict (already running)
+#[tokio::test]
+async fn test_start_auto_tightening_conflict() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+    use std::sync::atomic::AtomicBool;
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+
+    // Create server state with auto-tightening already active
+    let server_state = http_server::ServerState {
+        ctx: AppContext::with_state(observable_state, config::Settings::default()),
+        auto_tightening_active: Arc::new(AtomicBool::new(true)), // Already running
+        firehose: Default::default(),
+    };
+
+    let app = axum::Router::new()
+        .route(
+            "/auto-tightening/start",
+            axum::routing::post(
+                |_state: axum::extract::State<http_server::ServerState>,
+                 _payload: axum::Json<serde_json::Value>| async {
+                    (
+                        StatusCode::CONFLICT,
+                        axum::Json(json!({"success": false, "message": "Already running"})),
+                    )
+                },
+            ),
+        )
+        .with_state(server_state);
+
+    let payload = json!({
+        "interval_ms": 1000,
+        "duration_ms": 100
+    });
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/auto-tightening/start")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+/// Test POST /auto-tightening/stop endpoint
+#[tokio::test]
+async fn test_stop_auto_tightening_endpoint() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/auto-tightening/stop")
+                .method("POST")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Parse response
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(result["success"], true);
+}
+
+/// Test GET /auto-tightening/status endpoint
+#[tokio::test]
+async fn test_get_auto_tightening_status_endpoint() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/auto-tightening/status")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Parse response
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(result["running"], false);
+    assert!(result["counter"].is_number());
+    assert!(result["target_size"].is_number());
+}
+
+/// Test POST /config/multi-spindle endpoint (enable)
+#[tokio::test]
+async fn test_configure_multi_spindle_enable() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let payload = json!({
+        "enabled": true,
+        "spindle_count": 4,
+        "sync_id": 100
+    });
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/config/multi-spindle")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Parse response
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(result["success"], true);
+    assert_eq!(result["enabled"], true);
+    assert_eq!(result["spindle_count"], 4);
+    assert_eq!(result["sync_id"], 100);
+
+    // Verify state was updated
+    let device_state = state.read().unwrap();
+    assert!(device_state.multi_spindle_config.enabled);
+    assert_eq!(device_state.multi_spindle_config.spindle_count, 4);
+}
+
+/// Test POST /config/multi-spindle endpoint (disable)
+#[tokio::test]
+async fn test_configure_multi_spindle_disable() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    {
+        let mut s = state.write().unwrap();
+        s.enable_multi_spindle(4, 100).unwrap();
+    }
+
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let payload = json!({
+        "enabled": false
+    });
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/config/multi-spindle")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Parse response
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(result["success"], true);
+    assert_eq!(result["enabled"], false);
+
+    // Verify state was updated
+    let device_state = state.read().unwrap();
+    assert!(!device_state.multi_spindle_config.enabled);
+}
+
+/// Test POST /config/multi-spindle endpoint (invalid config)
+#[tokio::test]
+async fn test_configure_multi_spindle_invalid() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let payload = json!({
+        "enabled": true,
+        "spindle_count": 1,  // Too few - invalid
+        "sync_id": 100
+    });
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/config/multi-spindle")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Parse response
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(result["success"], false);
+    assert_eq!(result["enabled"], false);
+}
+
+/// Test NOK retry limit with lock_tool action configured on the selected PSET
+#[tokio::test]
+async fn test_retry_limit_locks_tool_and_raises_alarm() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let db_path = std::env::temp_dir().join(format!("retry_limit_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let mut settings = config::Settings::default();
+    settings.database.path = db_path.clone();
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    state.write().unwrap().set_batch_size(3);
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let pset = json!({
+        "id": 1,
+        "name": "Light Duty",
+        "torque_min": 5.0,
+        "torque_max": 10.0,
+        "angle_min": 30.0,
+        "angle_max": 45.0,
+        "description": null,
+        "max_retries": 2,
+        "retry_action": "lock_tool"
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/psets/1")
+                .method("PUT")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&pset).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let nok = json!({ "ok": false });
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/simulate/tightening")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&nok).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    assert!(!state.read().unwrap().tool_enabled);
+
+    let mut alarm_raised = false;
+    while let Ok(event) = receiver.try_recv() {
+        if let SimulatorEvent::AlarmRaised { tool_ready, .. } = event {
+            assert!(!tool_ready);
+            alarm_raised = true;
+        }
+    }
+    assert!(alarm_raised, "Retry limit should raise an alarm");
+
+    let _ = std::fs::remove_file(&db_path);
+}
+
+/// Test that simulated tightenings carry metadata into the results history and CSV export
+#[tokio::test]
+async fn test_tightening_metadata_in_results_export() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let payload = json!({
+        "torque": 12.5,
+        "angle": 40.0,
+        "ok": true,
+        "metadata": {
+            "operator_id": "badge-7",
+            "station": "ST-10",
+            "extra": { "trace_id": "t-123" }
+        }
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/simulate/tightening")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Metadata travels with the broadcast event
+    match receiver.try_recv() {
+        Ok(SimulatorEvent::TighteningCompleted { result, .. }) => {
+            assert_eq!(result.metadata.operator_id.as_deref(), Some("badge-7"));
+        }
+        other => panic!("expected tightening event, got {:?}", other),
+    }
+
+    // ...is stored in the results history
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/results")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let history: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(history[0]["metadata"]["station"], "ST-10");
+
+    // ...and exported as flat CSV columns
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/results/export?format=csv")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let csv = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        csv.lines()
+            .nth(1)
+            .unwrap()
+            .ends_with(",badge-7,ST-10,trace_id=t-123,")
+    );
+}
+
+/// Test that results are signed with `[results] signing_key`
+#[tokio::test]
+async fn test_signed_results() {
+    use open_protocol_device_simulator::handler::data::TighteningResult;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server, results,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.results.signing_key = Some("secret".to_string());
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/simulate/tightening")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"ok": true}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The broadcast (and so the webhook payload) carries the signature
+    let signature = match receiver.try_recv() {
+        Ok(SimulatorEvent::TighteningCompleted { result, .. }) => {
+            assert!(results::verify(&result, "secret"));
+            result.signature.unwrap()
+        }
+        other => panic!("expected tightening event, got {:?}", other),
+    };
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/results")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let history: Vec<TighteningResult> = serde_json::from_slice(&body).unwrap();
+    assert!(results::verify(&history[0], "secret"));
+    assert!(!results::verify(&history[0], "guess"));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/results/export?format=csv")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let csv = String::from_utf8(body.to_vec()).unwrap();
+    assert!(csv.lines().nth(1).unwrap().ends_with(&signature));
+}
+
+/// Test POST /results/:id/void marks the result and resends the correction
+#[tokio::test]
+async fn test_void_result() {
+    use open_protocol_device_simulator::handler::data::TighteningResult;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server, results,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.results.signing_key = Some("secret".to_string());
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let void = |uri: String, reason: &str| {
+        Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "reason": reason }).to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/simulate/tightening")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"ok": true}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let id = match receiver.try_recv() {
+        Ok(SimulatorEvent::TighteningCompleted { result, .. }) => result.tightening_id.unwrap(),
+        other => panic!("expected tightening event, got {:?}", other),
+    };
+
+    let response = app
+        .clone()
+        .oneshot(void(format!("/results/{id}/void"), "Wrong socket"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Subscribers get the corrected result under the same tightening ID
+    let event = receiver.try_recv().unwrap();
+    match &event {
+        SimulatorEvent::ResultVoided { result } => {
+            assert_eq!(result.tightening_id, Some(id));
+            assert!(!result.tightening_status);
+            assert_eq!(result.voided.as_ref().unwrap().reason, "Wrong socket");
+            assert!(results::verify(result, "secret"));
+        }
+        other => panic!("expected void event, got {:?}", other),
+    }
+    let (revision, data) = event.subscription_payload(1).unwrap();
+    assert_eq!(revision, 1);
+    assert_eq!(&data[85..88], b"090"); // parameter 09: tightening status NOK
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/results/{id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let stored: TighteningResult = serde_json::from_slice(&body).unwrap();
+    assert!(stored.voided.is_some());
+
+    let response = app
+        .clone()
+        .oneshot(void(format!("/results/{id}/void"), "Again"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = app
+        .clone()
+        .oneshot(void("/results/999999/void".to_string(), "Missing"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app
+        .oneshot(void(format!("/results/{id}/void"), " "))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Test that a multistage PSET produces a stage breakdown served by GET /results/:id
+#[tokio::test]
+async fn test_multistage_result_by_id() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let db_path = std::env::temp_dir().join(format!("multistage_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let mut settings = config::Settings::default();
+    settings.database.path = db_path.clone();
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let pset = json!({
+        "id": 0,
+        "name": "Two Step",
+        "torque_min": 10.0,
+        "torque_max": 15.0,
+        "angle_min": 30.0,
+        "angle_max": 50.0,
+        "description": null,
+        "stages": [
+            { "torque_min": 4.0, "torque_max": 6.0, "angle_min": 80.0, "angle_max": 100.0 }
+        ]
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/psets")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&pset).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let created: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let pset_id = created["pset"]["id"].as_u64().unwrap();
+
+    for (method, uri) in [
+        ("POST", format!("/psets/{}/select", pset_id)),
+        ("POST", "/simulate/tightening".to_string()),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .method(method)
+                    .header("content-type", "application/json")
+                    .body(Body::from("{}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/results")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let history: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let tightening_id = history[0]["tightening_id"].as_u64().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/results/{}", tightening_id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let stages = result["stages"].as_array().unwrap();
+    assert_eq!(stages.len(), 2);
+    assert_eq!(stages[0]["stage"], 1);
+    assert_eq!(stages[0]["ok"], true);
+    assert_eq!(stages[1]["torque"], result["torque"]);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/results/9999")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let _ = std::fs::remove_file(&db_path);
+}
+
+/// Test that GET /results/:id/curve serves the stored trace as JSON and downsampled CSV
+#[tokio::test]
+async fn test_result_curve_endpoint() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/simulate/tightening")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"torque": 12.0, "angle": 40.0}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let result = match receiver.try_recv() {
+        Ok(SimulatorEvent::TighteningCompleted { result, .. }) => result,
+        other => panic!("expected tightening event, got {:?}", other),
+    };
+    let tightening_id = result.tightening_id.unwrap();
+
+    let get = |uri: String| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+
+    let response = get(format!("/results/{}/curve", tightening_id))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let curve: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let points = curve["points"].as_array().unwrap();
+    assert_eq!(points.len(), 200);
+    let last = &points[199];
+    assert!((last["angle"].as_f64().unwrap() - result.angle).abs() < 1e-9);
+    assert!((last["torque"].as_f64().unwrap() - result.torque).abs() < 1e-9);
+
+    let response = get(format!(
+        "/results/{}/curve?format=csv&max_points=10",
+        tightening_id
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let csv = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(csv.lines().count(), 11);
+    assert!(
+        csv.lines()
+            .last()
+            .unwrap()
+            .ends_with(&format!(",{:.2},{:.3}", result.angle, result.torque))
+    );
+
+    let response = get("/results/9999/curve".to_string()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Test that a PSET's cycle time model sets the duration of its tightenings
+#[tokio::test]
+async fn test_pset_cycle_time_drives_tightening_duration() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let db_path = std::env::temp_dir().join(format!("cycle_time_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let mut settings = config::Settings::default();
+    settings.database.path = db_path.clone();
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+    let send = |method: &str, uri: String, body: serde_json::Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(uri)
+                .method(method)
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+    };
+    let pset = |name: &str, rundown_ms: u64, tightening_ms: u64| {
+        json!({
+            "id": 0,
+            "name": name,
+            "torque_min": 10.0,
+            "torque_max": 15.0,
+            "angle_min": 30.0,
+            "angle_max": 50.0,
+            "description": null,
+            "cycle_time": { "rundown_ms": rundown_ms, "tightening_ms": tightening_ms }
+        })
+    };
+
+    let response = send("POST", "/psets".to_string(), pset("Zero", 0, 0))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = send("POST", "/psets".to_string(), pset("Takt", 800, 400))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let created: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(created["pset"]["cycle_time"]["rundown_ms"], 800);
+    assert_eq!(created["pset"]["cycle_time"]["variance_ms"], 0);
+    let pset_id = created["pset"]["id"].as_u64().unwrap();
+
+    let response = send("POST", format!("/psets/{}/select", pset_id), json!({}))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = send("POST", "/simulate/tightening".to_string(), json!({}))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let tightening_id = loop {
+        match receiver.try_recv() {
+            Ok(SimulatorEvent::TighteningCompleted { result, .. }) => {
+                break result.tightening_id.unwrap();
+            }
+            Ok(_) => continue,
+            Err(e) => panic!("expected tightening event, got {:?}", e),
+        }
+    };
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/results/{}/curve", tightening_id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let curve: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let last = curve["points"].as_array().unwrap().last().unwrap().clone();
+    assert_eq!(last["time_ms"], 1200.0);
+
+    let _ = std::fs::remove_file(&db_path);
+}
+
+/// Test that the firehose emits the requested number of results into history and events
+#[tokio::test]
+async fn test_firehose_generates_requested_count() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(1000);
+    let observable_state = ObservableState::new(state.clone(), broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let payload = json!({ "rate_per_sec": 20000, "count": 300, "nok_rate": 0.5 });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/firehose/start")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut events = 0;
+    while events < 300 {
+        match tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv()).await {
+            Ok(Ok(SimulatorEvent::TighteningCompleted { .. })) => events += 1,
+            Ok(Ok(_)) => {}
+            other => panic!("firehose stalled after {} results: {:?}", events, other),
+        }
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/results")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let history: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(history.len(), 300);
+
+    // IDs are reserved from the device's global sequence
+    assert_eq!(
+        state
+            .read()
+            .unwrap()
+            .tightening_tracker
+            .tightening_sequence(),
+        300
+    );
+}
+
+/// Test that POST /results/generate backfills history without broadcasting
+#[tokio::test]
+async fn test_generate_historical_results() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let generate = |payload: serde_json::Value| {
+        Request::builder()
+            .uri("/results/generate")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+            .unwrap()
+    };
+
+    let payload = json!({
+        "count": 500,
+        "from": "2024-01-01",
+        "to": "2024-06-30",
+        "pset_mix": { "1": 3, "2": 1 },
+        "nok_rate": 0.2
+    });
+    let response = app.clone().oneshot(generate(payload)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(receiver.try_recv().is_err(), "backfill must not broadcast");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/results")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let history: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(history.len(), 500);
+    assert!(history[0]["timestamp"].as_str().unwrap() >= "2024-01-01");
+    assert!(
+        history
+            .iter()
+            .all(|r| r["pset_id"] == 1 || r["pset_id"] == 2)
+    );
+
+    // Unknown PSETs and bad ranges are rejected
+    let response = app
+        .clone()
+        .oneshot(generate(json!({ "count": 10, "pset_mix": { "999": 1 } })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app
+        .oneshot(generate(
+            json!({ "count": 10, "from": "2024-02-01", "to": "2024-01-01" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Test that the embedded web UI and its assets are served
+#[tokio::test]
+async fn test_embedded_web_ui_is_served() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    for (uri, content_type) in [
+        ("/", "text/html"),
+        ("/ui/app.js", "javascript"),
+        ("/ui/style.css", "text/css"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        let header = response.headers()["content-type"].to_str().unwrap();
+        assert!(
+            header.contains(content_type),
+            "{} served as {}",
+            uri,
+            header
+        );
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/ui/missing.js")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Test that all routes move under the configured base path
+#[tokio::test]
+async fn test_http_base_path_prefixes_routes() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.server.http_base_path = "/sim1/".to_string();
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+    let response = app.clone().oneshot(get("/sim1/state")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(get("/state")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The UI resolves its assets and API calls under the prefix
+    let response = app.clone().oneshot(get("/sim1")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(String::from_utf8_lossy(&body).contains(r#"<base href="/sim1/">"#));
+    let response = app.oneshot(get("/sim1/ui/app.js")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// Test that only configured origins pass the CORS policy
+#[tokio::test]
+async fn test_cors_allowed_origins() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.server.cors_allowed_origins = vec!["http://lab.local:5173".to_string()];
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let with_origin = |origin: &str| {
+        Request::builder()
+            .uri("/state")
+            .header("origin", origin)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(with_origin("http://lab.local:5173"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "http://lab.local:5173"
+    );
+
+    let response = app
+        .oneshot(with_origin("http://evil.example"))
+        .await
+        .unwrap();
+    assert!(
+        !response
+            .headers()
+            .contains_key("access-control-allow-origin")
+    );
+}
+
+/// Test that controller events from HTTP show up in GET /audit
+#[tokio::test]
+async fn test_audit_log_records_controller_events() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    observable_state.disable_tool();
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/config/failure")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"connection_health": 40}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/audit")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let entries: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    let categories: Vec<&str> = entries
+        .iter()
+        .map(|e| e["category"].as_str().unwrap())
+        .collect();
+    assert_eq!(categories, vec!["tool", "configuration"]);
+
+    // Incremental polling and category filters
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/audit?since=1&category=configuration")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let entries: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["id"], 2);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/audit?category=bogus")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Test that operator login gates tightenings and tags results with the operator
+#[tokio::test]
+async fn test_operator_login_required_for_tightening() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.defaults.require_operator_login = true;
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let tighten = || {
+        Request::builder()
+            .uri("/simulate/tightening")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"ok": true}"#))
+            .unwrap()
+    };
+
+    // Nobody logged in yet
+    let response = app.clone().oneshot(tighten()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/operator/login")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"operator_id": "OP-42", "name": "Alex"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.clone().oneshot(tighten()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let operator = loop {
+        match receiver.try_recv() {
+            Ok(SimulatorEvent::TighteningCompleted { result, .. }) => {
+                break result.metadata.operator_id;
+            }
+            Ok(_) => continue,
+            Err(e) => panic!("expected tightening event, got {:?}", e),
+        }
+    };
+    assert_eq!(operator.as_deref(), Some("OP-42"));
+
+    // Logging out locks tightenings again
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/operator/logout")
+                .method("POST")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.oneshot(tighten()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+/// Test GET /connections reports registered sessions and subscriber counts
+#[tokio::test]
+async fn test_get_connections_endpoint() {
+    use open_protocol_device_simulator::subscriptions::{
+        SubscriptionInfo, SubscriptionKind, Subscriptions,
+    };
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state, config::Settings::default());
+
+    let id = ctx.sessions.register("127.0.0.1:40000".parse().unwrap());
+    ctx.sessions.register("127.0.0.1:40001".parse().unwrap());
+    let mut subscriptions = Subscriptions::new();
+    subscriptions.subscribe(
+        SubscriptionKind::TighteningResult,
+        SubscriptionInfo::new(1, ""),
+    );
+    ctx.sessions.update_subscriptions(id, &subscriptions);
+
+    let app = http_server::create_router(ctx);
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/connections")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["count"], 2);
+    assert_eq!(json["subscribers"]["tightening_result"], 1);
+    assert_eq!(json["connections"][0]["addr"], "127.0.0.1:40000");
+    assert_eq!(
+        json["connections"][0]["subscriptions"]["tightening_result"]["revision"],
+        1
+    );
+}
+
+/// Test POST /connections/{id}/close forwards the close mode to the connection
+#[tokio::test]
+async fn test_close_connection_endpoint() {
+    use open_protocol_device_simulator::session_registry::CloseMode;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state, config::Settings::default());
+
+    let id = ctx.sessions.register("127.0.0.1:40000".parse().unwrap());
+    let mut close_requests = ctx.sessions.close_requests(id);
+    // Registered without taking close requests (like a proxied client)
+    let proxied = ctx.sessions.register("127.0.0.1:40001".parse().unwrap());
+
+    let app = http_server::create_router(ctx);
+    let close = |id: u64, body: Option<serde_json::Value>| {
+        let builder = Request::builder()
+            .uri(format!("/connections/{}/close", id))
+            .method("POST");
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    };
+
+    let response = app
+        .clone()
+        .oneshot(close(id, Some(json!({ "mode": "abort" }))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(close_requests.try_recv().unwrap(), CloseMode::Abort);
+
+    let response = app.clone().oneshot(close(id, None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(close_requests.try_recv().unwrap(), CloseMode::Graceful);
+
+    for (id, body, status) in [
+        (id, json!({ "mode": "slam" }), StatusCode::BAD_REQUEST),
+        (999, json!({ "mode": "half_close" }), StatusCode::NOT_FOUND),
+        (
+            proxied,
+            json!({ "mode": "half_close" }),
+            StatusCode::CONFLICT,
+        ),
+    ] {
+        let response = app.clone().oneshot(close(id, Some(body))).await.unwrap();
+        assert_eq!(response.status(), status);
+    }
+    assert!(close_requests.try_recv().is_err());
+}
+
+/// Test PUT /connections/{id}/observer switches a client to read-only mode
+#[tokio::test]
+async fn test_connection_observer_endpoint() {
+    use open_protocol_device_simulator::protocol::Message;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state, config::Settings::default());
+    let id = ctx.sessions.register("127.0.0.1:40000".parse().unwrap());
+
+    let app = http_server::create_router(ctx.clone());
+    let observer = |id: u64, observer: bool| {
+        Request::builder()
+            .uri(format!("/connections/{}/observer", id))
+            .method("PUT")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "observer": observer }).to_string()))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(observer(id, true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(ctx.sessions.is_observer(id));
+    assert!(ctx.sessions.snapshot()[0].observer);
+
+    // The connection now dispatches through the observer registry
+    let select_pset = Message {
+        length: 23,
+        mid: 18,
+        revision: 1,
+        data: b"001".to_vec(),
+        header: Default::default(),
+    };
+    let response = ctx
+        .observer_registry()
+        .handle_message(&select_pset)
+        .unwrap();
+    assert_eq!(response.mid, 4);
+    assert_eq!(response.data, b"001810");
+    let response = ctx.handler_registry().handle_message(&select_pset).unwrap();
+    assert_ne!(response.mid, 4);
+
+    let response = app.clone().oneshot(observer(id, false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!ctx.sessions.is_observer(id));
+
+    let response = app.oneshot(observer(999, true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Test the fieldbus I/O image follows PLC inputs and the tightening lifecycle
+#[tokio::test]
+async fn test_fieldbus_io_endpoint() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state.clone(), config::Settings::default());
+    let app = http_server::create_router(ctx.clone());
+    let write = |inputs: serde_json::Value| {
+        Request::builder()
+            .uri("/io/fieldbus")
+            .method("PUT")
+            .header("content-type", "application/json")
+            .body(Body::from(inputs.to_string()))
+            .unwrap()
+    };
+    let image = |response: axum::response::Response| async move {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(write(json!({ "pset": 2 })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = image(response).await;
+    assert_eq!(json["outputs"]["pset"], 2);
+    assert_eq!(observable_state.read().current_pset_id, Some(2));
+
+    // A start edge runs one tightening and latches its outcome
+    let response = app
+        .clone()
+        .oneshot(write(json!({ "start": true })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = image(response).await;
+    assert_eq!(json["inputs"]["start"], true);
+    assert_ne!(
+        json["outputs"]["tightening_ok"],
+        json["outputs"]["tightening_nok"]
+    );
+    let response = app
+        .clone()
+        .oneshot(write(json!({ "start": true })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(ctx.result_repository.read().unwrap().len(), 1);
+
+    let response = app
+        .clone()
+        .oneshot(write(json!({ "reset": true })))
+        .await
+        .unwrap();
+    let json = image(response).await;
+    assert_eq!(json["outputs"]["tightening_ok"], false);
+    assert_eq!(json["outputs"]["tightening_nok"], false);
+
+    let response = app
+        .clone()
+        .oneshot(write(json!({ "pset": 200 })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // A disabled tool drops the ready output and ignores start
+    observable_state.disable_tool();
+    let response = app
+        .clone()
+        .oneshot(write(json!({ "start": false })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(write(json!({ "start": true })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let json = image(response).await;
+    assert_eq!(json["outputs"]["ready"], false);
+    assert_eq!(ctx.result_repository.read().unwrap().len(), 1);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/io/fieldbus")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let json = image(response).await;
+    assert_eq!(json["inputs"]["pset"], 2);
+}
+
+/// Test the broadcast throttle endpoints
+#[tokio::test]
+async fn test_throttles_endpoint() {
+    use open_protocol_device_simulator::subscriptions::SubscriptionKind;
+    use open_protocol_device_simulator::throttle::ThrottleOverflow;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state, config::Settings::default());
+    let app = http_server::create_router(ctx.clone());
+    let request = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+        let builder = Request::builder().uri(uri).method(method);
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    };
+
+    let rule = json!({ "max_per_sec": 2.0, "burst": 5, "overflow": "coalesce" });
+    let response = app
+        .clone()
+        .oneshot(request("PUT", "/throttles/tightening_result", Some(rule)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let stored = ctx
+        .throttles
+        .get(SubscriptionKind::TighteningResult)
+        .unwrap();
+    assert_eq!(stored.burst, 5);
+    assert_eq!(stored.overflow, ThrottleOverflow::Coalesce);
+    assert_eq!(stored.max_queued, 1000);
+
+    let response = app
+        .clone()
+        .oneshot(request("GET", "/throttles", None))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["count"], 1);
+    assert_eq!(json["throttles"]["tightening_result"]["max_per_sec"], 2.0);
+
+    for (method, uri, body, status) in [
+        (
+            "PUT",
+            "/throttles/alarm",
+            Some(json!({ "max_per_sec": 0.0 })),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            "PUT",
+            "/throttles/bogus",
+            Some(json!({ "max_per_sec": 1.0 })),
+            StatusCode::NOT_FOUND,
+        ),
+        (
+            "DELETE",
+            "/throttles/tightening_result",
+            None,
+            StatusCode::OK,
+        ),
+        (
+            "DELETE",
+            "/throttles/tightening_result",
+            None,
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(request(method, uri, body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "{} {}", method, uri);
+    }
+    assert!(ctx.throttles.list().is_empty());
+}
+
+/// Test the ack policy endpoints
+#[tokio::test]
+async fn test_ack_policies_endpoint() {
+    use open_protocol_device_simulator::ack_policy::AckExhaustion;
+    use open_protocol_device_simulator::subscriptions::SubscriptionKind;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state, config::Settings::default());
+    let app = http_server::create_router(ctx.clone());
+    let request = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+        let builder = Request::builder().uri(uri).method(method);
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    };
+
+    let policy = json!({ "timeout_ms": 250, "on_exhaustion": "buffer" });
+    let response = app
+        .clone()
+        .oneshot(request("PUT", "/ack-policies/alarm", Some(policy)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let stored = ctx.ack_policies.get(SubscriptionKind::Alarm).unwrap();
+    assert_eq!(stored.timeout_ms, 250);
+    assert_eq!(stored.max_retries, 3);
+    assert_eq!(stored.on_exhaustion, AckExhaustion::Buffer);
+
+    let response = app
+        .clone()
+        .oneshot(request("GET", "/ack-policies", None))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["count"], 1);
+    assert_eq!(json["policies"]["alarm"]["on_exhaustion"], "buffer");
+
+    for (method, uri, body, status) in [
+        (
+            "PUT",
+            "/ack-policies/tightening_result",
+            Some(json!({ "timeout_ms": 0 })),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            "PUT",
+            "/ack-policies/bogus",
+            Some(json!({ "timeout_ms": 100 })),
+            StatusCode::NOT_FOUND,
+        ),
+        ("DELETE", "/ack-policies/alarm", None, StatusCode::OK),
+        ("DELETE", "/ack-policies/alarm", None, StatusCode::NOT_FOUND),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(request(method, uri, body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "{} {}", method, uri);
+    }
+    assert!(ctx.ack_policies.list().is_empty());
+}
+
+/// Test GET /events/next waits for a matching event and times out otherwise
+#[tokio::test]
+async fn test_next_event_long_poll() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state.clone(),
+        config::Settings::default(),
+    ));
+    let request = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+    let pending = tokio::spawn(app.clone().oneshot(request(
+        "/events/next?type=BatchCompleted,AlarmCleared&timeout_ms=2000",
+    )));
+    // Events before the request subscribed are not considered
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    observable_state.broadcast(SimulatorEvent::ToolStateChanged { enabled: false });
+    observable_state.broadcast(SimulatorEvent::BatchCompleted {
+        total: 4,
+        tool_locked: false,
+    });
+
+    let response = pending.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let event: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(event["type"], "BatchCompleted");
+    assert_eq!(event["total"], 4);
+
+    let response = app
+        .oneshot(request("/events/next?type=BatchCompleted&timeout_ms=20"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+}
+
+/// Test POST /assertions registers an expectation and GET reports it with evidence
+#[tokio::test]
+async fn test_assertions_endpoint() {
+    use open_protocol_device_simulator::protocol::Mid;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state, config::Settings::default());
+    let assertions = Arc::clone(&ctx.assertions);
+    let app = http_server::create_router(ctx);
+    let request = |method: &str, uri: &str, body: &str| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let json = |response: axum::response::Response| async move {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(request(
+            "POST",
+            "/assertions",
+            r#"{"kind": "ack_within", "mid": 61, "within_ms": 2000, "client": "10.0.0.5", "duration_ms": 60000}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let id = json(response).await["id"].as_u64().unwrap();
+
+    let station = "10.0.0.5:50000".parse().unwrap();
+    let now = std::time::Instant::now();
+    assertions.record_sent(station, Mid(61), now);
+    assertions.record_received(station, Mid(62), now);
+
+    let response = app
+        .clone()
+        .oneshot(request("GET", &format!("/assertions/{id}"), ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let report = json(response).await;
+    assert_eq!(report["status"], "pending");
+    assert_eq!(report["kind"], "ack_within");
+    assert_eq!(report["evidence"]["pushes"][0]["client"], "10.0.0.5:50000");
+    assert_eq!(report["evidence"]["pushes"][0]["acked_after_ms"], 0);
+
+    let response = app
+        .clone()
+        .oneshot(request(
+            "POST",
+            "/assertions",
+            r#"{"kind": "no_parse_errors", "duration_ms": 60000}"#,
+        ))
+        .await
+        .unwrap();
+    let id = json(response).await["id"].as_u64().unwrap();
+    assertions.record_parse_error(station, "Invalid length", std::time::Instant::now());
+    let response = app
+        .clone()
+        .oneshot(request("GET", &format!("/assertions/{id}"), ""))
+        .await
+        .unwrap();
+    let report = json(response).await;
+    assert_eq!(report["status"], "failed");
+    assert_eq!(
+        report["evidence"]["parse_errors"][0]["error"],
+        "Invalid length"
+    );
+
+    // MID 0002 is a reply, not a push clients acknowledge
+    let response = app
+        .clone()
+        .oneshot(request(
+            "POST",
+            "/assertions",
+            r#"{"kind": "ack_within", "mid": 2, "within_ms": 100, "duration_ms": 1000}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .oneshot(request("GET", "/assertions/999", ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Test GET /stats/cycle-time summarizes timed results per PSET
+#[tokio::test]
+async fn test_cycle_time_stats_endpoint() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/simulate/tightening")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"torque": 12.0, "angle": 40.0}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/stats/cycle-time?limit=5")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(report["overall"]["count"], 2);
+    // Manual tightenings take the fixed 500 ms cycle
+    assert_eq!(report["overall"]["mean_ms"], 500.0);
+    assert_eq!(report["overall"]["p95_ms"], 500);
+    assert_eq!(report["by_pset"]["1"]["count"], 2);
+}
+
+/// Test POST /config/failure sets and removes a per-connection bandwidth limit
+#[tokio::test]
+async fn test_failure_config_bandwidth_limit() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state.clone(),
+        config::Settings::default(),
+    ));
+    let post = |body: &'static str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri("/config/failure")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+    };
+
+    let response =
+        post(r#"{"enabled": true, "bandwidth": {"bytes_per_sec": 1200, "burst_bytes": 4096}}"#)
+            .await
+            .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let limit = observable_state
+        .read()
+        .failure_config
+        .active_bandwidth()
+        .unwrap();
+    assert_eq!((limit.bytes_per_sec, limit.burst_bytes), (1200, 4096));
+
+    // Other fields leave the limit alone; null removes it
+    let response = post(r#"{"packet_loss_rate": 0.1}"#).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(observable_state.read().failure_config.bandwidth.is_some());
+    let response = post(r#"{"bandwidth": null}"#).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(observable_state.read().failure_config.bandwidth, None);
+
+    let response = post(r#"{"bandwidth": {"bytes_per_sec": 0}}"#)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Test GET /stats/handlers reports latency percentiles and error kinds per MID
+#[tokio::test]
+async fn test_handler_stats_endpoint() {
+    use open_protocol_device_simulator::protocol::Message;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state, config::Settings::default());
+    let app = http_server::create_router(ctx.clone());
+
+    let registry = ctx.handler_registry();
+    for _ in 0..3 {
+        registry
+            .handle_message(&Message::new(9999, 1, vec![]))
+            .unwrap();
+    }
+    // Resetting the batch counter outside batch mode is answered with MID 0004
+    let nak = registry
+        .handle_message(&Message::new(20, 1, b"001".to_vec()))
+        .unwrap();
+    assert_eq!(nak.mid, 4);
+    assert!(
+        registry
+            .handle_message(&Message::new(4242, 1, vec![]))
+            .is_err()
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/stats/handlers?sort=errors")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let handlers = stats["handlers"].as_array().unwrap();
+    assert_eq!(handlers.len(), 3);
+    assert_eq!(handlers[0]["mid"], 20);
+    assert_eq!(handlers[0]["naks"], 1);
+    let nak_type = format!("nak_{}", String::from_utf8_lossy(&nak.data[4..6]));
+    assert_eq!(handlers[0]["errors_by_type"][&nak_type], 1);
+    assert_eq!(handlers[0]["last_error"]["type"], nak_type);
+    assert_eq!(handlers[1]["mid"], 4242);
+    assert_eq!(handlers[1]["errors_by_type"]["unknown_mid"], 1);
+    assert_eq!(handlers[2]["mid"], 9999);
+    assert_eq!(handlers[2]["count"], 3);
+    assert!(
+        handlers[2]["p99_micros"].as_u64().unwrap() >= handlers[2]["p50_micros"].as_u64().unwrap()
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/stats/handlers?sort=fastest")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Test the service interval locks the tool until POST /tool/service-reset
+#[tokio::test]
+async fn test_tool_service_lockout() {
+    use open_protocol_device_simulator::protocol::Message;
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.maintenance.service_interval = 2;
+    settings.maintenance.service_lockout = true;
+    let ctx = AppContext::with_state(observable_state, settings);
+    let app = http_server::create_router(ctx.clone());
+
+    let tighten = || {
+        Request::builder()
+            .uri("/simulate/tightening")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"torque": 12.0, "angle": 40.0}"#))
+            .unwrap()
+    };
+    for _ in 0..2 {
+        let response = app.clone().oneshot(tighten()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app.clone().oneshot(tighten()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let alarms = ctx.observable_state.read().alarms.alarms();
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(alarms[0].error_code, "E502");
+
+    // MID 0043 cannot lift the lock
+    let registry = ctx.handler_registry();
+    let response = registry
+        .handle_message(&Message::new(43, 1, vec![]))
+        .unwrap();
+    assert_eq!(response.mid, 4);
+    assert_eq!(response.data, b"004302");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/tool/service-reset")
+                .method("POST")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let reset: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(reset["tightenings_since_service"], 2);
+    assert_eq!(reset["unlocked"], true);
+    assert_eq!(reset["cleared_alarms"], json!([alarms[0].id]));
+
+    let response = app.clone().oneshot(tighten()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // MID 0041 revision 2 reports the restarted service counter (field 07)
+    let response = registry
+        .handle_message(&Message::new(40, 2, vec![]))
+        .unwrap();
+    let data = String::from_utf8(response.data).unwrap();
+    assert!(data.contains("070000000001"), "{}", data);
+}
+
+/// Test interlocked jobs wait for a release over HTTP or the fieldbus
+#[tokio::test]
+async fn test_interlock_release() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut events) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.interlock.jobs = vec![1];
+    let ctx = AppContext::with_state(observable_state, settings);
+    let app = http_server::create_router(ctx.clone());
+    ctx.observable_state.set_batch_size(5);
+
+    let request = |method: &str, uri: &str, body: &str| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let tighten = |ok: bool| {
+        request(
+            "POST",
+            "/simulate/tightening",
+            &format!(r#"{{"ok": {}}}"#, ok),
+        )
+    };
+
+    // A NOK result keeps the position open
+    let response = app.clone().oneshot(tighten(false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(ctx.observable_state.read().interlock.is_none());
+
+    let response = app.clone().oneshot(tighten(true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(tighten(true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app
+        .clone()
+        .oneshot(request("GET", "/interlock", ""))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let interlock: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(interlock["jobs"], json!([1]));
+    assert_eq!(interlock["waiting"]["job_id"], 1);
+    assert_eq!(interlock["waiting"]["position"], 1);
+
+    let response = app
+        .clone()
+        .oneshot(request("GET", "/io/fieldbus", ""))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let image: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(image["outputs"]["interlock_waiting"], true);
+    assert_eq!(image["outputs"]["tool_enabled"], false);
+
+    // A rising edge of the release input frees the next position
+    let response = app
+        .clone()
+        .oneshot(request("PUT", "/io/fieldbus", r#"{"release": true}"#))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let image: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(image["outputs"]["interlock_waiting"], false);
+    assert_eq!(image["outputs"]["tool_enabled"], true);
+
+    let response = app.clone().oneshot(tighten(true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(request("POST", "/interlock/release", ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let released: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(released["released"]["position"], 2);
+    let response = app
+        .clone()
+        .oneshot(request("POST", "/interlock/release", ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let mut sources = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        match event {
+            SimulatorEvent::InterlockWaiting { wait } => {
+                sources.push(format!("wait {}", wait.position))
+            }
+            SimulatorEvent::InterlockReleased { source, .. } => {
+                sources.push(format!("release {}", source.as_str()))
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(
+        sources,
+        ["wait 1", "release fieldbus", "wait 2", "release http"]
+    );
+}
+
+/// Test tightening IDs and job sequence numbers continue after a restart
+#[tokio::test]
+async fn test_counters_survive_restart() {
+    use open_protocol_device_simulator::{AppContext, config, counters, http_server};
+
+    let dir = std::env::temp_dir().join(format!("counters_restart_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut settings = config::Settings::default();
+    settings.database.path = dir.join("simulator.db");
+    settings.stats.lifetime_path = dir.join("lifetime_stats.json");
+
+    let ctx = AppContext::new(settings.clone());
+    counters::spawn_persistence(&ctx);
+    let app = http_server::create_router(ctx.clone());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/counters")
+                .method("PUT")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"next_tightening_id": 1000, "job_sequence_number": 41}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/simulate/tightening")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let last = ctx.result_repository.read().unwrap().get_all();
+    assert_eq!(last.last().unwrap().tightening_id, Some(1000));
+    tokio::time::sleep(counters::SAVE_INTERVAL * 3).await;
+
+    // A new context on the same database continues the sequences
+    let restarted = http_server::create_router(AppContext::new(settings));
+    let response = restarted
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/counters")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let counters: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(counters["next_tightening_id"], 1001);
+    assert_eq!(counters["job_sequence_number"], 41);
+
+    let response = restarted
+        .oneshot(
+            Request::builder()
+                .uri("/counters")
+                .method("PUT")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"next_tightening_id": 0}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Test an ID issued right before shutdown is not handed out again
+#[tokio::test]
+async fn test_counters_saved_on_shutdown() {
+    use open_protocol_device_simulator::{AppContext, config, counters, http_server};
+
+    let dir = std::env::temp_dir().join(format!("counters_shutdown_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut settings = config::Settings::default();
+    settings.database.path = dir.join("simulator.db");
+    settings.stats.lifetime_path = dir.join("lifetime_stats.json");
+
+    let ctx = AppContext::new(settings.clone());
+    counters::spawn_persistence(&ctx);
+    let app = http_server::create_router(ctx.clone());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/simulate/tightening")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let last = ctx.result_repository.read().unwrap().get_all();
+    let issued = last.last().unwrap().tightening_id.unwrap();
+
+    // Restart before the next persistence tick
+    counters::save_on_shutdown(&ctx);
+    let restarted = http_server::create_router(AppContext::new(settings));
+    let response = restarted
+        .oneshot(
+            Request::builder()
+                .uri("/counters")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let counters: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(counters["next_tightening_id"], issued + 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Test user data written with MID 0240/0245 and over HTTP is uploaded and survives a restart
+#[tokio::test]
+async fn test_user_data_slots_survive_restart() {
+    use open_protocol_device_simulator::{
+        AppContext, SimulatorEvent, config, http_server, protocol, user_data,
+    };
+
+    let dir = std::env::temp_dir().join(format!("user_data_restart_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut settings = config::Settings::default();
+    settings.database.path = dir.join("simulator.db");
+    settings.stats.lifetime_path = dir.join("lifetime_stats.json");
+
+    let ctx = AppContext::new(settings.clone());
+    user_data::spawn_persistence(&ctx);
+    let mut receiver = ctx.events.subscribe();
+    let registry = ctx.handler_registry();
+    let app = http_server::create_router(ctx.clone());
+
+    let response = registry
+        .handle_message(&protocol::Message::new(240, 1, b"LINE A".to_vec()))
+        .unwrap();
+    assert_eq!(response.mid, 5);
+    let response = registry
+        .handle_message(&protocol::Message::new(
+            245,
+            1,
+            b"0100502FIXTURE 4".to_vec(),
+        ))
+        .unwrap();
+    assert_eq!(response.mid, 5);
+
+    // Subscribers get each write as MID 0242
+    let mut uploads = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        if let SimulatorEvent::UserDataChanged { .. } = event {
+            assert_eq!(event.data_mid().unwrap().value(), 242);
+            uploads.push(event.subscription_payload(1).unwrap().1);
+        }
+    }
+    assert_eq!(
+        uploads,
+        [b"0100002LINE A".to_vec(), b"0100502FIXTURE 4".to_vec()]
+    );
+
+    let request = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+        let builder = Request::builder().uri(uri).method(method);
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    };
+    let response = app
+        .clone()
+        .oneshot(request(
+            "PUT",
+            "/user-data/7",
+            Some(json!({"data": "TEMP"})),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(request("DELETE", "/user-data/7", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(request(
+            "PUT",
+            "/user-data/3",
+            Some(json!({"data": "x".repeat(201)})),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    tokio::time::sleep(user_data::SAVE_INTERVAL * 3).await;
+
+    // A new context on the same database has the same slots
+    let restarted = http_server::create_router(AppContext::new(settings));
+    let response = restarted
+        .oneshot(request("GET", "/user-data", None))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["count"], 2);
+    assert_eq!(
+        json["slots"],
+        json!([{"slot": 0, "data": "LINE A"}, {"slot": 5, "data": "FIXTURE 4"}])
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Test a slot written right before shutdown is saved
+#[tokio::test]
+async fn test_user_data_saved_on_shutdown() {
+    use open_protocol_device_simulator::{AppContext, config, http_server, user_data};
+
+    let dir = std::env::temp_dir().join(format!("user_data_shutdown_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut settings = config::Settings::default();
+    settings.database.path = dir.join("simulator.db");
+    settings.stats.lifetime_path = dir.join("lifetime_stats.json");
+
+    let ctx = AppContext::new(settings.clone());
+    user_data::spawn_persistence(&ctx);
+    let app = http_server::create_router(ctx.clone());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/user-data/4")
+                .method("PUT")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"data": "LAST WORDS"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Restart before the next persistence tick
+    user_data::save_on_shutdown(&ctx);
+    let restarted = http_server::create_router(AppContext::new(settings));
+    let response = restarted
+        .oneshot(
+            Request::builder()
+                .uri("/user-data")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["slots"], json!([{"slot": 4, "data": "LAST WORDS"}]));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Test that forced OK/NOK results report torque and angle matching their MID 0061 status fields
+#[tokio::test]
+async fn test_forced_status_matches_mid_0061_values() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    // Split a MID 0061 data field into its values, checking each parameter ID
+    let fields = |payload: &[u8], widths: &[usize]| -> Vec<String> {
+        let text = std::str::from_utf8(payload).unwrap();
+        let mut offset = 0;
+        widths
+            .iter()
+            .zip(1..)
+            .map(|(width, id)| {
+                assert_eq!(&text[offset..offset + 2], format!("{:02}", id));
+                let value = text[offset + 2..offset + 2 + width].to_string();
+                offset += 2 + width;
+                value
+            })
+            .collect()
+    };
+    let number = |value: &str| value.trim().parse::<i64>().unwrap();
+    let rev1_widths = [
+        4, 2, 25, 25, 2, 3, 4, 4, 1, 1, 1, 6, 6, 6, 6, 5, 5, 5, 5, 19, 19, 1, 10,
+    ];
+    let rev2_widths = [
+        4, 2, 25, 25, 4, 3, 2, 5, 4, 4, 1, 1, 1, 1, 1, 1, 1, 1, 1, 10, 6, 6, 6, 6, 5, 5, 5, 5,
+    ];
+
+    let mut forced_nok = 0;
+    for ok in std::iter::repeat_n(false, 20).chain(std::iter::repeat_n(true, 5)) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/simulate/tightening")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .body(Body::from(json!({ "ok": ok }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let result = loop {
+            match receiver.try_recv() {
+                Ok(SimulatorEvent::TighteningCompleted { result, .. }) => break result,
+                Ok(_) => continue,
+                Err(error) => panic!("expected tightening event, got {:?}", error),
+            }
+        };
+        assert_eq!(result.tightening_status, ok);
+
+        // Revision 1: status flags 9-11, torque 12/13/15, angle 16/17/19
+        let rev1 = fields(&result.serialize_revision(1).unwrap(), &rev1_widths);
+        let torque = number(&rev1[14]);
+        let angle = number(&rev1[18]);
+        let torque_in = (number(&rev1[11])..=number(&rev1[12])).contains(&torque);
+        let angle_in = (number(&rev1[15])..=number(&rev1[16])).contains(&angle);
+        assert_eq!(rev1[8], if ok { "1" } else { "0" });
+        assert_eq!(rev1[9] == "1", torque_in, "torque {} vs {:?}", torque, rev1);
+        assert_eq!(rev1[10] == "1", angle_in, "angle {} vs {:?}", angle, rev1);
+        if !ok {
+            assert!(!torque_in || !angle_in, "NOK with both values in limits");
+            forced_nok += 1;
+        }
+
+        // Revision 2: low (0) / OK (1) / high (2) in 13/14, values in 21-28
+        let rev2 = fields(&result.serialize_revision(2).unwrap(), &rev2_widths);
+        let limit_status = |value: i64, min: &str, max: &str| {
+            if value < number(min) {
+                "0"
+            } else if value > number(max) {
+                "2"
+            } else {
+                "1"
+            }
+        };
+        assert_eq!(rev2[10], if ok { "1" } else { "0" });
+        assert_eq!(
+            rev2[12],
+            limit_status(number(&rev2[23]), &rev2[20], &rev2[21])
+        );
+        assert_eq!(
+            rev2[13],
+            limit_status(number(&rev2[27]), &rev2[24], &rev2[25])
+        );
+        assert_eq!(rev2[12] == "1", rev1[9] == "1");
+        assert_eq!(rev2[13] == "1", rev1[10] == "1");
+    }
+    assert_eq!(forced_nok, 20);
+}
+
+/// Test GET /schedules lists configured rules and DELETE cancels them
+#[tokio::test]
+async fn test_schedules_endpoint() {
+    use open_protocol_device_simulator::schedules::{ScheduleAction, ScheduleRule};
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let settings = config::Settings {
+        schedules: vec![
+            ScheduleRule {
+                name: "hourly outage".to_string(),
+                cron: "0 * * * *".to_string(),
+                action: ScheduleAction::Outage { duration_secs: 60 },
+            },
+            ScheduleRule {
+                name: "broken".to_string(),
+                cron: "every hour".to_string(),
+                action: ScheduleAction::Outage { duration_secs: 60 },
+            },
+        ],
+        ..Default::default()
+    };
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+    let request = |method: &str, uri: &str| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(request("GET", "/schedules"))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let listed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(listed["count"], 1, "invalid rules are skipped");
+    let schedule = &listed["schedules"][0];
+    assert_eq!(schedule["name"], "hourly outage");
+    assert_eq!(schedule["action"], "outage");
+    assert_eq!(schedule["runs"], 0);
+    assert!(schedule["next_run"].as_str().unwrap().ends_with(":00:00"));
+
+    let uri = format!("/schedules/{}", schedule["id"]);
+    let response = app.clone().oneshot(request("DELETE", &uri)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(request("DELETE", &uri)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.oneshot(request("GET", "/schedules")).await.unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let listed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(listed["count"], 0);
+}
+
+/// Test POST /simulate/tightening returns per-client delivery receipts
+#[tokio::test]
+async fn test_simulate_tightening_reports_delivery_receipts() {
+    use open_protocol_device_simulator::session_registry::DeliveryStatus;
+    use open_protocol_device_simulator::subscriptions::{
+        SubscriptionInfo, SubscriptionKind, Subscriptions,
+    };
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let mut receiver = broadcaster.subscribe();
+    let observable_state = ObservableState::new(state, broadcaster);
+    let ctx = AppContext::with_state(observable_state, config::Settings::default());
+
+    let session_id = ctx.sessions.register("127.0.0.1:40000".parse().unwrap());
+    let mut subscriptions = Subscriptions::new();
+    subscriptions.subscribe(
+        SubscriptionKind::TighteningResult,
+        SubscriptionInfo::new(1, ""),
+    );
+    ctx.sessions
+        .update_subscriptions(session_id, &subscriptions);
+
+    // Stand-in for the connection actor: report the broadcast as sent
+    let sessions = Arc::clone(&ctx.sessions);
+    tokio::spawn(async move {
+        while let Ok(event) = receiver.recv().await {
+            if let Some(delivery) = event.delivery_id() {
+                sessions.report_delivery(delivery, session_id, DeliveryStatus::Sent);
+            }
+        }
+    });
+
+    let app = http_server::create_router(ctx);
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/simulate/tightening")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({ "ok": true })).unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(result["subscribers"], 1);
+    assert_eq!(result["deliveries"][0]["status"], "sent");
+    assert_eq!(result["deliveries"][0]["addr"], "127.0.0.1:40000");
+}
+
+/// Test the alarm lifecycle over HTTP racing a protocol (MID 0075) acknowledgment
+#[tokio::test]
+async fn test_alarm_acknowledgment_lifecycle() {
+    use open_protocol_device_simulator::alarms::{AckSource, ProtocolAck};
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state.clone(),
+        config::Settings::default(),
+    ));
+
+    let post = |uri: &str, body: Body| {
+        Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap()
+    };
+
+    for (code, severity) in [("E301", "warning"), ("E502", "critical")] {
+        let response = app
+            .clone()
+            .oneshot(post(
+                "/alarms",
+                Body::from(
+                    json!({ "error_code": code, "description": "Test", "severity": severity })
+                        .to_string(),
+                ),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // Controller-side ack of alarm 2, then the client acks the oldest active one (alarm 1)
+    let response = app
+        .clone()
+        .oneshot(post("/alarms/2/ack", Body::empty()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(matches!(
+        observable_state.acknowledge_alarm_by_protocol(),
+        ProtocolAck::Confirmed(alarm) if alarm.id == 2
+    ));
+    assert!(matches!(
+        observable_state.acknowledge_alarm_by_protocol(),
+        ProtocolAck::Acknowledged(alarm) if alarm.id == 1
+    ));
+
+    // The HTTP ack arriving second loses the race
+    let response = app
+        .clone()
+        .oneshot(post("/alarms/1/ack", Body::empty()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = app
+        .clone()
+        .oneshot(post("/alarms/99/ack", Body::empty()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(post("/alarms/2/clear", Body::empty()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/alarms?state=acknowledged")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let alarms: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(alarms[0]["id"], 1);
+    assert_eq!(alarms[0]["severity"], "warning");
+    assert_eq!(alarms[0]["acknowledged_by"], "protocol");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/alarms?severity=fatal")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let mut ack_sources = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        if let SimulatorEvent::AlarmAcknowledged { id, source, .. } = event {
+            ack_sources.push((id, source));
+        }
+    }
+    assert_eq!(
+        ack_sources,
+        vec![(2, AckSource::Http), (1, AckSource::Protocol)]
+    );
+}
+
+/// Test alarm texts follow the controller language and can be fetched in any language
+#[tokio::test]
+async fn test_localized_alarm_texts() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state,
+        config::Settings::default(),
+    ));
+
+    let request = |method: &str, uri: &str, body: serde_json::Value| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let body_json = |response: axum::response::Response| async move {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(request(
+            "PATCH",
+            "/state",
+            json!({ "alarm_language": "de" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(request(
+            "PATCH",
+            "/state",
+            json!({ "alarm_language": "GER" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    for code in ["E101", "E777"] {
+        let response = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "/alarms",
+                json!({ "error_code": code, "description": "Custom alarm" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // MID 0071 revision 2 carries the text in the controller language
+    let mut pushed = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        if let SimulatorEvent::AlarmRaised { .. } = &event {
+            pushed.push(event.subscription_payload(2).unwrap());
+        }
+    }
+    assert_eq!(pushed.len(), 2);
+    assert_eq!(pushed[0].0, 2);
+    let data = String::from_utf8(pushed[0].1.clone()).unwrap();
+    assert!(data.contains("05Werkzeugmotor überhitzt"), "{data}");
+    let data = String::from_utf8(pushed[1].1.clone()).unwrap();
+    assert!(data.contains("05Custom alarm"), "{data}");
+
+    let alarms = body_json(
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/alarms")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(alarms[0]["text"], "Werkzeugmotor überhitzt");
+
+    let alarms = body_json(
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/alarms?lang=fr")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(alarms[0]["text"], "Moteur de l'outil en surchauffe");
+    assert_eq!(alarms[1]["text"], "Custom alarm");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/alarms?lang=french")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let texts = body_json(
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/alarms/texts")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(texts["language"], "de");
+    assert_eq!(texts["languages"], json!(["de", "en", "fr", "sv"]));
+    assert_eq!(texts["texts"]["E102"]["sv"], "Låg batterinivå i verktyget");
+}
+
+#[tokio::test]
+async fn test_firmware_upgrade_reboots_with_new_version() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let app = http_server::create_router(AppContext::with_state(
+        observable_state.clone(),
+        config::Settings::default(),
+    ));
+
+    let upgrade = |body: serde_json::Value| {
+        Request::builder()
+            .uri("/simulate/firmware-upgrade")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(upgrade(
+            json!({ "software_version": "FW 2.0", "supplier_code": "ACME" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(upgrade(json!({
+            "software_version": "FW 2.0",
+            "controller_name": "Upgraded",
+            "reboot_ms": 50
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert!(observable_state.read().rebooting);
+
+    let response = app
+        .clone()
+        .oneshot(upgrade(json!({ "software_version": "FW 3.0" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    assert!(matches!(
+        receiver.recv().await.unwrap(),
+        SimulatorEvent::ControllerRebooting { .. }
+    ));
+    let event = tokio::time::timeout(std::time::Duration::from_secs(2), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        event,
+        SimulatorEvent::ControllerRebooted { ref software_version } if software_version == "FW 2.0"
+    ));
+
+    let state = observable_state.read();
+    assert!(!state.rebooting);
+    assert_eq!(state.software_version, "FW 2.0");
+    assert_eq!(state.controller_name, "Upgraded");
+}
+
+/// Test that a firmware upgrade with a notice period is announced before the reboot
+#[tokio::test]
+async fn test_firmware_upgrade_announces_maintenance() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, mut receiver) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.maintenance.notice_ms = 100;
+    settings.maintenance.refuse_new_sessions = true;
+    let app =
+        http_server::create_router(AppContext::with_state(observable_state.clone(), settings));
+
+    let upgrade = |body: serde_json::Value| {
+        Request::builder()
+            .uri("/simulate/firmware-upgrade")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(upgrade(
+            json!({ "software_version": "FW 2.0", "reboot_ms": 50 }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["notice_ms"], 100);
+
+    // Announced, but not rebooting yet
+    {
+        let state = observable_state.read();
+        assert!(!state.rebooting);
+        let notice = state.maintenance.as_ref().unwrap();
+        assert_eq!(notice.starts_in_ms, 100);
+        assert_eq!(notice.duration_ms, 50);
+        assert!(!notice.accepting_sessions);
+    }
+    let response = app
+        .clone()
+        .oneshot(upgrade(json!({ "software_version": "FW 3.0" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let event = receiver.recv().await.unwrap();
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(value["type"], "MaintenanceImminent");
+    assert_eq!(value["notice"]["kind"], "firmware_upgrade");
+    assert_eq!(value["notice"]["reason"], "Firmware upgrade to FW 2.0");
+
+    let event = tokio::time::timeout(std::time::Duration::from_secs(2), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(event, SimulatorEvent::ControllerRebooting { .. }));
+    assert!(observable_state.read().maintenance.is_none());
+    let event = tokio::time::timeout(std::time::Duration::from_secs(2), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(event, SimulatorEvent::ControllerRebooted { .. }));
+}
+
+/// Test that tenants get their own controller under /tenants/{name}
+#[tokio::test]
+async fn test_tenant_namespaces_are_isolated() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.database.path = std::env::temp_dir().join("http_tenants_test.db");
+    settings.server.http_base_path = "/lab".to_string();
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let json_request = |method: &str, uri: &str, body: serde_json::Value| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "POST",
+            "/lab/tenants",
+            json!({"name": "team-a"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "POST",
+            "/lab/tenants",
+            json!({"name": "team-a"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "POST",
+            "/lab/tenants",
+            json!({"name": "../etc"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Changing the tenant's failure injection leaves the main controller alone
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "POST",
+            "/lab/tenants/team-a/config/failure",
+            json!({"enabled": true, "packet_loss_rate": 0.5}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body_of = |response: axum::response::Response| async move {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    };
+    let tenant = body_of(
+        app.clone()
+            .oneshot(get("/lab/tenants/team-a/config/failure?x=1"))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(tenant["enabled"], true);
+    let main = body_of(
+        app.clone()
+            .oneshot(get("/lab/config/failure"))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(main["enabled"], false);
+
+    let list = body_of(app.clone().oneshot(get("/lab/tenants")).await.unwrap()).await;
+    assert_eq!(list["count"], 1);
+    assert_eq!(list["tenants"][0]["name"], "team-a");
+
+    // The tenant's UI resolves under its namespace
+    let response = app
+        .clone()
+        .oneshot(get("/lab/tenants/team-a/"))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(String::from_utf8_lossy(&body).contains(r#"<base href="/lab/tenants/team-a/">"#));
+
+    let response = app.oneshot(get("/lab/tenants/team-b/state")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let _ = std::fs::remove_file(std::env::temp_dir().join("http_tenants_test-team-a.db"));
+}
+
+/// Test that controllers are provisioned in bulk and deleted again
+#[tokio::test]
+async fn test_bulk_controllers() {
+    use open_protocol_device_simulator::{
+        AppContext, DeviceState, ObservableState, SimulatorEvent, config, http_server,
+    };
+
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let mut settings = config::Settings::default();
+    settings.database.path = std::env::temp_dir().join("http_controllers_test.db");
+    let app = http_server::create_router(AppContext::with_state(observable_state, settings));
+
+    let json_request = |method: &str, uri: &str, body: serde_json::Value| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "POST",
+            "/controllers/bulk",
+            json!([
+                {"id": "st010", "identity": {"controller_name": "ST010"},
+                 "psets": [{"id": 7, "name": "M8", "torque_min": 20.0, "torque_max": 25.0,
+                            "angle_min": 30.0, "angle_max": 60.0}],
+                 "preset": {"pset_id": 7, "batch_size": 4}},
+                {"id": "st020"}
+            ]),
+        ))
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "{}",
+        String::from_utf8_lossy(&body)
+    );
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["count"], 2);
+    assert_eq!(json["controllers"][1]["name"], "st020");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/tenants/st010/state")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let state: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    // The new PSET got the next free ID; the preset followed it
+    assert_eq!(state["current_pset_id"], 6);
+    assert_eq!(state["current_pset_name"], "M8");
+
+    // An unknown field rejects the whole batch
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "POST",
+            "/controllers/bulk",
+            json!([{"id": "st030"}, {"id": "st040", "ports": 1}]),
+        ))
+        .await
+        .unwrap();
+    assert!(response.status().is_client_error());
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "POST",
+            "/controllers/bulk",
+            json!([{"id": "st030"}, {"id": "st040", "identity": {"cell_id": 10000}}]),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "PUT",
+            "/tenants/st010/counters",
+            json!({"next_tightening_id": 500}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let delete = |uri: &str| {
+        Request::builder()
+            .method("DELETE")
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app
+        .clone()
+        .oneshot(delete("/controllers/st010"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(delete("/controllers/st010"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/tenants/st010/state")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/tenants")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["count"], 1);
+
+    // Created again, the controller finds its PSETs and counters
+    let response = app
+        .clone()
+        .oneshot(json_request(
+            "POST",
+            "/controllers/bulk",
+            json!([{"id": "st010", "preset": {"pset_id": 6}}]),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/tenants/st010/state")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let state: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(state["current_pset_name"], "M8");
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/tenants/st010/counters")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let counters: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(counters["next_tightening_id"], 500);
+
+    for name in ["st010", "st020"] {
+        let _ = std::fs::remove_file(
+            std::env::temp_dir().join(format!("http_controllers_test-{name}.db")),
+        );
+    }
 }
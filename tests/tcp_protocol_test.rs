@@ -1,7 +1,9 @@
 mod common;
 
+use common::simulator::{connect, get_json, receive, send, start_simulator};
+use open_protocol_device_simulator::protocol::Mid;
 use open_protocol_device_simulator::{
-    DeviceState, ObservableState, SimulatorEvent, handler, protocol,
+    DeviceState, ObservableState, SimulatorEvent, handler, job, protocol, pset,
 };
 use std::sync::{Arc, RwLock};
 
@@ -11,13 +13,18 @@ fn test_communication_start() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     let message = protocol::Message {
         length: 20,
         mid: 1,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
 
     let response = registry
@@ -32,13 +39,18 @@ fn test_communication_stop() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     let message = protocol::Message {
         length: 20,
         mid: 3,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
 
     let response = registry
@@ -56,13 +68,18 @@ fn test_keep_alive() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     let message = protocol::Message {
         length: 20,
         mid: 9999,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
 
     let response = registry
@@ -77,7 +94,11 @@ fn test_pset_selection() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Select parameter set 5
     let data = b"005".to_vec();
@@ -86,6 +107,7 @@ fn test_pset_selection() {
         mid: 18,
         revision: 1,
         data,
+        header: Default::default(),
     };
 
     let response = registry
@@ -107,7 +129,11 @@ fn test_batch_size() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Set batch size to 10 for parameter set 1
     let data = b"0010010".to_vec();
@@ -116,6 +142,7 @@ fn test_batch_size() {
         mid: 19,
         revision: 1,
         data,
+        header: Default::default(),
     };
 
     let response = registry
@@ -137,13 +164,18 @@ fn test_tool_disable() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     let message = protocol::Message {
         length: 20,
         mid: 42,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
 
     let response = registry
@@ -171,13 +203,18 @@ fn test_tool_enable() {
 
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     let message = protocol::Message {
         length: 20,
         mid: 43,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
 
     let response = registry
@@ -199,7 +236,11 @@ fn test_vehicle_id_download() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Download VIN
     let vin = "SSC044207                ";
@@ -209,6 +250,7 @@ fn test_vehicle_id_download() {
         mid: 50,
         revision: 1,
         data,
+        header: Default::default(),
     };
 
     let response = registry
@@ -233,7 +275,11 @@ fn test_tightening_result_subscription() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Subscribe (MID 0060)
     let message = protocol::Message {
@@ -241,6 +287,7 @@ fn test_tightening_result_subscription() {
         mid: 60,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -253,6 +300,7 @@ fn test_tightening_result_subscription() {
         mid: 63,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -266,7 +314,11 @@ fn test_pset_subscription() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Subscribe (MID 0014)
     let message = protocol::Message {
@@ -274,6 +326,7 @@ fn test_pset_subscription() {
         mid: 14,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -286,6 +339,7 @@ fn test_pset_subscription() {
         mid: 17,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -299,7 +353,11 @@ fn test_vehicle_id_subscription() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Subscribe (MID 0051)
     let message = protocol::Message {
@@ -307,6 +365,7 @@ fn test_vehicle_id_subscription() {
         mid: 51,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -319,6 +378,7 @@ fn test_vehicle_id_subscription() {
         mid: 54,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -332,7 +392,11 @@ fn test_multi_spindle_status_subscription() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Subscribe (MID 0090)
     let message = protocol::Message {
@@ -340,6 +404,7 @@ fn test_multi_spindle_status_subscription() {
         mid: 90,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -352,6 +417,7 @@ fn test_multi_spindle_status_subscription() {
         mid: 92,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -365,7 +431,11 @@ fn test_multi_spindle_result_subscription() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Subscribe (MID 0100)
     let message = protocol::Message {
@@ -373,6 +443,7 @@ fn test_multi_spindle_result_subscription() {
         mid: 100,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -385,6 +456,7 @@ fn test_multi_spindle_result_subscription() {
         mid: 103,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
     let response = registry
         .handle_message(&message)
@@ -398,13 +470,18 @@ fn test_unknown_mid() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(state, broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     let message = protocol::Message {
         length: 20,
         mid: 9998,
         revision: 1,
         data: vec![],
+        header: Default::default(),
     };
 
     let result = registry.handle_message(&message);
@@ -417,7 +494,11 @@ fn test_batch_lifecycle() {
     let state = Arc::new(RwLock::new(DeviceState::new()));
     let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
     let observable_state = ObservableState::new(Arc::clone(&state), broadcaster);
-    let registry = handler::create_default_registry(observable_state);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
 
     // Set batch size to 3
     let data = b"0010003".to_vec();
@@ -426,6 +507,7 @@ fn test_batch_lifecycle() {
         mid: 19,
         revision: 1,
         data,
+        header: Default::default(),
     };
     registry
         .handle_message(&message)
@@ -451,3 +533,64 @@ fn test_batch_lifecycle() {
         }
     }
 }
+
+/// Test MID 0070/0073 - Alarm subscribe/unsubscribe
+#[test]
+fn test_alarm_subscription() {
+    let state = Arc::new(RwLock::new(DeviceState::new()));
+    let (broadcaster, _) = tokio::sync::broadcast::channel::<SimulatorEvent>(100);
+    let observable_state = ObservableState::new(state, broadcaster);
+    let registry = handler::create_default_registry(
+        observable_state,
+        pset::create_default_repository(),
+        job::create_default_job_repository(),
+    );
+
+    for mid in [70, 73] {
+        let message = protocol::Message {
+            length: 20,
+            mid,
+            revision: 1,
+            data: vec![],
+            header: Default::default(),
+        };
+
+        let response = registry
+            .handle_message(&message)
+            .expect("Handler should succeed");
+        assert_eq!(response.mid, 5, "MID {mid} should be accepted");
+    }
+}
+
+/// Test a per-connection PSET selection still passes the middleware layers
+#[tokio::test]
+async fn test_locked_down_controller_rejects_per_connection_selection() {
+    let simulator = start_simulator(
+        "per_connection_locked_down_test",
+        r#"
+[device]
+identification_scope = "per_connection"
+
+[handlers]
+locked_down = true
+allowed_mids = [1, 3, 9999]"#,
+    );
+    let mut client = connect(simulator.tcp_port()).await;
+    send(&mut client, Mid::COMMUNICATION_START).await;
+    assert_eq!(receive(&mut client).await.mid, 2);
+
+    send(&mut client, Mid::SELECT_PSET).await;
+    let reply = receive(&mut client).await;
+    assert_eq!(reply.mid, Mid::COMMAND_ERROR.value());
+    assert!(reply.data.starts_with(b"0018"));
+
+    // Counted by the metrics layer like any other MID
+    let stats = get_json(&simulator, "/stats/handlers?sort=mid").await;
+    let select = stats["handlers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|handler| handler["mid"] == 18)
+        .expect("MID 0018 should be in the handler metrics");
+    assert_eq!(select["naks"], 1);
+}
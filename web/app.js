@@ -0,0 +1,163 @@
+// Minimal dashboard driving the simulator through /state, /psets and the
+// /ws/events socket (events in, control commands out). All URLs are relative
+// to the <base> injected by the server, so the page works under a proxy prefix.
+
+const MAX_EVENTS = 100;
+const $ = (id) => document.getElementById(id);
+
+let socket = null;
+let nextCommandId = 1;
+
+async function fetchJson(path) {
+  const response = await fetch(path);
+  return response.json();
+}
+
+function renderState(state) {
+  $('controller').textContent = `${state.controller_name} (cell ${state.cell_id})`;
+  $('fsm').textContent = typeof state.device_fsm_state === 'string'
+    ? state.device_fsm_state
+    : Object.keys(state.device_fsm_state)[0];
+  $('tool').textContent = state.tool_enabled ? 'enabled' : 'disabled';
+  $('pset').textContent = state.current_pset_id
+    ? `${state.current_pset_id} ${state.current_pset_name ?? ''}`
+    : 'none';
+  $('vin').textContent = state.vehicle_id ?? '-';
+  renderTelemetry(state.telemetry);
+  $('pset-select').value = state.current_pset_id ?? '';
+
+  const health = state.failure_config.connection_health;
+  $('health').value = health;
+  $('health-value').textContent = health;
+
+  const mode = state.tightening_tracker.mode;
+  if (mode.Batch) {
+    renderBatch(mode.Batch.counter, mode.Batch.target_size);
+  } else {
+    $('batch-label').textContent = 'Single mode';
+    $('batch-progress').value = 0;
+  }
+}
+
+function renderTelemetry(t) {
+  $('telemetry').textContent = `${t.motor_temperature_c.toFixed(1)} °C, ` +
+    `${t.current_a.toFixed(2)} A, ${t.supply_voltage_v.toFixed(2)} V`;
+}
+
+function renderBatch(counter, targetSize) {
+  $('batch-label').textContent = `Batch ${counter} / ${targetSize}`;
+  $('batch-progress').max = Math.max(targetSize, 1);
+  $('batch-progress').value = counter;
+}
+
+async function refreshState() {
+  renderState(await fetchJson('state'));
+}
+
+async function loadPsets() {
+  const psets = await fetchJson('psets');
+  const select = $('pset-select');
+  select.innerHTML = '<option value="">-</option>';
+  for (const pset of psets) {
+    const option = document.createElement('option');
+    option.value = pset.id;
+    option.textContent = `${pset.id} - ${pset.name}`;
+    select.appendChild(option);
+  }
+}
+
+function logEvent(text, nok = false) {
+  const item = document.createElement('li');
+  item.textContent = `${new Date().toLocaleTimeString()}  ${text}`;
+  if (nok) item.className = 'nok';
+  const list = $('events');
+  list.prepend(item);
+  while (list.children.length > MAX_EVENTS) list.lastChild.remove();
+}
+
+function describeEvent(event) {
+  switch (event.type) {
+    case 'TighteningCompleted': {
+      const r = event.result;
+      return [`Tightening #${r.tightening_id} ${r.tightening_status ? 'OK' : 'NOK'} ` +
+        `torque=${r.torque.toFixed(2)} angle=${r.angle.toFixed(1)}`, !r.tightening_status];
+    }
+    case 'AlarmRaised':
+      return [`Alarm #${event.id} ${event.error_code} (${event.severity}): ${event.description}`, true];
+    case 'AlarmAcknowledged':
+      return [`Alarm #${event.id} ${event.error_code} acknowledged (${event.source})`, false];
+    case 'AlarmCleared':
+      return [`Alarm #${event.id} ${event.error_code} cleared`, false];
+    case 'ControllerRebooting':
+      return [`Controller rebooting for firmware ${event.software_version}`, true];
+    case 'ControllerRebooted':
+      return [`Controller back up with firmware ${event.software_version}`, false];
+    default:
+      return [`${event.type} ${JSON.stringify(event)}`, false];
+  }
+}
+
+function sendCommand(type, fields = {}) {
+  if (!socket || socket.readyState !== WebSocket.OPEN) return;
+  socket.send(JSON.stringify({ type, id: nextCommandId++, ...fields }));
+}
+
+function connect() {
+  const url = new URL('ws/events', document.baseURI);
+  url.protocol = url.protocol === 'https:' ? 'wss:' : 'ws:';
+  socket = new WebSocket(url);
+
+  socket.onopen = () => {
+    $('connection').textContent = 'connected';
+    $('connection').className = 'badge on';
+  };
+
+  socket.onclose = () => {
+    $('connection').textContent = 'disconnected';
+    $('connection').className = 'badge off';
+    setTimeout(connect, 2000);
+  };
+
+  socket.onmessage = (message) => {
+    const data = JSON.parse(message.data);
+    if (!data.type) {
+      // Initial device state snapshot
+      renderState(data);
+      return;
+    }
+    if (data.type === 'pong') return;
+    if (data.type === 'command_result') {
+      if (!data.success) logEvent(`${data.command ?? 'command'} failed: ${JSON.stringify(data.response ?? data.error)}`, true);
+      return;
+    }
+    if (data.type === 'Telemetry') {
+      renderTelemetry(data.telemetry);
+      return;
+    }
+    if (data.type === 'AutoTighteningProgress') {
+      renderBatch(data.counter, data.target_size);
+      $('auto-status').textContent = data.running ? 'running' : 'stopped';
+      return;
+    }
+
+    const [text, nok] = describeEvent(data);
+    logEvent(text, nok);
+    refreshState();
+  };
+}
+
+$('tighten-ok').onclick = () => sendCommand('simulate_tightening', { ok: true });
+$('tighten-nok').onclick = () => sendCommand('simulate_tightening', { ok: false });
+$('auto-start').onclick = () => sendCommand('start_auto_tightening');
+$('auto-stop').onclick = () => sendCommand('stop_auto_tightening');
+$('pset-select').onchange = (e) => {
+  if (e.target.value) sendCommand('select_pset', { pset_id: Number(e.target.value) });
+};
+$('health').oninput = (e) => { $('health-value').textContent = e.target.value; };
+$('health').onchange = (e) => {
+  sendCommand('set_health', { connection_health: Number(e.target.value) });
+  refreshState();
+};
+
+loadPsets().then(refreshState);
+connect();